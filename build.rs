@@ -16,7 +16,7 @@ pub fn main() {
         if val == "1" && key.starts_with(feature_prefix) {
             let krate = key[feature_prefix.len()..].to_lowercase();
             match krate.as_ref() {
-                "default" | "unix" | "redox" | "fuchsia" | "generic" | "nightly" | "test_unimplemented" => continue,
+                "default" | "unix" | "redox" | "fuchsia" | "generic" | "nightly" | "test_unimplemented" | "sandbox" | "decompress" => continue,
                 _ => {},
             }
             crates.push(krate.to_string());
@@ -36,26 +36,7 @@ pub fn main() {
     for krate in crates {
         cf.write_all(format!("extern crate uu_{krate};\n", krate=krate).as_bytes()).unwrap();
 
-        match krate.as_ref() {
-            "hashsum" => {
-                mf.write_all("map.insert(\"hashsum\", uu_hashsum::uumain);
-                              map.insert(\"md5sum\", uu_hashsum::uumain);
-                              map.insert(\"sha1sum\", uu_hashsum::uumain);
-                              map.insert(\"sha224sum\", uu_hashsum::uumain);
-                              map.insert(\"sha256sum\", uu_hashsum::uumain);
-                              map.insert(\"sha384sum\", uu_hashsum::uumain);
-                              map.insert(\"sha512sum\", uu_hashsum::uumain);
-                              map.insert(\"sha3sum\", uu_hashsum::uumain);
-                              map.insert(\"sha3-224sum\", uu_hashsum::uumain);
-                              map.insert(\"sha3-256sum\", uu_hashsum::uumain);
-                              map.insert(\"sha3-384sum\", uu_hashsum::uumain);
-                              map.insert(\"sha3-512sum\", uu_hashsum::uumain);
-                              map.insert(\"shake128sum\", uu_hashsum::uumain);
-                              map.insert(\"shake256sum\", uu_hashsum::uumain);\n".as_bytes()).unwrap();
-            },
-            _ =>
-                mf.write_all(format!("map.insert(\"{krate}\", uu_{krate}::uumain);\n", krate=krate).as_bytes()).unwrap(),
-        }
+        mf.write_all(format!("map.insert(\"{krate}\", uu_{krate}::uumain);\n", krate=krate).as_bytes()).unwrap();
     }
 
     mf.write_all("map\n}\n".as_bytes()).unwrap();
@@ -11,6 +11,7 @@
 #[macro_use]
 extern crate uucore;
 use uucore::encoding::{Data, Format, wrap_print};
+use uucore::quoting::quote;
 
 use std::fs::File;
 use std::io::{BufReader, Read, stdin};
@@ -45,7 +46,7 @@ pub fn uumain(args: Vec<String>) -> i32 {
             match s.parse() {
                 Ok(n) => n,
                 Err(e) => {
-                    crash!(1, "invalid wrap size: ‘{}’: {}", s, e);
+                    crash!(1, "invalid wrap size: {}: {}", quote(&s), e);
                 }
             }
         }
@@ -53,7 +54,7 @@ pub fn uumain(args: Vec<String>) -> i32 {
     };
 
     if matches.free.len() > 1 {
-        disp_err!("extra operand ‘{}’", matches.free[0]);
+        disp_err!("extra operand {}", quote(&matches.free[0]));
         return 1;
     }
 
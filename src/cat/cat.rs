@@ -22,12 +22,18 @@ use quick_error::ResultExt;
 use std::fs::{metadata, File};
 use std::io::{self, stdout, stdin, stderr, Write, Read, BufWriter};
 use uucore::fs::is_stdin_interactive;
+use uucore::numbering::LineNumbers;
+use uucore::pipes;
 
 /// Unix domain socket support
 #[cfg(unix)] use std::net::Shutdown;
 #[cfg(unix)] use std::os::unix::fs::FileTypeExt;
 #[cfg(unix)] use unix_socket::UnixStream;
 
+/// Copies in the kernel
+#[cfg(target_os = "linux")] use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
+#[cfg(target_os = "linux")] use uucore::copy;
+
 static SYNTAX: &'static str = "[OPTION]... [FILE]...";
 static SUMMARY: &'static str = "Concatenate FILE(s), or standard input, to standard output
  With no FILE, or when FILE is -, read standard input.";
@@ -80,16 +86,15 @@ struct OutputOptions {
     /// Line numbering mode
     number: NumberingMode,
 
+    /// How line numbers are written
+    line_numbers: LineNumbers,
+
     /// Suppress repeated empty output lines
     squeeze_blank: bool,
 
     /// display TAB characters as `tab`
     show_tabs: bool,
 
-    /// If `show_tabs == true`, this string will be printed in the
-    /// place of tabs
-    tab: String,
-
     /// Can be set to show characters other than '\n' a the end of
     /// each line, e.g. $
     end_of_line: String,
@@ -103,6 +108,9 @@ struct OutputOptions {
 struct InputHandle {
     reader: Box<Read>,
     is_interactive: bool,
+    /// The descriptor `reader` reads from, for copying in the kernel
+    #[cfg(target_os = "linux")]
+    fd: RawFd,
 }
 
 
@@ -126,8 +134,8 @@ type CatResult<T> = Result<T, CatError>;
 
 
 pub fn uumain(args: Vec<String>) -> i32 {
-    let matches = new_coreopts!(SYNTAX, SUMMARY, LONG_HELP)
-        .optflag("A", "show-all", "equivalent to -vET")
+    let mut opts = new_coreopts!(SYNTAX, SUMMARY, LONG_HELP);
+    opts.optflag("A", "show-all", "equivalent to -vET")
         .optflag("b",
                  "number-nonblank",
                  "number nonempty output lines, overrides -n")
@@ -139,8 +147,10 @@ pub fn uumain(args: Vec<String>) -> i32 {
         .optflag("T", "show-tabs", "display TAB characters as ^I")
         .optflag("v",
                  "show-nonprinting",
-                 "use ^ and M- notation, except for LF (\\n) and TAB (\\t)")
-        .parse(args);
+                 "use ^ and M- notation, except for LF (\\n) and TAB (\\t)");
+    #[cfg(all(target_os = "linux", feature = "sandbox"))]
+    opts.optflag("", "sandbox", "only allow reading the FILEs given on the command line");
+    let matches = opts.parse(args);
 
     let number_mode = if matches.opt_present("b") {
         NumberingMode::NumberNonEmpty
@@ -155,11 +165,20 @@ pub fn uumain(args: Vec<String>) -> i32 {
     let show_ends = matches.opts_present(&["E".to_owned(), "A".to_owned(), "e".to_owned()]);
     let show_tabs = matches.opts_present(&["A".to_owned(), "T".to_owned(), "t".to_owned()]);
     let squeeze_blank = matches.opt_present("s");
+    #[cfg(all(target_os = "linux", feature = "sandbox"))]
+    let sandboxed = matches.opt_present("sandbox");
     let mut files = matches.free;
     if files.is_empty() {
         files.push("-".to_owned());
     }
 
+    #[cfg(all(target_os = "linux", feature = "sandbox"))]
+    {
+        if sandboxed {
+            uucore::sandbox::Sandbox::for_operands(&files, None).enter(1);
+        }
+    }
+
     let can_write_fast = !(show_tabs
                           || show_nonprint
                           || show_ends
@@ -170,11 +189,6 @@ pub fn uumain(args: Vec<String>) -> i32 {
         write_fast(files).is_ok()
 
     } else {
-        let tab = match show_tabs {
-            true => "^I",
-            false => "\t",
-        }.to_owned();
-
         let end_of_line = match show_ends {
             true => "$\n",
             false => "\n",
@@ -183,10 +197,10 @@ pub fn uumain(args: Vec<String>) -> i32 {
         let options = OutputOptions {
             end_of_line: end_of_line,
             number: number_mode,
+            line_numbers: LineNumbers::new(),
             show_nonprint: show_nonprint,
             show_tabs: show_tabs,
             squeeze_blank: squeeze_blank,
-            tab: tab,
         };
 
         write_lines(files, &options).is_ok()
@@ -233,6 +247,8 @@ fn open(path: &str) -> CatResult<InputHandle> {
         return Ok(InputHandle {
             reader: Box::new(stdin) as Box<Read>,
             is_interactive: is_stdin_interactive(),
+            #[cfg(target_os = "linux")]
+            fd: 0,
         });
     }
 
@@ -242,6 +258,8 @@ fn open(path: &str) -> CatResult<InputHandle> {
             let socket = UnixStream::connect(path).context(path)?;
             socket.shutdown(Shutdown::Write).context(path)?;
             Ok(InputHandle {
+                #[cfg(target_os = "linux")]
+                fd: socket.as_raw_fd(),
                 reader: Box::new(socket) as Box<Read>,
                 is_interactive: false,
             })
@@ -249,6 +267,8 @@ fn open(path: &str) -> CatResult<InputHandle> {
         _ => {
             let file = File::open(path).context(path)?;
             Ok(InputHandle {
+                #[cfg(target_os = "linux")]
+                fd: file.as_raw_fd(),
                 reader: Box::new(file) as Box<Read>,
                 is_interactive: false,
             })
@@ -256,6 +276,47 @@ fn open(path: &str) -> CatResult<InputHandle> {
     }
 }
 
+/// Writes files to stdout with no configuration.  This allows the
+/// kernel to copy them, with `splice` through pipes and
+/// `copy_file_range` or `sendfile` from files, where it can.
+/// Returns `Ok(())` if no errors were encountered, or an error with
+/// the number of errors encountered.
+///
+/// # Arguments
+///
+/// * `files` - There is no short circuit when encountiner an error
+/// reading a file in this vector
+#[cfg(target_os = "linux")]
+fn write_fast(files: Vec<String>) -> CatResult<()> {
+    let mut error_count = 0;
+
+    for file in files {
+        match open(&file[..]) {
+            Ok(handle) => {
+                // neither descriptor is ours to close
+                let mut input = unsafe { File::from_raw_fd(handle.fd) };
+                let mut output = unsafe { File::from_raw_fd(1) };
+                let result = copy::copy_stream(&mut input, &mut output);
+                let _ = input.into_raw_fd();
+                let _ = output.into_raw_fd();
+                if let Err(error) = result {
+                    writeln!(&mut stderr(), "{}", CatError::Input(error, file))?;
+                    error_count += 1;
+                }
+            },
+            Err(error) => {
+                writeln!(&mut stderr(), "{}", error)?;
+                error_count += 1;
+            },
+        }
+    }
+
+    match error_count {
+        0 => Ok(()),
+        _ => Err(CatError::EncounteredErrors(error_count)),
+    }
+}
+
 /// Writes files to stdout with no configuration.  This allows a
 /// simple memory copy. Returns `Ok(())` if no errors were
 /// encountered, or an error with the number of errors encountered.
@@ -264,9 +325,12 @@ fn open(path: &str) -> CatResult<InputHandle> {
 ///
 /// * `files` - There is no short circuit when encountiner an error
 /// reading a file in this vector
+#[cfg(not(target_os = "linux"))]
 fn write_fast(files: Vec<String>) -> CatResult<()> {
     let mut writer = stdout();
-    let mut in_buf = [0; 1024 * 64];
+    // as big as the pipes on either side hold
+    let fds = if files.iter().any(|file| file == "-") { &[0, 1][..] } else { &[1][..] };
+    let mut in_buf = vec![0; pipes::buffer_size(1024 * 64, fds)];
     let mut error_count = 0;
 
     for file in files {
@@ -299,6 +363,14 @@ struct OutputState {
 
     /// Whether the output cursor is at the beginning of a new line
     at_line_start: bool,
+
+    /// Whether the last line written was empty, so that `-s` drops any
+    /// empty lines after it, whichever file they are in
+    one_blank_kept: bool,
+
+    /// Whether a carriage return has been read but not yet written, as
+    /// `-E` shows one that ends a line as `^M`
+    pending_cr: bool,
 }
 
 /// Writes files to stdout with `options` as configuration.  Returns
@@ -314,6 +386,8 @@ fn write_lines(files: Vec<String>, options: &OutputOptions) -> CatResult<()> {
     let mut state = OutputState {
         line_number: 1,
         at_line_start: true,
+        one_blank_kept: false,
+        pending_cr: false,
     };
 
     for file in files {
@@ -322,6 +396,9 @@ fn write_lines(files: Vec<String>, options: &OutputOptions) -> CatResult<()> {
             error_count += 1;
         }
     }
+    if state.pending_cr {
+        stdout().write_all(b"\r")?;
+    }
 
     match error_count {
         0 => Ok(()),
@@ -336,8 +413,7 @@ fn write_file_lines(file: &str,
                     state: &mut OutputState) -> CatResult<()> {
     let mut handle = open(file)?;
     let mut in_buf = [0; 1024 * 31];
-    let mut writer = BufWriter::with_capacity(1024 * 64, stdout());
-    let mut one_blank_kept = false;
+    let mut writer = BufWriter::with_capacity(pipes::buffer_size(1024 * 64, &[1]), stdout());
 
     while let Ok(n) = handle.reader.read(&mut in_buf) {
         if n == 0 {
@@ -348,13 +424,16 @@ fn write_file_lines(file: &str,
         while pos < n {
             // skip empty line_number enumerating them if needed
             if in_buf[pos] == '\n' as u8 {
-                if !state.at_line_start || !options.squeeze_blank || !one_blank_kept {
-                    one_blank_kept = true;
-                    if state.at_line_start && options.number == NumberingMode::NumberAll {
-                        write!(&mut writer, "{0:6}\t", state.line_number)?;
-                        state.line_number += 1;
+                if !state.at_line_start || !options.squeeze_blank || !state.one_blank_kept {
+                    // an empty line, rather than the end of one read before
+                    if state.at_line_start {
+                        state.one_blank_kept = true;
+                        if options.number == NumberingMode::NumberAll {
+                            options.line_numbers.write(&mut writer, state.line_number as u64)?;
+                            state.line_number += 1;
+                        }
                     }
-                    writer.write_all(options.end_of_line.as_bytes())?;
+                    write_end_of_line(&mut writer, options, state)?;
                     if handle.is_interactive {
                         writer.flush().context(&file[..])?;
                     }
@@ -363,27 +442,21 @@ fn write_file_lines(file: &str,
                 pos += 1;
                 continue;
             }
-            one_blank_kept = false;
+            state.one_blank_kept = false;
             if state.at_line_start && options.number != NumberingMode::NumberNone {
-                write!(&mut writer, "{0:6}\t", state.line_number)?;
+                options.line_numbers.write(&mut writer, state.line_number as u64)?;
                 state.line_number += 1;
             }
 
             // print to end of line or end of buffer
-            let offset = if options.show_nonprint {
-                write_nonprint_to_end(&in_buf[pos..], &mut writer, options.tab.as_bytes())
-            } else if options.show_tabs {
-                write_tab_to_end(&in_buf[pos..], &mut writer)
-            } else {
-                write_to_end(&in_buf[pos..], &mut writer)
-            };
+            let offset = write_to_end(&in_buf[pos..], &mut writer, options, state)?;
             // end of buffer?
             if offset == 0 {
                 state.at_line_start = false;
                 break;
             }
             // print suitable end of line
-            writer.write_all(options.end_of_line.as_bytes())?;
+            write_end_of_line(&mut writer, options, state)?;
             if handle.is_interactive {
                 writer.flush()?;
             }
@@ -395,59 +468,69 @@ fn write_file_lines(file: &str,
     Ok(())
 }
 
-// write***_to_end methods
-// Write all symbols till end of line or end of buffer is reached
-// Return the (number of written symbols + 1) or 0 if the end of buffer is reached
-fn write_to_end<W: Write>(in_buf: &[u8], writer: &mut W) -> usize {
-    match in_buf.iter().position(|c| *c == '\n' as u8) {
-        Some(p) => {
-            writer.write_all(&in_buf[..p]).unwrap();
-            p + 1
-        }
-        None => {
-            writer.write_all(in_buf).unwrap();
-            0
-        }
+/// Writes what ends a line, with a carriage return before it shown as `^M`
+/// if the end is shown.
+fn write_end_of_line<W: Write>(writer: &mut W, options: &OutputOptions, state: &mut OutputState) -> io::Result<()> {
+    if state.pending_cr {
+        writer.write_all(b"^M")?;
+        state.pending_cr = false;
     }
+    writer.write_all(options.end_of_line.as_bytes())
 }
 
-fn write_tab_to_end<W: Write>(mut in_buf: &[u8], writer: &mut W) -> usize {
-    loop {
-        match in_buf.iter().position(|c| *c == '\n' as u8 || *c == '\t' as u8) {
-            Some(p) => {
-                writer.write_all(&in_buf[..p]).unwrap();
-                if in_buf[p] == '\n' as u8 {
-                    return p + 1;
-                } else {
-                    writer.write_all("^I".as_bytes()).unwrap();
-                    in_buf = &in_buf[p + 1..];
-                }
-            }
-            None => {
-                writer.write_all(in_buf).unwrap();
-                return 0;
-            }
-        };
+/// Writes everything up to the end of the line or of the buffer, shown as
+/// `options` say.  Returns the number of bytes up to and including the
+/// newline, or 0 if the buffer ends first.
+fn write_to_end<W: Write>(in_buf: &[u8],
+                          writer: &mut W,
+                          options: &OutputOptions,
+                          state: &mut OutputState) -> io::Result<usize> {
+    let end = in_buf.iter().position(|&c| c == '\n' as u8);
+    let mut line = &in_buf[..end.unwrap_or(in_buf.len())];
+    if state.pending_cr {
+        writer.write_all(b"\r")?;
+        state.pending_cr = false;
+    }
+    // a carriage return that might end the line waits to see if it does
+    if options.end_of_line != "\n" && !options.show_nonprint && line.last() == Some(&('\r' as u8)) {
+        line = &line[..line.len() - 1];
+        state.pending_cr = true;
+    }
+
+    if options.show_nonprint || options.show_tabs {
+        write_shown(line, writer, options)?;
+    } else {
+        writer.write_all(line)?;
     }
+    Ok(end.map_or(0, |p| p + 1))
 }
 
-fn write_nonprint_to_end<W: Write>(in_buf: &[u8], writer: &mut W, tab: &[u8]) -> usize {
-    let mut count = 0;
+/// Writes `line` with tabs as `^I` if `show_tabs`, and other nonprinting
+/// bytes in `^` and `M-` notation if `show_nonprint`: control characters
+/// as `^` and the letter 64 after them, DEL as `^?`, and bytes above 127
+/// as `M-` and the byte 128 below them, shown in turn.
+fn write_shown<W: Write>(line: &[u8], writer: &mut W, options: &OutputOptions) -> io::Result<()> {
+    let mut start = 0;
+    for (i, &byte) in line.iter().enumerate() {
+        let as_is = match byte {
+            9 => !options.show_tabs,
+            32...126 => true,
+            _ => !options.show_nonprint,
+        };
+        if as_is {
+            continue;
+        }
+        writer.write_all(&line[start..i])?;
+        start = i + 1;
 
-    for byte in in_buf.iter().map(|c| *c) {
-        if byte == '\n' as u8 {
-            break;
+        if byte >= 128 {
+            writer.write_all(b"M-")?;
+        }
+        match byte & 127 {
+            c @ 0...31 => writer.write_all(&['^' as u8, c + 64])?,
+            127 => writer.write_all(b"^?")?,
+            c => writer.write_all(&[c])?,
         }
-        match byte {
-            9 => writer.write_all(tab),
-            0...8 | 10...31 => writer.write_all(&['^' as u8, byte + 64]),
-            32...126 => writer.write_all(&[byte]),
-            127 => writer.write_all(&['^' as u8, byte - 64]),
-            128...159 => writer.write_all(&['M' as u8, '-' as u8, '^' as u8, byte - 64]),
-            160...254 => writer.write_all(&['M' as u8, '-' as u8, byte - 128]),
-            _ => writer.write_all(&['M' as u8, '-' as u8, '^' as u8, 63]),
-        }.unwrap();
-        count += 1;
     }
-    if count != in_buf.len() { count + 1 } else { 0 }
+    writer.write_all(&line[start..])
 }
@@ -10,15 +10,15 @@
 
 #[macro_use]
 extern crate uucore;
-use uucore::libc::{self, gid_t, lchown};
+use uucore::libc::{self, gid_t};
 pub use uucore::entries;
 use uucore::fs::resolve_relative_path;
+use uucore::quoting::quote;
 
 extern crate walkdir;
 use walkdir::WalkDir;
 
 use std::io::Result as IOResult;
-use std::io::Error as IOError;
 
 use std::fs;
 use std::fs::Metadata;
@@ -26,9 +26,6 @@ use std::os::unix::fs::MetadataExt;
 
 use std::path::Path;
 
-use std::ffi::CString;
-use std::os::unix::ffi::OsStrExt;
-
 static SYNTAX: &'static str = "chgrp [OPTION]... GROUP FILE...\n or :  chgrp [OPTION]... --reference=RFILE FILE...";
 static SUMMARY: &'static str = "Change the group of each FILE to GROUP.";
 
@@ -72,6 +69,10 @@ pub fn uumain(args: Vec<String>) -> i32 {
     let mut derefer = -1;
     let flags: &[char] = &['H', 'L', 'P'];
     for opt in &args {
+        // -h may be grouped with other short options
+        if opt.starts_with('-') && !opt.starts_with("--") && opt.contains('h') {
+            derefer = 0;
+        }
         match opt.as_str() {
             // If more than one is specified, only the final one takes effect.
             s if s.contains(flags) => {
@@ -120,7 +121,7 @@ pub fn uumain(args: Vec<String>) -> i32 {
         disp_err!("missing operand");
         return 1;
     } else if matches.free.len() < 2 && !matches.opt_present("reference") {
-        disp_err!("missing operand after ‘{}’", matches.free[0]);
+        disp_err!("missing operand after {}", quote(&matches.free[0]));
         return 1;
     }
 
@@ -200,20 +201,7 @@ impl Chgrper {
     }
 
     fn chgrp<P: AsRef<Path>>(&self, path: P, dgid: gid_t, follow: bool) -> IOResult<()> {
-        let path = path.as_ref();
-        let s = CString::new(path.as_os_str().as_bytes()).unwrap();
-        let ret = unsafe {
-            if follow {
-                libc::chown(s.as_ptr(), (0 as gid_t).wrapping_sub(1), dgid)
-            } else {
-                lchown(s.as_ptr(), (0 as gid_t).wrapping_sub(1), dgid)
-            }
-        };
-        if ret == 0 {
-            Ok(())
-        } else {
-            Err(IOError::last_os_error())
-        }
+        uucore::fs::chown(path.as_ref(), (0 as libc::uid_t).wrapping_sub(1), dgid, follow)
     }
 
     #[cfg(windows)]
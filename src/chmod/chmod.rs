@@ -17,7 +17,7 @@ extern crate walker;
 extern crate uucore;
 
 use std::fs;
-use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::os::unix::fs::MetadataExt;
 use std::path::Path;
 use walker::Walker;
 #[cfg(not(windows))]
@@ -40,6 +40,8 @@ pub fn uumain(mut args: Vec<String>) -> i32 {
         .optflag("v", "verbose", "output a diagnostic for every file processed (unimplemented)")
         .optflag("", "no-preserve-root", "do not treat '/' specially (the default)")
         .optflag("", "preserve-root", "fail to operate recursively on '/'")
+        .optflag("", "dereference", "affect the referent of each symbolic link (this is the default), rather than the symbolic link itself")
+        .optflag("h", "no-dereference", "affect symbolic links instead of any referenced file")
         .optopt("", "reference", "use RFILE's mode instead of MODE values", "RFILE")
         .optflag("R", "recursive", "change files and directories recursively");
 
@@ -58,6 +60,7 @@ pub fn uumain(mut args: Vec<String>) -> i32 {
         let verbose = matches.opt_present("verbose");
         let preserve_root = matches.opt_present("preserve-root");
         let recursive = matches.opt_present("recursive");
+        let dereference = !matches.opt_present("no-dereference");
         let fmode = matches.opt_str("reference").and_then(|ref fref| {
             match fs::metadata(fref) {
                 Ok(meta) => Some(meta.mode()),
@@ -82,6 +85,7 @@ pub fn uumain(mut args: Vec<String>) -> i32 {
             verbose: verbose,
             preserve_root: preserve_root,
             recursive: recursive,
+            dereference: dereference,
             fmode: fmode,
             cmode: cmode,
         };
@@ -118,6 +122,7 @@ struct Chmoder {
     verbose: bool,
     preserve_root: bool,
     recursive: bool,
+    dereference: bool,
     fmode: Option<u32>,
     cmode: Option<String>,
 }
@@ -130,8 +135,9 @@ impl Chmoder {
         for filename in &files {
             let filename = &filename[..];
             let file = Path::new(filename);
-            if file.exists() {
-                if file.is_dir() {
+            let meta = if self.dereference { fs::metadata(file) } else { fs::symlink_metadata(file) };
+            if let Ok(meta) = meta {
+                if meta.is_dir() {
                     if !self.preserve_root || filename != "/" {
                         if self.recursive {
                             let walk_dir = match Walker::new(&file) {
@@ -180,7 +186,8 @@ impl Chmoder {
     }
     #[cfg(any(unix, target_os = "redox"))]
     fn chmod_file(&self, file: &Path, name: &str) -> Result<(), i32> {
-        let mut fperm = match fs::metadata(name) {
+        let meta = if self.dereference { fs::metadata(name) } else { fs::symlink_metadata(name) };
+        let mut fperm = match meta {
             Ok(meta) => meta.mode() & 0o7777,
             Err(err) => {
                 if !self.quiet {
@@ -226,9 +233,9 @@ impl Chmoder {
                 show_info!("mode of '{}' retained as {:o}", file.display(), fperm);
             }
             Ok(())
-        } else if let Err(err) = fs::set_permissions(Path::new(path), fs::Permissions::from_mode(mode)) {
+        } else if let Err(err) = uucore::fs::chmod(Path::new(path), mode, self.dereference) {
             if !self.quiet {
-                show_error!("{}", err);
+                show_error!("changing permissions of '{}': {}", file.display(), err);
             }
             if self.verbose {
                 show_info!("failed to change mode of file '{}' from {:o} to {:o}", file.display(), fperm, mode);
@@ -13,9 +13,10 @@
 
 #[macro_use]
 extern crate uucore;
-use uucore::libc::{self, uid_t, gid_t, lchown};
+use uucore::libc::{self, uid_t, gid_t};
 pub use uucore::entries::{self, Locate, Passwd, Group};
 use uucore::fs::resolve_relative_path;
+use uucore::quoting::quote;
 
 extern crate walkdir;
 use walkdir::WalkDir;
@@ -78,6 +79,10 @@ pub fn uumain(args: Vec<String>) -> i32 {
     let mut derefer = -1;
     let flags: &[char] = &['H', 'L', 'P'];
     for opt in &args {
+        // -h may be grouped with other short options
+        if opt.starts_with('-') && !opt.starts_with("--") && opt.contains('h') {
+            derefer = 0;
+        }
         match opt.as_str() {
             // If more than one is specified, only the final one takes effect.
             s if s.contains(flags) => {
@@ -141,7 +146,7 @@ pub fn uumain(args: Vec<String>) -> i32 {
         disp_err!("missing operand");
         return 1;
     } else if matches.free.len() < 2 && !matches.opt_present("reference") {
-        disp_err!("missing operand after ‘{}’", matches.free[0]);
+        disp_err!("missing operand after {}", quote(&matches.free[0]));
         return 1;
     }
 
@@ -195,23 +200,23 @@ fn parse_spec(spec: &str) -> Result<(Option<u32>, Option<u32>), String> {
     if usr_only {
         Ok((Some(match Passwd::locate(args[0]) {
             Ok(v) => v.uid(),
-            _ => return Err(format!("invalid user: ‘{}’", spec)),
+            _ => return Err(format!("invalid user: {}", quote(spec))),
         }),
             None))
     } else if grp_only {
         Ok((None,
             Some(match Group::locate(args[1]) {
             Ok(v) => v.gid(),
-            _ => return Err(format!("invalid group: ‘{}’", spec)),
+            _ => return Err(format!("invalid group: {}", quote(spec))),
         })))
     } else if usr_grp {
         Ok((Some(match Passwd::locate(args[0]) {
             Ok(v) => v.uid(),
-            _ => return Err(format!("invalid user: ‘{}’", spec)),
+            _ => return Err(format!("invalid user: {}", quote(spec))),
         }),
             Some(match Group::locate(args[1]) {
             Ok(v) => v.gid(),
-            _ => return Err(format!("invalid group: ‘{}’", spec)),
+            _ => return Err(format!("invalid group: {}", quote(spec))),
         })))
     } else {
         Ok((None, None))
@@ -264,20 +269,7 @@ impl Chowner {
     }
 
     fn chown<P: AsRef<Path>>(&self, path: P, duid: uid_t, dgid: gid_t, follow: bool) -> IOResult<()> {
-        let path = path.as_ref();
-        let s = CString::new(path.as_os_str().as_bytes()).unwrap();
-        let ret = unsafe {
-            if follow {
-                libc::chown(s.as_ptr(), duid, dgid)
-            } else {
-                lchown(s.as_ptr(), duid, dgid)
-            }
-        };
-        if ret == 0 {
-            Ok(())
-        } else {
-            Err(io::Error::last_os_error())
-        }
+        uucore::fs::chown(path.as_ref(), duid, dgid, follow)
     }
 
     fn traverse<P: AsRef<Path>>(&self, root: P) -> i32 {
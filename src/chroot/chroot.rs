@@ -15,15 +15,16 @@ extern crate getopts;
 #[macro_use]
 extern crate uucore;
 use uucore::libc::{self, setgid, setuid, chroot, setgroups};
-use uucore::entries;
+use uucore::entries::{self, Locate};
+use uucore::process::{self, FdPolicy};
 
 use std::ffi::CString;
-use std::io::Error;
+use std::io::{Error, ErrorKind};
 use std::iter::FromIterator;
+use std::os::unix::process::ExitStatusExt;
 use std::path::Path;
 use std::process::Command;
 
-static NAME: &'static str = "chroot";
 static SYNTAX: &'static str = "[OPTION]... NEWROOT [COMMAND [ARG]...]";
 static SUMMARY: &'static str = "Run COMMAND with root directory set to NEWROOT.";
 static LONG_HELP: &'static str = "
@@ -31,20 +32,25 @@ static LONG_HELP: &'static str = "
  If $(SHELL) is not set, /bin/sh is used.
 ";
 
+// Exit statuses used by GNU chroot
+const EXIT_CANCELED: i32 = 125;
+const EXIT_CANNOT_INVOKE: i32 = 126;
+const EXIT_ENOENT: i32 = 127;
+
 pub fn uumain(args: Vec<String>) -> i32 {
+    let inherited_fds = FdPolicy::inherited();
     let matches = new_coreopts!(SYNTAX, SUMMARY, LONG_HELP)
         .optopt("u", "user", "User (ID or name) to switch before running the program", "USER")
         .optopt("g", "group", "Group (ID or name) to switch to", "GROUP")
-        .optopt("G", "groups", "Comma-separated list of groups to switch to", "GROUP1,GROUP2...")
+        .optopt("G", "groups", "Comma-separated list of supplementary groups; an empty list clears them", "GROUP1,GROUP2...")
         .optopt("", "userspec", "Colon-separated user and group to switch to. \
         Same as -u USER -g GROUP. \
         Userspec has higher preference than -u and/or -g", "USER:GROUP")
         .parse(args);
 
     if matches.free.is_empty() {
-        println!("Missing operand: NEWROOT");
-        println!("Try `{} --help` for more information.", NAME);
-        return 1
+        disp_err!("missing operand");
+        return EXIT_CANCELED;
     }
 
     let default_shell: &'static str = "/bin/sh";
@@ -53,7 +59,7 @@ pub fn uumain(args: Vec<String>) -> i32 {
 
     let newroot = Path::new(&matches.free[0][..]);
     if !newroot.is_dir() {
-        crash!(1, "cannot change root directory to `{}`: no such directory", newroot.display());
+        crash!(EXIT_CANCELED, "cannot change root directory to '{}': no such directory", newroot.display());
     }
 
     let command: Vec<&str> = match matches.free.len() {
@@ -69,18 +75,32 @@ pub fn uumain(args: Vec<String>) -> i32 {
 
     set_context(&newroot, &matches);
 
-    let pstatus = Command::new(command[0])
-        .args(&command[1..])
-        .status()
-        .unwrap_or_else(|e| crash!(1, "Cannot exec: {}", e));
-
-    if pstatus.success() {
-        0
-    } else {
-        match pstatus.code() {
-            Some(i) => i,
-            None => -1,
+    // looking up names may have left files of the old root open
+    if let Err(e) = process::prepare_fds(&inherited_fds) {
+        crash!(EXIT_CANCELED, "failed to prepare file descriptors: {}", strerror(&e));
+    }
+
+    let pstatus = match Command::new(command[0]).args(&command[1..]).status() {
+        Ok(status) => status,
+        Err(e) => {
+            show_error!("failed to run command '{}': {}", command[0], strerror(&e));
+            return if e.kind() == ErrorKind::NotFound { EXIT_ENOENT } else { EXIT_CANNOT_INVOKE };
         }
+    };
+
+    match pstatus.code() {
+        Some(i) => i,
+        // mimic the shell and report death by signal as 128 + signal number
+        None => 128 + pstatus.signal().unwrap_or(0),
+    }
+}
+
+// Describe an I/O error the way GNU does, without Rust's "(os error N)" suffix
+fn strerror(err: &Error) -> String {
+    let msg = err.to_string();
+    match msg.find(" (os error ") {
+        Some(pos) => msg[..pos].to_owned(),
+        None => msg,
     }
 }
 
@@ -88,12 +108,13 @@ fn set_context(root: &Path, options: &getopts::Matches) {
     let userspec_str = options.opt_str("userspec");
     let user_str = options.opt_str("user").unwrap_or_default();
     let group_str = options.opt_str("group").unwrap_or_default();
-    let groups_str = options.opt_str("groups").unwrap_or_default();
+    // an explicitly empty --groups="" is meaningful: it clears the supplementary groups
+    let groups_str = options.opt_str("groups");
     let userspec = match userspec_str {
         Some(ref u) => {
             let s: Vec<&str> = u.split(':').collect();
             if s.len() != 2 {
-                crash!(1, "invalid userspec: `{}`", u)
+                crash!(EXIT_CANCELED, "invalid userspec: '{}'", u)
             };
             s
         }
@@ -102,39 +123,111 @@ fn set_context(root: &Path, options: &getopts::Matches) {
     let user = if userspec.is_empty() { &user_str[..] } else { &userspec[0][..] };
     let group = if userspec.is_empty() { &group_str[..] } else { &userspec[1][..] };
 
+    // Names are looked up with the databases of the current root, which are
+    // likely gone after chroot().  Only if that fails are they looked up again
+    // inside the new root, like GNU chroot does.
+    let early = resolve_credentials(user, group, groups_str.as_ref()).ok();
+
     enter_chroot(root);
 
-    set_groups_from_str(&groups_str[..]);
-    set_main_group(&group[..]);
-    set_user(&user[..]);
+    let creds = match early {
+        Some(creds) => creds,
+        None => match resolve_credentials(user, group, groups_str.as_ref()) {
+            Ok(creds) => creds,
+            Err(msg) => crash!(EXIT_CANCELED, "{}", msg),
+        },
+    };
+
+    // The order matters: both setgroups() and setgid() need privileges that
+    // are dropped by setuid().
+    if let Some(groups) = creds.groups {
+        set_supplementary_groups(&groups);
+    }
+    if let Some(gid) = creds.gid {
+        set_main_group(gid);
+    }
+    if let Some(uid) = creds.uid {
+        set_user(uid);
+    }
+}
+
+/// Numeric IDs to switch to; `None` leaves the current value alone.
+struct Credentials {
+    uid: Option<libc::uid_t>,
+    gid: Option<libc::gid_t>,
+    groups: Option<Vec<libc::gid_t>>,
+}
+
+fn resolve_credentials(user: &str, group: &str, groups: Option<&String>) -> Result<Credentials, String> {
+    let mut creds = Credentials { uid: None, gid: None, groups: None };
+    // primary and supplementary groups of the user, if it has an entry
+    let mut user_groups = None;
+
+    if !user.is_empty() {
+        match user.parse::<libc::uid_t>() {
+            Ok(uid) => {
+                // purely numeric IDs do not need to exist in the user database
+                creds.uid = Some(uid);
+                if let Ok(p) = entries::Passwd::locate(uid) {
+                    user_groups = Some((p.gid(), p.belongs_to()));
+                }
+            }
+            Err(_) => match entries::Passwd::locate(user) {
+                Ok(p) => {
+                    creds.uid = Some(p.uid());
+                    user_groups = Some((p.gid(), p.belongs_to()));
+                }
+                Err(_) => return Err(format!("invalid user: '{}'", user)),
+            },
+        }
+    }
+
+    if !group.is_empty() {
+        creds.gid = Some(try!(resolve_group(group)));
+    } else if let Some((gid, _)) = user_groups {
+        creds.gid = Some(gid);
+    } else if let Some(uid) = creds.uid {
+        return Err(format!("no group specified for unknown uid: {}", uid));
+    }
+
+    // Without --groups, a new user gets their own supplementary groups instead
+    // of inheriting ours; with neither, the groups are left alone.
+    creds.groups = match groups {
+        Some(g) => Some(try!(parse_groups(g))),
+        None => user_groups.map(|(_, groups)| groups),
+    };
+
+    Ok(creds)
+}
+
+fn resolve_group(group: &str) -> Result<libc::gid_t, String> {
+    match group.parse::<libc::gid_t>() {
+        Ok(gid) => Ok(gid),
+        Err(_) => entries::grp2gid(group).map_err(|_| format!("invalid group: '{}'", group)),
+    }
 }
 
 fn enter_chroot(root: &Path) {
     let root_str = root.display();
-    std::env::set_current_dir(root).unwrap();
-    let err = unsafe {
-        chroot(CString::new(".".as_bytes()).unwrap().as_bytes_with_nul().as_ptr() as *const libc::c_char)
-    };
+    if let Err(e) = std::env::set_current_dir(root) {
+        crash!(EXIT_CANCELED, "cannot change directory to '{}': {}", root_str, strerror(&e))
+    }
+    let dot = CString::new(".").unwrap();
+    let err = unsafe { chroot(dot.as_ptr()) };
     if err != 0 {
-        crash!(1, "cannot chroot to {}: {}", root_str, Error::last_os_error())
+        crash!(EXIT_CANCELED, "cannot change root directory to '{}': {}", root_str, strerror(&Error::last_os_error()))
     };
 }
 
-fn set_main_group(group: &str) {
-    if !group.is_empty() {
-        let group_id = match entries::grp2gid(group) {
-            Ok(g) => g,
-            _ => crash!(1, "no such group: {}", group),
-        };
-        let err = unsafe { setgid(group_id) };
-        if err != 0 {
-            crash!(1, "cannot set gid to {}: {}", group_id, Error::last_os_error())
-        }
+fn set_main_group(group_id: libc::gid_t) {
+    let err = unsafe { setgid(group_id) };
+    if err != 0 {
+        crash!(EXIT_CANCELED, "cannot set gid to {}: {}", group_id, strerror(&Error::last_os_error()))
     }
 }
 
 #[cfg(any(target_os = "macos", target_os = "freebsd"))]
-fn set_groups(groups: Vec<libc::gid_t>) -> libc::c_int {
+fn set_groups(groups: &[libc::gid_t]) -> libc::c_int {
     unsafe {
         setgroups(groups.len() as libc::c_int,
                   groups.as_ptr())
@@ -142,35 +235,27 @@ fn set_groups(groups: Vec<libc::gid_t>) -> libc::c_int {
 }
 
 #[cfg(target_os = "linux")]
-fn set_groups(groups: Vec<libc::gid_t>) -> libc::c_int {
+fn set_groups(groups: &[libc::gid_t]) -> libc::c_int {
     unsafe {
         setgroups(groups.len() as libc::size_t,
                   groups.as_ptr())
     }
 }
 
-fn set_groups_from_str(groups: &str) {
-    if !groups.is_empty() {
-        let groups_vec: Vec<libc::gid_t> = FromIterator::from_iter(
-            groups.split(',').map(
-                |x| match entries::grp2gid(x) {
-                    Ok(g) => g,
-                    _ => crash!(1, "no such group: {}", x),
-                })
-            );
-        let err = set_groups(groups_vec);
-        if err != 0 {
-            crash!(1, "cannot set groups: {}", Error::last_os_error())
-        }
+fn parse_groups(groups: &str) -> Result<Vec<libc::gid_t>, String> {
+    FromIterator::from_iter(groups.split(',').filter(|x| !x.is_empty()).map(resolve_group))
+}
+
+fn set_supplementary_groups(groups: &[libc::gid_t]) {
+    let err = set_groups(groups);
+    if err != 0 {
+        crash!(EXIT_CANCELED, "cannot set groups: {}", strerror(&Error::last_os_error()))
     }
 }
 
-fn set_user(user: &str) {
-    if !user.is_empty() {
-        let user_id = entries::usr2uid(user).unwrap();
-        let err = unsafe { setuid(user_id as libc::uid_t) };
-        if err != 0 {
-            crash!(1, "cannot set user to {}: {}", user, Error::last_os_error())
-        }
+fn set_user(user_id: libc::uid_t) {
+    let err = unsafe { setuid(user_id) };
+    if err != 0 {
+        crash!(EXIT_CANCELED, "cannot set user to {}: {}", user_id, strerror(&Error::last_os_error()))
     }
 }
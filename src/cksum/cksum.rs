@@ -85,8 +85,17 @@ fn cksum(fname: &str) -> io::Result<(u32, usize)> {
 }
 
 pub fn uumain(args: Vec<String>) -> i32 {
-    let matches = new_coreopts!(SYNTAX, SUMMARY, LONG_HELP)
-        .parse(args);
+    let mut opts = new_coreopts!(SYNTAX, SUMMARY, LONG_HELP);
+    #[cfg(all(target_os = "linux", feature = "sandbox"))]
+    opts.optflag("", "sandbox", "only allow reading the FILEs given on the command line");
+    let matches = opts.parse(args);
+
+    #[cfg(all(target_os = "linux", feature = "sandbox"))]
+    {
+        if matches.opt_present("sandbox") {
+            uucore::sandbox::Sandbox::for_operands(&matches.free, None).enter(1);
+        }
+    }
 
     let files = matches.free;
 
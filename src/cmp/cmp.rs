@@ -0,0 +1,165 @@
+#![crate_name = "uu_cmp"]
+
+/*
+ * This file is part of the uutils coreutils package.
+ *
+ * For the full copyright and license information, please view the LICENSE
+ * file that was distributed with this source code.
+ */
+
+// cmp belongs to diffutils; this is a thin front end to uucore::compare,
+// which install --compare also uses.
+
+extern crate getopts;
+
+#[macro_use]
+extern crate uucore;
+
+use std::fs::{self, File};
+use std::io::{self, Read, stdin};
+use uucore::compare::{self, Difference};
+
+static NAME: &'static str = "cmp";
+static VERSION: &'static str = env!("CARGO_PKG_VERSION");
+
+// exit statuses, as for diff
+const EXIT_SAME: i32 = 0;
+const EXIT_DIFFERENT: i32 = 1;
+const EXIT_TROUBLE: i32 = 2;
+
+#[derive(PartialEq)]
+enum Output {
+    /// where the first difference is
+    First,
+    /// every differing byte
+    All,
+    /// nothing, only the exit status
+    Silent,
+}
+
+pub fn uumain(args: Vec<String>) -> i32 {
+    let mut opts = getopts::Options::new();
+
+    opts.optflag("l", "verbose", "output byte numbers and differing byte values");
+    opts.optflag("s", "silent", "suppress all normal output");
+    opts.optflag("", "quiet", "same as --silent");
+    opts.optflag("h", "help", "display this help and exit");
+    opts.optflag("V", "version", "output version information and exit");
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            disp_err!("{}", f);
+            return EXIT_TROUBLE;
+        }
+    };
+
+    if matches.opt_present("help") {
+        let msg = format!("{0} {1}
+
+Usage:
+  {0} [OPTION]... FILE1 [FILE2]
+
+Compare two files byte by byte.", NAME, VERSION);
+        println!("{}\nWith no FILE2, or when a FILE is -, read standard input.\n\
+                  Exit status is 0 if the inputs are the same, 1 if different, 2 if trouble.",
+                 opts.usage(&msg));
+        return EXIT_SAME;
+    }
+    if matches.opt_present("version") {
+        println!("{} {}", NAME, VERSION);
+        return EXIT_SAME;
+    }
+
+    let silent = matches.opt_present("silent") || matches.opt_present("quiet");
+    let output = match (matches.opt_present("verbose"), silent) {
+        (true, true) => {
+            disp_err!("options -l and -s are incompatible");
+            return EXIT_TROUBLE;
+        }
+        (true, false) => Output::All,
+        (false, true) => Output::Silent,
+        (false, false) => Output::First,
+    };
+
+    let mut names = matches.free;
+    match names.len() {
+        0 => {
+            disp_err!("missing operand after '{}'", NAME);
+            return EXIT_TROUBLE;
+        }
+        1 => names.push("-".to_owned()),
+        2 => {}
+        _ => {
+            disp_err!("extra operand '{}'", names[2]);
+            return EXIT_TROUBLE;
+        }
+    }
+
+    match cmp(&names[0], &names[1], output) {
+        Ok(status) => status,
+        Err(msg) => {
+            show_error!("{}", msg);
+            EXIT_TROUBLE
+        }
+    }
+}
+
+fn open(name: &str) -> io::Result<Box<Read>> {
+    if name == "-" {
+        Ok(Box::new(stdin()))
+    } else {
+        Ok(Box::new(try!(File::open(name))))
+    }
+}
+
+fn cmp(name_a: &str, name_b: &str, output: Output) -> Result<i32, String> {
+    let a = try!(open(name_a).map_err(|e| format!("{}: {}", name_a, e)));
+    let b = try!(open(name_b).map_err(|e| format!("{}: {}", name_b, e)));
+
+    let result = if output == Output::All {
+        let width = offset_width(name_a, name_b);
+        compare::compare_all(a, b, |offset, x, y| println!("{:>3$} {:3o} {:3o}", offset, x, y, width))
+    } else {
+        compare::compare(a, b)
+    };
+    let difference = try!(result.map_err(|e| e.to_string()));
+
+    match difference {
+        None => Ok(EXIT_SAME),
+        Some(_) if output == Output::Silent => Ok(EXIT_DIFFERENT),
+        Some(Difference::Byte { offset, line, .. }) => {
+            if output == Output::First {
+                println!("{} {} differ: char {}, line {}", name_a, name_b, offset, line);
+            }
+            Ok(EXIT_DIFFERENT)
+        }
+        Some(Difference::Eof { first, offset, line, line_start }) => {
+            let name = if first { name_a } else { name_b };
+            if offset == 0 {
+                show_info!("EOF on {} which is empty", name);
+            } else if output == Output::All {
+                show_info!("EOF on {} after byte {}", name, offset);
+            } else if line_start {
+                show_info!("EOF on {} after byte {}, line {}", name, offset, line - 1);
+            } else {
+                show_info!("EOF on {} after byte {}, in line {}", name, offset, line);
+            }
+            Ok(EXIT_DIFFERENT)
+        }
+    }
+}
+
+// Byte numbers for -l are padded to the width of the largest one possible,
+// which is only known when one of the inputs is a regular file.
+fn offset_width(name_a: &str, name_b: &str) -> usize {
+    let max = [name_a, name_b]
+        .iter()
+        .filter(|name| **name != "-")
+        .filter_map(|name| fs::metadata(name).ok())
+        .filter(|meta| meta.is_file())
+        .map(|meta| meta.len())
+        .min()
+        .unwrap_or(i64::max_value() as u64);
+    max.to_string().len()
+}
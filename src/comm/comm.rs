@@ -16,8 +16,9 @@ extern crate uucore;
 
 use std::cmp::Ordering;
 use std::fs::File;
-use std::io::{self, BufRead, BufReader, stdin, Stdin};
+use std::io::{self, BufRead, BufReader, stdin};
 use std::path::Path;
+use uucore::lines::{self, LineEnding, Lines};
 
 static SYNTAX: &'static str = "[OPTIONS] FILE1 FILE2"; 
 static SUMMARY: &'static str = "Compare sorted files line by line"; 
@@ -40,102 +41,88 @@ fn mkdelim(col: usize, opts: &getopts::Matches) -> String {
     s
 }
 
-fn ensure_nl(line: &mut String) {
-    match line.chars().last() {
-        Some('\n') => (),
-        _ => line.push_str("\n")
-    }
-}
-
-enum LineReader {
-    Stdin(Stdin),
-    FileIn(BufReader<File>)
-}
+type LineReader = Lines<Box<BufRead>>;
 
-impl LineReader {
-    fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
-        match *self {
-            LineReader::Stdin(ref mut r)  => r.read_line(buf),
-            LineReader::FileIn(ref mut r) => r.read_line(buf),
-        }
-    }
-}
-
-fn comm(a: &mut LineReader, b: &mut LineReader, opts: &getopts::Matches) {
+// A line that can't be read ends its file, as if it were the last.
+fn comm(a: &mut LineReader, b: &mut LineReader, ending: LineEnding, opts: &getopts::Matches) {
 
     let delim : Vec<String> = (0 .. 4).map(|col| mkdelim(col, opts)).collect();
 
-    let ra = &mut String::new();
-    let mut na = a.read_line(ra);
-    let rb = &mut String::new();
-    let mut nb = b.read_line(rb);
-
-    while na.is_ok() || nb.is_ok() {
-        let ord = match (na.is_ok(), nb.is_ok()) {
-            (false, true)  => Ordering::Greater,
-            (true , false) => Ordering::Less,
-            (true , true) => match(&na, &nb) {
-                (&Ok(0), &Ok(0)) => break,
-                (&Ok(0), _) => Ordering::Greater,
-                (_, &Ok(0)) => Ordering::Less,
-                _ =>  ra.cmp(&rb),
-            },
-            _ => unreachable!(),
+    let mut ra = a.next().and_then(|line| line.ok());
+    let mut rb = b.next().and_then(|line| line.ok());
+
+    loop {
+        let ord = match (&ra, &rb) {
+            (&None, &None) => break,
+            (&None, &Some(_)) => Ordering::Greater,
+            (&Some(_), &None) => Ordering::Less,
+            (&Some(ref la), &Some(ref lb)) => la.cmp(lb),
         };
 
         match ord {
             Ordering::Less => {
                 if !opts.opt_present("1") {
-                    ensure_nl(ra);
-                    print!("{}{}", delim[1], ra);
+                    print!("{}{}{}", delim[1], ra.as_ref().unwrap(), ending.as_str());
                 }
-                ra.clear();
-                na = a.read_line(ra);
+                ra = a.next().and_then(|line| line.ok());
             },
             Ordering::Greater => {
                 if !opts.opt_present("2") {
-                    ensure_nl(rb);
-                    print!("{}{}", delim[2], rb);
+                    print!("{}{}{}", delim[2], rb.as_ref().unwrap(), ending.as_str());
                 }
-                rb.clear();
-                nb = b.read_line(rb);
+                rb = b.next().and_then(|line| line.ok());
             },
             Ordering::Equal => {
                 if !opts.opt_present("3") {
-                    ensure_nl(ra);
-                    print!("{}{}", delim[3], ra);
+                    print!("{}{}{}", delim[3], ra.as_ref().unwrap(), ending.as_str());
                 }
-                ra.clear();
-                rb.clear();
-                na = a.read_line(ra);
-                nb = b.read_line(rb);
+                ra = a.next().and_then(|line| line.ok());
+                rb = b.next().and_then(|line| line.ok());
             }
         }
     }
 }
 
-fn open_file(name: &str) -> io::Result<LineReader> {
+fn open_file(name: &str) -> io::Result<Box<BufRead>> {
     match name {
-        "-" => Ok(LineReader::Stdin(stdin())),
+        "-" => Ok(Box::new(BufReader::new(stdin()))),
         _  => {
             let f = try!(File::open(&Path::new(name)));
-            Ok(LineReader::FileIn(BufReader::new(f)))
+            Ok(Box::new(BufReader::new(f)))
         }
     }
 }
 
+#[cfg(feature = "decompress")]
+fn open_decompressed(name: &str) -> io::Result<Box<BufRead>> {
+    uucore::decompress::open(name, true)
+}
+
 pub fn uumain(args: Vec<String>) -> i32 {
-    let matches = new_coreopts!(SYNTAX, SUMMARY, LONG_HELP)
-        .optflag("1", "", "suppress column 1 (lines uniq to FILE1)")
+    let mut opts = new_coreopts!(SYNTAX, SUMMARY, LONG_HELP);
+    opts.optflag("1", "", "suppress column 1 (lines uniq to FILE1)")
         .optflag("2", "", "suppress column 2 (lines uniq to FILE2)")
         .optflag("3", "", "suppress column 3 (lines that appear in both files)")
         .optopt("", "output-delimiter", "separate columns with STR", "STR")
-        .parse(args);
+        .optflag("", "crlf", "lines end in carriage return and newline, as in Windows text files");
+    #[cfg(feature = "decompress")]
+    opts.optflag("", "decompress", "decompress gzip and zstd compressed input");
+    let matches = opts.parse(args);
+
+    #[cfg(feature = "decompress")]
+    let open: fn(&str) -> io::Result<Box<BufRead>> = if matches.opt_present("decompress") {
+        open_decompressed
+    } else {
+        open_file
+    };
+    #[cfg(not(feature = "decompress"))]
+    let open = open_file;
 
-    let mut f1 = open_file(matches.free[0].as_ref()).unwrap();
-    let mut f2 = open_file(matches.free[1].as_ref()).unwrap();
+    let ending = LineEnding::from_crlf(matches.opt_present("crlf"));
+    let mut f1 = lines::lines(open(matches.free[0].as_ref()).unwrap(), ending);
+    let mut f2 = lines::lines(open(matches.free[1].as_ref()).unwrap(), ending);
 
-    comm(&mut f1, &mut f2, &matches);
+    comm(&mut f1, &mut f2, ending, &matches);
 
     0
 }
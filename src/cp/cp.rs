@@ -13,13 +13,8 @@
 extern crate libc;
 extern crate clap;
 extern crate walkdir;
-extern crate filetime;
-#[cfg(target_os = "linux")]
-#[macro_use] extern crate ioctl_sys;
 #[macro_use] extern crate uucore;
 #[macro_use] extern crate quick_error;
-#[cfg(unix)]
-extern crate xattr;
 
 #[cfg(windows)]
 use std::os::windows::io::AsRawHandle;
@@ -29,30 +24,32 @@ extern crate kernel32;
 #[cfg(windows)]
 use kernel32::GetFileInformationByHandle;
 #[cfg(windows)]
-use kernel32::CreateFile2;
-#[cfg(windows)]
 extern crate winapi;
 
+#[cfg(windows)]
 use std::mem;
+#[cfg(unix)]
 use std::ffi::CString;
 use clap::{Arg, App, ArgMatches};
 use quick_error::ResultExt;
-use std::collections::HashSet;
+use std::cmp;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io::{BufReader, BufRead, stdin, stdout, Write};
 use std::io;
-use std::path::{Path, PathBuf, StripPrefixError};
-use std::str::FromStr;
+use std::path::{Component, Path, PathBuf, StripPrefixError};
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
+#[cfg(unix)]
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, MetadataExt, PermissionsExt};
+use uucore::backup::{self, BackupMode};
+use uucore::copy::{self, CopyMethod, FileCopier, Sparse};
 use uucore::fs::{canonicalize, CanonicalizeMode};
+#[cfg(unix)]
+use uucore::mounts::Boundary;
+use uucore::preserve::Attributes;
 use walkdir::WalkDir;
-#[cfg(target_os = "linux")] use std::os::unix::io::IntoRawFd;
-use std::fs::File;
 use std::fs::OpenOptions;
-use filetime::FileTime;
-
-#[cfg(unix)] use std::os::unix::fs::PermissionsExt;
-
-#[cfg(target_os = "linux")] ioctl!(write ficlone with 0x94, 9; std::os::raw::c_int);
 
 quick_error! {
     #[derive(Debug)]
@@ -75,8 +72,8 @@ quick_error! {
         }
 
         /// Represents the state when a non-fatal error has occured
-        /// and not all files were copied.
-        NotAllFilesCopied {}
+        /// and not all files were copied, with the status to exit with.
+        NotAllFilesCopied(status: i32) {}
 
         /// Simple walkdir::Error wrapper
         WalkDirErr(err: walkdir::Error) { from() display("{}", err) cause(err) }
@@ -128,6 +125,19 @@ macro_rules! prompt_yes(
     })
 );
 
+impl Error {
+    /// The status to exit with after this error: `EXIT_ERR`, or one of
+    /// those of `uucore::copy` for a copy that ran out of space or hit an
+    /// I/O error.
+    fn exit_status(&self) -> i32 {
+        match *self {
+            Error::IoErr(ref e) | Error::IoErrContext(ref e, _) => copy::exit_status(e).unwrap_or(EXIT_ERR),
+            Error::NotAllFilesCopied(status) => status,
+            _ => EXIT_ERR,
+        }
+    }
+}
+
 pub type CopyResult<T> = Result<T, Error>;
 pub type Source = PathBuf;
 pub type Target = PathBuf;
@@ -162,48 +172,33 @@ pub enum TargetType {
     File,
 }
 
-#[derive(Clone, Eq, PartialEq)]
-pub enum BackupMode {
-    ExistingBackup,
-    NoBackup,
-    NumberedBackup,
-    SimpleBackup,
-}
-
 pub enum CopyMode {
     Link,
     SymLink,
-    Sparse,
     Copy,
     Update,
     AttrOnly
 }
 
-#[derive(Clone, Eq, PartialEq)]
-pub enum Attribute {
-    #[cfg(unix)] Mode,
-    Ownership,
-    Timestamps,
-    Context,
-    Links,
-    Xattr,
-}
-
 /// Re-usable, extensible copy options
 #[allow(dead_code)]
 pub struct Options {
     attributes_only: bool,
-    backup: bool,
+    backup: BackupMode,
     copy_contents: bool,
     copy_mode: CopyMode,
+    /// follow symbolic links met while recursing
     dereference: bool,
+    /// follow symbolic links given as SOURCE
+    cli_dereference: bool,
     no_target_dir: bool,
     one_file_system: bool,
     overwrite: OverwriteMode,
     parents: bool,
     reflink: bool,
     reflink_mode: ReflinkMode,
-    preserve_attributes: Vec<Attribute>,
+    sparse_mode: Sparse,
+    preserve_attributes: Attributes,
     recursive: bool,
     backup_suffix: String,
     target_dir: Option<String>,
@@ -213,6 +208,26 @@ pub struct Options {
 
 static VERSION: &str = env!("CARGO_PKG_VERSION");
 static ABOUT: &str = "Copy SOURCE to DEST, or multiple SOURCE(s) to DIRECTORY.";
+static LONG_HELP: &str = "When --reflink[=always] is specified, perform a lightweight copy, where the
+data blocks are copied only when modified.  If this is not possible the copy
+fails, or if --reflink=auto is specified, fall back to a standard copy.
+Use --reflink=never to ensure a standard copy is performed.  Without --reflink,
+cp behaves as with --reflink=auto.
+
+By default, sparse SOURCE files are detected by a crude heuristic and the
+corresponding DEST file is made sparse as well.  That is the behavior
+selected by --sparse=auto.  Specify --sparse=always to create a sparse DEST
+file whenever the SOURCE file contains a long enough sequence of zero bytes.
+Use --sparse=never to inhibit creation of sparse files.
+
+The backup suffix is '~', unless set with --suffix or SIMPLE_BACKUP_SUFFIX.
+The version control method may be selected via the --backup option or through
+the VERSION_CONTROL environment variable.  Here are the values:
+
+  none, off       never make backups (even if --backup is given)
+  numbered, t     make numbered backups
+  existing, nil   numbered if numbered backups exist, simple otherwise
+  simple, never   always make simple backups";
 static EXIT_OK: i32 = 0;
 static EXIT_ERR: i32 = 1;
 
@@ -232,6 +247,7 @@ fn get_usage() -> String {
 static OPT_ARCHIVE:                       &str = "archive";
 static OPT_ATTRIBUTES_ONLY:               &str = "attributes-only";
 static OPT_BACKUP:                        &str = "backup";
+static OPT_BACKUP_NO_ARG:                 &str = "b";
 static OPT_CLI_SYMBOLIC_LINKS:            &str = "cli-symbolic-links";
 static OPT_CONTEXT:                       &str = "context";
 static OPT_COPY_CONTENTS:                 &str = "copy-contents";
@@ -262,17 +278,7 @@ static OPT_UPDATE:                        &str = "update";
 static OPT_VERBOSE:                       &str = "verbose";
 static OPT_VERSION:                       &str = "version";
 
-#[cfg(unix)]
-static PRESERVABLE_ATTRIBUTES: &[&str] = &["mode", "ownership", "timestamps", "context", "links", "xattr", "all"];
-
-#[cfg(not(unix))]
-static PRESERVABLE_ATTRIBUTES: &[&str] = &["ownership", "timestamps", "context", "links", "xattr", "all"];
-
-static DEFAULT_ATTRIBUTES: &[Attribute] = &[
-    #[cfg(unix)] Attribute::Mode,
-    Attribute::Ownership,
-    Attribute::Timestamps,
-];
+static DEFAULT_ATTRIBUTES: &str = "mode,ownership,timestamps";
 
 
 pub fn uumain(args: Vec<String>) -> i32 {
@@ -281,6 +287,7 @@ pub fn uumain(args: Vec<String>) -> i32 {
         .version(VERSION)
         .about(ABOUT)
         .usage(&usage[..])
+        .after_help(LONG_HELP)
         .arg(Arg::with_name(OPT_TARGET_DIRECTORY)
              .short("t")
              .conflicts_with(OPT_NO_TARGET_DIRECTORY)
@@ -300,8 +307,8 @@ pub fn uumain(args: Vec<String>) -> i32 {
         .arg(Arg::with_name(OPT_INTERACTIVE)
              .short("i")
              .long(OPT_INTERACTIVE)
-             .conflicts_with(OPT_NO_CLOBBER)
-             .help("ask before overwriting files"))
+             .overrides_with(OPT_NO_CLOBBER)
+             .help("ask before overwriting files (overrides a previous -n option)"))
         .arg(Arg::with_name(OPT_LINK)
              .short("l")
              .long(OPT_LINK)
@@ -310,8 +317,8 @@ pub fn uumain(args: Vec<String>) -> i32 {
         .arg(Arg::with_name(OPT_NO_CLOBBER)
              .short("n")
              .long(OPT_NO_CLOBBER)
-             .conflicts_with(OPT_INTERACTIVE)
-             .help("don't overwrite a file that already exists"))
+             .overrides_with(OPT_INTERACTIVE)
+             .help("don't overwrite a file that already exists (overrides a previous -i option)"))
         .arg(Arg::with_name(OPT_RECURSIVE)
              .short("r")
              .long(OPT_RECURSIVE)
@@ -341,24 +348,31 @@ pub fn uumain(args: Vec<String>) -> i32 {
              .help("remove each existing destination file before attempting to open it \
                     (contrast with --force). On Windows, current only works for writeable files."))
         .arg(Arg::with_name(OPT_BACKUP)
-             .short("b")
              .long(OPT_BACKUP)
+             .takes_value(true)
+             .min_values(0)
+             .require_equals(true)
+             .value_name("CONTROL")
              .help("make a backup of each existing destination file"))
+        .arg(Arg::with_name(OPT_BACKUP_NO_ARG)
+             .short("b")
+             .help("like --backup but does not accept an argument"))
         .arg(Arg::with_name(OPT_SUFFIX)
              .short("S")
              .long(OPT_SUFFIX)
              .takes_value(true)
-             .default_value("~")
              .value_name("SUFFIX")
              .help("override the usual backup suffix"))
         .arg(Arg::with_name(OPT_UPDATE)
              .short("u")
              .long(OPT_UPDATE)
-             .help("copy only when the SOURCE file is newer than the destination file\
+             .help("copy only when the SOURCE file is newer than the destination file \
                     or when the destination file is missing"))
         .arg(Arg::with_name(OPT_REFLINK)
              .long(OPT_REFLINK)
              .takes_value(true)
+             .min_values(0)
+             .require_equals(true)
              .value_name("WHEN")
              .help("control clone/CoW copies. See below"))
         .arg(Arg::with_name(OPT_ATTRIBUTES_ONLY)
@@ -370,70 +384,69 @@ pub fn uumain(args: Vec<String>) -> i32 {
              .long(OPT_PRESERVE)
              .takes_value(true)
              .multiple(true)
-             .use_delimiter(true)
-             .possible_values(PRESERVABLE_ATTRIBUTES)
+             .min_values(0)
+             .require_equals(true)
              .value_name("ATTR_LIST")
-             .conflicts_with_all(&[OPT_PRESERVE_DEFAULT_ATTRIBUTES, OPT_NO_PRESERVE, OPT_ARCHIVE])
-             .help("Preserve the specified attributes (default: mode(unix only),ownership,timestamps),\
+             .help("preserve the specified attributes (default: mode,ownership,timestamps), \
                     if possible additional attributes: context, links, xattr, all"))
         .arg(Arg::with_name(OPT_PRESERVE_DEFAULT_ATTRIBUTES)
              .short("-p")
-             .long(OPT_PRESERVE_DEFAULT_ATTRIBUTES)
-             .conflicts_with_all(&[OPT_PRESERVE, OPT_NO_PRESERVE, OPT_ARCHIVE])
-             .help("same as --preserve=mode(unix only),ownership,timestamps"))
+             .help("same as --preserve=mode,ownership,timestamps"))
         .arg(Arg::with_name(OPT_NO_PRESERVE)
              .long(OPT_NO_PRESERVE)
              .takes_value(true)
+             .multiple(true)
+             .require_equals(true)
              .value_name("ATTR_LIST")
-             .conflicts_with_all(&[OPT_PRESERVE_DEFAULT_ATTRIBUTES, OPT_PRESERVE, OPT_ARCHIVE])
              .help("don't preserve the specified attributes"))
-
-        // TODO: implement the following args
         .arg(Arg::with_name(OPT_ARCHIVE)
              .short("a")
              .long(OPT_ARCHIVE)
-             .conflicts_with_all(&[OPT_PRESERVE_DEFAULT_ATTRIBUTES, OPT_PRESERVE, OPT_NO_PRESERVE])
-             .help("NotImplemented: same as -dR --preserve=all"))
-        .arg(Arg::with_name(OPT_COPY_CONTENTS)
-             .long(OPT_COPY_CONTENTS)
-             .conflicts_with(OPT_ATTRIBUTES_ONLY)
-             .help("NotImplemented: copy contents of special files when recursive"))
+             .help("same as -dR --preserve=all"))
         .arg(Arg::with_name(OPT_NO_DEREFERENCE_PRESERVE_LINKS)
              .short("d")
-             .help("NotImplemented: same as --no-dereference --preserve=links"))
+             .help("same as --no-dereference --preserve=links"))
         .arg(Arg::with_name(OPT_DEREFERENCE)
              .short("L")
              .long(OPT_DEREFERENCE)
-             .conflicts_with(OPT_NO_DEREFERENCE)
-             .help("NotImplemented: always follow symbolic links in SOURCE"))
+             .overrides_with_all(&[OPT_NO_DEREFERENCE, OPT_CLI_SYMBOLIC_LINKS])
+             .help("always follow symbolic links in SOURCE"))
         .arg(Arg::with_name(OPT_NO_DEREFERENCE)
              .short("-P")
              .long(OPT_NO_DEREFERENCE)
-             .conflicts_with(OPT_DEREFERENCE)
-             .help("NotImplemented: never follow symbolic links in SOURCE"))
+             .overrides_with_all(&[OPT_DEREFERENCE, OPT_CLI_SYMBOLIC_LINKS])
+             .help("never follow symbolic links in SOURCE"))
+        .arg(Arg::with_name(OPT_CLI_SYMBOLIC_LINKS)
+             .short("H")
+             .overrides_with_all(&[OPT_DEREFERENCE, OPT_NO_DEREFERENCE])
+             .help("follow command-line symbolic links in SOURCE"))
+        .arg(Arg::with_name(OPT_COPY_CONTENTS)
+             .long(OPT_COPY_CONTENTS)
+             .conflicts_with(OPT_ATTRIBUTES_ONLY)
+             .help("copy contents of special files when recursive"))
+
         .arg(Arg::with_name(OPT_PARENTS)
              .long(OPT_PARENTS)
-             .help("NotImplemented: use full source file name under DIRECTORY"))
+             .help("use full source file name under DIRECTORY"))
+
+        // TODO: implement the following args
         .arg(Arg::with_name(OPT_SPARSE)
              .long(OPT_SPARSE)
              .takes_value(true)
              .value_name("WHEN")
-             .help("NotImplemented: control creation of sparse files. See below"))
+             .help("control creation of sparse files. See below"))
         .arg(Arg::with_name(OPT_STRIP_TRAILING_SLASHES)
              .long(OPT_STRIP_TRAILING_SLASHES)
              .help("NotImplemented: remove any trailing slashes from each SOURCE argument"))
         .arg(Arg::with_name(OPT_ONE_FILE_SYSTEM)
              .short("x")
              .long(OPT_ONE_FILE_SYSTEM)
-             .help("NotImplemented: stay on this file system"))
+             .help("stay on this file system"))
         .arg(Arg::with_name(OPT_CONTEXT)
              .long(OPT_CONTEXT)
              .takes_value(true)
              .value_name("CTX")
              .help("NotImplemented: set SELinux security context of destination file to default type"))
-        .arg(Arg::with_name(OPT_CLI_SYMBOLIC_LINKS)
-             .short("H")
-             .help("NotImplemented: follow command-line symbolic links in SOURCE"))
         // END TODO
 
         .arg(Arg::with_name(OPT_PATHS)
@@ -456,11 +469,11 @@ pub fn uumain(args: Vec<String>) -> i32 {
         match error {
             // Error::NotAllFilesCopied is non-fatal, but the error
             // code should still be EXIT_ERR as does GNU cp
-            Error::NotAllFilesCopied => {}
+            Error::NotAllFilesCopied(_) => {}
             // Else we caught a fatal bubbled-up error, log it to stderr
             _ => show_error!("{}", error),
         };
-        return EXIT_ERR;
+        return error.exit_status();
     }
 
     EXIT_OK
@@ -479,6 +492,7 @@ impl ClobberMode {
 }
 
 impl OverwriteMode {
+    /// Of -i and -n, clap only keeps whichever came last.
     fn from_matches(matches: &ArgMatches) -> OverwriteMode {
         if matches.is_present(OPT_INTERACTIVE) {
             OverwriteMode::Interactive(ClobberMode::from_matches(matches))
@@ -496,46 +510,21 @@ impl CopyMode {
             CopyMode::Link
         } else if matches.is_present(OPT_SYMBOLIC_LINK) {
             CopyMode::SymLink
-        } else if matches.is_present(OPT_SPARSE) {
-            CopyMode::Sparse
-        } else if matches.is_present(OPT_UPDATE) {
-            CopyMode::Update
         } else if matches.is_present(OPT_ATTRIBUTES_ONLY) {
             CopyMode::AttrOnly
+        } else if matches.is_present(OPT_UPDATE) {
+            CopyMode::Update
         } else {
             CopyMode::Copy
         }
     }
 }
 
-impl FromStr for Attribute {
-    type Err = Error;
-
-    fn from_str(value: &str) -> CopyResult<Attribute> {
-        Ok(match &*value.to_lowercase() {
-            #[cfg(unix)] "mode" => Attribute::Mode,
-            "ownership" => Attribute::Ownership,
-            "timestamps" => Attribute::Timestamps,
-            "context" => Attribute::Context,
-            "links" => Attribute::Links,
-            "xattr" => Attribute::Xattr,
-            _ => return Err(Error::InvalidArgument(format!("invalid attribute '{}'", value)))
-        })
-    }
-}
-
 impl Options {
     fn from_matches(matches: &ArgMatches) -> CopyResult<Options> {
         let not_implemented_opts =  vec![
-            OPT_ARCHIVE,
-            OPT_COPY_CONTENTS,
-            OPT_NO_DEREFERENCE_PRESERVE_LINKS,
-            OPT_DEREFERENCE,
-            OPT_NO_DEREFERENCE,
-            OPT_PARENTS,
-            OPT_SPARSE,
             OPT_STRIP_TRAILING_SLASHES,
-            OPT_ONE_FILE_SYSTEM,
+            #[cfg(not(unix))] OPT_ONE_FILE_SYSTEM,
             OPT_CONTEXT,
             #[cfg(windows)] OPT_FORCE,
         ];
@@ -550,69 +539,106 @@ impl Options {
             || matches.is_present(OPT_RECURSIVE_ALIAS)
             || matches.is_present(OPT_ARCHIVE);
 
-        let backup = matches.is_present(OPT_BACKUP)
-            || matches.is_present(OPT_SUFFIX);
+        // a suffix alone asks for backups as well, as it does in GNU cp
+        let backup = match matches.value_of(OPT_BACKUP) {
+            Some(control) => BackupMode::parse(control)?,
+            None if matches.is_present(OPT_BACKUP)
+                || matches.is_present(OPT_BACKUP_NO_ARG)
+                || matches.is_present(OPT_SUFFIX) => BackupMode::from_env()?,
+            None => BackupMode::NoBackup,
+        };
+        if backup != BackupMode::NoBackup && matches.is_present(OPT_NO_CLOBBER) {
+            return Err("options --backup and --no-clobber are mutually exclusive".into());
+        }
 
         // Parse target directory options
         let no_target_dir = matches.is_present(OPT_NO_TARGET_DIRECTORY);
         let target_dir = matches.value_of(OPT_TARGET_DIRECTORY).map(|v| v.to_string());
 
-        // Parse attributes to preserve
-        let preserve_attributes: Vec<Attribute> = if matches.is_present(OPT_PRESERVE) {
+        // -a and -d come first, so that --no-preserve can take away from them
+        let mut preserve_attributes = if matches.is_present(OPT_ARCHIVE) {
+            Attributes::all()
+        } else {
+            Attributes::none()
+        };
+        if matches.is_present(OPT_NO_DEREFERENCE_PRESERVE_LINKS) {
+            preserve_attributes.links = true;
+        }
+        if matches.is_present(OPT_PRESERVE_DEFAULT_ATTRIBUTES) {
+            preserve_attributes.update(DEFAULT_ATTRIBUTES, true)?;
+        }
+        if matches.is_present(OPT_PRESERVE) {
             match matches.values_of(OPT_PRESERVE) {
-                None => DEFAULT_ATTRIBUTES.to_vec(),
-                Some(attribute_strs) => {
-                    let mut attributes = Vec::new();
-                    for attribute_str in attribute_strs {
-                        if attribute_str == "all" {
-                           #[cfg(unix)]
-                           attributes.push(Attribute::Mode);
-                           attributes.push(Attribute::Ownership);
-                           attributes.push(Attribute::Timestamps);
-                           attributes.push(Attribute::Context);
-                           attributes.push(Attribute::Xattr);
-                           attributes.push(Attribute::Links);
-                           break;
-                        } else {
-                            attributes.push(Attribute::from_str(attribute_str)?);
-                        }
+                None => preserve_attributes.update(DEFAULT_ATTRIBUTES, true)?,
+                Some(lists) => {
+                    for list in lists {
+                        preserve_attributes.update(list, true)?;
                     }
-                    attributes
                 }
             }
-        } else if matches.is_present(OPT_PRESERVE_DEFAULT_ATTRIBUTES) {
-            DEFAULT_ATTRIBUTES.to_vec()
+        }
+        for list in matches.values_of(OPT_NO_PRESERVE).into_iter().flat_map(|lists| lists) {
+            preserve_attributes.update(list, false)?;
+        }
+
+        // Without -H, -L or -P, symbolic links are only copied as links
+        // when recursing (except for hard links, which GNU makes to what
+        // the links point to)
+        let dereference = if matches.is_present(OPT_DEREFERENCE) {
+            true
+        } else if matches.is_present(OPT_NO_DEREFERENCE)
+            || matches.is_present(OPT_NO_DEREFERENCE_PRESERVE_LINKS)
+            || matches.is_present(OPT_ARCHIVE)
+            || matches.is_present(OPT_CLI_SYMBOLIC_LINKS) {
+            false
         } else {
-            vec![]
+            !recursive || matches.is_present(OPT_LINK)
         };
 
         let options = Options {
             attributes_only: matches.is_present(OPT_ATTRIBUTES_ONLY),
             copy_contents: matches.is_present(OPT_COPY_CONTENTS),
             copy_mode: CopyMode::from_matches(matches),
-            dereference: matches.is_present(OPT_DEREFERENCE),
+            cli_dereference: dereference || matches.is_present(OPT_CLI_SYMBOLIC_LINKS),
+            dereference,
             one_file_system: matches.is_present(OPT_ONE_FILE_SYSTEM),
             overwrite: OverwriteMode::from_matches(matches),
             parents: matches.is_present(OPT_PARENTS),
-            backup_suffix: matches.value_of(OPT_SUFFIX).unwrap().to_string(),
+            backup_suffix: backup::suffix(matches.value_of(OPT_SUFFIX)),
             update: matches.is_present(OPT_UPDATE),
             verbose: matches.is_present(OPT_VERBOSE),
             reflink: matches.is_present(OPT_REFLINK),
             reflink_mode: {
-                if let Some(reflink) = matches.value_of(OPT_REFLINK) {
-                    match reflink {
+                if matches.is_present(OPT_REFLINK) {
+                    // a bare --reflink means --reflink=always
+                    match matches.value_of(OPT_REFLINK).unwrap_or("always") {
                         "always" => {
                             ReflinkMode::Always
                         },
                         "auto" => {
                             ReflinkMode::Auto
                         },
+                        "never" => {
+                            ReflinkMode::Never
+                        },
                         value => {
-                            return Err(Error::InvalidArgument(format!("invalid argument '{}' for \'reflink\'", value)))
+                            return Err(Error::InvalidArgument(format!("invalid argument '{}' for '--reflink'\n\
+                                                                       Valid arguments are:\n  \
+                                                                       - 'auto'\n  - 'always'\n  - 'never'", value)))
                         }
                     }
                 } else {
-                    ReflinkMode::Never
+                    ReflinkMode::Auto
+                }
+            },
+            sparse_mode: match matches.value_of(OPT_SPARSE).unwrap_or("auto") {
+                "always" => Sparse::Always,
+                "auto" => Sparse::Auto,
+                "never" => Sparse::Never,
+                value => {
+                    return Err(Error::InvalidArgument(format!("invalid argument '{}' for '--sparse'\n\
+                                                               Valid arguments are:\n  \
+                                                               - 'never'\n  - 'auto'\n  - 'always'", value)))
                 }
             },
             backup,
@@ -622,6 +648,10 @@ impl Options {
             target_dir,
         };
 
+        if options.reflink_mode == ReflinkMode::Always && options.sparse_mode != Sparse::Auto {
+            return Err(Error::InvalidArgument("--reflink can be used only with --sparse=auto".to_string()));
+        }
+
         Ok(options)
     }
 }
@@ -673,50 +703,77 @@ fn parse_path_args(path_args: &[String], options: &Options) -> CopyResult<(Vec<S
     Ok((sources, target))
 }
 
-fn preserve_hardlinks(hard_links: &mut Vec<(String, u64)>, source: &std::path::PathBuf, dest: std::path::PathBuf, found_hard_link: &mut bool) -> CopyResult<()> {
-    // Redox does not currently support hard links
-    #[cfg(not(target_os = "redox"))]
-    {
-    if !source.is_dir() {
-        unsafe {
-            let src_path = CString::new(source.as_os_str().to_str().unwrap()).unwrap();
-            let inode: u64;
-            let nlinks: u64;
-            #[cfg(unix)]
-            {
-                let mut stat = mem::zeroed();
-                if libc::lstat(src_path.as_ptr(), &mut stat) < 0 {
-                    return Err(format!("cannot stat {:?}: {}", src_path, std::io::Error::last_os_error()).into());
-                }
-                inode = stat.st_ino as u64;
-                nlinks = stat.st_nlink as u64;
-            }
-            #[cfg(windows)]
-            {
-                let mut stat = mem::uninitialized();
-                let handle = CreateFile2(src_path.as_ptr() as *const u16,
-                                                        winapi::winnt::GENERIC_READ,
-                                                        winapi::winnt::FILE_SHARE_READ,
-                                                        0,
-                                                        std::ptr::null_mut());
-                if GetFileInformationByHandle(handle, stat) != 0 {
-                    return Err(format!("cannot get file information {:?}: {}", source, std::io::Error::last_os_error()).into());
-                }
-                inode = (((*stat).nFileIndexHigh as u64) << 32 | (*stat).nFileIndexLow as u64);
-                nlinks = (*stat).nNumberOfLinks as u64;
-            }
+/// Where the first copy of each file with more than one link went, by
+/// the device and inode of the source, for --preserve=links to make its
+/// other names links to that copy.
+type LinkMap = HashMap<(u64, u64), PathBuf>;
+
+/// The device and inode of `path`, if it is a file with other links to it.
+#[cfg(all(unix, not(target_os = "redox")))]
+fn linked_file_id(path: &Path, dereference: bool) -> io::Result<Option<(u64, u64)>> {
+    let metadata = if dereference { fs::metadata(path)? } else { fs::symlink_metadata(path)? };
+    if metadata.is_dir() || metadata.nlink() < 2 {
+        Ok(None)
+    } else {
+        Ok(Some((metadata.dev(), metadata.ino())))
+    }
+}
+
+#[cfg(windows)]
+fn linked_file_id(path: &Path, _dereference: bool) -> io::Result<Option<(u64, u64)>> {
+    if fs::metadata(path)?.is_dir() {
+        return Ok(None);
+    }
+    let file = fs::File::open(path)?;
+    let mut info: winapi::BY_HANDLE_FILE_INFORMATION = unsafe { mem::zeroed() };
+    if unsafe { GetFileInformationByHandle(file.as_raw_handle(), &mut info) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if info.nNumberOfLinks < 2 {
+        Ok(None)
+    } else {
+        Ok(Some((info.dwVolumeSerialNumber as u64, (info.nFileIndexHigh as u64) << 32 | info.nFileIndexLow as u64)))
+    }
+}
+
+// Redox does not currently support hard links
+#[cfg(target_os = "redox")]
+fn linked_file_id(_: &Path, _: bool) -> io::Result<Option<(u64, u64)>> {
+    Ok(None)
+}
 
-            for hard_link in hard_links.iter() {
-                if hard_link.1 == inode {
-                    std::fs::hard_link(hard_link.0.clone(), dest.clone()).unwrap();
-                    *found_hard_link = true;
+/// Copy `source` to `dest`, unless --preserve=links and another name of the
+/// same file has been copied already, in which case `dest` becomes a hard
+/// link to that copy.
+fn copy_or_link(source: &Path, dest: &Path, options: &Options, dereference: bool, links: &mut LinkMap)
+                -> CopyResult<()> {
+    let id = if options.preserve_attributes.links {
+        linked_file_id(source, dereference).context(&*format!("cannot stat '{}'", source.display()))?
+    } else {
+        None
+    };
+    let first_copy = id.and_then(|id| links.get(&id).cloned());
+
+    match first_copy {
+        Some(first_copy) => {
+            if fs::symlink_metadata(dest).is_ok() {
+                handle_existing_dest(source, dest, options)?;
+                if fs::symlink_metadata(dest).is_ok() {
+                    fs::remove_file(dest)?;
                 }
             }
-            if !(*found_hard_link) && nlinks > 1 {
-                hard_links.push((dest.clone().to_str().unwrap().to_string(), inode));
+            if options.verbose {
+                println!("{}", context_for(source, dest));
+            }
+            fs::hard_link(&first_copy, dest)
+                .context(&*format!("cannot create hard link '{}' to '{}'", dest.display(), first_copy.display()))?;
+        }
+        None => {
+            copy_file(source, dest, options, dereference)?;
+            if let Some(id) = id {
+                links.insert(id, dest.to_path_buf());
             }
         }
-    }
     }
     Ok(())
 }
@@ -729,35 +786,46 @@ fn preserve_hardlinks(hard_links: &mut Vec<(String, u64)>, source: &std::path::P
 ///
 /// [`Options`]: ./struct.Options.html
 fn copy(sources: &[Source], target: &Target, options: &Options) -> CopyResult<()> {
+    if options.parents && !target.is_dir() {
+        return Err("with --parents, the destination must be a directory".into());
+    }
     let target_type = TargetType::determine(sources, target);
     verify_target_type(target, &target_type)?;
 
-    let mut preserve_hard_links = false;
-    for attribute in &options.preserve_attributes {
-        if *attribute == Attribute::Links {
-            preserve_hard_links = true;
-        }
-    }
-
-    let mut hard_links: Vec<(String, u64)> = vec![];
+    let mut links = LinkMap::new();
 
     let mut non_fatal_errors = false;
+    let mut status = EXIT_ERR;
     let mut seen_sources = HashSet::with_capacity(sources.len());
     for source in sources {
         if seen_sources.contains(source) {
             show_warning!("source '{}' specified more than once", source.display());
         } else {
-            let mut found_hard_link = false;
-            if preserve_hard_links {
-                    let dest = construct_dest_path(source, target, &target_type, options)?;
-                    preserve_hardlinks(&mut hard_links, source, dest, &mut found_hard_link).unwrap();
-               }
-            if !found_hard_link {
-                if let Err(error) = copy_source(source, target, &target_type, options) {
-                    show_error!("{}", error);
-                    match error {
-                        Error::Skipped(_) => (),
-                        _ => non_fatal_errors = true,
+            let parent_dirs = if options.parents {
+                match make_parent_dirs(source, target, options) {
+                    Ok(dirs) => dirs,
+                    Err(error) => {
+                        show_error!("{}", error);
+                        non_fatal_errors = true;
+                        continue;
+                    }
+                }
+            } else {
+                vec![]
+            };
+            if let Err(error) = copy_source(source, target, &target_type, options, &mut links) {
+                show_error!("{}", error);
+                match error {
+                    Error::Skipped(_) => (),
+                    _ => non_fatal_errors = true,
+                }
+                status = cmp::max(status, error.exit_status());
+            }
+            if options.preserve_attributes != Attributes::none() {
+                for &(ref source, ref dest) in parent_dirs.iter().rev() {
+                    if let Err(error) = options.preserve_attributes.preserve(source, dest, true) {
+                        show_error!("{}", error);
+                        non_fatal_errors = true;
                     }
                 }
             }
@@ -765,7 +833,7 @@ fn copy(sources: &[Source], target: &Target, options: &Options) -> CopyResult<()
         }
     }
     if non_fatal_errors {
-        Err(Error::NotAllFilesCopied)
+        Err(Error::NotAllFilesCopied(status))
     } else {
        Ok(())
     }
@@ -780,6 +848,7 @@ fn construct_dest_path(source_path: &Path, target: &Target, target_type: &Target
     }
 
     Ok(match *target_type {
+        TargetType::Directory if options.parents => target.join(relative(source_path)),
         TargetType::Directory => {
             let root = source_path.parent().unwrap_or(source_path);
             localize_to_target(root, source_path, target)?
@@ -788,75 +857,140 @@ fn construct_dest_path(source_path: &Path, target: &Target, target_type: &Target
     })
 }
 
-fn copy_source(source: &Source, target: &Target, target_type: &TargetType, options: &Options)
-               -> CopyResult<()>
+fn copy_source(source: &Source, target: &Target, target_type: &TargetType, options: &Options,
+               links: &mut LinkMap) -> CopyResult<()>
 {
     let source_path = Path::new(&source);
-    if source_path.is_dir() {
+    let is_dir = if options.cli_dereference {
+        source_path.is_dir()
+    } else {
+        fs::symlink_metadata(source_path).map(|meta| meta.is_dir()).unwrap_or(false)
+    };
+    if is_dir && options.parents {
+        // the tree goes where its name says, under the directories made for it
+        let dest = construct_dest_path(source_path, target, target_type, options)?;
+        copy_directory(source, &dest.parent().unwrap_or(target).to_path_buf(), options, links)
+    } else if is_dir {
         // Copy as directory
-        copy_directory(source, target, options)
+        copy_directory(source, target, options, links)
     } else {
         // Copy as file
         let dest = construct_dest_path(source_path, target, target_type, options)?;
-        copy_file(source_path, dest.as_path(), options)
+        copy_or_link(source_path, dest.as_path(), options, options.cli_dereference, links)
     }
 }
 
 
+/// The components of `path` without any root, to be joined to a target
+/// directory with `--parents`.
+fn relative(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| match *component {
+            Component::Prefix(_) | Component::RootDir => false,
+            _ => true,
+        })
+        .collect()
+}
+
+/// With `--parents`, make the directories leading to `source` under
+/// `target`, each with the permissions of the directory it stands for.
+/// Returns the pairs of source and destination directories that had to be
+/// made, for their attributes to be preserved once `source` is copied.
+fn make_parent_dirs(source: &Path, target: &Path, options: &Options) -> CopyResult<Vec<(PathBuf, PathBuf)>> {
+    let mut made = vec![];
+    let mut source_dir = PathBuf::new();
+    let mut dest_dir = target.to_path_buf();
+    let components: Vec<Component> = source.components().collect();
+
+    for component in components.iter().take(components.len().saturating_sub(1)) {
+        source_dir.push(component.as_os_str());
+        match *component {
+            Component::Prefix(_) | Component::RootDir => continue,
+            _ => dest_dir.push(component.as_os_str()),
+        }
+        if dest_dir.is_dir() {
+            continue;
+        }
+        if dest_dir.exists() {
+            return Err(format!("'{}' exists but is not a directory", dest_dir.display()).into());
+        }
+
+        let mut builder = fs::DirBuilder::new();
+        #[cfg(unix)]
+        builder.mode(fs::metadata(&source_dir)?.permissions().mode());
+        builder.create(&dest_dir).context(&*format!("cannot make directory '{}'", dest_dir.display()))?;
+        if options.verbose {
+            println!("{} -> {}", source_dir.display(), dest_dir.display());
+        }
+        made.push((source_dir.clone(), dest_dir.clone()));
+    }
+
+    Ok(made)
+}
+
 /// Read the contents of the directory `root` and recursively copy the
 /// contents to `target`.
 ///
 /// Any errors encounted copying files in the tree will be logged but
 /// will not cause a short-circuit.
-fn copy_directory(root: &Path, target: &Target, options: &Options) -> CopyResult<()> {
+fn copy_directory(root: &Path, target: &Target, options: &Options, links: &mut LinkMap) -> CopyResult<()> {
     if !options.recursive {
         return Err(format!("omitting directory '{}'", root.display()).into());
     }
 
-    let root_path = Path::new(&root).canonicalize()?;
+    // copied into an existing directory, the tree keeps its name there
+    let dest_root = if target.exists() {
+        match root.file_name() {
+            Some(name) => target.join(name),
+            None => target.join(root.canonicalize()?.file_name().unwrap_or_default()),
+        }
+    } else {
+        target.to_path_buf()
+    };
 
-    let root_parent = if target.exists() {
-        root_path.parent()
+    // a link to a directory only gets here when it is to be followed
+    let walk_root = if fs::symlink_metadata(root)?.file_type().is_symlink() {
+        root.canonicalize()?
     } else {
-        Some(root_path.as_path())
+        root.to_path_buf()
     };
 
+    // directories get their attributes once everything in them is copied,
+    // so that copying doesn't change their timestamps again
+    let mut dirs = vec![];
+
+    // with -x, directories on other file systems are copied, but empty
     #[cfg(unix)]
-    let mut hard_links: Vec<(String, u64)> = vec![];
-     let mut preserve_hard_links = false;
-        for attribute in &options.preserve_attributes {
-            if *attribute == Attribute::Links {
-                preserve_hard_links = true;
-            }
-        }
+    let boundary = if options.one_file_system { Some(Boundary::of(&walk_root, true)?) } else { None };
 
-    // This should be changed once Redox supports hardlinks
-    #[cfg(any(windows, target_os = "redox"))]
-    let mut hard_links: Vec<(String, u64)> = vec![];
+    let mut entries = WalkDir::new(&walk_root).follow_links(options.dereference).into_iter();
+    while let Some(entry) = entries.next() {
+        let entry = or_continue!(entry);
+        let path = entry.path();
+        let local_to_target = dest_root.join(or_continue!(path.strip_prefix(&walk_root)));
 
-    for path in WalkDir::new(root) {
-        let path = or_continue!(or_continue!(path).path().canonicalize());
-        let local_to_root_parent = match root_parent {
-            Some(parent) => or_continue!(path.strip_prefix(&parent)).to_path_buf(),
-            None         => path.clone(),
-        };
-
-        let local_to_target = target.join(&local_to_root_parent);
-
-        if path.is_dir() && !local_to_target.exists() {
-            or_continue!(fs::create_dir_all(local_to_target.clone()));
-        } else if !path.is_dir() {
-            if preserve_hard_links {
-                let mut found_hard_link = false;
-                let source = path.to_path_buf();
-                let dest = local_to_target.as_path().to_path_buf();
-                preserve_hardlinks(&mut hard_links, &source, dest, &mut found_hard_link).unwrap();
-                if !found_hard_link {
-                        copy_file(path.as_path(), local_to_target.as_path(), options)?;
+        if entry.file_type().is_dir() {
+            if !local_to_target.exists() {
+                or_continue!(fs::create_dir_all(local_to_target.clone()));
+            }
+            dirs.push((path.to_path_buf(), local_to_target));
+            #[cfg(unix)]
+            {
+                if let Some(ref boundary) = boundary {
+                    let metadata = or_continue!(entry.metadata());
+                    if entry.depth() > 0 && boundary.crosses(metadata.dev(), metadata.ino()) {
+                        entries.skip_current_dir();
+                    }
                 }
-            } else {
-                copy_file(path.as_path(), local_to_target.as_path(), options)?;
             }
+        } else {
+            copy_or_link(path, local_to_target.as_path(), options, options.dereference, links)?;
+        }
+    }
+
+    if options.preserve_attributes != Attributes::none() {
+        for &(ref source, ref dest) in dirs.iter().rev() {
+            or_continue!(options.preserve_attributes.preserve(source, dest, true));
         }
     }
 
@@ -879,46 +1013,17 @@ impl OverwriteMode {
             OverwriteMode::Clobber(_) => Ok(()),
         }
     }
-}
-
 
-fn copy_attribute(source: &Path, dest: &Path, attribute: &Attribute) -> CopyResult<()> {
-    let context = &*format!("'{}' -> '{}'", source.display().to_string(), dest.display());
-    Ok(match *attribute {
-        #[cfg(unix)]
-        Attribute::Mode => {
-            let mode = fs::metadata(source).context(context)?.permissions().mode();
-            let mut dest_metadata = fs::metadata(source).context(context)?.permissions();
-            dest_metadata.set_mode(mode);
-        },
-        Attribute::Ownership => {
-            let metadata = fs::metadata(source).context(context)?;
-            fs::set_permissions(dest, metadata.permissions()).context(context)?;
-        },
-        Attribute::Timestamps => {
-            let metadata = fs::metadata(source)?;
-            filetime::set_file_times(Path::new(dest), FileTime::from_last_access_time(&metadata), FileTime::from_last_modification_time(&metadata))?;
-        },
-        Attribute::Context    => {},
-        Attribute::Links      => {},
-        Attribute::Xattr      => {
-            #[cfg(unix)]
-            {
-               let xattrs = xattr::list(source)?;
-               for attr in xattrs {
-                    if let Some(attr_value) = xattr::get(source, attr.clone())? {
-                        crash_if_err!(EXIT_ERR, xattr::set(dest, attr, &attr_value[..]));
-                    }
-               }
-            }
-            #[cfg(not(unix))]
-            {
-                return Err(format!("XAttrs are only supported on unix.").into());
-            }
-        },
-    })
+    fn removes_destination(&self) -> bool {
+        match *self {
+            OverwriteMode::Clobber(ClobberMode::RemoveDestination) |
+            OverwriteMode::Interactive(ClobberMode::RemoveDestination) => true,
+            _ => false,
+        }
+    }
 }
 
+
 #[cfg(not(windows))]
 fn symlink_file(source: &Path, dest: &Path, context: &str) -> CopyResult<()> {
     Ok(std::os::unix::fs::symlink(source, dest).context(context)?)
@@ -933,26 +1038,27 @@ fn context_for(src: &Path, dest: &Path) -> String {
     format!("'{}' -> '{}'", src.display(), dest.display())
 }
 
-/// Implements a relatively naive backup that is not as full featured
-/// as GNU cp.  No CONTROL version control method argument is taken
-/// for backups.
-/// TODO: Add version control methods
-fn backup_file(path: &Path, suffix: &str) -> CopyResult<PathBuf> {
-    let mut backup_path = path.to_path_buf().into_os_string();
-    backup_path.push(suffix);
-    fs::copy(path, &backup_path)?;
-    Ok(backup_path.into())
-}
-
-fn handle_existing_dest(source: &Path, dest: &Path, options: &Options) -> CopyResult<()> {
-    if paths_refer_to_same_file(source, dest)? {
+/// Move the existing `dest` out of the way as a backup, returning where it
+/// went, or `None` if no backup was asked for.
+fn handle_existing_dest(source: &Path, dest: &Path, options: &Options) -> CopyResult<Option<PathBuf>> {
+    // a destination that is removed first may be another name for the
+    // source, as long as removing it leaves the source there
+    let same = if options.overwrite.removes_destination() {
+        paths_name_same_entry(source, dest)?
+    } else {
+        paths_refer_to_same_file(source, dest)?
+    };
+    if same {
         return Err(format!("{}: same file", context_for(source, dest)).into());
     }
 
     options.overwrite.verify(dest)?;
 
-    if options.backup {
-        backup_file(dest, &options.backup_suffix)?;
+    if let Some(backup_path) = backup::backup_path(dest, options.backup, &options.backup_suffix) {
+        if let Err(e) = fs::rename(dest, &backup_path) {
+            return Err(format!("cannot backup '{}': {}", dest.display(), e).into());
+        }
+        return Ok(Some(backup_path));
     }
 
     match options.overwrite {
@@ -961,13 +1067,14 @@ fn handle_existing_dest(source: &Path, dest: &Path, options: &Options) -> CopyRe
                 fs::remove_file(dest)?;
             }
         },
-        OverwriteMode::Clobber(ClobberMode::RemoveDestination) => {
-            fs::remove_file(dest)?;
+        OverwriteMode::Clobber(ClobberMode::RemoveDestination) |
+        OverwriteMode::Interactive(ClobberMode::RemoveDestination) => {
+            fs::remove_file(dest).context(&*format!("cannot remove '{}'", dest.display()))?;
         },
         _ => (),
     };
 
-    Ok(())
+    Ok(None)
 }
 
 /// Copy the a file from `source` to `dest`. No path manipulation is
@@ -978,110 +1085,155 @@ fn handle_existing_dest(source: &Path, dest: &Path, options: &Options) -> CopyRe
 /// should be `Error:Skipped`
 ///
 /// The original permissions of `source` will be copied to `dest`
-/// after a successful copy.
-fn copy_file(source: &Path, dest: &Path, options: &Options) -> CopyResult<()> {
-    if dest.exists() {
-        handle_existing_dest(source, dest, options)?;
+/// after a successful copy.  Unless `dereference`, a symbolic link is
+/// copied as a link to the same place.
+fn copy_file(source: &Path, dest: &Path, options: &Options, dereference: bool) -> CopyResult<()> {
+    if options.update {
+        // an up to date destination isn't touched, let alone backed up,
+        // whether it would be copied or linked to
+        if dest.exists() && fs::metadata(source)?.modified()? <= fs::metadata(dest)?.modified()? {
+            return Ok(());
+        }
     }
 
-    if options.verbose {
-        println!("{}", context_for(source, dest));
-    }
+    // a dangling link is only in the way of a destination removed first
+    let dest_exists = if options.overwrite.removes_destination() {
+        fs::symlink_metadata(dest).is_ok()
+    } else {
+        dest.exists()
+    };
+    let backup_path = if dest_exists {
+        handle_existing_dest(source, dest, options)?
+    } else {
+        None
+    };
 
-    #[allow(unused)]
-    {
-        // TODO: implement --preserve flag
-        let mut preserve_context = false;
-        for attribute in &options.preserve_attributes {
-            if *attribute == Attribute::Context {
-                preserve_context = true;
-            }
+    if options.verbose {
+        match backup_path {
+            Some(path) => println!("{} (backup: '{}')", context_for(source, dest), path.display()),
+            None => println!("{}", context_for(source, dest)),
         }
     }
 
+    let is_link = !dereference && fs::symlink_metadata(source)?.file_type().is_symlink();
+    let is_special = options.recursive && !options.copy_contents && !is_link && is_special_file(source)?;
+
     match options.copy_mode {
         CopyMode::Link => {
             fs::hard_link(source, dest).context(&*context_for(source, dest))?;
         }
-        CopyMode::Copy => {
+        CopyMode::Copy | CopyMode::Update | CopyMode::AttrOnly if is_link => {
+            copy_link(source, dest)?;
+        }
+        CopyMode::Copy | CopyMode::Update if is_special => {
+            copy_special_file(source, dest)?;
+        }
+        CopyMode::Copy | CopyMode::Update => {
             copy_helper(source, dest, options)?;
         }
         CopyMode::SymLink => {
+            // a relative source would be taken from the link's directory
+            if source.is_relative() && !in_current_dir(dest) {
+                return Err(format!("{}: can make relative symbolic links only in current directory",
+                                   dest.display()).into());
+            }
             symlink_file(source, dest, &*context_for(source, dest))?;
         }
-        CopyMode::Sparse => return Err(Error::NotImplemented(OPT_SPARSE.to_string())),
-        CopyMode::Update => {
-            if dest.exists() {
-                let src_metadata = fs::metadata(source.clone())?;
-                let dest_metadata = fs::metadata(dest.clone())?;
-
-                let src_time = src_metadata.modified()?;
-                let dest_time = dest_metadata.modified()?;
-                if src_time <= dest_time {
-                    return Ok(());
-                } else {
-                    copy_helper(source, dest, options)?;
-                }
-            } else {
-                copy_helper(source, dest, options)?;
-            }
-        },
         CopyMode::AttrOnly => {
-            OpenOptions::new()
-                .write(true)
-                .truncate(false)
-                .create(true)
-                .open(dest)
-                .unwrap();
+            copy_attributes_only(source, dest)?;
         }
     };
 
-    for attribute in &options.preserve_attributes {
-        copy_attribute(source, dest, attribute)?;
+    if options.preserve_attributes != Attributes::none() {
+        options.preserve_attributes.preserve(source, dest, !is_link)?;
+    }
+
+    Ok(())
+}
+
+/// Make sure there is a file at `dest` for the attributes of `source` to be
+/// copied to, without copying any data: one that is already there is left
+/// as it is, and a new one is empty, with the permissions of `source`.
+fn copy_attributes_only(source: &Path, dest: &Path) -> CopyResult<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(fs::metadata(source)?.permissions().mode());
+    }
+    options.open(dest).context(&*context_for(source, dest))?;
+    Ok(())
+}
+
+/// Make `dest` a symbolic link to wherever the link `source` points,
+/// replacing whatever `dest` was.
+fn copy_link(source: &Path, dest: &Path) -> CopyResult<()> {
+    let link = fs::read_link(source).context(&*context_for(source, dest))?;
+    if fs::symlink_metadata(dest).is_ok() {
+        fs::remove_file(dest)?;
+    }
+    symlink_file(&link, dest, &*context_for(source, dest))
+}
+
+/// Whether `path` is a FIFO, socket or device, which a recursive copy
+/// makes anew instead of reading unless asked to with --copy-contents.
+#[cfg(unix)]
+fn is_special_file(path: &Path) -> io::Result<bool> {
+    let file_type = fs::metadata(path)?.file_type();
+    Ok(file_type.is_fifo() || file_type.is_socket() || file_type.is_char_device() || file_type.is_block_device())
+}
+
+#[cfg(not(unix))]
+fn is_special_file(_: &Path) -> io::Result<bool> {
+    Ok(false)
+}
+
+/// Make `dest` a special file of the same kind as `source`, and for a
+/// device, the same device.
+#[cfg(unix)]
+fn copy_special_file(source: &Path, dest: &Path) -> CopyResult<()> {
+    let metadata = fs::metadata(source)?;
+    if fs::symlink_metadata(dest).is_ok() {
+        fs::remove_file(dest)?;
     }
+    let path = CString::new(dest.as_os_str().as_bytes()).unwrap();
+    if unsafe { libc::mknod(path.as_ptr(), metadata.mode() as libc::mode_t, metadata.rdev() as libc::dev_t) } != 0 {
+        return Err(format!("cannot create special file '{}': {}", dest.display(), io::Error::last_os_error()).into());
+    }
+    Ok(())
+}
 
+#[cfg(not(unix))]
+fn copy_special_file(_: &Path, _: &Path) -> CopyResult<()> {
     Ok(())
 }
 
-///Copy the file from `source` to `dest` either using the normal `fs::copy` or the
-///`FICLONE` ioctl if --reflink is specified and the filesystem supports it.
+/// Copy the file from `source` to `dest`, cloning it with `FICLONE` when
+/// --reflink asks for it and the filesystem supports it, and otherwise
+/// falling back to the cheapest way of copying the data.
 fn copy_helper(source: &Path, dest: &Path, options: &Options) -> CopyResult<()> {
-    if options.reflink {
-        #[cfg(not(target_os = "linux"))]
-        return Err(format!("--reflink is only supported on linux").into());
-
-        #[cfg(target_os = "linux")]
-        {
-            let src_file = File::open(source).unwrap().into_raw_fd();
-            let dst_file = OpenOptions::new()
-                .write(true)
-                .truncate(false)
-                .create(true)
-                .open(dest)
-                .unwrap()
-                .into_raw_fd();
-            match options.reflink_mode {
-                ReflinkMode::Always => unsafe {
-                    let result = ficlone(dst_file, src_file as *const i32);
-                    if result != 0 {
-                        return Err(format!("failed to clone {:?} from {:?}: {}", source, dest, std::io::Error::last_os_error()).into());
-                    } else {
-                        return Ok(())
-                    }
-                },
-                ReflinkMode::Auto => unsafe {
-                    let result = ficlone(dst_file, src_file as *const i32);
-                    if result != 0 {
-                        fs::copy(source, dest).context(&*context_for(source, dest))?;
-                    }
-                },
-                ReflinkMode::Never => {}
+    let methods: &[CopyMethod] = match options.reflink_mode {
+        ReflinkMode::Always => &[CopyMethod::Clone],
+        ReflinkMode::Auto => copy::ALL_METHODS,
+        ReflinkMode::Never => &copy::ALL_METHODS[1..],
+    };
+    let copier = FileCopier::default().methods(methods).sparse(options.sparse_mode);
+
+    match copier.copy_path(source, dest) {
+        Ok(_) => Ok(()),
+        Err(ref e) if options.reflink_mode == ReflinkMode::Always && e.kind() == io::ErrorKind::Other => {
+            Err(format!("failed to clone '{}' from '{}': Operation not supported", dest.display(), source.display()).into())
+        }
+        Err(e) => {
+            // one that failed part of the way through says which file it was on
+            if copy::PartialCopy::of(&e).is_some() {
+                Err(Error::IoErr(e))
+            } else {
+                Err(Error::IoErrContext(e, context_for(source, dest)))
             }
         }
-    } else {
-        fs::copy(source, dest).context(&*context_for(source, dest))?;
     }
-    Ok(())
 }
 
 /// Generate an error message if `target` is not the correct `target_type`
@@ -1115,6 +1267,29 @@ pub fn localize_to_target(root: &Path, source: &Path, target: &Path) -> CopyResu
 }
 
 
+/// Whether `p1` and `p2` are the same name in the same directory, so that
+/// removing one removes the other, whatever the names are links to.
+pub fn paths_name_same_entry(p1: &Path, p2: &Path) -> io::Result<bool> {
+    let entry = |path: &Path| -> io::Result<PathBuf> {
+        let parent = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        Ok(canonicalize(parent, CanonicalizeMode::Normal)?.join(path.file_name().unwrap_or_default()))
+    };
+    Ok(entry(p1)? == entry(p2)?)
+}
+
+/// Whether `path` names something in the current directory.
+fn in_current_dir(path: &Path) -> bool {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => {
+            paths_refer_to_same_file(parent, Path::new(".")).unwrap_or(false)
+        }
+        _ => true,
+    }
+}
+
 pub fn paths_refer_to_same_file(p1: &Path, p2: &Path) -> io::Result<bool> {
     // We have to take symlinks and relative paths into account.
     let pathbuf1 = try!(canonicalize(p1, CanonicalizeMode::Normal));
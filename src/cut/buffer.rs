@@ -135,8 +135,13 @@ impl<R: Read> self::Bytes::Select for ByteReader<R> {
                 },
             };
 
+            // the newline is left for the caller to write as it sees fit
+            let written = match res {
+                SRes::Newl => consume_val - 1,
+                _ => consume_val,
+            };
             match out {
-                Some(out) => crash_if_err!(1, out.write_all(&buffer[0..consume_val])),
+                Some(out) => crash_if_err!(1, out.write_all(&buffer[0..written])),
                 None => (),
             }
             (res, consume_val)
@@ -12,11 +12,14 @@
 #[macro_use]
 extern crate uucore;
 
+use std::cmp;
 use std::fs::File;
 use std::io::{stdout, stdin, BufRead, BufReader, Read, Stdout, Write};
 use std::path::Path;
 
 use ranges::Range;
+use uucore::graphemes::{self, CharUnit};
+use uucore::lines::{self, LineEnding};
 use searcher::Searcher;
 
 mod buffer;
@@ -31,7 +34,20 @@ static LONG_HELP: &'static str = "
 
  Specifying a mode
 
-    Use --bytes (-b) or --characters (-c) to specify byte mode
+    Use --bytes (-b) to specify byte mode, or --characters (-c) for
+    character mode, where a character may be several bytes of UTF-8. Bytes
+    that are not UTF-8 count as a character each
+
+    With -n, byte mode does not split characters either: a range of bytes
+    takes the characters that end within it, starting with the one its
+    first byte is in
+
+    echo 'aéb' | cut -n -b 1-2
+    will result in 'a', as the 'é' goes on to the third byte
+
+    With --graphemes, --characters (-c) and -b -n count each grapheme
+    cluster, such as a letter and the accents on it or a sequence of joined
+    emoji, as one character, so that none is cut in two
 
     Use --fields (-f) to specify field mode, where each line is broken into
     fields identified by a delimiter character. For example for a typical CSV
@@ -92,7 +108,18 @@ static LONG_HELP: &'static str = "
         If the --output-delimiter option is provided, the argument used for
         it will replace the delimiter character in each line printed. This is
         useful for transforming tabular data - e.g. to convert a CSV to a
-        TSV (tab-separated file)
+        TSV (tab-separated file). It may be longer than one character, and
+        if it is empty, a \\0 (NUL) is printed.
+
+        cut -d , -f 1,3 --output-delimiter ' | ' data.csv
+        will display the 1st and 3rd fields of data.csv with ' | ' between them
+
+        In byte or character mode, the output delimiter is printed between
+        the ranges selected of each line. Ranges that overlap are one range,
+        but ranges that only touch are not
+
+        echo abcdef | cut -b 1-2,3-4,4-6 --output-delimiter :
+        will result in 'ab:cdef'
 
  Line endings
 
@@ -103,11 +130,18 @@ static LONG_HELP: &'static str = "
 
     echo 'ab\\0cd' | cut -z -c 1
     will result in 'a\\0c\\0'
+
+    The lines printed can end differently from those read: with
+    --output-terminator=newline, 'cut -z' prints lines ending in newlines.
+    The other endings are crlf and nul.
 ";
 
 struct Options {
     out_delim: Option<String>,
     zero_terminated: bool,
+    out_ending: LineEnding,
+    graphemes: bool,
+    whole_chars: bool,
 }
 
 struct FieldOptions {
@@ -115,6 +149,7 @@ struct FieldOptions {
     out_delimeter: Option<String>,
     only_delimited: bool,
     zero_terminated: bool,
+    out_ending: LineEnding,
 }
 
 enum Mode {
@@ -138,7 +173,16 @@ fn cut_bytes<R: Read>(reader: R, ranges: &[Range], opts: &Options) -> i32 {
     let newline_char =
         if opts.zero_terminated { b'\0' } else { b'\n' };
     let mut buf_read = buffer::ByteReader::new(reader, newline_char);
-    let mut out = stdout();
+    let mut out = lines::Writer::new(stdout(), opts.out_ending);
+
+    // nothing is selected of any line, as with --complement -b 1-, and the
+    // loop below would never get to the end of the input
+    if ranges.is_empty() {
+        while buf_read.consume_line() > 0 {
+            crash_if_err!(1, out.end_line());
+        }
+        return 0;
+    }
 
     'newline: loop {
         let mut cur_pos = 1;
@@ -146,11 +190,10 @@ fn cut_bytes<R: Read>(reader: R, ranges: &[Range], opts: &Options) -> i32 {
 
         for &Range { low, high } in ranges.iter() {
             // skip upto low
-            let orig_pos = cur_pos;
             loop {
-                match buf_read.select(low - cur_pos, None::<&mut Stdout>) {
+                match buf_read.select(low - cur_pos, None::<&mut lines::Writer<Stdout>>) {
                     NewlineFound => {
-                        crash_if_err!(1, out.write_all(&[newline_char]));
+                        crash_if_err!(1, out.end_line());
                         continue 'newline
                     }
                     Complete(len) => {
@@ -159,8 +202,9 @@ fn cut_bytes<R: Read>(reader: R, ranges: &[Range], opts: &Options) -> i32 {
                     }
                     Partial(len) => cur_pos += len,
                     EndOfFile => {
-                        if orig_pos != cur_pos {
-                            crash_if_err!(1, out.write_all(&[newline_char]));
+                        // a last line without an ending is given one
+                        if cur_pos > 1 {
+                            crash_if_err!(1, out.end_line());
                         }
 
                         break 'newline
@@ -181,15 +225,24 @@ fn cut_bytes<R: Read>(reader: R, ranges: &[Range], opts: &Options) -> i32 {
             // write out from low to high
             loop {
                 match buf_read.select(high - cur_pos + 1, Some(&mut out)) {
-                    NewlineFound => continue 'newline,
-                    Partial(len) => cur_pos += len,
+                    NewlineFound => {
+                        crash_if_err!(1, out.end_line());
+                        continue 'newline
+                    }
+                    Partial(len) => {
+                        cur_pos += len;
+                        // the input ran out just at the end of the range
+                        if cur_pos > high {
+                            break
+                        }
+                    }
                     Complete(_) => {
                         cur_pos = high + 1;
                         break
                     }
                     EndOfFile => {
-                        if cur_pos != low || low == high {
-                            crash_if_err!(1, out.write_all(&[newline_char]));
+                        if cur_pos > 1 {
+                            crash_if_err!(1, out.end_line());
                         }
 
                         break 'newline
@@ -199,18 +252,23 @@ fn cut_bytes<R: Read>(reader: R, ranges: &[Range], opts: &Options) -> i32 {
         }
 
         buf_read.consume_line();
-        crash_if_err!(1, out.write_all(&[newline_char]));
+        crash_if_err!(1, out.end_line());
     }
 
     0
 }
 
-fn cut_fields_delimiter<R: Read>(reader: R, ranges: &[Range], delim: &str, only_delimited: bool, newline_char: u8, out_delim: &str) -> i32 {
+// characters are cut from whole lines, as unlike bytes their lengths are only
+// known once they are read.  With -b -n the ranges are still of bytes, but
+// only whole characters are printed of them.
+fn cut_characters<R: Read>(reader: R, ranges: &[Range], opts: &Options, unit: CharUnit) -> i32 {
+    let newline_char =
+        if opts.zero_terminated { b'\0' } else { b'\n' };
     let mut buf_in = BufReader::new(reader);
-    let mut out = stdout();
+    let mut out = lines::Writer::new(stdout(), opts.out_ending);
     let mut buffer = Vec::new();
 
-    'newline: loop {
+    loop {
         buffer.clear();
         match buf_in.read_until(newline_char, &mut buffer) {
             Ok(n) if n == 0 => break,
@@ -222,7 +280,77 @@ fn cut_fields_delimiter<R: Read>(reader: R, ranges: &[Range], delim: &str, only_
             _ => (),
         }
 
-        let line = &buffer[..];
+        // the ending is left for the writer to put back
+        let line = if buffer.last() == Some(&newline_char) {
+            &buffer[..buffer.len() - 1]
+        } else {
+            &buffer[..]
+        };
+        let chars: Vec<&[u8]> = graphemes::byte_chars(line, unit).collect();
+        // the last byte of each character
+        let ends: Vec<usize> = if opts.whole_chars {
+            chars.iter().scan(0, |end, ch| { *end += ch.len(); Some(*end) }).collect()
+        } else {
+            Vec::new()
+        };
+
+        let mut printed = false;
+        for &Range { low, high } in ranges {
+            // the characters from `first` up to `last` are selected
+            let (first, last) = if opts.whole_chars {
+                (ends.binary_search(&low).unwrap_or_else(|i| i),
+                 ends.binary_search(&high).map(|i| i + 1).unwrap_or_else(|i| i))
+            } else {
+                (low - 1, cmp::min(high, chars.len()))
+            };
+            if first >= chars.len() {
+                break
+            }
+            // a range within one character selects none
+            if first >= last {
+                continue
+            }
+
+            match opts.out_delim {
+                Some(ref delim) if printed => crash_if_err!(1, out.write_all(delim.as_bytes())),
+                _ => (),
+            }
+            printed = true;
+
+            for ch in &chars[first..last] {
+                crash_if_err!(1, out.write_all(ch));
+            }
+        }
+
+        crash_if_err!(1, out.end_line());
+    }
+
+    0
+}
+
+fn cut_fields_delimiter<R: Read>(reader: R, ranges: &[Range], delim: &str, only_delimited: bool, newline_char: u8, out_delim: &str, out_ending: LineEnding) -> i32 {
+    let mut buf_in = BufReader::new(reader);
+    let mut out = lines::Writer::new(stdout(), out_ending);
+    let mut buffer = Vec::new();
+
+    loop {
+        buffer.clear();
+        match buf_in.read_until(newline_char, &mut buffer) {
+            Ok(n) if n == 0 => break,
+            Err(e) => {
+                if buffer.is_empty() {
+                    crash!(1, "read error: {}", e);
+                }
+            },
+            _ => (),
+        }
+
+        // the ending is left for the writer to put back
+        let line = if buffer.last() == Some(&newline_char) {
+            &buffer[..buffer.len() - 1]
+        } else {
+            &buffer[..]
+        };
         let mut fields_pos = 1;
         let mut low_idx = 0;
         let mut delim_search = Searcher::new(line, delim.as_bytes()).peekable();
@@ -230,16 +358,13 @@ fn cut_fields_delimiter<R: Read>(reader: R, ranges: &[Range], delim: &str, only_
 
         if delim_search.peek().is_none() {
             if ! only_delimited {
-                crash_if_err!(1, out.write_all(line));
-                if line[line.len() - 1] != newline_char {
-                    crash_if_err!(1, out.write_all(&[newline_char]));
-                }
+                crash_if_err!(1, out.write_line(line));
             }
 
             continue
         }
 
-        for &Range { low, high } in ranges.iter() {
+        'ranges: for &Range { low, high } in ranges.iter() {
             if low - fields_pos > 0 {
                 low_idx = match delim_search.nth(low - fields_pos - 1) {
                     Some((_, beyond_delim)) => beyond_delim,
@@ -266,18 +391,15 @@ fn cut_fields_delimiter<R: Read>(reader: R, ranges: &[Range], delim: &str, only_
                     None => {
                         let segment = &line[low_idx..];
 
+                        // that was the last field, and no range has more
                         crash_if_err!(1, out.write_all(segment));
-
-                        if line[line.len() - 1] == newline_char {
-                            continue 'newline
-                        }
-                        break
+                        break 'ranges
                     }
                 }
             }
         }
 
-        crash_if_err!(1, out.write_all(&[newline_char]));
+        crash_if_err!(1, out.end_line());
     }
 
     0
@@ -289,16 +411,16 @@ fn cut_fields<R: Read>(reader: R, ranges: &[Range], opts: &FieldOptions) -> i32
     match opts.out_delimeter {
         Some(ref o_delim) => {
             return cut_fields_delimiter(reader, ranges, &opts.delimiter,
-                                            opts.only_delimited, newline_char, o_delim)
+                                            opts.only_delimited, newline_char, o_delim, opts.out_ending)
         }
         None => ()
     }
 
     let mut buf_in = BufReader::new(reader);
-    let mut out = stdout();
+    let mut out = lines::Writer::new(stdout(), opts.out_ending);
     let mut buffer = Vec::new();
 
-    'newline: loop {
+    loop {
         buffer.clear();
         match buf_in.read_until(newline_char, &mut buffer) {
             Ok(n) if n == 0 => break,
@@ -310,7 +432,12 @@ fn cut_fields<R: Read>(reader: R, ranges: &[Range], opts: &FieldOptions) -> i32
             _ => (),
         }
 
-        let line = &buffer[..];
+        // the ending is left for the writer to put back
+        let line = if buffer.last() == Some(&newline_char) {
+            &buffer[..buffer.len() - 1]
+        } else {
+            &buffer[..]
+        };
         let mut fields_pos = 1;
         let mut low_idx = 0;
         let mut delim_search = Searcher::new(line, opts.delimiter.as_bytes()).peekable();
@@ -318,10 +445,7 @@ fn cut_fields<R: Read>(reader: R, ranges: &[Range], opts: &FieldOptions) -> i32
 
         if delim_search.peek().is_none() {
             if ! opts.only_delimited {
-                crash_if_err!(1, out.write_all(line));
-                if line[line.len() - 1] != newline_char {
-                    crash_if_err!(1, out.write_all(&[newline_char]));
-                }
+                crash_if_err!(1, out.write_line(line));
             }
 
             continue
@@ -355,21 +479,21 @@ fn cut_fields<R: Read>(reader: R, ranges: &[Range], opts: &FieldOptions) -> i32
                     let segment = &line[low_idx..line.len()];
 
                     crash_if_err!(1, out.write_all(segment));
-
-                    if line[line.len() - 1] == newline_char {
-                        continue 'newline
-                    }
                     break
                 }
             }
         }
 
-        crash_if_err!(1, out.write_all(&[newline_char]));
+        crash_if_err!(1, out.end_line());
     }
 
     0
 }
 
+fn char_unit(opts: &Options) -> CharUnit {
+    if opts.graphemes { CharUnit::Grapheme } else { CharUnit::CodePoint }
+}
+
 fn cut_files(mut filenames: Vec<String>, mode: Mode) -> i32 {
     let mut stdin_read = false;
     let mut exit_code = 0;
@@ -381,8 +505,9 @@ fn cut_files(mut filenames: Vec<String>, mode: Mode) -> i32 {
             if stdin_read { continue }
 
             exit_code |= match mode {
-                Mode::Bytes(ref ranges, ref opts) => cut_bytes(stdin(), ranges, opts),
-                Mode::Characters(ref ranges, ref opts) => cut_bytes(stdin(), ranges, opts),
+                Mode::Bytes(ref ranges, ref opts) if !opts.whole_chars => cut_bytes(stdin(), ranges, opts),
+                Mode::Bytes(ref ranges, ref opts) |
+                Mode::Characters(ref ranges, ref opts) => cut_characters(stdin(), ranges, opts, char_unit(opts)),
                 Mode::Fields(ref ranges, ref opts) => cut_fields(stdin(), ranges, opts),
             };
 
@@ -404,8 +529,9 @@ fn cut_files(mut filenames: Vec<String>, mode: Mode) -> i32 {
             };
 
             exit_code |= match mode {
-                Mode::Bytes(ref ranges, ref opts) => cut_bytes(file, ranges, opts),
-                Mode::Characters(ref ranges, ref opts) => cut_bytes(file, ranges, opts),
+                Mode::Bytes(ref ranges, ref opts) if !opts.whole_chars => cut_bytes(file, ranges, opts),
+                Mode::Bytes(ref ranges, ref opts) |
+                Mode::Characters(ref ranges, ref opts) => cut_characters(file, ranges, opts, char_unit(opts)),
                 Mode::Fields(ref ranges, ref opts) => cut_fields(file, ranges, opts),
             };
         }
@@ -420,41 +546,46 @@ pub fn uumain(args: Vec<String>) -> i32 {
         .optopt("c", "characters", "alias for character mode", "sequence")
         .optopt("d", "delimiter", "specify the delimiter character that separates fields in the input source. Defaults to Tab.", "delimiter")
         .optopt("f", "fields", "filter field columns from the input source", "sequence")
-        .optflag("n", "", "legacy option - has no effect.")
+        .optflag("n", "", "with -b, do not split characters: print those that end within each range")
+        .optflag("", "graphemes", "with -c or -b -n, count a grapheme cluster, such as a letter and the accents on it, as one character")
         .optflag("", "complement", "invert the filter - instead of displaying only the filtered columns, display all but those columns")
         .optflag("s", "only-delimited", "in field mode, only print lines which contain the delimiter")
-        .optflag("z", "zero-terminated", "instead of filtering columns based on line, filter columns based on \\0 (NULL character)")
-        .optopt("", "output-delimiter", "in field mode, replace the delimiter in output lines with this option's argument", "new delimiter")
+        .optflag("z", "zero-terminated", "lines read and printed end in \\0 (NUL) rather than newline, as with sort -z, find -print0 and xargs -0")
+        .optopt("", "output-delimiter", "print STRING between the fields, or the byte or character ranges, printed of each line; an empty STRING is a NUL", "STRING")
+        .optopt("", "output-terminator", "end output lines with NAME (newline, crlf or nul) rather than as input lines end", "NAME")
         .parse(args);
     let complement = matches.opt_present("complement");
+    let zero_terminated = matches.opt_present("zero-terminated");
+    let graphemes = matches.opt_present("graphemes");
+    // an empty output delimiter is a NUL, as it is to GNU cut
+    let out_delim = matches.opt_str("output-delimiter").map(|delim| if delim.is_empty() { "\0".to_owned() } else { delim });
+    let out_ending = match matches.opt_str("output-terminator") {
+        Some(name) => match LineEnding::from_name(&name) {
+            Some(ending) => ending,
+            None => {
+                show_error!("invalid output terminator: '{}'", name);
+                return 1;
+            }
+        },
+        None if zero_terminated => LineEnding::Nul,
+        None => LineEnding::Newline,
+    };
 
     let mode_parse = match (matches.opt_str("bytes"),
                             matches.opt_str("characters"),
                             matches.opt_str("fields")) {
         (Some(byte_ranges), None, None) => {
             list_to_ranges(&byte_ranges[..], complement)
-                .map(|ranges| Mode::Bytes(ranges, Options { out_delim: matches.opt_str("output-delimiter"), zero_terminated : matches.opt_present("zero-terminated") }))
+                .map(|ranges| Mode::Bytes(ranges, Options { out_delim: out_delim.clone(), zero_terminated : zero_terminated, out_ending: out_ending, graphemes: graphemes, whole_chars: matches.opt_present("n") }))
         }
         (None, Some(char_ranges), None) => {
             list_to_ranges(&char_ranges[..], complement)
-                .map(|ranges| Mode::Characters(ranges, Options { out_delim: matches.opt_str("output-delimiter"), zero_terminated : matches.opt_present("zero-terminated") }))
+                .map(|ranges| Mode::Characters(ranges, Options { out_delim: out_delim.clone(), zero_terminated : zero_terminated, out_ending: out_ending, graphemes: graphemes, whole_chars: false }))
         }
         (None, None, Some(field_ranges)) => {
             list_to_ranges(&field_ranges[..], complement).and_then(|ranges|
                 {
-                    let out_delim = match matches.opt_str("output-delimiter") {
-                        Some(s) => {
-                            if s.is_empty() {
-                                Some("\0".to_owned())
-                            } else {
-                                Some(s)
-                            }
-                        },
-                        None => None,
-                    };
-
                     let only_delimited = matches.opt_present("only-delimited");
-                    let zero_terminated = matches.opt_present("zero-terminated");
 
                     match matches.opt_str("delimiter") {
                         Some(delim) => {
@@ -470,18 +601,20 @@ pub fn uumain(args: Vec<String>) -> i32 {
                                 Ok(Mode::Fields(ranges,
                                           FieldOptions {
                                               delimiter: delim,
-                                              out_delimeter: out_delim,
+                                              out_delimeter: out_delim.clone(),
                                               only_delimited: only_delimited,
-                                              zero_terminated: zero_terminated
+                                              zero_terminated: zero_terminated,
+                                              out_ending: out_ending
                                           }))
                             }
                         }
                         None => Ok(Mode::Fields(ranges,
                                           FieldOptions {
                                               delimiter: "\t".to_owned(),
-                                              out_delimeter: out_delim,
+                                              out_delimeter: out_delim.clone(),
                                               only_delimited: only_delimited,
-                                              zero_terminated: zero_terminated
+                                              zero_terminated: zero_terminated,
+                                              out_ending: out_ending
                                           }))
                     }
                 }
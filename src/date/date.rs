@@ -14,11 +14,13 @@ extern crate chrono;
 extern crate clap;
 extern crate uucore;
 
-use chrono::{DateTime, FixedOffset, Offset, Local};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, TimeZone as ChronoTimeZone};
 use chrono::offset::Utc;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::path::PathBuf;
+use uucore::clock::{Clock, SystemClock};
+use uucore::tz::TimeZone;
 
 // Options
 const DATE: &'static str = "date";
@@ -120,25 +122,9 @@ pub fn uumain(args: Vec<String>) -> i32 {
         // Declare a file here because it needs to outlive the `dates` iterator.
         let file: File;
 
-        // Get the current time, either in the local time zone or UTC.
-        let now: DateTime<FixedOffset> = match settings.utc {
-            true => {
-                let now = Utc::now();
-                now.with_timezone(&now.offset().fix())
-            }
-            false => {
-                let now = Local::now();
-                now.with_timezone(now.offset())
-            }
-        };
-
-        /// Parse a `String` into a `DateTime`.
-        /// If it fails, return a tuple of the `String` along with its `ParseError`.
-        fn parse_date(s: String)
-                      -> Result<DateTime<FixedOffset>, (String, chrono::format::ParseError)> {
-            // TODO: The GNU date command can parse a wide variety of inputs.
-            s.parse().map_err(|e| (s, e))
-        }
+        let zone = if settings.utc { TimeZone::utc() } else { TimeZone::local() };
+        let now = now(&SystemClock, &zone);
+        let parse_date = |s: String| parse_date(s, &zone);
 
         // Iterate over all dates - whether it's a single date or a file.
         let dates: Box<Iterator<Item = _>> = match settings.date_source {
@@ -165,10 +151,10 @@ pub fn uumain(args: Vec<String>) -> i32 {
         for date in dates {
             match date {
                 Ok(date) => {
-                    let formatted = date.format(format_string);
-                    println!("{}", formatted);
+                    let format = with_zone_name(format_string, &zone.find(date.timestamp()).abbreviation);
+                    println!("{}", date.format(&format));
                 }
-                Err((input, _err)) => {
+                Err(input) => {
                     println!("date: invalid date '{}'", input);
                 }
             }
@@ -179,6 +165,65 @@ pub fn uumain(args: Vec<String>) -> i32 {
 }
 
 
+/// The time on `clock`, in `zone`.
+fn now<C: Clock>(clock: &C, zone: &TimeZone) -> DateTime<FixedOffset> {
+    let now: DateTime<Utc> = DateTime::from(clock.now());
+    in_zone(now.timestamp(), now.timestamp_subsec_nanos(), zone)
+}
+
+/// The time `secs` and `nanos` since the epoch, in `zone`.
+fn in_zone(secs: i64, nanos: u32, zone: &TimeZone) -> DateTime<FixedOffset> {
+    let offset = FixedOffset::east(zone.find(secs).offset);
+    offset.timestamp(secs, nanos)
+}
+
+/// Parse a `String` into a `DateTime` in `zone`: `@SECONDS` since the epoch,
+/// an RFC 3339 time, or a date with or without a time of day taken as local
+/// time in `zone`.  If it fails, return the `String`.
+fn parse_date(s: String, zone: &TimeZone) -> Result<DateTime<FixedOffset>, String> {
+    // TODO: The GNU date command can parse a wide variety of inputs.
+    if s.starts_with('@') {
+        return match s[1..].parse() {
+            Ok(secs) => Ok(in_zone(secs, 0, zone)),
+            Err(_) => Err(s),
+        };
+    }
+    if let Ok(date) = s.parse::<DateTime<FixedOffset>>() {
+        return Ok(in_zone(date.timestamp(), date.timestamp_subsec_nanos(), zone));
+    }
+    let local = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"].iter()
+        .filter_map(|format| NaiveDateTime::parse_from_str(&s, format).ok())
+        .next()
+        .or_else(|| NaiveDate::parse_from_str(&s, "%Y-%m-%d").ok().map(|date| date.and_hms(0, 0, 0)));
+    // a local time that clocks skip over is no time at all
+    match local.and_then(|local| zone.to_utc(local.timestamp()).map(|secs| (secs, local.timestamp_subsec_nanos()))) {
+        Some((secs, nanos)) => Ok(in_zone(secs, nanos, zone)),
+        None => Err(s),
+    }
+}
+
+/// `format` with each `%Z` in it, which chrono would write as an offset,
+/// replaced by the zone's `abbreviation`.
+fn with_zone_name(format: &str, abbreviation: &str) -> String {
+    let mut result = String::with_capacity(format.len());
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Z') => result.push_str(&abbreviation.replace('%', "%%")),
+            Some(c) => {
+                result.push('%');
+                result.push(c);
+            }
+            None => result.push('%'),
+        }
+    }
+    result
+}
+
 /// Handle command line arguments.
 fn parse_cli(args: Vec<String>) -> Settings {
     let matches = clap_app!(
@@ -190,13 +235,13 @@ fn parse_cli(args: Vec<String>) -> Settings {
               "like --date; once for each line of DATEFILE"))
 
             (@group format =>
-             (@arg iso_8601: -I --("iso-8601") <FMT>
+             (@arg iso_8601: -I --("iso-8601") [FMT]
               possible_value[date hours minutes seconds ns]
               #{0, 1}
               ISO_8601_HELP_STRING)
              (@arg rfc_2822: -R --("rfc-2822")
               RFC_2822_HELP_STRING)
-             (@arg rfc_3339: --("rfc-3339") <FMT>
+             (@arg rfc_3339: --("rfc-3339") [FMT]
               possible_value[date seconds ns]
               RFC_3339_HELP_STRING)
              (@arg custom_format: +takes_value {
@@ -281,3 +326,34 @@ fn make_format_string(settings: &Settings) -> &str {
         Format::Default => "%c",
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uucore::clock::FakeClock;
+
+    #[test]
+    fn test_now() {
+        let clock = FakeClock::at(1_000_000_000);
+        assert_eq!(now(&clock, &TimeZone::utc()).to_rfc3339(), "2001-09-09T01:46:40+00:00");
+        assert_eq!(now(&clock, &TimeZone::named("JST-9")).to_rfc3339(), "2001-09-09T10:46:40+09:00");
+        assert_eq!(now(&clock, &TimeZone::local()).timestamp(), 1_000_000_000);
+    }
+
+    #[test]
+    fn test_parse_date() {
+        let zone = TimeZone::named("EST5EDT,M3.2.0,M11.1.0");
+        let parse = |s: &str| parse_date(s.to_owned(), &zone).map(|date| date.to_rfc3339());
+        assert_eq!(parse("@0"), Ok("1969-12-31T19:00:00-05:00".to_owned()));
+        assert_eq!(parse("2021-07-01T12:00:00+00:00"), Ok("2021-07-01T08:00:00-04:00".to_owned()));
+        assert_eq!(parse("2021-03-14 01:59:59"), Ok("2021-03-14T01:59:59-05:00".to_owned()));
+        assert_eq!(parse("2021-03-14 03:00"), Ok("2021-03-14T03:00:00-04:00".to_owned()));
+        assert_eq!(parse("2021-03-14 02:30"), Err("2021-03-14 02:30".to_owned()));
+        assert_eq!(parse("2021-11-07"), Ok("2021-11-07T00:00:00-04:00".to_owned()));
+    }
+
+    #[test]
+    fn test_with_zone_name() {
+        assert_eq!(with_zone_name("%H %Z %%Z %", "EST"), "%H EST %%Z %");
+    }
+}
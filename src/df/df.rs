@@ -0,0 +1,327 @@
+#![crate_name = "uu_df"]
+
+/*
+ * This file is part of the uutils coreutils package.
+ *
+ * For the full copyright and license information, please view the LICENSE
+ * file that was distributed with this source code.
+ */
+
+#[macro_use]
+extern crate uucore;
+
+use std::ffi::CString;
+use std::fs;
+use std::io;
+use std::mem;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use uucore::libc;
+use uucore::mounts::{self, Mount, MountFilter};
+use uucore::quoting::quote;
+use uucore::size::{self, BlockSize};
+
+const SUMMARY: &'static str = "Show information about the file system on which each FILE resides,
+ or all file systems by default.";
+const LONG_HELP: &'static str = "
+ Display  values  are  in  units  of  the  first  available  SIZE from
+ --block-size,  and the DF_BLOCK_SIZE, BLOCK_SIZE and BLOCKSIZE environ‐
+ ment variables.  Otherwise, units default to  1024  bytes  (or  512  if
+ POSIXLY_CORRECT is set).
+
+ SIZE  is  an  integer and optional unit (example: 10M is 10*1024*1024).
+ Units are K, M, G, T, P, E, Z, Y (powers of 1024) or KB, MB, ...  (pow‐
+ ers of 1000).
+";
+
+static UNITS: &'static str = "KMGTPEZY";
+
+struct Options {
+    block_size: BlockSize,
+    inodes: bool,
+    portable: bool,
+    print_type: bool,
+}
+
+/// What a file system holds, in bytes and in inodes.
+#[derive(Clone, Copy, Default)]
+struct Usage {
+    size: u64,
+    used: u64,
+    avail: u64,
+    inodes: u64,
+    iused: u64,
+    ifree: u64,
+}
+
+impl Usage {
+    fn of(path: &Path) -> io::Result<Usage> {
+        let path = try!(CString::new(path.as_os_str().as_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e)));
+        let mut st: libc::statvfs = unsafe { mem::zeroed() };
+        if unsafe { libc::statvfs(path.as_ptr(), &mut st) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let block = if st.f_frsize != 0 { st.f_frsize as u64 } else { st.f_bsize as u64 };
+        let (blocks, free, files, ffree) = (st.f_blocks as u64, st.f_bfree as u64, st.f_files as u64, st.f_ffree as u64);
+        Ok(Usage {
+            size: blocks * block,
+            used: blocks.saturating_sub(free) * block,
+            avail: st.f_bavail as u64 * block,
+            inodes: files,
+            iused: files.saturating_sub(ffree),
+            ifree: ffree,
+        })
+    }
+
+    fn add(&mut self, other: &Usage) {
+        self.size += other.size;
+        self.used += other.used;
+        self.avail += other.avail;
+        self.inodes += other.inodes;
+        self.iused += other.iused;
+        self.ifree += other.ifree;
+    }
+}
+
+/// How much of `used` and `avail` together is used, rounded up, as GNU
+/// does, or `-` when there is nothing at all.
+fn percent(used: u64, avail: u64) -> String {
+    let total = used + avail;
+    if total == 0 {
+        return "-".to_owned();
+    }
+    format!("{}%", (used as f64 * 100.0 / total as f64).ceil())
+}
+
+// The heading of the size column, such as `1K-blocks` or `Size`
+fn size_header(options: &Options) -> String {
+    match options.block_size {
+        BlockSize::Human(_) => "Size".to_owned(),
+        BlockSize::Units(size, _) if options.portable => format!("{}-blocks", size),
+        BlockSize::Units(_, ref suffix) if !suffix.is_empty() => format!("1{}-blocks", suffix),
+        BlockSize::Units(size, _) => format!("{}-blocks", block_size_name(size)),
+    }
+}
+
+// `size` in the largest unit it is a whole number of, such as `2K` or `1MB`
+fn block_size_name(size: u64) -> String {
+    for &(base, suffix) in &[(1024, ""), (1000, "B")] {
+        let (mut number, mut power) = (size, 0);
+        while number % base == 0 && power < UNITS.len() {
+            number /= base;
+            power += 1;
+        }
+        if power > 0 {
+            let unit = match &UNITS[power - 1..power] {
+                "K" if base == 1000 => "k",
+                unit => unit,
+            };
+            return format!("{}{}{}", number, unit, suffix);
+        }
+    }
+    format!("{}B", size)
+}
+
+fn header(options: &Options) -> Vec<String> {
+    let mut row = vec!["Filesystem".to_owned()];
+    if options.print_type {
+        row.push("Type".to_owned());
+    }
+    if options.inodes {
+        row.extend(["Inodes", "IUsed", "IFree", "IUse%"].iter().map(|s| s.to_string()));
+    } else {
+        let human = match options.block_size {
+            BlockSize::Human(_) => true,
+            _ => false,
+        };
+        row.push(size_header(options));
+        row.push("Used".to_owned());
+        row.push(if human { "Avail" } else { "Available" }.to_owned());
+        row.push(if options.portable { "Capacity" } else { "Use%" }.to_owned());
+    }
+    row.push("Mounted on".to_owned());
+    row
+}
+
+fn row(source: &str, fs_type: &str, usage: &Usage, target: &str, options: &Options) -> Vec<String> {
+    let mut row = vec![source.to_owned()];
+    if options.print_type {
+        row.push(fs_type.to_owned());
+    }
+    if options.inodes {
+        let count = |n: u64| match options.block_size {
+            BlockSize::Human(base) => size::human(n, base),
+            _ => n.to_string(),
+        };
+        row.push(count(usage.inodes));
+        row.push(count(usage.iused));
+        row.push(count(usage.ifree));
+        row.push(percent(usage.iused, usage.ifree));
+    } else {
+        row.push(options.block_size.format(usage.size));
+        row.push(options.block_size.format(usage.used));
+        row.push(options.block_size.format(usage.avail));
+        row.push(percent(usage.used, usage.avail));
+    }
+    row.push(target.to_owned());
+    row
+}
+
+// Line the columns up as GNU does: the names to the left and the numbers to
+// the right, each column no narrower than GNU's least width for it.
+fn print_table(rows: &[Vec<String>], options: &Options) {
+    let columns = rows[0].len();
+    let mut widths: Vec<usize> = (0..columns).map(|i| match i {
+        0 => 14,
+        1 if options.print_type => 4,
+        i if i == columns - 2 => 4,
+        _ => 5,
+    }).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let left = if options.print_type { 2 } else { 1 };
+    for row in rows {
+        let mut line = String::new();
+        for (i, cell) in row.iter().enumerate() {
+            if i == columns - 1 {
+                line.push_str(cell);
+            } else if i < left {
+                line.push_str(&format!("{:<1$} ", cell, widths[i]));
+            } else {
+                line.push_str(&format!("{:>1$} ", cell, widths[i]));
+            }
+        }
+        println!("{}", line);
+    }
+}
+
+// The mount `path` is on: the one on the longest mount point leading to it,
+// and the last one there when something was mounted over another.
+fn mount_of<'a>(path: &Path, table: &'a [Mount]) -> Option<&'a Mount> {
+    let path = match fs::canonicalize(path) {
+        Ok(path) => path,
+        Err(_) => return None,
+    };
+    table.iter()
+         .filter(|mount| path.starts_with(&mount.mount_point))
+         .max_by_key(|mount| mount.mount_point.components().count())
+}
+
+pub fn uumain(args: Vec<String>) -> i32 {
+    let matches = new_coreopts!("[OPTION]... [FILE]...", SUMMARY, LONG_HELP)
+        .optflag("a", "all", "include pseudo, duplicate and inaccessible file systems")
+        .optopt("B", "block-size", "scale sizes by SIZE before printing them; e.g., '-BM' prints sizes in units of 1,048,576 bytes; see SIZE format below", "SIZE")
+        .optflag("h", "human-readable", "print sizes in powers of 1024 (e.g., 1023M)")
+        .optflag("H", "si", "print sizes in powers of 1000 (e.g., 1.1G)")
+        .optflag("i", "inodes", "list inode information instead of block usage")
+        .optflag("k", "", "like --block-size=1K")
+        .optflag("l", "local", "limit listing to local file systems")
+        .optflag("P", "portability", "use the POSIX output format")
+        .optflag("", "total", "elide all entries insignificant to available space, and produce a grand total")
+        .optmulti("t", "type", "limit listing to file systems of type TYPE", "TYPE")
+        .optflag("T", "print-type", "print file system type")
+        .optmulti("x", "exclude-type", "limit listing to file systems not of type TYPE", "TYPE")
+        .parse(args);
+
+    let block_size = if matches.opt_present("human-readable") {
+        BlockSize::Human(1024)
+    } else if matches.opt_present("si") {
+        BlockSize::Human(1000)
+    } else if let Some(s) = matches.opt_str("block-size") {
+        match BlockSize::parse(&s) {
+            Some(block_size) => block_size,
+            None => {
+                show_error!("invalid --block-size argument {}", quote(&s));
+                return 1;
+            }
+        }
+    } else if matches.opt_present("k") {
+        BlockSize::Units(1024, String::new())
+    } else {
+        BlockSize::from_env("DF_BLOCK_SIZE").unwrap_or_else(BlockSize::default_for_blocks)
+    };
+    let options = Options {
+        block_size: block_size,
+        inodes: matches.opt_present("inodes"),
+        portable: matches.opt_present("portability"),
+        print_type: matches.opt_present("print-type"),
+    };
+    let filter = MountFilter {
+        all: matches.opt_present("all"),
+        local: matches.opt_present("local"),
+        types: matches.opt_strs("type"),
+        exclude_types: matches.opt_strs("exclude-type"),
+    };
+    if let Some(both) = filter.types.iter().find(|&fs_type| filter.exclude_types.contains(fs_type)) {
+        show_error!("file system type {} both selected and excluded", quote(both));
+        return 1;
+    }
+
+    let table = match mounts::mounts() {
+        Ok(table) => table,
+        Err(e) => crash!(1, "cannot read table of mounted file systems: {}", e),
+    };
+
+    let chosen;
+    let mut status = 0;
+    let mut listed: Vec<(&Mount, Usage)> = Vec::new();
+    if matches.free.is_empty() {
+        chosen = filter.filter(table.clone());
+        for mount in &chosen {
+            match Usage::of(&mount.mount_point) {
+                // file systems with nothing on them are pseudo ones too
+                Ok(usage) => if filter.all || usage.size > 0 {
+                    listed.push((mount, usage));
+                },
+                Err(e) => {
+                    show_error!("{}: {}", mount.mount_point.display(), e);
+                    status = 1;
+                }
+            }
+        }
+    } else {
+        for file in &matches.free {
+            let usage = match Usage::of(Path::new(file)) {
+                Ok(usage) => usage,
+                Err(e) => {
+                    show_error!("{}: {}", file, e);
+                    status = 1;
+                    continue;
+                }
+            };
+            match mount_of(Path::new(file), &table) {
+                Some(mount) => if filter.selects(mount) {
+                    listed.push((mount, usage));
+                },
+                None => {
+                    show_error!("{}: cannot find mount point", file);
+                    status = 1;
+                }
+            }
+        }
+    }
+
+    if listed.is_empty() {
+        if status == 0 {
+            show_error!("no file systems processed");
+        }
+        return 1;
+    }
+
+    let mut rows = vec![header(&options)];
+    let mut total = Usage::default();
+    for &(mount, ref usage) in &listed {
+        total.add(usage);
+        rows.push(row(&mount.device, &mount.fs_type, usage, &mount.mount_point.to_string_lossy(), &options));
+    }
+    if matches.opt_present("total") {
+        rows.push(row("total", "-", &total, "-", &options));
+    }
+    print_table(&rows, &options);
+
+    status
+}
@@ -12,6 +12,8 @@ extern crate glob;
 
 #[macro_use]
 extern crate uucore;
+use uucore::lscolors;
+use uucore::quoting::quote;
 
 use std::fs::File;
 use std::io::{BufRead, BufReader};
@@ -76,9 +78,9 @@ pub fn uumain(args: Vec<String>) -> i32 {
 
     if matches.opt_present("print-database") {
         if !matches.free.is_empty() {
-            disp_err!("extra operand ‘{}’\nfile operands cannot be combined with \
+            disp_err!("extra operand {}\nfile operands cannot be combined with \
                       --print-database (-p)",
-                      matches.free[0]);
+                      quote(&matches.free[0]));
             return 1;
         }
         println!("{}", INTERNAL_DB);
@@ -107,7 +109,7 @@ pub fn uumain(args: Vec<String>) -> i32 {
         result = parse(INTERNAL_DB.lines(), out_format, "")
     } else {
         if matches.free.len() > 1 {
-            disp_err!("extra operand ‘{}’", matches.free[1]);
+            disp_err!("extra operand {}", quote(&matches.free[1]));
             return 1;
         }
         match File::open(matches.free[0].as_str()) {
@@ -191,7 +193,6 @@ enum ParseState {
     Continue,
     Pass,
 }
-use std::collections::HashMap;
 fn parse<T>(lines: T, fmt: OutputFmt, fp: &str) -> Result<String, String>
     where T: IntoIterator,
           T::Item: Borrow<str>
@@ -204,45 +205,6 @@ fn parse<T>(lines: T, fmt: OutputFmt, fp: &str) -> Result<String, String>
         _ => unreachable!(),
     }
 
-    let mut table: HashMap<&str, &str> = HashMap::with_capacity(48);
-    table.insert("normal", "no");
-    table.insert("norm", "no");
-    table.insert("file", "fi");
-    table.insert("reset", "rs");
-    table.insert("dir", "di");
-    table.insert("lnk", "ln");
-    table.insert("link", "ln");
-    table.insert("symlink", "ln");
-    table.insert("orphan", "or");
-    table.insert("missing", "mi");
-    table.insert("fifo", "pi");
-    table.insert("pipe", "pi");
-    table.insert("sock", "so");
-    table.insert("blk", "bd");
-    table.insert("block", "bd");
-    table.insert("chr", "cd");
-    table.insert("char", "cd");
-    table.insert("door", "do");
-    table.insert("exec", "ex");
-    table.insert("left", "lc");
-    table.insert("leftcode", "lc");
-    table.insert("right", "rc");
-    table.insert("rightcode", "rc");
-    table.insert("end", "ec");
-    table.insert("endcode", "ec");
-    table.insert("suid", "su");
-    table.insert("setuid", "su");
-    table.insert("sgid", "sg");
-    table.insert("setgid", "sg");
-    table.insert("sticky", "st");
-    table.insert("other_writable", "ow");
-    table.insert("owr", "ow");
-    table.insert("sticky_other_writable", "tw");
-    table.insert("owt", "tw");
-    table.insert("capability", "ca");
-    table.insert("multihardlink", "mh");
-    table.insert("clrtoeol", "cl");
-
     let term = env::var("TERM").unwrap_or("none".to_owned());
     let term = term.as_str();
 
@@ -281,7 +243,7 @@ fn parse<T>(lines: T, fmt: OutputFmt, fp: &str) -> Result<String, String>
                 } else if lower == "options" || lower == "color" || lower == "eightbit" {
                     // Slackware only. Ignore
                 } else {
-                    if let Some(s) = table.get(lower.as_str()) {
+                    if let Some(s) = lscolors::file_type_code(&lower) {
                         result.push_str(format!("{}={}:", s, val).as_str());
                     } else {
                         return Err(format!("{}:{}: unrecognized keyword {}", fp, num, key));
@@ -14,11 +14,15 @@ extern crate time;
 #[macro_use]
 extern crate uucore;
 
-use std::fs;
+use std::fs::{self, File};
 use std::iter;
-use std::io::{stderr, Write};
+use std::io::{stderr, BufRead, BufReader, Write};
 use std::os::unix::fs::MetadataExt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use uucore::fnmatch::{fnmatch, Flags};
+use uucore::mounts::Boundary;
+use uucore::quoting::{quote, quote_path};
+use uucore::size::BlockSize;
 use time::Timespec;
 
 const NAME: &'static str = "du";
@@ -40,11 +44,27 @@ struct Options {
     max_depth: Option<usize>,
     total: bool,
     separate_dirs: bool,
+    exclude: Vec<String>,
+}
+
+impl Options {
+    /// Whether `path` is left out by --exclude or --exclude-from, through a
+    /// pattern matching all of it or all that follows one of its slashes.
+    fn excludes(&self, path: &Path) -> bool {
+        let path = path.to_string_lossy();
+        self.exclude.iter().any(|pattern| {
+            fnmatch(pattern, &path, Flags::default())
+                || path.match_indices('/').any(|(i, _)| !path[i + 1..].starts_with('/')
+                                                   && fnmatch(pattern, &path[i + 1..], Flags::default()))
+        })
+    }
 }
 
 struct Stat {
     path: PathBuf,
     is_dir: bool,
+    dev: u64,
+    inode: u64,
     size: u64,
     blocks: u64,
     nlink: u64,
@@ -55,10 +75,12 @@ struct Stat {
 
 impl Stat {
     fn new(path: PathBuf) -> Stat {
-        let metadata = safe_unwrap!(fs::symlink_metadata(&path));
+        let metadata = safe_unwrap!(uucore::fs::metadata(&path, false));
         Stat {
             path: path,
             is_dir: metadata.is_dir(),
+            dev: metadata.dev(),
+            inode: metadata.ino(),
             size: metadata.len(),
             blocks: metadata.blocks() as u64,
             nlink: metadata.nlink() as u64,
@@ -70,8 +92,9 @@ impl Stat {
 }
 
 // this takes `my_stat` to avoid having to stat files multiple times.
+// With -x, `boundary` is the edge of the file system not to go beyond.
 // XXX: this should use the impl Trait return type when it is stabilized
-fn du(mut my_stat: Stat, options: &Options, depth: usize)
+fn du(mut my_stat: Stat, options: &Options, depth: usize, boundary: Option<&Boundary>)
     -> Box<DoubleEndedIterator<Item = Stat>>
 {
     let mut stats = vec!();
@@ -81,17 +104,23 @@ fn du(mut my_stat: Stat, options: &Options, depth: usize)
         let read = match fs::read_dir(&my_stat.path) {
             Ok(read) => read,
             Err(e) => {
-                safe_writeln!(stderr(), "{}: cannot read directory ‘{}‘: {}",
-                              options.program_name, my_stat.path.display(), e);
+                safe_writeln!(stderr(), "{}: cannot read directory {}: {}",
+                              options.program_name, quote_path(&my_stat.path), e);
                 return Box::new(iter::once(my_stat))
             }
         };
 
         for f in read.into_iter() {
             let entry = crash_if_err!(1, f);
+            if options.excludes(&entry.path()) {
+                continue;
+            }
             let this_stat = Stat::new(entry.path());
             if this_stat.is_dir {
-                futures.push(du(this_stat, options, depth + 1));
+                if boundary.map_or(false, |boundary| boundary.crosses(this_stat.dev, this_stat.inode)) {
+                    continue;
+                }
+                futures.push(du(this_stat, options, depth + 1, boundary));
             } else {
                 my_stat.size += this_stat.size;
                 my_stat.blocks += this_stat.blocks;
@@ -164,12 +193,12 @@ pub fn uumain(args: Vec<String>) -> i32 {
         .optflag("S", "separate-dirs", "do not include size of subdirectories")
     // In main
         .optflag("s", "summarize", "display only a total for each argument")
-    // // In task
-    // opts.optflag("x", "one-file-system", "skip directories on different file systems"),
-    // // In task
-    // opts.optopt("X", "exclude-from", "exclude files that match any pattern in FILE", "FILE"),
-    // // In task
-    // opts.optopt("", "exclude", "exclude files that match PATTERN", "PATTERN"),
+    // In task
+        .optflag("x", "one-file-system", "skip directories on different file systems")
+    // In task
+        .optmulti("X", "exclude-from", "exclude files that match any pattern in FILE", "FILE")
+    // In task
+        .optmulti("", "exclude", "exclude files that match PATTERN", "PATTERN")
     // In main
         .optopt("d", "max-depth", "print the total for a directory (or file, with --all)
             only if it is N or fewer levels below the command
@@ -199,74 +228,51 @@ pub fn uumain(args: Vec<String>) -> i32 {
         (Some(_), Some(_)) | (None, _) => { /* valid */ }
     }
 
+    let mut exclude = matches.opt_strs("exclude");
+    for file in matches.opt_strs("exclude-from") {
+        let read = File::open(&file).and_then(|f| BufReader::new(f).lines().collect::<Result<Vec<_>, _>>());
+        match read {
+            Ok(patterns) => exclude.extend(patterns),
+            Err(e) => {
+                show_error!("{}: {}", quote(&file), e);
+                return 1;
+            }
+        }
+    }
+
     let options = Options {
         all: matches.opt_present("all"),
         program_name: NAME.to_owned(),
         max_depth: max_depth,
         total: matches.opt_present("total"),
         separate_dirs: matches.opt_present("S"),
+        exclude: exclude,
     };
 
     let strs = if matches.free.is_empty() {vec!("./".to_owned())} else {matches.free.clone()};
 
-    let mb = if matches.opt_present("si") {1000 * 1000} else {1024 * 1024};
-    let kb = if matches.opt_present("si") {1000} else {1024};
-
-    let block_size = match matches.opt_str("block-size") {
-        Some(s) => {
-            let mut found_number = false;
-            let mut found_letter = false;
-            let mut numbers = String::new();
-            let mut letters = String::new();
-            for c in s.chars() {
-                if found_letter && c.is_digit(10) || !found_number && !c.is_digit(10) {
-                    show_error!("invalid --block-size argument '{}'", s);
-                    return 1;
-                } else if c.is_digit(10) {
-                    found_number = true;
-                    numbers.push(c);
-                } else if c.is_alphabetic() {
-                    found_letter = true;
-                    letters.push(c);
-                }
+    let block_size = if matches.opt_present("human-readable") {
+        BlockSize::Human(1024)
+    } else if matches.opt_present("si") {
+        BlockSize::Human(1000)
+    } else if let Some(s) = matches.opt_str("block-size") {
+        match BlockSize::parse(&s) {
+            Some(block_size) => block_size,
+            None => {
+                show_error!("invalid --block-size argument {}", quote(&s));
+                return 1;
             }
-            let number = numbers.parse::<u64>().unwrap();
-            let multiple = match &letters[..] {
-                "K" => 1024u64.pow(1), "M" => 1024u64.pow(2),
-                "G" => 1024u64.pow(3), "T" => 1024u64.pow(4),
-                "P" => 1024u64.pow(5), "E" => 1024u64.pow(6),
-                "Z" => 1024u64.pow(7), "Y" => 1024u64.pow(8),
-                "KB" => 1000u64.pow(1), "MB" => 1000u64.pow(2),
-                "GB" => 1000u64.pow(3), "TB" => 1000u64.pow(4),
-                "PB" => 1000u64.pow(5), "EB" => 1000u64.pow(6),
-                "ZB" => 1000u64.pow(7), "YB" => 1000u64.pow(8),
-                _ => {
-                    show_error!("invalid --block-size argument '{}'", s);
-                    return 1;
-                }
-            };
-            number * multiple
-        },
-        None => 1024
-    };
-
-    let convert_size = |size: u64| -> String {
-        if matches.opt_present("human-readable") || matches.opt_present("si") {
-            if size >= mb {
-                format!("{:.1}M", (size as f64) / (mb as f64))
-            } else if size >= kb {
-                format!("{:.1}K", (size as f64) / (kb as f64))
-            } else {
-                format!("{}B", size)
-            }
-        } else if matches.opt_present("k") {
-            format!("{}", ((size as f64) / (kb as f64)).ceil())
-        } else if matches.opt_present("m") {
-            format!("{}", ((size as f64) / (mb as f64)).ceil())
-        } else {
-            format!("{}", ((size as f64) / (block_size as f64)).ceil())
         }
+    } else if matches.opt_present("bytes") {
+        BlockSize::Units(1, String::new())
+    } else if matches.opt_present("k") {
+        BlockSize::Units(1024, String::new())
+    } else if matches.opt_present("m") {
+        BlockSize::Units(1024 * 1024, String::new())
+    } else {
+        BlockSize::from_env("DU_BLOCK_SIZE").unwrap_or_else(BlockSize::default_for_blocks)
     };
+    let convert_size = |size: u64| block_size.format(size);
 
     let time_format_str = match matches.opt_str("time-style") {
         Some(s) => {
@@ -293,11 +299,16 @@ Try '{} --help' for more information.", s, NAME);
     let mut grand_total = 0;
     for path_str in strs.into_iter() {
         let path = PathBuf::from(path_str);
-        let iter = du(Stat::new(path), &options, 0).into_iter();
+        if options.excludes(&path) {
+            continue;
+        }
+        let stat = Stat::new(path);
+        let boundary = if matches.opt_present("one-file-system") { Some(Boundary::new(stat.dev)) } else { None };
+        let iter = du(stat, &options, 0, boundary.as_ref()).into_iter();
         let (_, len) = iter.size_hint();
         let len = len.unwrap();
         for (index, stat) in iter.enumerate() {
-            let size = if matches.opt_present("apparent-size") {
+            let size = if matches.opt_present("apparent-size") || matches.opt_present("bytes") {
                 stat.nlink * stat.size
             } else {
                 // C's stat is such that each block is assume to be 512 bytes
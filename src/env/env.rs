@@ -13,26 +13,252 @@
 
 #![allow(non_camel_case_types)]
 
+#[cfg(unix)]
+extern crate libc;
 #[macro_use]
 extern crate uucore;
 
 use std::env;
 use std::io::{Write, stdout};
 use std::process::Command;
+#[cfg(unix)]
+use std::io;
+#[cfg(unix)]
+use std::mem;
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+#[cfg(unix)]
+use std::ptr;
+#[cfg(unix)]
+use uucore::process::{self, FdPolicy};
+#[cfg(unix)]
+use uucore::signals::{signal_by_name_or_value, signal_max, signal_name_by_value};
 
 static NAME: &'static str = "env";
 static SYNTAX: &'static str = "[OPTION]... [-] [NAME=VALUE]... [COMMAND [ARG]...]";
 static SUMMARY: &'static str = "Set each NAME to VALUE in the environment and run COMMAND";
 static LONG_HELP: &'static str = "
  A mere - implies -i. If no COMMAND, print the resulting environment
+
+ SIG may be a signal name like 'PIPE', or a signal number like '13'.
+ Without SIG, all known signals are included. Multiple signals can be
+ comma-separated. An empty SIG argument is a no-op.
 ";
 
+const EXIT_CANCELED: i32 = 125;
+
 struct options {
     ignore_env: bool,
     null: bool,
     unsets: Vec<String>,
     sets: Vec<(String, String)>,
-    program: Vec<String>
+    program: Vec<String>,
+    #[cfg(unix)]
+    signals: SignalHandling,
+    #[cfg(unix)]
+    fds: FdPolicy
+}
+
+#[cfg(unix)]
+#[derive(Clone, Copy, PartialEq)]
+enum SignalAction {
+    Default,
+    Ignore,
+}
+
+// How COMMAND is to start out with each signal, by number.  An action
+// comes with whether it was only asked for as one of all signals, in which
+// case the signals it cannot be set for (KILL and STOP) are let go.
+#[cfg(unix)]
+struct SignalHandling {
+    actions: Vec<Option<(SignalAction, bool)>>,
+    // blocked if true, unblocked if false, left as it is in env if None
+    blocked: Vec<Option<bool>>,
+    list: bool,
+}
+
+#[cfg(unix)]
+impl SignalHandling {
+    fn new() -> SignalHandling {
+        let count = signal_max() + 1;
+        SignalHandling {
+            actions: vec![None; count],
+            blocked: vec![None; count],
+            list: false,
+        }
+    }
+
+    // Takes in one of the signal options, returning false if `opt` is not
+    // one and the signal that is not known if there is one in its list.
+    fn parse_option(&mut self, opt: &str) -> Result<bool, String> {
+        let mut sp = opt.splitn(2, '=');
+        let name = sp.next().unwrap();
+        let list = sp.next();
+        match name {
+            "--default-signal" => {
+                for signal in try!(SignalHandling::signals(list)) {
+                    self.actions[signal] = Some((SignalAction::Default, list.is_none()));
+                    self.blocked[signal] = Some(false);
+                }
+            }
+            "--ignore-signal" => {
+                for signal in try!(SignalHandling::signals(list)) {
+                    self.actions[signal] = Some((SignalAction::Ignore, list.is_none()));
+                }
+            }
+            "--block-signal" => {
+                for signal in try!(SignalHandling::signals(list)) {
+                    self.blocked[signal] = Some(true);
+                }
+            }
+            "--list-signal-handling" if list.is_none() => self.list = true,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    // The signals in a comma separated list of names and numbers, or all of
+    // them without a list.
+    fn signals(list: Option<&str>) -> Result<Vec<usize>, String> {
+        match list {
+            None => Ok((1..signal_max() + 1).filter(|&signal| signal_name_by_value(signal).is_some()).collect()),
+            Some(list) => {
+                list.split(',')
+                    .filter(|name| !name.is_empty())
+                    .map(|name| match signal_by_name_or_value(&name.to_uppercase()) {
+                        Some(0) | None => Err(name.to_owned()),
+                        Some(signal) => Ok(signal),
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    // Sets COMMAND up to start with the signals handled as asked, listing
+    // how that is if asked to.  Being in a new process, COMMAND would
+    // otherwise have no signals blocked and SIGPIPE set to its default,
+    // rather than inherit them from env as it would from the exec of GNU
+    // env, so the signal mask of env is passed on too.
+    fn apply(&self, command: Option<&mut Command>) -> Result<(), String> {
+        let mut mask: libc::sigset_t = unsafe { mem::zeroed() };
+        if unsafe { libc::sigprocmask(libc::SIG_SETMASK, ptr::null(), &mut mask) } != 0 {
+            return Err(format!("failed to get signal process mask: {}", io::Error::last_os_error()));
+        }
+        let mut handlers = vec![];
+        for (signal, action) in self.actions.iter().enumerate() {
+            match self.blocked[signal] {
+                Some(true) => unsafe { libc::sigaddset(&mut mask, signal as libc::c_int) },
+                Some(false) => unsafe { libc::sigdelset(&mut mask, signal as libc::c_int) },
+                None => 0,
+            };
+            let (action, all) = match *action {
+                Some(action) => action,
+                None => continue,
+            };
+            // setting the action it already has shows whether it can be set
+            let mut act: libc::sigaction = unsafe { mem::zeroed() };
+            if unsafe { libc::sigaction(signal as libc::c_int, ptr::null(), &mut act) } != 0 {
+                if all {
+                    continue;
+                }
+                return Err(format!("failed to get signal action for signal {}: {}", signal, io::Error::last_os_error()));
+            }
+            if unsafe { libc::sigaction(signal as libc::c_int, &act, ptr::null_mut()) } != 0 {
+                if all {
+                    continue;
+                }
+                return Err(format!("failed to set signal action for signal {}: {}", signal, io::Error::last_os_error()));
+            }
+            let handler = match action {
+                SignalAction::Default => libc::SIG_DFL,
+                SignalAction::Ignore => libc::SIG_IGN,
+            };
+            handlers.push((signal as libc::c_int, handler));
+        }
+
+        if self.list {
+            SignalHandling::list(&mask, &handlers);
+        }
+
+        if let Some(command) = command {
+            unsafe {
+                command.pre_exec(move || {
+                    for &(signal, handler) in &handlers {
+                        let mut act: libc::sigaction = mem::zeroed();
+                        act.sa_sigaction = handler;
+                        libc::sigemptyset(&mut act.sa_mask);
+                        if libc::sigaction(signal, &act, ptr::null_mut()) != 0 {
+                            return Err(io::Error::last_os_error());
+                        }
+                    }
+                    if libc::sigprocmask(libc::SIG_SETMASK, &mask, ptr::null_mut()) != 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                    Ok(())
+                });
+            }
+        }
+        Ok(())
+    }
+
+    // Prints the signals that COMMAND starts with blocked or ignored.
+    fn list(mask: &libc::sigset_t, handlers: &[(libc::c_int, libc::sighandler_t)]) {
+        let mut stderr = io::stderr();
+        for signal in 1..signal_max() + 1 {
+            let signal = signal as libc::c_int;
+            let mut act: libc::sigaction = unsafe { mem::zeroed() };
+            if unsafe { libc::sigaction(signal, ptr::null(), &mut act) } != 0 {
+                continue;
+            }
+            let ignored = match handlers.iter().find(|&&(s, _)| s == signal) {
+                Some(&(_, handler)) => handler == libc::SIG_IGN,
+                // what Rust ignores for env is set back to its default
+                None => act.sa_sigaction == libc::SIG_IGN && signal != libc::SIGPIPE,
+            };
+            let blocked = unsafe { libc::sigismember(mask, signal) } == 1;
+            if !ignored && !blocked {
+                continue;
+            }
+            let name = signal_name_by_value(signal as usize).unwrap_or_else(|| signal.to_string());
+            let _ = writeln!(stderr, "{:<10} ({:2}): {}{}{}",
+                             name,
+                             signal,
+                             if blocked { "BLOCK" } else { "" },
+                             if blocked && ignored { "," } else { "" },
+                             if ignored { "IGNORE" } else { "" });
+        }
+    }
+}
+
+#[cfg(unix)]
+fn parse_signal_option(opts: &mut options, opt: &str) -> Result<bool, String> {
+    opts.signals.parse_option(opt)
+}
+
+#[cfg(not(unix))]
+fn parse_signal_option(_: &mut options, _: &str) -> Result<bool, String> {
+    Ok(false)
+}
+
+#[cfg(unix)]
+fn apply_signal_handling(opts: &options, command: Option<&mut Command>) -> Result<(), String> {
+    opts.signals.apply(command)
+}
+
+#[cfg(not(unix))]
+fn apply_signal_handling(_: &options, _: Option<&mut Command>) -> Result<(), String> {
+    Ok(())
+}
+
+// COMMAND is given the descriptors env was, with the standard ones blocking
+#[cfg(unix)]
+fn prepare_fds(opts: &options) -> Result<(), String> {
+    process::prepare_fds(&opts.fds).map_err(|e| format!("failed to prepare file descriptors: {}", e))
+}
+
+#[cfg(not(unix))]
+fn prepare_fds(_: &options) -> Result<(), String> {
+    Ok(())
 }
 
 // print name=value env pairs on screen
@@ -48,13 +274,23 @@ pub fn uumain(args: Vec<String>) -> i32 {
     core_opts.optflag("i", "ignore-environment", "start with an empty environment")
         .optflag("0", "null", "end each output line with a 0 byte rather than newline")
         .optopt("u", "unset", "remove variable from the environment", "NAME");
+    if cfg!(unix) {
+        core_opts.optflagopt("", "default-signal", "reset handling of SIG signal(s) to the default", "SIG")
+            .optflagopt("", "ignore-signal", "set handling of SIG signal(s) to do nothing", "SIG")
+            .optflagopt("", "block-signal", "block delivery of SIG signal(s) to COMMAND", "SIG")
+            .optflag("", "list-signal-handling", "list non default signal handling to standard error");
+    }
 
     let mut opts = Box::new(options {
         ignore_env: false,
         null: false,
         unsets: vec!(),
         sets: vec!(),
-        program: vec!()
+        program: vec!(),
+        #[cfg(unix)]
+        signals: SignalHandling::new(),
+        #[cfg(unix)]
+        fds: FdPolicy::inherited()
     });
 
     let mut wait_cmd = false;
@@ -100,10 +336,17 @@ pub fn uumain(args: Vec<String>) -> i32 {
                     }
                 }
 
-                _ => {
-                    println!("{}: invalid option \"{}\"", NAME, *opt);
-                    println!("Type \"{} --help\" for detailed informations", NAME);
-                    return 1;
+                _ => match parse_signal_option(&mut opts, opt) {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        println!("{}: invalid option \"{}\"", NAME, *opt);
+                        println!("Type \"{} --help\" for detailed informations", NAME);
+                        return 1;
+                    }
+                    Err(signal) => {
+                        show_error!("'{}': invalid signal\nTry '{} --help' for more information.", signal, NAME);
+                        return EXIT_CANCELED;
+                    }
                 }
             }
         } else if opt.starts_with("-") {
@@ -182,12 +425,22 @@ pub fn uumain(args: Vec<String>) -> i32 {
     if opts.program.len() >= 1 {
         let prog = opts.program[0].clone();
         let args = &opts.program[1..];
-        match Command::new(prog).args(args).status() {
+        let mut command = Command::new(prog);
+        command.args(args);
+        if let Err(msg) = apply_signal_handling(&opts, Some(&mut command)).and_then(|_| prepare_fds(&opts)) {
+            show_error!("{}", msg);
+            return EXIT_CANCELED;
+        }
+        match command.status() {
             Ok(exit) => return if exit.success() { 0 } else { exit.code().unwrap() },
             Err(_) => return 1
         }
     } else {
         // no program provided
+        if let Err(msg) = apply_signal_handling(&opts, None) {
+            show_error!("{}", msg);
+            return EXIT_CANCELED;
+        }
         print_env(opts.null);
         return_if_err!(1, stdout().flush());
     }
@@ -20,6 +20,7 @@ use std::fs::File;
 use std::io::{stdin, stdout, Write};
 use linebreak::break_lines;
 use parasplit::ParagraphStream;
+use uucore::graphemes::CharUnit;
 
 macro_rules! silent_unwrap(
     ($exp:expr) => (
@@ -55,6 +56,7 @@ pub struct FmtOptions {
     width           : usize,
     goal            : usize,
     tabwidth        : usize,
+    unit            : CharUnit,
 }
 
 pub fn uumain(args: Vec<String>) -> i32 {
@@ -71,6 +73,7 @@ pub fn uumain(args: Vec<String>) -> i32 {
         .optopt("w", "width", "Fill output lines up to a maximum of WIDTH columns, default 79.", "WIDTH")
         .optopt("g", "goal", "Goal width, default ~0.94*WIDTH. Must be less than WIDTH.", "GOAL")
         .optflag("q", "quick", "Break lines more quickly at the expense of a potentially more ragged appearance.")
+        .optflag("", "graphemes", "Measure a word by its grapheme clusters, so that a letter and the accents on it, or a sequence of joined emoji, is as wide as one character.")
         .optopt("T", "tab-width", "Treat tabs as TABWIDTH spaces for determining line length, default 8. Note that this is used only for calculating line lengths; tabs are preserved in the output.", "TABWIDTH")
         .parse(args);
 
@@ -90,6 +93,7 @@ pub fn uumain(args: Vec<String>) -> i32 {
         width           : 79,
         goal            : 74,
         tabwidth        : 8,
+        unit            : CharUnit::CodePoint,
     };
 
     if matches.opt_present("t") { fmt_opts.tagged       = true; }
@@ -100,6 +104,7 @@ pub fn uumain(args: Vec<String>) -> i32 {
     if matches.opt_present("s") { fmt_opts.split_only   = true; fmt_opts.crown  = false; fmt_opts.tagged = false; }
     if matches.opt_present("x") { fmt_opts.xprefix      = true; }
     if matches.opt_present("X") { fmt_opts.xanti_prefix = true; }
+    if matches.opt_present("graphemes") { fmt_opts.unit = CharUnit::Grapheme; }
 
     match matches.opt_str("p") {
         Some(s) => {
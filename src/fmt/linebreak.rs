@@ -254,7 +254,7 @@ fn find_kp_breakpoints<'a, T: Iterator<Item=&'a WordInfo<'a>>>(iter: T, args: &B
                             // there is no penalty for the final line's length
                             (0, 0.0)
                         } else {
-                            compute_demerits((args.opts.goal - tlen) as isize, stretch, w.word_nchars as isize, active.prev_rat)
+                            compute_demerits(args.opts.goal as isize - tlen as isize, stretch, w.word_nchars as isize, active.prev_rat)
                         };
 
                     // do not even consider adding a line that has too many demerits
@@ -381,8 +381,8 @@ fn restart_active_breaks<'a>(args: &BreakArgs<'a>, active: &LineBreak<'a>, act_i
         } else {
             // choose the lesser evil: breaking too early, or breaking too late
             let wlen = w.word_nchars + args.compute_width(w, active.length, active.fresh);
-            let underlen = (min - active.length) as isize;
-            let overlen = ((wlen + slen + active.length) - args.opts.width) as isize;
+            let underlen = min as isize - active.length as isize;
+            let overlen = (wlen + slen + active.length) as isize - args.opts.width as isize;
             if overlen > underlen {
                 // break early, put this word on the next line
                 (true, args.indent_len + w.word_nchars)
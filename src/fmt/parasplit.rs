@@ -11,6 +11,7 @@ use std::iter::Peekable;
 use std::io::{BufRead, Lines};
 use std::slice::Iter;
 use unicode_width::UnicodeWidthChar;
+use uucore::graphemes::{self, CharUnit};
 use FileOrStdReader;
 use FmtOptions;
 
@@ -27,6 +28,12 @@ fn char_width(c: char) -> usize {
     }
 }
 
+// the printed width of a word; a grapheme cluster is as wide as the widest
+// character in it
+fn word_width(word: &str, unit: CharUnit) -> usize {
+    graphemes::chars(word, unit).map(|g| g.chars().map(char_width).max().unwrap_or(0)).sum()
+}
+
 // lines with PSKIP, lacking PREFIX, or which are entirely blank are
 // NoFormatLines; otherwise, they are FormatLines
 #[derive(Debug)]
@@ -530,13 +537,12 @@ impl<'a> Iterator for WordSplit<'a> {
         // find the beginning of the next whitespace
         // note that this preserves the invariant that self.position
         // points to whitespace character OR end of string
-        let mut word_nchars = 0;
         self.position =
-            match self.string[word_start..]
-            .find(|x: char| if !x.is_whitespace() { word_nchars += char_width(x); false } else { true }) {
+            match self.string[word_start..].find(|x: char| x.is_whitespace()) {
                 None => self.length,
                 Some(s) => s + word_start
             };
+        let word_nchars = word_width(&self.string[word_start..self.position], self.opts.unit);
 
         let word_start_relative = word_start - old_position;
         // if the previous sentence was punctuation and this sentence has >2 whitespace or one tab, is a new sentence.
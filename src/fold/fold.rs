@@ -15,6 +15,8 @@ extern crate uucore;
 use std::fs::File;
 use std::io::{BufRead, BufReader, Read, stdin};
 use std::path::Path;
+use uucore::graphemes::{self, CharUnit};
+use uucore::parse_number::NumberArg;
 
 static SYNTAX: &'static str = "[OPTION]... [FILE]...";
 static SUMMARY: &'static str = "Writes each file (or standard input if no files are given)
@@ -25,12 +27,14 @@ pub fn uumain(args: Vec<String>) -> i32 {
     let (args, obs_width) = handle_obsolete(&args[..]);
     let matches = new_coreopts!(SYNTAX, SUMMARY, LONG_HELP)
         .optflag("b", "bytes", "count using bytes rather than columns (meaning control characters such as newline are not treated specially)")
+        .optflag("", "graphemes", "count a grapheme cluster, such as a letter and the accents on it, as one column, and never break one")
         .optflag("s", "spaces", "break lines at word boundaries rather than a hard cut-off")
         .optopt("w", "width", "set WIDTH as the maximum line width rather than 80", "WIDTH")
         .parse(args);
 
     let bytes = matches.opt_present("b");
     let spaces = matches.opt_present("s");
+    let unit = if matches.opt_present("graphemes") { CharUnit::Grapheme } else { CharUnit::CodePoint };
     let poss_width =
         if matches.opt_present("w") {
             matches.opt_str("w")
@@ -38,9 +42,9 @@ pub fn uumain(args: Vec<String>) -> i32 {
             obs_width
         };
     let width = match poss_width {
-        Some(inp_width) => match inp_width.parse::<usize>() {
+        Some(inp_width) => match NumberArg::new("number of columns").min(1).parse_usize(&inp_width) {
             Ok(width) => width,
-            Err(e) => crash!(1, "illegal width value (\"{}\"): {}", inp_width, e)
+            Err(msg) => crash!(1, "{}", msg)
         },
         None => 80
     };
@@ -49,7 +53,7 @@ pub fn uumain(args: Vec<String>) -> i32 {
     } else {
         matches.free
     };
-    fold(files, bytes, spaces, width);
+    fold(files, bytes, spaces, width, unit);
 
     0
 }
@@ -67,7 +71,7 @@ fn handle_obsolete(args: &[String]) -> (Vec<String>, Option<String>) {
 }
 
 #[inline]
-fn fold(filenames: Vec<String>, bytes: bool, spaces: bool, width: usize) {
+fn fold(filenames: Vec<String>, bytes: bool, spaces: bool, width: usize, unit: CharUnit) {
     for filename in &filenames {
         let filename: &str = &filename;
         let mut stdin_buf;
@@ -81,14 +85,18 @@ fn fold(filenames: Vec<String>, bytes: bool, spaces: bool, width: usize) {
                 &mut file_buf as &mut Read
             }
         );
-        fold_file(buffer, bytes, spaces, width);
+        fold_file(buffer, bytes, spaces, width, unit);
     }
 }
 
 #[inline]
-fn fold_file<T: Read>(mut file: BufReader<T>, bytes: bool, spaces: bool, width: usize) {
+fn fold_file<T: Read>(mut file: BufReader<T>, bytes: bool, spaces: bool, width: usize, unit: CharUnit) {
     let mut line = String::new();
-    while safe_unwrap!(file.read_line(&mut line)) > 0 {
+    loop {
+        line.clear();
+        if safe_unwrap!(file.read_line(&mut line)) == 0 {
+            break;
+        }
         if bytes {
             let len = line.len();
             let mut i = 0;
@@ -109,67 +117,47 @@ fn fold_file<T: Read>(mut file: BufReader<T>, bytes: bool, spaces: bool, width:
                 i += slice.len();
             }
         } else {
-            let mut len = line.chars().count();
-            let newline = line.ends_with("\n");
+            let newline = line.ends_with('\n');
             if newline {
-                if len == 1 {
+                if line.len() == 1 {
                     println!("");
                     continue;
                 }
-                len -= 1;
+                let len = line.len() - 1;
                 line.truncate(len);
             }
-            let mut output = String::new();
+            let chars: Vec<&str> = graphemes::chars(&line, unit).collect();
+            let len = chars.len();
+            let mut output: Vec<&str> = Vec::new();
             let mut count = 0;
-            for (i, ch) in line.chars().enumerate() {
+            for (i, &ch) in chars.iter().enumerate() {
                 if count >= width {
-                    let (val, ncount) = {
-                        let slice = &output[..];
-                        let (out, val, ncount) =
-                            if spaces && i + 1 < len {
-                                match rfind_whitespace(slice) {
-                                    Some(m) => {
-                                        let routput = &slice[m + 1 .. slice.chars().count()];
-                                        let ncount = routput.chars().fold(0, |out, ch: char| {
-                                            out + match ch {
-                                                '\t' => 8,
-                                                '\x08' => if out > 0 { !0 } else { 0 },
-                                                '\r' => return 0,
-                                                _ => 1
-                                            }
-                                        });
-                                        (&slice[0 .. m + 1], routput, ncount)
-                                    },
-                                    None => (slice, "", 0)
-                                }
-                            } else {
-                                (slice, "", 0)
-                            };
-                        println!("{}", out);
-                        (val.to_owned(), ncount)
+                    let rest = match rfind_whitespace(&output) {
+                        Some(m) if spaces && i + 1 < len => output.split_off(m + 1),
+                        _ => Vec::new(),
                     };
-                    output = val;
-                    count = ncount;
+                    println!("{}", output.concat());
+                    count = columns(&rest);
+                    output = rest;
                 }
                 match ch {
-                    '\t' => {
+                    "\t" => {
                         count += 8;
                         if count > width {
-                            println!("{}", output);
-                            output.truncate(0);
+                            println!("{}", output.concat());
+                            output.clear();
                             count = 8;
                         }
                     }
-                    '\x08' => {
+                    "\x08" => {
                         if count > 0 {
                             count -= 1;
-                            let len = output.len() - 1;
-                            output.truncate(len);
+                            output.pop();
                         }
                         continue;
                     }
-                    '\r' => {
-                        output.truncate(0);
+                    "\r" => {
+                        output.clear();
                         count = 0;
                         continue;
                     }
@@ -179,21 +167,26 @@ fn fold_file<T: Read>(mut file: BufReader<T>, bytes: bool, spaces: bool, width:
             }
             if count > 0 {
                 if newline {
-                    println!("{}", output);
+                    println!("{}", output.concat());
                 } else {
-                    print!("{}", output);
+                    print!("{}", output.concat());
                 }
             }
         }
     }
 }
 
+// the columns taken up by characters carried over to the next line
+fn columns(chars: &[&str]) -> usize {
+    chars.iter().fold(0, |count, &ch| match ch {
+        "\t" => count + 8,
+        "\x08" => count.saturating_sub(1),
+        "\r" => 0,
+        _ => count + 1
+    })
+}
+
 #[inline]
-fn rfind_whitespace(slice: &str) -> Option<usize> {
-    for (i, ch) in slice.chars().rev().enumerate() {
-        if ch.is_whitespace() {
-            return Some(slice.chars().count() - (i + 1));
-        }
-    }
-    None
+fn rfind_whitespace(chars: &[&str]) -> Option<usize> {
+    chars.iter().rposition(|ch| ch.chars().next().map_or(false, char::is_whitespace))
 }
@@ -12,24 +12,49 @@
 
 #[macro_use]
 extern crate uucore;
-use uucore::entries::{Passwd, Locate, get_groups, gid2grp};
+use uucore::entries::{self, Passwd, Locate};
+use uucore::libc::gid_t;
 
-static SYNTAX: &'static str = "[user]";
-static SUMMARY: &'static str = "display current group names";
+static SYNTAX: &'static str = "[user]...";
+static SUMMARY: &'static str = "Print the groups each USER is in, or the groups of the current process";
 
 pub fn uumain(args: Vec<String>) -> i32 {
     let matches = new_coreopts!(SYNTAX, SUMMARY, "")
           .parse(args);
 
+    let mut status = 0;
     if matches.free.is_empty() {
-        println!("{}", get_groups().unwrap().iter().map(|&g| gid2grp(g).unwrap()).collect::<Vec<_>>().join(" "));
+        match entries::group_list(None) {
+            Ok(groups) => println!("{}", names(&groups, &mut status)),
+            Err(e) => crash!(1, "cannot get groups: {}", e),
+        }
     } else {
-        if let Ok(p) = Passwd::locate(matches.free[0].as_str()) {
-            println!("{}", p.belongs_to().iter().map(|&g| gid2grp(g).unwrap()).collect::<Vec<_>>().join(" "));
-        } else {
-            crash!(1, "unknown user {}", matches.free[0]);
+        for user in &matches.free {
+            match Passwd::locate(user.as_str()) {
+                Ok(p) => {
+                    let groups = crash_if_err!(1, entries::group_list(Some(&p)));
+                    println!("{} : {}", user, names(&groups, &mut status));
+                }
+                Err(_) => {
+                    show_error!("'{}': no such user", user);
+                    status = 1;
+                }
+            }
         }
     }
 
-    0
+    status
+}
+
+// The names of the groups, or their numbers, with an error, for those
+// without one.
+fn names(groups: &[gid_t], status: &mut i32) -> String {
+    groups.iter()
+          .map(|&gid| entries::gid2grp(gid).unwrap_or_else(|_| {
+              show_error!("cannot find name for group ID {}", gid);
+              *status = 1;
+              gid.to_string()
+          }))
+          .collect::<Vec<_>>()
+          .join(" ")
 }
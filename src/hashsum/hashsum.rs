@@ -362,37 +362,37 @@ fn digest_reader<'a, T: Read>(digest: &mut Box<Digest+'a>, reader: &mut BufReade
 
     // Digest file, do not hold too much in memory at any given moment
     let windows = cfg!(windows);
-    let mut buffer = Vec::with_capacity(524288);
+    let mut buffer = vec![0; 524288];
     let mut vec = Vec::with_capacity(524288);
     let mut looking_for_newline = false;
     loop {
-        match reader.read_to_end(&mut buffer) {
-            Ok(0) => { break; },
-            Ok(nread) => {
-                if windows && !binary {
-                    // Windows text mode returns '\n' when reading '\r\n'
-                    for i in 0 .. nread {
-                        if looking_for_newline {
-                            if buffer[i] != ('\n' as u8) {
-                                vec.push('\r' as u8);
-                            }
-                            if buffer[i] != ('\r' as u8) {
-                                vec.push(buffer[i]);
-                                looking_for_newline = false;
-                            }
-                        } else if buffer[i] != ('\r' as u8) {
-                            vec.push(buffer[i]);
-                        } else {
-                            looking_for_newline = true;
-                        }
+        let nread = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(nread) => nread,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if windows && !binary {
+            // Windows text mode returns '\n' when reading '\r\n'
+            for i in 0 .. nread {
+                if looking_for_newline {
+                    if buffer[i] != ('\n' as u8) {
+                        vec.push('\r' as u8);
                     }
-                    digest.input(&vec);
-                    vec.clear();
+                    if buffer[i] != ('\r' as u8) {
+                        vec.push(buffer[i]);
+                        looking_for_newline = false;
+                    }
+                } else if buffer[i] != ('\r' as u8) {
+                    vec.push(buffer[i]);
                 } else {
-                    digest.input(&buffer[..nread]);
+                    looking_for_newline = true;
                 }
-            },
-            Err(e) => return Err(e)
+            }
+            digest.input(&vec);
+            vec.clear();
+        } else {
+            digest.input(&buffer[..nread]);
         }
     }
     if windows && looking_for_newline {
@@ -410,3 +410,27 @@ fn digest_reader<'a, T: Read>(digest: &mut Box<Digest+'a>, reader: &mut BufReade
         Ok(bytes.to_hex())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::ErrorKind;
+    use uucore::faults::Faulty;
+
+    fn md5<R: Read>(reader: R) -> io::Result<String> {
+        let mut digest = Box::new(Md5::new()) as Box<Digest>;
+        digest_reader(&mut digest, &mut BufReader::new(reader), true, 128)
+    }
+
+    #[test]
+    fn test_digest_reader_faults() {
+        let data: Vec<u8> = (0..1000000u32).map(|i| (i % 251) as u8).collect();
+        let expected = md5(&data[..]).unwrap();
+        assert_eq!(md5(Faulty::new(&data[..]).short(4093).interrupt(3)).unwrap(), expected);
+
+        let err = md5(Faulty::new(&data[..]).short(4093).fail_at(600000, ErrorKind::Other)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Other);
+        let err = md5(Faulty::new(&data[..]).would_block(2)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::WouldBlock);
+    }
+}
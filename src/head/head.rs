@@ -14,10 +14,14 @@
 #[macro_use]
 extern crate uucore;
 
-use std::io::{BufRead, BufReader, Read, stdin};
+use std::cmp;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, stdin, stdout, Write};
 use std::fs::File;
 use std::path::Path;
 use std::str::from_utf8;
+use uucore::parse_number::NumberArg;
+use uucore::sparse::SparseReader;
 
 static SYNTAX: &'static str = "";
 static SUMMARY: &'static str = "";
@@ -30,6 +34,10 @@ enum FilterMode {
 
 struct Settings {
     mode: FilterMode,
+    // print all but the last bytes or lines the mode counts
+    all_but_last: bool,
+    // what lines end with
+    delimiter: u8,
     verbose: bool,
 }
 
@@ -37,6 +45,8 @@ impl Default for Settings {
     fn default() -> Settings {
         Settings {
             mode: FilterMode::Lines(10),
+            all_but_last: false,
+            delimiter: b'\n',
             verbose: false,
         }
     }
@@ -54,92 +64,120 @@ pub fn uumain(args: Vec<String>) -> i32 {
     let matches = new_coreopts!(SYNTAX, SUMMARY, LONG_HELP)
         .optopt("c", "bytes", "Print the first K bytes.  With the leading '-', print all but the last K bytes", "[-]K")
         .optopt("n", "lines", "Print the first K lines.  With the leading '-', print all but the last K lines", "[-]K")
-        .optflag("q", "quiet", "never print headers giving file names")
-        .optflag("v", "verbose", "always print headers giving file names")
+        .optflagmulti("q", "quiet", "never print headers giving file names")
+        .optflagmulti("", "silent", "same as --quiet")
+        .optflagmulti("v", "verbose", "always print headers giving file names")
+        .optflag("z", "zero-terminated", "line delimiter is NUL, not newline")
         .optflag("h", "help", "display this help and exit")
         .optflag("V", "version", "output version information and exit")
         .parse(new_args);
 
     let use_bytes = matches.opt_present("c");
 
-    // TODO: suffixes (e.g. b, kB, etc.)
     match matches.opt_str("n") {
         Some(n) => {
             if use_bytes {
                 show_error!("cannot specify both --bytes and --lines.");
                 return 1;
             }
-            match n.parse::<usize>() {
+            settings.all_but_last = n.starts_with('-');
+            match NumberArg::new("number of lines").suffixes(true).parse_usize(strip_minus(&n)) {
                 Ok(m) => { settings.mode = FilterMode::Lines(m) }
-                Err(e) => {
-                    show_error!("invalid line count '{}': {}", n, e);
+                Err(msg) => {
+                    show_error!("{}", msg);
                     return 1;
                 }
             }
         }
         None => match matches.opt_str("c") {
-            Some(count) => match count.parse::<usize>() {
-                Ok(m) => settings.mode = FilterMode::Bytes(m),
-                Err(e)=> {
-                    show_error!("invalid byte count '{}': {}", count, e);
-                    return 1;
+            Some(count) => {
+                settings.all_but_last = count.starts_with('-');
+                match NumberArg::new("number of bytes").suffixes(true).parse_usize(strip_minus(&count)) {
+                    Ok(m) => settings.mode = FilterMode::Bytes(m),
+                    Err(msg) => {
+                        show_error!("{}", msg);
+                        return 1;
+                    }
                 }
-            },
+            }
             None => {}
         }
     };
 
-    let quiet = matches.opt_present("q");
-    let verbose = matches.opt_present("v");
-    let files = matches.free;
-
-    // GNU implementation allows multiple declarations of "-q" and "-v" with the
-    // last flag winning. This can't be simulated with the getopts cargo unless
-    // we manually parse the arguments. Given the declaration of both flags,
-    // verbose mode always wins. This is a potential future improvement.
-    if files.len() > 1 && !quiet && !verbose {
-        settings.verbose = true;
-    }
-    if quiet {
-        settings.verbose = false;
-    }
-    if verbose {
-        settings.verbose = true;
+    if matches.opt_present("z") {
+        settings.delimiter = 0;
     }
 
+    // the last of -q and -v given wins, and without either, there are
+    // headers only for several files
+    let (quiet, verbose) = {
+        let last = |name| matches.opt_positions(name).into_iter().max();
+        (cmp::max(last("q"), last("silent")), last("v"))
+    };
+    let mut files = matches.free;
     if files.is_empty() {
-        let mut buffer = BufReader::new(stdin());
-        head(&mut buffer, &settings);
-    } else {
-        let mut firstime = true;
-
-        for file in &files {
-            if settings.verbose {
-                if !firstime { println!(""); }
-                println!("==> {} <==", file);
+        files.push("-".to_owned());
+    }
+    settings.verbose = if verbose > quiet { true } else { quiet.is_none() && files.len() > 1 };
+
+    let mut status = 0;
+    let mut first_header = true;
+    let mut header = |name: &str| {
+        if settings.verbose {
+            if !first_header { println!(""); }
+            println!("==> {} <==", name);
+        }
+        first_header = false;
+    };
+
+    for file in &files {
+        if file == "-" {
+            header("standard input");
+            if let Err(e) = head(&mut BufReader::new(stdin()), &settings) {
+                show_error!("error reading 'standard input': {}", e);
+                status = 1;
             }
-            firstime = false;
+            continue;
+        }
 
-            let path = Path::new(file);
-            let reader = File::open(&path).unwrap();
-            let mut buffer = BufReader::new(reader);
-            if !head(&mut buffer, &settings) {
-                break;
+        let reader = match File::open(Path::new(file)) {
+            Ok(reader) => reader,
+            Err(e) => {
+                show_error!("cannot open '{}' for reading: {}", file, e);
+                status = 1;
+                continue;
             }
+        };
+        header(file);
+        let is_file = reader.metadata().map(|metadata| metadata.is_file()).unwrap_or(false);
+        let mut reader = SparseReader::new(reader);
+        let result = if settings.all_but_last && is_file {
+            head_file(&mut reader, &settings)
+        } else {
+            head(&mut BufReader::new(reader), &settings)
+        };
+        if let Err(e) = result {
+            show_error!("error reading '{}': {}", file, e);
+            status = 1;
         }
     }
 
-    0
+    status
+}
+
+fn strip_minus(count: &str) -> &str {
+    if count.starts_with('-') { &count[1..] } else { count }
 }
 
-// It searches for an option in the form of -123123
+// It searches for an option in the form of -123123, which only the first
+// argument can be, as any other could be the count of -c or -n.
 //
 // In case is found, the options vector will get rid of that object so that
 // getopts works correctly.
 fn obsolete(options: &[String]) -> (Vec<String>, Option<usize>) {
     let mut options: Vec<String> = options.to_vec();
     let mut a = 1;
-    let b = options.len();
+    let b = cmp::min(options.len(), 2);
 
     while a < b {
         let current = options[a].clone();
@@ -166,19 +204,107 @@ fn obsolete(options: &[String]) -> (Vec<String>, Option<usize>) {
     (options, None)
 }
 
-// TODO: handle errors on read
-fn head<T: Read>(reader: &mut BufReader<T>, settings: &Settings) -> bool {
-    match settings.mode {
-        FilterMode::Bytes(count) => {
-            for byte in reader.bytes().take(count) {
-                print!("{}", byte.unwrap() as char);
+/// The size of the blocks copied at a time.
+const BLOCK_SIZE: usize = 1 << 16;
+
+fn head<T: Read>(reader: &mut BufReader<T>, settings: &Settings) -> io::Result<()> {
+    let stdout = stdout();
+    let mut stdout = stdout.lock();
+    match (&settings.mode, settings.all_but_last) {
+        (&FilterMode::Bytes(count), false) => {
+            try!(io::copy(&mut reader.take(count as u64), &mut stdout));
+        }
+        (&FilterMode::Lines(count), false) => {
+            let mut line = Vec::new();
+            for _ in 0..count {
+                line.clear();
+                if try!(reader.read_until(settings.delimiter, &mut line)) == 0 {
+                    break;
+                }
+                try!(stdout.write_all(&line));
             }
-        },
-        FilterMode::Lines(count) => {
-            for line in reader.lines().take(count) {
-                println!("{}", line.unwrap());
+        }
+        // whatever is more than the last bytes or lines is printed as soon
+        // as the window holding them grows beyond them
+        (&FilterMode::Bytes(count), true) => {
+            let mut window = VecDeque::new();
+            let mut buf = vec![0; BLOCK_SIZE];
+            loop {
+                let n = try!(reader.read(&mut buf));
+                if n == 0 {
+                    break;
+                }
+                window.extend(&buf[..n]);
+                if window.len() > count {
+                    let excess = window.len() - count;
+                    {
+                        let (front, back) = window.as_slices();
+                        let from_front = cmp::min(excess, front.len());
+                        try!(stdout.write_all(&front[..from_front]));
+                        try!(stdout.write_all(&back[..excess - from_front]));
+                    }
+                    window.drain(..excess);
+                }
+            }
+        }
+        (&FilterMode::Lines(count), true) => {
+            let mut window = VecDeque::new();
+            loop {
+                let mut line = Vec::new();
+                if try!(reader.read_until(settings.delimiter, &mut line)) == 0 {
+                    break;
+                }
+                window.push_back(line);
+                if window.len() > count {
+                    try!(stdout.write_all(&window.pop_front().unwrap()));
+                }
+            }
+        }
+    }
+    stdout.flush()
+}
+
+/// All but the last bytes or lines of a regular file, which are found from
+/// its size, and for lines, by reading back from the end.
+fn head_file(file: &mut SparseReader, settings: &Settings) -> io::Result<()> {
+    let start = try!(file.seek(SeekFrom::Current(0)));
+    let end = cmp::max(start, try!(file.seek(SeekFrom::End(0))));
+    let until = match settings.mode {
+        FilterMode::Bytes(count) => cmp::max(start, end.saturating_sub(count as u64)),
+        FilterMode::Lines(count) => try!(last_lines_start(file, start, end, count, settings.delimiter)),
+    };
+
+    try!(file.seek(SeekFrom::Start(start)));
+    let stdout = stdout();
+    let mut stdout = stdout.lock();
+    try!(io::copy(&mut file.take(until - start), &mut stdout));
+    stdout.flush()
+}
+
+/// Where the last `count` lines of the file between `start` and `end`
+/// begin.  A delimiter at the very end only ends the last line.
+fn last_lines_start(file: &mut SparseReader, start: u64, end: u64, mut count: usize, delimiter: u8)
+    -> io::Result<u64>
+{
+    if count == 0 {
+        return Ok(end);
+    }
+    let mut buf = vec![0; BLOCK_SIZE];
+    let mut block_end = end;
+    while block_end > start {
+        let block_start = cmp::max(start, block_end.saturating_sub(BLOCK_SIZE as u64));
+        let block = &mut buf[..(block_end - block_start) as usize];
+        try!(file.seek(SeekFrom::Start(block_start)));
+        try!(file.read_exact(block));
+        for (i, &byte) in block.iter().enumerate().rev() {
+            if byte == delimiter && block_start + i as u64 != end - 1 {
+                count -= 1;
+                if count == 0 {
+                    return Ok(block_start + i as u64 + 1);
+                }
             }
         }
+        block_end = block_start;
     }
-    true
+    Ok(start)
 }
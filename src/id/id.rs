@@ -19,18 +19,13 @@
 #[macro_use]
 extern crate uucore;
 pub use uucore::libc;
-use uucore::libc::{getlogin, uid_t};
-use uucore::entries::{self, Passwd, Group, Locate};
+use uucore::libc::{getlogin, uid_t, gid_t};
+use uucore::entries::{self, Passwd, Locate};
 use uucore::process::{getgid, getuid, getegid, geteuid};
 use std::ffi::CStr;
+use std::io;
 
-macro_rules! cstr2cow {
-    ($v:expr) => (
-        unsafe { CStr::from_ptr($v).to_string_lossy() }
-    )
-}
-
-#[cfg(not(target_os = "linux"))]
+#[cfg(any(target_os = "macos", target_os = "freebsd"))]
 mod audit {
     pub use std::mem::uninitialized;
     use super::libc::{uid_t, pid_t, c_int, c_uint, uint64_t, dev_t};
@@ -76,22 +71,21 @@ pub fn uumain(args: Vec<String>) -> i32 {
     let mut opts = new_coreopts!(SYNTAX, SUMMARY, "");
     opts.optflag("A",
                  "",
-                 "Display the process audit (not available on Linux)");
-    opts.optflag("G", "", "Display the different group IDs");
-    opts.optflag("g", "", "Display the effective group ID as a number");
+                 "Display the process audit user ID and session ID");
+    opts.optflag("G", "groups", "Display the different group IDs");
+    opts.optflag("g", "group", "Display the effective group ID as a number");
     opts.optflag("n",
-                 "",
+                 "name",
                  "Display the name of the user or group ID for the -G, -g and -u options");
     opts.optflag("P", "", "Display the id as a password file entry");
     opts.optflag("p", "", "Make the output human-readable");
-    opts.optflag("r", "", "Display the real ID for the -g and -u options");
-    opts.optflag("u", "", "Display the effective user ID as a number");
+    opts.optflag("r", "real", "Display the real ID for the -g and -u options");
+    opts.optflag("u", "user", "Display the effective user ID as a number");
 
     let matches = opts.parse(args);
 
     if matches.opt_present("A") {
-        auditid();
-        return 0;
+        return auditid();
     }
 
     let possible_pw = if matches.free.is_empty() {
@@ -99,127 +93,136 @@ pub fn uumain(args: Vec<String>) -> i32 {
     } else {
         match Passwd::locate(matches.free[0].as_str()) {
             Ok(p) => Some(p),
-            Err(_) => crash!(1, "No such user/group: {}", matches.free[0]),
+            Err(_) => {
+                show_error!("'{}': no such user", matches.free[0]);
+                return 1;
+            }
         }
     };
 
+    if matches.opt_present("P") {
+        pline(possible_pw.map(|v| v.uid()));
+        return 0;
+    };
+
+    if matches.opt_present("p") {
+        pretty(possible_pw);
+        return 0;
+    }
+
     let nflag = matches.opt_present("n");
     let uflag = matches.opt_present("u");
     let gflag = matches.opt_present("g");
+    let groups_flag = matches.opt_present("G");
     let rflag = matches.opt_present("r");
 
-    if gflag {
-        let id = possible_pw.map(|p| p.gid()).unwrap_or(if rflag {
-            getgid()
-        } else {
-            getegid()
-        });
-        println!("{}",
-                 if nflag {
-                     entries::gid2grp(id).unwrap_or(id.to_string())
-                 } else {
-                     id.to_string()
-                 });
-        return 0;
+    if [uflag, gflag, groups_flag].iter().filter(|&&flag| flag).count() > 1 {
+        show_error!("cannot print \"only\" of more than one choice");
+        return 1;
+    }
+    if !(uflag || gflag || groups_flag) && (nflag || rflag) {
+        show_error!("cannot print only names or real IDs in default format");
+        return 1;
     }
 
+    let mut status = 0;
     if uflag {
         let id = possible_pw.map(|p| p.uid()).unwrap_or(if rflag {
             getuid()
         } else {
             geteuid()
         });
+        println!("{}", if nflag { user_name(id, &mut status) } else { id.to_string() });
+    } else if gflag {
+        let id = possible_pw.map(|p| p.gid()).unwrap_or(if rflag {
+            getgid()
+        } else {
+            getegid()
+        });
+        println!("{}", if nflag { group_name(id, &mut status) } else { id.to_string() });
+    } else if groups_flag {
+        let groups = match entries::group_list(possible_pw.as_ref()) {
+            Ok(groups) => groups,
+            Err(e) => crash!(1, "cannot get groups: {}", e),
+        };
         println!("{}",
-                 if nflag {
-                     entries::uid2usr(id).unwrap_or(id.to_string())
-                 } else {
-                     id.to_string()
-                 });
-        return 0;
+                 groups.iter()
+                       .map(|&id| if nflag { group_name(id, &mut status) } else { id.to_string() })
+                       .collect::<Vec<_>>()
+                       .join(" "));
+    } else {
+        id_print(possible_pw.as_ref());
     }
 
-    if matches.opt_present("G") {
-        println!("{}",
-                 if nflag {
-                     possible_pw.map(|p| p.belongs_to())
-                                .unwrap_or(entries::get_groups().unwrap())
-                                .iter()
-                                .map(|&id| entries::gid2grp(id).unwrap())
-                                .collect::<Vec<_>>()
-                                .join(" ")
-                 } else {
-                     possible_pw.map(|p| p.belongs_to())
-                                .unwrap_or(entries::get_groups().unwrap())
-                                .iter()
-                                .map(|&id| id.to_string())
-                                .collect::<Vec<_>>()
-                                .join(" ")
-                 });
-        return 0;
-    }
+    status
+}
 
-    if matches.opt_present("P") {
-        pline(possible_pw.map(|v| v.uid()));
-        return 0;
-    };
+// The name of the user `id`, or the number, with an error, if it has none.
+fn user_name(id: uid_t, status: &mut i32) -> String {
+    entries::uid2usr(id).unwrap_or_else(|_| {
+        show_error!("cannot find name for user ID {}", id);
+        *status = 1;
+        id.to_string()
+    })
+}
 
-    if matches.opt_present("p") {
-        pretty(possible_pw);
-        return 0;
+// The name of the group `id`, or the number, with an error, if it has none.
+fn group_name(id: gid_t, status: &mut i32) -> String {
+    entries::gid2grp(id).unwrap_or_else(|_| {
+        show_error!("cannot find name for group ID {}", id);
+        *status = 1;
+        id.to_string()
+    })
+}
+
+// `id` with its name in parentheses, if it has one.
+fn with_name(id: u32, name: io::Result<String>) -> String {
+    match name {
+        Ok(name) => format!("{}({})", id, name),
+        Err(_) => id.to_string(),
     }
+}
 
-    if possible_pw.is_some() {
-        id_print(possible_pw, false, false)
+/// The name the user logged in as, where the system keeps one.
+fn login_name() -> Option<String> {
+    let login = unsafe { getlogin() };
+    if login.is_null() {
+        None
     } else {
-        id_print(possible_pw, true, true)
+        Some(unsafe { CStr::from_ptr(login) }.to_string_lossy().into_owned())
     }
-
-    0
 }
 
 fn pretty(possible_pw: Option<Passwd>) {
+    let groups = entries::group_list(possible_pw.as_ref()).unwrap_or_default();
+    let group_names = groups.iter()
+                            .map(|&gr| entries::gid2grp(gr).unwrap_or(gr.to_string()))
+                            .collect::<Vec<_>>()
+                            .join(" ");
     if let Some(p) = possible_pw {
-        print!("uid\t{}\ngroups\t", p.name());
-        println!("{}",
-                 p.belongs_to().iter().map(|&gr| entries::gid2grp(gr).unwrap()).collect::<Vec<_>>().join(" "));
+        println!("uid\t{}", p.name());
     } else {
-        let login = cstr2cow!(getlogin() as *const _);
+        // the login name only when it is not the real user's
         let rid = getuid();
-        if let Ok(p) = Passwd::locate(rid) {
-            if login == p.name() {
+        let name = entries::uid2usr(rid).ok();
+        if let Some(login) = login_name() {
+            if name.as_ref() != Some(&login) {
                 println!("login\t{}", login);
             }
-            println!("uid\t{}", p.name());
-        } else {
-            println!("uid\t{}", rid);
         }
+        println!("uid\t{}", name.unwrap_or(rid.to_string()));
 
-        let eid = getegid();
-        if eid == rid {
-            if let Ok(p) = Passwd::locate(eid) {
-                println!("euid\t{}", p.name());
-            } else {
-                println!("euid\t{}", eid);
-            }
+        let eid = geteuid();
+        if eid != rid {
+            println!("euid\t{}", entries::uid2usr(eid).unwrap_or(eid.to_string()));
         }
 
-        let rid = getgid();
-        if rid != eid {
-            if let Ok(g) = Group::locate(rid) {
-                println!("euid\t{}", g.name());
-            } else {
-                println!("euid\t{}", rid);
-            }
+        let rgid = getgid();
+        if rgid != getegid() {
+            println!("rgid\t{}", entries::gid2grp(rgid).unwrap_or(rgid.to_string()));
         }
-
-        println!("groups\t{}",
-                 entries::get_groups()
-                     .unwrap()
-                     .iter()
-                     .map(|&gr| entries::gid2grp(gr).unwrap())
-                     .collect::<Vec<_>>()
-                     .join(" "));
     }
+    println!("groups\t{}", group_names);
 }
 
 #[cfg(any(target_os = "macos", target_os = "freebsd"))]
@@ -255,16 +258,38 @@ fn pline(possible_uid: Option<uid_t>) {
              pw.user_shell());
 }
 
+/// Where the kernel keeps the login user ID and session it audits, in
+/// /proc, with all bits set for neither.
 #[cfg(target_os = "linux")]
-fn auditid() {}
+fn auditid() -> i32 {
+    use std::fs::File;
+    use std::io::Read;
+
+    let read = |name: &str| {
+        let mut value = String::new();
+        File::open(format!("/proc/self/{}", name)).and_then(|mut file| file.read_to_string(&mut value))
+                                                  .map(|_| value.trim().to_owned())
+    };
+    match (read("loginuid"), read("sessionid")) {
+        (Ok(auid), Ok(asid)) => {
+            println!("auid={}", auid);
+            println!("asid={}", asid);
+            0
+        }
+        (Err(e), _) | (_, Err(e)) => {
+            show_error!("couldn't retrieve information: {}", e);
+            1
+        }
+    }
+}
 
-#[cfg(not(target_os = "linux"))]
-fn auditid() {
+#[cfg(any(target_os = "macos", target_os = "freebsd"))]
+fn auditid() -> i32 {
     let mut auditinfo: audit::c_auditinfo_addr_t = unsafe { audit::uninitialized() };
     let address = &mut auditinfo as *mut audit::c_auditinfo_addr_t;
     if unsafe { audit::getaudit(address) } < 0 {
-        println!("couldn't retrieve information");
-        return;
+        show_error!("couldn't retrieve information");
+        return 1;
     }
 
     println!("auid={}", auditinfo.ai_auid);
@@ -272,29 +297,46 @@ fn auditid() {
     println!("mask.failure=0x{:x}", auditinfo.ai_mask.am_failure);
     println!("termid.port=0x{:x}", auditinfo.ai_termid.port);
     println!("asid={}", auditinfo.ai_asid);
+    0
 }
 
-fn id_print(possible_pw: Option<Passwd>, p_euid: bool, p_egid: bool) {
-    let (uid, gid) = possible_pw.map(|p| (p.uid(), p.gid())).unwrap_or((getuid(), getgid()));;
-
-    let groups = Passwd::locate(uid).unwrap().belongs_to();
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "freebsd")))]
+fn auditid() -> i32 {
+    show_error!("audit information is not available on this system");
+    1
+}
 
-    print!("uid={}({})", uid, entries::uid2usr(uid).unwrap());
-    print!(" gid={}({})", gid, entries::gid2grp(gid).unwrap());
+/// What `id` prints by default: the real user and group, the effective ones
+/// where they differ, and all the groups.  For a user given by name, the
+/// real and effective ones are the same.
+fn id_print(possible_pw: Option<&Passwd>) {
+    let (uid, gid, euid, egid) = match possible_pw {
+        Some(p) => (p.uid(), p.gid(), p.uid(), p.gid()),
+        None => (getuid(), getgid(), geteuid(), getegid()),
+    };
 
-    let euid = geteuid();
-    if p_euid && (euid != uid) {
-        print!(" euid={}({})", euid, entries::uid2usr(euid).unwrap());
+    print!("uid={}", with_name(uid, entries::uid2usr(uid)));
+    print!(" gid={}", with_name(gid, entries::gid2grp(gid)));
+    if euid != uid {
+        print!(" euid={}", with_name(euid, entries::uid2usr(euid)));
     }
-
-    let egid = getegid();
-    if p_egid && (egid != gid) {
-        print!(" egid={}({})", euid, entries::gid2grp(egid).unwrap());
+    if egid != gid {
+        print!(" egid={}", with_name(egid, entries::gid2grp(egid)));
     }
 
+    // the groups the process has, which the real group is not among unless
+    // it is also a supplementary one
+    let groups = match entries::group_list(possible_pw) {
+        Ok(groups) => groups,
+        Err(e) => crash!(1, "cannot get groups: {}", e),
+    };
+    let supplementary = match possible_pw {
+        Some(_) => groups.clone(),
+        None => entries::get_groups().unwrap_or_default(),
+    };
+    let groups = groups.into_iter().filter(|&gr| gr != gid || gr == egid || supplementary.contains(&gr));
     println!(" groups={}",
-             groups.iter()
-                   .map(|&gr| format!("{}({})", gr, entries::gid2grp(gr).unwrap()))
+             groups.map(|gr| with_name(gr, entries::gid2grp(gr)))
                    .collect::<Vec<_>>()
                    .join(","));
 }
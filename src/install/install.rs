@@ -17,20 +17,25 @@ mod mode;
 #[macro_use]
 extern crate uucore;
 
+use std::cmp;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::result::Result;
+use uucore::compare;
+use uucore::copy;
+use uucore::quoting::quote_path;
 
 static NAME: &'static str = "install";
 static SUMMARY: &'static str = "Copy SOURCE to DEST or multiple SOURCE(s) to the existing
  DIRECTORY, while setting permission modes and owner/group";
 static LONG_HELP: &'static str = "";
 
-const DEFAULT_MODE: u32 = 755;
+const DEFAULT_MODE: u32 = 0o755;
 
 #[allow(dead_code)]
 pub struct Behaviour {
     main_function: MainFunction,
+    compare: bool,
     specified_mode: Option<u32>,
     suffix: String,
     verbose: bool
@@ -107,8 +112,7 @@ fn parse_opts(args: Vec<String>) -> getopts::Matches {
     // TODO implement flag
         .optflag("b", "", "(unimplemented) like --backup but does not accept an argument")
         .optflag("c", "", "ignored")
-    // TODO implement flag
-        .optflag("C", "compare", "(unimplemented) compare each pair of source and destination\n \
+        .optflag("C", "compare", "compare each pair of source and destination\n \
                                   files, and in some cases, do not modify the destination at all")
         .optflag("d", "directory", "treat all arguments as directory names.\n \
                                     create all components of the specified directories")
@@ -160,8 +164,6 @@ fn check_unimplemented(matches: &getopts::Matches) -> Result<(), &str> {
         Err("--backup")
     } else if matches.opt_present("b") {
         Err("-b")
-    } else if matches.opt_present("compare") {
-        Err("--compare, -C")
     } else if matches.opt_present("D") {
         Err("-D")
     } else if matches.opt_present("group") {
@@ -240,8 +242,14 @@ fn behaviour(matches: &getopts::Matches) -> Result<Behaviour, i32> {
         "~".to_owned()
     };
 
+    let compare = matches.opt_present("compare");
+    if compare && specified_mode.map(|mode| mode & 0o7000 != 0).unwrap_or(false) {
+        show_warning!("the --compare (-C) option is ignored when you specify a mode with non-permission bits");
+    }
+
     Ok(Behaviour {
         main_function: main_function,
+        compare: compare,
         specified_mode: specified_mode,
         suffix: backup_suffix,
         verbose: matches.opt_present("v"),
@@ -326,28 +334,28 @@ fn standard(paths: &[PathBuf], b: Behaviour) -> i32 {
 ///
 fn copy_files_into_dir(files: &[PathBuf], target_dir: &PathBuf, b: &Behaviour) -> i32 {
     if !target_dir.is_dir() {
-        show_error!("target ‘{}’ is not a directory", target_dir.display());
+        show_error!("target {} is not a directory", quote_path(target_dir));
         return 1;
     }
 
-    let mut all_successful = true;
+    let mut status = 0;
     for sourcepath in files.iter() {
         let targetpath = match sourcepath.as_os_str().to_str() {
             Some(name) => target_dir.join(name),
             None => {
-                show_error!("cannot stat ‘{}’: No such file or directory",
-                            sourcepath.display());
+                show_error!("cannot stat {}: No such file or directory",
+                            quote_path(sourcepath));
 
-                all_successful = false;
+                status = cmp::max(status, 1);
                 continue;
             }
         };
 
-        if copy(sourcepath, &targetpath, b).is_err() {
-            all_successful = false;
+        if let Err(code) = copy(sourcepath, &targetpath, b) {
+            status = cmp::max(status, code);
         }
     };
-    if all_successful { 0 } else { 1 }
+    status
 }
 
 /// Copy a file to another file.
@@ -361,7 +369,10 @@ fn copy_files_into_dir(files: &[PathBuf], target_dir: &PathBuf, b: &Behaviour) -
 /// _target_ must be a non-directory
 ///
 fn copy_file_to_file(file: &PathBuf, target: &PathBuf, b: &Behaviour) -> i32 {
-    if copy(file, &target, b).is_err() { 1 } else { 0 }
+    match copy(file, &target, b) {
+        Ok(()) => 0,
+        Err(code) => code,
+    }
 }
 
 /// Copy one file to a new location, changing metadata.
@@ -373,19 +384,29 @@ fn copy_file_to_file(file: &PathBuf, target: &PathBuf, b: &Behaviour) -> i32 {
 ///
 /// # Errors
 ///
-/// If the copy system call fails, we print a verbose error and return an empty error value.
+/// If the copy system call fails, we print a verbose error and return the
+/// status to exit with.
 ///
-fn copy(from: &PathBuf, to: &PathBuf, b: &Behaviour) -> Result<(), ()> {
-    let io_result = fs::copy(from, to);
+fn copy(from: &PathBuf, to: &PathBuf, b: &Behaviour) -> Result<(), i32> {
+    if b.compare && !need_copy(from, to, b) {
+        return Ok(());
+    }
+
+    let io_result = copy::copy_file(from, to);
 
     if let Err(err) = io_result {
-        show_error!("install: cannot install ‘{}’ to ‘{}’: {}",
-                    from.display(), to.display(), err);
-        return Err(());
+        if copy::PartialCopy::of(&err).is_some() {
+            // which file failed is already said
+            show_error!("{}", err);
+        } else {
+            show_error!("install: cannot install {} to {}: {}",
+                        quote_path(from), quote_path(to), err);
+        }
+        return Err(copy::exit_status(&err).unwrap_or(1));
     }
 
     if mode::chmod(&to, b.mode()).is_err() {
-        return Err(());
+        return Err(1);
     }
 
     if b.verbose {
@@ -394,3 +415,31 @@ fn copy(from: &PathBuf, to: &PathBuf, b: &Behaviour) -> Result<(), ()> {
 
     Ok(())
 }
+
+/// Whether `to` has to be replaced for --compare: anything but a regular file
+/// with the contents of `from` and the mode and ownership it would be given.
+///
+fn need_copy(from: &Path, to: &Path, b: &Behaviour) -> bool {
+    let (from_meta, to_meta) = match (fs::metadata(from), fs::symlink_metadata(to)) {
+        (Ok(f), Ok(t)) => (f, t),
+        _ => return true,
+    };
+    if !from_meta.is_file() || !to_meta.is_file() || b.mode() & 0o7000 != 0 {
+        return true;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        if to_meta.mode() & 0o7777 != b.mode() ||
+           to_meta.uid() != unsafe { libc::geteuid() } ||
+           to_meta.gid() != unsafe { libc::getegid() } {
+            return true;
+        }
+    }
+
+    match compare::same_contents(from, to) {
+        Ok(same) => !same,
+        Err(_) => true,
+    }
+}
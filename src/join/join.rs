@@ -15,9 +15,11 @@ extern crate clap;
 extern crate uucore;
 
 use std::fs::File;
-use std::io::{BufRead, BufReader, Lines, Stdin, stdin};
+use std::io::{BufRead, BufReader, Stdin, Write, stdin, stdout};
 use std::cmp::Ordering;
 use clap::{App, Arg};
+use uucore::lines::{self, LineEnding, Lines};
+use uucore::parse_number::NumberArg;
 
 static NAME: &'static str = "join";
 static VERSION: &'static str = env!("CARGO_PKG_VERSION");
@@ -42,6 +44,10 @@ struct Settings {
     print_unpaired: FileNum,
     ignore_case: bool,
     separator: Sep,
+    line_ending: LineEnding,
+    out_ending: LineEnding,
+    decompress: bool,
+    stats: bool,
 }
 
 impl Default for Settings {
@@ -52,23 +58,52 @@ impl Default for Settings {
             print_unpaired: FileNum::None,
             ignore_case: false,
             separator: Sep::Whitespaces,
+            line_ending: LineEnding::native(),
+            out_ending: LineEnding::native(),
+            decompress: false,
+            stats: false,
         }
     }
 }
 
+/// What the merge found, as `--stats` reports it.
+#[derive(Default)]
+struct Stats {
+    /// lines written for pairs of lines with the same key
+    paired: u64,
+    /// keys paired while more than one line in either file has them
+    duplicate_keys: u64,
+}
+
 struct Line {
     fields: Vec<String>,
+    /// The join field in upper case, which orders it as GNU join does, folded
+    /// once here rather than on every comparison when ignoring case.
+    folded_key: Option<String>,
 }
 
 impl Line {
-    fn new(string: String, separator: Sep) -> Line {
-        let fields = match separator {
+    fn new(string: String, separator: Sep, key: usize, ignore_case: bool) -> Line {
+        let fields: Vec<String> = match separator {
             Sep::Whitespaces => string.split_whitespace().map(String::from).collect(),
             Sep::Char(sep) => string.split(sep).map(String::from).collect(),
             Sep::Line => vec![string],
         };
+        let folded_key = if ignore_case {
+            Some(fields.get(key).map_or(String::new(), |field| field.to_uppercase()))
+        } else {
+            None
+        };
+
+        Line { fields, folded_key }
+    }
 
-        Line { fields }
+    /// Get the join field at index as it is compared.
+    fn get_key(&self, index: usize) -> &str {
+        match self.folded_key {
+            Some(ref key) => key,
+            None => self.get_field(index),
+        }
     }
 
     /// Get field at index.
@@ -81,10 +116,10 @@ impl Line {
     }
 
     /// Print each field except the one at the index.
-    fn print_fields(&self, index: usize, separator: char) {
+    fn print_fields<W: Write>(&self, index: usize, separator: char, out: &mut lines::Writer<W>) {
         for i in 0..self.fields.len() {
             if i != index {
-                print!("{}{}", separator, self.fields[i]);
+                crash_if_err!(1, write!(out, "{}{}", separator, self.fields[i]));
             }
         }
     }
@@ -93,12 +128,21 @@ impl Line {
 struct State<'a> {
     key: usize,
     print_unpaired: bool,
+    ignore_case: bool,
     lines: Lines<Box<BufRead + 'a>>,
     seq: Vec<Line>,
+    unpaired: u64,
 }
 
 impl<'a> State<'a> {
-    fn new(name: &str, stdin: &'a Stdin, key: usize, print_unpaired: bool) -> State<'a> {
+    fn new(name: &str,
+           stdin: &'a Stdin,
+           key: usize,
+           print_unpaired: bool,
+           ignore_case: bool,
+           line_ending: LineEnding,
+           decompress: bool)
+           -> State<'a> {
         let f = if name == "-" {
             Box::new(stdin.lock()) as Box<BufRead>
         } else {
@@ -107,28 +151,32 @@ impl<'a> State<'a> {
                 Err(err) => crash!(1, "{}: {}", name, err),
             }
         };
+        let f = if decompress { decompressed(name, f) } else { f };
 
         State {
             key: key,
             print_unpaired: print_unpaired,
-            lines: f.lines(),
+            ignore_case: ignore_case,
+            lines: lines::lines(f, line_ending),
             seq: Vec::new(),
+            unpaired: 0,
         }
     }
 
     /// Compare the key fields of the two current lines.
-    fn compare(&self, other: &State, ignore_case: bool) -> Ordering {
-        let key1 = self.seq[0].get_field(self.key);
-        let key2 = other.seq[0].get_field(other.key);
+    fn compare(&self, other: &State) -> Ordering {
+        let key1 = self.seq[0].get_key(self.key);
+        let key2 = other.seq[0].get_key(other.key);
 
-        compare(key1, key2, ignore_case)
+        key1.cmp(key2)
     }
 
     /// Skip the current unpaired line.
-    fn skip_line(&mut self, read_sep: Sep, write_sep: char) {
+    fn skip_line<W: Write>(&mut self, read_sep: Sep, write_sep: char, out: &mut lines::Writer<W>) {
         if self.print_unpaired {
-            self.print_unpaired_line(&self.seq[0], write_sep);
+            self.print_unpaired_line(&self.seq[0], write_sep, out);
         }
+        self.unpaired += 1;
 
         match self.read_line(read_sep) {
             Some(line) => self.seq[0] = line,
@@ -138,13 +186,9 @@ impl<'a> State<'a> {
 
     /// Keep reading line sequence until the key does not change, return
     /// the first line whose key differs.
-    fn extend(&mut self, read_sep: Sep, ignore_case: bool) -> Option<Line> {
+    fn extend(&mut self, read_sep: Sep) -> Option<Line> {
         while let Some(line) = self.read_line(read_sep) {
-            let diff = compare(
-                self.seq[0].get_field(self.key),
-                line.get_field(self.key),
-                ignore_case,
-            );
+            let diff = self.seq[0].get_key(self.key).cmp(line.get_key(self.key));
 
             if diff == Ordering::Equal {
                 self.seq.push(line);
@@ -157,15 +201,19 @@ impl<'a> State<'a> {
     }
 
     /// Combine two line sequences.
-    fn combine(&self, other: &State, write_sep: char) {
-        let key = self.seq[0].get_field(self.key);
-
+    fn combine<W: Write>(&self, other: &State, write_sep: char, stats: &mut Stats, out: &mut lines::Writer<W>) {
+        stats.paired += (self.seq.len() * other.seq.len()) as u64;
+        if self.seq.len() > 1 || other.seq.len() > 1 {
+            stats.duplicate_keys += 1;
+        }
         for line1 in &self.seq {
+            // with -i, the keys of a sequence may differ in case
+            let key = line1.get_field(self.key);
             for line2 in &other.seq {
-                print!("{}", key);
-                line1.print_fields(self.key, write_sep);
-                line2.print_fields(other.key, write_sep);
-                println!();
+                crash_if_err!(1, write!(out, "{}", key));
+                line1.print_fields(self.key, write_sep, out);
+                line2.print_fields(other.key, write_sep, out);
+                crash_if_err!(1, out.end_line());
             }
         }
     }
@@ -189,32 +237,40 @@ impl<'a> State<'a> {
         }
     }
 
-    fn finalize(&mut self, read_sep: Sep, write_sep: char) {
-        if self.has_line() && self.print_unpaired {
-            self.print_unpaired_line(&self.seq[0], write_sep);
+    /// Print the lines left once the other file has run out, or only count
+    /// them if `count` is set.
+    fn finalize<W: Write>(&mut self, read_sep: Sep, write_sep: char, count: bool, out: &mut lines::Writer<W>) {
+        if self.has_line() && (self.print_unpaired || count) {
+            if self.print_unpaired {
+                self.print_unpaired_line(&self.seq[0], write_sep, out);
+            }
+            self.unpaired += 1;
 
             while let Some(line) = self.read_line(read_sep) {
-                self.print_unpaired_line(&line, write_sep);
+                if self.print_unpaired {
+                    self.print_unpaired_line(&line, write_sep, out);
+                }
+                self.unpaired += 1;
             }
         }
     }
 
     fn read_line(&mut self, sep: Sep) -> Option<Line> {
         match self.lines.next() {
-            Some(value) => Some(Line::new(crash_if_err!(1, value), sep)),
+            Some(value) => Some(Line::new(crash_if_err!(1, value), sep, self.key, self.ignore_case)),
             None => None,
         }
     }
 
-    fn print_unpaired_line(&self, line: &Line, sep: char) {
-        print!("{}", line.get_field(self.key));
-        line.print_fields(self.key, sep);
-        println!();
+    fn print_unpaired_line<W: Write>(&self, line: &Line, sep: char, out: &mut lines::Writer<W>) {
+        crash_if_err!(1, write!(out, "{}", line.get_field(self.key)));
+        line.print_fields(self.key, sep, out);
+        crash_if_err!(1, out.end_line());
     }
 }
 
 pub fn uumain(args: Vec<String>) -> i32 {
-    let matches = App::new(NAME)
+    let app = App::new(NAME)
         .version(VERSION)
         .about(
             "For each pair of input lines with identical join fields, write a line to
@@ -254,6 +310,25 @@ FILENUM is 1 or 2, corresponding to FILE1 or FILE2"))
             .takes_value(true)
             .value_name("FIELD")
             .help("join on this FIELD of file 2"))
+        .arg(Arg::with_name("crlf")
+            .long("crlf")
+            .help("lines end in carriage return and newline, as in Windows text files"))
+        .arg(Arg::with_name("z")
+            .short("z")
+            .long("zero-terminated")
+            .conflicts_with("crlf")
+            .help("line delimiter is NUL, not newline"))
+        .arg(Arg::with_name("output-terminator")
+            .long("output-terminator")
+            .takes_value(true)
+            .value_name("NAME")
+            .possible_values(&["newline", "crlf", "nul"])
+            .help("end output lines with NAME rather than as input lines end"))
+        .arg(Arg::with_name("stats")
+            .long("stats")
+            .help("when done, report on standard error how many lines were paired and
+unpaired in each file, and how many keys were paired more
+than once (uutils extension)"))
         .arg(Arg::with_name("file1")
             .required(true)
             .value_name("FILE1")
@@ -261,8 +336,12 @@ FILENUM is 1 or 2, corresponding to FILE1 or FILE2"))
         .arg(Arg::with_name("file2")
             .required(true)
             .value_name("FILE2")
-            .hidden(true))
-        .get_matches_from(args);
+            .hidden(true));
+    #[cfg(feature = "decompress")]
+    let app = app.arg(Arg::with_name("decompress")
+        .long("decompress")
+        .help("decompress gzip and zstd compressed input"));
+    let matches = app.get_matches_from(args);
 
     let keys = parse_field_number(matches.value_of("j"));
     let key1 = parse_field_number(matches.value_of("1"));
@@ -280,6 +359,17 @@ FILENUM is 1 or 2, corresponding to FILE1 or FILE2"))
         None => FileNum::None,
     };
     settings.ignore_case = matches.is_present("i");
+    settings.line_ending = if matches.is_present("z") {
+        LineEnding::Nul
+    } else {
+        LineEnding::from_crlf(matches.is_present("crlf"))
+    };
+    settings.out_ending = match matches.value_of("output-terminator") {
+        Some(name) => LineEnding::from_name(name).unwrap(),
+        None => settings.line_ending,
+    };
+    settings.decompress = matches.is_present("decompress");
+    settings.stats = matches.is_present("stats");
     settings.key1 = get_field_number(keys, key1);
     settings.key2 = get_field_number(keys, key2);
 
@@ -309,6 +399,9 @@ fn exec(file1: &str, file2: &str, settings: &Settings) -> i32 {
         &stdin,
         settings.key1,
         settings.print_unpaired == FileNum::File1,
+        settings.ignore_case,
+        settings.line_ending,
+        settings.decompress,
     );
 
     let mut state2 = State::new(
@@ -316,6 +409,9 @@ fn exec(file1: &str, file2: &str, settings: &Settings) -> i32 {
         &stdin,
         settings.key2,
         settings.print_unpaired == FileNum::File2,
+        settings.ignore_case,
+        settings.line_ending,
+        settings.decompress,
     );
 
     let write_sep = match settings.separator {
@@ -323,24 +419,29 @@ fn exec(file1: &str, file2: &str, settings: &Settings) -> i32 {
         _ => ' ',
     };
 
+    let stdout = stdout();
+    let mut out = lines::Writer::new(stdout.lock(), settings.out_ending);
+
+    let mut stats = Stats::default();
+
     state1.initialize(settings.separator);
     state2.initialize(settings.separator);
 
     while state1.has_line() && state2.has_line() {
-        let diff = state1.compare(&state2, settings.ignore_case);
+        let diff = state1.compare(&state2);
 
         match diff {
             Ordering::Less => {
-                state1.skip_line(settings.separator, write_sep);
+                state1.skip_line(settings.separator, write_sep, &mut out);
             }
             Ordering::Greater => {
-                state2.skip_line(settings.separator, write_sep);
+                state2.skip_line(settings.separator, write_sep, &mut out);
             }
             Ordering::Equal => {
-                let next_line1 = state1.extend(settings.separator, settings.ignore_case);
-                let next_line2 = state2.extend(settings.separator, settings.ignore_case);
+                let next_line1 = state1.extend(settings.separator);
+                let next_line2 = state2.extend(settings.separator);
 
-                state1.combine(&state2, write_sep);
+                state1.combine(&state2, write_sep, &mut stats, &mut out);
 
                 state1.reset(next_line1);
                 state2.reset(next_line2);
@@ -348,8 +449,16 @@ fn exec(file1: &str, file2: &str, settings: &Settings) -> i32 {
         }
     }
 
-    state1.finalize(settings.separator, write_sep);
-    state2.finalize(settings.separator, write_sep);
+    state1.finalize(settings.separator, write_sep, settings.stats, &mut out);
+    state2.finalize(settings.separator, write_sep, settings.stats, &mut out);
+
+    if settings.stats {
+        crash_if_err!(1, out.flush());
+        show_info!("paired lines: {}", stats.paired);
+        show_info!("unpaired lines in {}: {}", file1, state1.unpaired);
+        show_info!("unpaired lines in {}: {}", file2, state2.unpaired);
+        show_info!("duplicate-key groups: {}", stats.duplicate_keys);
+    }
 
     0
 }
@@ -374,22 +483,30 @@ fn get_field_number(keys: Option<usize>, key: Option<usize>) -> usize {
 }
 
 /// Parse the specified field string as a natural number and return it.
+/// Field numbers too large to be stored count as the largest there is.
 fn parse_field_number(value: Option<&str>) -> Option<usize> {
+    let field = NumberArg::new("field number").saturate(true);
     match value {
         Some(value) => {
-            match value.parse() {
-                Ok(result) if result > 0 => Some(result),
-                _ => crash!(1, "invalid field number: '{}'", value),
+            match field.parse_usize(value) {
+                Ok(0) => crash!(1, "{}", field.invalid(value)),
+                Ok(result) => Some(result),
+                Err(msg) => crash!(1, "{}", msg),
             }
         }
         None => None,
     }
 }
 
-fn compare(field1: &str, field2: &str, ignore_case: bool) -> Ordering {
-    if ignore_case {
-        field1.to_lowercase().cmp(&field2.to_lowercase())
-    } else {
-        field1.cmp(field2)
+#[cfg(feature = "decompress")]
+fn decompressed<'a>(name: &str, input: Box<BufRead + 'a>) -> Box<BufRead + 'a> {
+    match uucore::decompress::decoder(input) {
+        Ok(decoded) => decoded,
+        Err(err) => crash!(1, "{}: {}", name, err),
     }
 }
+
+#[cfg(not(feature = "decompress"))]
+fn decompressed<'a>(_: &str, input: Box<BufRead + 'a>) -> Box<BufRead + 'a> {
+    input
+}
@@ -15,12 +15,25 @@ extern crate libc;
 extern crate uucore;
 
 use libc::{c_int, pid_t};
+use std::cmp;
 use std::io::Error;
-use uucore::signals::ALL_SIGNALS;
+use std::thread;
+use std::time::{Duration, Instant};
+use uucore::signals::{ALL_SIGNALS, DEFAULT_SIGNAL};
 
 static SYNTAX: &'static str = "[options] <pid> [...]";
 static SUMMARY: &'static str = "";
-static LONG_HELP: &'static str = "";
+static LONG_HELP: &'static str = "
+ Signal 0 sends nothing but still checks that each process exists and may
+ be signalled, so the exit status tells whether all of them do.
+
+ With --timeout, kill waits up to DURATION for the processes it signalled
+ to go away, and sends the KILL signal to those still there after it.
+ DURATION is a number of seconds, or a number followed by 's', 'm', 'h'
+ or 'd' as for timeout(1).";
+
+// how often to check whether the processes signalled have gone
+const POLL_INTERVAL_MS: u64 = 20;
 
 static EXIT_OK:  i32 = 0;
 static EXIT_ERR: i32 = 1;
@@ -38,6 +51,7 @@ pub fn uumain(args: Vec<String>) -> i32 {
         .optopt("s", "signal", "specify the <signal> to be sent", "SIGNAL")
         .optflagopt("l", "list", "list all signal names, or convert one to a name", "LIST")
         .optflag("L", "table", "list all signal names in a nice table")
+        .optopt("", "timeout", "send the KILL signal to processes still running DURATION after the first signal", "DURATION")
         .parse(args);
 
     let mode = if matches.opt_present("table") {
@@ -49,7 +63,14 @@ pub fn uumain(args: Vec<String>) -> i32 {
     };
 
     match mode {
-        Mode::Kill    => return kill(&matches.opt_str("signal").unwrap_or(obs_signal.unwrap_or("9".to_owned())), matches.free),
+        Mode::Kill    => {
+            let timeout = matches.opt_str("timeout").map(|duration| match uucore::parse_time::from_str(&duration) {
+                Ok(duration) => duration,
+                Err(e) => crash!(EXIT_ERR, "{}", e),
+            });
+            let signal = matches.opt_str("signal").or(obs_signal).unwrap_or(DEFAULT_SIGNAL.to_string());
+            return kill(&signal, matches.free, timeout)
+        }
         Mode::Table   => table(),
         Mode::List    => list(matches.opt_str("list")),
     }
@@ -133,23 +154,59 @@ fn list(arg: Option<String>) {
     };
 }
 
-fn kill(signalname: &str, pids: std::vec::Vec<String>) -> i32 {
+fn kill(signalname: &str, pids: std::vec::Vec<String>, timeout: Option<Duration>) -> i32 {
     let mut status = 0;
     let optional_signal_value = uucore::signals::signal_by_name_or_value(signalname);
     let signal_value = match optional_signal_value {
         Some(x) => x,
         None => crash!(EXIT_ERR, "unknown signal name {}", signalname)
     };
+    let mut signalled = vec![];
     for pid in &pids {
         match pid.parse::<usize>() {
             Ok(x) => {
                 if unsafe { libc::kill(x as pid_t, signal_value as c_int) } != 0 {
-                    show_error!("{}", Error::last_os_error());
+                    show_error!("{}: {}", pid, Error::last_os_error());
                     status = 1;
+                } else {
+                    signalled.push(x as pid_t);
                 }
             },
             Err(e) => crash!(EXIT_ERR, "failed to parse argument {}: {}", pid, e)
         };
     }
+    match timeout {
+        // signal 0 leaves nothing to wait for, and KILL nothing to escalate to
+        Some(timeout) if signal_value != 0 && signal_value != libc::SIGKILL as usize => {
+            escalate(signalled, timeout).max(status)
+        }
+        _ => status,
+    }
+}
+
+/// Wait up to `timeout` for the processes in `pids` to exit, then send
+/// KILL to any still running.
+fn escalate(mut pids: Vec<pid_t>, timeout: Duration) -> i32 {
+    let start = Instant::now();
+    loop {
+        pids.retain(|&pid| unsafe { libc::kill(pid, 0) } == 0);
+        let elapsed = start.elapsed();
+        if pids.is_empty() || elapsed >= timeout {
+            break;
+        }
+        thread::sleep(cmp::min(timeout - elapsed, Duration::from_millis(POLL_INTERVAL_MS)));
+    }
+
+    let mut status = 0;
+    for pid in pids {
+        if unsafe { libc::kill(pid, libc::SIGKILL) } != 0 {
+            let err = Error::last_os_error();
+            // it may have gone since it was last looked for
+            if err.raw_os_error() != Some(libc::ESRCH) {
+                show_error!("{}: {}", pid, err);
+                status = 1;
+            }
+        }
+    }
     status
 }
@@ -10,11 +10,19 @@
  */
 
 
+#[cfg(unix)]
+extern crate libc;
 #[macro_use]
 extern crate uucore;
 
+use uucore::backup::{self, BackupMode};
+
+#[cfg(unix)]
+use std::ffi::CString;
 use std::fs;
-use std::io::{BufRead, BufReader, Result, stdin};
+use std::io::{BufRead, BufReader, Error, ErrorKind, Result, stdin};
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
 #[cfg(unix)] use std::os::unix::fs::symlink;
 #[cfg(windows)] use std::os::windows::fs::{symlink_file,symlink_dir};
 use std::path::{Path, PathBuf};
@@ -30,6 +38,9 @@ static LONG_HELP: &'static str = "
  When creating hard links, each TARGET must exist.  Symbolic links
  can hold arbitrary text; if later resolved, a relative link is
  interpreted in relation to its parent directory.
+
+ Using -s ignores -L and -P.  Otherwise, the last option specified controls
+ behavior when a TARGET is a symbolic link, defaulting to -P.
 ";
 
 pub struct Settings {
@@ -37,6 +48,7 @@ pub struct Settings {
     backup: BackupMode,
     suffix: String,
     symbolic: bool,
+    logical: bool,
     target_dir: Option<String>,
     no_target_dir: bool,
     verbose: bool,
@@ -49,14 +61,6 @@ pub enum OverwriteMode {
     Force,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub enum BackupMode {
-    NoBackup,
-    SimpleBackup,
-    NumberedBackup,
-    ExistingBackup,
-}
-
 pub fn uumain(args: Vec<String>) -> i32 {
     let syntax = format!("[OPTION]... [-T] TARGET LINK_NAME   (1st form)
  {0} [OPTION]... TARGET                  (2nd form)
@@ -68,9 +72,9 @@ pub fn uumain(args: Vec<String>) -> i32 {
     // TODO: opts.optflag("d", "directory", "allow users with appropriate privileges to attempt to make hard links to directories");
         .optflag("f", "force", "remove existing destination files")
         .optflag("i", "interactive", "prompt whether to remove existing destination files")
-    // TODO: opts.optflag("L", "logical", "dereference TARGETs that are symbolic links");
+        .optflag("L", "logical", "dereference TARGETs that are symbolic links")
     // TODO: opts.optflag("n", "no-dereference", "treat LINK_NAME as a normal file if it is a symbolic link to a directory");
-    // TODO: opts.optflag("P", "physical", "make hard links directly to symbolic links");
+        .optflag("P", "physical", "make hard links directly to symbolic links")
     // TODO: opts.optflag("r", "relative", "create symbolic links relative to link location");
         .optflag("s", "symbolic", "make symbolic links instead of hard links")
         .optopt("S", "suffix", "override the usual backup suffix", "SUFFIX")
@@ -87,40 +91,25 @@ pub fn uumain(args: Vec<String>) -> i32 {
         OverwriteMode::NoClobber
     };
 
-    let backup_mode = if matches.opt_present("b") {
-        BackupMode::ExistingBackup
-    } else if matches.opt_present("backup") {
-        match matches.opt_str("backup") {
-            None => BackupMode::ExistingBackup,
-            Some(mode) => match &mode[..] {
-                "simple" | "never" => BackupMode::SimpleBackup,
-                "numbered" | "t"   => BackupMode::NumberedBackup,
-                "existing" | "nil" => BackupMode::ExistingBackup,
-                "none" | "off"     => BackupMode::NoBackup,
-                x => {
-                    show_error!("invalid argument '{}' for 'backup method'\n\
-                                Try '{} --help' for more information.", x, NAME);
-                    return 1;
-                }
-            }
+    // a suffix alone asks for backups as well, as it does in GNU ln
+    let backup_mode = match matches.opt_str("backup") {
+        Some(control) => BackupMode::parse(&control),
+        None if matches.opt_present("backup") || matches.opt_present("b") || matches.opt_present("suffix") => {
+            BackupMode::from_env()
         }
-    } else {
-        BackupMode::NoBackup
+        None => Ok(BackupMode::NoBackup),
     };
-
-    let backup_suffix = if matches.opt_present("suffix") {
-        match matches.opt_str("suffix") {
-            Some(x) => x,
-            None => {
-                show_error!("option '--suffix' requires an argument\n\
-                            Try '{} --help' for more information.", NAME);
-                return 1;
-            }
+    let backup_mode = match backup_mode {
+        Ok(mode) => mode,
+        Err(msg) => {
+            show_error!("{}\n\
+                        Try '{} --help' for more information.", msg, NAME);
+            return 1;
         }
-    } else {
-        "~".to_owned()
     };
 
+    let backup_suffix = backup::suffix(matches.opt_str("suffix").as_ref().map(|s| &s[..]));
+
     if matches.opt_present("T") && matches.opt_present("t") {
         show_error!("cannot combine --target-directory (-t) and --no-target-directory (-T)");
         return 1;
@@ -131,6 +120,8 @@ pub fn uumain(args: Vec<String>) -> i32 {
         backup: backup_mode,
         suffix: backup_suffix,
         symbolic: matches.opt_present("s"),
+        // whichever of -L and -P comes last
+        logical: matches.opt_positions("L").into_iter().max() > matches.opt_positions("P").into_iter().max(),
         target_dir: matches.opt_str("t"),
         no_target_dir: matches.opt_present("T"),
         verbose: matches.opt_present("v"),
@@ -226,35 +217,26 @@ fn link(src: &PathBuf, dst: &PathBuf, settings: &Settings) -> Result<()> {
     let mut backup_path = None;
 
     if is_symlink(dst) || dst.exists() {
-        match settings.overwrite {
-            OverwriteMode::NoClobber => {},
-            OverwriteMode::Interactive => {
-                print!("{}: overwrite '{}'? ", NAME, dst.display());
-                if !read_yes() {
-                    return Ok(());
-                }
-                try!(fs::remove_file(dst))
-            },
-            OverwriteMode::Force => {
-                try!(fs::remove_file(dst))
+        if settings.overwrite == OverwriteMode::Interactive {
+            print!("{}: overwrite '{}'? ", NAME, dst.display());
+            if !read_yes() {
+                return Ok(());
             }
-        };
+        }
 
-        backup_path = match settings.backup {
-            BackupMode::NoBackup => None,
-            BackupMode::SimpleBackup => Some(simple_backup_path(dst, &settings.suffix)),
-            BackupMode::NumberedBackup => Some(numbered_backup_path(dst)),
-            BackupMode::ExistingBackup => Some(existing_backup_path(dst, &settings.suffix))
-        };
-        if let Some(ref p) = backup_path {
-            try!(fs::rename(dst, p));
+        // the backup takes it out of the way, else it is removed if asked
+        backup_path = backup::backup_path(dst, settings.backup, &settings.suffix);
+        match backup_path {
+            Some(ref p) => try!(fs::rename(dst, p)),
+            None if settings.overwrite != OverwriteMode::NoClobber => try!(fs::remove_file(dst)),
+            None => {}
         }
     }
 
     if settings.symbolic {
         try!(symlink(src, dst));
     } else {
-        try!(fs::hard_link(src, dst));
+        try!(hard_link(src, dst, settings.logical));
     }
 
     if settings.verbose {
@@ -267,6 +249,33 @@ fn link(src: &PathBuf, dst: &PathBuf, settings: &Settings) -> Result<()> {
     Ok(())
 }
 
+/// Make `dst` a hard link to `src`, or with `follow`, to whatever `src` is
+/// a symbolic link to.
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "macos", target_os = "freebsd",
+          target_os = "netbsd", target_os = "openbsd"))]
+fn hard_link(src: &Path, dst: &Path, follow: bool) -> Result<()> {
+    let cstring = |path: &Path| CString::new(path.as_os_str().as_bytes()).map_err(|e| Error::new(ErrorKind::InvalidInput, e));
+    let (src, dst) = (try!(cstring(src)), try!(cstring(dst)));
+    let flags = if follow { libc::AT_SYMLINK_FOLLOW } else { 0 };
+    if unsafe { libc::linkat(libc::AT_FDCWD, src.as_ptr(), libc::AT_FDCWD, dst.as_ptr(), flags) } == 0 {
+        Ok(())
+    } else {
+        Err(Error::last_os_error())
+    }
+}
+
+// Without linkat, -L resolves the link beforehand, and -P gets whatever
+// link(2) does with a symbolic link here.
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos", target_os = "freebsd",
+              target_os = "netbsd", target_os = "openbsd")))]
+fn hard_link(src: &Path, dst: &Path, follow: bool) -> Result<()> {
+    if follow && is_symlink(src) {
+        fs::hard_link(try!(fs::canonicalize(src)), dst)
+    } else {
+        fs::hard_link(src, dst)
+    }
+}
+
 fn read_yes() -> bool {
     let mut s = String::new();
     match BufReader::new(stdin()).read_line(&mut s) {
@@ -278,31 +287,6 @@ fn read_yes() -> bool {
     }
 }
 
-fn simple_backup_path(path: &PathBuf, suffix: &str) -> PathBuf {
-    let mut p = path.as_os_str().to_str().unwrap().to_owned();
-    p.push_str(suffix);
-    PathBuf::from(p)
-}
-
-fn numbered_backup_path(path: &PathBuf) -> PathBuf {
-    let mut i: u64 = 1;
-    loop {
-        let new_path = simple_backup_path(path, &format!(".~{}~", i));
-        if !new_path.exists() {
-            return new_path;
-        }
-        i += 1;
-    }
-}
-
-fn existing_backup_path(path: &PathBuf, suffix: &str) -> PathBuf {
-    let test_path = simple_backup_path(path, &".~1~".to_owned());
-    if test_path.exists() {
-        return numbered_backup_path(path);
-    }
-    simple_backup_path(path, suffix)
-}
-
 #[cfg(windows)]
 pub fn symlink<P: AsRef<Path>>(src: P, dst: P) -> Result<()> {
     if src.as_ref().is_dir()
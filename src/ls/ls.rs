@@ -9,12 +9,10 @@
 //
 
 extern crate getopts;
-extern crate pretty_bytes;
 extern crate termsize;
 extern crate term_grid;
 extern crate time;
 extern crate unicode_width;
-use pretty_bytes::converter::convert;
 use term_grid::{Grid, GridOptions, Direction, Filling, Cell};
 use time::{Timespec, strftime};
 
@@ -23,22 +21,28 @@ extern crate lazy_static;
 
 #[macro_use]
 extern crate uucore;
+use uucore::clock::{to_timespec, Clock, SystemClock};
+use uucore::fnmatch::{fnmatch, Flags};
+#[cfg(unix)]
+use uucore::lscolors::LsColors;
+use uucore::quoting::{quote, quote_as, quote_path, QuotingStyle};
+use uucore::size::BlockSize;
+use uucore::version_cmp::version_cmp;
 #[cfg(unix)]
 use uucore::libc::{S_ISUID, S_ISGID, S_ISVTX, S_IRUSR, S_IWUSR, S_IXUSR, S_IRGRP, S_IWGRP, S_IXGRP,
                    S_IROTH, S_IWOTH, S_IXOTH, mode_t};
 
+use std::env;
 use std::fs;
 use std::fs::{DirEntry, FileType, Metadata};
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
-use std::cmp::Reverse;
-#[cfg(unix)]
-use std::collections::HashMap;
+use std::cmp::{self, Ordering};
 
 #[cfg(any(unix, target_os = "redox"))]
 use std::os::unix::fs::MetadataExt;
 #[cfg(unix)]
 use std::os::unix::fs::FileTypeExt;
-#[cfg(unix)]
 use unicode_width::UnicodeWidthStr;
 
 #[cfg(windows)]
@@ -52,27 +56,15 @@ static LONG_HELP: &'static str = "
  whose names start with '.'
 ";
 
-#[cfg(unix)]
-static DEFAULT_COLORS: &'static str = "rs=0:di=01;34:ln=01;36:mh=00:pi=40;33:so=01;35:do=01;35:bd=40;33;01:cd=40;33;01:or=40;31;01:mi=00:su=37;41:sg=30;43:ca=30;41:tw=30;42:ow=34;42:st=37;44:ex=01;32:*.tar=01;31:*.tgz=01;31:*.arc=01;31:*.arj=01;31:*.taz=01;31:*.lha=01;31:*.lz4=01;31:*.lzh=01;31:*.lzma=01;31:*.tlz=01;31:*.txz=01;31:*.tzo=01;31:*.t7z=01;31:*.zip=01;31:*.z=01;31:*.Z=01;31:*.dz=01;31:*.gz=01;31:*.lrz=01;31:*.lz=01;31:*.lzo=01;31:*.xz=01;31:*.bz2=01;31:*.bz=01;31:*.tbz=01;31:*.tbz2=01;31:*.tz=01;31:*.deb=01;31:*.rpm=01;31:*.jar=01;31:*.war=01;31:*.ear=01;31:*.sar=01;31:*.rar=01;31:*.alz=01;31:*.ace=01;31:*.zoo=01;31:*.cpio=01;31:*.7z=01;31:*.rz=01;31:*.cab=01;31:*.jpg=01;35:*.jpeg=01;35:*.gif=01;35:*.bmp=01;35:*.pbm=01;35:*.pgm=01;35:*.ppm=01;35:*.tga=01;35:*.xbm=01;35:*.xpm=01;35:*.tif=01;35:*.tiff=01;35:*.png=01;35:*.svg=01;35:*.svgz=01;35:*.mng=01;35:*.pcx=01;35:*.mov=01;35:*.mpg=01;35:*.mpeg=01;35:*.m2v=01;35:*.mkv=01;35:*.webm=01;35:*.ogm=01;35:*.mp4=01;35:*.m4v=01;35:*.mp4v=01;35:*.vob=01;35:*.qt=01;35:*.nuv=01;35:*.wmv=01;35:*.asf=01;35:*.rm=01;35:*.rmvb=01;35:*.flc=01;35:*.avi=01;35:*.fli=01;35:*.flv=01;35:*.gl=01;35:*.dl=01;35:*.xcf=01;35:*.xwd=01;35:*.yuv=01;35:*.cgm=01;35:*.emf=01;35:*.ogv=01;35:*.ogx=01;35:*.aac=00;36:*.au=00;36:*.flac=00;36:*.m4a=00;36:*.mid=00;36:*.midi=00;36:*.mka=00;36:*.mp3=00;36:*.mpc=00;36:*.ogg=00;36:*.ra=00;36:*.wav=00;36:*.oga=00;36:*.opus=00;36:*.spx=00;36:*.xspf=00;36:";
-
 #[cfg(unix)]
 lazy_static! {
-    static ref LS_COLORS: String = std::env::var("LS_COLORS").unwrap_or(DEFAULT_COLORS.to_string());
-    static ref COLOR_MAP: HashMap<&'static str, &'static str> = {
-        let codes = LS_COLORS.split(":");
-        let mut map = HashMap::new();
-        for c in codes {
-            let p: Vec<_> = c.split("=").collect();
-            if p.len() == 2 {
-                map.insert(p[0], p[1]);
-            }
+    static ref LS_COLORS: Option<LsColors> = match LsColors::from_env() {
+        Ok(colors) => Some(colors),
+        Err(_) => {
+            show_warning!("unparsable value for LS_COLORS environment variable");
+            None
         }
-        map
     };
-    static ref RESET_CODE: &'static str = COLOR_MAP.get("rs").unwrap_or(&"0");
-    static ref LEFT_CODE: &'static str = COLOR_MAP.get("lc").unwrap_or(&"\x1b[");
-    static ref RIGHT_CODE: &'static str = COLOR_MAP.get("rc").unwrap_or(&"m");
-    static ref END_CODE: &'static str = COLOR_MAP.get("ec").unwrap_or(&"");
 }
 
 pub fn uumain(args: Vec<String>) -> i32 {
@@ -89,36 +81,72 @@ pub fn uumain(args: Vec<String>) -> i32 {
         .optflag("B",
                  "ignore-backups",
                  "Ignore entries which end with ~.")
+        .optflag("C", "", "List entries by columns.")
+        .optmulti("I",
+                  "ignore",
+                  "Do not list entries matching the shell PATTERN.",
+                  "PATTERN")
+        .optmulti("",
+                  "hide",
+                  "Do not list entries matching the shell PATTERN, unless -a or -A is given.",
+                  "PATTERN")
         .optflag("c",
                  "",
                  "If the long listing format (e.g., -l, -o) is being used, print the status \
                  change time (the ‘ctime’ in the inode) instead of the modification time. When \
                  explicitly sorting by time (--sort=time or -t) or when not using a long listing \
                  format, sort according to the status change time.")
+        .optflag("D",
+                 "dired",
+                 "Generate output designed for Emacs' dired mode: with a long listing format, \
+                 also print the byte offsets of every file name.")
         .optflag("d",
                  "directory",
                  "Only list the names of directories, rather than listing directory contents. \
                   This will not follow symbolic links unless one of `--dereference-command-line \
                   (-H)`, `--dereference (-L)`, or `--dereference-command-line-symlink-to-dir` is \
                   specified.")
+        .optflag("g",
+                 "",
+                 "Like -l, but do not list the owner.")
+        .optflag("",
+                 "full-time",
+                 "Like -l --time-style=full-iso.")
+        .optflag("G",
+                 "no-group",
+                 "In a long listing, do not print group names.")
         .optflag("F",
                  "classify",
                  "Append a character to each file name indicating the file type. Also, for \
                  regular files that are executable, append '*'. The file type indicators are \
                  '/' for directories, '@' for symbolic links, '|' for FIFOs, '=' for sockets, \
                  '>' for doors, and nothing for regular files.")
+        .optopt("",
+                "block-size",
+                "Scale sizes by SIZE when printing them, e.g. '--block-size=M' prints sizes in \
+                units of 1,048,576 bytes.",
+                "SIZE")
         .optflag("h",
                  "human-readable",
                  "Print human readable file sizes (e.g. 1K 234M 56G).")
+        .optflag("",
+                 "si",
+                 "Like -h, but use powers of 1000 rather than 1024.")
         .optflag("i",
                  "inode",
                  "print the index number of each file")
+        .optflag("k",
+                 "kibibytes",
+                 "Default to 1024-byte blocks for disk usage; used only with -s and per \
+                 directory totals.")
         .optflag("L",
                  "dereference",
                  "When showing file information for a symbolic link, show information for the \
                  file the link references rather than the link itself.")
         .optflag("l", "long", "Display detailed information.")
+        .optflag("m", "", "Fill width with a comma separated list of entries.")
         .optflag("n", "numeric-uid-gid", "-l with numeric UIDs and GIDs.")
+        .optflag("o", "", "Like -l, but do not list group information.")
         .optflag("r",
                  "reverse",
                  "Reverse whatever the sorting method is--e.g., list files in reverse \
@@ -126,23 +154,148 @@ pub fn uumain(args: Vec<String>) -> i32 {
         .optflag("R",
                  "recursive",
                  "List the contents of all directories recursively.")
+        .optflag("s", "size", "Print the allocated size of each file, in blocks.")
         .optflag("S", "", "Sort by file size, largest first.")
+        .optopt("",
+                "sort",
+                "Sort by WORD instead of name: none (-U), size (-S), time (-t), version (-v), \
+                extension (-X).",
+                "WORD")
         .optflag("t",
                  "",
                  "Sort by modification time (the 'mtime' in the inode), newest first.")
+        .optopt("",
+                "time-style",
+                "Show times in long listings in STYLE, which is one of full-iso, long-iso, iso, \
+                locale or +FORMAT. FORMAT is interpreted like in date(1); with two formats \
+                separated by a newline, the first applies to files older than six months and \
+                the second to recent ones. The TIME_STYLE environment variable sets the \
+                default.",
+                "STYLE")
+        .optflag("u",
+                 "",
+                 "If the long listing format is being used, print the access time instead of \
+                 the modification time. When explicitly sorting by time or when not using a \
+                 long listing format, sort according to the access time.")
         .optflag("U",
                  "",
                  "Do not sort; list the files in whatever order they are stored in the \
                  directory.  This is especially useful when listing very large directories, \
                  since not doing any sorting can be noticeably faster.")
-        .optflag("", "color", "Color output based on file type.")
+        .optflag("f",
+                 "",
+                 "Do not sort, and list all entries as -aU would; also turns off -l, -s and \
+                 --color.")
+        .optflag("v", "", "Natural sort of (version) numbers within text.")
+        .optopt("w",
+                "width",
+                "Set the output width to COLS, 0 meaning no limit. The default is the width of \
+                the terminal, or else the COLUMNS environment variable.",
+                "COLS")
+        .optflag("x", "", "List entries by lines instead of by columns.")
+        .optflag("X", "", "Sort alphabetically by entry extension.")
+        .optflag("1", "", "List one file per line.")
+        .optflagopt("",
+                    "color",
+                    "Color output based on file type, as configured by LS_COLORS. WHEN is \
+                    'always' (the default), 'auto' (only when writing to a terminal) or 'never'.",
+                    "WHEN")
+        .optflagopt("",
+                    "hyperlink",
+                    "Hyperlink file names to their file:// URLs, for terminals that support it. \
+                    WHEN is as for --color.",
+                    "WHEN")
         .parse(args);
 
-    list(matches);
-    0
+    for option in &["color", "hyperlink"] {
+        if let Some(when) = matches.opt_str(option) {
+            if !WHEN_ARGS.iter().any(|&(name, _)| name == when) {
+                show_error!("invalid argument {} for {}", quote(&when), quote(&format!("--{}", option)));
+                show_error!("Valid arguments are: 'always', 'yes', 'force', 'never', 'no', 'none', \
+                             'auto', 'tty', 'if-tty'");
+                return 2;
+            }
+        }
+    }
+    if time_style(&matches).is_none() {
+        let style = matches.opt_str("time-style").or_else(|| env::var("TIME_STYLE").ok()).unwrap_or_default();
+        show_error!("invalid argument {} for {}", quote(&style), quote("time style"));
+        show_error!("Valid arguments are: 'full-iso', 'long-iso', 'iso', 'locale', '+FORMAT'");
+        return 2;
+    }
+    if let Some(width) = matches.opt_str("width") {
+        if width.parse::<usize>().is_err() {
+            show_error!("invalid line width: {}", quote(&width));
+            return 2;
+        }
+    }
+    if let Some(size) = matches.opt_str("block-size") {
+        if BlockSize::parse(&size).is_none() {
+            show_error!("invalid --block-size argument {}", quote(&size));
+            return 2;
+        }
+    }
+    if let Some(word) = matches.opt_str("sort") {
+        if !SORT_WORDS.iter().any(|&(name, _)| name == word) {
+            show_error!("invalid argument {} for {}", quote(&word), quote("--sort"));
+            show_error!("Valid arguments are: 'none', 'time', 'size', 'extension', 'version'");
+            return 2;
+        }
+    }
+
+    list(matches)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum When {
+    Always,
+    Never,
+    Auto,
+}
+
+static WHEN_ARGS: &'static [(&'static str, When)] = &[
+    ("always", When::Always),
+    ("yes", When::Always),
+    ("force", When::Always),
+    ("never", When::Never),
+    ("no", When::Never),
+    ("none", When::Never),
+    ("auto", When::Auto),
+    ("tty", When::Auto),
+    ("if-tty", When::Auto),
+];
+
+/// Whether an option taking WHEN, like --color, is in effect
+fn enabled(options: &getopts::Matches, option: &str) -> bool {
+    if !options.opt_present(option) {
+        return false;
+    }
+    let when = match options.opt_str(option) {
+        Some(when) => WHEN_ARGS.iter().find(|&&(name, _)| name == when).map_or(When::Never, |&(_, w)| w),
+        None => When::Always,
+    };
+    match when {
+        When::Always => true,
+        When::Never => false,
+        When::Auto => uucore::fs::is_stdout_interactive(),
+    }
 }
 
-fn list(options: getopts::Matches) {
+fn use_color(options: &getopts::Matches) -> bool {
+    enabled(options, "color") && !options.opt_present("f")
+}
+
+/// Whether hidden files are listed, with -a or -f
+fn show_all(options: &getopts::Matches) -> bool {
+    options.opt_present("a") || options.opt_present("f")
+}
+
+/// Whether allocated sizes are shown, with -s and not -f
+fn print_size(options: &getopts::Matches) -> bool {
+    options.opt_present("size") && !options.opt_present("f")
+}
+
+fn list(options: getopts::Matches) -> i32 {
     let locs: Vec<String> = if options.free.is_empty() {
         vec![String::from(".")]
     } else {
@@ -157,8 +310,8 @@ fn list(options: getopts::Matches) {
 
         if p.is_dir() && !options.opt_present("d") {
             dir = true;
-            if options.opt_present("l") && !(options.opt_present("L")) {
-                if let Ok(md) = p.symlink_metadata() {
+            if long_format(&options) && !(options.opt_present("L")) {
+                if let Ok(md) = uucore::fs::metadata(&p, false) {
                     if md.file_type().is_symlink() && !p.ends_with( "/" ) {
                         dir = false;
                     }
@@ -171,67 +324,229 @@ fn list(options: getopts::Matches) {
             files.push(p);
         }
     }
+    let mut out = Output::new(&options);
     sort_entries(&mut files, &options);
-    display_items(&files, None, &options);
+    display_items(&files, None, &options, &mut out);
 
     sort_entries(&mut dirs, &options);
-    for dir in dirs {
-        if options.free.len() > 1 {
-            println!("\n{}:", dir.to_string_lossy());
+    let headers = options.free.len() > 1 || options.opt_present("R");
+    let mut status = 0;
+    for (i, dir) in dirs.iter().enumerate() {
+        if headers {
+            if i > 0 || !files.is_empty() {
+                out.print("\n");
+            }
+            out.header(dir);
         }
-        enter_directory(&dir, &options);
+        status = cmp::max(status, enter_directory(dir, &options, &mut Vec::new(), &mut out));
     }
+    out.finish();
+    status
 }
 
-#[cfg(any(unix, target_os = "redox"))]
-fn sort_entries(entries: &mut Vec<PathBuf>, options: &getopts::Matches) {
-    let mut reverse = options.opt_present("r");
-    if options.opt_present("t") {
-        if options.opt_present("c") {
-            entries.sort_by_key(|k| {
-                Reverse(get_metadata(k, options)
-                    .map(|md| md.ctime())
-                    .unwrap_or(0))
-            });
-        } else {
-            entries.sort_by_key(|k| {
-                // Newest first
-                Reverse(get_metadata(k, options)
-                    .and_then(|md| md.modified())
-                    .unwrap_or(std::time::UNIX_EPOCH))
-            });
+/// Standard output, keeping track of the byte offsets of the file names
+/// written to it for --dired
+struct Output {
+    dired: bool,
+    offset: usize,
+    names: Vec<(usize, usize)>,
+    subdirs: Vec<(usize, usize)>,
+}
+
+impl Output {
+    fn new(options: &getopts::Matches) -> Output {
+        Output {
+            // like GNU ls, which can't point into the escape sequences of links
+            dired: options.opt_present("dired") && long_format(options) && !enabled(options, "hyperlink"),
+            offset: 0,
+            names: Vec::new(),
+            subdirs: Vec::new(),
+        }
+    }
+
+    fn print<T: AsRef<[u8]>>(&mut self, s: T) {
+        let s = s.as_ref();
+        if let Err(e) = io::stdout().write_all(s) {
+            panic!("failed printing to stdout: {}", e);
         }
+        self.offset += s.len();
+    }
+
+    /// Start a line of a long listing, which dired expects to be indented
+    fn indent(&mut self) {
+        if self.dired {
+            self.print("  ");
+        }
+    }
+
+    /// Print `s`, of which the bytes in `name` are a file name
+    fn print_name(&mut self, s: &[u8], name: (usize, usize)) {
+        self.names.push((self.offset + name.0, self.offset + name.1));
+        self.print(s);
+    }
+
+    fn header(&mut self, dir: &Path) {
+        self.indent();
+        let name = dir.to_string_lossy();
+        self.subdirs.push((self.offset, self.offset + name.len()));
+        self.print(name.as_bytes());
+        self.print(":\n");
+    }
+
+    fn finish(&self) {
+        if !self.dired {
+            return;
+        }
+        for &(label, spans) in &[("//DIRED//", &self.names), ("//SUBDIRED//", &self.subdirs)] {
+            if !spans.is_empty() {
+                let offsets: Vec<_> = spans.iter().map(|&(start, end)| format!("{} {}", start, end)).collect();
+                println!("{} {}", label, offsets.join(" "));
+            }
+        }
+        println!("//DIRED-OPTIONS// --quoting-style=literal");
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum SortKey {
+    None,
+    Name,
+    Time,
+    Size,
+    Extension,
+    Version,
+}
+
+static SORT_WORDS: &'static [(&'static str, SortKey)] = &[
+    ("none", SortKey::None),
+    ("time", SortKey::Time),
+    ("size", SortKey::Size),
+    ("extension", SortKey::Extension),
+    ("version", SortKey::Version),
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum TimeKey {
+    Modification,
+    Access,
+    Change,
+}
+
+fn sort_key(options: &getopts::Matches) -> SortKey {
+    if let Some(word) = options.opt_str("sort") {
+        // validated in uumain()
+        return SORT_WORDS.iter().find(|&&(name, _)| name == word).map_or(SortKey::Name, |&(_, k)| k);
+    }
+    if options.opt_present("U") || options.opt_present("f") {
+        SortKey::None
     } else if options.opt_present("S") {
-        entries.sort_by_key(|k| get_metadata(k, options).map(|md| md.size()).unwrap_or(0));
-        reverse = !reverse;
-    } else if !options.opt_present("U") {
-        entries.sort();
+        SortKey::Size
+    } else if options.opt_present("t") {
+        SortKey::Time
+    } else if options.opt_present("v") {
+        SortKey::Version
+    } else if options.opt_present("X") {
+        SortKey::Extension
+    } else if (options.opt_present("c") || options.opt_present("u")) && !long_format(options) {
+        SortKey::Time
+    } else {
+        SortKey::Name
     }
+}
 
-    if reverse {
-        entries.reverse();
+fn time_key(options: &getopts::Matches) -> TimeKey {
+    if options.opt_present("c") {
+        TimeKey::Change
+    } else if options.opt_present("u") {
+        TimeKey::Access
+    } else {
+        TimeKey::Modification
+    }
+}
+
+type SortItem = (PathBuf, Option<Metadata>);
+
+/// The ordering for `key`, before any reversal.  Anything but a plain name
+/// sort falls back to the name when the keys are equal.
+fn comparator(key: SortKey, options: &getopts::Matches) -> Box<Fn(&SortItem, &SortItem) -> Ordering> {
+    let by_name = |a: &SortItem, b: &SortItem| a.0.cmp(&b.0);
+    match key {
+        SortKey::None | SortKey::Name => Box::new(by_name),
+        SortKey::Time => {
+            let time = time_key(options);
+            // newest first
+            Box::new(move |a: &SortItem, b: &SortItem| {
+                file_time(b.1.as_ref(), time).cmp(&file_time(a.1.as_ref(), time)).then_with(|| by_name(a, b))
+            })
+        }
+        SortKey::Size => {
+            // largest first
+            Box::new(move |a: &SortItem, b: &SortItem| {
+                let size = |item: &SortItem| item.1.as_ref().map_or(0, |md| md.len());
+                size(b).cmp(&size(a)).then_with(|| by_name(a, b))
+            })
+        }
+        SortKey::Extension => {
+            Box::new(move |a: &SortItem, b: &SortItem| extension(&a.0).cmp(&extension(&b.0)).then_with(|| by_name(a, b)))
+        }
+        SortKey::Version => {
+            Box::new(move |a: &SortItem, b: &SortItem| {
+                let name = |item: &SortItem| item.0.file_name().map_or(String::new(), |n| n.to_string_lossy().into_owned());
+                version_cmp(&name(a), &name(b)).then_with(|| by_name(a, b))
+            })
+        }
     }
 }
 
-#[cfg(windows)]
 fn sort_entries(entries: &mut Vec<PathBuf>, options: &getopts::Matches) {
-    let mut reverse = options.opt_present("r");
-    if options.opt_present("t") {
-        entries.sort_by_key(|k| {
-            // Newest first
-            Reverse(get_metadata(k, options)
-                .and_then(|md| md.modified())
-                .unwrap_or(std::time::UNIX_EPOCH))
-        });
-    } else if options.opt_present("S") {
-        entries.sort_by_key(|k| get_metadata(k, options).map(|md| md.file_size()).unwrap_or(0));
-        reverse = !reverse;
-    } else if !options.opt_present("U") {
-        entries.sort();
+    let key = sort_key(options);
+    if key == SortKey::None {
+        return;
+    }
+    let compare = comparator(key, options);
+    let reverse = options.opt_present("r");
+
+    let mut items: Vec<SortItem> = entries.drain(..)
+        .map(|path| {
+            let md = get_metadata(&path, options).ok();
+            (path, md)
+        })
+        .collect();
+    items.sort_by(|a, b| if reverse { compare(b, a) } else { compare(a, b) });
+    entries.extend(items.into_iter().map(|(path, _)| path));
+}
+
+/// The part of the file name after its last dot, if any.
+fn extension(path: &Path) -> &str {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    match name.rfind('.') {
+        Some(pos) => &name[pos + 1..],
+        None => "",
     }
+}
 
-    if reverse {
-        entries.reverse();
+#[cfg(any(unix, target_os = "redox"))]
+fn file_time(md: Option<&Metadata>, key: TimeKey) -> (i64, i64) {
+    match md {
+        Some(md) => match key {
+            TimeKey::Modification => (md.mtime(), md.mtime_nsec()),
+            TimeKey::Access => (md.atime(), md.atime_nsec()),
+            TimeKey::Change => (md.ctime(), md.ctime_nsec()),
+        },
+        None => (0, 0),
+    }
+}
+
+#[cfg(windows)]
+fn file_time(md: Option<&Metadata>, key: TimeKey) -> (i64, i64) {
+    // FILETIME ticks; Windows has no change time, so use the creation time
+    match md {
+        Some(md) => match key {
+            TimeKey::Modification => (md.last_write_time() as i64, 0),
+            TimeKey::Access => (md.last_access_time() as i64, 0),
+            TimeKey::Change => (md.creation_time() as i64, 0),
+        },
+        None => (0, 0),
     }
 }
 
@@ -246,7 +561,7 @@ fn max(lhs: usize, rhs: usize) -> usize {
 fn should_display(entry: &DirEntry, options: &getopts::Matches) -> bool {
     let ffi_name = entry.file_name();
     let name = ffi_name.to_string_lossy();
-    if !options.opt_present("a") && !options.opt_present("A") {
+    if !show_all(options) && !options.opt_present("A") {
         if name.starts_with('.') {
             return false;
         }
@@ -254,154 +569,448 @@ fn should_display(entry: &DirEntry, options: &getopts::Matches) -> bool {
     if options.opt_present("B") && name.ends_with('~') {
         return false;
     }
-    return true;
+    return !is_ignored(&name, options);
 }
 
-fn enter_directory(dir: &PathBuf, options: &getopts::Matches) {
-    let mut entries = safe_unwrap!(fs::read_dir(dir)
-        .and_then(|e| e.collect::<Result<Vec<_>, _>>()));
+/// Whether `name` matches a pattern given with --ignore, or with --hide
+/// unless -a or -A was given too.
+fn is_ignored(name: &str, options: &getopts::Matches) -> bool {
+    let flags = Flags { period: true, ..Flags::default() };
+    let mut patterns = options.opt_strs("ignore");
+    if !show_all(options) && !options.opt_present("A") {
+        patterns.extend(options.opt_strs("hide"));
+    }
+    patterns.iter().any(|pattern| fnmatch(pattern, name, flags))
+}
 
-    entries.retain(|e| should_display(e, options));
+/// List the contents of `dir`, and with -R those of its subdirectories.
+/// `ancestors` identifies the directories currently being listed, so that
+/// symbolic links (with -L) cannot send the recursion around in circles.
+fn enter_directory(dir: &PathBuf,
+                   options: &getopts::Matches,
+                   ancestors: &mut Vec<(u64, u64)>,
+                   out: &mut Output)
+                   -> i32 {
+    // trouble with an operand is more serious than with a subdirectory
+    let failure = if ancestors.is_empty() { 2 } else { 1 };
+
+    let id = directory_id(dir);
+    if let Some(id) = id {
+        if ancestors.contains(&id) {
+            show_error!("{}: not listing already-listed directory",
+                        quote_as(dir, QuotingStyle::ShellEscape));
+            return 2;
+        }
+    }
+
+    let read = match fs::read_dir(dir) {
+        Ok(read) => read,
+        Err(e) => {
+            show_error!("cannot open directory {}: {}", quote_path(dir), e);
+            return failure;
+        }
+    };
 
-    let mut entries: Vec<_> = entries.iter().map(DirEntry::path).collect();
-    sort_entries(&mut entries, options);
+    let (entries, mut status) = if streams(options) {
+        stream_directory(dir, read, options, failure, out)
+    } else {
+        let mut entries = match read.collect::<Result<Vec<_>, _>>() {
+            Ok(entries) => entries,
+            Err(e) => {
+                show_error!("cannot open directory {}: {}", quote_path(dir), e);
+                return failure;
+            }
+        };
+        entries.retain(|e| should_display(e, options));
 
+        let mut entries: Vec<_> = entries.iter().map(DirEntry::path).collect();
+        sort_entries(&mut entries, options);
 
+        if show_all(options) {
+            let mut display_entries = entries.clone();
+            for name in &["..", "."] {
+                if !is_ignored(name, options) {
+                    display_entries.insert(0, dir.join(name));
+                }
+            }
+            display_items(&display_entries, Some(dir), options, out);
+        } else {
+            display_items(&entries, Some(dir), options, out);
+        }
+        (entries, 0)
+    };
 
-    if options.opt_present("a") {
-        let mut display_entries = entries.clone();
-        display_entries.insert(0, dir.join(".."));
-        display_entries.insert(0, dir.join("."));
-        display_items(&display_entries, Some(dir), options);
-    }
-    else
-    {
-        display_items(&entries, Some(dir), options);
+    if options.opt_present("R") {
+        // symbolic links to directories are only followed with -L
+        let subdirs = entries.iter()
+            .filter(|e| get_metadata(e, options).map(|md| md.is_dir()).unwrap_or(false));
+        if let Some(id) = id {
+            ancestors.push(id);
+        }
+        for e in subdirs {
+            out.print("\n");
+            out.header(e);
+            status = cmp::max(status, enter_directory(e, options, ancestors, out));
+        }
+        if id.is_some() {
+            ancestors.pop();
+        }
     }
+    status
+}
 
+/// Whether the contents of a directory can be printed as they are read:
+/// they are not sorted, and nothing about one entry is aligned with the
+/// others or added up into a total.
+fn streams(options: &getopts::Matches) -> bool {
+    sort_key(options) == SortKey::None && format(options) == Format::OneLine &&
+    !options.opt_present("inode") && !print_size(options)
+}
 
-    if options.opt_present("R") {
-        for e in entries.iter().filter(|p| p.is_dir()) {
-            println!("\n{}:", e.to_string_lossy());
-            enter_directory(&e, options);
+/// List the contents of `dir` one entry at a time as `read` yields them,
+/// so that a directory of millions of files is neither held in memory nor
+/// waited for as a whole.  Only what -R goes on to enter is kept.
+fn stream_directory(dir: &PathBuf,
+                    read: fs::ReadDir,
+                    options: &getopts::Matches,
+                    failure: i32,
+                    out: &mut Output)
+                    -> (Vec<PathBuf>, i32) {
+    if show_all(options) {
+        for name in &[".", ".."] {
+            if !is_ignored(name, options) {
+                stream_item(&dir.join(name), dir, options, out);
+            }
         }
     }
+    let mut subdirs = Vec::new();
+    for entry in read {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                show_error!("reading directory {}: {}", quote_path(dir), e);
+                return (subdirs, failure);
+            }
+        };
+        if !should_display(&entry, options) {
+            continue;
+        }
+        let path = entry.path();
+        let is_dir = stream_item(&path, dir, options, out).map_or(false, |md| md.is_dir());
+        if is_dir && options.opt_present("R") {
+            subdirs.push(path);
+        }
+    }
+    (subdirs, 0)
+}
+
+fn stream_item(item: &PathBuf, dir: &Path, options: &getopts::Matches, out: &mut Output) -> Option<Metadata> {
+    match get_metadata(item, options) {
+        Err(e) => {
+            show_error!("{}: {}", get_file_name(item, Some(dir)), e);
+            None
+        }
+        Ok(md) => {
+            display_lines(&[display_file_name(item, Some(dir), &md, options)], out);
+            Some(md)
+        }
+    }
+}
+
+#[cfg(any(unix, target_os = "redox"))]
+fn directory_id(dir: &Path) -> Option<(u64, u64)> {
+    fs::metadata(dir).ok().map(|md| (md.dev(), md.ino()))
+}
+
+#[cfg(windows)]
+fn directory_id(_dir: &Path) -> Option<(u64, u64)> {
+    None
 }
 
 fn get_metadata(entry: &PathBuf, options: &getopts::Matches) -> std::io::Result<Metadata> {
     if options.opt_present("L") {
-        entry.metadata().or(entry.symlink_metadata())
+        uucore::fs::metadata(entry, true).or(uucore::fs::metadata(entry, false))
     } else {
-        entry.symlink_metadata()
+        uucore::fs::metadata(entry, false)
     }
 }
 
-fn display_dir_entry_size(entry: &PathBuf, options: &getopts::Matches) -> (usize, usize) {
-    if let Ok(md) = get_metadata(entry, options) {
-        (display_symlink_count(&md).len(), display_file_size(&md, options).len())
+fn long_format(options: &getopts::Matches) -> bool {
+    !options.opt_present("f") &&
+    (options.opt_present("long") || options.opt_present("numeric-uid-gid") ||
+     options.opt_present("o") || options.opt_present("g") || options.opt_present("full-time"))
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Long,
+    Columns,
+    Across,
+    Commas,
+    OneLine,
+}
+
+fn format(options: &getopts::Matches) -> Format {
+    if long_format(options) {
+        Format::Long
+    } else if options.opt_present("1") {
+        Format::OneLine
+    } else if options.opt_present("m") {
+        Format::Commas
+    } else if options.opt_present("x") {
+        Format::Across
+    } else if options.opt_present("C") || uucore::fs::is_stdout_interactive() {
+        Format::Columns
     } else {
-        (0, 0)
+        Format::OneLine
     }
 }
 
-fn pad_left(string: String, count: usize) -> String {
-    if count > string.len() {
-        let pad = count - string.len();
-        let pad = String::from_utf8(vec![' ' as u8; pad]).unwrap();
-        format!("{}{}", pad, string)
-    } else {
-        string
+/// The width of the lines that the output is fitted into, 0 for no limit
+fn line_width(options: &getopts::Matches) -> usize {
+    if let Some(width) = options.opt_str("width") {
+        // validated in uumain()
+        return width.parse().unwrap();
+    }
+    if let Some(size) = termsize::get() {
+        return size.cols as usize;
+    }
+    match env::var("COLUMNS").ok().and_then(|cols| cols.parse().ok()) {
+        Some(cols) if cols > 0 => cols,
+        _ => 80,
     }
 }
 
-fn display_items(items: &Vec<PathBuf>, strip: Option<&Path>, options: &getopts::Matches) {
-    if options.opt_present("long") || options.opt_present("numeric-uid-gid") {
-        let (mut max_links, mut max_size) = (1, 1);
-        for item in items {
-            let (links, size) = display_dir_entry_size(item, options);
-            max_links = max(links, max_links);
-            max_size = max(size, max_size);
+/// The columns of one line of a long listing, before they are padded
+struct LongFields {
+    prefix: String,
+    mode: String,
+    links: String,
+    owner: Option<String>,
+    group: Option<String>,
+    size: String,
+    date: String,
+    name: Vec<u8>,
+    // where the name proper is in `name`, without colors or what follows it
+    name_span: (usize, usize),
+}
+
+impl LongFields {
+    fn new(item: &PathBuf, strip: Option<&Path>, md: &Metadata, prefix: String, options: &getopts::Matches)
+           -> LongFields {
+        let name = display_file_name(item, strip, md, options);
+        LongFields {
+            prefix: prefix,
+            mode: display_file_type(md.file_type()) + &display_permissions(md) +
+                  uucore::fs::access_control(item, !md.file_type().is_symlink()).indicator(),
+            links: display_symlink_count(md),
+            owner: if options.opt_present("g") {
+                None
+            } else {
+                Some(display_uname(md, options))
+            },
+            group: if options.opt_present("o") || options.opt_present("no-group") {
+                None
+            } else {
+                Some(display_group(md, options))
+            },
+            size: display_file_size(md, options),
+            date: display_date(md, options),
+            name_span: name.span,
+            name: name.contents,
         }
-        for item in items {
-            display_item_long(item, strip, max_links, max_size, options);
+    }
+}
+
+/// Widths of the columns that are aligned across every line of a long listing
+struct LongWidths {
+    mode: usize,
+    links: usize,
+    owner: usize,
+    group: usize,
+    size: usize,
+}
+
+impl LongWidths {
+    fn new(lines: &[LongFields]) -> LongWidths {
+        let mut widths = LongWidths { mode: 0, links: 1, owner: 0, group: 0, size: 1 };
+        for line in lines {
+            // a space for the access control indicator if any line has one
+            widths.mode = max(widths.mode, line.mode.len());
+            widths.links = max(widths.links, line.links.len());
+            widths.owner = max(widths.owner, line.owner.as_ref().map_or(0, |o| o.len()));
+            widths.group = max(widths.group, line.group.as_ref().map_or(0, |g| g.len()));
+            widths.size = max(widths.size, line.size.len());
+        }
+        widths
+    }
+}
+
+fn display_items(items: &Vec<PathBuf>, strip: Option<&Path>, options: &getopts::Matches, out: &mut Output) {
+    let entries: Vec<_> = items.iter()
+        .filter_map(|item| match get_metadata(item, options) {
+            Err(e) => {
+                let filename = get_file_name(item, strip);
+                show_error!("{}: {}", filename, e);
+                None
+            }
+            Ok(md) => Some((item, md)),
+        })
+        .collect();
+
+    // only the contents of a directory get a total
+    if strip.is_some() && (long_format(options) || print_size(options)) {
+        let blocks = entries.iter().fold(0, |total, &(_, ref md)| total + get_block_count(md));
+        out.indent();
+        out.print(&format!("total {}\n", display_block_count(blocks, options)));
+    }
+
+    let prefixes = name_prefixes(&entries, options);
+    if long_format(options) {
+        let lines: Vec<_> = entries.iter()
+            .zip(prefixes)
+            .map(|(&(item, ref md), prefix)| LongFields::new(item, strip, md, prefix, options))
+            .collect();
+        let widths = LongWidths::new(&lines);
+        for line in &lines {
+            display_item_long(line, &widths, out);
         }
     } else {
-        let names: Vec<_> = items.iter()
-            .filter_map(|i| {
-                let md = get_metadata(i, options);
-                match md {
-                    Err(e) => {
-                        let filename = get_file_name(i, strip);
-                        show_error!("{}: {}", filename, e);
-                        None
-                    }
-                    Ok(md) => Some(display_file_name(&i, strip, &md, options)),
-                }
+        let names: Vec<_> = entries.iter()
+            .zip(prefixes)
+            .map(|(&(item, ref md), prefix)| {
+                let mut name = display_file_name(item, strip, md, options);
+                name.contents.splice(0..0, prefix.bytes());
+                name.width += prefix.len();
+                name
             })
             .collect();
-        if let Some(size) = termsize::get() {
-            let mut grid = Grid::new(GridOptions {
-                filling: Filling::Spaces(2),
-                direction: Direction::TopToBottom,
-            });
-            for name in names {
-                grid.add(name);
-            }
-            if let Some(output) = grid.fit_into_width(size.cols as usize) {
-                print!("{}", output);
-                return;
+        match format(options) {
+            Format::Columns => display_grid(names, Direction::TopToBottom, options, out),
+            Format::Across => display_grid(names, Direction::LeftToRight, options, out),
+            Format::Commas => display_commas(&names, line_width(options), out),
+            _ => display_lines(&names, out),
+        }
+    }
+}
+
+fn display_grid(names: Vec<Name>, direction: Direction, options: &getopts::Matches, out: &mut Output) {
+    let width = match line_width(options) {
+        0 => usize::max_value(),
+        width => width,
+    };
+    let mut grid = Grid::new(GridOptions {
+        filling: Filling::Spaces(2),
+        direction: direction,
+    });
+    // the grid is only told how wide each name is, as a name need not be
+    // UTF-8, and is laid out with the name's number between NULs, which no
+    // file name holds
+    for (i, name) in names.iter().enumerate() {
+        grid.add(Cell {
+            contents: format!("\0{}\0", i),
+            width: name.width,
+        });
+    }
+    match grid.fit_into_width(width) {
+        Some(output) => {
+            for (i, piece) in output.to_string().split('\0').enumerate() {
+                if i % 2 == 0 {
+                    out.print(piece);
+                } else {
+                    out.print(&names[piece.parse::<usize>().unwrap()].contents);
+                }
             }
         }
+        // some name is wider than the line on its own
+        None => display_lines(&names, out),
+    }
+}
 
-        // Couldn't display a grid, either because we don't know
-        // the terminal width or because fit_into_width failed
-        for i in items {
-            let md = get_metadata(i, options);
-            if let Ok(md) = md {
-                println!("{}", display_file_name(&i, strip, &md, options).contents);
+/// Names separated by commas, as many on a line as fit
+fn display_commas(names: &[Name], width: usize, out: &mut Output) {
+    let mut pos = 0;
+    for (i, name) in names.iter().enumerate() {
+        if i > 0 {
+            if width == 0 || pos + name.width + 2 < width {
+                out.print(", ");
+                pos += 2;
+            } else {
+                out.print(",\n");
+                pos = 0;
             }
         }
+        out.print(&name.contents);
+        pos += name.width;
+    }
+    if !names.is_empty() {
+        out.print("\n");
     }
 }
 
-fn display_item_long(item: &PathBuf,
-                     strip: Option<&Path>,
-                     max_links: usize,
-                     max_size: usize,
-                     options: &getopts::Matches) {
-    let md = match get_metadata(item, options) {
-        Err(e) => {
-            let filename = get_file_name(&item, strip);
-            show_error!("{}: {}", filename, e);
-            return;
+fn display_lines(names: &[Name], out: &mut Output) {
+    for name in names {
+        out.print(&name.contents);
+        out.print("\n");
+    }
+}
+
+/// The inode numbers (-i) and allocated sizes (-s) that go in front of each
+/// entry, right-aligned across all of them
+fn name_prefixes(entries: &[(&PathBuf, Metadata)], options: &getopts::Matches) -> Vec<String> {
+    let columns: Vec<Vec<String>> = entries.iter()
+        .map(|&(_, ref md)| {
+            let mut fields = Vec::new();
+            if options.opt_present("inode") {
+                fields.push(get_inode(md));
+            }
+            if print_size(options) {
+                fields.push(display_block_count(get_block_count(md), options));
+            }
+            fields
+        })
+        .collect();
+    let mut widths = Vec::new();
+    for fields in &columns {
+        widths.resize(fields.len(), 0);
+        for (width, field) in widths.iter_mut().zip(fields) {
+            *width = max(*width, field.len());
         }
-        Ok(md) => md,
-    };
+    }
+    columns.iter()
+        .map(|fields| {
+            fields.iter()
+                .zip(&widths)
+                .map(|(field, &width)| format!("{:>width$} ", field, width = width))
+                .collect()
+        })
+        .collect()
+}
 
-    println!("{}{}{} {} {} {} {} {} {}",
-             get_inode(&md, options),
-             display_file_type(md.file_type()),
-             display_permissions(&md),
-             pad_left(display_symlink_count(&md), max_links),
-             display_uname(&md, options),
-             display_group(&md, options),
-             pad_left(display_file_size(&md, options), max_size),
-             display_date(&md, options),
-             display_file_name(&item, strip, &md, options).contents);
+fn display_item_long(line: &LongFields, widths: &LongWidths, out: &mut Output) {
+    let mut output = format!("{}{:<mode_width$} {:>width$} ", line.prefix, line.mode, line.links,
+                             mode_width = widths.mode, width = widths.links);
+    if let Some(ref owner) = line.owner {
+        output.push_str(&format!("{:<width$} ", owner, width = widths.owner));
+    }
+    if let Some(ref group) = line.group {
+        output.push_str(&format!("{:<width$} ", group, width = widths.group));
+    }
+    output.push_str(&format!("{:>width$} {} ", line.size, line.date, width = widths.size));
+    out.indent();
+    out.print(&output);
+    out.print_name(&line.name, line.name_span);
+    out.print("\n");
 }
 
 #[cfg(unix)]
-fn get_inode(metadata: &Metadata, options: &getopts::Matches) -> String {
-    if options.opt_present("inode") {
-        format!("{:8} ", metadata.ino())
-    } else {
-        "".to_string()
-    }
+fn get_inode(metadata: &Metadata) -> String {
+    metadata.ino().to_string()
 }
 
 #[cfg(not(unix))]
-fn get_inode(_metadata: &Metadata, _options: &getopts::Matches) -> String {
-    "".to_string()
+fn get_inode(_metadata: &Metadata) -> String {
+    "?".to_string()
 }
 
 
@@ -442,38 +1051,222 @@ fn display_group(metadata: &Metadata, _options: &getopts::Matches) -> String {
 
 #[cfg(unix)]
 fn display_date(metadata: &Metadata, options: &getopts::Matches) -> String {
-    let secs = if options.opt_present("c") {
-        metadata.ctime()
-    } else {
-        metadata.mtime()
-    };
-    let time = time::at(Timespec::new(secs, 0));
-    strftime("%F %R", &time).unwrap()
+    let (secs, nsec) = file_time(Some(metadata), time_key(options));
+    format_time(Timespec::new(secs, nsec as i32), options)
 }
 
 #[cfg(not(unix))]
-#[allow(unused_variables)]
 fn display_date(metadata: &Metadata, options: &getopts::Matches) -> String {
     if let Ok(mtime) = metadata.modified() {
-        let time =
-            time::at(Timespec::new(mtime.duration_since(std::time::UNIX_EPOCH)
-                                       .unwrap()
-                                       .as_secs() as i64,
-                                   0));
-        strftime("%F %R", &time).unwrap()
+        let since_epoch = mtime.duration_since(std::time::UNIX_EPOCH).unwrap();
+        format_time(Timespec::new(since_epoch.as_secs() as i64, since_epoch.subsec_nanos() as i32),
+                    options)
     } else {
         "???".to_string()
     }
 }
 
+#[derive(PartialEq)]
+enum TimeStyle {
+    Locale,
+    Iso,
+    LongIso,
+    FullIso,
+    // formats for old and for recent files
+    Format(String, String),
+}
+
+/// The style selected by --time-style, --full-time or `TIME_STYLE`, or None
+/// if it is not valid.
+fn time_style(options: &getopts::Matches) -> Option<TimeStyle> {
+    let style = match options.opt_str("time-style") {
+        Some(style) => style,
+        None if options.opt_present("full-time") => "full-iso".to_owned(),
+        None => match env::var("TIME_STYLE") {
+            Ok(style) => style,
+            Err(_) => return Some(TimeStyle::Locale),
+        },
+    };
+    // posix-STYLE only applies outside the POSIX locale
+    let style = if style.starts_with("posix-") {
+        if posix_time_locale() {
+            return Some(TimeStyle::Locale);
+        }
+        &style["posix-".len()..]
+    } else {
+        &style[..]
+    };
+
+    if style.starts_with('+') {
+        let format = &style[1..];
+        let (old, recent) = match format.find('\n') {
+            Some(pos) => (format[..pos].to_owned(), format[pos + 1..].to_owned()),
+            None => (format.to_owned(), format.to_owned()),
+        };
+        return Some(TimeStyle::Format(old, recent));
+    }
+    match style {
+        "locale" => Some(TimeStyle::Locale),
+        "iso" => Some(TimeStyle::Iso),
+        "long-iso" => Some(TimeStyle::LongIso),
+        "full-iso" => Some(TimeStyle::FullIso),
+        _ => None,
+    }
+}
+
+fn posix_time_locale() -> bool {
+    let locale = ["LC_ALL", "LC_TIME", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty());
+    match locale {
+        Some(ref locale) => locale == "C" || locale == "POSIX",
+        None => true,
+    }
+}
+
+/// Translate the date(1) conversions that the time crate handles differently.
+fn gnu_format(format: &str, tm: &time::Tm) -> String {
+    let mut result = String::with_capacity(format.len());
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('N') => result.push_str("%f"),
+            // the time crate writes UTC as -0000
+            Some('z') => {
+                let sign = if tm.tm_utcoff < 0 { '-' } else { '+' };
+                let offset = tm.tm_utcoff.abs();
+                result.push_str(&format!("{}{:02}{:02}", sign, offset / 3600, offset % 3600 / 60));
+            }
+            Some(c) => {
+                result.push('%');
+                result.push(c);
+            }
+            None => result.push('%'),
+        }
+    }
+    result
+}
+
+// Like GNU, files from the last six months, and not from the future, are
+// recent, and shown with the time of day rather than the year.
+fn is_recent<C: Clock>(time: Timespec, clock: &C) -> bool {
+    let (sec, nsec) = to_timespec(clock.now());
+    let now = Timespec::new(sec, nsec as i32);
+    let six_months_ago = Timespec::new(now.sec - 31556952 / 2, now.nsec);
+    six_months_ago < time && time <= now
+}
+
+fn format_time(time: Timespec, options: &getopts::Matches) -> String {
+    // validated in uumain()
+    let style = time_style(options).unwrap_or(TimeStyle::Locale);
+
+    let recent = is_recent(time, &SystemClock);
+
+    let tm = time::at(time);
+    let format = match style {
+        TimeStyle::Locale if recent => "%b %e %H:%M",
+        TimeStyle::Locale => "%b %e  %Y",
+        TimeStyle::Iso if recent => "%m-%d %H:%M",
+        TimeStyle::Iso => "%Y-%m-%d ",
+        TimeStyle::LongIso => "%Y-%m-%d %H:%M",
+        TimeStyle::FullIso => "%Y-%m-%d %H:%M:%S.%N %z",
+        TimeStyle::Format(ref old, ref recent_format) => if recent { recent_format } else { old },
+    };
+    let format = gnu_format(format, &tm);
+    strftime(&format, &tm).unwrap_or(format)
+}
+
+#[cfg(unix)]
 fn display_file_size(metadata: &Metadata, options: &getopts::Matches) -> String {
+    let file_type = metadata.file_type();
+    if file_type.is_block_device() || file_type.is_char_device() {
+        let rdev = metadata.rdev();
+        return format!("{}, {}", major(rdev), minor(rdev));
+    }
+    display_size(metadata.len(), options)
+}
+
+#[cfg(not(unix))]
+fn display_file_size(metadata: &Metadata, options: &getopts::Matches) -> String {
+    display_size(metadata.len(), options)
+}
+
+fn display_size(len: u64, options: &getopts::Matches) -> String {
+    output_block_size(options, false).format(len)
+}
+
+/// The scale for file sizes, which are in bytes unless asked otherwise, or
+/// for the allocated sizes of -s and totals
+fn output_block_size(options: &getopts::Matches, allocated: bool) -> BlockSize {
     if options.opt_present("human-readable") {
-        convert(metadata.len() as f64)
+        BlockSize::Human(1024)
+    } else if options.opt_present("si") {
+        BlockSize::Human(1000)
+    } else if let Some(size) = options.opt_str("block-size") {
+        // validated in uumain()
+        BlockSize::parse(&size).unwrap()
+    } else if allocated && options.opt_present("kibibytes") {
+        BlockSize::Units(1024, String::new())
+    } else if let Some(size) = BlockSize::from_env("LS_BLOCK_SIZE") {
+        size
+    } else if allocated {
+        BlockSize::default_for_blocks()
+    } else {
+        BlockSize::Units(1, String::new())
+    }
+}
+
+// the inverse of makedev() from <sys/sysmacros.h>
+#[cfg(unix)]
+fn major(rdev: u64) -> u64 {
+    ((rdev >> 8) & 0xfff) | ((rdev >> 32) & !0xfff)
+}
+
+#[cfg(unix)]
+fn minor(rdev: u64) -> u64 {
+    (rdev & 0xff) | ((rdev >> 12) & !0xff)
+}
+
+/// Space allocated to a file, in 512-byte blocks
+#[cfg(unix)]
+fn get_block_count(metadata: &Metadata) -> u64 {
+    metadata.blocks()
+}
+
+#[cfg(not(unix))]
+fn get_block_count(metadata: &Metadata) -> u64 {
+    (metadata.len() + 511) / 512
+}
+
+fn display_block_count(blocks: u64, options: &getopts::Matches) -> String {
+    output_block_size(options, true).format(blocks * 512)
+}
+
+#[cfg(unix)]
+fn display_file_type(file_type: FileType) -> String {
+    if file_type.is_dir() {
+        "d".to_string()
+    } else if file_type.is_symlink() {
+        "l".to_string()
+    } else if file_type.is_fifo() {
+        "p".to_string()
+    } else if file_type.is_socket() {
+        "s".to_string()
+    } else if file_type.is_block_device() {
+        "b".to_string()
+    } else if file_type.is_char_device() {
+        "c".to_string()
     } else {
-        metadata.len().to_string()
+        "-".to_string()
     }
 }
 
+#[cfg(not(unix))]
 fn display_file_type(file_type: FileType) -> String {
     if file_type.is_dir() {
         "d".to_string()
@@ -484,6 +1277,39 @@ fn display_file_type(file_type: FileType) -> String {
     }
 }
 
+/// A file name as it is printed, with the escape sequences around it and
+/// whatever follows it
+struct Name {
+    contents: Vec<u8>,
+    // the columns it takes up, which escape sequences do not
+    width: usize,
+    // where the name proper is in `contents`
+    span: (usize, usize),
+}
+
+impl Name {
+    fn new(name: String) -> Name {
+        Name {
+            width: UnicodeWidthStr::width(&*name),
+            span: (0, name.len()),
+            contents: name.into_bytes(),
+        }
+    }
+
+    /// Put the escape sequences `before` and `after` around what there is
+    fn wrap(&mut self, before: &[u8], after: &[u8]) {
+        self.contents.splice(0..0, before.iter().cloned());
+        self.contents.extend_from_slice(after);
+        self.span = (self.span.0 + before.len(), self.span.1 + before.len());
+    }
+
+    /// Add `c`, which takes up a column, after what there is
+    fn push(&mut self, c: u8) {
+        self.contents.push(c);
+        self.width += 1;
+    }
+}
+
 fn get_file_name(name: &Path, strip: Option<&Path>) -> String {
     let mut name = match strip {
         Some(prefix) => name.strip_prefix(prefix).unwrap_or(name),
@@ -500,58 +1326,114 @@ fn display_file_name(path: &Path,
                      strip: Option<&Path>,
                      metadata: &Metadata,
                      options: &getopts::Matches)
-                     -> Cell {
-    let mut name = get_file_name(path, strip);
-
-    if !options.opt_present("long") {
-        name = get_inode(metadata, options) + &name;
+                     -> Name {
+    let mut name = Name::new(get_file_name(path, strip));
+    if enabled(options, "hyperlink") {
+        let (before, after) = hyperlink(path);
+        name.wrap(before.as_bytes(), after.as_bytes());
     }
 
     if options.opt_present("classify") {
         let file_type = metadata.file_type();
         if file_type.is_dir() {
-            name.push('/');
+            name.push(b'/');
         } else if file_type.is_symlink() {
-            name.push('@');
+            name.push(b'@');
         }
     }
 
-    if options.opt_present("long") && metadata.file_type().is_symlink() {
+    if long_format(options) && metadata.file_type().is_symlink() {
         if let Ok(target) = path.read_link() {
             // We don't bother updating width here because it's not used for long listings
-            let target_name = target.to_string_lossy().to_string();
-            name.push_str(" -> ");
-            name.push_str(&target_name);
+            name.contents.extend_from_slice(b" -> ");
+            name.contents.extend_from_slice(target.to_string_lossy().as_bytes());
         }
     }
 
-    name.into()
+    name
+}
+
+lazy_static! {
+    // percent-encoded for file:// URLs
+    static ref HOSTNAME: String = percent_encode(&hostname(), false);
 }
 
 #[cfg(unix)]
-fn color_name(name: String, typ: &str) -> String {
-    let mut typ = typ;
-    if !COLOR_MAP.contains_key(typ) {
+fn hostname() -> Vec<u8> {
+    use uucore::libc::{c_char, gethostname};
+
+    let mut name = vec![0u8; 256];
+    if unsafe { gethostname(name.as_mut_ptr() as *mut c_char, name.len()) } != 0 {
+        return Vec::new();
+    }
+    let len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+    name.truncate(len);
+    name
+}
+
+#[cfg(not(unix))]
+fn hostname() -> Vec<u8> {
+    Vec::new()
+}
+
+/// The OSC 8 escape sequences that go around a name to link it to `path`
+fn hyperlink(path: &Path) -> (String, &'static str) {
+    let absolute = fs::canonicalize(path).unwrap_or_else(|_| match env::current_dir() {
+        Ok(dir) => dir.join(path),
+        Err(_) => path.to_path_buf(),
+    });
+    (format!("\x1b]8;;file://{}{}\x1b\\", *HOSTNAME, url_path(&absolute)), "\x1b]8;;\x1b\\")
+}
+
+#[cfg(unix)]
+fn url_path(path: &Path) -> String {
+    use std::os::unix::ffi::OsStrExt;
+    percent_encode(path.as_os_str().as_bytes(), true)
+}
+
+#[cfg(not(unix))]
+fn url_path(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let path = path.trim_left_matches("//?/");
+    format!("/{}", percent_encode(path.as_bytes(), true)).replace("%3A", ":")
+}
+
+/// Percent-encode everything but the unreserved characters of RFC 3986 and,
+/// if `path` is set, slashes
+fn percent_encode(bytes: &[u8], path: bool) -> String {
+    let mut result = String::with_capacity(bytes.len());
+    for &b in bytes {
+        match b {
+            b'A'...b'Z' | b'a'...b'z' | b'0'...b'9' | b'-' | b'.' | b'_' | b'~' => result.push(b as char),
+            b'/' if path => result.push('/'),
+            _ => result.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    result
+}
+
+/// The escape sequences that go around the name of `path`, of the file
+/// type `typ`, to color it
+#[cfg(unix)]
+fn color_codes(typ: &str, path: &Path) -> Option<(Vec<u8>, Vec<u8>)> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let colors = match *LS_COLORS {
+        Some(ref colors) => colors,
+        None => return None,
+    };
+    let mut code = colors.code(typ);
+    if typ == "fi" {
+        // suffix patterns only apply to plain regular files
+        code = colors.suffix(path.file_name().unwrap_or(path.as_os_str()).as_bytes()).or(code);
+    } else if code.is_none() {
         if typ == "or" {
-            typ = "ln";
+            code = colors.code("ln");
         } else if typ == "mi" {
-            typ = "fi";
+            code = colors.code("fi");
         }
-    };
-    if let Some(code) = COLOR_MAP.get(typ) {
-        format!("{}{}{}{}{}{}{}{}",
-                *LEFT_CODE,
-                code,
-                *RIGHT_CODE,
-                name,
-                *END_CODE,
-                *LEFT_CODE,
-                *RESET_CODE,
-                *RIGHT_CODE,
-        )
-    } else {
-        name
     }
+    code.map(|code| (colors.start(code), colors.end()))
 }
 
 macro_rules! has {
@@ -564,32 +1446,31 @@ fn display_file_name(path: &Path,
                      strip: Option<&Path>,
                      metadata: &Metadata,
                      options: &getopts::Matches)
-                     -> Cell {
-    let mut name = get_file_name(path, strip);
-    if !options.opt_present("long") {
-        name = get_inode(metadata, options) + &name;
+                     -> Name {
+    let mut name = Name::new(get_file_name(path, strip));
+    if enabled(options, "hyperlink") {
+        let (before, after) = hyperlink(path);
+        name.wrap(before.as_bytes(), after.as_bytes());
     }
-    let mut width = UnicodeWidthStr::width(&*name);
 
-    let color = options.opt_present("color");
+    let color = use_color(options);
     let classify = options.opt_present("classify");
-    let ext;
 
     if color || classify {
         let file_type = metadata.file_type();
 
         let (code, sym) = if file_type.is_dir() {
-            ("di", Some('/'))
+            ("di", Some(b'/'))
         } else if file_type.is_symlink() {
             if path.exists() {
-                ("ln", Some('@'))
+                ("ln", Some(b'@'))
             } else {
-                ("or", Some('@'))
+                ("or", Some(b'@'))
             }
         } else if file_type.is_socket() {
-            ("so", Some('='))
+            ("so", Some(b'='))
         } else if file_type.is_fifo() {
-            ("pi", Some('|'))
+            ("pi", Some(b'|'))
         } else if file_type.is_block_device() {
             ("bd", None)
         } else if file_type.is_char_device() {
@@ -597,7 +1478,7 @@ fn display_file_name(path: &Path,
         } else if file_type.is_file() {
             let mode = metadata.mode() as mode_t;
             let sym = if has!(mode, S_IXUSR | S_IXGRP | S_IXOTH) {
-                Some('*')
+                Some(b'*')
             } else {
                 None
             };
@@ -615,9 +1496,6 @@ fn display_file_name(path: &Path,
                 ("ex", sym)
             } else if metadata.nlink() > 1 {
                 ("mh", sym)
-            } else if let Some(e) = path.extension() {
-                ext = format!("*.{}", e.to_string_lossy());
-                (ext.as_str(), None)
             } else {
                 ("fi", None)
             }
@@ -626,17 +1504,18 @@ fn display_file_name(path: &Path,
         };
 
         if color {
-            name = color_name(name, code);
+            if let Some((before, after)) = color_codes(code, path) {
+                name.wrap(&before, &after);
+            }
         }
         if classify {
             if let Some(s) = sym {
                 name.push(s);
-                width += 1;
             }
         }
     }
 
-    if options.opt_present("long") && metadata.file_type().is_symlink() {
+    if long_format(options) && metadata.file_type().is_symlink() {
         if let Ok(target) = path.read_link() {
             // We don't bother updating width here because it's not used for long listings
             let code = if target.exists() {
@@ -644,16 +1523,20 @@ fn display_file_name(path: &Path,
             } else {
                 "mi"
             };
-            let target_name = color_name(target.to_string_lossy().to_string(), code);
-            name.push_str(" -> ");
-            name.push_str(&target_name);
+            let colors = if use_color(options) { color_codes(code, &target) } else { None };
+            name.contents.extend_from_slice(b" -> ");
+            match colors {
+                Some((before, after)) => {
+                    name.contents.extend(before);
+                    name.contents.extend_from_slice(target.to_string_lossy().as_bytes());
+                    name.contents.extend(after);
+                }
+                None => name.contents.extend_from_slice(target.to_string_lossy().as_bytes()),
+            }
         }
     }
 
-    Cell {
-        contents: name,
-        width: width,
-    }
+    name
 }
 
 #[cfg(not(unix))]
@@ -747,3 +1630,19 @@ fn display_permissions(metadata: &Metadata) -> String {
 
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uucore::clock::FakeClock;
+
+    #[test]
+    fn test_is_recent() {
+        let clock = FakeClock::at(1_000_000_000);
+        assert!(is_recent(Timespec::new(1_000_000_000, 0), &clock));
+        assert!(is_recent(Timespec::new(1_000_000_000 - 86400 * 180, 0), &clock));
+        assert!(!is_recent(Timespec::new(1_000_000_000 - 86400 * 183, 0), &clock));
+        // from the future
+        assert!(!is_recent(Timespec::new(1_000_000_001, 0), &clock));
+    }
+}
@@ -15,9 +15,11 @@ extern crate libc;
 #[macro_use]
 extern crate uucore;
 
-use libc::mkfifo;
+use libc::{mkfifo, mode_t};
 use std::ffi::CString;
 use std::io::Error;
+use uucore::mode;
+use uucore::quoting::{quote, quote_path};
 
 static NAME: &'static str = "mkfifo";
 static VERSION: &'static str = env!("CARGO_PKG_VERSION");
@@ -25,13 +27,18 @@ static VERSION: &'static str = env!("CARGO_PKG_VERSION");
 pub fn uumain(args: Vec<String>) -> i32 {
     let mut opts = getopts::Options::new();
 
-    opts.optopt("m", "mode", "file permissions for the fifo", "(default 0666)");
+    opts.optopt("m", "mode", "set file permission bits to MODE, not a=rw - umask", "MODE");
+    opts.optflag("Z", "", "set the SELinux security context to default type");
+    opts.optflagopt("",
+                    "context",
+                    "like -Z, or if CTX is specified then set the SELinux or SMACK security context to CTX",
+                    "CTX");
     opts.optflag("h", "help", "display this help and exit");
     opts.optflag("V", "version", "output version information and exit");
 
     let matches = match opts.parse(&args[1..]) {
         Ok(m) => m,
-        Err(err) => panic!("{}", err),
+        Err(f) => crash!(1, "{}\nTry '{} --help' for more information.", f, NAME),
     };
 
     if matches.opt_present("version") {
@@ -39,37 +46,54 @@ pub fn uumain(args: Vec<String>) -> i32 {
         return 0;
     }
 
-    if matches.opt_present("help") || matches.free.is_empty() {
+    if matches.opt_present("help") {
         let msg = format!("{0} {1}
 
 Usage:
-  {0} [OPTIONS] NAME...
+  {0} [OPTION]... NAME...
 
-Create a FIFO with the given name.", NAME, VERSION);
+Create named pipes (FIFOs) with the given NAMEs.", NAME, VERSION);
 
         print!("{}", opts.usage(&msg));
-        if matches.free.is_empty() {
-            return 1;
-        }
         return 0;
     }
 
+    if matches.free.is_empty() {
+        disp_err!("missing operand");
+        return 1;
+    }
+
+    // fifos are made with whatever context the policy gives them, which is
+    // the default -Z asks for, but no other can be set
+    if let Some(context) = matches.opt_str("context") {
+        show_warning!("ignoring --context={}; setting a security context is not supported", quote(&context));
+    }
+
+    // with -m, fifos get exactly the mode given, whatever the umask
     let mode = match matches.opt_str("m") {
-        Some(m) => match usize::from_str_radix(&m, 8) {
-            Ok(m) => m,
-            Err(e)=> {
-                show_error!("invalid mode: {}", e);
+        Some(m) => match mode::parse_mode(0o666, &m, false) {
+            Ok(m) if m > 0o777 => {
+                show_info!("mode must specify only file permission bits");
+                return 1;
+            }
+            Ok(m) => Some(m as mode_t),
+            Err(e) => {
+                show_info!("invalid mode {}: {}", quote(&m), e);
                 return 1;
             }
         },
-        None => 0o666,
+        None => None,
     };
 
     let mut exit_status = 0;
     for f in &matches.free {
-        let err = unsafe { mkfifo(CString::new(f.as_bytes()).unwrap().as_ptr(), mode as libc::mode_t) };
+        let path = CString::new(f.as_bytes()).unwrap();
+        let err = match mode {
+            Some(mode) => mode::with_umask(0, || unsafe { mkfifo(path.as_ptr(), mode) }),
+            None => unsafe { mkfifo(path.as_ptr(), 0o666) },
+        };
         if err == -1 {
-            show_error!("creating '{}': {}", f, Error::last_os_error().raw_os_error().unwrap());
+            show_info!("cannot create fifo {}: {}", quote_path(f), Error::last_os_error());
             exit_status = 1;
         }
     }
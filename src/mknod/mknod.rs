@@ -11,10 +11,11 @@
 extern crate getopts;
 extern crate libc;
 
-mod parsemode;
-
 #[macro_use]
 extern crate uucore;
+use uucore::mode;
+use uucore::parse_number::NumberArg;
+use uucore::quoting::{quote, quote_path};
 
 use libc::{mode_t, dev_t};
 use libc::{S_IRUSR, S_IWUSR, S_IRGRP, S_IWGRP, S_IROTH, S_IWOTH, S_IFIFO, S_IFBLK, S_IFCHR};
@@ -22,6 +23,7 @@ use libc::{S_IRUSR, S_IWUSR, S_IRGRP, S_IWGRP, S_IROTH, S_IWOTH, S_IFIFO, S_IFBL
 use getopts::Options;
 
 use std::ffi::CString;
+use std::io;
 
 static NAME: &'static str = "mknod";
 static VERSION: &'static str = env!("CARGO_PKG_VERSION");
@@ -36,25 +38,35 @@ fn makedev(maj: u64, min: u64) -> dev_t {
 }
 
 #[cfg(windows)]
-fn _makenod(path: CString, mode: mode_t, dev: dev_t) -> i32 {
+fn makenod(path: &str, mode: mode_t, dev: dev_t, exact_mode: bool) -> io::Result<()> {
     panic!("Unsupported for windows platform")
 }
 
+/// Makes the node `path`, with `mode` as the umask leaves it, or exactly
+/// `mode` if `exact_mode`.
 #[cfg(unix)]
-fn _makenod(path: CString, mode: mode_t, dev: dev_t) -> i32 {
-    unsafe { libc::mknod(path.as_ptr(), mode, dev) }
+fn makenod(path: &str, mode: mode_t, dev: dev_t, exact_mode: bool) -> io::Result<()> {
+    let path = CString::new(path).expect("Failed to convert to CString");
+    let ret = if exact_mode {
+        mode::with_umask(0, || unsafe { libc::mknod(path.as_ptr(), mode, dev) })
+    } else {
+        unsafe { libc::mknod(path.as_ptr(), mode, dev) }
+    };
+    if ret == 0 { Ok(()) } else { Err(io::Error::last_os_error()) }
 }
 
 pub fn uumain(args: Vec<String>) -> i32 {
     let mut opts = Options::new();
 
-    // Linux-specific options, not implemented
-    // opts.optflag("Z", "", "set the SELinux security context to default type");
-    // opts.optopt("", "context", "like -Z, or if CTX is specified then set the SELinux or SMACK security context to CTX");
     opts.optopt("m",
                 "mode",
                 "set file permission bits to MODE, not a=rw - umask",
                 "MODE");
+    opts.optflag("Z", "", "set the SELinux security context to default type");
+    opts.optflagopt("",
+                    "context",
+                    "like -Z, or if CTX is specified then set the SELinux or SMACK security context to CTX",
+                    "CTX");
 
     opts.optflag("", "help", "display this help and exit");
     opts.optflag("", "version", "output version information and exit");
@@ -70,6 +82,9 @@ pub fn uumain(args: Vec<String>) -> i32 {
 
 Mandatory arguments to long options are mandatory for short options too.
   -m, --mode=MODE    set file permission bits to MODE, not a=rw - umask
+  -Z                 set the SELinux security context to default type
+      --context[=CTX]  like -Z, or if CTX is specified then set the SELinux
+                         or SMACK security context to CTX
       --help     display this help and exit
       --version  output version information and exit
 
@@ -93,101 +108,111 @@ for details about the options it supports.", NAME);
         return 0;
     }
 
-    let mut last_umask: mode_t = 0;
+    // nodes are made with whatever context the policy gives them, which is
+    // the default -Z asks for, but no other can be set
+    if let Some(context) = matches.opt_str("context") {
+        show_warning!("ignoring --context={}; setting a security context is not supported", quote(&context));
+    }
+
     let mut newmode: mode_t = MODE_RW_UGO;
-    if matches.opt_present("mode") {
-        match parsemode::parse_mode(matches.opt_str("mode")) {
+    let exact_mode = matches.opt_present("mode");
+    if let Some(m) = matches.opt_str("mode") {
+        match mode::parse_mode(MODE_RW_UGO as u32, &m, false) {
             Ok(parsed) => {
                 if parsed > 0o777 {
                     show_info!("mode must specify only file permission bits");
                     return 1;
                 }
-                newmode = parsed;
+                newmode = parsed as mode_t;
             }
             Err(e) => {
                 show_info!("{}", e);
                 return 1;
             }
         }
-        unsafe {
-            last_umask = libc::umask(0);
+    }
+
+    let args = &matches.free;
+    match args.len() {
+        0 => {
+            disp_err!("missing operand");
+            return 1;
         }
+        1 => {
+            disp_err!("missing operand after {}", quote(&args[0]));
+            return 1;
+        }
+        _ => {}
     }
 
-    let mut ret = 0i32;
-    match matches.free.len() {
-        0 => disp_err!("missing operand"),
-        1 => disp_err!("missing operand after ‘{}’", matches.free[0]),
-        _ => {
-            let args = &matches.free;
-            let c_str = CString::new(args[0].as_str()).expect("Failed to convert to CString");
-
-            // Only check the first character, to allow mnemonic usage like
-            // 'mknod /dev/rst0 character 18 0'.
-            let ch = args[1].chars().nth(0).expect("Failed to get the first char");
-
-            if ch == 'p' {
-                if args.len() > 2 {
-                    show_info!("{}: extra operand ‘{}’", NAME, args[2]);
-                    if args.len() == 4 {
-                        eprintln!("Fifos do not have major and minor device numbers.");
-                    }
-                    eprintln!("Try '{} --help' for more information.", NAME);
-                    return 1;
-                }
+    // Only check the first character, to allow mnemonic usage like
+    // 'mknod /dev/rst0 character 18 0'.
+    let ch = args[1].chars().nth(0).unwrap_or('\0');
 
-                ret = _makenod(c_str, S_IFIFO | newmode, 0);
-            } else {
-                if args.len() < 4 {
-                    show_info!("missing operand after ‘{}’", args[args.len() - 1]);
-                    if args.len() == 2 {
-                        eprintln!("Special files require major and minor device numbers.");
-                    }
-                    eprintln!("Try '{} --help' for more information.", NAME);
-                    return 1;
-                } else if args.len() > 4 {
-                    disp_err!("extra operand ‘{}’", args[4]);
-                    return 1;
-                } else if !"bcu".contains(ch) {
-                    disp_err!("invalid device type ‘{}’", args[1]);
-                    return 1;
-                }
+    let result = if ch == 'p' {
+        if args.len() > 2 {
+            show_info!("extra operand {}", quote(&args[2]));
+            if args.len() == 4 {
+                eprintln!("Fifos do not have major and minor device numbers.");
+            }
+            eprintln!("Try '{} --help' for more information.", NAME);
+            return 1;
+        }
 
-                let maj = args[2].parse::<u64>();
-                let min = args[3].parse::<u64>();
-                if maj.is_err() {
-                    show_info!("invalid major device number ‘{}’", args[2]);
-                    return 1;
-                } else if min.is_err() {
-                    show_info!("invalid minor device number ‘{}’", args[3]);
-                    return 1;
-                }
+        makenod(&args[0], S_IFIFO | newmode, 0, exact_mode)
+    } else {
+        if args.len() < 4 {
+            show_info!("missing operand after {}", quote(&args[args.len() - 1]));
+            if args.len() == 2 {
+                eprintln!("Special files require major and minor device numbers.");
+            }
+            eprintln!("Try '{} --help' for more information.", NAME);
+            return 1;
+        } else if args.len() > 4 {
+            disp_err!("extra operand {}", quote(&args[4]));
+            return 1;
+        } else if ch == '\0' || !"bcu".contains(ch) {
+            disp_err!("invalid device type {}", quote(&args[1]));
+            return 1;
+        }
 
-                let (maj, min) = (maj.unwrap(), min.unwrap());
-                let dev = makedev(maj, min);
-                if ch == 'b' {
-                    // block special file
-                    ret = _makenod(c_str, S_IFBLK | newmode, dev);
-                } else {
-                    // char special file
-                    ret = _makenod(c_str, S_IFCHR | newmode, dev);
-                }
+        // 0x and 0 before them as in C
+        let maj = NumberArg::new("major device number").c_radix(true).parse(&args[2]);
+        let min = NumberArg::new("minor device number").c_radix(true).parse(&args[3]);
+        let (maj, min) = match (maj, min) {
+            (Err(_), _) => {
+                show_info!("invalid major device number {}", quote(&args[2]));
+                return 1;
             }
+            (_, Err(_)) => {
+                show_info!("invalid minor device number {}", quote(&args[3]));
+                return 1;
+            }
+            (Ok(maj), Ok(min)) => (maj, min),
+        };
+        if maj > u32::max_value() as u64 || min > u32::max_value() as u64 {
+            show_info!("invalid device {} {}", args[2], args[3]);
+            return 1;
         }
-    }
 
-    if last_umask != 0 {
-        unsafe {
-            libc::umask(last_umask);
+        let dev = makedev(maj, min);
+        if ch == 'b' {
+            // block special file
+            makenod(&args[0], S_IFBLK | newmode, dev, exact_mode)
+        } else {
+            // char special file
+            makenod(&args[0], S_IFCHR | newmode, dev, exact_mode)
         }
-    }
-    if ret == -1 {
-        let c_str = CString::new(format!("{}: {}", NAME, matches.free[0]).as_str())
-                        .expect("Failed to convert to CString");
-        unsafe {
-            libc::perror(c_str.as_ptr());
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            show_info!("{}: {}", quote_path(&args[0]), e);
+            if ch != 'p' && e.raw_os_error() == Some(libc::EPERM) {
+                show_info!("only a privileged user can create block and character special files");
+            }
+            1
         }
     }
-
-    ret
 }
@@ -15,6 +15,7 @@ extern crate rand;
 
 #[macro_use]
 extern crate uucore;
+use uucore::quoting::quote;
 
 use std::env;
 use std::path::{PathBuf, is_separator};
@@ -107,7 +108,7 @@ pub fn uumain(args: Vec<String>) -> i32 {
     let tmpdir = match matches.opt_str("tmpdir") {
         Some(s) => {
             if PathBuf::from(prefix).is_absolute() {
-                show_info!("invalid template, ‘{}’; with --tmpdir, it may not be absolute", template);
+                show_info!("invalid template, {}; with --tmpdir, it may not be absolute", quote(template));
                 return 1;
             }
             PathBuf::from(s)
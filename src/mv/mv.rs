@@ -13,10 +13,20 @@ extern crate getopts;
 
 #[macro_use]
 extern crate uucore;
+use uucore::backup::{self, BackupMode};
+use uucore::copy::{self, copy_file};
+use uucore::preserve::Attributes;
+use uucore::quoting::{quote, quote_path};
 
+use std::cmp;
 use std::fs;
-use std::env;
-use std::io::{BufRead, BufReader, Result, stdin};
+use std::io::{Error, ErrorKind, Result, stdin};
+#[cfg(unix)]
+use std::ffi::CString;
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
 
 static NAME: &'static str = "mv";
@@ -32,21 +42,13 @@ pub struct Behaviour {
     verbose: bool,
 }
 
-#[derive(Clone, Eq, PartialEq)]
+#[derive(Clone, Copy, Eq, PartialEq)]
 pub enum OverwriteMode {
     NoClobber,
     Interactive,
     Force,
 }
 
-#[derive(Clone, Copy, Eq, PartialEq)]
-pub enum BackupMode {
-    NoBackup,
-    SimpleBackup,
-    NumberedBackup,
-    ExistingBackup,
-}
-
 pub fn uumain(args: Vec<String>) -> i32 {
     let mut opts = getopts::Options::new();
 
@@ -87,7 +89,16 @@ pub fn uumain(args: Vec<String>) -> i32 {
     let usage = opts.usage("Move SOURCE to DEST, or multiple SOURCE(s) to DIRECTORY.");
 
     let overwrite_mode = determine_overwrite_mode(&matches);
-    let backup_mode = determine_backup_mode(&matches);
+    let backup_mode = match determine_backup_mode(&matches) {
+        Ok(mode) => mode,
+        Err(msg) => {
+            show_error!("{}\n\
+                        Try '{} --help' for more information.",
+                        msg,
+                        NAME);
+            return 1;
+        }
+    };
 
     if overwrite_mode == OverwriteMode::NoClobber && backup_mode != BackupMode::NoBackup {
         show_error!("options --backup and --no-clobber are mutually exclusive\n\
@@ -96,7 +107,7 @@ pub fn uumain(args: Vec<String>) -> i32 {
         return 1;
     }
 
-    let backup_suffix = determine_backup_suffix(backup_mode, &matches);
+    let backup_suffix = backup::suffix(matches.opt_str("suffix").as_ref().map(|s| &s[..]));
 
     if matches.opt_present("T") && matches.opt_present("t") {
         show_error!("cannot combine --target-directory (-t) and --no-target-directory (-T)");
@@ -138,62 +149,26 @@ pub fn uumain(args: Vec<String>) -> i32 {
 }
 
 fn determine_overwrite_mode(matches: &getopts::Matches) -> OverwriteMode {
-    // This does not exactly match the GNU implementation:
-    // The GNU mv defaults to Force, but if more than one of the
-    // overwrite options are supplied, only the last takes effect.
-    // To default to no-clobber in that situation seems safer:
-    //
-    if matches.opt_present("no-clobber") {
-        OverwriteMode::NoClobber
-    } else if matches.opt_present("interactive") {
-        OverwriteMode::Interactive
-    } else {
-        OverwriteMode::Force
-    }
+    // As in GNU mv, whichever of -f, -i and -n comes last is the one that
+    // counts, and without any of them, files are overwritten.
+    let last = |name| matches.opt_positions(name).into_iter().max();
+    let modes = [(last("force"), OverwriteMode::Force),
+                 (last("interactive"), OverwriteMode::Interactive),
+                 (last("no-clobber"), OverwriteMode::NoClobber)];
+    modes.iter()
+        .filter_map(|&(position, mode)| position.map(|position| (position, mode)))
+        .max_by_key(|&(position, _)| position)
+        .map(|(_, mode)| mode)
+        .unwrap_or(OverwriteMode::Force)
 }
 
-fn determine_backup_mode(matches: &getopts::Matches) -> BackupMode {
-    if matches.opt_present("b") {
-        BackupMode::SimpleBackup
-    } else if matches.opt_present("backup") {
-        match matches.opt_str("backup") {
-            None => BackupMode::SimpleBackup,
-            Some(mode) => {
-                match &mode[..] {
-                    "simple" | "never" => BackupMode::SimpleBackup,
-                    "numbered" | "t" => BackupMode::NumberedBackup,
-                    "existing" | "nil" => BackupMode::ExistingBackup,
-                    "none" | "off" => BackupMode::NoBackup,
-                    x => {
-                        crash!(1, "invalid argument ‘{}’ for ‘backup type’\n\
-                                Try '{} --help' for more information.",
-                                    x,
-                                    NAME);
-                    }
-                }
-            }
-        }
-    } else {
-        BackupMode::NoBackup
-    }
-}
-
-fn determine_backup_suffix(backup_mode: BackupMode, matches: &getopts::Matches) -> String {
-    if matches.opt_present("suffix") {
-        match matches.opt_str("suffix") {
-            Some(x) => x,
-            None => {
-                crash!(1, "option '--suffix' requires an argument\n\
-                            Try '{} --help' for more information.",
-                            NAME);
-            }
-        }
-    } else {
-        if let (Ok(s), BackupMode::SimpleBackup) = (env::var("SIMPLE_BACKUP_SUFFIX"), backup_mode) {
-            s
-        } else {
-            "~".to_owned()
+fn determine_backup_mode(matches: &getopts::Matches) -> ::std::result::Result<BackupMode, String> {
+    match matches.opt_str("backup") {
+        Some(control) => BackupMode::parse(&control),
+        None if matches.opt_present("backup") || matches.opt_present("b") || matches.opt_present("suffix") => {
+            BackupMode::from_env()
         }
+        None => Ok(BackupMode::NoBackup),
     }
 }
 
@@ -222,23 +197,23 @@ fn exec(files: &[PathBuf], b: Behaviour) -> i32 {
             let source = &files[0];
             let target = &files[1];
             if !source.exists() {
-                show_error!("cannot stat ‘{}’: No such file or directory",
-                            source.display());
+                show_error!("cannot stat {}: No such file or directory",
+                            quote_path(source));
                 return 1;
             }
 
             if target.is_dir() {
                 if b.no_target_dir {
                     if !source.is_dir() {
-                        show_error!("cannot overwrite directory ‘{}’ with non-directory",
-                                    target.display());
+                        show_error!("cannot overwrite directory {} with non-directory",
+                                    quote_path(target));
                         return 1;
                     }
 
                     return match rename(source, target, &b) {
                         Err(e) => {
                             show_error!("{}", e);
-                            1
+                            exit_status(&e)
                         }
                         _ => 0,
                     };
@@ -249,19 +224,19 @@ fn exec(files: &[PathBuf], b: Behaviour) -> i32 {
 
             if let Err(e) = rename(source, target, &b) {
                 show_error!("{}", e);
-                return 1;
+                return exit_status(&e);
             }
         }
         _ => {
             if b.no_target_dir {
-                show_error!("mv: extra operand ‘{}’\n\
+                show_error!("mv: extra operand {}\n\
                             Try '{} --help' for more information.",
-                            files[2].display(),
+                            quote(&files[2]),
                             NAME);
                 return 1;
             }
             let target_dir = files.last().unwrap();
-            move_files_into_dir(&files[..files.len() - 1], target_dir, &b);
+            return move_files_into_dir(&files[..files.len() - 1], target_dir, &b);
         }
     }
     0
@@ -269,46 +244,56 @@ fn exec(files: &[PathBuf], b: Behaviour) -> i32 {
 
 fn move_files_into_dir(files: &[PathBuf], target_dir: &PathBuf, b: &Behaviour) -> i32 {
     if !target_dir.is_dir() {
-        show_error!("target ‘{}’ is not a directory", target_dir.display());
+        show_error!("target {} is not a directory", quote_path(target_dir));
         return 1;
     }
 
-    let mut all_successful = true;
+    let mut status = 0;
     for sourcepath in files.iter() {
         let targetpath = match sourcepath.as_os_str().to_str() {
             Some(name) => target_dir.join(name),
             None => {
-                show_error!("cannot stat ‘{}’: No such file or directory",
-                            sourcepath.display());
+                show_error!("cannot stat {}: No such file or directory",
+                            quote_path(sourcepath));
 
-                all_successful = false;
+                status = cmp::max(status, 1);
                 continue;
             }
         };
 
         if let Err(e) = rename(sourcepath, &targetpath, b) {
-            show_error!("mv: cannot move ‘{}’ to ‘{}’: {}",
-                        sourcepath.display(),
-                        targetpath.display(),
+            show_error!("mv: cannot move {} to {}: {}",
+                        quote_path(sourcepath),
+                        quote_path(&targetpath),
                         e);
-            all_successful = false;
+            status = cmp::max(status, exit_status(&e));
         }
     }
-    if all_successful {
-        0
-    } else {
-        1
-    }
+    status
+}
+
+// 1, or a status of its own for a copy that ran out of space or hit an I/O
+// error on the way to another file system.
+fn exit_status(e: &Error) -> i32 {
+    copy::exit_status(e).unwrap_or(1)
 }
 
 fn rename(from: &PathBuf, to: &PathBuf, b: &Behaviour) -> Result<()> {
     let mut backup_path = None;
 
     if to.exists() {
+        // with -u, a file not older than the one it would be replaced with
+        // is kept without asking
+        if b.update {
+            if try!(try!(fs::metadata(from)).modified()) <= try!(try!(fs::metadata(to)).modified()) {
+                return Ok(());
+            }
+        }
+
         match b.overwrite {
             OverwriteMode::NoClobber => return Ok(()),
             OverwriteMode::Interactive => {
-                print!("{}: overwrite ‘{}’? ", NAME, to.display());
+                eprint!("{}: overwrite {}? ", NAME, quote_path(to));
                 if !read_yes() {
                     return Ok(());
                 }
@@ -316,67 +301,138 @@ fn rename(from: &PathBuf, to: &PathBuf, b: &Behaviour) -> Result<()> {
             OverwriteMode::Force => {}
         };
 
-        backup_path = match b.backup {
-            BackupMode::NoBackup => None,
-            BackupMode::SimpleBackup => Some(simple_backup_path(to, &b.suffix)),
-            BackupMode::NumberedBackup => Some(numbered_backup_path(to)),
-            BackupMode::ExistingBackup => Some(existing_backup_path(to, &b.suffix)),
-        };
+        backup_path = backup::backup_path(to, b.backup, &b.suffix);
         if let Some(ref p) = backup_path {
             try!(fs::rename(to, p));
         }
-
-        if b.update {
-            if try!(try!(fs::metadata(from)).modified()) <= try!(try!(fs::metadata(to)).modified()) {
-                return Ok(());
-            }
-        }
     }
 
-    try!(fs::rename(from, to));
+    try!(move_path(from, to));
 
     if b.verbose {
-        print!("‘{}’ -> ‘{}’", from.display(), to.display());
+        print!("{} -> {}", quote_path(from), quote_path(to));
         match backup_path {
-            Some(path) => println!(" (backup: ‘{}’)", path.display()),
+            Some(path) => println!(" (backup: {})", quote_path(path)),
             None => println!(""),
         }
     }
     Ok(())
 }
 
-fn read_yes() -> bool {
-    let mut s = String::new();
-    match BufReader::new(stdin()).read_line(&mut s) {
-        Ok(_) => {
-            match s.chars().nth(0) {
-                Some(x) => x == 'y' || x == 'Y',
-                _ => false,
-            }
+// Rename `from` to `to`, or where they are on different file systems,
+// copy it with everything about it and remove it.
+fn move_path(from: &Path, to: &Path) -> Result<()> {
+    match fs::rename(from, to) {
+        Err(ref e) if is_cross_device(e) => {}
+        result => return result,
+    }
+
+    // replace what is there as rename would have
+    let is_dir = try!(fs::symlink_metadata(from)).is_dir();
+    if let Ok(metadata) = fs::symlink_metadata(to) {
+        match (is_dir, metadata.is_dir()) {
+            (true, true) => try!(fs::remove_dir(to)),
+            (false, true) => return Err(Error::new(ErrorKind::Other, "Is a directory")),
+            (true, false) => return Err(Error::new(ErrorKind::Other, "Not a directory")),
+            (false, false) => try!(fs::remove_file(to)),
         }
-        _ => false,
     }
+
+    if let Err(e) = copy_tree(from, to) {
+        // leave the source whole rather than half of it in each place
+        let _ = remove(to);
+        return Err(e);
+    }
+    remove(from)
 }
 
-fn simple_backup_path(path: &PathBuf, suffix: &str) -> PathBuf {
-    let mut p = path.to_string_lossy().into_owned();
-    p.push_str(suffix);
-    PathBuf::from(p)
+#[cfg(unix)]
+fn is_cross_device(e: &Error) -> bool {
+    e.raw_os_error() == Some(uucore::libc::EXDEV)
 }
 
-fn numbered_backup_path(path: &PathBuf) -> PathBuf {
-    (1_u64..)
-        .map(|i| path.with_extension(format!("~{}~", i)))
-        .skip_while(|p| p.exists())
-        .next()
-        .expect("cannot create backup")
+#[cfg(windows)]
+fn is_cross_device(e: &Error) -> bool {
+    // ERROR_NOT_SAME_DEVICE
+    e.raw_os_error() == Some(17)
 }
 
-fn existing_backup_path(path: &PathBuf, suffix: &str) -> PathBuf {
-    let test_path = path.with_extension("~1~");
-    if test_path.exists() {
-        numbered_backup_path(path)
+fn copy_tree(from: &Path, to: &Path) -> Result<()> {
+    let file_type = try!(fs::symlink_metadata(from)).file_type();
+    if file_type.is_dir() {
+        try!(fs::create_dir(to));
+        for entry in try!(fs::read_dir(from)) {
+            let entry = try!(entry);
+            try!(copy_tree(&entry.path(), &to.join(entry.file_name())));
+        }
+    } else if file_type.is_symlink() {
+        try!(copy_symlink(from, to));
+    } else if file_type.is_file() {
+        try!(copy_file(from, to));
     } else {
-        simple_backup_path(path, suffix)
+        try!(copy_special_file(from, to));
+    }
+    // after the contents, which would change the times of a directory
+    Attributes::all().preserve(from, to, false)
+}
+
+fn remove(path: &Path) -> Result<()> {
+    if try!(fs::symlink_metadata(path)).is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+#[cfg(unix)]
+fn copy_symlink(from: &Path, to: &Path) -> Result<()> {
+    ::std::os::unix::fs::symlink(try!(fs::read_link(from)), to)
+}
+
+#[cfg(windows)]
+fn copy_symlink(from: &Path, to: &Path) -> Result<()> {
+    let target = try!(fs::read_link(from));
+    if fs::metadata(from).map(|metadata| metadata.is_dir()).unwrap_or(false) {
+        ::std::os::windows::fs::symlink_dir(target, to)
+    } else {
+        ::std::os::windows::fs::symlink_file(target, to)
+    }
+}
+
+// FIFOs, devices and sockets are made anew, since reading them would not
+// copy them.
+#[cfg(unix)]
+fn copy_special_file(from: &Path, to: &Path) -> Result<()> {
+    let metadata = try!(fs::symlink_metadata(from));
+    let path = try!(CString::new(to.as_os_str().as_bytes()).map_err(|e| Error::new(ErrorKind::InvalidInput, e)));
+    let result = unsafe {
+        uucore::libc::mknod(path.as_ptr(),
+                            metadata.mode() as uucore::libc::mode_t,
+                            metadata.rdev() as uucore::libc::dev_t)
+    };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(Error::last_os_error())
+    }
+}
+
+#[cfg(windows)]
+fn copy_special_file(from: &Path, to: &Path) -> Result<()> {
+    copy_file(from, to).map(|_| ())
+}
+
+// Reads an answer from the shared buffer of stdin, so that whatever is
+// after it is left for the next question.
+fn read_yes() -> bool {
+    let mut s = String::new();
+    match stdin().read_line(&mut s) {
+        Ok(_) => {
+            match s.chars().nth(0) {
+                Some(x) => x == 'y' || x == 'Y',
+                _ => false,
+            }
+        }
+        _ => false,
     }
 }
@@ -1,6 +1,8 @@
 extern crate getopts;
 extern crate regex;
 
+use uucore::parse_number::NumberArg;
+
 // parse_style parses a style string into a NumberingStyle.
 fn parse_style(chars: &[char]) -> Result<::NumberingStyle, String> {
     if chars.len() == 1 && chars[0] == 'a' {
@@ -32,11 +34,9 @@ pub fn parse_options(settings: &mut ::Settings, opts: &getopts::Matches) -> Vec<
     }
     match opts.opt_str("n") {
         None => {},
-        Some(val) => match val.as_ref() {
-            "ln" => { settings.number_format = ::NumberFormat::Left; },
-            "rn" => { settings.number_format = ::NumberFormat::Right; },
-            "rz" => { settings.number_format = ::NumberFormat::RightZero; },
-            _ => { errs.push(String::from("Illegal value for -n")); },
+        Some(val) => match val.parse() {
+            Ok(format) => { settings.number_format = format; },
+            Err(message) => { errs.push(message); },
         }
     }
     match opts.opt_str("b") {
@@ -72,48 +72,36 @@ pub fn parse_options(settings: &mut ::Settings, opts: &getopts::Matches) -> Vec<
     match opts.opt_str("i") {
         None => {}
         Some(val) => {
-            let conv: Option<u64> = val.parse().ok();
-            match conv {
-              None => {
-                  errs.push(String::from("Illegal value for -i"));
-              }
-              Some(num) => { settings.line_increment = num }
+            match NumberArg::new("line number increment").parse(&val) {
+                Ok(num) => { settings.line_increment = num }
+                Err(msg) => { errs.push(msg); }
             }
         }
     }
     match opts.opt_str("w") {
         None => {}
         Some(val) => {
-            let conv: Option<usize> = val.parse().ok();
-            match conv {
-              None => {
-                  errs.push(String::from("Illegal value for -w"));
-              }
-              Some(num) => { settings.number_width = num }
+            match NumberArg::new("line number field width").min(1).parse_usize(&val) {
+                Ok(num) => { settings.number_width = num }
+                Err(msg) => { errs.push(msg); }
             }
         }
     }
     match opts.opt_str("v") {
         None => {}
         Some(val) => {
-            let conv: Option<u64> = val.parse().ok();
-            match conv {
-              None => {
-                  errs.push(String::from("Illegal value for -v"));
-              }
-              Some(num) => { settings.starting_line_number = num }
+            match NumberArg::new("starting line number").parse(&val) {
+                Ok(num) => { settings.starting_line_number = num }
+                Err(msg) => { errs.push(msg); }
             }
         }
     }
     match opts.opt_str("l") {
         None => {}
         Some(val) => {
-            let conv: Option<u64> = val.parse().ok();
-            match conv {
-              None => {
-                  errs.push(String::from("Illegal value for -l"));
-              }
-              Some(num) => { settings.join_blank_lines = num }
+            match NumberArg::new("line number of blank lines").min(1).parse(&val) {
+                Ok(num) => { settings.join_blank_lines = num }
+                Err(msg) => { errs.push(msg); }
             }
         }
     }
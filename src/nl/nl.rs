@@ -21,8 +21,8 @@ extern crate uucore;
 
 use std::fs::File;
 use std::io::{BufRead, BufReader, Read, stdin};
-use std::iter::repeat;
 use std::path::Path;
+use uucore::numbering::{LineNumbers, NumberFormat};
 
 mod helper;
 
@@ -43,7 +43,7 @@ pub struct Settings {
     starting_line_number: u64,
     line_increment: u64,
     join_blank_lines: u64,
-    number_width: usize, // Used with String::from_char, hence usize.
+    number_width: usize,
     // The format of the number and the (default value for)
     // renumbering each page.
     number_format: NumberFormat,
@@ -65,15 +65,6 @@ enum NumberingStyle {
     NumberForRegularExpression(regex::Regex)
 }
 
-// NumberFormat specifies how line numbers are output within their allocated
-// space. They are justified to the left or right, in the latter case with
-// the option of having all unused space to its left turned into leading zeroes.
-enum NumberFormat {
-    Left,
-    Right,
-    RightZero,
-}
-
 pub fn uumain(args: Vec<String>) -> i32 {
     let mut opts = getopts::Options::new();
 
@@ -159,18 +150,11 @@ pub fn uumain(args: Vec<String>) -> i32 {
 fn nl<T: Read> (reader: &mut BufReader<T>, settings: &Settings) {
     let regexp: regex::Regex = regex::Regex::new(r".?").unwrap();
     let mut line_no = settings.starting_line_number;
-    // The current line number's width as a string. Using to_string is inefficient
-    // but since we only do it once, it should not hurt.
-    let mut line_no_width = line_no.to_string().len();
-    let line_no_width_initial = line_no_width;
-    // Stores the smallest integer with one more digit than line_no, so that
-    // when line_no >= line_no_threshold, we need to use one more digit.
-    let mut line_no_threshold = 10u64.pow(line_no_width as u32);
+    let line_numbers = LineNumbers::new()
+        .format(settings.number_format)
+        .width(settings.number_width)
+        .separator(&settings.number_separator);
     let mut empty_line_count: u64 = 0;
-    let fill_char = match settings.number_format {
-        NumberFormat::RightZero => '0',
-        _ => ' '
-    };
     // Initially, we use the body's line counting settings
     let mut regex_filter = match settings.body_numbering {
         NumberingStyle::NumberForRegularExpression(ref re) => re,
@@ -226,8 +210,6 @@ fn nl<T: Read> (reader: &mut BufReader<T>, settings: &Settings) {
                     // line number and the line width
                     if settings.renumber {
                         line_no = settings.starting_line_number;
-                        line_no_width = line_no_width_initial;
-                        line_no_threshold = 10u64.pow(line_no_width as u32);
                     }
                     &settings.header_numbering
                 },
@@ -280,28 +262,10 @@ fn nl<T: Read> (reader: &mut BufReader<T>, settings: &Settings) {
         // way, start counting empties from zero once more.
         empty_line_count = 0;
         // A line number is to be printed.
-        let mut w: usize = 0;
-        if settings.number_width > line_no_width {
-            w = settings.number_width - line_no_width;
-        }
-        let fill: String = repeat(fill_char).take(w).collect();
-        match settings.number_format {
-            NumberFormat::Left => {
-                println!("{1}{0}{2}{3}", fill, line_no, settings.number_separator, line)
-            },
-            _ => {
-                println!("{0}{1}{2}{3}", fill, line_no, settings.number_separator, line)
-            }
-        }
+        println!("{}{}", line_numbers.render(line_no), line);
         // Now update the variables for the (potential) next
         // line.
         line_no += settings.line_increment;
-        while line_no >= line_no_threshold {
-            // The line number just got longer.
-            line_no_threshold *= 10;
-            line_no_width += 1;
-        }
-
     }
 }
 
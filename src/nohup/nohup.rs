@@ -24,6 +24,7 @@ use std::os::unix::prelude::*;
 use std::path::{Path, PathBuf};
 use std::env;
 use uucore::fs::{is_stderr_interactive, is_stdin_interactive, is_stdout_interactive};
+use uucore::process::{self, FdPolicy};
 
 static NAME: &'static str = "nohup";
 static VERSION: &'static str = env!("CARGO_PKG_VERSION");
@@ -37,6 +38,7 @@ extern {
 unsafe fn _vprocmgr_detach_from_console(_: u32) -> *const libc::c_int { std::ptr::null() }
 
 pub fn uumain(args: Vec<String>) -> i32 {
+    let inherited_fds = FdPolicy::inherited();
     let mut opts = getopts::Options::new();
 
     opts.optflag("h", "help", "Show help and exit");
@@ -60,6 +62,9 @@ pub fn uumain(args: Vec<String>) -> i32 {
         return 1
     }
     replace_fds();
+    if let Err(e) = process::prepare_fds(&inherited_fds) {
+        crash!(2, "Cannot prepare file descriptors: {}", e)
+    }
 
     unsafe { signal(SIGHUP, SIG_IGN) };
 
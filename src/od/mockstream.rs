@@ -2,6 +2,7 @@
 
 use std::io::{Cursor, Read, Result, Error, ErrorKind};
 use std::error::Error as errorError;
+use uucore::skiptake::Skip;
 
 /// `FailingMockStream` mocks a stream which will fail upon read or write
 ///
@@ -77,6 +78,8 @@ impl Read for FailingMockStream {
     }
 }
 
+impl Skip for FailingMockStream {}
+
 #[test]
 fn test_failing_mock_stream_read() {
     let mut s = FailingMockStream::new(ErrorKind::BrokenPipe, "The dog ate the ethernet cable", 1);
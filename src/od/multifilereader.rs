@@ -3,18 +3,19 @@ use std::io;
 use std::io::BufReader;
 use std::fs::File;
 use std::vec::Vec;
+use uucore::skiptake::{Skip, SkipTake};
 
 pub enum InputSource<'a> {
     FileName(&'a str),
     Stdin,
     #[allow(dead_code)]
-    Stream(Box<io::Read>),
+    Stream(Box<Skip>),
 }
 
 // MultifileReader - concatenate all our input, file or stdin.
 pub struct MultifileReader<'a> {
     ni: Vec<InputSource<'a>>,
-    curr_file: Option<Box<io::Read>>,
+    curr_file: Option<Box<Skip>>,
     any_err: bool,
 }
 
@@ -111,12 +112,47 @@ impl<'b> io::Read for MultifileReader<'b> {
     }
 }
 
+impl<'b> Skip for MultifileReader<'b> {
+    // Skips within the current file, moving on to the next one as each runs
+    // out, so regular files are skipped over without being read.
+    // Like read(), handles io errors itself by moving on to the next file.
+    fn skip(&mut self, n: u64) -> io::Result<u64> {
+        let mut skipped = 0;
+        while skipped < n {
+            match self.curr_file {
+                None => break,
+                Some(ref mut curr_file) => {
+                    match curr_file.skip(n - skipped) {
+                        Ok(k) => skipped += k,
+                        Err(e) => {
+                            eprintln!("{}: I/O: {}",
+                                executable!().split("::").next().unwrap(), // remove module
+                                e);
+                            self.any_err = true;
+                        }
+                    }
+                }
+            }
+            if skipped < n {
+                self.next_file();
+            }
+        }
+        Ok(skipped)
+    }
+}
+
 impl<'b> HasError for MultifileReader<'b> {
     fn has_error(&self) -> bool {
         self.any_err
     }
 }
 
+impl<R: HasError> HasError for SkipTake<R> {
+    fn has_error(&self) -> bool {
+        self.get_ref().has_error()
+    }
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -173,6 +209,24 @@ mod tests {
         // note: no retry on i/o error, so 5678 is missing
     }
 
+    #[test]
+    fn test_multi_file_reader_skip() {
+        let mut inputs = Vec::new();
+        inputs.push(InputSource::Stream(Box::new(Cursor::new(&b"abcd"[..]))));
+        inputs.push(InputSource::Stream(Box::new(FailingMockStream::new(ErrorKind::Other, "Failing", 1))));
+        inputs.push(InputSource::Stream(Box::new(Cursor::new(&b"ABCD"[..]))));
+        let mut v = [0; 5];
+
+        let mut sut = MultifileReader::new(inputs);
+
+        assert_eq!(sut.skip(2).unwrap(), 2);
+        assert_eq!(sut.skip(3).unwrap(), 3);
+        assert!(sut.has_error());
+        assert_eq!(sut.read(v.as_mut()).unwrap(), 3);
+        assert_eq!(v, [0x42, 0x43, 0x44, 0, 0]);
+        assert_eq!(sut.skip(1).unwrap(), 0);
+    }
+
     #[test]
     fn test_multi_file_reader_read_error_at_start() {
         let mut inputs = Vec::new();
@@ -17,7 +17,6 @@ extern crate half;
 extern crate uucore;
 
 mod multifilereader;
-mod partialreader;
 mod peekreader;
 mod byteorder_io;
 mod formatteriteminfo;
@@ -36,7 +35,7 @@ mod mockstream;
 use std::cmp;
 use byteorder_io::*;
 use multifilereader::*;
-use partialreader::*;
+use uucore::skiptake::SkipTake;
 use peekreader::*;
 use formatteriteminfo::*;
 use parse_nrofbytes::parse_number_of_bytes;
@@ -428,7 +427,7 @@ fn print_bytes(prefix: &str, input_decoder: &MemoryDecoder, output_info: &Output
 /// `skip_bytes` is the number of bytes skipped from the input
 /// `read_bytes` is an optional limit to the number of bytes to read
 fn open_input_peek_reader<'a>(input_strings: &'a Vec<String>, skip_bytes: usize,
-        read_bytes: Option<usize>) -> PeekReader<PartialReader<MultifileReader<'a>>> {
+        read_bytes: Option<usize>) -> PeekReader<SkipTake<MultifileReader<'a>>> {
     // should return  "impl PeekRead + Read + HasError" when supported in (stable) rust
     let inputs = input_strings
         .iter()
@@ -439,7 +438,7 @@ fn open_input_peek_reader<'a>(input_strings: &'a Vec<String>, skip_bytes: usize,
         .collect::<Vec<_>>();
 
     let mf = MultifileReader::new(inputs);
-    let pr = PartialReader::new(mf, skip_bytes, read_bytes);
+    let pr = SkipTake::new(mf, skip_bytes as u64, read_bytes.map(|n| n as u64));
     let input = PeekReader::new(pr);
     input
 }
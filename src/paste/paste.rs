@@ -14,10 +14,11 @@ extern crate getopts;
 #[macro_use]
 extern crate uucore;
 
-use std::io::{BufRead, BufReader, Read, stdin};
+use std::io::{BufReader, Read, stdin, stdout};
 use std::iter::repeat;
 use std::fs::File;
 use std::path::Path;
+use uucore::lines::{self, LineEnding};
 
 static NAME: &'static str = "paste";
 static VERSION: &'static str = env!("CARGO_PKG_VERSION");
@@ -27,6 +28,8 @@ pub fn uumain(args: Vec<String>) -> i32 {
 
     opts.optflag("s", "serial", "paste one file at a time instead of in parallel");
     opts.optopt("d", "delimiters", "reuse characters from LIST instead of TABs", "LIST");
+    opts.optflag("z", "zero-terminated", "line delimiter is NUL, not newline");
+    opts.optopt("", "output-terminator", "end output lines with NAME (newline, crlf or nul) rather than as input lines end", "NAME");
     opts.optflag("h", "help", "display this help and exit");
     opts.optflag("V", "version", "output version information and exit");
 
@@ -49,23 +52,37 @@ FILE, separated by TABs, to standard output.", NAME, VERSION);
     } else {
         let serial = matches.opt_present("serial");
         let delimiters = matches.opt_str("delimiters").unwrap_or("\t".to_owned());
-        paste(matches.free, serial, delimiters);
+        let ending = if matches.opt_present("zero-terminated") {
+            LineEnding::Nul
+        } else {
+            LineEnding::Newline
+        };
+        let out_ending = match matches.opt_str("output-terminator") {
+            Some(name) => match LineEnding::from_name(&name) {
+                Some(ending) => ending,
+                None => crash!(1, "invalid output terminator: '{}'", name),
+            },
+            None => ending,
+        };
+        paste(matches.free, serial, delimiters, ending, out_ending);
     }
 
     0
 }
 
-fn paste(filenames: Vec<String>, serial: bool, delimiters: String) {
-    let mut files: Vec<BufReader<Box<Read>>> = filenames.into_iter().map(|name|
-        BufReader::new(
+fn paste(filenames: Vec<String>, serial: bool, delimiters: String, ending: LineEnding, out_ending: LineEnding) {
+    let mut files: Vec<lines::Lines<BufReader<Box<Read>>>> = filenames.into_iter().map(|name|
+        lines::lines(BufReader::new(
             if name == "-" {
                 Box::new(stdin()) as Box<Read>
             } else {
                 let r = crash_if_err!(1, File::open(Path::new(&name)));
                 Box::new(r) as Box<Read>
             }
-        )
+        ), ending)
     ).collect();
+    let stdout = stdout();
+    let mut out = lines::Writer::new(stdout.lock(), out_ending);
 
     let delimiters: Vec<String> = unescape(delimiters).chars().map(|x| x.to_string()).collect();
     let mut delim_count = 0;
@@ -73,19 +90,12 @@ fn paste(filenames: Vec<String>, serial: bool, delimiters: String) {
     if serial {
         for file in &mut files {
             let mut output = String::new();
-            loop {
-                let mut line = String::new();
-                match file.read_line(&mut line) {
-                    Ok(0) => break,
-                    Ok(_) => {
-                        output.push_str(line.trim_right());
-                        output.push_str(&delimiters[delim_count % delimiters.len()]);
-                    }
-                    Err(e) => crash!(1, "{}", e.to_string())
-                }
+            for line in file {
+                output.push_str(&crash_if_err!(1, line));
+                output.push_str(&delimiters[delim_count % delimiters.len()]);
                 delim_count += 1;
             }
-            println!("{}", &output[..output.len()-1]);
+            crash_if_err!(1, out.write_line(&output[..output.len()-1]));
         }
     } else {
         let mut eof: Vec<bool> = repeat(false).take(files.len()).collect();
@@ -96,14 +106,13 @@ fn paste(filenames: Vec<String>, serial: bool, delimiters: String) {
                 if eof[i] {
                     eof_count += 1;
                 } else {
-                    let mut line = String::new();
-                    match file.read_line(&mut line) {
-                        Ok(0) => {
+                    match file.next() {
+                        None => {
                             eof[i] = true;
                             eof_count += 1;
                         }
-                        Ok(_) => output.push_str(line.trim_right()),
-                        Err(e) => crash!(1, "{}", e.to_string())
+                        Some(Ok(line)) => output.push_str(&line),
+                        Some(Err(e)) => crash!(1, "{}", e.to_string())
                     }
                 }
                 output.push_str(&delimiters[delim_count % delimiters.len()]);
@@ -112,7 +121,7 @@ fn paste(filenames: Vec<String>, serial: bool, delimiters: String) {
             if files.len() == eof_count {
                 break;
             }
-            println!("{}", &output[..output.len()-1]);
+            crash_if_err!(1, out.write_line(&output[..output.len()-1]));
             delim_count = 0;
         }
     }
@@ -208,10 +208,13 @@ static LONGHELP_BODY: &'static str = "
     %.10e means a scientific notation number with 10 significant digits
     %.10g means the same behavior for decimal and Sci. Note, respectively, and provides the shorter of each's output.
 
-    Like with GNU coreutils, the value after the decimal point is these outputs is parsed as a double first before being rendered to text. For both implementations do not expect meaningful precision past the 18th decimal place. When using a number of decimal places that is 18 or higher, you can expect variation in output between GNU coreutils printf and this printf at the 18th decimal place of +/- 1
+    Like with GNU coreutils, the value is parsed as a long double (a 64 bit significand, as on x86)
+    before being rendered to text, so digits past the 19th or so show where the number stops being
+    the one given: %.25g of 0.1 is 0.1000000000000000000013553. The same output is given on every
+    platform. Numbers too large or too small for a long double are printed as inf or 0 with a warning.
+    The length modifier L, as in %Lf, is accepted and changes nothing.
 
-   %f - floating point value presented in decimal, truncated and displayed to 6 decimal places by default.
-        There is not past-double behavior parity with Coreutils printf, values are not estimated or adjusted beyond input values.
+   %f - floating point value presented in decimal, rounded and displayed to 6 decimal places by default.
 
    %e or %E - floating point value presented in scientific notation
             7 significant digits by default
@@ -247,8 +250,9 @@ static LONGHELP_BODY: &'static str = "
 
     Hexadecimal: if the argument begins with 0x the proceeding characters will be interpreted
       will be interpreted as hex (base 16) for any numeric fields
-      for float fields, hexadecimal input results in a precision
-      limit (in converting input past the decimal point) of 10^-15
+      for float fields other than %a, a binary exponent may follow, as in 0x1.8p3
+
+    Scientific notation, inf and nan (for float fields): 1.5e3, -inf, nan
 
     Character Constant: if the argument begins with a single quote character, the first byte
       of the next character will be interpreted as an 8-bit unsigned integer. If there are
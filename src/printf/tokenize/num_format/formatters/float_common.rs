@@ -1,11 +1,11 @@
 use super::super::format_field::FormatField;
 use super::super::formatter::{InPrefix, Base, FormatPrimitive, warn_incomplete_conv, get_it_at};
-use super::base_conv;
-use super::base_conv::RadixDef;
+use super::super::num_format::warn_expected_numeric;
+use uucore::long_double::LongDouble;
+use cli;
 
 // if the memory, copy, and comparison cost of chars
 //  becomes an issue, we can always operate in vec<u8> here
-//  rather than just at de_hex
 
 pub struct FloatAnalysis {
     pub len_important: usize,
@@ -123,178 +123,59 @@ impl FloatAnalysis {
     }
 }
 
-fn de_hex(src: &str, before_decimal: bool) -> String {
-    let rten = base_conv::RadixTen;
-    let rhex = base_conv::RadixHex;
-    if before_decimal {
-        base_conv::base_conv_str(src, &rhex, &rten)
-    } else {
-        let as_arrnum_hex = base_conv::str_to_arrnum(src, &rhex);
-        let s = format!("{}",
-                        base_conv::base_conv_float(&as_arrnum_hex, rhex.get_max(), rten.get_max()));
-        if s.len() > 2 {
-            String::from(&s[2..])
-        } else {
-            // zero
-            s
-        }
+// parse the argument as a long double, warning as GNU printf does about
+// what is left of it or a value out of range, and format it as the field says
+pub fn get_primitive_ld(field: &FormatField, str_in: &str) -> FormatPrimitive {
+    let parsed = LongDouble::parse_prefix(str_in);
+    if parsed.len == 0 {
+        warn_expected_numeric(&String::from(str_in));
+    } else if parsed.len < str_in.len() {
+        warn_incomplete_conv(str_in);
+    } else if parsed.out_of_range {
+        cli::err_msg(&format!("{}: Numerical result out of range", str_in));
     }
-}
+    let precision = field.second_field.unwrap_or(6) as usize;
+    let formatted = parsed.value.format(*field.field_char, precision);
 
-// takes a string in,
-// truncates to a position,
-// bumps the last digit up one,
-// and if the digit was nine
-// propagate to the next, etc.
-fn _round_str_from(in_str: &str, position: usize) -> (String, bool) {
-
-    let mut it = in_str[0..position].chars();
-    let mut rev = String::new();
-    let mut i = position;
-    let mut finished_in_dec = false;
-    while let Some(c) = it.next_back() {
-        i -= 1;
-        match c {
-            '9' => {
-                rev.push('0');
-            }
-            e @ _ => {
-                rev.push(((e as u8) + 1) as char);
-                finished_in_dec = true;
-                break;
-            }                        
-        }
+    let mut f: FormatPrimitive = Default::default();
+    let mut number = &formatted[..];
+    if number.starts_with('-') {
+        f.prefix = Some(String::from("-"));
+        number = &number[1..];
     }
-    let mut fwd = String::from(&in_str[0..i]);
-    for ch in rev.chars().rev() {
-        fwd.push(ch);
+    if let Some(pos) = number.find(|c| c == 'e' || c == 'E') {
+        f.suffix = Some(String::from(&number[pos..]));
+        number = &number[..pos];
     }
-    (fwd, finished_in_dec)
+    let (pre_decimal, post_decimal) = match number.find('.') {
+        Some(pos) => (&number[..pos], &number[pos + 1..]),
+        None => (number, ""),
+    };
+    f.pre_decimal = Some(String::from(pre_decimal));
+    f.post_decimal = Some(String::from(post_decimal));
+    f
 }
 
-fn round_terminal_digit(before_dec: String,
-                        after_dec: String,
-                        position: usize)
-                        -> (String, String) {
-
-    if position < after_dec.len() {
-        let digit_at_pos: char;
-        {
-            digit_at_pos = (&after_dec[position..position + 1])
-                               .chars()
-                               .next()
-                               .expect("");
-        }
-        match digit_at_pos {
-            '5'...'9' => {
-                let (new_after_dec, finished_in_dec) = _round_str_from(&after_dec, position);
-                if finished_in_dec {
-                    return (before_dec, new_after_dec);
-                } else {
-                    let (new_before_dec, _) = _round_str_from(&before_dec, before_dec.len());
-                    return (new_before_dec, new_after_dec);
-                }
-                // TODO
-            }
-            _ => {}
-        }
+// the digits of a primitive from get_primitive_ld are already
+// the ones to print, however many decimal places that is
+pub fn primitive_to_str_ld(prim: &FormatPrimitive) -> String {
+    let mut final_str = String::new();
+    if let Some(ref prefix) = prim.prefix {
+        final_str.push_str(prefix);
     }
-    (before_dec, after_dec)
-}
-
-pub fn get_primitive_dec(inprefix: &InPrefix,
-                         str_in: &str,
-                         analysis: &FloatAnalysis,
-                         last_dec_place: usize,
-                         sci_mode: Option<bool>)
-                         -> FormatPrimitive {
-    let mut f: FormatPrimitive = Default::default();
-
-    // add negative sign section
-    if inprefix.sign == -1 {
-        f.prefix = Some(String::from("-"));
+    if let Some(ref pre_decimal) = prim.pre_decimal {
+        final_str.push_str(pre_decimal);
     }
-
-    // assign the digits before and after the decimal points
-    // to separate slices. If no digits after decimal point,
-    // assign 0
-    let (mut first_segment_raw, second_segment_raw) = match analysis.decimal_pos {
-        Some(pos) => (&str_in[..pos], &str_in[pos + 1..]),
-        None => (&str_in[..], "0"),
-    };
-    if first_segment_raw.len() == 0 {
-        first_segment_raw = "0";
-    }
-    // convert to string, de_hexifying if input is in hex.
-    let (first_segment, second_segment) = match inprefix.radix_in {
-        Base::Hex => {
-            (de_hex(first_segment_raw, true),
-             de_hex(second_segment_raw, false))
-        }
-        _ => {
-            (String::from(first_segment_raw),
-             String::from(second_segment_raw))
-        }
-    };
-    let (pre_dec_unrounded, post_dec_unrounded, mantissa) = if sci_mode.is_some() {
-        if first_segment.len() > 1 {
-            let mut post_dec = String::from(&first_segment[1..]);
-            post_dec.push_str(&second_segment);
-            (String::from(&first_segment[0..1]),
-             post_dec,
-             first_segment.len() as isize - 1)
-        } else {
-            match first_segment.chars().next() {
-                Some('0') => {
-                    let mut it = second_segment.chars().enumerate();
-                    let mut m: isize = 0;
-                    let mut pre = String::from("0");
-                    let mut post = String::from("0");
-                    while let Some((i, c)) = it.next() {
-                        match c {
-                            '0' => {}
-                            _ => {
-                                m = ((i as isize) + 1) * -1;
-                                pre = String::from(&second_segment[i..i + 1]);
-                                post = String::from(&second_segment[i + 1..]);
-                                break;
-                            }
-                        }
-                    }
-                    (pre, post, m)
-                }
-                Some(_) => (first_segment, second_segment, 0),
-                None => {
-                    panic!("float_common: no chars in first segment.");
-                }
-            }
+    if let Some(ref post_decimal) = prim.post_decimal {
+        if post_decimal.len() > 0 {
+            final_str.push('.');
+            final_str.push_str(post_decimal);
         }
-    } else {
-        (first_segment, second_segment, 0)
-    };
-
-    let (pre_dec_draft, post_dec_draft) = round_terminal_digit(pre_dec_unrounded,
-                                                               post_dec_unrounded,
-                                                               last_dec_place - 1);
-
-    f.pre_decimal = Some(pre_dec_draft);
-    f.post_decimal = Some(post_dec_draft);
-    if let Some(capitalized) = sci_mode {
-        let si_ind = if capitalized {
-            'E'
-        } else {
-            'e'
-        };
-        f.suffix = Some(if mantissa >= 0 {
-            format!("{}+{:02}", si_ind, mantissa)
-        } else {
-            // negative sign is considered in format!s
-            // leading zeroes
-            format!("{}{:03}", si_ind, mantissa)
-        });
     }
-
-    f
+    if let Some(ref suffix) = prim.suffix {
+        final_str.push_str(suffix);
+    }
+    final_str
 }
 
 pub fn primitive_to_str_common(prim: &FormatPrimitive, field: &FormatField) -> String {
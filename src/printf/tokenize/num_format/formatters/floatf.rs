@@ -1,7 +1,7 @@
 //! formatter for %f %F common-notation floating-point subs
 use super::super::format_field::FormatField;
 use super::super::formatter::{InPrefix, FormatPrimitive, Formatter};
-use super::float_common::{FloatAnalysis, get_primitive_dec, primitive_to_str_common};
+use super::float_common::{get_primitive_ld, primitive_to_str_ld};
 
 pub struct Floatf {
     as_num: f64,
@@ -14,23 +14,12 @@ impl Floatf {
 impl Formatter for Floatf {
     fn get_primitive(&self,
                      field: &FormatField,
-                     inprefix: &InPrefix,
+                     _: &InPrefix,
                      str_in: &str)
                      -> Option<FormatPrimitive> {
-        let second_field = field.second_field.unwrap_or(6) + 1;
-        let analysis = FloatAnalysis::analyze(&str_in,
-                                              inprefix,
-                                              None,
-                                              Some(second_field as usize),
-                                              false);
-        let f = get_primitive_dec(inprefix,
-                                  &str_in[inprefix.offset..],
-                                  &analysis,
-                                  second_field as usize,
-                                  None);
-        Some(f)
+        Some(get_primitive_ld(field, str_in))
     }
-    fn primitive_to_str(&self, prim: &FormatPrimitive, field: FormatField) -> String {
-        primitive_to_str_common(prim, &field)
+    fn primitive_to_str(&self, prim: &FormatPrimitive, _: FormatField) -> String {
+        primitive_to_str_ld(prim)
     }
 }
@@ -242,7 +242,7 @@ pub fn num_format(field: &FormatField, in_str_opt: Option<&String>) -> Option<St
                     tmp.pre_decimal = Some(
                         format!("{:o}", provided_num));
                 },
-                'e' | 'E' | 'g' | 'G' => {
+                'e' | 'E' | 'f' | 'F' | 'g' | 'G' => {
                     let as_str = format!("{}", provided_num);
                     let inprefix = get_inprefix(
                         &as_str,
@@ -237,9 +237,6 @@ impl SubParser {
                     break;
                 }
                 x if specifiers.binary_search(&x).is_ok() => {
-                    if !self.past_decimal {
-                        self.past_decimal = true;
-                    }
                     if !self.specifiers_found {
                         self.specifiers_found = true;
                     }
@@ -0,0 +1,126 @@
+/*
+ * This file is part of the uutils coreutils package.
+ *
+ * For the full copyright and license information, please view the LICENSE
+ * file that was distributed with this source code.
+ */
+
+// Directories as open file descriptors, with the names in them taken
+// relative to the directory rather than as whole paths, as GNU rm does
+// through fts.  However deep a tree goes, no path handed to the system is
+// longer than a name, and a directory swapped for a symbolic link between
+// looking and opening is not followed.
+
+use std::ffi::{CStr, CString};
+use std::io::{Error, ErrorKind, Result};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use uucore::libc;
+
+pub struct Dir {
+    fd: libc::c_int,
+}
+
+impl Dir {
+    /// The directory at `path`, unless it is a symbolic link.
+    pub fn open(path: &Path) -> Result<Dir> {
+        let path = try!(CString::new(path.as_os_str().as_bytes()).map_err(|e| Error::new(ErrorKind::InvalidInput, e)));
+        Dir::open_at(libc::AT_FDCWD, &path)
+    }
+
+    /// The directory `name` in this one, unless it is a symbolic link.
+    pub fn open_child(&self, name: &CStr) -> Result<Dir> {
+        Dir::open_at(self.fd, name)
+    }
+
+    /// The directory this one is in.
+    pub fn open_parent(&self) -> Result<Dir> {
+        Dir::open_at(self.fd, &CString::new("..").unwrap())
+    }
+
+    fn open_at(dirfd: libc::c_int, name: &CStr) -> Result<Dir> {
+        let flags = libc::O_RDONLY | libc::O_DIRECTORY | libc::O_NOFOLLOW | libc::O_CLOEXEC;
+        let fd = unsafe { libc::openat(dirfd, name.as_ptr(), flags) };
+        if fd < 0 {
+            Err(Error::last_os_error())
+        } else {
+            Ok(Dir { fd: fd })
+        }
+    }
+
+    pub fn stat(&self) -> Result<libc::stat> {
+        let mut stat: libc::stat = unsafe { ::std::mem::zeroed() };
+        if unsafe { libc::fstat(self.fd, &mut stat) } == 0 {
+            Ok(stat)
+        } else {
+            Err(Error::last_os_error())
+        }
+    }
+
+    /// What `name` in this directory is, not following it if it is a
+    /// symbolic link.
+    pub fn stat_child(&self, name: &CStr) -> Result<libc::stat> {
+        let mut stat: libc::stat = unsafe { ::std::mem::zeroed() };
+        if unsafe { libc::fstatat(self.fd, name.as_ptr(), &mut stat, libc::AT_SYMLINK_NOFOLLOW) } == 0 {
+            Ok(stat)
+        } else {
+            Err(Error::last_os_error())
+        }
+    }
+
+    /// The names in this directory, but for "." and "..".  They are all
+    /// read at once, so that no more than the directory itself is kept open.
+    pub fn entries(&self) -> Result<Vec<CString>> {
+        let fd = unsafe { libc::dup(self.fd) };
+        if fd < 0 {
+            return Err(Error::last_os_error());
+        }
+        let stream = unsafe { libc::fdopendir(fd) };
+        if stream.is_null() {
+            let err = Error::last_os_error();
+            unsafe { libc::close(fd) };
+            return Err(err);
+        }
+
+        // a name missed through an error reading shows up as the
+        // directory not being empty when it is removed
+        let mut names = Vec::new();
+        loop {
+            let entry = unsafe { libc::readdir(stream) };
+            if entry.is_null() {
+                break;
+            }
+            let name = unsafe { CStr::from_ptr((*entry).d_name.as_ptr()) };
+            if name.to_bytes() != b"." && name.to_bytes() != b".." {
+                names.push(name.to_owned());
+            }
+        }
+        unsafe { libc::closedir(stream) };
+        Ok(names)
+    }
+
+    /// Remove `name` from this directory, as an empty directory if `dir`.
+    pub fn remove(&self, name: &CStr, dir: bool) -> Result<()> {
+        let flags = if dir { libc::AT_REMOVEDIR } else { 0 };
+        if unsafe { libc::unlinkat(self.fd, name.as_ptr(), flags) } == 0 {
+            Ok(())
+        } else {
+            Err(Error::last_os_error())
+        }
+    }
+}
+
+impl Drop for Dir {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.fd) };
+    }
+}
+
+/// Whether `a` and `b` are the same file.
+pub fn same_file(a: &libc::stat, b: &libc::stat) -> bool {
+    a.st_dev == b.st_dev && a.st_ino == b.st_ino
+}
+
+pub fn is_dir(stat: &libc::stat) -> bool {
+    stat.st_mode & libc::S_IFMT == libc::S_IFDIR
+}
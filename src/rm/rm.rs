@@ -10,19 +10,39 @@
  */
 
 extern crate getopts;
+#[cfg(not(unix))]
 extern crate remove_dir_all;
+#[cfg(not(unix))]
 extern crate walkdir;
 
 #[macro_use]
 extern crate uucore;
 
+#[cfg(not(unix))]
 use std::collections::VecDeque;
+#[cfg(unix)]
+use std::ffi::{CStr, CString, OsStr};
 use std::fs;
 use std::io::{stdin, stderr, BufRead, Write};
 use std::ops::BitOr;
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
 use std::path::Path;
+#[cfg(not(unix))]
+use std::path::PathBuf;
+#[cfg(not(unix))]
 use remove_dir_all::remove_dir_all;
+#[cfg(not(unix))]
 use walkdir::{DirEntry, WalkDir};
+use uucore::fs::sync_parent_dir;
+#[cfg(unix)]
+use uucore::mounts::Boundary;
+
+#[cfg(unix)]
+mod dir;
+
+#[cfg(unix)]
+use dir::Dir;
 
 #[derive(Eq, PartialEq, Clone, Copy)]
 enum InteractiveMode {
@@ -34,11 +54,11 @@ enum InteractiveMode {
 struct Options {
     force: bool,
     interactive: InteractiveMode,
-    #[allow(dead_code)]
     one_fs: bool,
     preserve_root: bool,
     recursive: bool,
     dir: bool,
+    sync: bool,
     verbose: bool
 }
 
@@ -53,11 +73,12 @@ pub fn uumain(args: Vec<String>) -> i32 {
     opts.optflag("i", "", "prompt before every removal");
     opts.optflag("I", "", "prompt once before removing more than three files, or when removing recursively.  Less intrusive than -i, while still giving some protection against most mistakes");
     opts.optflagopt("", "interactive", "prompt according to WHEN: never, once (-I), or always (-i).  Without WHEN, prompts always", "WHEN");
-    opts.optflag("", "one-file-system", "when removing a hierarchy recursively, skip any directory that is on a file system different from that of the corresponding command line argument (NOT IMPLEMENTED)");
+    opts.optflag("", "one-file-system", "when removing a hierarchy recursively, skip any directory that is on a file system different from that of the corresponding command line argument");
     opts.optflag("", "no-preserve-root", "do not treat '/' specially");
     opts.optflag("", "preserve-root", "do not remove '/' (default)");
     opts.optflag("r", "recursive", "remove directories and their contents recursively");
     opts.optflag("d", "dir", "remove empty directories");
+    opts.optflag("", "sync", "sync the directory containing each operand after removing it, so the removal survives a crash (uutils extension)");
     opts.optflag("v", "verbose", "explain what is being done");
     opts.optflag("h", "help", "display this help and exit");
     opts.optflag("V", "version", "output version information and exit");
@@ -67,7 +88,18 @@ pub fn uumain(args: Vec<String>) -> i32 {
         Err(f) => crash!(1, "{}", f)
     };
 
-    let force = matches.opt_present("force");
+    let (force, interactive) = match interactive_mode(&matches) {
+        Ok(modes) => modes,
+        Err(val) => {
+            show_error!("invalid argument '{}' for '--interactive'\n\
+                        Valid arguments are:\n  \
+                        - 'never', 'no', 'none'\n  \
+                        - 'once'\n  \
+                        - 'always', 'yes'\n\
+                        Try '{} --help' for more information.", val, NAME);
+            return 1;
+        }
+    };
 
     if matches.opt_present("help") {
         println!("{} {}", NAME, VERSION);
@@ -97,39 +129,23 @@ pub fn uumain(args: Vec<String>) -> i32 {
     } else {
         let options = Options {
             force: force,
-            interactive: {
-                if matches.opt_present("i") {
-                    InteractiveMode::InteractiveAlways
-                } else if matches.opt_present("I") {
-                    InteractiveMode::InteractiveOnce
-                } else if matches.opt_present("interactive") {
-                    match &matches.opt_str("interactive").unwrap()[..] {
-                        "none" => InteractiveMode::InteractiveNone,
-                        "once" => InteractiveMode::InteractiveOnce,
-                        "always" => InteractiveMode::InteractiveAlways,
-                        val => {
-                            crash!(1, "Invalid argument to interactive ({})", val)
-                        }
-                    }
-                } else {
-                    InteractiveMode::InteractiveNone
-                }
-            },
+            interactive: interactive,
             one_fs: matches.opt_present("one-file-system"),
-            preserve_root: !matches.opt_present("no-preserve-root"),
+            preserve_root: last_position(&matches, "no-preserve-root") <= last_position(&matches, "preserve-root"),
             recursive: matches.opt_present("recursive"),
             dir: matches.opt_present("dir"),
+            sync: matches.opt_present("sync"),
             verbose: matches.opt_present("verbose")
         };
         if options.interactive == InteractiveMode::InteractiveOnce
                 && (options.recursive || matches.free.len() > 3) {
-            let msg =
-                if options.recursive {
-                    "Remove all arguments recursively? "
-                } else {
-                    "Remove all arguments? "
-                };
-            if !prompt(msg) {
+            let count = matches.free.len();
+            let msg = format!("{}: remove {} argument{}{}? ",
+                              NAME,
+                              count,
+                              if count == 1 { "" } else { "s" },
+                              if options.recursive { " recursively" } else { "" });
+            if !prompt(&msg) {
                 return 0;
             }
         }
@@ -142,16 +158,87 @@ pub fn uumain(args: Vec<String>) -> i32 {
     0
 }
 
-// TODO: implement one-file-system (this may get partially implemented in walkdir)
+// Where an option was last given, for those that cancel each other.
+fn last_position(matches: &getopts::Matches, name: &str) -> Option<usize> {
+    matches.opt_positions(name).into_iter().max()
+}
+
+// Whether to be forced and how to prompt, as the options leave it when taken
+// in order, as in GNU rm: -f is cancelled by all but --interactive=never,
+// and cancels them all in turn.
+fn interactive_mode(matches: &getopts::Matches) -> Result<(bool, InteractiveMode), String> {
+    let mut given = vec![];
+    for name in &["f", "i", "I", "interactive"] {
+        for position in matches.opt_positions(name) {
+            given.push((position, *name));
+        }
+    }
+    given.sort();
+    let whens = matches.opt_strs_pos("interactive");
+
+    let mut force = false;
+    let mut mode = InteractiveMode::InteractiveNone;
+    for (position, name) in given {
+        match name {
+            "f" => {
+                force = true;
+                mode = InteractiveMode::InteractiveNone;
+            }
+            "i" => {
+                force = false;
+                mode = InteractiveMode::InteractiveAlways;
+            }
+            "I" => {
+                force = false;
+                mode = InteractiveMode::InteractiveOnce;
+            }
+            _ => {
+                let when = whens.iter().find(|&&(p, _)| p == position).map(|&(_, ref when)| &when[..]);
+                mode = try!(interactive_when(when.unwrap_or("")));
+                if mode != InteractiveMode::InteractiveNone {
+                    force = false;
+                }
+            }
+        }
+    }
+    Ok((force, mode))
+}
+
+// The WHEN of --interactive, which may be cut short as long as it is only
+// the start of words that mean the same.  Without WHEN, it is always.
+fn interactive_when(when: &str) -> Result<InteractiveMode, String> {
+    if when.is_empty() {
+        return Ok(InteractiveMode::InteractiveAlways);
+    }
+    let words = [("never", InteractiveMode::InteractiveNone),
+                 ("no", InteractiveMode::InteractiveNone),
+                 ("none", InteractiveMode::InteractiveNone),
+                 ("once", InteractiveMode::InteractiveOnce),
+                 ("always", InteractiveMode::InteractiveAlways),
+                 ("yes", InteractiveMode::InteractiveAlways)];
+    if let Some(&(_, mode)) = words.iter().find(|&&(word, _)| word == when) {
+        return Ok(mode);
+    }
+    let mut modes = words.iter().filter(|&&(word, _)| word.starts_with(when)).map(|&(_, mode)| mode);
+    match modes.next() {
+        Some(mode) if modes.all(|other| other == mode) => Ok(mode),
+        _ => Err(when.to_owned()),
+    }
+}
+
 fn remove(files: Vec<String>, options: Options) -> bool {
     let mut had_err = false;
+    let mut removed: Vec<&Path> = Vec::new();
 
-    for filename in &files {
+    // resolved before anything is removed, which could change where they lead
+    let roots: Vec<bool> = files.iter().map(|filename| is_root(Path::new(filename))).collect();
+
+    for (filename, &is_root) in files.iter().zip(&roots) {
         let file = Path::new(filename);
         had_err = match file.symlink_metadata() {
             Ok(metadata) => {
                 if metadata.is_dir() {
-                    handle_dir(file, &options)
+                    handle_dir(file, is_root, &options)
                 } else {
                     remove_file(file, &options)
                 }
@@ -169,47 +256,45 @@ fn remove(files: Vec<String>, options: Options) -> bool {
                 }
             }
         }.bitor(had_err);
+
+        // several operands usually share a directory, so only sync each one once
+        if options.sync && file.symlink_metadata().is_err()
+                && !removed.iter().any(|f| f.parent() == file.parent()) {
+            removed.push(file);
+        }
+    }
+
+    for file in removed {
+        if let Err(e) = sync_parent_dir(file) {
+            show_error!("cannot sync directory of '{}': {}", file.display(), e);
+            had_err = true;
+        }
     }
 
     had_err
 }
 
-fn handle_dir(path: &Path, options: &Options) -> bool {
+// Whether `path` is the root directory, under whatever name.
+fn is_root(path: &Path) -> bool {
+    match fs::canonicalize(path) {
+        Ok(path) => path.has_root() && path.parent().is_none(),
+        Err(_) => false,
+    }
+}
+
+fn handle_dir(path: &Path, is_root: bool, options: &Options) -> bool {
     let mut had_err = false;
 
-    let is_root = path.has_root() && path.parent().is_none();
-    if options.recursive && (!is_root || !options.preserve_root) {
-        if options.interactive != InteractiveMode::InteractiveAlways {
-            // we need the extra crate because apparently fs::remove_dir_all() does not function
-            // correctly on Windows
-            if let Err(e) = remove_dir_all(path) {
-                had_err = true;
-                show_error!("could not remove '{}': {}", path.display(), e);
-            }
+    if options.recursive && is_root && options.preserve_root {
+        if path.has_root() && path.parent().is_none() {
+            show_error!("it is dangerous to operate recursively on '{}'", path.display());
         } else {
-            let mut dirs: VecDeque<DirEntry> = VecDeque::new();
-
-            for entry in WalkDir::new(path) {
-                match entry {
-                    Ok(entry) => {
-                        let file_type = entry.file_type();
-                        if file_type.is_dir() {
-                            dirs.push_back(entry);
-                        } else {
-                            had_err = remove_file(entry.path(), options).bitor(had_err);
-                        }
-                    }
-                    Err(e) => {
-                        had_err = true;
-                        show_error!("recursing in '{}': {}", path.display(), e);
-                    }
-                }
-            }
-
-            for dir in dirs.iter().rev() {
-                had_err = remove_dir(dir.path(), options).bitor(had_err);
-            }
+            show_error!("it is dangerous to operate recursively on '{}' (same as '/')", path.display());
         }
+        show_error!("use --no-preserve-root to override this failsafe");
+        had_err = true;
+    } else if options.recursive {
+        had_err = remove_tree(path, options);
     } else if options.dir && (!is_root || !options.preserve_root) {
         had_err = remove_dir(path, options).bitor(had_err);
     } else {
@@ -226,6 +311,197 @@ fn handle_dir(path: &Path, options: &Options) -> bool {
     had_err
 }
 
+// Remove the directory at `path` and everything in it, working down from
+// the directory itself rather than by whole paths, so that neither a very
+// deep tree nor a directory swapped for a symbolic link while it is being
+// removed sends the removal anywhere it should not go.
+#[cfg(unix)]
+fn remove_tree(path: &Path, options: &Options) -> bool {
+    // a directory on the way down, with the names in it still to go
+    struct Level {
+        name: Option<CString>,
+        stat: ::uucore::libc::stat,
+        entries: Vec<CString>,
+        // something in it was skipped, so it is to be left
+        keep: bool,
+    }
+
+    let mut had_err = false;
+    let (mut dir, root) = match Dir::open(path).and_then(|dir| dir.stat().map(|stat| (dir, stat))) {
+        Ok(opened) => opened,
+        Err(e) => {
+            show_error!("cannot remove '{}': {}", path.display(), e);
+            return true;
+        }
+    };
+    let entries = dir.entries().unwrap_or_else(|e| {
+        show_error!("recursing in '{}': {}", path.display(), e);
+        had_err = true;
+        vec![]
+    });
+    let boundary = if options.one_fs { Some(Boundary::new(root.st_dev as u64)) } else { None };
+    let mut levels = vec![Level { name: None, stat: root, entries: entries, keep: false }];
+    let mut current = path.to_path_buf();
+
+    loop {
+        let name = match levels.last_mut().unwrap().entries.pop() {
+            Some(name) => name,
+            None => {
+                let level = levels.pop().unwrap();
+                let name = match level.name {
+                    Some(name) => name,
+                    None => {
+                        levels.push(level);
+                        break;
+                    }
+                };
+                let parent = levels.last_mut().unwrap();
+                // back up by "..", which has to be the directory come down from
+                match dir.open_parent().and_then(|dir| dir.stat().map(|stat| (dir, stat))) {
+                    Ok((up, stat)) if dir::same_file(&stat, &parent.stat) => dir = up,
+                    _ => {
+                        show_error!("failed to return to the directory above '{}'", current.display());
+                        return true;
+                    }
+                }
+                if level.keep {
+                    parent.keep = true;
+                } else {
+                    had_err = remove_entry(&dir, &name, &current, true, options).bitor(had_err);
+                }
+                current.pop();
+                continue;
+            }
+        };
+
+        let entry_path = current.join(OsStr::from_bytes(name.to_bytes()));
+        let stat = match dir.stat_child(&name) {
+            Ok(stat) => stat,
+            Err(e) => {
+                show_error!("cannot remove '{}': {}", entry_path.display(), e);
+                had_err = true;
+                continue;
+            }
+        };
+        if !dir::is_dir(&stat) {
+            had_err = remove_entry(&dir, &name, &entry_path, false, options).bitor(had_err);
+            continue;
+        }
+        if boundary.as_ref().map_or(false, |boundary| boundary.crosses(stat.st_dev as u64, stat.st_ino as u64)) {
+            show_error!("skipping '{}', since it's on a different device", entry_path.display());
+            had_err = true;
+            levels.last_mut().unwrap().keep = true;
+            continue;
+        }
+        let down = dir.open_child(&name).and_then(|down| {
+            let opened = try!(down.stat());
+            if dir::same_file(&opened, &stat) {
+                Ok(down)
+            } else {
+                Err(::std::io::Error::new(::std::io::ErrorKind::Other, "directory replaced while being removed"))
+            }
+        });
+        match down {
+            Ok(down) => {
+                let entries = down.entries().unwrap_or_else(|e| {
+                    show_error!("recursing in '{}': {}", entry_path.display(), e);
+                    had_err = true;
+                    vec![]
+                });
+                levels.push(Level { name: Some(name), stat: stat, entries: entries, keep: false });
+                dir = down;
+                current = entry_path;
+            }
+            Err(e) => {
+                show_error!("recursing in '{}': {}", entry_path.display(), e);
+                had_err = true;
+            }
+        }
+    }
+
+    drop(dir);
+    if !levels[0].keep {
+        had_err = remove_dir(path, options).bitor(had_err);
+    }
+    had_err
+}
+
+#[cfg(not(unix))]
+fn remove_tree(path: &Path, options: &Options) -> bool {
+    let mut had_err = false;
+
+    if options.interactive != InteractiveMode::InteractiveAlways && !options.one_fs {
+        // we need the extra crate because apparently fs::remove_dir_all() does not function
+        // correctly on Windows
+        if let Err(e) = remove_dir_all(path) {
+            had_err = true;
+            show_error!("could not remove '{}': {}", path.display(), e);
+        }
+    } else {
+        let mut dirs: VecDeque<DirEntry> = VecDeque::new();
+        // directories on other file systems, which keep the ones they are in
+        let mut skipped: Vec<PathBuf> = Vec::new();
+        let root_device = path.symlink_metadata().ok().and_then(|metadata| device(&metadata));
+
+        let mut entries = WalkDir::new(path).into_iter();
+        while let Some(entry) = entries.next() {
+            match entry {
+                Ok(entry) => {
+                    let file_type = entry.file_type();
+                    if file_type.is_dir() {
+                        let entry_device = entry.metadata().ok().and_then(|metadata| device(&metadata));
+                        if options.one_fs && entry.depth() > 0 && entry_device != root_device {
+                            show_error!("skipping '{}', since it's on a different device", entry.path().display());
+                            had_err = true;
+                            skipped.push(entry.path().to_path_buf());
+                            entries.skip_current_dir();
+                        } else {
+                            dirs.push_back(entry);
+                        }
+                    } else {
+                        had_err = remove_file(entry.path(), options).bitor(had_err);
+                    }
+                }
+                Err(e) => {
+                    had_err = true;
+                    show_error!("recursing in '{}': {}", path.display(), e);
+                }
+            }
+        }
+
+        for dir in dirs.iter().rev() {
+            if skipped.iter().any(|skipped| skipped.starts_with(dir.path())) {
+                continue;
+            }
+            had_err = remove_dir(dir.path(), options).bitor(had_err);
+        }
+    }
+
+    had_err
+}
+
+#[cfg(not(unix))]
+fn device(_: &fs::Metadata) -> Option<u64> {
+    None
+}
+
+// Remove `name` from `dir`, as remove_file and remove_dir do by path.
+#[cfg(unix)]
+fn remove_entry(dir: &Dir, name: &CStr, path: &Path, is_dir: bool, options: &Options) -> bool {
+    if options.interactive == InteractiveMode::InteractiveAlways && !prompt_file(path, is_dir) {
+        return false;
+    }
+    match dir.remove(name, is_dir) {
+        Ok(_) => if options.verbose { println!("removed '{}'", path.display()); },
+        Err(e) => {
+            show_error!("removing '{}': {}", path.display(), e);
+            return true;
+        }
+    }
+
+    false
+}
+
 fn remove_dir(path: &Path, options: &Options) -> bool {
     let response =
         if options.interactive == InteractiveMode::InteractiveAlways {
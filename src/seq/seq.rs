@@ -1,6 +1,5 @@
 #![crate_name = "uu_seq"]
 
-// TODO: Make -w flag work with decimals
 // TODO: Support -f flag
 
 extern crate getopts;
@@ -9,7 +8,9 @@ extern crate getopts;
 extern crate uucore;
 
 use std::cmp;
+use std::i32;
 use std::io::{Write, stdout};
+use uucore::long_double::LongDouble;
 
 static NAME: &'static str = "seq";
 static VERSION: &'static str = env!("CARGO_PKG_VERSION");
@@ -21,13 +22,162 @@ struct SeqOptions {
     widths: bool
 }
 
-fn parse_float(mut s: &str) -> Result<f64, String> {
-    if s.starts_with("+") {
-        s = &s[1..];
+// A number from the command line, and how wide and with how many decimal
+// places it asks for the numbers to be printed, as GNU reckons it.
+struct Operand {
+    value: LongDouble,
+    width: usize,
+    /// unknown for hexadecimal numbers, which are printed as with "%Lg"
+    precision: Option<usize>,
+}
+
+impl Operand {
+    fn one() -> Operand {
+        Operand {
+            value: LongDouble::from(1),
+            width: 1,
+            precision: Some(0),
+        }
+    }
+}
+
+fn parse_operand(arg: &str) -> Result<Operand, String> {
+    let parsed = LongDouble::parse_prefix(arg);
+    if parsed.len == 0 || parsed.len < arg.len() ||
+       parsed.out_of_range && !parsed.value.is_zero() {
+        return Err(format!("invalid floating point argument: '{}'", arg));
+    }
+    if parsed.value.is_nan() {
+        return Err(format!("invalid 'not-a-number' argument: '{}'", arg));
     }
-    match s.parse() {
-        Ok(n) => Ok(n),
-        Err(e) => Err(format!("seq: invalid floating point argument `{}`: {}", s, e))
+    let mut operand = Operand {
+        value: parsed.value,
+        width: 0,
+        precision: None,
+    };
+    // spaces and '+' are not printed
+    let arg = arg.trim_left_matches(|c: char| c.is_whitespace() || c == '+');
+    let decimal_point = arg.find('.');
+    if decimal_point.is_none() && !arg.contains('p') {
+        operand.precision = Some(0);
+    }
+    if arg.contains(|c| c == 'x' || c == 'X') || !operand.value.is_finite() {
+        return Ok(operand);
+    }
+    let mut width = arg.len() as i64;
+    let mut fraction_len = 0;
+    if let Some(point) = decimal_point {
+        fraction_len = arg[point + 1..].find(|c| c == 'e' || c == 'E').unwrap_or(arg.len() - point - 1);
+        operand.precision = Some(fraction_len);
+        if fraction_len == 0 {
+            // "1." is printed as "1"
+            width -= 1;
+        } else if point == 0 || !arg.as_bytes()[point - 1].is_ascii_digit() {
+            // and ".5" as "0.5"
+            width += 1;
+        }
+    }
+    if let Some(e) = arg.find(|c| c == 'e' || c == 'E') {
+        let mut exponent = parse_exponent(&arg[e + 1..]);
+        let precision = operand.precision.unwrap_or(0) as i64;
+        let precision = if exponent < 0 {
+            precision - exponent
+        } else {
+            precision - cmp::min(precision, exponent)
+        };
+        operand.precision = Some(precision as usize);
+        // the exponent is not printed, but it moves the point
+        width -= (arg.len() - e) as i64;
+        if exponent < 0 {
+            if decimal_point.map_or(true, |point| e == point + 1) {
+                width += 1;
+            }
+            exponent = -exponent;
+        } else {
+            if decimal_point.is_some() && precision == 0 && fraction_len > 0 {
+                width -= 1;
+            }
+            exponent -= cmp::min(fraction_len as i64, exponent);
+        }
+        width += exponent;
+    }
+    operand.width = cmp::max(width, 0) as usize;
+    Ok(operand)
+}
+
+// The exponent after an 'e', read as strtol would.
+fn parse_exponent(s: &str) -> i64 {
+    let (negative, digits) = if s.starts_with('-') {
+        (true, &s[1..])
+    } else if s.starts_with('+') {
+        (false, &s[1..])
+    } else {
+        (false, s)
+    };
+    let exponent = digits.chars()
+        .take_while(|c| c.is_ascii_digit())
+        .fold(0i64, |n, c| cmp::min(n * 10 + c.to_digit(10).unwrap() as i64, i32::MAX as i64));
+    if negative { -exponent } else { exponent }
+}
+
+// How the numbers are printed: "%0*.*Lf" or "%Lg".
+enum Format {
+    Fixed { width: usize, precision: usize },
+    General,
+}
+
+impl Format {
+    fn new(first: &Operand, step: &Operand, last: &Operand, equal_width: bool) -> Format {
+        let (first_precision, step_precision, last_precision) = match (first.precision, step.precision, last.precision) {
+            (Some(first), Some(step), Some(last)) => (first as i64, step as i64, last as i64),
+            _ => return Format::General,
+        };
+        let precision = cmp::max(first_precision, step_precision);
+        if !equal_width {
+            return Format::Fixed {
+                width: 0,
+                precision: precision as usize,
+            };
+        }
+        let mut first_width = first.width as i64 + precision - first_precision;
+        let mut last_width = last.width as i64 + precision - last_precision;
+        if last_precision != 0 && precision == 0 {
+            // no point
+            last_width -= 1;
+        }
+        if last_precision == 0 && precision != 0 {
+            last_width += 1;
+        }
+        if first_precision == 0 && precision != 0 {
+            first_width += 1;
+        }
+        let width = cmp::max(first_width, last_width);
+        if width > i32::MAX as i64 {
+            return Format::General;
+        }
+        Format::Fixed {
+            width: cmp::max(width, 0) as usize,
+            precision: precision as usize,
+        }
+    }
+
+    fn format(&self, value: LongDouble) -> String {
+        match *self {
+            Format::Fixed { width, precision } => {
+                let s = value.format('f', precision);
+                if s.len() >= width {
+                    return s;
+                }
+                let padding = width - s.len();
+                if !value.is_finite() {
+                    return format!("{}{}", " ".repeat(padding), s);
+                }
+                // zeros go after the sign
+                let sign = if s.starts_with('-') { 1 } else { 0 };
+                format!("{}{}{}", &s[..sign], "0".repeat(padding), &s[sign..])
+            }
+            Format::General => value.format('g', 6),
+        }
     }
 }
 
@@ -71,8 +221,8 @@ fn parse_options(args: Vec<String>, options: &mut SeqOptions) -> Result<Vec<Stri
                 },
                 _ => {
                     if arg.len() > 1 && arg.chars().next().unwrap() == '-' {
-                        let argptr: *const String = &arg;  // escape from the borrow checker
-                        let mut chiter = unsafe { &(*argptr)[..] }.chars().skip(1);
+                        let flags = arg.clone();
+                        let mut chiter = flags.chars().skip(1);
                         let mut ch = ' ';
                         while match chiter.next() { Some(m) => { ch = m; true } None => false } {
                             match ch {
@@ -159,84 +309,68 @@ pub fn uumain(args: Vec<String>) -> i32 {
         crash!(1, "too {} operands.\nTry '{} --help' for more information.",
                if free.len() < 1 { "few" } else { "many" }, NAME);
     }
-    let mut largest_dec = 0;
-    let mut padding = 0;
-    let first = if free.len() > 1 {
-        let slice = &free[0][..];
-        let len = slice.len();
-        let dec = slice.find('.').unwrap_or(len);
-        largest_dec = len - dec;
-        padding = dec;
-        match parse_float(slice) {
-            Ok(n) => n,
-            Err(s) => { show_error!("{}", s); return 1; }
-        }
-    } else {
-        1.0
-    };
-    let step = if free.len() > 2 {
-        let slice = &free[1][..];
-        let len = slice.len();
-        let dec = slice.find('.').unwrap_or(len);
-        largest_dec = cmp::max(largest_dec, len - dec);
-        padding = cmp::max(padding, dec);
-        match parse_float(slice) {
-            Ok(n) => n,
-            Err(s) => { show_error!("{}", s); return 1; }
-        }
-    } else {
-        1.0
-    };
-    let last = {
-        let slice = &free[free.len() - 1][..];
-        padding = cmp::max(padding, slice.find('.').unwrap_or(slice.len()));
-        match parse_float(slice) {
-            Ok(n) => n,
-            Err(s) => { show_error!("{}", s); return 1; }
+    let mut operands = Vec::with_capacity(free.len());
+    for arg in &free {
+        match parse_operand(arg) {
+            Ok(operand) => operands.push(operand),
+            Err(msg) => {
+                show_error!("{}\nTry '{} --help' for more information.", msg, NAME);
+                return 1;
+            }
         }
-    };
-    if largest_dec > 0 {
-        largest_dec -= 1;
     }
+    let last = operands.pop().unwrap();
+    let step = if operands.len() > 1 { operands.pop().unwrap() } else { Operand::one() };
+    let first = operands.pop().unwrap_or_else(Operand::one);
+    if step.value.is_zero() {
+        show_error!("invalid Zero increment value: '{}'\nTry '{} --help' for more information.",
+                    free[1], NAME);
+        return 1;
+    }
+    let format = Format::new(&first, &step, &last, options.widths);
     let separator = escape_sequences(&options.separator[..]);
     let terminator = match options.terminator {
         Some(term) => escape_sequences(&term[..]),
         None => separator.clone()
     };
-    print_seq(first, step, last, largest_dec, separator, terminator, options.widths, padding);
+    print_seq(first.value, step.value, last.value, &format, separator, terminator);
 
     0
 }
 
-fn done_printing(next: f64, step: f64, last: f64) -> bool {
-    if step >= 0f64 {
-        next > last
-    } else {
+fn done_printing(next: LongDouble, step: LongDouble, last: LongDouble) -> bool {
+    if step < LongDouble::zero() {
         next < last
+    } else {
+        next > last
     }
 }
 
-fn print_seq(first: f64, step: f64, last: f64, largest_dec: usize, separator: String, terminator: String, pad: bool, padding: usize) {
-    let mut i = 0isize;
-    let mut value = first + i as f64 * step;
-    while !done_printing(value, step, last) {
-        let istr = format!("{:.*}", largest_dec, value);
-        let ilen = istr.len();
-        let before_dec = istr.find('.').unwrap_or(ilen);
-        if pad && before_dec < padding {
-            for _ in 0..(padding - before_dec) {
-                print!("0");
+fn print_seq(first: LongDouble, step: LongDouble, last: LongDouble, format: &Format, separator: String, terminator: String) {
+    if done_printing(first, step, last) {
+        return;
+    }
+    let mut value_str = format.format(first);
+    let mut i = 1u64;
+    loop {
+        print!("{}", value_str);
+        let next = first + LongDouble::from(i) * step;
+        let next_str = format.format(next);
+        if done_printing(next, step, last) {
+            // a number just past the last one that prints as it does, and
+            // not as the one before, is the last one with rounding errors,
+            // as in "seq 0 0.000001 0.000003"
+            let parsed = LongDouble::parse_prefix(&next_str);
+            if parsed.value != last || next_str == value_str {
+                break;
             }
+            print!("{}{}", separator, next_str);
+            break;
         }
-        print!("{}", istr);
+        print!("{}", separator);
+        value_str = next_str;
         i += 1;
-        value = first + i as f64 * step;
-        if !done_printing(value, step, last) {
-            print!("{}", separator);
-        }
-    }
-    if (first >= last && step < 0f64) || (first <= last && step > 0f64) {
-        print!("{}", terminator);
     }
+    print!("{}", terminator);
     crash_if_err!(1, stdout().flush());
 }
@@ -24,6 +24,7 @@ use std::path::{Path, PathBuf};
 
 #[macro_use]
 extern crate uucore;
+use uucore::fs::sync_parent_dir;
 
 static NAME: &'static str = "shred";
 static VERSION_STR: &'static str = "1.0.0";
@@ -40,6 +41,17 @@ const PATTERNS: [&'static [u8]; 22] = [
     b"\xDD",         b"\xEE"
 ];
 
+/// How --remove gets rid of the file once it has been overwritten
+#[derive(Clone, Copy, PartialEq)]
+enum RemoveMethod {
+    /// just unlink it
+    Unlink,
+    /// obfuscate its name before unlinking it
+    Wipe,
+    /// like Wipe, and also sync the directory after each change to it
+    WipeSync,
+}
+
 #[derive(Clone, Copy)]
 enum PassType<'a> {
     Pattern(&'a [u8]),
@@ -196,7 +208,7 @@ pub fn uumain(args: Vec<String>) -> i32 {
     // TODO: Add force option
     opts.optopt("n", "iterations", "overwrite N times instead of the default (3)", "N");
     opts.optopt("s", "size", "shred this many bytes (suffixes like K, M, G accepted)", "FILESIZE");
-    opts.optflag("u", "remove", "truncate and remove the file after overwriting; See below");
+    opts.optflagopt("u", "remove", "truncate and remove the file after overwriting; See below", "HOW");
     opts.optflag("v", "verbose", "show progress");
     opts.optflag("x", "exact", "do not round file sizes up to the next full block; \
                                 this is the default for non-regular files");
@@ -230,7 +242,23 @@ pub fn uumain(args: Vec<String>) -> i32 {
                        },
             None => 3
         };
-        let remove = matches.opt_present("remove");
+        let remove = if matches.opt_present("remove") {
+            match matches.opt_str("remove") {
+                None => Some(RemoveMethod::WipeSync),
+                Some(how) => match &how[..] {
+                    "unlink" => Some(RemoveMethod::Unlink),
+                    "wipe" => Some(RemoveMethod::Wipe),
+                    "wipesync" => Some(RemoveMethod::WipeSync),
+                    _ => {
+                        println!("{}: invalid argument '{}' for '--remove'", NAME, how);
+                        println!("Valid arguments are: 'unlink', 'wipe', 'wipesync'");
+                        return 1;
+                    }
+                },
+            }
+        } else {
+            None
+        };
         let size = get_size(matches.opt_str("size"));
         let exact = matches.opt_present("exact") && size.is_none(); // if -s is given, ignore -x
         let zero = matches.opt_present("zero");
@@ -252,6 +280,11 @@ fn show_help(opts: &getopts::Options) {
     println!("Delete FILE(s) if --remove (-u) is specified.  The default is not to remove");
     println!("the files because it is common to operate on device files like /dev/hda,");
     println!("and those files usually should not be removed.");
+    println!("The optional HOW parameter indicates how to remove a directory entry:");
+    println!("'unlink' => use a standard unlink call.");
+    println!("'wipe' => also first obfuscate bytes in the name.");
+    println!("'wipesync' => also sync each obfuscated byte to disk.");
+    println!("The default mode is 'wipesync', but note it can be expensive.");
     println!("");
     println!("CAUTION: Note that {} relies on a very important assumption:", NAME);
     println!("that the file system overwrites data in place.  This is the traditional");
@@ -328,7 +361,7 @@ fn pass_name(pass_type: &PassType) -> String {
     }
 }
 
-fn wipe_file(path_str: &str, n_passes: usize, remove: bool,
+fn wipe_file(path_str: &str, n_passes: usize, remove: Option<RemoveMethod>,
              size: Option<u64>, exact: bool, zero: bool, verbose: bool) {
 
     // Get these potential errors out of the way first
@@ -395,8 +428,8 @@ fn wipe_file(path_str: &str, n_passes: usize, remove: bool,
         }
     }
 
-    if remove {
-        do_remove(path, path_str, verbose).expect("Failed to remove file");
+    if let Some(method) = remove {
+        do_remove(path, path_str, method, verbose).expect("Failed to remove file");
     }
 }
 
@@ -427,7 +460,7 @@ fn get_file_size(path: &Path) -> Result<u64, io::Error> {
 
 // Repeatedly renames the file with strings of decreasing length (most likely all 0s)
 // Return the path of the file after its last renaming or None if error
-fn wipe_name(orig_path: &Path, verbose: bool) -> Option<PathBuf> {
+fn wipe_name(orig_path: &Path, method: RemoveMethod, verbose: bool) -> Option<PathBuf> {
     let file_name_len: usize = orig_path.file_name().unwrap().to_str().unwrap().len();
     
     let mut last_path: PathBuf = PathBuf::from(orig_path);
@@ -449,9 +482,8 @@ fn wipe_name(orig_path: &Path, verbose: bool) -> Option<PathBuf> {
                     }
                    
                     // Sync every file rename
-                    {
-                        let new_file: File = File::open(new_path.clone()).expect("Failed to open renamed file for syncing");
-                        new_file.sync_all().expect("Failed to sync renamed file");
+                    if method == RemoveMethod::WipeSync {
+                        sync_parent_dir(&new_path).expect("Failed to sync renamed file");
                     }
 
                     last_path = new_path;
@@ -471,15 +503,22 @@ fn wipe_name(orig_path: &Path, verbose: bool) -> Option<PathBuf> {
     Some(last_path)
 }
 
-fn do_remove(path: &Path, orig_filename: &str, verbose: bool) -> Result<(), io::Error> {
+fn do_remove(path: &Path, orig_filename: &str, method: RemoveMethod, verbose: bool) -> Result<(), io::Error> {
     if verbose {
         println!("{}: {}: removing", NAME, orig_filename);
     }
 
-    let renamed_path: Option<PathBuf> = wipe_name(&path, verbose);
+    let renamed_path: Option<PathBuf> = if method == RemoveMethod::Unlink {
+        Some(path.to_path_buf())
+    } else {
+        wipe_name(&path, method, verbose)
+    };
     match renamed_path {
         Some(rp) => {
-            try!(fs::remove_file(rp));
+            try!(fs::remove_file(&rp));
+            if method == RemoveMethod::WipeSync {
+                try!(sync_parent_dir(&rp));
+            }
         },
         None => ()
     }
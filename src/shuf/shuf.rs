@@ -19,6 +19,7 @@ use rand::Rng;
 use std::fs::File;
 use std::io::{stdin, stdout, BufReader, BufWriter, Read, Write};
 use std::usize::MAX as MAX_USIZE;
+use uucore::fs::create_output;
 
 enum Mode {
     Default,
@@ -35,6 +36,7 @@ pub fn uumain(args: Vec<String>) -> i32 {
     opts.optopt("i", "input-range", "treat each number LO through HI as an input line", "LO-HI");
     opts.optopt("n", "head-count", "output at most COUNT lines", "COUNT");
     opts.optopt("o", "output", "write result to FILE instead of standard output", "FILE");
+    opts.optflag("", "no-clobber", "fail rather than overwrite an existing output FILE");
     opts.optopt("", "random-source", "get random bytes from FILE", "FILE");
     opts.optflag("r", "repeat", "output lines can be repeated");
     opts.optflag("z", "zero-terminated", "end lines with 0 byte, not newline");
@@ -103,7 +105,7 @@ With no FILE, or when FILE is -, read standard input.", NAME, VERSION);
             },
             None => MAX_USIZE,
         };
-        let output = matches.opt_str("output");
+        let output = matches.opt_str("output").map(|path| (path, matches.opt_present("no-clobber")));
         let random = matches.opt_str("random-source");
 
         match mode {
@@ -183,11 +185,13 @@ fn find_seps(data: &mut Vec<&[u8]>, sep: u8) {
     }
 }
 
-fn shuf_bytes(input: &mut Vec<&[u8]>, repeat: bool, count: usize, sep: u8, output: Option<String>, random: Option<String>) {
+// All of the input has been read by now, so the output may be one of the
+// input files.
+fn shuf_bytes(input: &mut Vec<&[u8]>, repeat: bool, count: usize, sep: u8, output: Option<(String, bool)>, random: Option<String>) {
     let mut output = BufWriter::new(
         match output {
             None => Box::new(stdout()) as Box<Write>,
-            Some(s) => match File::create(&s[..]) {
+            Some((s, no_clobber)) => match create_output(&s[..], no_clobber) {
                 Ok(f) => Box::new(f) as Box<Write>,
                 Err(e) => crash!(1, "failed to open '{}' for writing: {}", &s[..], e),
             },
@@ -14,8 +14,9 @@ extern crate getopts;
 #[macro_use]
 extern crate uucore;
 
-use std::thread;
+use std::cmp;
 use std::time::Duration;
+use uucore::timer::{self, Clock};
 
 static NAME: &'static str = "sleep";
 static VERSION: &'static str = env!("CARGO_PKG_VERSION");
@@ -62,9 +63,11 @@ specified by the sum of their values.", NAME, VERSION);
 fn sleep(args: Vec<String>) {
     let sleep_dur = args.iter().fold(Duration::new(0, 0), |result, arg|
         match uucore::parse_time::from_str(&arg[..]) {
-            Ok(m) => m + result,
+            // one past what can be counted is as good as forever
+            Ok(m) => result.checked_add(m).unwrap_or(cmp::max(m, result)),
             Err(f) => crash!(1, "{}", f),
         });
 
-    thread::sleep(sleep_dur);
+    // as GNU's does, counting time the system spends suspended
+    timer::sleep(Clock::Boottime, sleep_dur);
 }
@@ -0,0 +1,278 @@
+/*
+ * This file is part of the uutils coreutils package.
+ *
+ * For the full copyright and license information, please view the LICENSE
+ * file that was distributed with this source code.
+ */
+
+//! Sort keys, as given with `-k POS1[,POS2]`, and the part of each line
+//! they pick out.
+//!
+//! A position is a field and a character in it, both counted from 1, and
+//! may be followed by the option letters of the key.  Fields are separated
+//! by the `-t` character, or else each starts with the blanks before it.
+//! Without POS2 a key runs to the end of the line, and without a character
+//! in POS2 to the end of its field.
+
+use super::SortMode;
+
+/// How a key is compared.
+#[derive(Clone, Copy)]
+pub struct KeyOptions {
+    pub mode: SortMode,
+    pub reverse: bool,
+    pub ignore_case: bool,
+    pub dictionary_order: bool,
+    pub ignore_nonprinting: bool,
+}
+
+#[derive(Clone, Copy)]
+pub struct KeyDef {
+    // counted from 0
+    start_field: usize,
+    start_char: usize,
+    skip_start_blanks: bool,
+    end_field: Option<usize>,
+    // counted from 1, 0 being the end of the field
+    end_char: usize,
+    skip_end_blanks: bool,
+    pub options: KeyOptions,
+}
+
+impl KeyDef {
+    /// The whole line, as sorted without -k.  `blanks` is -b, skipping the
+    /// blanks the line starts with.
+    pub fn whole_line(options: KeyOptions, blanks: bool) -> KeyDef {
+        KeyDef {
+            start_field: 0,
+            start_char: 0,
+            skip_start_blanks: blanks,
+            end_field: None,
+            end_char: 0,
+            skip_end_blanks: blanks,
+            options: options,
+        }
+    }
+
+    /// The key `spec` gives.  One with no option letters of its own is
+    /// compared as `global` is.
+    pub fn parse(spec: &str, global: &KeyDef) -> Result<KeyDef, String> {
+        let invalid = |why: &str| format!("{}: invalid field specification '{}'", why, spec);
+        let mut key = KeyDef::whole_line(KeyOptions {
+            mode: SortMode::Default,
+            reverse: false,
+            ignore_case: false,
+            dictionary_order: false,
+            ignore_nonprinting: false,
+        }, false);
+        let mut given = false;
+
+        let (field, mut rest) = try!(count(spec, "invalid number at field start"));
+        if field == 0 {
+            return Err(invalid("field number is zero"));
+        }
+        key.start_field = field - 1;
+        if rest.starts_with('.') {
+            let (offset, after) = try!(count(&rest[1..], "invalid number after '.'"));
+            if offset == 0 {
+                return Err(invalid("character offset is zero"));
+            }
+            key.start_char = offset - 1;
+            rest = after;
+        }
+        rest = key.set_options(rest, true, &mut given);
+
+        if rest.starts_with(',') {
+            let (field, after) = try!(count(&rest[1..], "invalid number after ','"));
+            if field == 0 {
+                return Err(invalid("field number is zero"));
+            }
+            key.end_field = Some(field - 1);
+            rest = after;
+            if rest.starts_with('.') {
+                let (offset, after) = try!(count(&rest[1..], "invalid number after '.'"));
+                key.end_char = offset;
+                rest = after;
+            }
+            rest = key.set_options(rest, false, &mut given);
+        }
+        if !rest.is_empty() {
+            return Err(invalid("stray character in field spec"));
+        }
+
+        if !given {
+            key.options = global.options;
+            key.skip_start_blanks = global.skip_start_blanks;
+            key.skip_end_blanks = global.skip_end_blanks;
+        }
+        Ok(key)
+    }
+
+    // Take the option letters `spec` starts with, `b` applying to the start
+    // of the key if `start` and to its end otherwise, and return the rest.
+    fn set_options<'a>(&mut self, spec: &'a str, start: bool, given: &mut bool) -> &'a str {
+        let letters = spec.find(|c| !"bdfghiMnRrV".contains(c)).unwrap_or(spec.len());
+        for letter in spec[..letters].chars() {
+            match letter {
+                'b' if start => self.skip_start_blanks = true,
+                'b' => self.skip_end_blanks = true,
+                'd' => self.options.dictionary_order = true,
+                'f' => self.options.ignore_case = true,
+                'g' => self.options.mode = SortMode::GeneralNumeric,
+                'h' => self.options.mode = SortMode::HumanNumeric,
+                'i' => self.options.ignore_nonprinting = true,
+                'M' => self.options.mode = SortMode::Month,
+                'n' => self.options.mode = SortMode::Numeric,
+                'R' => self.options.mode = SortMode::Random,
+                'r' => self.options.reverse = true,
+                _ => self.options.mode = SortMode::Version,
+            }
+            *given = true;
+        }
+        &spec[letters..]
+    }
+
+    /// The part of `line` the key covers, its fields separated by
+    /// `separator` if there is one and by blanks otherwise.
+    pub fn extract<'a>(&self, line: &'a str, separator: Option<char>) -> &'a str {
+        let start = self.start(line, separator);
+        let end = match self.end_field {
+            Some(_) => self.end(line, separator),
+            None => line.len(),
+        };
+        if start < end {
+            &line[start..end]
+        } else {
+            ""
+        }
+    }
+
+    fn start(&self, line: &str, separator: Option<char>) -> usize {
+        let mut pos = 0;
+        for _ in 0..self.start_field {
+            if pos >= line.len() {
+                break;
+            }
+            pos = match separator {
+                Some(separator) => match line[pos..].find(separator) {
+                    Some(found) => pos + found + separator.len_utf8(),
+                    None => line.len(),
+                },
+                None => skip_field(line, pos),
+            };
+        }
+        if self.skip_start_blanks {
+            pos = skip_blanks(line, pos);
+        }
+        advance(line, pos, self.start_char)
+    }
+
+    fn end(&self, line: &str, separator: Option<char>) -> usize {
+        // all of the last field, unless a character in it is given
+        let fields = self.end_field.unwrap() + if self.end_char == 0 { 1 } else { 0 };
+        let mut pos = 0;
+        for field in 0..fields {
+            if pos >= line.len() {
+                break;
+            }
+            pos = match separator {
+                Some(separator) => match line[pos..].find(separator) {
+                    // past the separator unless it ends the key
+                    Some(found) if field + 1 < fields || self.end_char != 0 => pos + found + separator.len_utf8(),
+                    Some(found) => pos + found,
+                    None => line.len(),
+                },
+                None => skip_field(line, pos),
+            };
+        }
+        if self.end_char != 0 {
+            // no further than the end of the field
+            let field_end = match separator {
+                Some(separator) => line[pos..].find(separator).map_or(line.len(), |end| pos + end),
+                None => skip_field(line, pos),
+            };
+            if self.skip_end_blanks {
+                pos = skip_blanks(&line[..field_end], pos);
+            }
+            pos = advance(&line[..field_end], pos, self.end_char);
+        }
+        pos
+    }
+}
+
+pub fn is_blank(c: char) -> bool {
+    c == ' ' || c == '\t' || c == '\n'
+}
+
+// Past the blanks at `pos` and the field after them.
+fn skip_field(line: &str, pos: usize) -> usize {
+    let pos = skip_blanks(line, pos);
+    line[pos..].find(is_blank).map_or(line.len(), |end| pos + end)
+}
+
+fn skip_blanks(line: &str, pos: usize) -> usize {
+    line[pos..].find(|c| !is_blank(c)).map_or(line.len(), |start| pos + start)
+}
+
+// `chars` characters on from `pos`, or the end of the line.
+fn advance(line: &str, pos: usize, chars: usize) -> usize {
+    line[pos..].char_indices().nth(chars).map_or(line.len(), |(offset, _)| pos + offset)
+}
+
+// The number `spec` starts with, too large ones being as large as can be,
+// and what follows it.
+fn count<'a>(spec: &'a str, what: &str) -> Result<(usize, &'a str), String> {
+    let digits = spec.find(|c: char| !c.is_digit(10)).unwrap_or(spec.len());
+    if digits == 0 {
+        return Err(format!("{}: invalid count at start of '{}'", what, spec));
+    }
+    Ok((spec[..digits].parse().unwrap_or(::std::usize::MAX), &spec[digits..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(spec: &str) -> KeyDef {
+        let global = KeyDef::whole_line(KeyOptions {
+            mode: SortMode::Default,
+            reverse: false,
+            ignore_case: false,
+            dictionary_order: false,
+            ignore_nonprinting: false,
+        }, false);
+        KeyDef::parse(spec, &global).unwrap()
+    }
+
+    #[test]
+    fn test_blank_separated() {
+        let line = "  ab  cd ef";
+        assert_eq!(key("2").extract(line, None), "  cd ef");
+        assert_eq!(key("2,2").extract(line, None), "  cd");
+        assert_eq!(key("2b,2").extract(line, None), "cd");
+        assert_eq!(key("2.2,2.3").extract(line, None), " c");
+        assert_eq!(key("2.2b,2.3b").extract(line, None), "d");
+        assert_eq!(key("1.4,1").extract(line, None), "b");
+        assert_eq!(key("4").extract(line, None), "");
+    }
+
+    #[test]
+    fn test_separator() {
+        let line = "a:b::d";
+        assert_eq!(key("2").extract(line, Some(':')), "b::d");
+        assert_eq!(key("2,2").extract(line, Some(':')), "b");
+        assert_eq!(key("3,3").extract(line, Some(':')), "");
+        assert_eq!(key("2,3.1").extract(line, Some(':')), "b:");
+        assert_eq!(key("4,5").extract(line, Some(':')), "d");
+    }
+
+    #[test]
+    fn test_invalid() {
+        let global = key("1");
+        assert_eq!(KeyDef::parse("0", &global).err().unwrap(), "field number is zero: invalid field specification '0'");
+        assert_eq!(KeyDef::parse("1.0", &global).err().unwrap(), "character offset is zero: invalid field specification '1.0'");
+        assert_eq!(KeyDef::parse("1,x", &global).err().unwrap(), "invalid number after ',': invalid count at start of 'x'");
+        assert_eq!(KeyDef::parse("1z", &global).err().unwrap(), "stray character in field spec: invalid field specification '1z'");
+        assert!(KeyDef::parse("1.2b,3.0nr", &global).is_ok());
+    }
+}
@@ -0,0 +1,104 @@
+/*
+ * This file is part of the uutils coreutils package.
+ *
+ * For the full copyright and license information, please view the LICENSE
+ * file that was distributed with this source code.
+ */
+
+//! Sorting on more than one thread: the lines are cut into a chunk for each
+//! thread, the chunks are sorted at the same time, and then merged in pairs,
+//! again at the same time, until one is left.  Where lines compare equal the
+//! one from the earlier chunk is taken first, so that the sort is as stable
+//! as sorting on a single thread.
+
+use std::cmp::{self, Ordering};
+use std::mem;
+use std::thread;
+
+/// Fewer lines than this to a thread are sorted quicker on one.
+const MIN_CHUNK: usize = 8192;
+
+/// How many threads there are CPUs for.
+pub fn available_threads() -> usize {
+    thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+/// Sort `items` stably by `compare` on up to `threads` threads.
+pub fn sort_by<T, F>(items: &mut Vec<T>, threads: usize, compare: F)
+    where T: Send, F: Fn(&T, &T) -> Ordering + Sync
+{
+    let threads = cmp::min(threads, items.len() / MIN_CHUNK);
+    if threads <= 1 {
+        items.sort_by(|a, b| compare(a, b));
+        return;
+    }
+
+    let chunk = (items.len() + threads - 1) / threads;
+    let mut chunks = Vec::with_capacity(threads);
+    let mut rest = mem::replace(items, Vec::new());
+    while rest.len() > chunk {
+        let tail = rest.split_off(chunk);
+        chunks.push(rest);
+        rest = tail;
+    }
+    chunks.push(rest);
+
+    let compare = &compare;
+    thread::scope(|scope| {
+        for chunk in &mut chunks {
+            scope.spawn(move || chunk.sort_by(|a, b| compare(a, b)));
+        }
+    });
+
+    while chunks.len() > 1 {
+        let mut pairs = Vec::with_capacity((chunks.len() + 1) / 2);
+        let mut drain = chunks.into_iter();
+        while let Some(first) = drain.next() {
+            pairs.push((first, drain.next()));
+        }
+        chunks = thread::scope(|scope| {
+            let merging: Vec<_> = pairs.into_iter().map(|(first, second)| {
+                scope.spawn(move || match second {
+                    Some(second) => merge(first, second, compare),
+                    None => first,
+                })
+            }).collect();
+            merging.into_iter().map(|merged| merged.join().unwrap()).collect()
+        });
+    }
+    *items = chunks.pop().unwrap();
+}
+
+// The sorted `first` and `second` as one, taking from `first` when an item
+// from each compares equal.
+fn merge<T, F>(first: Vec<T>, second: Vec<T>, compare: &F) -> Vec<T>
+    where F: Fn(&T, &T) -> Ordering
+{
+    let mut merged = Vec::with_capacity(first.len() + second.len());
+    let mut first = first.into_iter().peekable();
+    let mut second = second.into_iter().peekable();
+    loop {
+        let take_second = match (first.peek(), second.peek()) {
+            (Some(a), Some(b)) => compare(b, a) == Ordering::Less,
+            (Some(_), None) => false,
+            (None, Some(_)) => true,
+            (None, None) => break,
+        };
+        merged.push(if take_second { second.next() } else { first.next() }.unwrap());
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stable() {
+        let mut items: Vec<(usize, usize)> = (0..100000).map(|i| ((i * 7919) % 13, i)).collect();
+        let mut expected = items.clone();
+        expected.sort_by_key(|item| item.0);
+        sort_by(&mut items, 5, |a, b| a.0.cmp(&b.0));
+        assert_eq!(items, expected);
+    }
+}
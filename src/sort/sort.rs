@@ -12,60 +12,93 @@
 #![allow(dead_code)]
 
 extern crate getopts;
-extern crate semver;
 
 #[macro_use]
 extern crate uucore;
 extern crate itertools;
 
+mod key;
+mod parallel;
+mod spill;
+
+use std::borrow::Cow;
 use std::cmp::Ordering;
 use std::collections::BinaryHeap;
+use std::collections::hash_map::{DefaultHasher, RandomState};
 use std::fs::File;
-use std::io::{BufRead, BufReader, BufWriter, Lines, Read, stdin, stdout, Write};
-use std::mem::replace;
-use std::path::Path;
-use uucore::fs::is_stdin_interactive;
-use semver::Version;
-use itertools::Itertools; // for Iterator::dedup()
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, BufReader, BufWriter, Read, stdin, stdout, Write};
+use std::env;
+use std::mem::{replace, size_of};
+use std::path::{Path, PathBuf};
+use uucore::fs::{is_stdin_interactive, create_output, FileId};
+use uucore::lines::{self, LineEnding, Lines};
+use uucore::numcompare;
+use uucore::version_cmp::version_cmp;
+use itertools::Itertools; // for Iterator::coalesce()
+use key::{KeyDef, KeyOptions};
 
 static NAME: &'static str = "sort";
 static VERSION: &'static str = env!("CARGO_PKG_VERSION");
 
+static SALT_LEN: usize = 16;
+
 static DECIMAL_PT: char = '.';
 static THOUSANDS_SEP: char = ',';
 
+#[derive(Clone, Copy, PartialEq)]
 enum SortMode {
     Numeric,
+    GeneralNumeric,
     HumanNumeric,
     Month,
     Version,
+    Random,
     Default,
 }
 
 struct Settings {
-    mode: SortMode,
     merge: bool,
     reverse: bool,
     outfile: Option<String>,
+    no_clobber: bool,
     stable: bool,
     unique: bool,
     check: bool,
-    ignore_case: bool,
-    compare_fns: Vec<fn(&String, &String) -> Ordering>,
+    check_silent: bool,
+    line_ending: LineEnding,
+    decompress: bool,
+    buffer_size: Option<usize>,
+    batch_size: usize,
+    parallel: usize,
+    tmp_dir: Option<String>,
+    compress_program: Option<String>,
+    keys: Vec<KeyDef>,
+    separator: Option<char>,
+    salt: Vec<u8>,
 }
 
 impl Default for Settings {
     fn default() -> Settings {
         Settings {
-            mode: SortMode::Default,
             merge: false,
             reverse: false,
             outfile: None,
+            no_clobber: false,
             stable: false,
             unique: false,
             check: false,
-            ignore_case: false,
-            compare_fns: Vec::new(),
+            check_silent: false,
+            line_ending: LineEnding::native(),
+            decompress: false,
+            buffer_size: default_buffer_size(),
+            batch_size: 16,
+            parallel: parallel::available_threads(),
+            tmp_dir: None,
+            compress_program: None,
+            keys: Vec::new(),
+            separator: None,
+            salt: Vec::new(),
         }
     }
 }
@@ -73,6 +106,8 @@ impl Default for Settings {
 struct MergeableFile<'a> {
     lines: Lines<BufReader<Box<Read>>>,
     current_line: String,
+    // equal lines are taken from earlier files first
+    index: usize,
     settings: &'a Settings,
 }
 
@@ -81,7 +116,9 @@ struct MergeableFile<'a> {
 // trick it into the right order by calling reverse() here.
 impl<'a> Ord for MergeableFile<'a> {
     fn cmp(&self, other: &MergeableFile) -> Ordering {
-        compare_by(&self.current_line, &other.current_line, &self.settings).reverse()
+        compare_by(&self.current_line, &other.current_line, &self.settings)
+            .then(self.index.cmp(&other.index))
+            .reverse()
     }
 }
 
@@ -93,7 +130,7 @@ impl<'a> PartialOrd for MergeableFile<'a> {
 
 impl<'a> PartialEq for MergeableFile<'a> {
     fn eq(&self, other: &MergeableFile) -> bool {
-        Ordering::Equal == compare_by(&self.current_line, &other.current_line, &self.settings)
+        self.cmp(other) == Ordering::Equal
     }
 }
 
@@ -101,6 +138,7 @@ impl<'a> Eq for MergeableFile<'a> {}
 
 struct FileMerger<'a> {
     heap: BinaryHeap<MergeableFile<'a>>,
+    files: usize,
     settings: &'a Settings,
 }
 
@@ -108,20 +146,24 @@ impl<'a> FileMerger<'a> {
     fn new(settings: &'a Settings) -> FileMerger<'a> {
         FileMerger {
             heap: BinaryHeap::new(),
+            files: 0,
             settings: settings,
         }
     }
     fn push_file(&mut self, mut lines: Lines<BufReader<Box<Read>>>){
+        self.files += 1;
         match lines.next() {
             Some(Ok(next_line)) => {
                 let mergeable_file = MergeableFile {
                     lines: lines,
                     current_line: next_line,
+                    index: self.files,
                     settings: &self.settings,
                 };
                 self.heap.push(mergeable_file);
             }
-            _ => {}
+            Some(Err(e)) => crash!(2, "{}", e),
+            None => {}
         }
     }
 }
@@ -137,7 +179,8 @@ impl<'a> Iterator for FileMerger<'a> {
                         self.heap.push(current);
                         Some(ret)
                     },
-                    _ => {
+                    Some(Err(e)) => crash!(2, "{}", e),
+                    None => {
                         // Don't put it back in the heap (it's empty/erroring)
                         // but its first line is still valid.
                         Some(current.current_line)
@@ -153,19 +196,41 @@ pub fn uumain(args: Vec<String>) -> i32 {
     let mut settings: Settings = Default::default();
     let mut opts = getopts::Options::new();
 
+    opts.optflag("b", "ignore-leading-blanks", "ignore leading blanks");
+    opts.optflag("d", "dictionary-order", "consider only blanks and alphanumeric characters");
     opts.optflag("f", "ignore-case", "fold lower case to upper case characters");
+    opts.optflag("i", "ignore-nonprinting", "consider only printable characters");
     opts.optflag("n", "numeric-sort", "compare according to string numerical value");
+    opts.optflag("g", "general-numeric-sort", "compare according to general numerical value, as in 1.5e3 or 0x10");
     opts.optflag("h", "human-numeric-sort", "compare according to human readable sizes, eg 1M > 100k");
     opts.optflag("M", "month-sort", "compare according to month name abbreviation");
+    opts.optflag("R", "random-sort", "shuffle, but group identical keys");
+    opts.optopt("", "random-source", "get random bytes from FILE", "FILE");
     opts.optflag("r", "reverse", "reverse the output");
-    opts.optflag("h", "help", "display this help and exit");
+    opts.optflag("", "help", "display this help and exit");
     opts.optflag("", "version", "output version information and exit");
     opts.optflag("m", "merge", "merge already sorted files; do not sort");
     opts.optopt("o", "output", "write output to FILENAME instead of stdout", "FILENAME");
+    opts.optflag("", "no-clobber", "fail rather than overwrite an existing output file");
     opts.optflag("s", "stable", "stabilize sort by disabling last-resort comparison");
     opts.optflag("u", "unique", "output only the first of an equal run");
-    opts.optflag("V", "version-sort", "Sort by SemVer version number, eg 1.12.2 > 1.1.2");
-    opts.optflag("c", "check", "check for sorted input; do not sort");
+    opts.optflag("V", "version-sort", "natural sort of (version) numbers within text");
+    opts.optflag("c", "", "check for sorted input; do not sort");
+    opts.optflag("C", "", "like -c, but do not report the first bad line");
+    opts.optflagopt("", "check", "as -c, or with quiet or silent as -C", "WHEN");
+    opts.optmulti("k", "key", "sort via a key; KEYDEF gives location and type", "KEYDEF");
+    opts.optopt("t", "field-separator", "use SEP instead of non-blank to blank transition", "SEP");
+    opts.optflag("", "crlf", "lines end in carriage return and newline, as in Windows text files");
+    opts.optflag("z", "zero-terminated", "end lines with 0 byte, not newline");
+    opts.optopt("S", "buffer-size", "use SIZE for the main memory buffer, spilling sorted runs to temporary files beyond it", "SIZE");
+    opts.optopt("T", "temporary-directory", "use DIR for temporaries, not $TMPDIR or /tmp", "DIR");
+    opts.optopt("", "batch-size", "merge at most NMERGE runs at once; for more use temporary files", "NMERGE");
+    opts.optopt("", "parallel", "change the number of sorts run concurrently to N", "N");
+    opts.optopt("", "compress-program", "compress temporaries with PROG; decompress them with PROG -d", "PROG");
+    #[cfg(feature = "decompress")]
+    opts.optflag("", "decompress", "decompress gzip and zstd compressed input");
+    #[cfg(all(target_os = "linux", feature = "sandbox"))]
+    opts.optflag("", "sandbox", "only allow reading the FILEs given on the command line, writing the output file, and temporary files in the temporary directory; not with --compress-program");
 
     let matches = match opts.parse(&args[1..]) {
         Ok(m) => m,
@@ -181,7 +246,15 @@ Write the sorted concatenation of all FILE(s) to standard output.
 
 Mandatory arguments for long options are mandatory for short options too.
 
-With no FILE, or when FILE is -, read standard input.", NAME, VERSION);
+With no FILE, or when FILE is -, read standard input.
+
+KEYDEF is F[.C][OPTS][,F[.C][OPTS]] for start and stop position, where F is a
+field number and C a character position in the field; both are origin 1, and
+the stop position defaults to the line's end.  If neither -t nor -b is in
+effect, characters in a field are counted from the beginning of the preceding
+whitespace.  OPTS is one or more single-letter ordering options [bdfghiMnRrV],
+which override global ordering options for that key.  If no key is given, use
+the entire line as the key.", NAME, VERSION);
         print!("{}", opts.usage(&msg));
         return 0;
     }
@@ -191,14 +264,18 @@ With no FILE, or when FILE is -, read standard input.", NAME, VERSION);
         return 0;
     }
 
-    settings.mode = if matches.opt_present("numeric-sort") {
+    let mode = if matches.opt_present("numeric-sort") {
         SortMode::Numeric
+    } else if matches.opt_present("general-numeric-sort") {
+        SortMode::GeneralNumeric
     } else if matches.opt_present("human-numeric-sort") {
         SortMode::HumanNumeric
     } else if matches.opt_present("month-sort") {
         SortMode::Month
     } else if matches.opt_present("version-sort") {
         SortMode::Version
+    } else if matches.opt_present("random-sort") {
+        SortMode::Random
     } else {
         SortMode::Default
     };
@@ -206,10 +283,90 @@ With no FILE, or when FILE is -, read standard input.", NAME, VERSION);
     settings.merge = matches.opt_present("merge");
     settings.reverse = matches.opt_present("reverse");
     settings.outfile = matches.opt_str("output");
+    settings.no_clobber = matches.opt_present("no-clobber");
     settings.stable = matches.opt_present("stable");
     settings.unique = matches.opt_present("unique");
-    settings.check = matches.opt_present("check");
-    settings.ignore_case = matches.opt_present("ignore-case");
+    settings.check_silent = matches.opt_present("C");
+    if let Some(when) = matches.opt_str("check") {
+        match &*when {
+            "quiet" | "silent" => settings.check_silent = true,
+            "diagnose-first" => {}
+            _ => crash!(1, "invalid argument '{}' for '--check'", when),
+        }
+    }
+    settings.check = settings.check_silent || matches.opt_present("c") || matches.opt_present("check");
+    settings.line_ending = if matches.opt_present("zero-terminated") {
+        LineEnding::Nul
+    } else {
+        LineEnding::from_crlf(matches.opt_present("crlf"))
+    };
+    if let Some(size) = matches.opt_str("buffer-size") {
+        settings.buffer_size = match parse_buffer_size(&size) {
+            Some(size) => Some(size),
+            None => crash!(2, "invalid -S argument '{}'", size),
+        };
+    }
+    if let Some(batch_size) = matches.opt_str("batch-size") {
+        settings.batch_size = match batch_size.parse() {
+            Ok(n) if n >= 2 => n,
+            Ok(_) => {
+                show_error!("invalid --batch-size argument '{}'", batch_size);
+                crash!(2, "minimum --batch-size argument is '2'")
+            }
+            Err(_) => crash!(2, "invalid --batch-size argument '{}'", batch_size),
+        };
+    }
+    if let Some(parallel) = matches.opt_str("parallel") {
+        settings.parallel = match parallel.parse() {
+            Ok(0) => crash!(2, "number in parallel must be nonzero"),
+            Ok(n) => n,
+            Err(_) => crash!(2, "invalid --parallel argument '{}'", parallel),
+        };
+    }
+    settings.tmp_dir = matches.opt_str("temporary-directory");
+    settings.compress_program = matches.opt_str("compress-program");
+    #[cfg(feature = "decompress")]
+    {
+        settings.decompress = matches.opt_present("decompress");
+    }
+    #[cfg(all(target_os = "linux", feature = "sandbox"))]
+    let sandboxed = matches.opt_present("sandbox");
+
+    let global = KeyDef::whole_line(KeyOptions {
+        mode: mode,
+        reverse: settings.reverse,
+        ignore_case: matches.opt_present("ignore-case"),
+        dictionary_order: matches.opt_present("dictionary-order"),
+        ignore_nonprinting: matches.opt_present("ignore-nonprinting"),
+    }, matches.opt_present("ignore-leading-blanks"));
+    for spec in matches.opt_strs("key") {
+        match KeyDef::parse(&spec, &global) {
+            Ok(key) => settings.keys.push(key),
+            Err(e) => crash!(2, "{}", e),
+        }
+    }
+    if settings.keys.is_empty() {
+        settings.keys.push(global);
+    }
+    if settings.keys.iter().any(|key| key.options.mode == SortMode::Random) {
+        settings.salt = match matches.opt_str("random-source") {
+            Some(path) => match read_salt(&path) {
+                Ok(salt) => salt,
+                Err(e) => crash!(2, "{}: {}", path, e),
+            },
+            None => random_salt(),
+        };
+    }
+
+    if let Some(separator) = matches.opt_str("field-separator") {
+        let mut chars = separator.chars();
+        settings.separator = match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(c),
+            (None, _) => crash!(2, "empty tab"),
+            _ if separator == "\\0" => Some('\0'),
+            _ => crash!(2, "multi-character tab '{}'", separator),
+        };
+    }
 
     let mut files = matches.free;
     if files.is_empty() {
@@ -217,216 +374,337 @@ With no FILE, or when FILE is -, read standard input.", NAME, VERSION);
         files.push("-".to_owned());
     }
     else if settings.check && files.len() != 1 {
-        crash!(1, "sort: extra operand `{}' not allowed with -c", files[1])
-
+        crash!(2, "extra operand '{}' not allowed with -{}", files[1], if settings.check_silent { 'C' } else { 'c' })
     }
 
-    settings.compare_fns.push(match settings.mode {
-        SortMode::Numeric => numeric_compare,
-        SortMode::HumanNumeric => human_numeric_size_compare,
-        SortMode::Month => month_compare,
-        SortMode::Version => version_compare,
-        SortMode::Default => String::cmp
-    });
-
-    if !settings.stable {
-        match settings.mode {
-            SortMode::Default => {}
-            _ => settings.compare_fns.push(String::cmp)
+    #[cfg(all(target_os = "linux", feature = "sandbox"))]
+    {
+        if sandboxed {
+            enter_sandbox(&files, &settings);
         }
     }
 
     exec(files, &settings)
 }
 
+/// Confine sort to `files`, the output file and the temporary directory,
+/// where runs are spilled and inputs about to be overwritten are copied.
+/// A --compress-program could need anything to run, so it is refused.
+#[cfg(all(target_os = "linux", feature = "sandbox"))]
+fn enter_sandbox(files: &[String], settings: &Settings) {
+    if settings.compress_program.is_some() {
+        crash!(2, "options '--compress-program' and '--sandbox' are incompatible");
+    }
+    uucore::sandbox::Sandbox::for_operands(files, settings.outfile.as_ref().map(Path::new))
+        .allow_temp_dir(temp_dir(settings))
+        .enter(1);
+}
+
+fn temp_dir(settings: &Settings) -> PathBuf {
+    settings.tmp_dir.as_ref().map_or_else(env::temp_dir, PathBuf::from)
+}
+
 fn exec(files: Vec<String>, settings: &Settings) -> i32 {
-    let mut lines = Vec::new();
-    let mut file_merger = FileMerger::new(&settings);
+    let mut runs = spill::Runs::new(temp_dir(settings),
+                                    settings.compress_program.clone(),
+                                    run_ending(settings));
+
+    if settings.check {
+        return match open_input(&files[0], None, settings) {
+            Some(reader) => exec_check_file(&files[0], lines::lines(BufReader::new(reader), settings.line_ending), settings),
+            None => 2,
+        };
+    }
+    if settings.merge {
+        exec_merge(&files, &mut runs, settings);
+        return 0;
+    }
 
+    let mut lines = Vec::new();
+    let mut buffered = 0;
     for path in &files {
-        let (reader, _) = match open(path) {
-            Some(x) => x,
+        let reader = match open_input(path, None, settings) {
+            Some(reader) => reader,
             None => continue,
         };
-
-        let buf_reader = BufReader::new(reader);
-
-        if settings.merge {
-            file_merger.push_file(buf_reader.lines());
-        }
-        else if settings.check {
-            return exec_check_file(buf_reader.lines(), &settings)
-        }
-        else {
-            for line in buf_reader.lines() {
-                if let Ok(n) = line {
-                        lines.push(n);
-                }
-                else {
-                    break;
-                }
+        for line in lines::lines(BufReader::new(reader), settings.line_ending) {
+            if let Ok(n) = line {
+                    buffered += n.len() + size_of::<String>();
+                    lines.push(n);
+            }
+            else {
+                break;
+            }
+            if settings.buffer_size.map_or(false, |size| buffered > size) {
+                spill_run(&mut lines, &mut runs, settings);
+                buffered = 0;
             }
         }
     }
 
     sort_by(&mut lines, &settings);
 
-    if settings.merge {
-        if settings.unique {
-            print_sorted(file_merger.dedup(), &settings.outfile)
-        }
-        else {
-            print_sorted(file_merger, &settings.outfile)
-        }
+    if !runs.is_empty() {
+        // merge the runs on disk with what is left in memory
+        spill_run(&mut lines, &mut runs, settings);
+        merge_spilled(&mut runs, settings);
+        return 0;
+    }
+
+    if settings.unique {
+        print_sorted(unique(lines.iter(), settings), settings)
     }
     else {
-        if settings.unique {
-            print_sorted(lines.iter().dedup(), &settings.outfile)
-        }
-        else {
-            print_sorted(lines.iter(), &settings.outfile)
-        }
+        print_sorted(lines.iter(), settings)
     }
 
     0
 
 }
 
-fn exec_check_file(lines: Lines<BufReader<Box<Read>>>, settings: &Settings) -> i32 {
-    // errors yields the line before each disorder,
-    // plus the last line (quirk of .coalesce())
-    let unwrapped_lines = lines.filter_map(|maybe_line| {
-        if let Ok(line) = maybe_line {
-            Some(line)
-        }
-        else {
-            None
-        }
-    });
-    let mut errors = unwrapped_lines.enumerate().coalesce(
-        |(last_i, last_line), (i, line)| {
-            if compare_by(&last_line, &line, &settings) == Ordering::Greater {
-                Err(((last_i, last_line), (i, line)))
-            }
-            else {
-                Ok((i, line))
-            }
-    });
-    if let Some((first_error_index, _line)) = errors.next() {
-        // Check for a second "error", as .coalesce() always returns the last
-        // line, no matter what our merging function does.
-        if let Some(_last_line_or_next_error) = errors.next() {
-            println!("sort: disorder in line {}", first_error_index);
-            return 1;
+/// Merge the already sorted `files`.  Past --batch-size of them, they are
+/// merged that many at a time into runs, which are then merged in turn, so
+/// that no more are open at once.
+fn exec_merge(files: &[String], runs: &mut spill::Runs, settings: &Settings) {
+    // merged inputs are still being read while the output is written
+    let output = match settings.outfile {
+        Some(ref outfile) => FileId::of_path(outfile).ok(),
+        None => None,
+    };
+    let open_batch = |batch: &[String], runs: &mut spill::Runs| -> Vec<Box<Read>> {
+        batch.iter().filter_map(|path| {
+            let overwritten = output.is_some() && FileId::of_input(path).ok() == output;
+            open_input(path, if overwritten { Some(&mut *runs) } else { None }, settings)
+        }).collect()
+    };
+
+    if files.len() <= settings.batch_size {
+        let readers = open_batch(files, runs);
+        print_merged(readers, settings.line_ending, settings);
+        return;
+    }
+    for batch in files.chunks(settings.batch_size) {
+        let readers = open_batch(batch, runs);
+        if let Err(e) = runs.push_merged(|out| merge_runs(readers, settings.line_ending, out, settings)) {
+            crash!(2, "cannot write temporary file: {}", e);
         }
-        else {
-            // first "error" was actually the last line. 
-            return 0;
+    }
+    merge_spilled(runs, settings);
+}
+
+/// Merge the runs on disk, --batch-size at a time until they can all be
+/// merged into the output.
+fn merge_spilled(runs: &mut spill::Runs, settings: &Settings) {
+    while runs.len() > settings.batch_size {
+        let merge = |readers, out: &mut Write| merge_runs(readers, run_ending(settings), out, settings);
+        if let Err(e) = runs.merge_oldest(settings.batch_size, merge) {
+            crash!(2, "cannot write temporary file: {}", e);
         }
     }
+    let readers = match runs.open() {
+        Ok(readers) => readers,
+        Err(e) => crash!(2, "{}", e),
+    };
+    print_merged(readers, run_ending(settings), settings);
+}
+
+/// How the lines in runs end: in newlines whatever the input had, unless
+/// they are zero-terminated and may hold newlines themselves.
+fn run_ending(settings: &Settings) -> LineEnding {
+    match settings.line_ending {
+        LineEnding::Nul => LineEnding::Nul,
+        _ => LineEnding::Newline,
+    }
+}
+
+fn print_merged(readers: Vec<Box<Read>>, ending: LineEnding, settings: &Settings) {
+    let mut file_merger = FileMerger::new(settings);
+    for reader in readers {
+        file_merger.push_file(lines::lines(BufReader::new(reader), ending));
+    }
+    if settings.unique {
+        print_sorted(unique(file_merger, settings), settings)
+    }
     else {
-        // unwrapped_lines was empty. Empty files are defined to be sorted.
-        return 0;
+        print_sorted(file_merger, settings)
     }
 }
 
-fn sort_by(lines: &mut Vec<String>, settings: &Settings) {
-    lines.sort_by(|a, b| {
-        compare_by(a, b, &settings)
-    })
+/// Write the buffered lines out as a sorted run.
+fn spill_run(lines: &mut Vec<String>, runs: &mut spill::Runs, settings: &Settings) {
+    sort_by(lines, settings);
+    if let Err(e) = runs.push(lines) {
+        crash!(2, "cannot write temporary file: {}", e);
+    }
+    lines.clear();
 }
 
-fn compare_by(a: &String, b: &String, settings: &Settings) -> Ordering {
-    // Convert to uppercase if necessary
-    let (a_upper, b_upper): (String, String);
-    let (a, b) = if settings.ignore_case {
-        a_upper = a.to_uppercase();
-        b_upper = b.to_uppercase();
-        (&a_upper, &b_upper)
-    } else {
-        (a, b)
+/// Merge sorted runs, their lines ending in `ending`, into one, as they are
+/// written, its lines ending as runs' do.
+fn merge_runs(readers: Vec<Box<Read>>, ending: LineEnding, out: &mut Write, settings: &Settings) -> io::Result<()> {
+    let mut merger = FileMerger::new(settings);
+    for reader in readers {
+        merger.push_file(lines::lines(BufReader::new(reader), ending));
+    }
+    let run_ending = run_ending(settings).as_str().as_bytes();
+    for line in merger {
+        try!(out.write_all(line.as_bytes()));
+        try!(out.write_all(run_ending));
+    }
+    Ok(())
+}
+
+/// Parse the argument of --buffer-size: a number of kibibytes, or of the unit
+/// given by one of the suffixes b, K, M, G, T, P and E, or with % a
+/// percentage of physical memory.
+fn parse_buffer_size(size: &str) -> Option<usize> {
+    let (digits, unit) = match size.find(|c: char| !c.is_digit(10)) {
+        Some(pos) => (&size[..pos], &size[pos..]),
+        None => (size, "K"),
+    };
+    if unit == "%" {
+        let percent = match digits.parse::<usize>() {
+            Ok(percent) if percent <= 100 => percent,
+            _ => return None,
+        };
+        return physical_memory().map(|memory| memory / 100 * percent);
+    }
+    let exponent = match unit {
+        "b" => 0,
+        "K" | "k" => 1,
+        "M" | "m" => 2,
+        "G" | "g" => 3,
+        "T" | "t" => 4,
+        "P" | "p" => 5,
+        "E" | "e" => 6,
+        _ => return None,
     };
+    let number = match digits.parse::<usize>() {
+        Ok(number) => number,
+        Err(_) => return None,
+    };
+    (0..exponent).fold(Some(number), |size, _| size.and_then(|s| s.checked_mul(1024)))
+}
 
-    for compare_fn in &settings.compare_fns {
-        let cmp = compare_fn(a, b);
-        if cmp != Ordering::Equal {
-            if settings.reverse {
-                return cmp.reverse();
-            }
-            else {
-                return cmp;
+/// The buffer used without -S, an eighth of physical memory as in GNU sort,
+/// so that inputs larger than memory go through temporary files.
+fn default_buffer_size() -> Option<usize> {
+    physical_memory().map(|memory| memory / 8)
+}
+
+#[cfg(unix)]
+fn physical_memory() -> Option<usize> {
+    use uucore::libc;
+
+    let pages = unsafe { libc::sysconf(libc::_SC_PHYS_PAGES) };
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    if pages > 0 && page_size > 0 {
+        (pages as usize).checked_mul(page_size as usize)
+    } else {
+        None
+    }
+}
+
+#[cfg(not(unix))]
+fn physical_memory() -> Option<usize> {
+    None
+}
+
+/// Check that the lines of `path` are sorted, telling of the first that is
+/// not unless -C was given.  With -u, equal lines are out of order too.
+fn exec_check_file(path: &str, lines: Lines<BufReader<Box<Read>>>, settings: &Settings) -> i32 {
+    let mut previous: Option<String> = None;
+    for (i, line) in lines.enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => crash!(2, "{}: {}", path, e),
+        };
+        if let Some(ref previous) = previous {
+            let cmp = compare_by(previous, &line, settings);
+            if cmp == Ordering::Greater || (settings.unique && cmp == Ordering::Equal) {
+                if !settings.check_silent {
+                    show_error!("{}:{}: disorder: {}", path, i + 1, line);
+                }
+                return 1;
             }
         }
+        previous = Some(line);
     }
-    return Ordering::Equal;
+    0
 }
 
-/// Parse the beginning string into an f64, returning -inf instead of NaN on errors.
-fn permissive_f64_parse(a: &str) -> f64 {
-    // Maybe should be split on non-digit, but then 10e100 won't parse properly.
-    // On the flip side, this will give NEG_INFINITY for "1,234", which might be OK
-    // because there's no way to handle both CSV and thousands separators without a new flag.
-    // GNU sort treats "1,234" as "1" in numeric, so maybe it's fine.
-    let sa: &str = a.split_whitespace().next().unwrap();
-    match sa.parse::<f64>() {
-        Ok(a) => a,
-        Err(_) => std::f64::NEG_INFINITY
-    }
+fn sort_by(lines: &mut Vec<String>, settings: &Settings) {
+    parallel::sort_by(lines, settings.parallel, |a, b| {
+        compare_by(a, b, &settings)
+    })
 }
 
-/// Compares two floating point numbers, with errors being assumed to be -inf.
-/// Stops coercing at the first whitespace char, so 1e2 will parse as 100 but
-/// 1,000 will parse as -inf.
-fn numeric_compare(a: &String, b: &String) -> Ordering {
-    let fa = permissive_f64_parse(a);
-    let fb = permissive_f64_parse(b);
-    // f64::cmp isn't implemented because NaN messes with it
-    // but we sidestep that with permissive_f64_parse so just fake it
-    if fa > fb {
-        Ordering::Greater
+/// Compare by the keys in turn, then unless -s or -u by the whole lines.
+fn compare_by(a: &str, b: &str, settings: &Settings) -> Ordering {
+    let cmp = compare_keys(a, b, settings);
+    if cmp != Ordering::Equal || settings.stable || settings.unique {
+        return cmp;
     }
-    else if fa < fb {
-        Ordering::Less
+    if settings.reverse {
+        b.cmp(a)
+    } else {
+        a.cmp(b)
     }
-    else {
-        Ordering::Equal
-    }
-}
-
-fn human_numeric_convert(a: &String) -> f64 {
-    let int_iter = a.chars();
-    let suffix_iter = a.chars();
-    let int_str: String = int_iter.take_while(|c| c.is_numeric()).collect();
-    let suffix = suffix_iter.skip_while(|c| c.is_numeric()).next();
-    let int_part = match int_str.parse::<f64>() {
-        Ok(i) => i,
-        Err(_) => -1f64
-    } as f64;
-    let suffix: f64 = match suffix.unwrap_or('\0') {
-        'K' => 1000f64,
-        'M' => 1E6,
-        'G' => 1E9,
-        'T' => 1E12,
-        'P' => 1E15,
-        _ => 1f64
-    };
-    int_part * suffix
 }
 
-/// Compare two strings as if they are human readable sizes.
-/// AKA 1M > 100k
-fn human_numeric_size_compare(a: &String, b: &String) -> Ordering {
-    let fa = human_numeric_convert(a);
-    let fb = human_numeric_convert(b);
-    if fa > fb {
-        Ordering::Greater
+fn compare_keys(a: &str, b: &str, settings: &Settings) -> Ordering {
+    for key in &settings.keys {
+        let options = &key.options;
+        let key_a = comparable(key.extract(a, settings.separator), options);
+        let key_b = comparable(key.extract(b, settings.separator), options);
+        let cmp = match options.mode {
+            SortMode::Numeric => numcompare::numeric(&key_a, &key_b),
+            SortMode::GeneralNumeric => numcompare::general_numeric(&key_a, &key_b),
+            SortMode::HumanNumeric => numcompare::human_numeric(&key_a, &key_b),
+            SortMode::Month => month_compare(&key_a, &key_b),
+            SortMode::Version => version_cmp(&key_a, &key_b),
+            SortMode::Random => random_compare(&key_a, &key_b, &settings.salt),
+            SortMode::Default => key_a.cmp(&key_b),
+        };
+        if cmp != Ordering::Equal {
+            return if options.reverse { cmp.reverse() } else { cmp };
+        }
     }
-    else if fa < fb {
-        Ordering::Less
+    Ordering::Equal
+}
+
+/// The key as compared: only what -d and -i leave of it, folded to upper
+/// case with -f.
+fn comparable<'a>(key: &'a str, options: &KeyOptions) -> Cow<'a, str> {
+    if !options.ignore_case && !options.dictionary_order && !options.ignore_nonprinting {
+        return Cow::Borrowed(key);
     }
-    else {
-        Ordering::Equal
+    let mut comparable = String::with_capacity(key.len());
+    for c in key.chars() {
+        if options.dictionary_order && !c.is_alphanumeric() && !key::is_blank(c) {
+            continue;
+        }
+        if options.ignore_nonprinting && c.is_control() {
+            continue;
+        }
+        if options.ignore_case {
+            comparable.extend(c.to_uppercase());
+        } else {
+            comparable.push(c);
+        }
     }
+    Cow::Owned(comparable)
+}
+
+/// The first of each run of lines whose keys are equal.
+fn unique<'a, S: AsRef<str>, I: Iterator<Item=S> + 'a>(lines: I, settings: &'a Settings) -> Box<Iterator<Item=S> + 'a> {
+    Box::new(lines.coalesce(move |a, b| {
+        if compare_keys(a.as_ref(), b.as_ref(), settings) == Ordering::Equal {
+            Ok(a)
+        } else {
+            Err((a, b))
+        }
+    }))
 }
 
 #[derive(Eq, Ord, PartialEq, PartialOrd)]
@@ -447,8 +725,8 @@ enum Month {
 }
 
 /// Parse the beginning string into a Month, returning Month::Unknown on errors.
-fn month_parse(line: &String) -> Month {
-    match line.split_whitespace().next().unwrap().to_uppercase().as_ref() {
+fn month_parse(line: &str) -> Month {
+    match line.split_whitespace().next().unwrap_or("").to_uppercase().as_ref() {
         "JAN" => Month::January,
         "FEB" => Month::February,
         "MAR" => Month::March,
@@ -465,33 +743,52 @@ fn month_parse(line: &String) -> Month {
     }
 }
 
-fn month_compare(a: &String, b: &String) -> Ordering {
+fn month_compare(a: &str, b: &str) -> Ordering {
     month_parse(a).cmp(&month_parse(b))
 }
 
-fn version_compare(a: &String, b: &String) -> Ordering {
-    let ver_a = Version::parse(a);
-    let ver_b = Version::parse(b);
-    if ver_a > ver_b {
-        Ordering::Greater
-    }
-    else if ver_a < ver_b {
-        Ordering::Less
+/// Compare the keys by a hash of them salted with random bytes, which puts
+/// them in a random order, but one that unlike a shuffle keeps identical
+/// keys together.
+fn random_compare(a: &str, b: &str, salt: &[u8]) -> Ordering {
+    random_hash(a, salt).cmp(&random_hash(b, salt)).then_with(|| a.cmp(b))
+}
+
+fn random_hash(key: &str, salt: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(salt);
+    hasher.write(key.as_bytes());
+    hasher.finish()
+}
+
+/// The salt for -R from --random-source, so that the same bytes give the
+/// same order every time.
+fn read_salt(path: &str) -> io::Result<Vec<u8>> {
+    let mut salt = Vec::with_capacity(SALT_LEN);
+    try!(try!(File::open(path)).take(SALT_LEN as u64).read_to_end(&mut salt));
+    if salt.len() < SALT_LEN {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "end of file"));
     }
-    else {
-        Ordering::Equal
+    Ok(salt)
+}
+
+fn random_salt() -> Vec<u8> {
+    let mut salt = Vec::with_capacity(SALT_LEN);
+    while salt.len() < SALT_LEN {
+        // every RandomState is seeded afresh
+        let random = RandomState::new().build_hasher().finish();
+        salt.extend((0..8).map(|i| (random >> (i * 8)) as u8));
     }
+    salt
 }
 
-fn print_sorted<S, T: Iterator<Item=S>>(iter: T, outfile: &Option<String>) where S: std::fmt::Display {
-    let mut file: Box<Write> = match *outfile {
+fn print_sorted<S, T: Iterator<Item=S>>(iter: T, settings: &Settings) where S: std::fmt::Display {
+    let ending = settings.line_ending;
+    let mut file: Box<Write> = match settings.outfile {
         Some(ref filename) => {
-            match File::create(Path::new(&filename)) {
+            match create_output(filename, settings.no_clobber) {
                 Ok(f) => Box::new(BufWriter::new(f)) as Box<Write>,
-                Err(e) => {
-                    show_error!("sort: {0}: {1}", filename, e.to_string());
-                    panic!("Could not open output file");
-                },
+                Err(e) => crash!(2, "open failed: {}: {}", filename, e),
             }
         },
         None => Box::new(stdout()) as Box<Write>,
@@ -499,7 +796,7 @@ fn print_sorted<S, T: Iterator<Item=S>>(iter: T, outfile: &Option<String>) where
 
 
     for line in iter {
-        let str = format!("{}\n", line);
+        let str = format!("{}{}", line, ending.as_str());
         match file.write_all(str.as_bytes()) {
             Err(e) => {
                 show_error!("sort: {0}", e.to_string());
@@ -510,6 +807,43 @@ fn print_sorted<S, T: Iterator<Item=S>>(iter: T, outfile: &Option<String>) where
     }
 }
 
+#[cfg(feature = "decompress")]
+fn decompressed(path: &str, reader: Box<Read>) -> Option<Box<Read>> {
+    match uucore::decompress::decoder(reader) {
+        Ok(decoded) => Some(Box::new(decoded) as Box<Read>),
+        Err(e) => {
+            show_error!("sort: {0}: {1}", path, e.to_string());
+            None
+        },
+    }
+}
+
+#[cfg(not(feature = "decompress"))]
+fn decompressed(_: &str, reader: Box<Read>) -> Option<Box<Read>> {
+    Some(reader)
+}
+
+/// The input at `path`, decompressed with --decompress, or `None` if it
+/// cannot be read.  With `copy_into`, it is read from a copy made there
+/// first, as an input the output is about to overwrite.
+fn open_input(path: &str, copy_into: Option<&mut spill::Runs>, settings: &Settings) -> Option<Box<Read>> {
+    let (mut reader, _) = match open(path) {
+        Some(x) => x,
+        None => return None,
+    };
+    if let Some(runs) = copy_into {
+        reader = match runs.copy(&mut reader) {
+            Ok(copy) => copy,
+            Err(e) => crash!(2, "cannot write temporary file: {}", e),
+        };
+    }
+    if settings.decompress {
+        decompressed(path, reader)
+    } else {
+        Some(reader)
+    }
+}
+
 // from cat.rs
 fn open(path: &str) -> Option<(Box<Read>, bool)> {
     if path == "-" {
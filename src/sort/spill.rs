@@ -0,0 +1,199 @@
+/*
+ * This file is part of the uutils coreutils package.
+ *
+ * For the full copyright and license information, please view the LICENSE
+ * file that was distributed with this source code.
+ */
+
+//! Sorted runs that did not fit into the buffer, kept in temporary files
+//! until they are merged, along with copies of the inputs that are to be
+//! overwritten by the output while they are merged.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, ErrorKind, Read, Write};
+use std::path::PathBuf;
+use std::process::{self, Child, Command, Stdio};
+use uucore::lines::LineEnding;
+
+pub struct Runs {
+    dir: PathBuf,
+    compress_program: Option<String>,
+    ending: LineEnding,
+    paths: Vec<PathBuf>,
+    copies: Vec<PathBuf>,
+}
+
+impl Runs {
+    /// Runs in `dir`, their lines ending in `ending`.
+    pub fn new(dir: PathBuf, compress_program: Option<String>, ending: LineEnding) -> Runs {
+        Runs {
+            dir: dir,
+            compress_program: compress_program,
+            ending: ending,
+            paths: Vec::new(),
+            copies: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    /// Write the already sorted `lines` out as a new run.
+    pub fn push(&mut self, lines: &[String]) -> io::Result<()> {
+        let last = self.paths.len();
+        let ending = self.ending;
+        self.write_run(last, |out| write_lines(out, lines, ending))
+    }
+
+    /// Write a new run with `merge`, from inputs that were already sorted.
+    pub fn push_merged<F>(&mut self, merge: F) -> io::Result<()>
+        where F: FnOnce(&mut Write) -> io::Result<()>
+    {
+        let last = self.paths.len();
+        self.write_run(last, merge)
+    }
+
+    /// Replace the `count` oldest runs with one, which `merge` writes from
+    /// readers for them in the order they were written.  It takes their
+    /// place among the rest, so that runs are still in the order of the
+    /// input.
+    pub fn merge_oldest<F>(&mut self, count: usize, merge: F) -> io::Result<()>
+        where F: FnOnce(Vec<Box<Read>>, &mut Write) -> io::Result<()>
+    {
+        let readers = try!(self.open_paths(&self.paths[..count]));
+        try!(self.write_run(count, |out| merge(readers, out)));
+        for path in self.paths.drain(..count) {
+            let _ = fs::remove_file(path);
+        }
+        Ok(())
+    }
+
+    // Write a new run with `write`, to go at `index` among the others.
+    fn write_run<F>(&mut self, index: usize, write: F) -> io::Result<()>
+        where F: FnOnce(&mut Write) -> io::Result<()>
+    {
+        let (path, file) = try!(self.create());
+        self.paths.insert(index, path);
+        match self.compress_program {
+            Some(ref program) => {
+                let mut child = try!(Command::new(program)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::from(file))
+                    .spawn()
+                    .map_err(|e| spawn_error(program, e)));
+                {
+                    let mut stdin = BufWriter::new(child.stdin.take().unwrap());
+                    try!(write(&mut stdin));
+                    try!(stdin.flush());
+                }
+                wait(program, &mut child)
+            }
+            None => {
+                let mut file = BufWriter::new(file);
+                try!(write(&mut file));
+                file.flush()
+            }
+        }
+    }
+
+    /// Readers for all the runs, in the order they were written.
+    pub fn open(&self) -> io::Result<Vec<Box<Read>>> {
+        self.open_paths(&self.paths)
+    }
+
+    fn open_paths(&self, paths: &[PathBuf]) -> io::Result<Vec<Box<Read>>> {
+        let mut readers = Vec::with_capacity(paths.len());
+        for path in paths {
+            let file = try!(File::open(path));
+            readers.push(match self.compress_program {
+                Some(ref program) => {
+                    let child = try!(Command::new(program)
+                        .arg("-d")
+                        .stdin(Stdio::from(file))
+                        .stdout(Stdio::piped())
+                        .spawn()
+                        .map_err(|e| spawn_error(program, e)));
+                    Box::new(Decompressor {
+                        program: program.clone(),
+                        child: child,
+                    }) as Box<Read>
+                }
+                None => Box::new(file) as Box<Read>,
+            });
+        }
+        Ok(readers)
+    }
+
+    /// A copy of all of `reader`, for an input that is about to be
+    /// overwritten before it has been read.
+    pub fn copy(&mut self, reader: &mut Read) -> io::Result<Box<Read>> {
+        let (path, file) = try!(self.create());
+        self.copies.push(path.clone());
+        let mut file = BufWriter::new(file);
+        try!(io::copy(reader, &mut file));
+        try!(file.flush());
+        Ok(Box::new(try!(File::open(path))) as Box<Read>)
+    }
+
+    fn create(&self) -> io::Result<(PathBuf, File)> {
+        let mut n = self.paths.len() + self.copies.len();
+        loop {
+            let path = self.dir.join(format!("sort{}.{}", process::id(), n));
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => return Ok((path, file)),
+                Err(ref e) if e.kind() == ErrorKind::AlreadyExists => n += 1,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl Drop for Runs {
+    fn drop(&mut self) {
+        for path in self.paths.iter().chain(&self.copies) {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// The output of `PROG -d`, which must exit successfully once it is done.
+struct Decompressor {
+    program: String,
+    child: Child,
+}
+
+impl Read for Decompressor {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = try!(self.child.stdout.as_mut().unwrap().read(buf));
+        if n == 0 && !buf.is_empty() {
+            try!(wait(&format!("{} -d", self.program), &mut self.child));
+        }
+        Ok(n)
+    }
+}
+
+fn write_lines(out: &mut Write, lines: &[String], ending: LineEnding) -> io::Result<()> {
+    for line in lines {
+        try!(out.write_all(line.as_bytes()));
+        try!(out.write_all(ending.as_str().as_bytes()));
+    }
+    Ok(())
+}
+
+fn wait(program: &str, child: &mut Child) -> io::Result<()> {
+    let status = try!(child.wait());
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::new(ErrorKind::Other, format!("{}: {}", program, status)))
+    }
+}
+
+fn spawn_error(program: &str, e: io::Error) -> io::Error {
+    io::Error::new(e.kind(), format!("couldn't create process for {}: {}", program, e))
+}
@@ -15,9 +15,12 @@ extern crate getopts;
 extern crate uucore;
 
 use std::char;
-use std::fs::{File, OpenOptions};
+use std::fs::File;
 use std::io::{BufRead, BufReader, BufWriter, Read, stdin, stdout, Write};
 use std::path::Path;
+use uucore::fs::{create_output, FileId};
+use uucore::parse_number::NumberArg;
+use uucore::sparse::SparseReader;
 
 static NAME: &'static str = "split";
 static VERSION: &'static str = env!("CARGO_PKG_VERSION");
@@ -31,6 +34,7 @@ pub fn uumain(args: Vec<String>) -> i32 {
     opts.optflag("d", "numeric-suffixes", "use numeric suffixes instead of alphabetic");
     opts.optopt("l", "lines", "put NUMBER lines per output file", "NUMBER");
     opts.optflag("", "verbose", "print a diagnostic just before each output file is opened");
+    opts.optflag("", "no-clobber", "fail rather than overwrite an existing output file");
     opts.optflag("h", "help", "display help and exit");
     opts.optflag("V", "version", "output version information and exit");
 
@@ -66,19 +70,21 @@ size is 1000, and default PREFIX is 'x'. With no INPUT, or when INPUT is
         strategy: "".to_owned(),
         strategy_param: "".to_owned(),
         verbose: false,
+        no_clobber: false,
     };
 
     settings.numeric_suffix = matches.opt_present("d");
 
     settings.suffix_length = match matches.opt_str("a") {
-        Some(n) => match n.parse() {
+        Some(n) => match NumberArg::new("suffix length").parse_usize(&n) {
             Ok(m) => m,
-            Err(e) => crash!(1, "cannot parse num: {}", e)
+            Err(msg) => crash!(1, "{}", msg)
         },
         None => 2
     };
 
     settings.verbose = matches.opt_present("verbose");
+    settings.no_clobber = matches.opt_present("no-clobber");
 
     settings.strategy = "l".to_owned();
     settings.strategy_param = "1000".to_owned();
@@ -117,6 +123,7 @@ struct Settings {
     strategy: String,
     strategy_param: String,
     verbose: bool,
+    no_clobber: bool,
 }
 
 struct SplitControl {
@@ -136,9 +143,9 @@ struct LineSplitter {
 
 impl LineSplitter {
     fn new(settings: &Settings) -> Box<Splitter> {
-        let n = match settings.strategy_param.parse() {
+        let n = match NumberArg::new("number of lines").min(1).parse_usize(&settings.strategy_param) {
             Ok(a) => a,
-            Err(e) => crash!(1, "invalid number of lines: {}", e)
+            Err(msg) => crash!(1, "{}", msg)
         };
         Box::new(LineSplitter {
             saved_lines_to_write: n,
@@ -168,29 +175,13 @@ struct ByteSplitter {
 
 impl ByteSplitter {
     fn new(settings: &Settings) -> Box<Splitter> {
-        let mut strategy_param : Vec<char> = settings.strategy_param.chars().collect();
-        let suffix = strategy_param.pop().unwrap();
-        let multiplier = match suffix {
-            '0'...'9' => 1usize,
-            'b' => 512usize,
-            'k' => 1024usize,
-            'm' => 1024usize * 1024usize,
-            _ => crash!(1, "invalid number of bytes")
-        };
-        let n = if suffix.is_alphabetic() {
-            match strategy_param.iter().cloned().collect::<String>().parse::<usize>() {
-                Ok(a) => a,
-                Err(e) => crash!(1, "invalid number of bytes: {}", e)
-            }
-        } else {
-            match settings.strategy_param.parse::<usize>() {
-                Ok(a) => a,
-                Err(e) => crash!(1, "invalid number of bytes: {}", e)
-            }
+        let n = match NumberArg::new("number of bytes").suffixes(true).min(1).parse_usize(&settings.strategy_param) {
+            Ok(a) => a,
+            Err(msg) => crash!(1, "{}", msg)
         };
         Box::new(ByteSplitter {
-            saved_bytes_to_write: n * multiplier,
-            bytes_to_write: n * multiplier,
+            saved_bytes_to_write: n,
+            bytes_to_write: n,
             break_on_line_end: settings.strategy == "b",
             require_whole_line: false,
         }) as Box<Splitter>
@@ -258,7 +249,7 @@ fn split(settings: &Settings) -> i32 {
                 Ok(a) => a,
                 Err(_) => crash!(1, "cannot open '{}' for reading: No such file or directory", settings.input)
             };
-            Box::new(r) as Box<Read>
+            Box::new(SparseReader::new(r)) as Box<Read>
         }
     );
 
@@ -274,6 +265,7 @@ fn split(settings: &Settings) -> i32 {
         request_new_file: true, // Request new file
     };
 
+    let input = FileId::of_input(&settings.input).ok();
     let mut writer = BufWriter::new(Box::new(stdout()) as Box<Write>);
     let mut fileno = 0;
     loop {
@@ -296,7 +288,10 @@ fn split(settings: &Settings) -> i32 {
                 crash_if_err!(1, writer.flush());
             }
             fileno += 1;
-            writer = BufWriter::new(Box::new(OpenOptions::new().write(true).create(true).open(Path::new(&filename)).unwrap()) as Box<Write>);
+            if input.is_some() && FileId::of_path(&filename).ok() == input {
+                crash!(1, "'{}' would overwrite input; aborting", filename);
+            }
+            writer = BufWriter::new(Box::new(crash_if_err!(1, create_output(&filename, settings.no_clobber))) as Box<Write>);
             control.request_new_file = false;
             if settings.verbose {
                 println!("creating file '{}'", filename);
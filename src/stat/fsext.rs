@@ -10,9 +10,14 @@ pub use super::uucore::libc;
 extern crate time;
 
 use self::time::Timespec;
+#[cfg(unix)]
 pub use libc::{S_IFMT, S_IFDIR, S_IFCHR, S_IFBLK, S_IFREG, S_IFIFO, S_IFLNK, S_IFSOCK, S_ISUID, S_ISGID, S_ISVTX,
                S_IRUSR, S_IWUSR, S_IXUSR, S_IRGRP, S_IWGRP, S_IXGRP, S_IROTH, S_IWOTH, S_IXOTH, mode_t, c_int,
                strerror};
+#[cfg(windows)]
+pub use self::windows::*;
+#[cfg(target_os = "linux")]
+pub use self::linux::*;
 
 pub trait BirthTime {
     fn pretty_birth(&self) -> String;
@@ -20,6 +25,33 @@ pub trait BirthTime {
 }
 
 use std::fs::Metadata;
+#[cfg(all(unix, not(target_os = "linux")))]
+use std::fs;
+#[cfg(all(unix, not(target_os = "linux")))]
+use std::io;
+
+/// How far attributes cached by the file system may be used, as chosen with
+/// `--cached`.  Only Linux makes the distinction.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Cached {
+    /// whatever the file system does for stat(2)
+    Default,
+    /// synchronize with the server first
+    Never,
+    /// whatever is cached, without going to the server
+    Always,
+}
+
+/// Metadata of `path`, of the link itself unless `follow` is set
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn stat<P: AsRef<Path>>(path: P, follow: bool, _cached: Cached) -> io::Result<Metadata> {
+    if follow {
+        fs::metadata(path)
+    } else {
+        fs::symlink_metadata(path)
+    }
+}
+
 impl BirthTime for Metadata {
     #[cfg(feature = "nightly")]
     fn pretty_birth(&self) -> String {
@@ -167,12 +199,18 @@ pub fn pretty_access(mode: mode_t) -> String {
     result
 }
 
-use std::mem::{self, transmute};
 use std::path::Path;
 use std::borrow::Cow;
+use std::convert::AsRef;
+#[cfg(unix)]
+use std::mem::{self, transmute};
+#[cfg(unix)]
 use std::ffi::CString;
-use std::convert::{AsRef, From};
+#[cfg(unix)]
+use std::convert::From;
+#[cfg(unix)]
 use std::error::Error;
+#[cfg(unix)]
 use std::io::Error as IOError;
 
 #[cfg(any(target_os = "linux", target_os = "macos", target_os = "android"))]
@@ -196,8 +234,10 @@ pub trait FsMeta {
     fn free_fnodes(&self) -> u64;
     fn fsid(&self) -> u64;
     fn namelen(&self) -> i64;
+    fn fs_name(&self) -> String;
 }
 
+#[cfg(unix)]
 impl FsMeta for Sstatfs {
     fn blksize(&self) -> i64 {
         self.f_bsize as i64
@@ -265,8 +305,13 @@ impl FsMeta for Sstatfs {
     fn namelen(&self) -> u64 {
         0
     }
+
+    fn fs_name(&self) -> String {
+        pretty_fstype(self.fs_type()).into_owned()
+    }
 }
 
+#[cfg(unix)]
 pub fn statfs<P: AsRef<Path>>(path: P) -> Result<Sstatfs, String>
     where Vec<u8>: From<P>
 {
@@ -408,3 +453,443 @@ pub fn pretty_fstype<'a>(fstype: i64) -> Cow<'a, str> {
         other => format!("UNKNOWN ({:#x})", other).into(),
     }
 }
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::{libc, pretty_time, Cached};
+
+    use std::ffi::CString;
+    use std::fs::{self, Metadata};
+    use std::io;
+    use std::mem;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::MetadataExt;
+    use std::path::Path;
+
+    /// What statx(2) reports, offering the same accessors as `MetadataExt`.
+    pub struct StatxMetadata {
+        stx: libc::statx,
+    }
+
+    fn makedev(major: u32, minor: u32) -> u64 {
+        let (major, minor) = (major as u64, minor as u64);
+        (major & 0xfffff000) << 32 | (major & 0xfff) << 8 | (minor & 0xffffff00) << 12 | (minor & 0xff)
+    }
+
+    fn split_dev(dev: u64) -> (u32, u32) {
+        ((((dev >> 8) & 0xfff) | ((dev >> 32) & !0xfff)) as u32, ((dev & 0xff) | ((dev >> 12) & !0xff)) as u32)
+    }
+
+    impl StatxMetadata {
+        pub fn mode(&self) -> libc::mode_t {
+            self.stx.stx_mode as libc::mode_t
+        }
+        pub fn ino(&self) -> u64 {
+            self.stx.stx_ino
+        }
+        pub fn dev(&self) -> u64 {
+            makedev(self.stx.stx_dev_major, self.stx.stx_dev_minor)
+        }
+        pub fn nlink(&self) -> u64 {
+            self.stx.stx_nlink as u64
+        }
+        pub fn uid(&self) -> u32 {
+            self.stx.stx_uid
+        }
+        pub fn gid(&self) -> u32 {
+            self.stx.stx_gid
+        }
+        pub fn rdev(&self) -> u64 {
+            makedev(self.stx.stx_rdev_major, self.stx.stx_rdev_minor)
+        }
+        pub fn len(&self) -> u64 {
+            self.stx.stx_size
+        }
+        pub fn blksize(&self) -> u64 {
+            self.stx.stx_blksize as u64
+        }
+        pub fn blocks(&self) -> u64 {
+            self.stx.stx_blocks
+        }
+        pub fn atime(&self) -> i64 {
+            self.stx.stx_atime.tv_sec
+        }
+        pub fn atime_nsec(&self) -> i64 {
+            self.stx.stx_atime.tv_nsec as i64
+        }
+        pub fn mtime(&self) -> i64 {
+            self.stx.stx_mtime.tv_sec
+        }
+        pub fn mtime_nsec(&self) -> i64 {
+            self.stx.stx_mtime.tv_nsec as i64
+        }
+        pub fn ctime(&self) -> i64 {
+            self.stx.stx_ctime.tv_sec
+        }
+        pub fn ctime_nsec(&self) -> i64 {
+            self.stx.stx_ctime.tv_nsec as i64
+        }
+        pub fn pretty_birth(&self) -> String {
+            if self.stx.stx_mask & libc::STATX_BTIME != 0 {
+                pretty_time(self.stx.stx_btime.tv_sec, self.stx.stx_btime.tv_nsec as i64)
+            } else {
+                "-".to_owned()
+            }
+        }
+        pub fn birth(&self) -> String {
+            if self.stx.stx_mask & libc::STATX_BTIME != 0 {
+                format!("{}", self.stx.stx_btime.tv_sec)
+            } else {
+                "0".to_owned()
+            }
+        }
+    }
+
+    impl<'a> From<&'a Metadata> for StatxMetadata {
+        fn from(md: &Metadata) -> StatxMetadata {
+            let mut stx: libc::statx = unsafe { mem::zeroed() };
+            stx.stx_mask = libc::STATX_BASIC_STATS;
+            stx.stx_mode = md.mode() as u16;
+            stx.stx_ino = md.ino();
+            stx.stx_nlink = md.nlink() as u32;
+            stx.stx_uid = md.uid();
+            stx.stx_gid = md.gid();
+            stx.stx_size = md.size();
+            stx.stx_blksize = md.blksize() as u32;
+            stx.stx_blocks = md.blocks();
+            let (major, minor) = split_dev(md.dev());
+            stx.stx_dev_major = major;
+            stx.stx_dev_minor = minor;
+            let (major, minor) = split_dev(md.rdev());
+            stx.stx_rdev_major = major;
+            stx.stx_rdev_minor = minor;
+            stx.stx_atime.tv_sec = md.atime();
+            stx.stx_atime.tv_nsec = md.atime_nsec() as u32;
+            stx.stx_mtime.tv_sec = md.mtime();
+            stx.stx_mtime.tv_nsec = md.mtime_nsec() as u32;
+            stx.stx_ctime.tv_sec = md.ctime();
+            stx.stx_ctime.tv_nsec = md.ctime_nsec() as u32;
+            StatxMetadata { stx: stx }
+        }
+    }
+
+    /// Metadata of `path`, of the link itself unless `follow` is set.
+    /// Automount points are not triggered, like with GNU stat.
+    pub fn stat<P: AsRef<Path>>(path: P, follow: bool, cached: Cached) -> io::Result<StatxMetadata> {
+        let path = path.as_ref();
+        let cpath = try!(CString::new(path.as_os_str().as_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e)));
+        let mut flags = libc::AT_NO_AUTOMOUNT |
+                        match cached {
+            Cached::Default => libc::AT_STATX_SYNC_AS_STAT,
+            Cached::Never => libc::AT_STATX_FORCE_SYNC,
+            Cached::Always => libc::AT_STATX_DONT_SYNC,
+        };
+        if !follow {
+            flags |= libc::AT_SYMLINK_NOFOLLOW;
+        }
+
+        let mut stx: libc::statx = unsafe { mem::zeroed() };
+        let mask = libc::STATX_BASIC_STATS | libc::STATX_BTIME;
+        if unsafe { libc::statx(libc::AT_FDCWD, cpath.as_ptr(), flags, mask, &mut stx) } == 0 {
+            return Ok(StatxMetadata { stx: stx });
+        }
+        let err = io::Error::last_os_error();
+        if err.raw_os_error() != Some(libc::ENOSYS) {
+            return Err(err);
+        }
+
+        // kernels before 4.11 have no statx, and no say in caching either
+        let md = try!(if follow {
+            fs::metadata(path)
+        } else {
+            fs::symlink_metadata(path)
+        });
+        Ok(StatxMetadata::from(&md))
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    extern crate kernel32;
+    extern crate winapi;
+
+    use self::winapi::{BY_HANDLE_FILE_INFORMATION, DWORD, FILETIME, HANDLE, MAX_PATH, WCHAR,
+                       FILE_FLAG_BACKUP_SEMANTICS, FILE_FLAG_OPEN_REPARSE_POINT};
+    use super::{Cached, FsMeta, pretty_time};
+    use uucore::wide::{FromWide, ToWide};
+
+    use std::cmp;
+    use std::fs::{self, Metadata, OpenOptions};
+    use std::io;
+    use std::mem;
+    use std::ops::Deref;
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::AsRawHandle;
+    use std::path::Path;
+    use std::ptr;
+
+    pub use super::libc::c_int;
+
+    // Windows has no mode bits of its own, so they are synthesised from the
+    // file attributes using the usual POSIX values.
+    #[allow(non_camel_case_types)]
+    pub type mode_t = u32;
+
+    pub const S_IFMT: mode_t = 0o170000;
+    pub const S_IFSOCK: mode_t = 0o140000;
+    pub const S_IFLNK: mode_t = 0o120000;
+    pub const S_IFREG: mode_t = 0o100000;
+    pub const S_IFBLK: mode_t = 0o060000;
+    pub const S_IFDIR: mode_t = 0o040000;
+    pub const S_IFCHR: mode_t = 0o020000;
+    pub const S_IFIFO: mode_t = 0o010000;
+    pub const S_ISUID: mode_t = 0o4000;
+    pub const S_ISGID: mode_t = 0o2000;
+    pub const S_ISVTX: mode_t = 0o1000;
+    pub const S_IRUSR: mode_t = 0o400;
+    pub const S_IWUSR: mode_t = 0o200;
+    pub const S_IXUSR: mode_t = 0o100;
+    pub const S_IRGRP: mode_t = 0o040;
+    pub const S_IWGRP: mode_t = 0o020;
+    pub const S_IXGRP: mode_t = 0o010;
+    pub const S_IROTH: mode_t = 0o004;
+    pub const S_IWOTH: mode_t = 0o002;
+    pub const S_IXOTH: mode_t = 0o001;
+
+    // FILETIME counts 100ns intervals since 1601-01-01
+    const FILETIME_UNIX_EPOCH: i64 = 116444736000000000;
+    const FILETIME_PER_SEC: i64 = 10000000;
+
+    /// Files are owned by security identifiers rather than numeric ids, so
+    /// there is nothing to look up.
+    pub mod entries {
+        use std::io::{Error, ErrorKind, Result};
+
+        pub fn uid2usr(_id: u32) -> Result<String> {
+            Err(Error::new(ErrorKind::NotFound, "no such id"))
+        }
+
+        pub fn gid2grp(_id: u32) -> Result<String> {
+            Err(Error::new(ErrorKind::NotFound, "no such id"))
+        }
+    }
+
+    /// `Metadata` extended with what GetFileInformationByHandle reports,
+    /// offering the same accessors as `MetadataExt` does on Unix.
+    pub struct WinMetadata {
+        meta: Metadata,
+        info: BY_HANDLE_FILE_INFORMATION,
+        blksize: u64,
+    }
+
+    impl Deref for WinMetadata {
+        type Target = Metadata;
+
+        fn deref(&self) -> &Metadata {
+            &self.meta
+        }
+    }
+
+    fn filetime(ft: &FILETIME) -> (i64, i64) {
+        let ticks = (ft.dwHighDateTime as u64) << 32 | ft.dwLowDateTime as u64;
+        let since_epoch = ticks as i64 - FILETIME_UNIX_EPOCH;
+        let (secs, rem) = (since_epoch / FILETIME_PER_SEC, since_epoch % FILETIME_PER_SEC);
+        if rem < 0 {
+            (secs - 1, (rem + FILETIME_PER_SEC) * 100)
+        } else {
+            (secs, rem * 100)
+        }
+    }
+
+    impl WinMetadata {
+        pub fn mode(&self) -> mode_t {
+            let ftype = self.meta.file_type();
+            let (kind, perm) = if ftype.is_symlink() {
+                (S_IFLNK, 0o777)
+            } else if ftype.is_dir() {
+                (S_IFDIR, 0o755)
+            } else {
+                (S_IFREG, 0o644)
+            };
+            if !ftype.is_symlink() && self.meta.permissions().readonly() {
+                kind | (perm & !(S_IWUSR | S_IWGRP | S_IWOTH))
+            } else {
+                kind | perm
+            }
+        }
+        pub fn ino(&self) -> u64 {
+            (self.info.nFileIndexHigh as u64) << 32 | self.info.nFileIndexLow as u64
+        }
+        pub fn dev(&self) -> u64 {
+            self.info.dwVolumeSerialNumber as u64
+        }
+        pub fn nlink(&self) -> u64 {
+            self.info.nNumberOfLinks as u64
+        }
+        pub fn uid(&self) -> u32 {
+            0
+        }
+        pub fn gid(&self) -> u32 {
+            0
+        }
+        pub fn rdev(&self) -> u64 {
+            0
+        }
+        pub fn blksize(&self) -> u64 {
+            self.blksize
+        }
+        pub fn blocks(&self) -> u64 {
+            (self.meta.len() + 511) / 512
+        }
+        pub fn atime(&self) -> i64 {
+            filetime(&self.info.ftLastAccessTime).0
+        }
+        pub fn atime_nsec(&self) -> i64 {
+            filetime(&self.info.ftLastAccessTime).1
+        }
+        pub fn mtime(&self) -> i64 {
+            filetime(&self.info.ftLastWriteTime).0
+        }
+        pub fn mtime_nsec(&self) -> i64 {
+            filetime(&self.info.ftLastWriteTime).1
+        }
+        // there is no separate status change time
+        pub fn ctime(&self) -> i64 {
+            self.mtime()
+        }
+        pub fn ctime_nsec(&self) -> i64 {
+            self.mtime_nsec()
+        }
+        pub fn pretty_birth(&self) -> String {
+            let (secs, nsecs) = filetime(&self.info.ftCreationTime);
+            pretty_time(secs, nsecs)
+        }
+        pub fn birth(&self) -> String {
+            format!("{}", filetime(&self.info.ftCreationTime).0)
+        }
+    }
+
+    pub fn stat<P: AsRef<Path>>(path: P, follow: bool, _cached: Cached) -> io::Result<WinMetadata> {
+        let path = path.as_ref();
+        let meta = try!(if follow {
+            fs::metadata(path)
+        } else {
+            fs::symlink_metadata(path)
+        });
+
+        // backup semantics are needed to open a directory
+        let mut flags = FILE_FLAG_BACKUP_SEMANTICS;
+        if !follow {
+            flags |= FILE_FLAG_OPEN_REPARSE_POINT;
+        }
+        let file = try!(OpenOptions::new().access_mode(0).custom_flags(flags).open(path));
+        let mut info: BY_HANDLE_FILE_INFORMATION = unsafe { mem::zeroed() };
+        if unsafe { kernel32::GetFileInformationByHandle(file.as_raw_handle() as HANDLE, &mut info) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let blksize = statfs(path).map(|fs| fs.blksize() as u64).unwrap_or(4096);
+        Ok(WinMetadata {
+            meta: meta,
+            info: info,
+            blksize: blksize,
+        })
+    }
+
+    /// The root of the volume holding `path`, such as `C:\`
+    pub fn volume_root<P: AsRef<Path>>(path: P) -> io::Result<String> {
+        let mut root = [0 as WCHAR; MAX_PATH + 1];
+        let path = path.as_ref().to_wide_null();
+        if unsafe { kernel32::GetVolumePathNameW(path.as_ptr(), root.as_mut_ptr(), root.len() as DWORD) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(String::from_wide_null(&root))
+    }
+
+    pub struct WinStatfs {
+        cluster_size: u64,
+        total_bytes: u64,
+        free_bytes: u64,
+        avail_bytes: u64,
+        serial: DWORD,
+        max_component: DWORD,
+        fs_name: String,
+    }
+
+    impl FsMeta for WinStatfs {
+        fn blksize(&self) -> i64 {
+            self.cluster_size as i64
+        }
+        fn iosize(&self) -> i64 {
+            self.cluster_size as i64
+        }
+        fn total_blocks(&self) -> u64 {
+            self.total_bytes / self.cluster_size
+        }
+        fn free_blocks(&self) -> u64 {
+            self.free_bytes / self.cluster_size
+        }
+        fn avail_blocks(&self) -> u64 {
+            self.avail_bytes / self.cluster_size
+        }
+        // there is no fixed inode table to report on
+        fn total_fnodes(&self) -> u64 {
+            0
+        }
+        fn free_fnodes(&self) -> u64 {
+            0
+        }
+        fn fs_type(&self) -> i64 {
+            0
+        }
+        fn fsid(&self) -> u64 {
+            self.serial as u64
+        }
+        fn namelen(&self) -> i64 {
+            self.max_component as i64
+        }
+        fn fs_name(&self) -> String {
+            self.fs_name.clone()
+        }
+    }
+
+    pub fn statfs<P: AsRef<Path>>(path: P) -> Result<WinStatfs, String> {
+        let root = try!(volume_root(path).map_err(|e| e.to_string())).to_wide_null();
+
+        let (mut sectors_per_cluster, mut bytes_per_sector, mut free_clusters, mut total_clusters) = (0, 0, 0, 0);
+        let (mut avail_bytes, mut total_bytes, mut free_bytes) = (0, 0, 0);
+        let (mut serial, mut max_component, mut fs_flags) = (0, 0, 0);
+        let mut fs_name = [0 as WCHAR; MAX_PATH + 1];
+        let ok = unsafe {
+            kernel32::GetDiskFreeSpaceW(root.as_ptr(),
+                                        &mut sectors_per_cluster,
+                                        &mut bytes_per_sector,
+                                        &mut free_clusters,
+                                        &mut total_clusters) != 0 &&
+            // the cluster counts above saturate on large volumes
+            kernel32::GetDiskFreeSpaceExW(root.as_ptr(), &mut avail_bytes, &mut total_bytes, &mut free_bytes) != 0 &&
+            kernel32::GetVolumeInformationW(root.as_ptr(),
+                                            ptr::null_mut(),
+                                            0,
+                                            &mut serial,
+                                            &mut max_component,
+                                            &mut fs_flags,
+                                            fs_name.as_mut_ptr(),
+                                            fs_name.len() as DWORD) != 0
+        };
+        if !ok {
+            return Err(io::Error::last_os_error().to_string());
+        }
+
+        Ok(WinStatfs {
+            cluster_size: cmp::max(sectors_per_cluster as u64 * bytes_per_sector as u64, 1),
+            total_bytes: total_bytes,
+            free_bytes: free_bytes,
+            avail_bytes: avail_bytes,
+            serial: serial,
+            max_component: max_component,
+            fs_name: String::from_wide_null(&fs_name),
+        })
+    }
+}
@@ -17,20 +17,25 @@ pub use fsext::*;
 
 #[macro_use]
 extern crate uucore;
+#[cfg(unix)]
 use uucore::entries;
+use uucore::quoting::quote;
 
 use std::{fs, iter, cmp};
+#[cfg(unix)]
 use std::fs::File;
+#[cfg(unix)]
 use std::io::{BufReader, BufRead};
 use std::borrow::Cow;
-use std::os::unix::fs::{FileTypeExt, MetadataExt};
+#[cfg(all(unix, not(target_os = "linux")))]
+use std::os::unix::fs::MetadataExt;
 use std::path::Path;
 use std::convert::AsRef;
 
 macro_rules! check_bound {
     ($str: ident, $bound:expr, $beg: expr, $end: expr) => (
         if $end >= $bound {
-            return Err(format!("‘{}’: invalid directive", &$str[$beg..$end]));
+            return Err(format!("{}: invalid directive", quote(&$str[$beg..$end])));
         }
 
     )
@@ -88,6 +93,7 @@ macro_rules! print_adjusted {
 static NAME: &'static str = "stat";
 static VERSION: &'static str = env!("CARGO_PKG_VERSION");
 
+#[cfg(unix)]
 const MOUNT_INFO: &'static str = "/etc/mtab";
 pub const F_ALTER: u8 = 1;
 pub const F_ZERO: u8 = 1 << 1;
@@ -199,6 +205,7 @@ pub fn group_num<'a>(s: &'a str) -> Cow<'a, str> {
 
 pub struct Stater {
     follow: bool,
+    cached: Cached,
     showfs: bool,
     from_user: bool,
     files: Vec<String>,
@@ -471,25 +478,29 @@ impl Stater {
         let default_dev_tokens = Stater::generate_tokens(&Stater::default_fmt(showfs, terse, true), use_printf)
             .unwrap();
 
-        let reader = BufReader::new(File::open(MOUNT_INFO).expect("Failed to read /etc/mtab"));
-        let mut mount_list = reader.lines()
-                                   .filter_map(|s| s.ok())
-                                   .filter_map(|line| line.split_whitespace().nth(1).map(|s| s.to_owned()))
-                                   .collect::<Vec<String>>();
-        // Reverse sort. The longer comes first.
-        mount_list.sort_by(|a, b| b.cmp(a));
-
         Ok(Stater {
             follow: matches.opt_present("dereference"),
+            cached: match matches.opt_str("cached") {
+                // validated in uumain()
+                Some(ref mode) if mode == "never" => Cached::Never,
+                Some(ref mode) if mode == "always" => Cached::Always,
+                _ => Cached::Default,
+            },
             showfs: showfs,
             from_user: !fmtstr.is_empty(),
             files: matches.free,
             default_tokens: default_tokens,
             default_dev_tokens: default_dev_tokens,
-            mount_list: mount_list,
+            mount_list: read_mount_list(),
         })
     }
 
+    #[cfg(windows)]
+    fn find_mount_point<P: AsRef<Path>>(&self, p: P) -> Option<String> {
+        volume_root(p).ok()
+    }
+
+    #[cfg(unix)]
     fn find_mount_point<P: AsRef<Path>>(&self, p: P) -> Option<String> {
         let path = match p.as_ref().canonicalize() {
             Ok(s) => s,
@@ -514,15 +525,10 @@ impl Stater {
     fn do_stat(&self, file: &str) -> i32 {
 
         if !self.showfs {
-            let result = if self.follow {
-                fs::metadata(file)
-            } else {
-                fs::symlink_metadata(file)
-            };
-            match result {
+            match stat(file, self.follow, self.cached) {
                 Ok(meta) => {
-                    let ftype = meta.file_type();
-                    let tokens = if self.from_user || !(ftype.is_char_device() || ftype.is_block_device()) {
+                    let kind = meta.mode() as mode_t & S_IFMT;
+                    let tokens = if self.from_user || !(kind == S_IFCHR || kind == S_IFBLK) {
                         &self.default_tokens
                     } else {
                         &self.default_dev_tokens
@@ -616,7 +622,7 @@ impl Stater {
                                     }
                                     // quoted file name with dereference if symbolic link
                                     'N' => {
-                                        if ftype.is_symlink() {
+                                        if kind == S_IFLNK {
                                             let dst = match fs::read_link(file) {
                                                 Ok(path) => path,
                                                 Err(e) => {
@@ -791,7 +797,7 @@ impl Stater {
                                     }
                                     // file system type in human readable form
                                     'T' => {
-                                        arg = pretty_fstype(meta.fs_type()).into_owned();
+                                        arg = meta.fs_name();
                                         otype = OutputType::Str;
                                     }
                                     _ => {
@@ -844,6 +850,24 @@ impl Stater {
     }
 }
 
+#[cfg(unix)]
+fn read_mount_list() -> Vec<String> {
+    let reader = BufReader::new(File::open(MOUNT_INFO).expect("Failed to read /etc/mtab"));
+    let mut mount_list = reader.lines()
+                               .filter_map(|s| s.ok())
+                               .filter_map(|line| line.split_whitespace().nth(1).map(|s| s.to_owned()))
+                               .collect::<Vec<String>>();
+    // Reverse sort. The longer comes first.
+    mount_list.sort_by(|a, b| b.cmp(a));
+    mount_list
+}
+
+// volumes are looked up directly in find_mount_point()
+#[cfg(windows)]
+fn read_mount_list() -> Vec<String> {
+    Vec::new()
+}
+
 pub fn uumain(args: Vec<String>) -> i32 {
     let mut opts = Options::new();
 
@@ -851,6 +875,7 @@ pub fn uumain(args: Vec<String>) -> i32 {
     opts.optflag("", "version", "output version information and exit");
 
     opts.optflag("L", "dereference", "follow links");
+    opts.optopt("", "cached", "", "MODE");
     opts.optflag("f",
                  "file-system",
                  "display file system status instead of file status");
@@ -880,6 +905,16 @@ pub fn uumain(args: Vec<String>) -> i32 {
         return 1;
     }
 
+    if let Some(mode) = matches.opt_str("cached") {
+        if !["default", "never", "always"].contains(&mode.as_str()) {
+            disp_err!("invalid argument {} for {}\nValid arguments are:\n  - 'default'\n  - 'never'\n  \
+                       - 'always'",
+                      quote(&mode),
+                      quote("--cached"));
+            return 1;
+        }
+    }
+
     match Stater::new(matches) {
         Ok(stater) => stater.exec(),
         Err(e) => {
@@ -900,6 +935,8 @@ Display file or file system status.
 
 Mandatory arguments to long options are mandatory for short options too.
   -L, --dereference     follow links
+      --cached=MODE     specify how to use cached attributes;
+                          useful on remote file systems. See MODE below
   -f, --file-system     display file system status instead of file status
   -c  --format=FORMAT   use the specified FORMAT instead of the default;
                           output a newline after each use of FORMAT
@@ -958,6 +995,11 @@ Valid format sequences for file systems:
   %t   file system type in hex
   %T   file system type in human readable form
 
+The --cached MODE argument can be: always, never, or default.
+'always' will use cached attributes if available, while
+'never' will try to synchronize with the latest attributes, and
+'default' will leave it up to the underlying file system.
+
 NOTE: your shell may have its own version of stat, which usually supersedes
 the version described here.  Please refer to your shell's documentation
 for details about the options it supports."#,
@@ -0,0 +1,76 @@
+/*
+ * This file is part of the uutils coreutils package.
+ *
+ * For the full copyright and license information, please view the LICENSE
+ * file that was distributed with this source code.
+ */
+
+extern crate libc;
+
+use std::cmp;
+use std::ffi::CString;
+use std::fs::File;
+use std::io::{Error, ErrorKind};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::time::Duration;
+use uucore::timer::{self, Clock};
+
+/// Waits for followed files to change, as inotify tells, or where that
+/// cannot be done for each of them, for the time given.
+pub struct Watcher {
+    fd: libc::c_int,
+    polling: bool,
+}
+
+impl Watcher {
+    pub fn new() -> Watcher {
+        let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC | libc::IN_NONBLOCK) };
+        Watcher { fd: fd, polling: fd < 0 }
+    }
+
+    /// Watch `path`, which is open as `_file`.
+    pub fn watch(&mut self, path: &Path, _file: &File) {
+        if self.polling {
+            return;
+        }
+        let mask = libc::IN_MODIFY | libc::IN_ATTRIB | libc::IN_DELETE_SELF | libc::IN_MOVE_SELF;
+        self.polling = match CString::new(path.as_os_str().as_bytes()) {
+            Ok(path) => unsafe { libc::inotify_add_watch(self.fd, path.as_ptr(), mask) < 0 },
+            Err(_) => true,
+        };
+    }
+
+    /// Wait until a watched file may have changed, or at most `timeout`.
+    pub fn wait(&mut self, timeout: Duration) {
+        if self.polling {
+            timer::sleep(Clock::Monotonic, timeout);
+            return;
+        }
+        let mut pollfd = libc::pollfd { fd: self.fd, events: libc::POLLIN, revents: 0 };
+        // poll takes at most a c_int of milliseconds, some 24 days; a longer
+        // wait is cut short to that, and followed files are looked at again
+        let millis = timeout.as_secs().saturating_mul(1000).saturating_add(timeout.subsec_nanos() as u64 / 1_000_000);
+        let millis = cmp::min(millis, libc::c_int::MAX as u64) as libc::c_int;
+        match unsafe { libc::poll(&mut pollfd, 1, millis) } {
+            -1 if Error::last_os_error().kind() != ErrorKind::Interrupted => {
+                self.polling = true;
+                timer::sleep(Clock::Monotonic, timeout);
+            }
+            n if n > 0 => {
+                // which file changed makes no difference, as all are read again
+                let mut events = [0u8; 4096];
+                while unsafe { libc::read(self.fd, events.as_mut_ptr() as *mut libc::c_void, events.len()) } > 0 {}
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        if self.fd >= 0 {
+            unsafe { libc::close(self.fd) };
+        }
+    }
+}
@@ -0,0 +1,72 @@
+/*
+ * This file is part of the uutils coreutils package.
+ *
+ * For the full copyright and license information, please view the LICENSE
+ * file that was distributed with this source code.
+ */
+
+extern crate libc;
+
+use std::fs::File;
+use std::io::{Error, ErrorKind};
+use std::mem;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::ptr;
+use std::time::Duration;
+use uucore::timer::{self, Clock};
+
+/// Waits for followed files to change, as kqueue tells, or where that
+/// cannot be done for each of them, for the time given.
+pub struct Watcher {
+    kq: libc::c_int,
+    polling: bool,
+}
+
+impl Watcher {
+    pub fn new() -> Watcher {
+        let kq = unsafe { libc::kqueue() };
+        Watcher { kq: kq, polling: kq < 0 }
+    }
+
+    /// Watch `_path`, which is open as `file`.
+    pub fn watch(&mut self, _path: &Path, file: &File) {
+        if self.polling {
+            return;
+        }
+        // the fields' types differ from system to system
+        let mut event: libc::kevent = unsafe { mem::zeroed() };
+        event.ident = file.as_raw_fd() as _;
+        event.filter = libc::EVFILT_VNODE as _;
+        event.flags = (libc::EV_ADD | libc::EV_CLEAR) as _;
+        event.fflags = (libc::NOTE_WRITE | libc::NOTE_EXTEND | libc::NOTE_ATTRIB | libc::NOTE_DELETE
+                        | libc::NOTE_RENAME) as _;
+        self.polling = unsafe { libc::kevent(self.kq, &event, 1, ptr::null_mut(), 0, ptr::null()) < 0 };
+    }
+
+    /// Wait until a watched file may have changed, or at most `timeout`.
+    pub fn wait(&mut self, timeout: Duration) {
+        if self.polling {
+            timer::sleep(Clock::Monotonic, timeout);
+            return;
+        }
+        let timeout = libc::timespec {
+            tv_sec: timeout.as_secs() as libc::time_t,
+            tv_nsec: timeout.subsec_nanos() as libc::c_long,
+        };
+        // which file changed makes no difference, as all are read again
+        let mut event: libc::kevent = unsafe { mem::zeroed() };
+        if unsafe { libc::kevent(self.kq, ptr::null(), 0, &mut event, 1, &timeout) < 0 }
+            && Error::last_os_error().kind() != ErrorKind::Interrupted {
+            self.polling = true;
+        }
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        if self.kq >= 0 {
+            unsafe { libc::close(self.kq) };
+        }
+    }
+}
@@ -13,8 +13,30 @@ pub use self::unix::{Pid, supports_pid_checks, ProcessChecker};
 #[cfg(windows)]
 pub use self::windows::{Pid, supports_pid_checks, ProcessChecker};
 
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use self::inotify::Watcher;
+
+#[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd", target_os = "dragonfly",
+          target_os = "netbsd", target_os = "openbsd"))]
+pub use self::kqueue::Watcher;
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos", target_os = "ios",
+              target_os = "freebsd", target_os = "dragonfly", target_os = "netbsd", target_os = "openbsd")))]
+pub use self::polling::Watcher;
+
 #[cfg(unix)]
 mod unix;
 
 #[cfg(windows)]
 mod windows;
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod inotify;
+
+#[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd", target_os = "dragonfly",
+          target_os = "netbsd", target_os = "openbsd"))]
+mod kqueue;
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos", target_os = "ios",
+              target_os = "freebsd", target_os = "dragonfly", target_os = "netbsd", target_os = "openbsd")))]
+mod polling;
@@ -0,0 +1,26 @@
+/*
+ * This file is part of the uutils coreutils package.
+ *
+ * For the full copyright and license information, please view the LICENSE
+ * file that was distributed with this source code.
+ */
+
+use std::fs::File;
+use std::path::Path;
+use std::time::Duration;
+use uucore::timer::{self, Clock};
+
+/// Waits the time given, on systems that cannot tell when a file changes.
+pub struct Watcher;
+
+impl Watcher {
+    pub fn new() -> Watcher {
+        Watcher
+    }
+
+    pub fn watch(&mut self, _path: &Path, _file: &File) {}
+
+    pub fn wait(&mut self, timeout: Duration) {
+        timer::sleep(Clock::Monotonic, timeout);
+    }
+}
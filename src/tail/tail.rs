@@ -19,15 +19,16 @@ extern crate uucore;
 
 mod platform;
 
+use std::cmp;
 use std::collections::VecDeque;
-use std::error::Error;
-use std::fmt;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, stdin, stdout, Write};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, stdin, stdout, Write};
 use std::path::Path;
 use std::str::from_utf8;
-use std::thread::sleep;
 use std::time::Duration;
+use uucore::filesystem::{FileSystem, RealFileSystem};
+use uucore::parse_number::NumberArg;
+use uucore::sparse::SparseReader;
 
 static NAME: &'static str = "tail";
 static VERSION: &'static str = env!("CARGO_PKG_VERSION");
@@ -42,7 +43,10 @@ struct Settings {
     sleep_msec: u32,
     beginning: bool,
     follow: bool,
+    follow_name: bool,
+    retry: bool,
     pid: platform::Pid,
+    headers: bool,
 }
 
 impl Default for Settings {
@@ -52,7 +56,10 @@ impl Default for Settings {
             sleep_msec: 1000,
             beginning: false,
             follow: false,
+            follow_name: false,
+            retry: false,
             pid: 0,
+            headers: false,
         }
     }
 }
@@ -72,15 +79,19 @@ pub fn uumain(args: Vec<String>) -> i32 {
 
     opts.optopt("c", "bytes", "Number of bytes to print", "k");
     opts.optopt("n", "lines", "Number of lines to print", "k");
-    opts.optflag("f", "follow", "Print the file as it grows");
+    opts.optflag("f", "", "Print the file as it grows");
+    opts.optflagopt("", "follow", "Print the file as it grows, following its descriptor, or its name if HOW is \
+                                   'name'", "HOW");
+    opts.optflag("F", "", "Same as --follow=name --retry");
+    opts.optflag("", "retry", "Keep trying to open a file that cannot be");
     opts.optopt("s", "sleep-interval", "Number or seconds to sleep between polling the file when running with -f", "n");
     opts.optopt("", "pid", "with -f, terminate after process ID, PID dies", "PID");
     opts.optflag("z", "zero-terminated", "Line delimiter is NUL, not newline");
     opts.optflag("h", "help", "help");
     opts.optflag("V", "version", "version");
     opts.optflag("v", "verbose", "always output headers giving file names");
-    // TODO: --silent flag as alias to --quiet
     opts.optflag("q", "quiet", "never output headers giving file names");
+    opts.optflag("", "silent", "same as --quiet");
 
     let given_options = match opts.parse(&args) {
         Ok (m) => { m }
@@ -96,62 +107,81 @@ pub fn uumain(args: Vec<String>) -> i32 {
     }
     if given_options.opt_present("V") { version(); return 0 }
 
-    settings.follow = given_options.opt_present("f");
+    settings.follow = given_options.opt_present("f") || given_options.opt_present("follow")
+                      || given_options.opt_present("F");
+    if let Some(how) = given_options.opt_str("follow") {
+        if !how.is_empty() && "name".starts_with(&how[..]) {
+            settings.follow_name = true;
+        } else if how.is_empty() || !"descriptor".starts_with(&how[..]) {
+            show_error!("invalid argument '{}' for '--follow'", how);
+            return 1;
+        }
+    }
+    if given_options.opt_present("F") {
+        settings.follow_name = true;
+        settings.retry = true;
+    }
+    if given_options.opt_present("retry") {
+        settings.retry = true;
+        if !settings.follow {
+            show_warning!("--retry ignored; --retry is useful only when following");
+        } else if !settings.follow_name {
+            show_warning!("--retry only effective for the initial open");
+        }
+    }
     if settings.follow {
-        match given_options.opt_str("s") {
-            Some(n) => {
-                let parsed: Option<u32> = n.parse().ok();
-                match parsed {
-                    Some(m) => { settings.sleep_msec = m * 1000 }
-                    None => {}
+        if let Some(n) = given_options.opt_str("s") {
+            match n.parse::<f64>() {
+                Ok(secs) if secs >= 0.0 => settings.sleep_msec = (secs * 1000.0) as u32,
+                _ => {
+                    show_error!("invalid number of seconds: '{}'", n);
+                    return 1;
                 }
             }
-            None => {}
-        };
+        }
     }
 
     if let Some(pid_str) = given_options.opt_str("pid") {
-        if let Ok(pid) = pid_str.parse() {
-            settings.pid = pid;
-            if pid != 0 {
-                if !settings.follow {
-                    show_warning!("PID ignored; --pid=PID is useful only when following");
-                }
+        let pid = match pid_str.parse::<platform::Pid>() {
+            Ok(pid) if !pid_str.starts_with('-') => pid,
+            _ => {
+                show_error!("invalid PID: '{}'", pid_str);
+                return 1;
+            }
+        };
+        settings.pid = pid;
+        if pid != 0 {
+            if !settings.follow {
+                show_warning!("PID ignored; --pid=PID is useful only when following");
+            }
 
-                if !platform::supports_pid_checks(pid) {
-                    show_warning!("--pid=PID is not supported on this system");
-                    settings.pid = 0;
-                }
+            if !platform::supports_pid_checks(pid) {
+                show_warning!("--pid=PID is not supported on this system");
+                settings.pid = 0;
             }
         }
     }
 
+    // +N counts from the start, and -N (the same as N) from the end, where
+    // the + is part of the number but the - isn't
     match given_options.opt_str("n") {
         Some(n) => {
-            let mut slice: &str = n.as_ref();
-            if slice.chars().next().unwrap_or('_') == '+' {
-                settings.beginning = true;
-                slice = &slice[1..];
-            }
-            match parse_size(slice) {
+            settings.beginning = n.starts_with('+');
+            match NumberArg::new("number of lines").suffixes(true).parse(strip_minus(&n)) {
                 Ok(m) => settings.mode = FilterMode::Lines(m, '\n' as u8),
-                Err(e) => {
-                    show_error!("{}", e.description());
+                Err(msg) => {
+                    show_error!("{}", msg);
                     return 1;
                 }
             }
         }
         None => match given_options.opt_str("c") {
             Some(n) => {
-                let mut slice: &str = n.as_ref();
-                if slice.chars().next().unwrap_or('_') == '+' {
-                    settings.beginning = true;
-                    slice = &slice[1..];
-                }
-                match parse_size(slice) {
+                settings.beginning = n.starts_with('+');
+                match parse_size(strip_minus(&n)) {
                     Ok(m) => settings.mode = FilterMode::Bytes(m),
-                    Err(e) => {
-                        show_error!("{}", e.description());
+                    Err(msg) => {
+                        show_error!("{}", msg);
                         return 1;
                     }
                 }
@@ -166,139 +196,98 @@ pub fn uumain(args: Vec<String>) -> i32 {
         }
     }
 
-    let verbose = given_options.opt_present("v");
-    let quiet = given_options.opt_present("q");
-
-    let files = given_options.free;
-
+    // headers as whichever of -q and -v comes last has it, and without
+    // either, only for several files
+    let (quiet, verbose) = {
+        let last = |name| given_options.opt_positions(name).into_iter().max();
+        (cmp::max(last("q"), last("silent")), last("v"))
+    };
+    let mut files = given_options.free;
     if files.is_empty() {
-        let mut buffer = BufReader::new(stdin());
-        unbounded_tail(&mut buffer, &settings);
-    } else {
-        let multiple = files.len() > 1;
-        let mut first_header = true;
-        let mut readers = Vec::new();
-
-        for filename in &files {
-            if (multiple || verbose) && !quiet {
-                if !first_header { println!(""); }
-                println!("==> {} <==", filename);
+        files.push("-".to_owned());
+    }
+    settings.headers = if verbose > quiet { true } else { quiet.is_none() && files.len() > 1 };
+
+    let mut status = 0;
+    let mut first_header = true;
+    let mut readers = Vec::new();
+    let mut header = |name: &str| {
+        if settings.headers {
+            if !first_header { println!(""); }
+            println!("==> {} <==", name);
+        }
+        first_header = false;
+    };
+
+    for filename in &files {
+        if filename == "-" {
+            header("standard input");
+            match stdin_file() {
+                Some(file) => bounded_tail(&mut SparseReader::new(file), &settings),
+                None => unbounded_tail(&mut BufReader::new(stdin()), &settings),
             }
-            first_header = false;
+            continue;
+        }
 
-            let path = Path::new(filename);
-            if path.is_dir() {
-                continue;
+        let path = Path::new(filename);
+        if path.is_dir() {
+            header(filename);
+            show_error!("error reading '{}': Is a directory", filename);
+            if settings.follow {
+                show_error!("{}: cannot follow end of this type of file; giving up on this name", filename);
             }
-            let mut file = File::open(&path).unwrap();
-            if is_seekable(&mut file) {
-                bounded_tail(&file, &settings);
-                if settings.follow {
-                    let reader = BufReader::new(file);
-                    readers.push(reader);
-                }
-            } else {
-                let mut reader = BufReader::new(file);
-                unbounded_tail(&mut reader, &settings);
-                if settings.follow {
-                    readers.push(reader);
+            status = 1;
+            continue;
+        }
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(e) => {
+                show_error!("cannot open '{}' for reading: {}", filename, e);
+                status = 1;
+                if settings.follow && settings.retry {
+                    readers.push(Followed { reader: None, identity: None, name: filename, partial: Vec::new() });
                 }
+                continue;
             }
-        }
+        };
 
-        if settings.follow {
-            follow(&mut readers[..], &files[..], &settings);
+        header(filename);
+        let identity = file_named(&RealFileSystem, path, None).unwrap_or(None);
+        let mut file = SparseReader::new(file);
+        if is_seekable(&mut file) {
+            bounded_tail(&mut file, &settings);
+            if settings.follow {
+                let reader = BufReader::new(file);
+                readers.push(Followed { reader: Some(reader), identity: identity, name: filename, partial: Vec::new() });
+            }
+        } else {
+            let mut reader = BufReader::new(file);
+            unbounded_tail(&mut reader, &settings);
+            if settings.follow {
+                readers.push(Followed { reader: Some(reader), identity: identity, name: filename, partial: Vec::new() });
+            }
         }
     }
 
-    0
-}
-
-#[derive(Debug, PartialEq, Eq)]
-pub enum ParseSizeErr {
-    ParseFailure(String),
-    SizeTooBig(String),
-}
-
-impl Error for ParseSizeErr {
-    fn description(&self) -> &str {
-        match *self {
-            ParseSizeErr::ParseFailure(ref s) => &*s,
-            ParseSizeErr::SizeTooBig(ref s) => &*s,
+    if settings.follow {
+        if !readers.is_empty() {
+            follow(&mut readers[..], &settings);
+        } else if status != 0 {
+            show_error!("no files remaining");
         }
     }
-}
 
-impl fmt::Display for ParseSizeErr {
-    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        write!(f, "{}", Error::description(self))
-    }
+    status
 }
 
-impl ParseSizeErr {
-    fn parse_failure(s: &str) -> ParseSizeErr {
-        ParseSizeErr::ParseFailure(format!("invalid size: '{}'", s))
-    }
-
-    fn size_too_big(s: &str) -> ParseSizeErr {
-        ParseSizeErr::SizeTooBig(
-            format!("invalid size: '{}': Value too large to be stored in data type", s))
-    }
+fn strip_minus(count: &str) -> &str {
+    if count.starts_with('-') { &count[1..] } else { count }
 }
 
-pub type ParseSizeResult = Result<u64, ParseSizeErr>;
-
-pub fn parse_size(mut size_slice: &str) -> Result<u64, ParseSizeErr> {
-    let mut base =
-        if size_slice.chars().last().unwrap_or('_') == 'B' {
-            size_slice = &size_slice[..size_slice.len() - 1];
-            1000u64
-        } else {
-            1024u64
-        };
-
-    let exponent =
-        if size_slice.len() > 0 {
-            let mut has_suffix = true;
-            let exp = match size_slice.chars().last().unwrap_or('_') {
-                'K' | 'k' => 1u64,
-                'M' => 2u64,
-                'G' => 3u64,
-                'T' => 4u64,
-                'P' => 5u64,
-                'E' => 6u64,
-                'Z' | 'Y' => {
-                    return Err(ParseSizeErr::size_too_big(size_slice));
-                },
-                'b' => {
-                    base = 512u64;
-                    1u64
-                }
-                _ => {
-                    has_suffix = false;
-                    0u64
-                }
-            };
-            if has_suffix {
-                size_slice = &size_slice[..size_slice.len() - 1];
-            }
-            exp
-        } else {
-            0u64
-        };
-
-    let mut multiplier = 1u64;
-    for _ in 0u64 .. exponent {
-        multiplier *= base;
-    }
-    if base == 1000u64 && exponent == 0u64 {
-        // sole B is not a valid suffix
-        Err(ParseSizeErr::parse_failure(size_slice))
-    } else {
-        let value: Option<u64> = size_slice.parse().ok();
-        value.map(|v| Ok(multiplier * v))
-             .unwrap_or(Err(ParseSizeErr::parse_failure(size_slice)))
-    }
+/// Parse the argument of -c, which may have a size suffix such as `K` or
+/// `MB`.
+pub fn parse_size(size: &str) -> Result<u64, String> {
+    NumberArg::new("number of bytes").suffixes(true).parse(size)
 }
 
 // It searches for an option in the form of -123123
@@ -335,125 +324,282 @@ fn obsolete(options: &[String]) -> (Vec<String>, Option<u64>) {
     (options, None)
 }
 
-/// When reading files in reverse in `bounded_tail`, this is the size of each
-/// block read at a time.
+/// When reading files in `bounded_tail`, this is the size of each block read
+/// at a time.
 const BLOCK_SIZE: u64 = 1 << 16;
 
-fn follow<T: Read>(readers: &mut [BufReader<T>], filenames: &[String], settings: &Settings) {
+/// A file followed, by the name it was given as.  It has no reader while it
+/// cannot be opened.
+struct Followed<'a> {
+    reader: Option<BufReader<SparseReader>>,
+    /// The device and inode numbers of the file open, as far as they are known
+    identity: Option<(u64, u64)>,
+    name: &'a String,
+    /// The start of a line read from the file whose end has not been
+    /// written to it yet
+    partial: Vec<u8>,
+}
+
+/// Print what is added to the files in `readers`, each with its name, as it
+/// is written, until the process given by --pid dies.  A file that shrinks
+/// has been truncated, and is printed again from its start.  With
+/// --follow=name, a file whose name comes to name another file, as when logs
+/// are rotated, is followed there, and with --retry, so is one that cannot
+/// be opened until it can be.
+///
+/// Everything read on one pass over the files is written at once.  With
+/// more than one file, a line is only written once its end has been, so
+/// that the lines of different files are not mixed together however slowly
+/// they are written.  Only one longer than a block, or one left unfinished
+/// by a file that is truncated, replaced or gone, or when tail stops, is
+/// written in pieces.
+fn follow(readers: &mut [Followed], settings: &Settings) {
     assert!(settings.follow);
     let mut last = readers.len() - 1;
-    let mut read_some = false;
+    let hold_partial = readers.len() > 1;
     let mut process = platform::ProcessChecker::new(settings.pid);
+    let mut watcher = platform::Watcher::new();
+    for followed in readers.iter() {
+        if let Some(ref reader) = followed.reader {
+            watcher.watch(Path::new(followed.name), reader.get_ref().get_ref());
+        }
+    }
+    let delimiter = match settings.mode {
+        FilterMode::Lines(_, delimiter) => delimiter,
+        FilterMode::Bytes(_) => b'\n',
+    };
+    let stdout = stdout();
+    let mut stdout = BufWriter::with_capacity(BLOCK_SIZE as usize, stdout.lock());
 
     loop {
-        sleep(Duration::new(0, settings.sleep_msec*1000));
+        crash_if_err!(1, stdout.flush());
+        watcher.wait(Duration::from_millis(settings.sleep_msec as u64));
 
-        let pid_is_dead = !read_some && settings.pid != 0 && process.is_dead();
-        read_some = false;
+        // whatever the process wrote before it died is read on this pass
+        let pid_is_dead = settings.pid != 0 && process.is_dead();
 
-        for (i, reader) in readers.iter_mut().enumerate() {
-            // Print all new content since the last pass
+        for (i, followed) in readers.iter_mut().enumerate() {
+            let filename = followed.name;
+            // what is left of the file, then of any that has replaced it
             loop {
-                let mut datum = String::new();
-                match reader.read_line(&mut datum) {
-                    Ok(0) => break,
-                    Ok(_) => {
-                        read_some = true;
-                        if i != last {
-                            println!("\n==> {} <==", filenames[i]);
-                            last = i;
+                if let Some(ref mut reader) = followed.reader {
+                    if is_truncated(reader) {
+                        print_partial(&mut stdout, followed.name, &mut followed.partial, i, &mut last, settings);
+                        show_error!("{}: file truncated", filename);
+                        if let Err(e) = reader.seek(SeekFrom::Start(0)) {
+                            crash!(1, "{}: cannot seek to the start: {}", filename, e);
                         }
-                        print!("{}", datum);
-                    },
-                    Err(err) => panic!(err)
+                    }
+
+                    // Print all new content since the last pass
+                    loop {
+                        let mut datum = Vec::new();
+                        match reader.read_until(delimiter, &mut datum) {
+                            Ok(0) => break,
+                            Ok(_) => {
+                                followed.partial.extend_from_slice(&datum);
+                                let complete = datum.last() == Some(&delimiter);
+                                if complete || !hold_partial || followed.partial.len() >= BLOCK_SIZE as usize {
+                                    print_partial(&mut stdout, filename, &mut followed.partial, i, &mut last, settings);
+                                }
+                            },
+                            Err(e) => crash!(1, "error reading '{}': {}", filename, e),
+                        }
+                    }
+                }
+
+                let recheck = if followed.reader.is_some() { settings.follow_name } else { settings.retry };
+                if !recheck {
+                    break;
+                }
+                crash_if_err!(1, stdout.flush());
+                let reopened = reopen(followed, settings, &mut watcher);
+                // the end of a line that was in a file no longer followed
+                // will never come
+                if reopened || followed.reader.is_none() {
+                    print_partial(&mut stdout, filename, &mut followed.partial, i, &mut last, settings);
+                }
+                if !reopened {
+                    break;
                 }
             }
         }
 
+        if !settings.retry && readers.iter().all(|followed| followed.reader.is_none()) {
+            crash_if_err!(1, stdout.flush());
+            show_error!("no files remaining");
+            break;
+        }
         if pid_is_dead {
+            for (i, followed) in readers.iter_mut().enumerate() {
+                print_partial(&mut stdout, followed.name, &mut followed.partial, i, &mut last, settings);
+            }
+            crash_if_err!(1, stdout.flush());
             break;
         }
     }
 }
 
-/// Iterate over bytes in the file, in reverse, until `should_stop` returns
-/// true. The `file` is left seek'd to the position just after the byte that
-/// `should_stop` returned true for.
-fn backwards_thru_file<F>(mut file: &File, size: u64, buf: &mut Vec<u8>, delimiter: u8, should_stop: &mut F)
-    where F: FnMut(u8) -> bool
-{
-    assert!(buf.len() >= BLOCK_SIZE as usize);
+/// Print what has been read of the file `name`, the `i`th followed, and not
+/// yet printed, after a header if the last printed was from another file.
+fn print_partial<W: Write>(stdout: &mut W, name: &str, partial: &mut Vec<u8>, i: usize, last: &mut usize,
+                           settings: &Settings) {
+    if partial.is_empty() {
+        return;
+    }
+    if settings.headers && i != *last {
+        crash_if_err!(1, write!(stdout, "\n==> {} <==\n", name));
+        *last = i;
+    }
+    print_bytes(stdout, partial);
+    partial.clear();
+}
 
-    let max_blocks_to_read = (size as f64 / BLOCK_SIZE as f64).ceil() as usize;
+/// Open the file `followed` is named by if it is not the one it has open,
+/// and return whether it was.  A file that cannot be opened is given up on,
+/// unless with --retry.
+fn reopen(followed: &mut Followed, settings: &Settings, watcher: &mut platform::Watcher) -> bool {
+    let path = Path::new(followed.name);
+    let open = followed.reader.as_ref().and(followed.identity);
+    let opened = file_named(&RealFileSystem, path, open).and_then(|named| match named {
+        Some(identity) => File::open(path).map(|file| Some((file, identity))),
+        None => Ok(None),
+    });
+    match opened {
+        Ok(None) => false,
+        Ok(Some((file, identity))) => {
+            if followed.reader.is_some() {
+                show_error!("'{}' has been replaced;  following new file", followed.name);
+            } else {
+                show_error!("'{}' has appeared;  following new file", followed.name);
+            }
+            watcher.watch(path, &file);
+            followed.reader = Some(BufReader::new(SparseReader::new(file)));
+            followed.identity = Some(identity);
+            true
+        }
+        Err(e) => {
+            followed.identity = None;
+            if followed.reader.take().is_some() {
+                if settings.retry {
+                    show_error!("'{}' has become inaccessible: {}", followed.name, e);
+                } else {
+                    show_error!("{}: {}", followed.name, e);
+                }
+            }
+            false
+        }
+    }
+}
 
-    for block_idx in 0..max_blocks_to_read {
-        let block_size = if block_idx == max_blocks_to_read - 1 {
-            size % BLOCK_SIZE
-        } else {
-            BLOCK_SIZE
-        };
+/// The device and inode numbers of the file `path` names if it is not the
+/// one they are `open` for, as when a log has been rotated, or if nothing
+/// was open.  Where there are no inode numbers, a file is never replaced.
+fn file_named<F: FileSystem>(fs: &F, path: &Path, open: Option<(u64, u64)>) -> io::Result<Option<(u64, u64)>> {
+    let info = try!(fs.metadata(path, true));
+    let identity = (info.dev, info.ino);
+    Ok(if open == Some(identity) { None } else { Some(identity) })
+}
 
-        // Seek backwards by the next block, read the full block into
-        // `buf`, and then seek back to the start of the block again.
-        let pos = file.seek(SeekFrom::Current(-(block_size as i64))).unwrap();
-        file.read_exact(&mut buf[0..(block_size as usize)]).unwrap();
-        let pos2 = file.seek(SeekFrom::Current(-(block_size as i64))).unwrap();
-        assert_eq!(pos, pos2);
-
-        // Iterate backwards through the bytes, calling `should_stop` on each
-        // one.
-        let slice = &buf[0..(block_size as usize)];
-        for (i, ch) in slice.iter().enumerate().rev() {
-            // Ignore one trailing newline.
-            if block_idx == 0 && i as u64 == block_size - 1 && *ch == delimiter {
-                continue;
+// Whether a regular file has become shorter than what has been read of it.
+fn is_truncated(reader: &mut BufReader<SparseReader>) -> bool {
+    match reader.get_ref().get_ref().metadata() {
+        Ok(ref metadata) if metadata.is_file() => {
+            reader.seek(SeekFrom::Current(0)).map_or(false, |pos| metadata.len() < pos)
+        }
+        _ => false,
+    }
+}
+
+/// Where the last `count` lines of the file between `start` and `end`
+/// begin, found by reading backwards from `end` a block at a time.  A
+/// delimiter at the very end only ends the last line.
+fn last_lines_start(file: &mut SparseReader, start: u64, end: u64, mut count: u64, delimiter: u8, buf: &mut [u8])
+    -> io::Result<u64>
+{
+    if count == 0 {
+        return Ok(end);
+    }
+    let mut block_end = end;
+    while block_end > start {
+        let block_start = cmp::max(start, block_end.saturating_sub(buf.len() as u64));
+        let block = &mut buf[..(block_end - block_start) as usize];
+        try!(file.seek(SeekFrom::Start(block_start)));
+        try!(file.read_exact(block));
+        for (i, &byte) in block.iter().enumerate().rev() {
+            if byte == delimiter && block_start + i as u64 != end - 1 {
+                count -= 1;
+                if count == 0 {
+                    return Ok(block_start + i as u64 + 1);
+                }
             }
+        }
+        block_end = block_start;
+    }
+    Ok(start)
+}
 
-            if should_stop(*ch) {
-                file.seek(SeekFrom::Current((i + 1) as i64)).unwrap();
-                return;
+/// Where the file from `start` goes on after its first `count` lines,
+/// found by reading forwards a block at a time.
+fn first_lines_end(file: &mut SparseReader, start: u64, mut count: u64, delimiter: u8, buf: &mut [u8])
+    -> io::Result<u64>
+{
+    let mut block_start = try!(file.seek(SeekFrom::Start(start)));
+    while count > 0 {
+        let n = try!(file.read(buf));
+        if n == 0 {
+            break;
+        }
+        for (i, &byte) in buf[..n].iter().enumerate() {
+            if byte == delimiter {
+                count -= 1;
+                if count == 0 {
+                    return Ok(block_start + i as u64 + 1);
+                }
             }
         }
+        block_start += n as u64;
     }
+    Ok(block_start)
 }
 
 /// When tail'ing a file, we do not need to read the whole file from start to
 /// finish just to find the last n lines or bytes. Instead, we can seek to the
 /// end of the file, and then read the file "backwards" in blocks of size
 /// `BLOCK_SIZE` until we find the location of the first line/byte. This ends up
-/// being a nice performance win for very large files.
-fn bounded_tail(mut file: &File, settings: &Settings) {
-    let size = file.seek(SeekFrom::End(0)).unwrap();
+/// being a nice performance win for very large files, all the more so as
+/// the holes in sparse ones are not read at all.
+///
+/// What comes before where the file is when given to us is left out, as
+/// standard input may have been read from already.
+fn bounded_tail(file: &mut SparseReader, settings: &Settings) {
     let mut buf = vec![0; BLOCK_SIZE as usize];
+    let start = crash_if_err!(1, file.seek(SeekFrom::Current(0)));
+    let end = crash_if_err!(1, file.seek(SeekFrom::End(0)));
+    let end = cmp::max(start, end);
 
     // Find the position in the file to start printing from.
-    match settings.mode {
-        FilterMode::Lines(mut count, delimiter) => {
-            backwards_thru_file(&mut file, size, &mut buf, delimiter, &mut |byte| {
-                if byte == delimiter {
-                    count -= 1;
-                    count == 0
-                } else {
-                    false
-                }
-            });
-        },
-        FilterMode::Bytes(count) => {
-            file.seek(SeekFrom::End(-(count as i64))).unwrap();
-        },
-    }
+    let from = match settings.mode {
+        FilterMode::Lines(count, delimiter) if settings.beginning => {
+            crash_if_err!(1, first_lines_end(file, start, count.saturating_sub(1), delimiter, &mut buf))
+        }
+        FilterMode::Lines(count, delimiter) => {
+            crash_if_err!(1, last_lines_start(file, start, end, count, delimiter, &mut buf))
+        }
+        FilterMode::Bytes(count) if settings.beginning => {
+            cmp::min(start.saturating_add(count.saturating_sub(1)), end)
+        }
+        FilterMode::Bytes(count) => cmp::max(start, end.saturating_sub(count)),
+    };
 
     // Print the target section of the file.
+    crash_if_err!(1, file.seek(SeekFrom::Start(from)));
+    let stdout = stdout();
+    let mut stdout = stdout.lock();
     loop {
-        let bytes_read = file.read(&mut buf).unwrap();
-
-        let mut stdout = stdout();
-        for b in &buf[0..bytes_read] {
-            print_byte(&mut stdout, b);
-        }
-
-        if bytes_read == 0 {
-            break;
+        match crash_if_err!(1, file.read(&mut buf)) {
+            0 => break,
+            n => crash_if_err!(1, stdout.write_all(&buf[..n])),
         }
     }
 }
@@ -463,23 +609,23 @@ fn unbounded_tail<T: Read>(reader: &mut BufReader<T>, settings: &Settings) {
     // contains count lines/chars. When reaching the end of file, output the
     // data in the ringbuf.
     match settings.mode {
-        FilterMode::Lines(mut count, _delimiter) => {
-            let mut ringbuf: VecDeque<String> = VecDeque::new();
+        FilterMode::Lines(mut count, delimiter) => {
+            let mut ringbuf: VecDeque<Vec<u8>> = VecDeque::new();
             let mut skip = if settings.beginning {
                 let temp = count;
                 count = ::std::u64::MAX;
-                temp - 1
+                temp.saturating_sub(1)
             } else {
                 0
             };
             loop {
-                let mut datum = String::new();
-                match reader.read_line(&mut datum) {
+                let mut datum = Vec::new();
+                match reader.read_until(delimiter, &mut datum) {
                     Ok(0) => break,
                     Ok(_) => {
                         if skip > 0 {
                             skip -= 1;
-                        } else {
+                        } else if count > 0 {
                             if count <= ringbuf.len() as u64 {
                                 ringbuf.pop_front();
                             }
@@ -491,7 +637,7 @@ fn unbounded_tail<T: Read>(reader: &mut BufReader<T>, settings: &Settings) {
             }
             let mut stdout = stdout();
             for datum in &ringbuf {
-                print_string(&mut stdout, datum);
+                print_bytes(&mut stdout, datum);
             }
         },
         FilterMode::Bytes(mut count) => {
@@ -499,7 +645,7 @@ fn unbounded_tail<T: Read>(reader: &mut BufReader<T>, settings: &Settings) {
             let mut skip = if settings.beginning {
                 let temp = count;
                 count = ::std::u64::MAX;
-                temp - 1
+                temp.saturating_sub(1)
             } else {
                 0
             };
@@ -510,7 +656,7 @@ fn unbounded_tail<T: Read>(reader: &mut BufReader<T>, settings: &Settings) {
                     Ok(_) => {
                         if skip > 0 {
                             skip -= 1;
-                        } else {
+                        } else if count > 0 {
                             if count <= ringbuf.len() as u64 {
                                 ringbuf.pop_front();
                             }
@@ -532,6 +678,27 @@ fn is_seekable<T: Seek>(file: &mut T) -> bool {
     file.seek(SeekFrom::Current(0)).is_ok()
 }
 
+/// Standard input as a file of its own, if it is a regular file, which can
+/// be read from the end.
+#[cfg(unix)]
+fn stdin_file() -> Option<File> {
+    use std::os::unix::io::FromRawFd;
+    let fd = unsafe { libc::dup(0) };
+    if fd < 0 {
+        return None;
+    }
+    let file = unsafe { File::from_raw_fd(fd) };
+    match file.metadata() {
+        Ok(ref metadata) if metadata.is_file() => Some(file),
+        _ => None,
+    }
+}
+
+#[cfg(not(unix))]
+fn stdin_file() -> Option<File> {
+    None
+}
+
 #[inline]
 fn print_byte<T: Write>(stdout: &mut T, ch: &u8) {
     if let Err(err) = stdout.write(&[*ch]) {
@@ -540,10 +707,40 @@ fn print_byte<T: Write>(stdout: &mut T, ch: &u8) {
 }
 
 #[inline]
-fn print_string<T: Write>(_: &mut T, s: &str) {
-    print!("{}", s);
+fn print_bytes<T: Write>(stdout: &mut T, bytes: &[u8]) {
+    if let Err(err) = stdout.write_all(bytes) {
+        crash!(1, "{}", err);
+    }
 }
 
 fn version() {
     println!("{} {}", NAME, VERSION);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::ErrorKind;
+    use uucore::clock::FakeClock;
+    use uucore::filesystem::MemoryFileSystem;
+
+    #[test]
+    fn test_file_named() {
+        let clock = FakeClock::at(1_000_000_000);
+        let fs = MemoryFileSystem::new(&clock);
+        let path = Path::new("log");
+
+        assert_eq!(file_named(&fs, path, None).unwrap_err().kind(), ErrorKind::NotFound);
+        fs.create(path).unwrap();
+        let first = file_named(&fs, path, None).unwrap().unwrap();
+        assert_eq!(file_named(&fs, path, Some(first)).unwrap(), None);
+
+        // rotated away, and then made anew
+        fs.rename(path, "log.1").unwrap();
+        assert_eq!(file_named(&fs, path, Some(first)).unwrap_err().kind(), ErrorKind::NotFound);
+        fs.create(path).unwrap();
+        let second = file_named(&fs, path, Some(first)).unwrap().unwrap();
+        assert!(second != first);
+        assert_eq!(file_named(&fs, path, Some(second)).unwrap(), None);
+    }
+}
@@ -14,8 +14,13 @@ extern crate getopts;
 extern crate uucore;
 
 use std::fs::OpenOptions;
-use std::io::{copy, Error, ErrorKind, Read, Result, sink, stdin, stdout, Write};
+use std::io::{Error, ErrorKind, Read, Result, sink, stdin, stdout, Write};
+#[cfg(unix)]
+use std::os::unix::io::AsRawFd;
 use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use uucore::fs::open_outputs;
+use uucore::pipes;
 
 static NAME: &'static str = "tee";
 static VERSION: &'static str = env!("CARGO_PKG_VERSION");
@@ -33,7 +38,8 @@ struct Options {
     append: bool,
     ignore_interrupts: bool,
     print_and_exit: Option<String>,
-    files: Vec<String>
+    files: Vec<String>,
+    commands: Vec<String>
 }
 
 fn options(args: &[String]) -> Result<Options> {
@@ -41,6 +47,8 @@ fn options(args: &[String]) -> Result<Options> {
 
     opts.optflag("a", "append", "append to the given FILEs, do not overwrite");
     opts.optflag("i", "ignore-interrupts", "ignore interrupt signals");
+    opts.optmulti("", "pipe-to", "also copy to the standard input of the shell command CMD; \
+                                  may be given more than once (uutils extension)", "CMD");
     opts.optflag("h", "help", "display this help and exit");
     opts.optflag("V", "version", "output version information and exit");
 
@@ -48,7 +56,8 @@ fn options(args: &[String]) -> Result<Options> {
         let version = format!("{} {}", NAME, VERSION);
         let arguments = "[OPTION]... [FILE]...";
         let brief = "Copy standard input to each FILE, and also to standard output.";
-        let comment = "If a FILE is -, copy again to standard output.";
+        let comment = "If a FILE is -, copy again to standard output.\n\
+                       tee fails if any CMD given to --pipe-to exits unsuccessfully.";
         let help = format!("{}\n\nUsage:\n  {} {}\n\n{}\n{}",
                            version, NAME, arguments, opts.usage(brief),
                            comment);
@@ -62,7 +71,8 @@ fn options(args: &[String]) -> Result<Options> {
             append: m.opt_present("append"),
             ignore_interrupts: m.opt_present("ignore-interrupts"),
             print_and_exit: to_print,
-            files: names
+            files: names,
+            commands: m.opt_strs("pipe-to")
         })
     }).map_err(|message| warn(format!("{}", message).as_ref()))
 }
@@ -75,47 +85,133 @@ fn exec(options: Options) -> Result<()> {
 }
 
 fn tee(options: Options) -> Result<()> {
-    let writers: Vec<Box<Write>> = options.files.clone().into_iter().map(|file| open(file, options.append)).collect();
-    let output = &mut MultiWriter { writers: writers };
-    let input = &mut NamedReader { inner: Box::new(stdin()) as Box<Read> };
-    if copy(input, output).is_err() || output.flush().is_err() {
+    let mut failed = false;
+    let mut writers: Vec<Box<Write>> = Vec::new();
+    for (file, result) in open(&options.files, options.append) {
+        match result {
+            Ok(inner) => writers.push(Box::new(NamedWriter { inner: inner, path: PathBuf::from(file) })),
+            Err(f) => {
+                warn(format!("{}: {}", file, f).as_ref());
+                failed = true;
+            }
+        }
+    }
+    let mut children = Vec::new();
+    // the standard input and output, and the commands' standard inputs
+    let mut fds = vec![0, 1];
+    for command in options.commands {
+        match spawn(&command) {
+            Ok(mut child) => {
+                let stdin = child.stdin.take().unwrap();
+                #[cfg(unix)]
+                fds.push(stdin.as_raw_fd());
+                writers.push(Box::new(NamedWriter { inner: Box::new(stdin), path: PathBuf::from(&command) }));
+                children.push((command, child));
+            }
+            Err(f) => {
+                warn(format!("{}: {}", command, f).as_ref());
+                failed = true;
+            }
+        }
+    }
+
+    {
+        let output = &mut MultiWriter { writers: writers, failed: false };
+        let input = &mut NamedReader { inner: Box::new(stdin()) as Box<Read> };
+        let mut buf = vec![0; pipes::buffer_size(8 * 1024, &fds)];
+        if copy_through(input, output, &mut buf).is_err() || output.flush().is_err() || output.failed {
+            failed = true;
+        }
+        // dropping the writers closes the pipes, so that the commands see EOF
+    }
+
+    for (command, mut child) in children {
+        match child.wait() {
+            Ok(ref status) if status.success() => {}
+            Ok(status) => {
+                warn(format!("{}: {}", command, status).as_ref());
+                failed = true;
+            }
+            Err(f) => {
+                warn(format!("{}: {}", command, f).as_ref());
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
         Err(Error::new(ErrorKind::Other, ""))
     } else {
         Ok(())
     }
 }
 
-fn open(name: String, append: bool) -> Box<Write> {
-    let is_stdout = name == "-";
-    let path = PathBuf::from(name);
-    let inner: Box<Write> = if is_stdout {
-        Box::new(stdout())
-    } else {
-        let mut options = OpenOptions::new();
-        let mode = if append { options.append(true) } else { options.truncate(true) };
-        match mode.write(true).create(true).open(path.as_path()) {
-            Ok(file) => Box::new(file),
-            Err(_) => Box::new(sink())
-        }
-    };
-    Box::new(NamedWriter { inner: inner, path: path }) as Box<Write>
+/// Copy all of `input` to `output` through `buf`, as `io::copy` does through
+/// a small buffer of its own.
+fn copy_through(input: &mut Read, output: &mut Write, buf: &mut [u8]) -> Result<u64> {
+    let mut copied = 0;
+    loop {
+        let n = match input.read(buf) {
+            Ok(0) => return Ok(copied),
+            Ok(n) => n,
+            Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        output.write_all(&buf[..n])?;
+        copied += n as u64;
+    }
 }
 
+#[cfg(unix)]
+fn spawn(command: &str) -> Result<Child> {
+    Command::new("sh").arg("-c").arg(command).stdin(Stdio::piped()).spawn()
+}
+
+#[cfg(windows)]
+fn spawn(command: &str) -> Result<Child> {
+    Command::new("cmd").arg("/C").arg(command).stdin(Stdio::piped()).spawn()
+}
+
+// Opens all the files at once, so that FIFOs among them can be opened in
+// whatever order their readers turn up in.
+fn open(names: &[String], append: bool) -> Vec<(&String, Result<Box<Write>>)> {
+    let mut options = OpenOptions::new();
+    if append { options.append(true) } else { options.truncate(true) };
+    options.write(true).create(true);
+    let paths: Vec<&String> = names.iter().filter(|name| *name != "-").collect();
+    let mut files = open_outputs(&paths, &options).into_iter();
+    names.iter().map(|name| {
+        let inner: Result<Box<Write>> = if name == "-" {
+            Ok(Box::new(stdout()))
+        } else {
+            files.next().unwrap().map(|file| Box::new(file) as Box<Write>)
+        };
+        (name, inner)
+    }).collect()
+}
+
+/// Writes to all of `writers`.  One that fails is reported by its
+/// NamedWriter and recorded in `failed`, without holding up the others.
 struct MultiWriter {
-    writers: Vec<Box<Write>>
+    writers: Vec<Box<Write>>,
+    failed: bool
 }
 
 impl Write for MultiWriter {
     fn write(&mut self, buf: &[u8]) -> Result<usize> {
         for writer in &mut self.writers {
-            try!(writer.write_all(buf));
+            if writer.write_all(buf).is_err() {
+                self.failed = true;
+            }
         }
         Ok(buf.len())
     }
 
     fn flush(&mut self) -> Result<()> {
         for writer in &mut self.writers {
-            try!(writer.flush());
+            if writer.flush().is_err() {
+                self.failed = true;
+            }
         }
         Ok(())
     }
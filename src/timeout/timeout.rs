@@ -19,14 +19,23 @@ extern crate uucore;
 use std::io::ErrorKind;
 use std::process::{Command, Stdio};
 use std::time::Duration;
-use uucore::process::ChildExt;
+use uucore::process::{self, ChildExt, FdPolicy};
 
 static NAME: &'static str = "timeout";
 static VERSION: &'static str = env!("CARGO_PKG_VERSION");
 
 static ERR_EXIT_STATUS: i32 = 125;
 
+struct Options {
+    signal: usize,
+    kill_after: Duration,
+    foreground: bool,
+    preserve_status: bool,
+    fds: FdPolicy,
+}
+
 pub fn uumain(args: Vec<String>) -> i32 {
+    let inherited_fds = FdPolicy::inherited();
     let program = args[0].clone();
 
     let mut opts = getopts::Options::new();
@@ -56,8 +65,6 @@ Usage:
         show_error!("for help, try '{0} --help'", program);
         return ERR_EXIT_STATUS;
     } else {
-        let status = matches.opt_present("preserve-status");
-        let foreground = matches.opt_present("foreground");
         let kill_after = match matches.opt_str("kill-after") {
             Some(tstr) => match uucore::parse_time::from_str(&tstr) {
                 Ok(time) => time,
@@ -85,16 +92,27 @@ Usage:
                 return ERR_EXIT_STATUS;
             }
         };
-        return timeout(&matches.free[1], &matches.free[2..], duration, signal, kill_after, foreground, status);
+        let options = Options {
+            signal: signal,
+            kill_after: kill_after,
+            foreground: matches.opt_present("foreground"),
+            preserve_status: matches.opt_present("preserve-status"),
+            fds: inherited_fds,
+        };
+        return timeout(&matches.free[1], &matches.free[2..], duration, &options);
     }
 
     0
 }
 
-fn timeout(cmdname: &str, args: &[String], duration: Duration, signal: usize, kill_after: Duration, foreground: bool, preserve_status: bool) -> i32 {
-    if !foreground {
+fn timeout(cmdname: &str, args: &[String], duration: Duration, options: &Options) -> i32 {
+    if !options.foreground {
         unsafe { libc::setpgid(0, 0) };
     }
+    if let Err(err) = process::prepare_fds(&options.fds) {
+        show_error!("failed to prepare file descriptors: {}", err);
+        return ERR_EXIT_STATUS;
+    }
     let mut process = match Command::new(cmdname).args(args)
                                                  .stdin(Stdio::inherit())
                                                  .stdout(Stdio::inherit())
@@ -115,17 +133,17 @@ fn timeout(cmdname: &str, args: &[String], duration: Duration, signal: usize, ki
     match process.wait_or_timeout(duration) {
         Ok(Some(status)) => status.code().unwrap_or_else(|| status.signal().unwrap()),
         Ok(None) => {
-            return_if_err!(ERR_EXIT_STATUS, process.send_signal(signal));
-            match process.wait_or_timeout(kill_after) {
+            return_if_err!(ERR_EXIT_STATUS, process.send_signal(options.signal));
+            match process.wait_or_timeout(options.kill_after) {
                 Ok(Some(status)) => {
-                    if preserve_status {
+                    if options.preserve_status {
                         status.code().unwrap_or_else(|| status.signal().unwrap())
                     } else {
                         124
                     }
                 },
                 Ok(None) => {
-                    if kill_after == Duration::new(0, 0) {
+                    if options.kill_after == Duration::new(0, 0) {
                         // XXX: this may not be right
                         return 124;
                     }
@@ -137,7 +155,7 @@ fn timeout(cmdname: &str, args: &[String], duration: Duration, signal: usize, ki
             }
         },
         Err(_) => {
-            return_if_err!(ERR_EXIT_STATUS, process.send_signal(signal));
+            return_if_err!(ERR_EXIT_STATUS, process.send_signal(options.signal));
             ERR_EXIT_STATUS
         },
     }
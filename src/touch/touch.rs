@@ -16,58 +16,16 @@ pub extern crate filetime;
 #[macro_use]
 extern crate uucore;
 
-use filetime::*;
-use std::fs::{self, File};
-use std::io::{self, Error};
 use std::path::Path;
+use std::time::SystemTime;
+use uucore::clock::{from_timespec, to_timespec, Clock, SystemClock};
+use uucore::filesystem::{FileSystem, RealFileSystem};
+use uucore::tz::TimeZone;
 
 static NAME: &'static str = "touch";
 static VERSION: &'static str = env!("CARGO_PKG_VERSION");
 
-// Since touch's date/timestamp parsing doesn't account for timezone, the
-// returned value from time::strptime() is UTC. We get system's timezone to
-// localize the time.
-macro_rules! to_local(
-    ($exp:expr) => ({
-        let mut tm = $exp;
-        tm.tm_utcoff = time::now().tm_utcoff;
-        tm
-    })
-);
-
-macro_rules! local_tm_to_filetime(
-    ($exp:expr) => ({
-        let ts = $exp.to_timespec();
-        FileTime::from_seconds_since_1970(ts.sec as u64, ts.nsec as u32)
-    })
-);
-
-macro_rules! to_timeval {
-    ($ft:expr) => (
-        timeval {
-            tv_sec: $ft.seconds() as time_t,
-            tv_usec: ($ft.nanoseconds() / 1000) as suseconds_t,
-        }
-    )
-}
-
-#[cfg(unix)]
-fn set_symlink_times(p: &str, atime: FileTime, mtime: FileTime) -> io::Result<()> {
-    use std::ffi::CString;
-    use uucore::libc::{lutimes, timeval, time_t, suseconds_t};
-
-    let times = [to_timeval!(atime), to_timeval!(mtime)];
-    let p = try!(CString::new(p));
-    return unsafe {
-        if lutimes(p.as_ptr() as *const _, times.as_ptr()) == 0 {
-            Ok(())
-        } else {
-            Err(io::Error::last_os_error())
-        }
-    };
-}
-
-pub fn uumain(args: Vec<String>) -> i32 {
+fn options() -> getopts::Options {
     let mut opts = getopts::Options::new();
 
     opts.optflag("a", "", "change only the access time");
@@ -98,6 +56,12 @@ pub fn uumain(args: Vec<String>) -> i32 {
     opts.optflag("h", "help", "display this help and exit");
     opts.optflag("V", "version", "output version information and exit");
 
+    opts
+}
+
+pub fn uumain(args: Vec<String>) -> i32 {
+    let opts = options();
+
     let matches = match opts.parse(&args[1..]) {
         Ok(m) => m,
         Err(e) => panic!("Invalid options\n{}", e),
@@ -127,31 +91,36 @@ pub fn uumain(args: Vec<String>) -> i32 {
         panic!("Invalid options: cannot specify reference time from more than one source");
     }
 
+    touch(&matches, &RealFileSystem, &SystemClock)
+}
+
+fn touch<F: FileSystem, C: Clock>(matches: &getopts::Matches, fs: &F, clock: &C) -> i32 {
     let (mut atime, mut mtime) = if matches.opt_present("reference") {
-        stat(&matches.opt_str("reference").unwrap()[..],
+        stat(fs,
+             &matches.opt_str("reference").unwrap()[..],
              !matches.opt_present("no-dereference"))
     } else if matches.opts_present(&["date".to_owned(), "t".to_owned()]) {
         let timestamp = if matches.opt_present("date") {
             parse_date(matches.opt_str("date").unwrap().as_ref())
         } else {
-            parse_timestamp(matches.opt_str("t").unwrap().as_ref())
+            parse_timestamp(matches.opt_str("t").unwrap().as_ref(), clock)
         };
         (timestamp, timestamp)
     } else {
-        let now = local_tm_to_filetime!(time::now());
+        let now = clock.now();
         (now, now)
     };
 
     for filename in &matches.free {
         let path = &filename[..];
 
-        if !Path::new(path).exists() {
+        if fs.metadata(Path::new(path), true).is_err() {
             // no-dereference included here for compatibility
             if matches.opts_present(&["no-create".to_owned(), "no-dereference".to_owned()]) {
                 continue;
             }
 
-            if let Err(e) = File::create(path) {
+            if let Err(e) = fs.create(Path::new(path)) {
                 show_warning!("cannot touch '{}': {}", path, e);
                 continue;
             };
@@ -165,7 +134,7 @@ pub fn uumain(args: Vec<String>) -> i32 {
         // If changing "only" atime or mtime, grab the existing value of the other.
         // Note that "-a" and "-m" may be passed together; this is not an xor.
         if matches.opts_present(&["a".to_owned(), "m".to_owned(), "time".to_owned()]) {
-            let st = stat(path, !matches.opt_present("no-dereference"));
+            let st = stat(fs, path, !matches.opt_present("no-dereference"));
             let time = matches.opt_strs("time");
 
             if !(matches.opt_present("a") || time.contains(&"access".to_owned()) ||
@@ -179,46 +148,47 @@ pub fn uumain(args: Vec<String>) -> i32 {
             }
         }
 
-        if matches.opt_present("h") {
-            if let Err(e) = set_symlink_times(path, atime, mtime) {
-                show_warning!("cannot touch '{}': {}", path, e);
-            }
-        } else {
-            if let Err(e) = filetime::set_file_times(path, atime, mtime) {
-                show_warning!("cannot touch '{}': {}", path, e);
-            }
+        if let Err(e) = fs.set_times(Path::new(path), atime, mtime, !matches.opt_present("h")) {
+            show_warning!("cannot touch '{}': {}", path, e);
         }
     }
 
     0
 }
 
-fn stat(path: &str, follow: bool) -> (FileTime, FileTime) {
-    let metadata = if follow {
-        fs::symlink_metadata(path)
-    } else {
-        fs::metadata(path)
-    };
+fn stat<F: FileSystem>(fs: &F, path: &str, follow: bool) -> (SystemTime, SystemTime) {
+    match fs.metadata(Path::new(path), follow) {
+        Ok(m) => (m.accessed, m.modified),
+        Err(e) => crash!(1, "failed to get attributes of '{}': {}", path, e),
+    }
+}
 
-    match metadata {
-        Ok(m) => (FileTime::from_last_access_time(&m), FileTime::from_last_modification_time(&m)),
-        Err(_) => crash!(1, "failed to get attributes of '{}': {}", path, Error::last_os_error()),
+// time::strptime() leaves the time it parses in UTC, so it is taken instead
+// as local time in the zone TZ names, at whatever offset that zone keeps on
+// that day.  `input` is what it was parsed from.
+fn tm_to_time(tm: time::Tm, input: &str) -> SystemTime {
+    let ts = tm.to_timespec();
+    match TimeZone::local().to_utc(ts.sec) {
+        Some(sec) => from_timespec(sec, ts.nsec as u32),
+        // a time the clocks skip over
+        None => crash!(1, "invalid date format '{}'", input),
     }
 }
 
-fn parse_date(str: &str) -> FileTime {
+fn parse_date(str: &str) -> SystemTime {
     // This isn't actually compatible with GNU touch, but there doesn't seem to
     // be any simple specification for what format this parameter allows and I'm
     // not about to implement GNU parse_datetime.
     // http://git.savannah.gnu.org/gitweb/?p=gnulib.git;a=blob_plain;f=lib/parse-datetime.y
     match time::strptime(str, "%c") {
-        Ok(tm) => local_tm_to_filetime!(to_local!(tm)),
+        Ok(tm) => tm_to_time(tm, str),
         Err(e) => panic!("Unable to parse date\n{}", e),
     }
 }
 
-fn parse_timestamp(s: &str) -> FileTime {
-    let now = time::now();
+fn parse_timestamp<C: Clock>(s: &str, clock: &C) -> SystemTime {
+    let (sec, nsec) = to_timespec(clock.now());
+    let now = time::at(time::Timespec::new(sec, nsec as i32));
     let (format, ts) = match s.chars().count() {
         15 => ("%Y%m%d%H%M.%S", s.to_owned()),
         12 => ("%Y%m%d%H%M", s.to_owned()),
@@ -230,7 +200,69 @@ fn parse_timestamp(s: &str) -> FileTime {
     };
 
     match time::strptime(&ts, format) {
-        Ok(tm) => local_tm_to_filetime!(to_local!(tm)),
+        Ok(tm) => tm_to_time(tm, s),
         Err(e) => panic!("Unable to parse timestamp\n{}", e),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use uucore::clock::FakeClock;
+    use uucore::filesystem::MemoryFileSystem;
+
+    fn run<F: FileSystem, C: Clock>(args: &[&str], fs: &F, clock: &C) {
+        let matches = options().parse(args).unwrap();
+        assert_eq!(touch(&matches, fs, clock), 0);
+    }
+
+    #[test]
+    fn test_create() {
+        let clock = FakeClock::at(1_000_000_000);
+        let fs = MemoryFileSystem::new(&clock);
+
+        run(&["-c", "a"], &fs, &clock);
+        assert!(fs.get("a").is_none());
+
+        run(&["a"], &fs, &clock);
+        assert_eq!(fs.get("a").unwrap().modified, clock.now());
+    }
+
+    #[test]
+    fn test_only_one_time() {
+        let clock = FakeClock::at(1_000_000_000);
+        let fs = MemoryFileSystem::new(&clock);
+        let then = clock.now();
+        fs.insert("a", then);
+        clock.advance(Duration::from_secs(60));
+
+        run(&["-m", "a"], &fs, &clock);
+        let file = fs.get("a").unwrap();
+        assert_eq!((file.accessed, file.modified), (then, clock.now()));
+    }
+
+    #[test]
+    fn test_reference() {
+        let clock = FakeClock::at(1_000_000_000);
+        let fs = MemoryFileSystem::new(&clock);
+        fs.insert("ref", from_timespec(86400, 0));
+        fs.insert("a", clock.now());
+
+        run(&["-r", "ref", "a"], &fs, &clock);
+        assert_eq!(fs.get("a").unwrap().modified, from_timespec(86400, 0));
+    }
+
+    #[test]
+    fn test_timestamp_in_this_year() {
+        // the middle of 2001, so that the local time is in it too
+        let clock = FakeClock::at(994_000_000);
+        let fs = MemoryFileSystem::new(&clock);
+        fs.insert("a", clock.now());
+
+        run(&["-t", "01020304", "a"], &fs, &clock);
+        let (sec, _) = to_timespec(fs.get("a").unwrap().modified);
+        let tm = time::at(time::Timespec::new(sec, 0));
+        assert_eq!((tm.tm_year + 1900, tm.tm_mon + 1, tm.tm_mday), (2001, 1, 2));
+    }
+}
@@ -17,9 +17,10 @@ extern crate uucore;
 
 use getopts::{Matches, Options};
 use std::fs::File;
-use std::io::{BufRead, BufReader, BufWriter, Read, stdin, stdout, Write};
+use std::io::{BufReader, BufWriter, Read, stdin, stdout, Write};
 use std::path::Path;
 use std::str::FromStr;
+use uucore::lines::{self, LineEnding};
 
 static NAME: &'static str = "uniq";
 static VERSION: &'static str = env!("CARGO_PKG_VERSION");
@@ -34,7 +35,7 @@ struct Uniq {
     slice_start: Option<usize>,
     slice_stop: Option<usize>,
     ignore_case: bool,
-    zero_terminated: bool,
+    line_ending: LineEnding,
 }
 
 impl Uniq {
@@ -42,10 +43,9 @@ impl Uniq {
         let mut lines: Vec<String> = vec!();
         let mut first_line_printed = false;
         let delimiters = &self.delimiters[..];
-        let line_terminator = self.get_line_terminator();
 
-        for io_line in reader.split(line_terminator) {
-            let line = String::from_utf8(crash_if_err!(1, io_line)).unwrap();
+        for io_line in lines::lines(reader, self.line_ending) {
+            let line = crash_if_err!(1, io_line);
             if !lines.is_empty() && self.cmp_key(&lines[0]) != self.cmp_key(&line) {
                 let print_delimiter = delimiters == "prepend" || (delimiters == "separate" && first_line_printed);
                 first_line_printed |= self.print_lines(writer, &lines, print_delimiter);
@@ -59,35 +59,15 @@ impl Uniq {
         }
     }
 
+    /// What is left of the line after the fields skipped, each blanks and
+    /// then other characters, even if that is only blanks.
     fn skip_fields(&self, line: &str) -> String {
-        if let Some(skip_fields) = self.skip_fields {
-            if line.split_whitespace().count() > skip_fields {
-                let mut field = 0;
-                let mut i = 0;
-                while field < skip_fields && i < line.len() {
-                    while i < line.len() && line.chars().nth(i).unwrap().is_whitespace() {
-                        i = i + 1;
-                    }
-                    while i < line.len() && !line.chars().nth(i).unwrap().is_whitespace() {
-                        i = i + 1;
-                    }
-                    field = field + 1;
-                }
-                line[i..].to_owned()
-            } else {
-                "".to_owned()
-            }
-        } else {
-            line[..].to_owned()
-        }
-    }
-
-    fn get_line_terminator(&self) -> u8 {
-        if self.zero_terminated {
-            0
-        } else {
-            '\n' as u8
+        let is_blank = |c| c == ' ' || c == '\t';
+        let mut rest = line;
+        for _ in 0..self.skip_fields.unwrap_or(0) {
+            rest = rest.trim_left_matches(&is_blank).trim_left_matches(|c| !is_blank(c));
         }
+        rest.to_owned()
     }
 
     fn cmp_key(&self, line: &str) -> String {
@@ -126,10 +106,10 @@ impl Uniq {
     }
 
     fn print_line<W: Write>(&self, writer: &mut BufWriter<W>, line: &str, count: usize, print_delimiter: bool) {
-        let line_terminator = self.get_line_terminator();
+        let line_terminator = self.line_ending.as_str().as_bytes();
 
         if print_delimiter {
-            crash_if_err!(1, writer.write_all(&[line_terminator]));
+            crash_if_err!(1, writer.write_all(line_terminator));
         }
 
         crash_if_err!(1, if self.show_counts {
@@ -137,7 +117,7 @@ impl Uniq {
         } else {
             writer.write_all(line.as_bytes())
         });
-        crash_if_err!(1, writer.write_all(&[line_terminator]));
+        crash_if_err!(1, writer.write_all(line_terminator));
     }
 }
 
@@ -166,6 +146,7 @@ pub fn uumain(args: Vec<String>) -> i32 {
     opts.optflag("i", "ignore-case", "ignore differences in case when comparing");
     opts.optflag("u", "unique", "only print unique lines");
     opts.optflag("z", "zero-terminated", "end lines with 0 byte, not newline");
+    opts.optflag("", "crlf", "lines end in carriage return and newline, as in Windows text files");
     opts.optflag("h", "help", "display this help and exit");
     opts.optflag("V", "version", "output version information and exit");
 
@@ -215,7 +196,11 @@ pub fn uumain(args: Vec<String>) -> i32 {
             slice_start: opt_parsed("skip-chars", &matches),
             slice_stop: opt_parsed("check-chars", &matches),
             ignore_case: matches.opt_present("ignore-case"),
-            zero_terminated: matches.opt_present("zero-terminated"),
+            line_ending: if matches.opt_present("zero-terminated") {
+                LineEnding::Nul
+            } else {
+                LineEnding::from_crlf(matches.opt_present("crlf"))
+            },
         };
         uniq.print_uniq(&mut open_input_file(in_file_name),
                         &mut open_output_file(out_file_name));
@@ -0,0 +1,192 @@
+// This file is part of the uutils coreutils package.
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+//
+
+//! Backups of files about to be overwritten, as selected by
+//! `--backup[=CONTROL]`, `-b` and `--suffix=SUFFIX`, or failing those by
+//! the `VERSION_CONTROL` and `SIMPLE_BACKUP_SUFFIX` environment variables.
+//!
+//! A simple backup of `FILE` is `FILE` followed by the suffix, `~` unless
+//! asked otherwise, and a numbered one is `FILE.~N~`, one more than the
+//! highest such backup already there.
+
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BackupMode {
+    NoBackup,
+    SimpleBackup,
+    NumberedBackup,
+    /// numbered if there already are numbered backups, else simple
+    ExistingBackup,
+}
+
+static CONTROLS: &'static [(&'static str, BackupMode)] = &[("none", BackupMode::NoBackup),
+                                                            ("off", BackupMode::NoBackup),
+                                                            ("simple", BackupMode::SimpleBackup),
+                                                            ("never", BackupMode::SimpleBackup),
+                                                            ("existing", BackupMode::ExistingBackup),
+                                                            ("nil", BackupMode::ExistingBackup),
+                                                            ("numbered", BackupMode::NumberedBackup),
+                                                            ("t", BackupMode::NumberedBackup)];
+
+impl BackupMode {
+    /// Parse the CONTROL of `--backup=CONTROL`, which may be cut short as
+    /// long as what is left isn't the start of two different ones.  The
+    /// error is the whole diagnostic.
+    pub fn parse(control: &str) -> Result<BackupMode, String> {
+        parse(control, "backup type")
+    }
+
+    /// What `-b`, or `--backup` or `--suffix` without a CONTROL, asks for:
+    /// the one in `VERSION_CONTROL`, or `existing` if it isn't set.
+    pub fn from_env() -> Result<BackupMode, String> {
+        match env::var("VERSION_CONTROL") {
+            Ok(ref control) if !control.is_empty() => parse(control, "$VERSION_CONTROL"),
+            _ => Ok(BackupMode::ExistingBackup),
+        }
+    }
+}
+
+fn parse(control: &str, context: &str) -> Result<BackupMode, String> {
+    if let Some(&(_, mode)) = CONTROLS.iter().find(|&&(name, _)| name == control) {
+        return Ok(mode);
+    }
+    let mut modes = CONTROLS.iter().filter(|&&(name, _)| name.starts_with(control)).map(|&(_, mode)| mode);
+    match modes.next() {
+        Some(mode) if modes.all(|other| other == mode) => Ok(mode),
+        found => {
+            Err(format!("{} argument '{}' for '{}'\n\
+                         Valid arguments are:\n  \
+                         - 'none', 'off'\n  \
+                         - 'simple', 'never'\n  \
+                         - 'existing', 'nil'\n  \
+                         - 'numbered', 't'",
+                        if found.is_some() { "ambiguous" } else { "invalid" },
+                        control,
+                        context))
+        }
+    }
+}
+
+/// The suffix for simple backups: the one `given` with `--suffix`, else
+/// the one in `SIMPLE_BACKUP_SUFFIX`, else `~`.  One that is empty or would
+/// put the backup in another directory is taken as `~`.
+pub fn suffix(given: Option<&str>) -> String {
+    let suffix = match given {
+        Some(suffix) => suffix.to_owned(),
+        None => env::var("SIMPLE_BACKUP_SUFFIX").unwrap_or_default(),
+    };
+    if suffix.is_empty() || suffix.contains('/') {
+        "~".to_owned()
+    } else {
+        suffix
+    }
+}
+
+/// Where to move `path` to before overwriting it, or `None` for no backup.
+pub fn backup_path(path: &Path, mode: BackupMode, suffix: &str) -> Option<PathBuf> {
+    match mode {
+        BackupMode::NoBackup => None,
+        BackupMode::SimpleBackup => Some(with_suffix(path, suffix)),
+        BackupMode::NumberedBackup => Some(with_suffix(path, &format!(".~{}~", highest_backup(path) + 1))),
+        BackupMode::ExistingBackup => {
+            match highest_backup(path) {
+                0 => Some(with_suffix(path, suffix)),
+                n => Some(with_suffix(path, &format!(".~{}~", n + 1))),
+            }
+        }
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path);
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+// The highest N of the `path.~N~` there are, or 0 if there are none.
+fn highest_backup(path: &Path) -> u64 {
+    let name = match path.file_name().and_then(|name| name.to_str()) {
+        Some(name) => format!("{}.~", name),
+        None => return 0,
+    };
+    let dir = match path.parent() {
+        Some(dir) if dir != Path::new("") => dir,
+        _ => Path::new("."),
+    };
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return 0,
+    };
+    entries.filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter_map(|entry| {
+            if entry.starts_with(&name) && entry.ends_with('~') && entry.len() > name.len() + 1 {
+                entry[name.len()..entry.len() - 1].parse::<u64>().ok()
+            } else {
+                None
+            }
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::process;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(BackupMode::parse("none"), Ok(BackupMode::NoBackup));
+        assert_eq!(BackupMode::parse("never"), Ok(BackupMode::SimpleBackup));
+        assert_eq!(BackupMode::parse("t"), Ok(BackupMode::NumberedBackup));
+        assert_eq!(BackupMode::parse("ex"), Ok(BackupMode::ExistingBackup));
+        assert_eq!(BackupMode::parse("nu"), Ok(BackupMode::NumberedBackup));
+        assert_eq!(BackupMode::parse("o"), Ok(BackupMode::NoBackup));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(BackupMode::parse("n").unwrap_err().starts_with("ambiguous argument 'n' for 'backup type'\n"));
+        assert!(BackupMode::parse("").unwrap_err().starts_with("ambiguous argument '' for 'backup type'\n"));
+        let err = BackupMode::parse("nope").unwrap_err();
+        assert!(err.starts_with("invalid argument 'nope' for 'backup type'\nValid arguments are:\n"));
+        assert!(err.ends_with("\n  - 'numbered', 't'"));
+    }
+
+    #[test]
+    fn test_suffix() {
+        assert_eq!(suffix(Some(".bak")), ".bak");
+        assert_eq!(suffix(Some("")), "~");
+        assert_eq!(suffix(Some("x/y")), "~");
+    }
+
+    #[test]
+    fn test_backup_path() {
+        let dir = env::temp_dir().join(format!("uucore-backup-{}", process::id()));
+        fs::create_dir(&dir).unwrap();
+        let file = dir.join("f");
+
+        assert_eq!(backup_path(&file, BackupMode::NoBackup, "~"), None);
+        assert_eq!(backup_path(&file, BackupMode::SimpleBackup, ".bak"), Some(dir.join("f.bak")));
+        assert_eq!(backup_path(&file, BackupMode::ExistingBackup, "~"), Some(dir.join("f~")));
+        assert_eq!(backup_path(&file, BackupMode::NumberedBackup, "~"), Some(dir.join("f.~1~")));
+
+        // numbering goes on from the highest, whatever is missing below it
+        for name in &["f.~2~", "f.~10~", "f.~x~", "g.~20~"] {
+            File::create(dir.join(name)).unwrap();
+        }
+        assert_eq!(backup_path(&file, BackupMode::NumberedBackup, "~"), Some(dir.join("f.~11~")));
+        assert_eq!(backup_path(&file, BackupMode::ExistingBackup, "~"), Some(dir.join("f.~11~")));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,105 @@
+// This file is part of the uutils coreutils package.
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+//
+
+//! Where the current time comes from.
+//!
+//! Utilities that depend on the time ask a [`Clock`](trait.Clock.html)
+//! rather than the system, so that their tests can give them one that
+//! stands still with [`FakeClock`](struct.FakeClock.html).
+
+use std::cell::Cell;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub trait Clock {
+    fn now(&self) -> SystemTime;
+}
+
+/// The system's own clock.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock that shows the time it was last set to, for tests.
+pub struct FakeClock {
+    now: Cell<SystemTime>,
+}
+
+impl FakeClock {
+    pub fn new(now: SystemTime) -> FakeClock {
+        FakeClock { now: Cell::new(now) }
+    }
+
+    /// A clock showing `sec` seconds since the epoch.
+    pub fn at(sec: i64) -> FakeClock {
+        FakeClock::new(from_timespec(sec, 0))
+    }
+
+    pub fn set(&self, now: SystemTime) {
+        self.now.set(now);
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.now.set(self.now.get() + by);
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> SystemTime {
+        self.now.get()
+    }
+}
+
+/// `time` as seconds and nanoseconds since the epoch, the seconds being
+/// negative before it and the nanoseconds always counting forwards, as in
+/// a `struct timespec`.
+pub fn to_timespec(time: SystemTime) -> (i64, u32) {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => (since.as_secs() as i64, since.subsec_nanos()),
+        Err(e) => {
+            let before = e.duration();
+            if before.subsec_nanos() == 0 {
+                (-(before.as_secs() as i64), 0)
+            } else {
+                (-(before.as_secs() as i64) - 1, 1_000_000_000 - before.subsec_nanos())
+            }
+        }
+    }
+}
+
+/// The time `sec` seconds and `nsec` nanoseconds since the epoch, taken
+/// as [`to_timespec`](fn.to_timespec.html) gives them.
+pub fn from_timespec(sec: i64, nsec: u32) -> SystemTime {
+    if sec >= 0 {
+        UNIX_EPOCH + Duration::new(sec as u64, nsec)
+    } else {
+        UNIX_EPOCH - Duration::from_secs(sec.wrapping_neg() as u64) + Duration::new(0, nsec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timespec() {
+        for &(sec, nsec) in &[(0, 0), (1_000_000_000, 5), (-1, 0), (-1, 999_999_999), (-86401, 1)] {
+            assert_eq!(to_timespec(from_timespec(sec, nsec)), (sec, nsec));
+        }
+        assert!(from_timespec(-1, 500_000_000) < UNIX_EPOCH);
+    }
+
+    #[test]
+    fn test_fake_clock() {
+        let clock = FakeClock::at(1_000_000_000);
+        assert_eq!(to_timespec(clock.now()), (1_000_000_000, 0));
+        clock.advance(Duration::new(1, 5));
+        assert_eq!(to_timespec(clock.now()), (1_000_000_001, 5));
+    }
+}
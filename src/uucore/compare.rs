@@ -0,0 +1,184 @@
+// This file is part of the uutils coreutils package.
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+//
+
+//! Byte by byte comparison of two inputs, as done by `cmp`.
+//!
+//! Both inputs are read in large chunks, however little each read returns,
+//! and whole chunks are compared at once, falling back to single bytes only
+//! for a chunk that turns out to differ.
+
+use std::cmp;
+use std::fs::{self, File};
+use std::io::{self, ErrorKind, Read};
+use std::path::Path;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Where two inputs stop being the same.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Difference {
+    /// The byte at `offset`, which is in line `line` (both counting from
+    /// 1), is `a` in the first input and `b` in the second.
+    Byte { offset: u64, line: u64, a: u8, b: u8 },
+    /// The first input (or if not `first`, the second) ended after `offset`
+    /// bytes that were the same in both.  The next byte would have been in
+    /// line `line`, and `line_start` tells whether it would have started it.
+    Eof { first: bool, offset: u64, line: u64, line_start: bool },
+}
+
+/// Compare `a` with `b`, stopping at the first difference.  Returns `None`
+/// when they are the same.
+pub fn compare<A: Read, B: Read>(a: A, b: B) -> io::Result<Option<Difference>> {
+    compare_bytes(a, b, None::<fn(u64, u8, u8)>)
+}
+
+/// Compare `a` with `b` all the way, calling `f` with the offset and both
+/// values of every byte that differs.  The result is only `Some` when one
+/// input is shorter than the other.
+pub fn compare_all<A: Read, B: Read, F: FnMut(u64, u8, u8)>(a: A, b: B, f: F) -> io::Result<Option<Difference>> {
+    compare_bytes(a, b, Some(f))
+}
+
+/// Whether the files at `a` and `b` have the same contents.  Regular files
+/// of different sizes are not read at all.
+pub fn same_contents<P: AsRef<Path>, Q: AsRef<Path>>(a: P, b: Q) -> io::Result<bool> {
+    let (meta_a, meta_b) = (try!(fs::metadata(&a)), try!(fs::metadata(&b)));
+    if meta_a.is_file() && meta_b.is_file() && meta_a.len() != meta_b.len() {
+        return Ok(false);
+    }
+    let result = try!(compare(try!(File::open(a)), try!(File::open(b))));
+    Ok(result.is_none())
+}
+
+fn compare_bytes<A: Read, B: Read, F: FnMut(u64, u8, u8)>(mut a: A, mut b: B, mut all: Option<F>)
+                                                          -> io::Result<Option<Difference>> {
+    let mut buf_a = vec![0; CHUNK_SIZE];
+    let mut buf_b = vec![0; CHUNK_SIZE];
+    let mut offset = 0;
+    let mut line = 1;
+    let mut line_start = true;
+
+    loop {
+        let len_a = try!(read_full(&mut a, &mut buf_a));
+        let len_b = try!(read_full(&mut b, &mut buf_b));
+        let len = cmp::min(len_a, len_b);
+        let (chunk_a, chunk_b) = (&buf_a[..len], &buf_b[..len]);
+
+        if chunk_a != chunk_b {
+            match all {
+                Some(ref mut f) => {
+                    for (i, (&x, &y)) in chunk_a.iter().zip(chunk_b).enumerate() {
+                        if x != y {
+                            f(offset + i as u64 + 1, x, y);
+                        }
+                    }
+                }
+                None => {
+                    let i = chunk_a.iter().zip(chunk_b).position(|(x, y)| x != y).unwrap();
+                    return Ok(Some(Difference::Byte {
+                        offset: offset + i as u64 + 1,
+                        line: line + count_lines(&chunk_a[..i]),
+                        a: chunk_a[i],
+                        b: chunk_b[i],
+                    }));
+                }
+            }
+        }
+
+        offset += len as u64;
+        line += count_lines(chunk_a);
+        if len > 0 {
+            line_start = chunk_a[len - 1] == b'\n';
+        }
+
+        if len_a != len_b {
+            return Ok(Some(Difference::Eof {
+                first: len_a < len_b,
+                offset: offset,
+                line: line,
+                line_start: line_start,
+            }));
+        }
+        if len == 0 {
+            return Ok(None);
+        }
+    }
+}
+
+fn count_lines(bytes: &[u8]) -> u64 {
+    bytes.iter().filter(|&&c| c == b'\n').count() as u64
+}
+
+// Fill `buf` unless the input ends first, so that both inputs are always
+// compared in the same pieces.
+fn read_full<R: Read>(input: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match input.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use faults::Faulty;
+
+    #[test]
+    fn test_same() {
+        assert_eq!(compare(&b""[..], &b""[..]).unwrap(), None);
+        assert_eq!(compare(&b"abc\ndef"[..], Faulty::new(&b"abc\ndef"[..]).short(2)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_byte() {
+        assert_eq!(compare(&b"a\nbX\nc"[..], Faulty::new(&b"a\nbY\nd"[..]).short(1).interrupt(3)).unwrap(),
+                   Some(Difference::Byte { offset: 4, line: 2, a: b'X', b: b'Y' }));
+
+        let mut long = vec![b'\n'; CHUNK_SIZE + 10];
+        let same = long.clone();
+        long[CHUNK_SIZE + 5] = b'x';
+        assert_eq!(compare(&same[..], &long[..]).unwrap(),
+                   Some(Difference::Byte {
+                       offset: CHUNK_SIZE as u64 + 6,
+                       line: CHUNK_SIZE as u64 + 6,
+                       a: b'\n',
+                       b: b'x',
+                   }));
+    }
+
+    #[test]
+    fn test_error() {
+        let failing = Faulty::new(&b"abcdef"[..]).short(2).fail_at(3, ErrorKind::Other);
+        assert_eq!(compare(&b"abcdef"[..], failing).unwrap_err().kind(), ErrorKind::Other);
+    }
+
+    #[test]
+    fn test_eof() {
+        assert_eq!(compare(&b"abc"[..], &b"abcd"[..]).unwrap(),
+                   Some(Difference::Eof { first: true, offset: 3, line: 1, line_start: false }));
+        assert_eq!(compare(&b"a\nb"[..], &b"a\n"[..]).unwrap(),
+                   Some(Difference::Eof { first: false, offset: 2, line: 2, line_start: true }));
+        assert_eq!(compare(&b""[..], &b"a"[..]).unwrap(),
+                   Some(Difference::Eof { first: true, offset: 0, line: 1, line_start: true }));
+    }
+
+    #[test]
+    fn test_all() {
+        let mut diffs = vec![];
+        let result = compare_all(&b"a\nbX\nc"[..], &b"a\nbY\ndef"[..], |offset, a, b| diffs.push((offset, a, b)));
+        assert_eq!(diffs, vec![(4, b'X', b'Y'), (6, b'c', b'd')]);
+        match result.unwrap() {
+            Some(Difference::Eof { first: true, offset: 6, .. }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,900 @@
+// This file is part of the uutils coreutils package.
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+//
+
+//! Copy file contents using the cheapest mechanism the system offers.
+//!
+//! The available mechanisms are tried in a fixed order, falling back to the
+//! next one whenever a layer turns out to be unsupported for the pair of
+//! files at hand:
+//!
+//! 1. `FICLONE`: share the source extents (copy-on-write filesystems only)
+//! 2. `copy_file_range(2)`: in-kernel copy, possibly offloaded to the server
+//! 3. `sendfile(2)`: in-kernel copy through the page cache
+//! 4. `read(2)`/`write(2)`: plain userspace copy, always available
+//!
+//! Each layer can be disabled, which is how `cp --reflink` selects between
+//! mandatory, opportunistic and no cloning, and how the layers are tested
+//! in isolation.
+//!
+//! Streams, such as `cat` copies to its output, may be pipes, sockets or
+//! terminals rather than files.  `copy_stream` tries `splice(2)` for them
+//! first, which moves data in the kernel as long as either end is a pipe,
+//! and none of the layers that only work between files.
+//!
+//! Holes in sparse files are kept by copying only the data extents found
+//! with `SEEK_DATA`/`SEEK_HOLE`, and optionally by skipping over blocks of
+//! zeros, which is what `cp --sparse` selects.
+//!
+//! A copy that fails part of the way through says which file it failed on
+//! and how far it got, with a [`PartialCopy`](struct.PartialCopy.html)
+//! inside the `io::Error`.  A destination the copy created is removed then,
+//! rather than left holding the start of the data; one that was already
+//! there is left as it is.
+
+#[cfg(unix)]
+use super::libc;
+use std::cmp;
+use std::error;
+use std::fmt;
+use std::fs::{self, File, Metadata, OpenOptions};
+use std::io::{self, Error, ErrorKind, Read, Seek, SeekFrom, Write};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+#[cfg(target_os = "linux")]
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use pipes;
+
+#[cfg(target_os = "linux")]
+const FICLONE: libc::c_ulong = 0x40049409;
+
+// copy_file_range() and sendfile() are asked for at most this much at once
+#[cfg(target_os = "linux")]
+const CHUNK_SIZE: usize = 1 << 30;
+
+const BUF_SIZE: usize = 128 * 1024;
+
+// blocks of zeros this big become holes with `Sparse::Always`; it is the
+// smallest unit most filesystems allocate
+const HOLE_SIZE: usize = 4096;
+
+/// The exit status of `cp`, `mv` and `install` after a copy ran out of
+/// room: no space left on the device, the quota or the file size limit
+/// reached.
+pub const EXIT_NO_SPACE: i32 = 3;
+
+/// The exit status of `cp`, `mv` and `install` after reading or writing a
+/// copy failed with an I/O error.
+pub const EXIT_IO_ERROR: i32 = 4;
+
+/// One way of moving bytes from one file to another.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CopyMethod {
+    Clone,
+    CopyFileRange,
+    SendFile,
+    Splice,
+    ReadWrite,
+}
+
+/// The full fallback chain, from cheapest to most expensive.
+pub static ALL_METHODS: &[CopyMethod] = &[
+    CopyMethod::Clone,
+    CopyMethod::CopyFileRange,
+    CopyMethod::SendFile,
+    CopyMethod::ReadWrite,
+];
+
+/// The fallback chain for streams, which may be pipes as well as files.
+pub static STREAM_METHODS: &[CopyMethod] = &[
+    CopyMethod::Splice,
+    CopyMethod::CopyFileRange,
+    CopyMethod::SendFile,
+    CopyMethod::ReadWrite,
+];
+
+/// Which parts of the destination are left as holes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Sparse {
+    /// none: every byte is written
+    Never,
+    /// the holes of the source, if it looks sparse
+    Auto,
+    /// the holes of the source, and any block of zeros
+    Always,
+}
+
+/// Which of the two files a copy failed on.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Side {
+    Source,
+    Destination,
+    /// either, as a copy made in the kernel does not say which
+    Unknown,
+}
+
+/// How far a copy got before it failed, the inner error of the `io::Error`
+/// that `FileCopier::copy_path` returns then.
+#[derive(Debug)]
+pub struct PartialCopy {
+    pub error: Error,
+    pub side: Side,
+    pub source: PathBuf,
+    pub dest: PathBuf,
+    /// how many bytes into the destination the copy had got
+    pub offset: u64,
+    /// whether the destination, which the copy had created, was removed
+    pub removed: bool,
+}
+
+impl PartialCopy {
+    /// The `PartialCopy` inside `err`, if there is one.
+    pub fn of(err: &Error) -> Option<&PartialCopy> {
+        err.get_ref().and_then(|inner| inner.downcast_ref::<PartialCopy>())
+    }
+}
+
+impl fmt::Display for PartialCopy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(match self.side {
+            Side::Source => write!(f, "cannot read '{}'", self.source.display()),
+            Side::Destination => write!(f, "cannot write '{}'", self.dest.display()),
+            Side::Unknown => write!(f, "cannot copy '{}' to '{}'", self.source.display(), self.dest.display()),
+        });
+        write!(f, " at byte {}: {}", self.offset, self.error)
+    }
+}
+
+impl error::Error for PartialCopy {
+    fn description(&self) -> &str {
+        "copy failed part of the way through"
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        Some(&self.error)
+    }
+}
+
+/// The exit status for an error from copying, if it is one with a status of
+/// its own: `EXIT_NO_SPACE` or `EXIT_IO_ERROR`.
+pub fn exit_status(err: &Error) -> Option<i32> {
+    status_for(PartialCopy::of(err).map_or(err, |partial| &partial.error))
+}
+
+#[cfg(unix)]
+fn status_for(err: &Error) -> Option<i32> {
+    match err.raw_os_error() {
+        Some(libc::ENOSPC) | Some(libc::EDQUOT) | Some(libc::EFBIG) => Some(EXIT_NO_SPACE),
+        Some(libc::EIO) => Some(EXIT_IO_ERROR),
+        _ => None,
+    }
+}
+
+#[cfg(not(unix))]
+fn status_for(_: &Error) -> Option<i32> {
+    None
+}
+
+/// Copies file contents through a chain of `CopyMethod`s.
+pub struct FileCopier {
+    methods: Vec<CopyMethod>,
+    preallocate: bool,
+    sparse: Sparse,
+}
+
+impl Default for FileCopier {
+    fn default() -> FileCopier {
+        FileCopier {
+            methods: ALL_METHODS.to_vec(),
+            preallocate: true,
+            sparse: Sparse::Never,
+        }
+    }
+}
+
+impl FileCopier {
+    /// Only try `methods`, in the given order.  If none of them is usable,
+    /// copying fails with `ErrorKind::Other`.
+    pub fn methods(mut self, methods: &[CopyMethod]) -> FileCopier {
+        self.methods = methods.to_vec();
+        self
+    }
+
+    /// Reserve the space for the whole destination up front (where the
+    /// filesystem supports it), so that running out of space is reported
+    /// before any data is written.  Enabled by default.
+    pub fn preallocate(mut self, preallocate: bool) -> FileCopier {
+        self.preallocate = preallocate;
+        self
+    }
+
+    /// Keep holes in the destination as `sparse` says.  `Sparse::Never` by
+    /// default.
+    pub fn sparse(mut self, sparse: Sparse) -> FileCopier {
+        self.sparse = sparse;
+        self
+    }
+
+    /// Copy the contents of `source` to `dest`, creating or truncating the
+    /// latter and giving it the permissions of the former, like `fs::copy`,
+    /// except that a FIFO or device is read like any other file.  Returns
+    /// the number of bytes copied and the method that copied them.
+    ///
+    /// If copying the contents fails, the error holds a `PartialCopy`, and
+    /// the destination is removed if this created it.
+    pub fn copy_path(&self, source: &Path, dest: &Path) -> io::Result<(u64, CopyMethod)> {
+        let mut src = try!(File::open(source));
+        let metadata = try!(src.metadata());
+        if metadata.is_dir() {
+            return Err(Error::new(ErrorKind::InvalidInput, "the source path is a directory"));
+        }
+
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            options.mode(metadata.permissions().mode());
+        }
+        let (mut dst, created) = match options.clone().create_new(true).open(dest) {
+            Ok(dst) => (dst, true),
+            Err(ref e) if e.kind() == ErrorKind::AlreadyExists => (try!(options.open(dest)), false),
+            Err(e) => return Err(e),
+        };
+
+        let result = match self.copy_contents(&mut src, &mut dst, metadata.len()) {
+            Ok(result) => result,
+            Err(failure) => {
+                let offset = current_offset(&mut dst).unwrap_or(0);
+                drop(dst);
+                let removed = created && fs::remove_file(dest).is_ok();
+                let partial = PartialCopy {
+                    error: failure.error,
+                    side: failure.side,
+                    source: source.to_path_buf(),
+                    dest: dest.to_path_buf(),
+                    offset: offset,
+                    removed: removed,
+                };
+                return Err(Error::new(partial.error.kind(), partial));
+            }
+        };
+        // what a device allows has little to do with what a copy of what
+        // was read from it should, so that is left to the umask
+        if metadata.is_file() {
+            try!(fs::set_permissions(dest, metadata.permissions()));
+        }
+        Ok(result)
+    }
+
+    /// Copy everything from the current offset of `src` to the current
+    /// offset of `dst`.  `len` is the expected amount of data, and is only
+    /// used as a hint for preallocation.
+    pub fn copy(&self, src: &mut File, dst: &mut File, len: u64) -> io::Result<(u64, CopyMethod)> {
+        self.copy_contents(src, dst, len).map_err(|failure| failure.error)
+    }
+
+    fn copy_contents(&self, src: &mut File, dst: &mut File, len: u64) -> Result<(u64, CopyMethod), Failure> {
+        if self.keep_holes(src, dst) {
+            return self.copy_sparse(src, dst);
+        }
+
+        // a pipe or a device has no room to reserve, and cannot be seeked in
+        let reserve = self.preallocate && is_regular(dst);
+        let mut preallocated = false;
+        let mut copied = 0;
+
+        for &method in &self.methods {
+            if method != CopyMethod::Clone && reserve && !preallocated {
+                try!(preallocate(dst, len).map_err(Failure::writing));
+                preallocated = true;
+            }
+
+            match copy_with(method, src, dst) {
+                Ok(n) => {
+                    copied += n;
+                    if preallocated {
+                        // the source may have shrunk while it was being copied
+                        let end = try!(current_offset(dst));
+                        try!(dst.set_len(end).map_err(Failure::writing));
+                    }
+                    return Ok((copied, method));
+                }
+                Err(Fallback::Unsupported(n)) => {
+                    // carry on from wherever this layer gave up
+                    copied += n;
+                }
+                Err(Fallback::Failed(failure)) => return Err(failure),
+            }
+        }
+
+        Err(Error::new(ErrorKind::Other, "no usable copy method").into())
+    }
+
+    fn keep_holes(&self, src: &mut File, dst: &mut File) -> bool {
+        // only regular files have holes to find or to make, and they can only
+        // be found relative to the start of both
+        if self.sparse == Sparse::Never || !is_regular(src) || !is_regular(dst) {
+            return false;
+        }
+        match (current_offset(src), current_offset(dst)) {
+            (Ok(0), Ok(0)) => self.sparse == Sparse::Always || src.metadata().map_or(false, |md| looks_sparse(&md)),
+            _ => false,
+        }
+    }
+
+    fn copy_sparse(&self, src: &mut File, dst: &mut File) -> Result<(u64, CopyMethod), Failure> {
+        // a clone shares the holes along with the data, but not for Always,
+        // which asks for holes where the source has none
+        if self.sparse == Sparse::Auto && self.methods.contains(&CopyMethod::Clone) {
+            match clone(src, dst) {
+                Ok(n) => return Ok((n, CopyMethod::Clone)),
+                Err(Fallback::Unsupported(_)) => {}
+                Err(Fallback::Failed(failure)) => return Err(failure),
+            }
+        }
+        if self.methods.iter().all(|&method| method == CopyMethod::Clone) {
+            return Err(Error::new(ErrorKind::Other, "no usable copy method").into());
+        }
+
+        let len = try!(src.metadata()).len();
+        let mut pos = 0;
+        while let Some((start, end)) = try!(next_data(src, pos, len)) {
+            try!(seek_to(src, start));
+            try!(seek_to(dst, start));
+            try!(copy_extent(src, dst, end - start, self.sparse == Sparse::Always));
+            pos = end;
+        }
+        // the holes at the end, which nothing was written after
+        try!(dst.set_len(len).map_err(Failure::writing));
+        try!(seek_to(dst, len));
+        Ok((len, CopyMethod::ReadWrite))
+    }
+}
+
+/// Copy the contents of `source` to `dest` with the default fallback chain.
+pub fn copy_file(source: &Path, dest: &Path) -> io::Result<u64> {
+    FileCopier::default().copy_path(source, dest).map(|(n, _)| n)
+}
+
+/// Copy everything left in `src` to `dst`, either of which may be a pipe,
+/// socket or device, with the `STREAM_METHODS` chain.  Nothing is done to
+/// `dst` but writing to it.
+pub fn copy_stream(src: &mut File, dst: &mut File) -> io::Result<(u64, CopyMethod)> {
+    FileCopier::default().methods(STREAM_METHODS).preallocate(false).copy(src, dst, 0)
+}
+
+// An error, and which file it was on.
+#[derive(Debug)]
+struct Failure {
+    error: Error,
+    side: Side,
+}
+
+impl Failure {
+    fn reading(error: Error) -> Failure {
+        Failure { error: error, side: Side::Source }
+    }
+
+    fn writing(error: Error) -> Failure {
+        Failure { error: error, side: Side::Destination }
+    }
+}
+
+impl From<Error> for Failure {
+    fn from(error: Error) -> Failure {
+        Failure { error: error, side: Side::Unknown }
+    }
+}
+
+enum Fallback {
+    /// The layer cannot be used here, after copying this many bytes
+    Unsupported(u64),
+    /// A genuine I/O error
+    Failed(Failure),
+}
+
+impl From<Error> for Fallback {
+    fn from(error: Error) -> Fallback {
+        Fallback::Failed(error.into())
+    }
+}
+
+fn copy_with(method: CopyMethod, src: &mut File, dst: &mut File) -> Result<u64, Fallback> {
+    match method {
+        CopyMethod::Clone => clone(src, dst),
+        CopyMethod::CopyFileRange => copy_file_range(src, dst),
+        CopyMethod::SendFile => sendfile(src, dst),
+        CopyMethod::Splice => splice(src, dst),
+        CopyMethod::ReadWrite => {
+            let size = buffer_size(src, dst);
+            read_write(src, dst, size).map_err(Fallback::Failed)
+        }
+    }
+}
+
+// Errors meaning "this layer does not apply to these files", as opposed to
+// a failure that would happen just the same with any other layer.
+#[cfg(target_os = "linux")]
+fn is_unsupported(err: &Error) -> bool {
+    match err.raw_os_error() {
+        Some(libc::ENOSYS) |
+        Some(libc::EOPNOTSUPP) |
+        Some(libc::ENOTTY) |
+        Some(libc::EXDEV) |
+        Some(libc::EINVAL) |
+        Some(libc::EBADF) |
+        Some(libc::EPERM) => true,
+        _ => false,
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn clone(src: &mut File, dst: &mut File) -> Result<u64, Fallback> {
+    // FICLONE always shares the whole file, and only a regular one
+    if !is_regular(src) || !is_regular(dst) {
+        return Err(Fallback::Unsupported(0));
+    }
+    match (current_offset(src), current_offset(dst)) {
+        (Ok(0), Ok(0)) => {}
+        _ => return Err(Fallback::Unsupported(0)),
+    }
+
+    let res = unsafe { libc::ioctl(dst.as_raw_fd(), FICLONE as _, src.as_raw_fd()) };
+    if res != 0 {
+        let err = Error::last_os_error();
+        return Err(if is_unsupported(&err) { Fallback::Unsupported(0) } else { err.into() });
+    }
+
+    // keep the offsets where a copy would have left them
+    let len = try!(src.metadata()).len();
+    try!(seek_to(src, len));
+    try!(seek_to(dst, len));
+    Ok(len)
+}
+
+#[cfg(target_os = "linux")]
+fn copy_file_range(src: &mut File, dst: &mut File) -> Result<u64, Fallback> {
+    let copied = try!(in_kernel(|| unsafe {
+        libc::syscall(libc::SYS_copy_file_range,
+                      src.as_raw_fd(),
+                      0 as *mut libc::loff_t,
+                      dst.as_raw_fd(),
+                      0 as *mut libc::loff_t,
+                      CHUNK_SIZE,
+                      0u32) as isize
+    }));
+    // files in /proc and /sys say they are empty, and some kernels copy
+    // them as if they were
+    if copied == 0 && try!(src.metadata()).len() == 0 {
+        return Err(Fallback::Unsupported(0));
+    }
+    Ok(copied)
+}
+
+#[cfg(target_os = "linux")]
+fn sendfile(src: &mut File, dst: &mut File) -> Result<u64, Fallback> {
+    in_kernel(|| unsafe { libc::sendfile(dst.as_raw_fd(), src.as_raw_fd(), 0 as *mut libc::off_t, CHUNK_SIZE) })
+}
+
+// Only works with a pipe at one end or the other.
+#[cfg(target_os = "linux")]
+fn splice(src: &mut File, dst: &mut File) -> Result<u64, Fallback> {
+    in_kernel(|| unsafe {
+        libc::splice(src.as_raw_fd(),
+                     0 as *mut libc::loff_t,
+                     dst.as_raw_fd(),
+                     0 as *mut libc::loff_t,
+                     CHUNK_SIZE,
+                     libc::SPLICE_F_MOVE)
+    })
+}
+
+// Make a copy in the kernel, which returns how much it copied each time,
+// until it copies nothing.
+#[cfg(target_os = "linux")]
+fn in_kernel<F: FnMut() -> isize>(mut copy: F) -> Result<u64, Fallback> {
+    let mut copied = 0;
+    loop {
+        match copy() {
+            0 => return Ok(copied),
+            n if n > 0 => copied += n as u64,
+            _ => {
+                let err = Error::last_os_error();
+                if err.kind() == ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(if is_unsupported(&err) {
+                    Fallback::Unsupported(copied)
+                } else if status_for(&err) == Some(EXIT_NO_SPACE) {
+                    // only the destination can run out of room
+                    Fallback::Failed(Failure::writing(err))
+                } else {
+                    err.into()
+                });
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn clone(_: &mut File, _: &mut File) -> Result<u64, Fallback> {
+    Err(Fallback::Unsupported(0))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn copy_file_range(_: &mut File, _: &mut File) -> Result<u64, Fallback> {
+    Err(Fallback::Unsupported(0))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn sendfile(_: &mut File, _: &mut File) -> Result<u64, Fallback> {
+    Err(Fallback::Unsupported(0))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn splice(_: &mut File, _: &mut File) -> Result<u64, Fallback> {
+    Err(Fallback::Unsupported(0))
+}
+
+// Whether fewer blocks are allocated than the size needs, which is how GNU
+// decides that a file has holes.
+#[cfg(unix)]
+fn looks_sparse(metadata: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    metadata.is_file() && metadata.blocks() * 512 < metadata.len()
+}
+
+#[cfg(not(unix))]
+fn looks_sparse(_: &Metadata) -> bool {
+    false
+}
+
+/// The next extent of `src` at or after `pos` that holds data, as a start
+/// and end offset.  Without a way of finding holes, the rest of the file is
+/// all data.
+#[cfg(target_os = "linux")]
+fn next_data(src: &File, pos: u64, len: u64) -> io::Result<Option<(u64, u64)>> {
+    if pos >= len {
+        return Ok(None);
+    }
+    let start = unsafe { libc::lseek(src.as_raw_fd(), pos as libc::off_t, libc::SEEK_DATA) };
+    if start < 0 {
+        let err = Error::last_os_error();
+        return match err.raw_os_error() {
+            // only a hole is left
+            Some(libc::ENXIO) => Ok(None),
+            _ if is_unsupported(&err) => Ok(Some((pos, len))),
+            _ => Err(err),
+        };
+    }
+    if start as u64 >= len {
+        return Ok(None);
+    }
+    let end = unsafe { libc::lseek(src.as_raw_fd(), start, libc::SEEK_HOLE) };
+    if end < 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(Some((start as u64, cmp::min(end as u64, len))))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn next_data(_: &File, pos: u64, len: u64) -> io::Result<Option<(u64, u64)>> {
+    Ok(if pos < len { Some((pos, len)) } else { None })
+}
+
+// Copy `n` bytes at the current offsets, seeking over blocks of zeros in
+// the destination instead of writing them if `zeros_to_holes` is set.
+fn copy_extent<R: Read, W: Write + Seek>(src: &mut R, dst: &mut W, n: u64, zeros_to_holes: bool) -> Result<(), Failure> {
+    let mut buf = vec![0; BUF_SIZE];
+    let mut left = n;
+    while left > 0 {
+        let want = cmp::min(left, BUF_SIZE as u64) as usize;
+        let got = match src.read(&mut buf[..want]) {
+            // the source has shrunk
+            Ok(0) => return Ok(()),
+            Ok(got) => got,
+            Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(Failure::reading(e)),
+        };
+        if zeros_to_holes {
+            for block in buf[..got].chunks(HOLE_SIZE) {
+                if block.iter().all(|&b| b == 0) {
+                    try!(dst.seek(SeekFrom::Current(block.len() as i64)));
+                } else {
+                    try!(dst.write_all(block).map_err(Failure::writing));
+                }
+            }
+        } else {
+            try!(dst.write_all(&buf[..got]).map_err(Failure::writing));
+        }
+        left -= got as u64;
+    }
+    Ok(())
+}
+
+fn read_write<R: Read, W: Write>(src: &mut R, dst: &mut W, buf_size: usize) -> Result<u64, Failure> {
+    let mut buf = vec![0; buf_size];
+    let mut copied = 0;
+    loop {
+        let n = match src.read(&mut buf) {
+            Ok(0) => return Ok(copied),
+            Ok(n) => n,
+            Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(Failure::reading(e)),
+        };
+        try!(dst.write_all(&buf[..n]).map_err(Failure::writing));
+        copied += n as u64;
+    }
+}
+
+// BUF_SIZE, or as much as a FIFO at either end holds if that is more
+#[cfg(target_os = "linux")]
+fn buffer_size(src: &File, dst: &File) -> usize {
+    pipes::buffer_size(BUF_SIZE, &[src.as_raw_fd(), dst.as_raw_fd()])
+}
+
+#[cfg(not(target_os = "linux"))]
+fn buffer_size(_: &File, _: &File) -> usize {
+    pipes::buffer_size(BUF_SIZE, &[])
+}
+
+#[cfg(target_os = "linux")]
+fn preallocate(dst: &mut File, len: u64) -> io::Result<()> {
+    if len == 0 {
+        return Ok(());
+    }
+    let start = try!(current_offset(dst));
+    let res = unsafe { libc::fallocate(dst.as_raw_fd(), 0, start as libc::off_t, len as libc::off_t) };
+    if res != 0 {
+        let err = Error::last_os_error();
+        return match err.raw_os_error() {
+            Some(libc::ENOSPC) | Some(libc::EFBIG) => Err(err),
+            // not every filesystem can reserve space; that is not an error
+            _ => Ok(()),
+        };
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn preallocate(_: &mut File, _: u64) -> io::Result<()> {
+    Ok(())
+}
+
+fn is_regular(file: &File) -> bool {
+    file.metadata().map_or(false, |md| md.is_file())
+}
+
+fn current_offset(file: &mut File) -> io::Result<u64> {
+    file.seek(SeekFrom::Current(0))
+}
+
+fn seek_to(file: &mut File, pos: u64) -> io::Result<u64> {
+    file.seek(SeekFrom::Start(pos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use faults::Faulty;
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::{Cursor, Read, Write};
+    use std::path::PathBuf;
+    use std::process;
+
+    fn scratch(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("uucore-copy-{}-{}", process::id(), name))
+    }
+
+    fn write(path: &Path, data: &[u8]) {
+        File::create(path).unwrap().write_all(data).unwrap();
+    }
+
+    fn read(path: &Path) -> Vec<u8> {
+        let mut data = vec![];
+        File::open(path).unwrap().read_to_end(&mut data).unwrap();
+        data
+    }
+
+    fn check_layer(method: CopyMethod) {
+        let name = format!("{:?}", method);
+        let source = scratch(&format!("{}-src", name));
+        let dest = scratch(&format!("{}-dst", name));
+        let data: Vec<u8> = (0..300000u32).map(|i| (i % 251) as u8).collect();
+        write(&source, &data);
+        // a longer destination has to be truncated
+        write(&dest, &[1; 400000]);
+
+        // the read/write layer is the only one that is always available
+        let copier = FileCopier::default().methods(&[method, CopyMethod::ReadWrite]);
+        let (n, _) = copier.copy_path(&source, &dest).unwrap();
+        assert_eq!(n, data.len() as u64);
+        assert_eq!(read(&dest), data);
+
+        fs::remove_file(&source).unwrap();
+        fs::remove_file(&dest).unwrap();
+    }
+
+    #[test]
+    fn test_clone() {
+        check_layer(CopyMethod::Clone);
+    }
+
+    #[test]
+    fn test_copy_file_range() {
+        check_layer(CopyMethod::CopyFileRange);
+    }
+
+    #[test]
+    fn test_sendfile() {
+        check_layer(CopyMethod::SendFile);
+    }
+
+    #[test]
+    fn test_splice() {
+        // between two files, which it cannot do
+        check_layer(CopyMethod::Splice);
+    }
+
+    #[test]
+    fn test_read_write() {
+        check_layer(CopyMethod::ReadWrite);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_copy_stream_from_pipe() {
+        use std::os::unix::io::FromRawFd;
+        use std::thread;
+
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        let (mut reader, mut writer) = unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) };
+        let data: Vec<u8> = (0..300000u32).map(|i| (i % 251) as u8).collect();
+        let sent = data.clone();
+        let feeder = thread::spawn(move || writer.write_all(&sent).unwrap());
+
+        let dest = scratch("stream-dst");
+        let mut dst = File::create(&dest).unwrap();
+        let (n, method) = copy_stream(&mut reader, &mut dst).unwrap();
+        feeder.join().unwrap();
+        assert_eq!(n, data.len() as u64);
+        assert_eq!(method, CopyMethod::Splice);
+        assert_eq!(read(&dest), data);
+        fs::remove_file(&dest).unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_copy_stream_from_proc() {
+        // a file that says it is empty, but is not
+        let dest = scratch("proc-dst");
+        let mut dst = File::create(&dest).unwrap();
+        let (n, _) = copy_stream(&mut File::open("/proc/self/status").unwrap(), &mut dst).unwrap();
+        assert!(n > 0);
+        assert!(String::from_utf8(read(&dest)).unwrap().contains("Pid:"));
+        fs::remove_file(&dest).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sparse() {
+        let source = scratch("sparse-src");
+        let dest = scratch("sparse-dst");
+        {
+            let mut file = File::create(&source).unwrap();
+            file.set_len(1 << 20).unwrap();
+            seek_to(&mut file, 1 << 19).unwrap();
+            file.write_all(b"abc").unwrap();
+        }
+        let mut data = vec![0; 1 << 20];
+        data[1 << 19..(1 << 19) + 3].copy_from_slice(b"abc");
+        // whether there are holes to keep depends on the filesystem
+        let holes = looks_sparse(&fs::metadata(&source).unwrap());
+
+        for &sparse in &[Sparse::Auto, Sparse::Always] {
+            let copier = FileCopier::default().methods(&ALL_METHODS[1..]).sparse(sparse);
+            let (n, _) = copier.copy_path(&source, &dest).unwrap();
+            assert_eq!(n, data.len() as u64);
+            assert_eq!(read(&dest), data);
+            assert_eq!(looks_sparse(&fs::metadata(&dest).unwrap()), holes);
+        }
+
+        // with Always, zeros that were written out become holes as well
+        write(&source, &[0; 1 << 16]);
+        let copier = FileCopier::default().sparse(Sparse::Always);
+        copier.copy_path(&source, &dest).unwrap();
+        assert_eq!(read(&dest), vec![0; 1 << 16]);
+        assert_eq!(looks_sparse(&fs::metadata(&dest).unwrap()), holes);
+
+        fs::remove_file(&source).unwrap();
+        fs::remove_file(&dest).unwrap();
+    }
+
+    #[test]
+    fn test_no_usable_method() {
+        let source = scratch("none-src");
+        let dest = scratch("none-dst");
+        write(&source, b"abc");
+
+        let result = FileCopier::default().methods(&[]).copy_path(&source, &dest);
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::Other);
+        // the destination it made is not left behind
+        assert!(!dest.exists());
+
+        // but one that was there is kept
+        write(&dest, b"xyz");
+        assert!(FileCopier::default().methods(&[]).copy_path(&source, &dest).is_err());
+        assert_eq!(read(&dest), b"");
+
+        fs::remove_file(&source).unwrap();
+        fs::remove_file(&dest).unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_partial_copy() {
+        let source = scratch("full-src");
+        write(&source, &[1; 10000]);
+
+        for methods in &[ALL_METHODS, &[CopyMethod::ReadWrite]] {
+            let err = FileCopier::default().methods(methods).copy_path(&source, Path::new("/dev/full")).unwrap_err();
+            assert_eq!(exit_status(&err), Some(EXIT_NO_SPACE));
+            let partial = PartialCopy::of(&err).unwrap();
+            assert_eq!(partial.side, Side::Destination);
+            assert_eq!(partial.error.raw_os_error(), Some(libc::ENOSPC));
+            assert_eq!(partial.offset, 0);
+            assert!(!partial.removed);
+            assert_eq!(err.to_string(), "cannot write '/dev/full' at byte 0: No space left on device (os error 28)");
+        }
+
+        fs::remove_file(&source).unwrap();
+    }
+
+    #[test]
+    fn test_loops_with_faults() {
+        let data: Vec<u8> = (0..100000u32).map(|i| (i % 251) as u8).collect();
+        let mut src = Faulty::new(&data[..]).short(1000).interrupt(3);
+        let mut dst = Faulty::new(Vec::new()).short(777).interrupt(5);
+        assert_eq!(read_write(&mut src, &mut dst, BUF_SIZE).unwrap(), data.len() as u64);
+        assert_eq!(dst.into_inner(), data);
+
+        // blocks of zeros read in pieces are still made into holes
+        let mut sparse = data.clone();
+        for b in &mut sparse[HOLE_SIZE..3 * HOLE_SIZE] {
+            *b = 0;
+        }
+        let mut src = Faulty::new(&sparse[..]).short(HOLE_SIZE / 3).interrupt(2);
+        let mut dst = Faulty::new(Cursor::new(Vec::new())).short(1000).interrupt(4);
+        copy_extent(&mut src, &mut dst, sparse.len() as u64, true).unwrap();
+        assert!(dst.moved() < sparse.len() as u64);
+        assert_eq!(dst.into_inner().into_inner(), sparse);
+
+        // what was read before the source failed is written
+        let mut src = Faulty::new(&data[..]).short(1000).fail_at(5500, ErrorKind::Other);
+        let mut dst = Vec::new();
+        let failure = read_write(&mut src, &mut dst, BUF_SIZE).unwrap_err();
+        assert_eq!(failure.side, Side::Source);
+        assert_eq!(dst, &data[..5500]);
+
+        let mut dst = Faulty::new(Vec::new()).would_block(3);
+        let failure = read_write(&mut &data[..], &mut dst, 1000).unwrap_err();
+        assert_eq!(failure.side, Side::Destination);
+        assert_eq!(failure.error.kind(), ErrorKind::WouldBlock);
+        assert_eq!(dst.into_inner(), &data[..2000]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exit_status() {
+        assert_eq!(exit_status(&Error::from_raw_os_error(libc::EDQUOT)), Some(EXIT_NO_SPACE));
+        assert_eq!(exit_status(&Error::from_raw_os_error(libc::EIO)), Some(EXIT_IO_ERROR));
+        assert_eq!(exit_status(&Error::from_raw_os_error(libc::EACCES)), None);
+        assert_eq!(exit_status(&Error::new(ErrorKind::Other, "other")), None);
+    }
+}
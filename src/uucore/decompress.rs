@@ -0,0 +1,120 @@
+// This file is part of the uutils coreutils package.
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+//
+
+//! Transparent decompression of input files (uutils extension).
+//!
+//! The compression format is recognized by its magic number rather than by
+//! the file name, so compressed and plain operands can be mixed freely and
+//! compressed data can be piped in on standard input.
+
+extern crate flate2;
+extern crate zstd;
+
+use self::flate2::read::MultiGzDecoder;
+use self::zstd::stream::read::Decoder as ZstdDecoder;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Cursor, Read, stdin};
+
+static GZIP_MAGIC: &'static [u8] = &[0x1f, 0x8b];
+static ZSTD_MAGIC: &'static [u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Plain,
+    Gzip,
+    Zstd,
+}
+
+impl Format {
+    /// Recognize the format from the first bytes of the data.
+    pub fn detect(head: &[u8]) -> Format {
+        if head.starts_with(GZIP_MAGIC) {
+            Format::Gzip
+        } else if head.starts_with(ZSTD_MAGIC) {
+            Format::Zstd
+        } else {
+            Format::Plain
+        }
+    }
+}
+
+/// Wrap `input` in a decoder for whichever format it turns out to be in.
+/// Data that is not compressed is passed through untouched.
+pub fn decoder<'a, R: Read + 'a>(mut input: R) -> io::Result<Box<BufRead + 'a>> {
+    // a pipe may deliver the magic number in several pieces
+    let mut head = Vec::with_capacity(ZSTD_MAGIC.len());
+    try!((&mut input).take(ZSTD_MAGIC.len() as u64).read_to_end(&mut head));
+    let format = Format::detect(&head);
+    let input = Cursor::new(head).chain(input);
+
+    Ok(match format {
+        Format::Plain => Box::new(BufReader::new(input)),
+        Format::Gzip => Box::new(BufReader::new(MultiGzDecoder::new(input))),
+        Format::Zstd => Box::new(BufReader::new(try!(ZstdDecoder::new(input)))),
+    })
+}
+
+/// Open the input named `name`, standard input for `-`, decompressing it if
+/// `decompress` is set.
+pub fn open(name: &str, decompress: bool) -> io::Result<Box<BufRead>> {
+    let input: Box<Read> = if name == "-" {
+        Box::new(stdin())
+    } else {
+        Box::new(try!(File::open(name)))
+    };
+    if decompress {
+        decoder(input)
+    } else {
+        Ok(Box::new(BufReader::new(input)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use super::flate2::Compression;
+    use super::flate2::write::GzEncoder;
+
+    fn decode(data: &[u8]) -> String {
+        let mut result = String::new();
+        decoder(data).unwrap().read_to_string(&mut result).unwrap();
+        result
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(Format::detect(b"\x1f\x8b\x08"), Format::Gzip);
+        assert_eq!(Format::detect(b"\x28\xb5\x2f\xfd"), Format::Zstd);
+        assert_eq!(Format::detect(b"\x28\xb5"), Format::Plain);
+        assert_eq!(Format::detect(b""), Format::Plain);
+    }
+
+    #[test]
+    fn test_plain() {
+        assert_eq!(decode(b"a\nb\n"), "a\nb\n");
+        assert_eq!(decode(b"a"), "a");
+        assert_eq!(decode(b""), "");
+    }
+
+    #[test]
+    fn test_gzip() {
+        // concatenated members, as produced by `gzip -c a b`
+        let mut data = Vec::new();
+        for part in &["one\n", "two\n"] {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(part.as_bytes()).unwrap();
+            data.extend(encoder.finish().unwrap());
+        }
+        assert_eq!(decode(&data), "one\ntwo\n");
+    }
+
+    #[test]
+    fn test_zstd() {
+        let data = super::zstd::stream::encode_all(&b"one\ntwo\n"[..], 0).unwrap();
+        assert_eq!(decode(&data), "one\ntwo\n");
+    }
+}
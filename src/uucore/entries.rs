@@ -28,7 +28,7 @@
 #[cfg(any(target_os = "freebsd", target_os = "macos"))]
 use libc::time_t;
 use libc::{uid_t, gid_t, c_char, c_int};
-use libc::{passwd, group, getpwnam, getpwuid, getgrnam, getgrgid, getgroups};
+use libc::{passwd, group, getpwnam, getpwuid, getgrnam, getgrgid, getgroups, getgid, getegid};
 
 use ::std::ptr;
 use ::std::io::ErrorKind;
@@ -58,6 +58,23 @@ pub fn get_groups() -> IOResult<Vec<gid_t>> {
     }
 }
 
+/// The groups of the user `user`, or of this process without one, as `id
+/// -G` and `groups` list them: the real group first, then the effective
+/// one if it differs, then the others, each once.
+pub fn group_list(user: Option<&Passwd>) -> IOResult<Vec<gid_t>> {
+    let (rgid, egid, others) = match user {
+        Some(user) => (user.gid(), user.gid(), user.belongs_to()),
+        None => unsafe { (getgid(), getegid(), try!(get_groups())) },
+    };
+    let mut groups = vec![rgid];
+    for gid in Some(egid).into_iter().chain(others) {
+        if !groups.contains(&gid) {
+            groups.push(gid);
+        }
+    }
+    Ok(groups)
+}
+
 pub struct Passwd {
     inner: passwd,
 }
@@ -0,0 +1,202 @@
+// This file is part of the uutils coreutils package.
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+//
+
+//! Readers and writers that go wrong on purpose, for tests.
+//!
+//! A [`Faulty`](struct.Faulty.html) wraps a reader or writer and makes the
+//! calls through it behave as a pipe, a terminal or a failing disk can:
+//! moving fewer bytes than asked for, being interrupted by a signal before
+//! moving any, finding nothing ready on a non-blocking descriptor, or
+//! failing for good part of the way through.  Code that only works when
+//! each call does all it was asked shows up by giving the wrong data.
+//!
+//! The faults come on a fixed schedule, so that a failing test fails the
+//! same way each time.  This is only built for uucore's own tests, and for
+//! other crates' tests that enable the `faults` feature.
+
+use std::cmp;
+use std::io::{self, Error, ErrorKind, Read, Seek, SeekFrom, Write};
+
+/// A reader or writer that goes wrong as it is told to.
+#[derive(Debug)]
+pub struct Faulty<T> {
+    inner: T,
+    chunk: usize,
+    interrupt_every: usize,
+    would_block_every: usize,
+    fail_at: Option<(u64, ErrorKind)>,
+    calls: usize,
+    moved: u64,
+}
+
+impl<T> Faulty<T> {
+    /// `inner`, going wrong in none of the ways yet.
+    pub fn new(inner: T) -> Faulty<T> {
+        Faulty {
+            inner: inner,
+            chunk: usize::max_value(),
+            interrupt_every: 0,
+            would_block_every: 0,
+            fail_at: None,
+            calls: 0,
+            moved: 0,
+        }
+    }
+
+    /// Move at most `chunk` bytes in a call, like a slow pipe.
+    ///
+    /// # Panics
+    ///
+    /// If `chunk` is 0, which would look like the end of the input.
+    pub fn short(mut self, chunk: usize) -> Faulty<T> {
+        assert!(chunk > 0, "a call has to move at least one byte");
+        self.chunk = chunk;
+        self
+    }
+
+    /// Fail every `every`th call with `ErrorKind::Interrupted`, as a call a
+    /// signal arrives in fails with `EINTR`, having moved nothing.
+    pub fn interrupt(mut self, every: usize) -> Faulty<T> {
+        self.interrupt_every = every;
+        self
+    }
+
+    /// Fail every `every`th call with `ErrorKind::WouldBlock`, as one on a
+    /// non-blocking descriptor with nothing ready fails with `EAGAIN`.
+    pub fn would_block(mut self, every: usize) -> Faulty<T> {
+        self.would_block_every = every;
+        self
+    }
+
+    /// Fail every call with an error of `kind` once `offset` bytes have
+    /// been moved, as a disk fails part of the way through a file.  The
+    /// call that would go past `offset` is cut short at it.
+    pub fn fail_at(mut self, offset: u64, kind: ErrorKind) -> Faulty<T> {
+        self.fail_at = Some((offset, kind));
+        self
+    }
+
+    /// How many bytes have been moved through this.
+    pub fn moved(&self) -> u64 {
+        self.moved
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    // How many of `len` bytes the next call may move, or the fault it has.
+    fn next_call(&mut self, len: usize) -> io::Result<usize> {
+        self.calls += 1;
+        if self.interrupt_every != 0 && self.calls % self.interrupt_every == 0 {
+            return Err(Error::new(ErrorKind::Interrupted, "interrupted (injected fault)"));
+        }
+        if self.would_block_every != 0 && self.calls % self.would_block_every == 0 {
+            return Err(Error::new(ErrorKind::WouldBlock, "would block (injected fault)"));
+        }
+        let len = cmp::min(len, self.chunk);
+        match self.fail_at {
+            Some((offset, kind)) if self.moved >= offset => Err(Error::new(kind, "injected fault")),
+            Some((offset, _)) => Ok(cmp::min(len as u64, offset - self.moved) as usize),
+            None => Ok(len),
+        }
+    }
+}
+
+impl<T: Read> Read for Faulty<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let len = try!(self.next_call(buf.len()));
+        let n = try!(self.inner.read(&mut buf[..len]));
+        self.moved += n as u64;
+        Ok(n)
+    }
+}
+
+impl<T: Write> Write for Faulty<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let len = try!(self.next_call(buf.len()));
+        let n = try!(self.inner.write(&buf[..len]));
+        self.moved += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Seeking goes straight through, and does not count as moving bytes.
+impl<T: Seek> Seek for Faulty<T> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_short() {
+        let mut input = Faulty::new(&b"abcdefg"[..]).short(3);
+        let mut buf = [0; 8];
+        assert_eq!(input.read(&mut buf).unwrap(), 3);
+        assert_eq!(&buf[..3], b"abc");
+        let mut rest = Vec::new();
+        input.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"defg");
+        assert_eq!(input.moved(), 7);
+
+        let mut output = Faulty::new(Vec::new()).short(2);
+        assert_eq!(output.write(b"abc").unwrap(), 2);
+        output.write_all(b"def").unwrap();
+        assert_eq!(output.into_inner(), b"abdef");
+    }
+
+    #[test]
+    fn test_interrupt_and_would_block() {
+        let mut input = Faulty::new(&b"abcdef"[..]).short(1).interrupt(2).would_block(3);
+        let mut buf = [0; 4];
+        assert_eq!(input.read(&mut buf).unwrap(), 1);
+        assert_eq!(input.read(&mut buf).unwrap_err().kind(), ErrorKind::Interrupted);
+        assert_eq!(input.read(&mut buf).unwrap_err().kind(), ErrorKind::WouldBlock);
+        assert_eq!(input.read(&mut buf).unwrap_err().kind(), ErrorKind::Interrupted);
+        assert_eq!(input.read(&mut buf).unwrap(), 1);
+        assert_eq!(&buf[..1], b"b");
+
+        // read_to_end goes on after EINTR
+        let mut input = Faulty::new(&b"abcdef"[..]).short(2).interrupt(2);
+        let mut all = Vec::new();
+        input.read_to_end(&mut all).unwrap();
+        assert_eq!(all, b"abcdef");
+    }
+
+    #[test]
+    fn test_fail_at() {
+        let mut input = Faulty::new(&b"abcdef"[..]).fail_at(4, ErrorKind::Other);
+        let mut all = Vec::new();
+        assert_eq!(input.read_to_end(&mut all).unwrap_err().kind(), ErrorKind::Other);
+        assert_eq!(all, b"abcd");
+        assert_eq!(input.read(&mut [0; 4]).unwrap_err().kind(), ErrorKind::Other);
+
+        let mut output = Faulty::new(Cursor::new(Vec::new())).fail_at(3, ErrorKind::Other);
+        assert_eq!(output.write_all(b"abcdef").unwrap_err().kind(), ErrorKind::Other);
+        output.seek(SeekFrom::Start(1)).unwrap();
+        assert_eq!(output.moved(), 3);
+        assert_eq!(output.into_inner().into_inner(), b"abc");
+    }
+}
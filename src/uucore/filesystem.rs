@@ -0,0 +1,238 @@
+// This file is part of the uutils coreutils package.
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+//
+
+//! The file system as some utilities see it.
+//!
+//! What they need of it is behind the [`FileSystem`](trait.FileSystem.html)
+//! trait, so that their tests can give them a
+//! [`MemoryFileSystem`](struct.MemoryFileSystem.html) to work on instead
+//! of the real one, with a [`Clock`](../clock/trait.Clock.html) of their
+//! choosing for the times of new files.
+
+#[cfg(unix)]
+use super::libc;
+use super::clock::Clock;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+#[cfg(unix)]
+use std::ffi::CString;
+use std::fs::{self, OpenOptions};
+use std::io::{Error, ErrorKind, Result};
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FileKind {
+    File,
+    Dir,
+    Symlink,
+    Other,
+}
+
+/// What there is to know about a file.  The device and inode numbers tell
+/// whether a name has come to refer to another file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileInfo {
+    pub kind: FileKind,
+    pub len: u64,
+    pub accessed: SystemTime,
+    pub modified: SystemTime,
+    pub dev: u64,
+    pub ino: u64,
+}
+
+pub trait FileSystem {
+    /// The file at `path`, or unless `follow` the link itself if it is one.
+    fn metadata(&self, path: &Path, follow: bool) -> Result<FileInfo>;
+
+    /// Make an empty file at `path` unless there is one already.
+    fn create(&self, path: &Path) -> Result<()>;
+
+    /// Set the access and modification times of the file at `path`, or
+    /// unless `follow` of the link itself if it is one.
+    fn set_times(&self, path: &Path, accessed: SystemTime, modified: SystemTime, follow: bool) -> Result<()>;
+}
+
+/// The file system the utility runs on.
+pub struct RealFileSystem;
+
+impl FileSystem for RealFileSystem {
+    fn metadata(&self, path: &Path, follow: bool) -> Result<FileInfo> {
+        let metadata = try!(if follow { fs::metadata(path) } else { fs::symlink_metadata(path) });
+        let file_type = metadata.file_type();
+        let kind = if file_type.is_file() {
+            FileKind::File
+        } else if file_type.is_dir() {
+            FileKind::Dir
+        } else if file_type.is_symlink() {
+            FileKind::Symlink
+        } else {
+            FileKind::Other
+        };
+        let (dev, ino) = identity(&metadata);
+        Ok(FileInfo {
+            kind: kind,
+            len: metadata.len(),
+            accessed: try!(metadata.accessed()),
+            modified: try!(metadata.modified()),
+            dev: dev,
+            ino: ino,
+        })
+    }
+
+    fn create(&self, path: &Path) -> Result<()> {
+        OpenOptions::new().write(true).create(true).open(path).map(|_| ())
+    }
+
+    #[cfg(unix)]
+    fn set_times(&self, path: &Path, accessed: SystemTime, modified: SystemTime, follow: bool) -> Result<()> {
+        use super::clock::to_timespec;
+
+        let timespec = |time| {
+            let (sec, nsec) = to_timespec(time);
+            libc::timespec { tv_sec: sec as libc::time_t, tv_nsec: nsec as libc::c_long }
+        };
+        let times = [timespec(accessed), timespec(modified)];
+        let path = try!(CString::new(path.as_os_str().as_bytes()).map_err(|e| Error::new(ErrorKind::InvalidInput, e)));
+        let flags = if follow { 0 } else { libc::AT_SYMLINK_NOFOLLOW };
+        if unsafe { libc::utimensat(libc::AT_FDCWD, path.as_ptr(), times.as_ptr(), flags) } == 0 {
+            Ok(())
+        } else {
+            Err(Error::last_os_error())
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn set_times(&self, path: &Path, accessed: SystemTime, modified: SystemTime, follow: bool) -> Result<()> {
+        use std::fs::FileTimes;
+
+        if !follow {
+            return Err(Error::new(ErrorKind::Other, "cannot set the times of a link itself here"));
+        }
+        let file = try!(OpenOptions::new().write(true).open(path));
+        file.set_times(FileTimes::new().set_accessed(accessed).set_modified(modified))
+    }
+}
+
+#[cfg(unix)]
+fn identity(metadata: &fs::Metadata) -> (u64, u64) {
+    (metadata.dev(), metadata.ino())
+}
+
+#[cfg(not(unix))]
+fn identity(_: &fs::Metadata) -> (u64, u64) {
+    (0, 0)
+}
+
+/// Regular files kept in memory, for tests.  There are no directories or
+/// links, so every path is a name for a file or for nothing.
+pub struct MemoryFileSystem<'a> {
+    clock: &'a Clock,
+    files: RefCell<HashMap<PathBuf, FileInfo>>,
+    last_ino: Cell<u64>,
+}
+
+impl<'a> MemoryFileSystem<'a> {
+    pub fn new(clock: &'a Clock) -> MemoryFileSystem<'a> {
+        MemoryFileSystem {
+            clock: clock,
+            files: RefCell::new(HashMap::new()),
+            last_ino: Cell::new(0),
+        }
+    }
+
+    /// Put a new empty file at `path`, last accessed and modified at
+    /// `time`, in place of any there.
+    pub fn insert<P: AsRef<Path>>(&self, path: P, time: SystemTime) {
+        self.last_ino.set(self.last_ino.get() + 1);
+        self.files.borrow_mut().insert(path.as_ref().to_path_buf(), FileInfo {
+            kind: FileKind::File,
+            len: 0,
+            accessed: time,
+            modified: time,
+            dev: 0,
+            ino: self.last_ino.get(),
+        });
+    }
+
+    pub fn get<P: AsRef<Path>>(&self, path: P) -> Option<FileInfo> {
+        self.files.borrow().get(path.as_ref()).cloned()
+    }
+
+    pub fn remove<P: AsRef<Path>>(&self, path: P) -> Option<FileInfo> {
+        self.files.borrow_mut().remove(path.as_ref())
+    }
+
+    /// Give the file at `from` the name `to`, in place of any there.
+    pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> Result<()> {
+        let file = try!(self.remove(from).ok_or_else(not_found));
+        self.files.borrow_mut().insert(to.as_ref().to_path_buf(), file);
+        Ok(())
+    }
+}
+
+impl<'a> FileSystem for MemoryFileSystem<'a> {
+    fn metadata(&self, path: &Path, _follow: bool) -> Result<FileInfo> {
+        self.get(path).ok_or_else(not_found)
+    }
+
+    fn create(&self, path: &Path) -> Result<()> {
+        if self.get(path).is_none() {
+            self.insert(path, self.clock.now());
+        }
+        Ok(())
+    }
+
+    fn set_times(&self, path: &Path, accessed: SystemTime, modified: SystemTime, _follow: bool) -> Result<()> {
+        match self.files.borrow_mut().get_mut(path) {
+            Some(file) => {
+                file.accessed = accessed;
+                file.modified = modified;
+                Ok(())
+            }
+            None => Err(not_found()),
+        }
+    }
+}
+
+fn not_found() -> Error {
+    Error::new(ErrorKind::NotFound, "No such file or directory")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clock::FakeClock;
+    use std::time::Duration;
+
+    #[test]
+    fn test_memory_file_system() {
+        let clock = FakeClock::at(1_000_000_000);
+        let fs = MemoryFileSystem::new(&clock);
+        let path = Path::new("log");
+
+        assert_eq!(fs.metadata(path, true).unwrap_err().kind(), ErrorKind::NotFound);
+        fs.create(path).unwrap();
+        let created = fs.metadata(path, true).unwrap();
+        assert_eq!(created.modified, clock.now());
+
+        // creating it again leaves it as it is
+        clock.advance(Duration::from_secs(60));
+        fs.create(path).unwrap();
+        assert_eq!(fs.metadata(path, true).unwrap(), created);
+
+        // a file rotated out of the way and made anew is another file
+        fs.rename(path, "log.1").unwrap();
+        fs.create(path).unwrap();
+        assert_eq!(fs.get("log.1").unwrap().ino, created.ino);
+        assert!(fs.get(path).unwrap().ino != created.ino);
+        assert_eq!(fs.get(path).unwrap().modified, clock.now());
+    }
+}
@@ -0,0 +1,272 @@
+// This file is part of the uutils coreutils package.
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+//
+
+//! Shell wildcard patterns, matched as `fnmatch(3)` matches them.
+//!
+//! `*` matches any run of characters, `?` any one character, and a bracket
+//! expression such as `[a-z]`, `[!0-9]` or `[[:alpha:]_]` any one character
+//! of a set.  A backslash takes the character after it literally.  A `[`
+//! without a closing `]` is only itself.
+
+/// How a pattern is matched, as by the `FNM_` flags of `fnmatch(3)`.
+#[derive(Clone, Copy, Default)]
+pub struct Flags {
+    /// `FNM_PATHNAME`: only a `/` in the pattern matches a `/`.
+    pub pathname: bool,
+    /// `FNM_PERIOD`: only a `.` in the pattern matches a `.` starting the
+    /// name, or with `pathname` starting any component of it.
+    pub period: bool,
+    /// `FNM_NOESCAPE`: a backslash is only itself.
+    pub noescape: bool,
+    /// `FNM_CASEFOLD`: letters match regardless of case.
+    pub casefold: bool,
+    /// `FNM_LEADING_DIR`: the pattern need only match up to a `/` in the name.
+    pub leading_dir: bool,
+}
+
+/// Whether `name` matches `pattern`.
+pub fn fnmatch(pattern: &str, name: &str, flags: Flags) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    matches(&pattern, &name, true, flags)
+}
+
+// `start` is whether `name` starts the name or, with `pathname`, one of its
+// components.
+fn matches(mut pattern: &[char], mut name: &[char], mut start: bool, flags: Flags) -> bool {
+    loop {
+        let (&p, rest) = match pattern.split_first() {
+            Some(split) => split,
+            None => return name.is_empty() || (flags.leading_dir && name[0] == '/'),
+        };
+        if p == '*' {
+            if is_hidden(name, start, flags) {
+                return false;
+            }
+            let rest = &rest[rest.iter().take_while(|&&c| c == '*').count()..];
+            for skip in 0..name.len() + 1 {
+                if matches(rest, &name[skip..], false, flags) {
+                    return true;
+                }
+                if skip < name.len() && flags.pathname && name[skip] == '/' {
+                    break;
+                }
+            }
+            return false;
+        }
+
+        let c = match name.first() {
+            Some(&c) => c,
+            None => return false,
+        };
+        let after = match p {
+            '?' => {
+                if (flags.pathname && c == '/') || is_hidden(name, start, flags) {
+                    return false;
+                }
+                rest
+            }
+            '[' => match bracket(rest, flags) {
+                Some((set, after)) => {
+                    if (flags.pathname && c == '/') || is_hidden(name, start, flags) || !set.contains(c, flags) {
+                        return false;
+                    }
+                    after
+                }
+                None if c == '[' => rest,
+                None => return false,
+            },
+            '\\' if !flags.noescape && !rest.is_empty() => {
+                if !same_char(rest[0], c, flags) {
+                    return false;
+                }
+                &rest[1..]
+            }
+            _ => {
+                if !same_char(p, c, flags) {
+                    return false;
+                }
+                rest
+            }
+        };
+        pattern = after;
+        name = &name[1..];
+        start = flags.pathname && c == '/';
+    }
+}
+
+// Whether `name` starts with a period that only a period in the pattern may
+// match.
+fn is_hidden(name: &[char], start: bool, flags: Flags) -> bool {
+    flags.period && start && name.first() == Some(&'.')
+}
+
+fn same_char(a: char, b: char, flags: Flags) -> bool {
+    a == b || (flags.casefold && fold(a) == fold(b))
+}
+
+fn fold(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+// The characters of a bracket expression.
+struct Set {
+    negated: bool,
+    items: Vec<Item>,
+}
+
+enum Item {
+    Range(char, char),
+    Class(fn(char) -> bool),
+    // a class with a name no class has, which matches nothing
+    Invalid,
+}
+
+impl Set {
+    fn contains(&self, c: char, flags: Flags) -> bool {
+        let found = self.items.iter().any(|item| match *item {
+            Item::Range(low, high) => {
+                (low <= c && c <= high)
+                    || (flags.casefold && c.to_lowercase().chain(c.to_uppercase()).any(|c| low <= c && c <= high))
+            }
+            Item::Class(class) => class(c),
+            Item::Invalid => false,
+        });
+        found != self.negated
+    }
+}
+
+// The bracket expression `pattern` starts with, just past its `[`, and what
+// follows it, or `None` if there is no `]` to close it.
+fn bracket(pattern: &[char], flags: Flags) -> Option<(Set, &[char])> {
+    let mut set = Set {
+        negated: false,
+        items: Vec::new(),
+    };
+    let mut i = 0;
+    if i < pattern.len() && (pattern[i] == '!' || pattern[i] == '^') {
+        set.negated = true;
+        i += 1;
+    }
+    let first = i;
+    loop {
+        let mut low = match pattern.get(i) {
+            Some(&']') if i > first => return Some((set, &pattern[i + 1..])),
+            Some(&c) => c,
+            None => return None,
+        };
+        if low == '[' && pattern.get(i + 1) == Some(&':') {
+            let name_start = i + 2;
+            let name_len = pattern[name_start..].windows(2).position(|w| w == [':', ']']);
+            if let Some(len) = name_len {
+                let name: String = pattern[name_start..name_start + len].iter().cloned().collect();
+                set.items.push(match class(&name) {
+                    Some(class) => Item::Class(class),
+                    None => Item::Invalid,
+                });
+                i = name_start + len + 2;
+                continue;
+            }
+        }
+        if low == '\\' && !flags.noescape {
+            i += 1;
+            low = match pattern.get(i) {
+                Some(&c) => c,
+                None => return None,
+            };
+        }
+        i += 1;
+        let mut high = low;
+        if pattern.get(i) == Some(&'-') && pattern.get(i + 1).map_or(false, |&c| c != ']') {
+            i += 1;
+            high = pattern[i];
+            if high == '\\' && !flags.noescape {
+                i += 1;
+                high = match pattern.get(i) {
+                    Some(&c) => c,
+                    None => return None,
+                };
+            }
+            i += 1;
+        }
+        set.items.push(Item::Range(low, high));
+    }
+}
+
+// The character class `[:name:]` stands for.
+fn class(name: &str) -> Option<fn(char) -> bool> {
+    Some(match name {
+        "alnum" => |c: char| c.is_alphanumeric(),
+        "alpha" => |c: char| c.is_alphabetic(),
+        "blank" => |c: char| c == ' ' || c == '\t',
+        "cntrl" => |c: char| c.is_control(),
+        "digit" => |c: char| c.is_digit(10),
+        "graph" => |c: char| !c.is_control() && !c.is_whitespace(),
+        "lower" => |c: char| c.is_lowercase(),
+        "print" => |c: char| !c.is_control(),
+        "punct" => |c: char| c.is_ascii_punctuation(),
+        "space" => |c: char| c.is_whitespace(),
+        "upper" => |c: char| c.is_uppercase(),
+        "xdigit" => |c: char| c.is_digit(16),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wildcards() {
+        let flags = Flags::default();
+        assert!(fnmatch("*.txt", "a.txt", flags));
+        assert!(fnmatch("*.txt", "dir/a.txt", flags));
+        assert!(!fnmatch("*.txt", "a.txt~", flags));
+        assert!(fnmatch("a?c", "abc", flags));
+        assert!(!fnmatch("a?c", "ac", flags));
+        assert!(fnmatch("**a*", "banana", flags));
+        assert!(fnmatch("", "", flags));
+        assert!(fnmatch(r"\*", "*", flags));
+        assert!(!fnmatch(r"\*", "a", flags));
+        assert!(fnmatch(r"\*", r"\a", Flags { noescape: true, ..flags }));
+        assert!(fnmatch("A*", "abc", Flags { casefold: true, ..flags }));
+    }
+
+    #[test]
+    fn test_brackets() {
+        let flags = Flags::default();
+        assert!(fnmatch("[a-c]x", "bx", flags));
+        assert!(!fnmatch("[!a-c]x", "bx", flags));
+        assert!(fnmatch("[^a-c]x", "dx", flags));
+        assert!(fnmatch("[]]", "]", flags));
+        assert!(fnmatch("[!]]", "a", flags));
+        assert!(fnmatch("[a-]", "-", flags));
+        assert!(fnmatch(r"[\]]", "]", flags));
+        assert!(fnmatch("[[:alpha:]_][[:digit:]]", "_1", flags));
+        assert!(!fnmatch("[[:upper:]]", "a", flags));
+        assert!(fnmatch("[A-Z]", "a", Flags { casefold: true, ..flags }));
+        assert!(!fnmatch("[[:nonsense:]]", "a", flags));
+        assert!(fnmatch("[ab", "[ab", flags));
+        assert!(!fnmatch("[ab", "a", flags));
+    }
+
+    #[test]
+    fn test_pathname_period() {
+        let pathname = Flags { pathname: true, ..Flags::default() };
+        assert!(!fnmatch("*", "a/b", pathname));
+        assert!(fnmatch("*/*", "a/b", pathname));
+        assert!(!fnmatch("a?b", "a/b", pathname));
+        assert!(!fnmatch("a[/]b", "a/b", pathname));
+        assert!(fnmatch("a", "a/b", Flags { leading_dir: true, ..pathname }));
+
+        let period = Flags { period: true, ..Flags::default() };
+        assert!(!fnmatch("*", ".h", period));
+        assert!(!fnmatch("[.]*", ".h", period));
+        assert!(fnmatch(".*", ".h", period));
+        assert!(fnmatch("*", "a/.h", period));
+        assert!(!fnmatch("*/*", "a/.h", Flags { pathname: true, ..period }));
+    }
+}
@@ -15,6 +15,20 @@ use std::io::{Error, ErrorKind};
 use std::io::Result as IOResult;
 use std::path::{Component, Path, PathBuf};
 use std::borrow::Cow;
+#[cfg(unix)]
+use std::ffi::CString;
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
+#[cfg(unix)]
+use std::os::unix::fs::{FileTypeExt, OpenOptionsExt};
+#[cfg(unix)]
+use std::os::unix::io::AsRawFd;
+#[cfg(unix)]
+use std::cmp;
+#[cfg(unix)]
+use std::thread;
+#[cfg(unix)]
+use std::time::Duration;
 
 pub fn resolve_relative_path<'a>(path: &'a Path) -> Cow<'a, Path> {
     if path.components().all(|e| e != Component::ParentDir) {
@@ -147,6 +161,75 @@ pub fn canonicalize<P: AsRef<Path>>(original: P, can_mode: CanonicalizeMode) ->
     Ok(result)
 }
 
+/// The metadata of `path`, or unless `follow` of the link itself if it is
+/// one, without mounting anything on an automount point the way
+/// `fs::metadata` would: that asks statx(2), which leaves them alone only
+/// when told to.  The file is opened with `O_PATH` instead, which neither
+/// triggers a mount nor needs permission to read, and asked through that.
+#[cfg(target_os = "linux")]
+pub fn metadata(path: &Path, follow: bool) -> IOResult<fs::Metadata> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let flags = if follow { libc::O_PATH } else { libc::O_PATH | libc::O_NOFOLLOW };
+    let file = try!(fs::OpenOptions::new().read(true).custom_flags(flags).open(path));
+    match file.metadata() {
+        // kernels before 3.6 can't tell anything about an O_PATH file
+        Err(ref e) if e.raw_os_error() == Some(libc::EBADF) => {
+            if follow { fs::metadata(path) } else { fs::symlink_metadata(path) }
+        }
+        result => result,
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn metadata(path: &Path, follow: bool) -> IOResult<fs::Metadata> {
+    if follow { fs::metadata(path) } else { fs::symlink_metadata(path) }
+}
+
+/// Change the owner and group of `path`, leaving either as it is when given
+/// as -1.  Unless `follow`, a symbolic link is changed itself rather than
+/// the file it points to.
+#[cfg(unix)]
+pub fn chown(path: &Path, uid: libc::uid_t, gid: libc::gid_t, follow: bool) -> IOResult<()> {
+    let path = try!(cstring(path));
+    let flags = if follow { 0 } else { libc::AT_SYMLINK_NOFOLLOW };
+    if unsafe { libc::fchownat(libc::AT_FDCWD, path.as_ptr(), uid, gid, flags) } == 0 {
+        Ok(())
+    } else {
+        Err(Error::last_os_error())
+    }
+}
+
+/// Change the permission bits of `path` to `mode`.  Unless `follow`, a
+/// symbolic link is changed itself rather than the file it points to,
+/// which fails with "Operation not supported" where links have no mode of
+/// their own, as on Linux.
+#[cfg(unix)]
+pub fn chmod(path: &Path, mode: u32, follow: bool) -> IOResult<()> {
+    // some C libraries refuse AT_SYMLINK_NOFOLLOW for anything, so it is
+    // only asked for when there is a link to take it
+    let nofollow = !follow && try!(fs::symlink_metadata(path)).file_type().is_symlink();
+    let path = try!(cstring(path));
+    let flags = if nofollow { libc::AT_SYMLINK_NOFOLLOW } else { 0 };
+    if unsafe { libc::fchmodat(libc::AT_FDCWD, path.as_ptr(), mode as libc::mode_t, flags) } == 0 {
+        Ok(())
+    } else {
+        let e = Error::last_os_error();
+        match e.raw_os_error() {
+            // worded as GNU has it, without the error number
+            Some(libc::EOPNOTSUPP) | Some(libc::ENOTSUP) if nofollow => {
+                Err(Error::new(ErrorKind::Other, "Operation not supported"))
+            }
+            _ => Err(e),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn cstring(path: &Path) -> IOResult<CString> {
+    CString::new(path.as_os_str().as_bytes()).map_err(|e| Error::new(ErrorKind::InvalidInput, e))
+}
+
 #[cfg(unix)]
 pub fn is_stdin_interactive() -> bool {
     unsafe { libc::isatty(libc::STDIN_FILENO) == 1 }
@@ -176,3 +259,253 @@ pub fn is_stderr_interactive() -> bool {
 pub fn is_stderr_interactive() -> bool {
     false
 }
+
+/// Open `path` for writing as `options` say, waiting for something to read
+/// it if it is a FIFO nothing reads yet, as
+/// [`open_outputs`](fn.open_outputs.html) does.
+pub fn open_output<P: AsRef<Path>>(path: P, options: &fs::OpenOptions) -> IOResult<fs::File> {
+    open_outputs(&[path], options).pop().unwrap()
+}
+
+/// Open `path` for a utility's output, as `-o FILE` does: created if there
+/// is nothing there and truncated if there is, unless `exclusive`, when an
+/// existing file is left alone and opening fails instead.
+pub fn create_output<P: AsRef<Path>>(path: P, exclusive: bool) -> IOResult<fs::File> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true);
+    if exclusive {
+        options.create_new(true);
+    } else {
+        options.create(true).truncate(true);
+    }
+    open_output(path, &options)
+}
+
+/// Open each of `paths` for writing as `options` say.
+///
+/// Opening a FIFO for writing waits until something opens it for reading,
+/// so opening outputs one after the other would deadlock if the reader of a
+/// later one had to be given data before it went on to open an earlier
+/// one, as `paste b a` reading from `tee a b` does.  FIFOs are opened
+/// without waiting instead, and those nothing reads yet are tried again
+/// until their readers turn up, whatever order that happens in.  A FIFO
+/// never read by anything is waited for forever, as it would be anyway.
+#[cfg(unix)]
+pub fn open_outputs<P: AsRef<Path>>(paths: &[P], options: &fs::OpenOptions) -> Vec<IOResult<fs::File>> {
+    let mut nonblocking = options.clone();
+    nonblocking.custom_flags(libc::O_NONBLOCK);
+
+    // None for a FIFO without a reader
+    let try_open = |path: &Path| -> Option<IOResult<fs::File>> {
+        match nonblocking.open(path) {
+            Ok(file) => Some(clear_nonblocking(&file).map(|_| file)),
+            Err(ref e) if e.raw_os_error() == Some(libc::ENXIO) => {
+                match fs::metadata(path) {
+                    Ok(ref metadata) if metadata.file_type().is_fifo() => None,
+                    // a device that isn't there, say, which fails the same
+                    // way when opened as usual
+                    _ => Some(options.open(path)),
+                }
+            }
+            Err(e) => Some(Err(e)),
+        }
+    };
+
+    let mut results: Vec<_> = paths.iter().map(|path| try_open(path.as_ref())).collect();
+    let mut delay = Duration::from_millis(1);
+    loop {
+        let pending: Vec<usize> = (0..results.len()).filter(|&i| results[i].is_none()).collect();
+        match pending.len() {
+            0 => break,
+            // nothing else to get out of the way of, so wait as usual
+            1 => {
+                let i = pending[0];
+                results[i] = Some(options.open(paths[i].as_ref()));
+            }
+            _ => {
+                thread::sleep(delay);
+                delay = cmp::min(delay * 2, Duration::from_millis(100));
+                for i in pending {
+                    results[i] = try_open(paths[i].as_ref());
+                }
+            }
+        }
+    }
+    results.into_iter().map(|result| result.unwrap()).collect()
+}
+
+#[cfg(windows)]
+pub fn open_outputs<P: AsRef<Path>>(paths: &[P], options: &fs::OpenOptions) -> Vec<IOResult<fs::File>> {
+    paths.iter().map(|path| options.open(path.as_ref())).collect()
+}
+
+// Writes to a FIFO opened without waiting would fail rather than wait for
+// the reader to catch up.
+#[cfg(unix)]
+fn clear_nonblocking(file: &fs::File) -> IOResult<()> {
+    let fd = file.as_raw_fd();
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+    if flags < 0 || unsafe { libc::fcntl(fd, libc::F_SETFL, flags & !libc::O_NONBLOCK) } < 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// What tells a file apart from all others, whatever it is called, so that
+/// an output can be checked against the inputs before it is written to.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FileId {
+    #[cfg(unix)]
+    dev: u64,
+    #[cfg(unix)]
+    ino: u64,
+    #[cfg(windows)]
+    path: PathBuf,
+}
+
+impl FileId {
+    /// The file `path` names, following symbolic links.
+    #[cfg(unix)]
+    pub fn of_path<P: AsRef<Path>>(path: P) -> IOResult<FileId> {
+        use std::os::unix::fs::MetadataExt;
+
+        let metadata = try!(fs::metadata(path));
+        Ok(FileId {
+            dev: metadata.dev(),
+            ino: metadata.ino(),
+        })
+    }
+
+    #[cfg(windows)]
+    pub fn of_path<P: AsRef<Path>>(path: P) -> IOResult<FileId> {
+        Ok(FileId { path: try!(fs::canonicalize(path)) })
+    }
+
+    /// The file standard input was opened from.
+    #[cfg(unix)]
+    pub fn of_stdin() -> IOResult<FileId> {
+        let mut stat: libc::stat = unsafe { ::std::mem::zeroed() };
+        if unsafe { libc::fstat(libc::STDIN_FILENO, &mut stat) } != 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(FileId {
+            dev: stat.st_dev as u64,
+            ino: stat.st_ino as u64,
+        })
+    }
+
+    #[cfg(windows)]
+    pub fn of_stdin() -> IOResult<FileId> {
+        Err(Error::new(ErrorKind::Other, "standard input has no path"))
+    }
+
+    /// The file an input operand names, `-` being standard input.
+    pub fn of_input(name: &str) -> IOResult<FileId> {
+        if name == "-" {
+            FileId::of_stdin()
+        } else {
+            FileId::of_path(name)
+        }
+    }
+}
+
+/// What controls access to a file beside its mode, as `ls -l` marks it
+/// after the permissions.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AccessControl {
+    /// the mode alone
+    Mode,
+    /// a security context, such as an SELinux or Smack label, too
+    SecurityContext,
+    /// an access control list besides, with or without a security context
+    Acl,
+}
+
+impl AccessControl {
+    /// What follows the permissions: nothing, `.` or `+`.
+    pub fn indicator(&self) -> &'static str {
+        match *self {
+            AccessControl::Mode => "",
+            AccessControl::SecurityContext => ".",
+            AccessControl::Acl => "+",
+        }
+    }
+}
+
+/// What controls access to `path`, found from its extended attributes.
+/// Files whose attributes cannot be listed, on file systems without them
+/// say, are taken to have only a mode.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn access_control(path: &Path, follow: bool) -> AccessControl {
+    let names = match xattr_names(path, follow) {
+        Ok(names) => names,
+        Err(_) => return AccessControl::Mode,
+    };
+    let has = |wanted: &[&[u8]]| names.iter().any(|name| wanted.contains(&&name[..]));
+    // the kernel keeps no ACL attribute for an ACL the mode says all of
+    if has(&[b"system.posix_acl_access", b"system.posix_acl_default", b"system.nfs4_acl"]) {
+        AccessControl::Acl
+    } else if has(&[b"security.selinux", b"security.SMACK64"]) {
+        AccessControl::SecurityContext
+    } else {
+        AccessControl::Mode
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn access_control(_path: &Path, _follow: bool) -> AccessControl {
+    AccessControl::Mode
+}
+
+/// The names of the extended attributes of `path`, or of the symbolic link
+/// itself unless `follow`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn xattr_names(path: &Path, follow: bool) -> IOResult<Vec<Vec<u8>>> {
+    let path = try!(CString::new(path.as_os_str().as_bytes()).map_err(|e| Error::new(ErrorKind::InvalidInput, e)));
+    let list = |buf: &mut [u8]| unsafe {
+        let buf_ptr = if buf.is_empty() { ::std::ptr::null_mut() } else { buf.as_mut_ptr() as *mut libc::c_char };
+        if follow {
+            libc::listxattr(path.as_ptr(), buf_ptr, buf.len())
+        } else {
+            libc::llistxattr(path.as_ptr(), buf_ptr, buf.len())
+        }
+    };
+    loop {
+        let size = list(&mut []);
+        if size < 0 {
+            return Err(Error::last_os_error());
+        }
+        let mut buf = vec![0; size as usize];
+        let size = list(&mut buf);
+        if size < 0 {
+            let err = Error::last_os_error();
+            // attributes were added since their size was asked for
+            if err.raw_os_error() == Some(libc::ERANGE) {
+                continue;
+            }
+            return Err(err);
+        }
+        buf.truncate(size as usize);
+        return Ok(buf.split(|&b| b == 0).filter(|name| !name.is_empty()).map(|name| name.to_vec()).collect());
+    }
+}
+
+/// Commit changes to the entries of the directory containing `path`, such as
+/// an unlink or a rename of `path`, to stable storage.  Without this, the
+/// change itself may not survive a power failure even though it has
+/// returned.
+#[cfg(unix)]
+pub fn sync_parent_dir(path: &Path) -> IOResult<()> {
+    let parent = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    try!(fs::File::open(parent)).sync_all()
+}
+
+/// Directories cannot be opened for syncing on Windows, where metadata
+/// updates are written through anyway.
+#[cfg(windows)]
+pub fn sync_parent_dir(_path: &Path) -> IOResult<()> {
+    Ok(())
+}
@@ -0,0 +1,153 @@
+// This file is part of the uutils coreutils package.
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+//
+
+//! Splitting text into the characters a reader sees.
+//!
+//! What `fold`, `fmt` and `cut -c` call a character is one Unicode code
+//! point by default.  A letter with an accent made of two code points, or
+//! an emoji made of several joined ones, is then counted as more than one
+//! character, and may be cut in two.  Counting in
+//! [`CharUnit::Grapheme`](enum.CharUnit.html) instead takes each extended
+//! grapheme cluster, as Unicode defines them, as one character.
+
+use std::str;
+use unicode_segmentation::{Graphemes, UnicodeSegmentation};
+
+/// What is counted as one character.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CharUnit {
+    /// Each code point, as a `char` holds.
+    CodePoint,
+    /// Each grapheme cluster: a letter with the marks on it, a whole emoji
+    /// sequence, or a carriage return and the newline after it.
+    Grapheme,
+}
+
+/// The characters of `s`, each as the slice of `s` it takes up.
+pub fn chars(s: &str, unit: CharUnit) -> Chars {
+    Chars {
+        inner: match unit {
+            CharUnit::CodePoint => Inner::CodePoints(s),
+            CharUnit::Grapheme => Inner::Graphemes(s.graphemes(true)),
+        },
+    }
+}
+
+/// How many characters `s` has.
+pub fn count(s: &str, unit: CharUnit) -> usize {
+    chars(s, unit).count()
+}
+
+/// The characters of `line`, which need not be UTF-8.  A byte that is not
+/// part of a valid sequence is a character on its own.
+pub fn byte_chars(line: &[u8], unit: CharUnit) -> ByteChars {
+    ByteChars {
+        valid: chars("", unit),
+        rest: line,
+        unit: unit,
+    }
+}
+
+/// An iterator over the characters of a string.
+pub struct Chars<'a> {
+    inner: Inner<'a>,
+}
+
+enum Inner<'a> {
+    CodePoints(&'a str),
+    Graphemes(Graphemes<'a>),
+}
+
+impl<'a> Iterator for Chars<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        match self.inner {
+            Inner::CodePoints(ref mut rest) => {
+                let len = match rest.chars().next() {
+                    Some(c) => c.len_utf8(),
+                    None => return None,
+                };
+                let (c, after) = rest.split_at(len);
+                *rest = after;
+                Some(c)
+            }
+            Inner::Graphemes(ref mut graphemes) => graphemes.next(),
+        }
+    }
+}
+
+/// An iterator over the characters of bytes that may not be UTF-8.
+pub struct ByteChars<'a> {
+    valid: Chars<'a>,
+    rest: &'a [u8],
+    unit: CharUnit,
+}
+
+impl<'a> Iterator for ByteChars<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        loop {
+            if let Some(c) = self.valid.next() {
+                return Some(c.as_bytes());
+            }
+            if self.rest.is_empty() {
+                return None;
+            }
+            let valid_len = match str::from_utf8(self.rest) {
+                Ok(_) => self.rest.len(),
+                Err(e) => e.valid_up_to(),
+            };
+            if valid_len == 0 {
+                let (byte, rest) = self.rest.split_at(1);
+                self.rest = rest;
+                return Some(byte);
+            }
+            let (valid, rest) = self.rest.split_at(valid_len);
+            self.valid = chars(str::from_utf8(valid).unwrap(), self.unit);
+            self.rest = rest;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // "e" and a combining acute accent, and a family joined by zero width joiners
+    const ACCENTED: &'static str = "e\u{301}";
+    const FAMILY: &'static str = "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}";
+
+    #[test]
+    fn test_code_points() {
+        let s = format!("a{}{}", ACCENTED, FAMILY);
+        let split: Vec<&str> = chars(&s, CharUnit::CodePoint).collect();
+        assert_eq!(split, ["a", "e", "\u{301}", "\u{1f468}", "\u{200d}", "\u{1f469}", "\u{200d}", "\u{1f467}"]);
+        assert_eq!(count("", CharUnit::CodePoint), 0);
+    }
+
+    #[test]
+    fn test_graphemes() {
+        let s = format!("a{}{}\r\n", ACCENTED, FAMILY);
+        let split: Vec<&str> = chars(&s, CharUnit::Grapheme).collect();
+        assert_eq!(split, ["a", ACCENTED, FAMILY, "\r\n"]);
+        assert_eq!(count(&s, CharUnit::Grapheme), 4);
+        // flags are pairs of regional indicators
+        assert_eq!(count("\u{1f1eb}\u{1f1f7}\u{1f1e9}\u{1f1ea}", CharUnit::Grapheme), 2);
+    }
+
+    #[test]
+    fn test_byte_chars() {
+        let mut line = b"\xffa".to_vec();
+        line.extend_from_slice(ACCENTED.as_bytes());
+        line.extend_from_slice(b"\xe2\x82b");
+        let split: Vec<&[u8]> = byte_chars(&line, CharUnit::Grapheme).collect();
+        assert_eq!(split, [&b"\xff"[..], b"a", ACCENTED.as_bytes(), b"\xe2", b"\x82", b"b"]);
+        assert_eq!(byte_chars(&line, CharUnit::CodePoint).count(), 7);
+        assert_eq!(byte_chars(b"", CharUnit::Grapheme).count(), 0);
+    }
+}
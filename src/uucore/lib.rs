@@ -1,5 +1,7 @@
 #[cfg(feature = "libc")]
 pub extern crate libc;
+#[cfg(feature = "unicode-segmentation")]
+extern crate unicode_segmentation;
 
 #[macro_use]
 mod macros;
@@ -17,6 +19,54 @@ pub mod utf8;
 pub mod encoding;
 #[cfg(feature = "parse_time")]
 pub mod parse_time;
+#[cfg(feature = "parse_number")]
+pub mod parse_number;
+#[cfg(feature = "copy")]
+pub mod copy;
+#[cfg(feature = "pipes")]
+pub mod pipes;
+#[cfg(feature = "compare")]
+pub mod compare;
+#[cfg(feature = "lines")]
+pub mod lines;
+#[cfg(feature = "numbering")]
+pub mod numbering;
+#[cfg(feature = "long_double")]
+pub mod long_double;
+#[cfg(feature = "numcompare")]
+pub mod numcompare;
+#[cfg(feature = "version_cmp")]
+pub mod version_cmp;
+#[cfg(feature = "fnmatch")]
+pub mod fnmatch;
+#[cfg(feature = "lscolors")]
+pub mod lscolors;
+#[cfg(feature = "quoting")]
+pub mod quoting;
+#[cfg(feature = "size")]
+pub mod size;
+#[cfg(feature = "skiptake")]
+pub mod skiptake;
+#[cfg(feature = "sparse")]
+pub mod sparse;
+#[cfg(feature = "backup")]
+pub mod backup;
+#[cfg(feature = "clock")]
+pub mod clock;
+#[cfg(feature = "timer")]
+pub mod timer;
+#[cfg(feature = "tz")]
+pub mod tz;
+#[cfg(feature = "filesystem")]
+pub mod filesystem;
+#[cfg(feature = "decompress")]
+pub mod decompress;
+#[cfg(feature = "preserve")]
+pub mod preserve;
+#[cfg(feature = "graphemes")]
+pub mod graphemes;
+#[cfg(any(test, feature = "faults"))]
+pub mod faults;
 
 #[cfg(all(not(windows), feature = "mode"))]
 pub mod mode;
@@ -28,8 +78,12 @@ pub mod utsname;
 pub mod entries;
 #[cfg(all(unix, feature = "process"))]
 pub mod process;
+#[cfg(all(unix, feature = "mounts"))]
+pub mod mounts;
 #[cfg(all(unix, not(target_os = "fuchsia"), feature = "signals"))]
 pub mod signals;
+#[cfg(all(target_os = "linux", feature = "sandbox"))]
+pub mod sandbox;
 
 #[cfg(all(windows, feature = "wide"))]
 pub mod wide;
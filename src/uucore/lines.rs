@@ -0,0 +1,225 @@
+// This file is part of the uutils coreutils package.
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+//
+
+//! Reading and writing lines of text, for the utilities that compare or
+//! rearrange whole lines.
+//!
+//! Lines end in a newline, or in a carriage return and newline for Windows
+//! text files, where a line ending in a newline alone is taken as well so
+//! that a carriage return is never left at the end of the last field.  The
+//! ending is left off the lines read and put back when they are written,
+//! not necessarily as it was: lines read with one ending can be written with
+//! another, as `--output-terminator` asks.
+
+use std::io::{self, BufRead, Error, ErrorKind, Write};
+use std::mem;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineEnding {
+    Newline,
+    CrLf,
+    /// a zero byte, as with `-z`
+    Nul,
+}
+
+impl LineEnding {
+    /// Carriage return and newline on Windows, newline elsewhere.
+    pub fn native() -> LineEnding {
+        if cfg!(windows) {
+            LineEnding::CrLf
+        } else {
+            LineEnding::Newline
+        }
+    }
+
+    /// What `--crlf` asks for if `crlf`, the native ending otherwise.
+    pub fn from_crlf(crlf: bool) -> LineEnding {
+        if crlf {
+            LineEnding::CrLf
+        } else {
+            LineEnding::native()
+        }
+    }
+
+    /// The ending named `name`, as given to `--output-terminator`.
+    pub fn from_name(name: &str) -> Option<LineEnding> {
+        match name {
+            "newline" => Some(LineEnding::Newline),
+            "crlf" => Some(LineEnding::CrLf),
+            "nul" => Some(LineEnding::Nul),
+            _ => None,
+        }
+    }
+
+    /// The ending as written.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            LineEnding::Newline => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Nul => "\0",
+        }
+    }
+
+    // The byte a line is read up to.
+    fn last_byte(&self) -> u8 {
+        match *self {
+            LineEnding::Nul => 0,
+            _ => b'\n',
+        }
+    }
+
+    // Take the ending off a line just read.
+    fn strip(&self, line: &mut Vec<u8>) {
+        if line.last() == Some(&self.last_byte()) {
+            line.pop();
+            if *self == LineEnding::CrLf && line.last() == Some(&b'\r') {
+                line.pop();
+            }
+        }
+    }
+}
+
+/// The lines of `reader`, each without its ending.
+pub fn lines<R: BufRead>(reader: R, ending: LineEnding) -> Lines<R> {
+    Lines {
+        reader: reader,
+        ending: ending,
+        line: Vec::new(),
+    }
+}
+
+/// Iterator over the lines of a reader, as made by [`lines`](fn.lines.html).
+///
+/// An error reading a line is given as it is, and the next call carries on
+/// with the same line rather than losing what was read of it, so that a
+/// caller can go on after an error that passes, such as
+/// `ErrorKind::WouldBlock`.
+pub struct Lines<R> {
+    reader: R,
+    ending: LineEnding,
+    // what was read of a line before reading it failed
+    line: Vec<u8>,
+}
+
+impl<R: BufRead> Iterator for Lines<R> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<io::Result<String>> {
+        match self.reader.read_until(self.ending.last_byte(), &mut self.line) {
+            Ok(0) if self.line.is_empty() => None,
+            Ok(_) => {
+                let mut line = mem::replace(&mut self.line, Vec::new());
+                self.ending.strip(&mut line);
+                Some(String::from_utf8(line).map_err(|_| {
+                    Error::new(ErrorKind::InvalidData, "stream did not contain valid UTF-8")
+                }))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// Writes lines to `inner`, each ended with the one ending whatever the
+/// lines read ended with.  What is written through `Write` goes as it is,
+/// so a line can be written in pieces and then ended.
+pub struct Writer<W> {
+    inner: W,
+    ending: LineEnding,
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(inner: W, ending: LineEnding) -> Writer<W> {
+        Writer {
+            inner: inner,
+            ending: ending,
+        }
+    }
+
+    /// Write `line` and end it.
+    pub fn write_line<T: AsRef<[u8]>>(&mut self, line: T) -> io::Result<()> {
+        try!(self.inner.write_all(line.as_ref()));
+        self.end_line()
+    }
+
+    /// End the line written so far.
+    pub fn end_line(&mut self) -> io::Result<()> {
+        self.inner.write_all(self.ending.as_str().as_bytes())
+    }
+}
+
+impl<W: Write> Write for Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use faults::Faulty;
+    use std::io::BufReader;
+
+    fn read(input: &str, ending: LineEnding) -> Vec<String> {
+        lines(input.as_bytes(), ending).map(|line| line.unwrap()).collect()
+    }
+
+    #[test]
+    fn test_newline() {
+        assert_eq!(read("a b\r\n\nc", LineEnding::Newline), vec!["a b\r", "", "c"]);
+        assert_eq!(read("", LineEnding::Newline), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_crlf() {
+        assert_eq!(read("a b\r\nc\n\r\r\nd\r", LineEnding::CrLf), vec!["a b", "c", "\r", "d\r"]);
+    }
+
+    #[test]
+    fn test_nul() {
+        assert_eq!(read("a\nb\0c\0", LineEnding::Nul), vec!["a\nb", "c"]);
+    }
+
+    #[test]
+    fn test_faults() {
+        let input = "first line\nsecond\n\nlast";
+        let expected = vec!["first line", "second", "", "last"];
+        let read = |reader| lines(BufReader::with_capacity(4, reader), LineEnding::Newline)
+            .collect::<io::Result<Vec<String>>>();
+        assert_eq!(read(Faulty::new(input.as_bytes()).short(3)).unwrap(), expected);
+        assert_eq!(read(Faulty::new(input.as_bytes()).short(1).interrupt(2)).unwrap(), expected);
+
+        let err = read(Faulty::new(input.as_bytes()).fail_at(15, ErrorKind::Other)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Other);
+
+        // after EAGAIN, the line goes on where it left off
+        let faulty = Faulty::new(input.as_bytes()).short(2).would_block(4);
+        let mut got = Vec::new();
+        let mut blocked = 0;
+        for line in lines(BufReader::with_capacity(4, faulty), LineEnding::Newline) {
+            match line {
+                Ok(line) => got.push(line),
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => blocked += 1,
+                Err(e) => panic!("{}", e),
+            }
+        }
+        assert!(blocked > 0);
+        assert_eq!(got, expected);
+    }
+
+    #[test]
+    fn test_writer() {
+        let mut out = Writer::new(Vec::new(), LineEnding::from_name("crlf").unwrap());
+        for line in lines("a\nb\0c".as_bytes(), LineEnding::Nul) {
+            write!(out, "[").unwrap();
+            out.write_line(line.unwrap()).unwrap();
+        }
+        assert_eq!(out.inner, b"[a\nb\r\n[c\r\n");
+    }
+}
@@ -0,0 +1,942 @@
+// This file is part of the uutils coreutils package.
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+//
+
+//! Numbers with the precision and range of C's `long double` on x86, for the
+//! utilities that read, compute with and print floating point numbers as GNU
+//! does with `strtold` and `printf("%Lg")`.
+//!
+//! The arithmetic is done in software, whatever the platform's own `long
+//! double` is, so that the same input gives the same output everywhere: a 64
+//! bit significand and exponents from -16382 to 16383, rounded to nearest
+//! with ties to even.  Numbers are printed from their exact binary value, as
+//! glibc does, so that `%.25Lg` of 0.1 shows where it stops being 0.1.
+
+use std::cmp::{self, Ordering};
+use std::ops::{Add, Mul, Neg};
+
+// A finite value is `mant * 2^exp`, with the top bit of `mant` set unless
+// `exp` is `MIN_EXP`, where the subnormal numbers and zero are.
+const MIN_EXP: i32 = -16445;
+const MAX_EXP: i32 = 16320;
+const TOP_BIT: u64 = 1 << 63;
+// A NaN's mantissa is the top bit, the quiet bit under it, and its payload.
+const QUIET_BIT: u64 = 1 << 62;
+
+#[derive(Clone, Copy, Debug)]
+enum Kind {
+    Finite(u64, i32),
+    Infinite,
+    // the payload, below the quiet bit
+    Nan(u64),
+}
+
+/// An x86 extended precision floating point number.
+#[derive(Clone, Copy, Debug)]
+pub struct LongDouble {
+    negative: bool,
+    kind: Kind,
+}
+
+/// What [`LongDouble::parse_prefix`](struct.LongDouble.html#method.parse_prefix) found.
+pub struct Parsed {
+    pub value: LongDouble,
+    /// how many bytes of the string make up the number, 0 if it does not
+    /// start with one
+    pub len: usize,
+    /// whether the number is too large or too small to be held, `value`
+    /// being infinite, zero or short of the usual precision
+    pub out_of_range: bool,
+}
+
+impl LongDouble {
+    pub fn zero() -> LongDouble {
+        LongDouble::finite(false, 0, MIN_EXP)
+    }
+
+    pub fn infinity() -> LongDouble {
+        LongDouble {
+            negative: false,
+            kind: Kind::Infinite,
+        }
+    }
+
+    // What x86 makes of an invalid operation, printed as "-nan".
+    fn default_nan() -> LongDouble {
+        LongDouble {
+            negative: true,
+            kind: Kind::Nan(0),
+        }
+    }
+
+    fn finite(negative: bool, mant: u64, exp: i32) -> LongDouble {
+        LongDouble {
+            negative: negative,
+            kind: Kind::Finite(mant, exp),
+        }
+    }
+
+    pub fn is_nan(&self) -> bool {
+        match self.kind {
+            Kind::Nan(_) => true,
+            _ => false,
+        }
+    }
+
+    /// The bits of a NaN's mantissa, from the top bit down, as `strtold`
+    /// sets them for `nan(n)`, or `None` for a number.
+    pub fn nan_mantissa(&self) -> Option<u64> {
+        match self.kind {
+            Kind::Nan(payload) => Some(TOP_BIT | QUIET_BIT | payload),
+            _ => None,
+        }
+    }
+
+    pub fn is_infinite(&self) -> bool {
+        match self.kind {
+            Kind::Infinite => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_finite(&self) -> bool {
+        match self.kind {
+            Kind::Finite(..) => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        match self.kind {
+            Kind::Finite(0, _) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the sign bit is set, as for -0 and "-nan".
+    pub fn is_sign_negative(&self) -> bool {
+        self.negative
+    }
+
+    /// Parse the number at the start of `s` as `strtold` does: after any
+    /// white space and a sign, a decimal number with an optional exponent,
+    /// a hexadecimal one such as `0x1.8p3`, `inf`, `infinity` or `nan`.
+    pub fn parse_prefix(s: &str) -> Parsed {
+        let bytes = s.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() && is_space(bytes[i]) {
+            i += 1;
+        }
+        let negative = match bytes.get(i) {
+            Some(&b'-') => {
+                i += 1;
+                true
+            }
+            Some(&b'+') => {
+                i += 1;
+                false
+            }
+            _ => false,
+        };
+        let rest = &bytes[i..];
+        let (value, len, out_of_range) = if starts_with_ignore_case(rest, b"inf") {
+            let len = if starts_with_ignore_case(&rest[3..], b"inity") { 8 } else { 3 };
+            (LongDouble::infinity(), len, false)
+        } else if starts_with_ignore_case(rest, b"nan") {
+            let mut len = 3;
+            let mut payload = 0;
+            if rest.get(3) == Some(&b'(') {
+                let chars = rest[4..]
+                    .iter()
+                    .take_while(|&&c| c.is_ascii_alphanumeric() || c == b'_')
+                    .count();
+                if rest.get(4 + chars) == Some(&b')') {
+                    len += chars + 2;
+                    payload = nan_payload(&rest[4..4 + chars]);
+                }
+            }
+            (LongDouble { negative: false, kind: Kind::Nan(payload) }, len, false)
+        } else if rest.len() > 2 && rest[0] == b'0' && (rest[1] == b'x' || rest[1] == b'X') &&
+                  (rest[2].is_ascii_hexdigit() ||
+                   rest[2] == b'.' && rest.get(3).map_or(false, |c| c.is_ascii_hexdigit())) {
+            let (value, len, out_of_range) = parse_hex(&rest[2..]);
+            (value, len + 2, out_of_range)
+        } else {
+            parse_decimal(rest)
+        };
+        if len == 0 {
+            return Parsed {
+                value: LongDouble::zero(),
+                len: 0,
+                out_of_range: false,
+            };
+        }
+        Parsed {
+            value: LongDouble { negative: negative, ..value },
+            len: i + len,
+            out_of_range: out_of_range,
+        }
+    }
+
+    /// Format as `printf` does with `%.*Lf`, `%.*Le`, `%.*Lg` and their
+    /// upper case forms, `conversion` being one of `fFeEgG`.
+    pub fn format(&self, conversion: char, precision: usize) -> String {
+        let upper = conversion.is_ascii_uppercase();
+        let mut s = String::new();
+        if self.negative {
+            s.push('-');
+        }
+        let (mant, exp) = match self.kind {
+            Kind::Finite(mant, exp) => (mant, exp),
+            Kind::Infinite => {
+                s.push_str(if upper { "INF" } else { "inf" });
+                return s;
+            }
+            Kind::Nan(_) => {
+                s.push_str(if upper { "NAN" } else { "nan" });
+                return s;
+            }
+        };
+        let (digits, point) = to_decimal(mant, exp);
+        match conversion {
+            'f' | 'F' => s.push_str(&fixed(&digits, point, precision)),
+            'e' | 'E' => s.push_str(&scientific(&digits, point, precision, upper)),
+            'g' | 'G' => {
+                let precision = cmp::max(precision, 1);
+                let exp10 = if digits.is_empty() {
+                    0
+                } else {
+                    let (_, carried) = round_digits(&digits, precision as i64);
+                    point - 1 + carried as i64
+                };
+                let formatted = if exp10 < precision as i64 && exp10 >= -4 {
+                    fixed(&digits, point, (precision as i64 - 1 - exp10) as usize)
+                } else {
+                    scientific(&digits, point, precision - 1, upper)
+                };
+                s.push_str(&strip_fraction_zeros(formatted));
+            }
+            _ => panic!("not a floating point conversion: {}", conversion),
+        }
+        s
+    }
+
+    // The magnitudes of two numbers that are not NaN compared.
+    fn cmp_magnitude(&self, other: &LongDouble) -> Ordering {
+        match (self.kind, other.kind) {
+            (Kind::Finite(a, ea), Kind::Finite(b, eb)) => (ea, a).cmp(&(eb, b)),
+            (Kind::Infinite, Kind::Infinite) => Ordering::Equal,
+            (Kind::Infinite, _) => Ordering::Greater,
+            _ => Ordering::Less,
+        }
+    }
+}
+
+impl From<u64> for LongDouble {
+    fn from(n: u64) -> LongDouble {
+        round(false, &Big::from_u64(n), 0, false).0
+    }
+}
+
+impl Neg for LongDouble {
+    type Output = LongDouble;
+
+    fn neg(self) -> LongDouble {
+        LongDouble { negative: !self.negative, ..self }
+    }
+}
+
+impl Add for LongDouble {
+    type Output = LongDouble;
+
+    fn add(self, other: LongDouble) -> LongDouble {
+        let ((a, ea), (b, eb)) = match (self.kind, other.kind) {
+            (Kind::Nan(_), _) => return self,
+            (_, Kind::Nan(_)) => return other,
+            (Kind::Infinite, Kind::Infinite) if self.negative != other.negative => {
+                return LongDouble::default_nan()
+            }
+            (Kind::Infinite, _) => return self,
+            (_, Kind::Infinite) => return other,
+            (Kind::Finite(a, ea), Kind::Finite(b, eb)) => ((a, ea), (b, eb)),
+        };
+        if a == 0 && b == 0 {
+            return LongDouble::finite(self.negative && other.negative, 0, MIN_EXP);
+        }
+        let exp = cmp::min(ea, eb);
+        let a = Big::from_u64(a).shl((ea - exp) as usize);
+        let b = Big::from_u64(b).shl((eb - exp) as usize);
+        if self.negative == other.negative {
+            return round(self.negative, &a.add(&b), exp as i64, false).0;
+        }
+        match a.cmp(&b) {
+            Ordering::Equal => LongDouble::zero(),
+            Ordering::Greater => round(self.negative, &a.sub(&b), exp as i64, false).0,
+            Ordering::Less => round(other.negative, &b.sub(&a), exp as i64, false).0,
+        }
+    }
+}
+
+impl Mul for LongDouble {
+    type Output = LongDouble;
+
+    fn mul(self, other: LongDouble) -> LongDouble {
+        let negative = self.negative != other.negative;
+        match (self.kind, other.kind) {
+            (Kind::Nan(_), _) => self,
+            (_, Kind::Nan(_)) => other,
+            (Kind::Infinite, _) | (_, Kind::Infinite) => {
+                if self.is_zero() || other.is_zero() {
+                    LongDouble::default_nan()
+                } else {
+                    LongDouble { negative: negative, kind: Kind::Infinite }
+                }
+            }
+            (Kind::Finite(a, ea), Kind::Finite(b, eb)) => {
+                let product = Big::from_u64(a).mul(&Big::from_u64(b));
+                round(negative, &product, ea as i64 + eb as i64, false).0
+            }
+        }
+    }
+}
+
+impl PartialEq for LongDouble {
+    fn eq(&self, other: &LongDouble) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for LongDouble {
+    fn partial_cmp(&self, other: &LongDouble) -> Option<Ordering> {
+        if self.is_nan() || other.is_nan() {
+            return None;
+        }
+        if self.is_zero() && other.is_zero() {
+            return Some(Ordering::Equal);
+        }
+        Some(match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => self.cmp_magnitude(other),
+            (true, true) => other.cmp_magnitude(self),
+        })
+    }
+}
+
+fn is_space(c: u8) -> bool {
+    c == b' ' || (c >= b'\t' && c <= b'\r')
+}
+
+fn starts_with_ignore_case(s: &[u8], prefix: &[u8]) -> bool {
+    s.len() >= prefix.len() && s[..prefix.len()].eq_ignore_ascii_case(prefix)
+}
+
+// The digits at the start of `s` that satisfy `is_digit`, with a point
+// among them, as (digits, number of them after the point, bytes read).
+fn scan_digits<F: Fn(u8) -> bool>(s: &[u8], is_digit: F) -> (Vec<u8>, usize, usize) {
+    let mut digits = Vec::new();
+    let mut fraction = 0;
+    let mut point = false;
+    let mut i = 0;
+    while i < s.len() {
+        if is_digit(s[i]) {
+            digits.push(s[i]);
+            if point {
+                fraction += 1;
+            }
+        } else if s[i] == b'.' && !point {
+            point = true;
+        } else {
+            break;
+        }
+        i += 1;
+    }
+    if digits.is_empty() {
+        return (digits, 0, 0);
+    }
+    (digits, fraction, i)
+}
+
+// The payload of `nan(chars)`: the characters read as `strtoull` reads a
+// C constant, if they are one, held at the largest that fits below the
+// quiet bit.
+fn nan_payload(chars: &[u8]) -> u64 {
+    let (digits, radix) = if chars.len() > 2 && chars[0] == b'0' && (chars[1] == b'x' || chars[1] == b'X') {
+        (&chars[2..], 16)
+    } else if chars.len() > 1 && chars[0] == b'0' {
+        (&chars[1..], 8)
+    } else {
+        (chars, 10)
+    };
+    let mut n: u64 = 0;
+    for &c in digits {
+        let digit = match (c as char).to_digit(radix) {
+            Some(digit) => digit as u64,
+            None => return 0,
+        };
+        n = n.checked_mul(radix as u64).and_then(|n| n.checked_add(digit)).unwrap_or(u64::max_value());
+    }
+    n & (QUIET_BIT - 1)
+}
+
+// An exponent of the given letter at the start of `s`, as (exponent, bytes
+// read), large ones held at a size that overflows or underflows anyway.
+fn scan_exponent(s: &[u8], letter: u8) -> (i64, usize) {
+    if s.is_empty() || s[0].to_ascii_lowercase() != letter {
+        return (0, 0);
+    }
+    let mut i = 1;
+    let negative = match s.get(1) {
+        Some(&b'-') => {
+            i += 1;
+            true
+        }
+        Some(&b'+') => {
+            i += 1;
+            false
+        }
+        _ => false,
+    };
+    let start = i;
+    let mut exp: i64 = 0;
+    while i < s.len() && s[i].is_ascii_digit() {
+        exp = cmp::min(exp * 10 + (s[i] - b'0') as i64, 1 << 40);
+        i += 1;
+    }
+    if i == start {
+        return (0, 0);
+    }
+    (if negative { -exp } else { exp }, i)
+}
+
+// The digits' value, in the given base.
+fn digits_value(digits: &[u8], base: u32) -> Big {
+    let mut n = Big::from_u64(0);
+    for &c in digits {
+        n.mul_small(base);
+        n.add_small((c as char).to_digit(base).unwrap());
+    }
+    n
+}
+
+fn parse_decimal(s: &[u8]) -> (LongDouble, usize, bool) {
+    let (digits, fraction, len) = scan_digits(s, |c| c.is_ascii_digit());
+    if len == 0 {
+        return (LongDouble::zero(), 0, false);
+    }
+    let (exp, exp_len) = scan_exponent(&s[len..], b'e');
+    let len = len + exp_len;
+    let significant = match digits.iter().position(|&c| c != b'0') {
+        Some(first) => &digits[first..],
+        None => return (LongDouble::zero(), len, false),
+    };
+    let exp = exp - fraction as i64;
+    // the value is at least 10^(magnitude - 1) and less than 10^magnitude
+    let magnitude = exp + significant.len() as i64;
+    if magnitude > 4933 {
+        return (LongDouble::infinity(), len, true);
+    }
+    if magnitude < -4950 {
+        return (LongDouble::zero(), len, true);
+    }
+    let n = digits_value(significant, 10);
+    let (value, out_of_range) = if exp >= 0 {
+        let mut n = n;
+        n.mul_pow(10, exp as u32);
+        round(false, &n, 0, false)
+    } else {
+        let mut divisor = Big::from_u64(1);
+        divisor.mul_pow(10, -exp as u32);
+        // enough bits in the quotient to round it, and any remainder
+        // tells which way a tie goes
+        let shift = cmp::max(66 + divisor.bit_len() as i64 - n.bit_len() as i64, 0);
+        let (quotient, remainder) = n.shl(shift as usize).div_rem(&divisor);
+        round(false, &quotient, -shift, !remainder.is_zero())
+    };
+    (value, len, out_of_range)
+}
+
+fn parse_hex(s: &[u8]) -> (LongDouble, usize, bool) {
+    let (digits, fraction, len) = scan_digits(s, |c| c.is_ascii_hexdigit());
+    let (exp, exp_len) = scan_exponent(&s[len..], b'p');
+    let len = len + exp_len;
+    let n = digits_value(&digits, 16);
+    if n.is_zero() {
+        return (LongDouble::zero(), len, false);
+    }
+    let exp = exp - 4 * fraction as i64;
+    let magnitude = exp + n.bit_len() as i64;
+    if magnitude > MAX_EXP as i64 + 64 {
+        return (LongDouble::infinity(), len, true);
+    }
+    if magnitude < MIN_EXP as i64 - 1 {
+        return (LongDouble::zero(), len, true);
+    }
+    let (value, out_of_range) = round(false, &n, exp, false);
+    (value, len, out_of_range)
+}
+
+// `n * 2^exp` rounded to the nearest long double, `sticky` saying whether
+// something more than nothing was left off below `n`.  Also whether it was
+// out of range: too large, or too small to keep its precision.
+fn round(negative: bool, n: &Big, exp: i64, sticky: bool) -> (LongDouble, bool) {
+    if n.is_zero() {
+        return (LongDouble::finite(negative, 0, MIN_EXP), sticky);
+    }
+    let mut exp_out = cmp::max(exp + n.bit_len() as i64 - 64, MIN_EXP as i64);
+    let shift = exp_out - exp;
+    let (mant, inexact) = if shift <= 0 {
+        (n.bits(0) << -shift, sticky)
+    } else {
+        let shift = shift as usize;
+        let mant = n.bits(shift);
+        let half = n.bit(shift - 1);
+        let below = sticky || n.any_below(shift - 1);
+        if half && (below || mant & 1 == 1) {
+            match mant.checked_add(1) {
+                Some(mant) => (mant, true),
+                None => {
+                    exp_out += 1;
+                    (TOP_BIT, true)
+                }
+            }
+        } else {
+            (mant, half || below)
+        }
+    };
+    if exp_out > MAX_EXP as i64 {
+        return (LongDouble { negative: negative, kind: Kind::Infinite }, true);
+    }
+    (LongDouble::finite(negative, mant, exp_out as i32), inexact && mant & TOP_BIT == 0)
+}
+
+// The exact value of `mant * 2^exp` as its decimal digits, without leading
+// or trailing zeros, and where the point goes: the value is 0.DIGITS times
+// 10 to that.  Zero has no digits.
+fn to_decimal(mant: u64, exp: i32) -> (Vec<u8>, i64) {
+    if mant == 0 {
+        return (Vec::new(), 0);
+    }
+    let zeros = mant.trailing_zeros();
+    let (mant, exp) = (mant >> zeros, exp as i64 + zeros as i64);
+    let (mut digits, point) = if exp >= 0 {
+        let digits = Big::from_u64(mant).shl(exp as usize).to_decimal();
+        let point = digits.len() as i64;
+        (digits, point)
+    } else {
+        // m / 2^k is m * 5^k / 10^k
+        let mut n = Big::from_u64(mant);
+        n.mul_pow(5, -exp as u32);
+        let digits = n.to_decimal();
+        let point = digits.len() as i64 + exp;
+        (digits, point)
+    };
+    while digits.last() == Some(&b'0') {
+        digits.pop();
+    }
+    (digits, point)
+}
+
+// The first `keep` of the digits, rounded to nearest with ties to even and
+// padded with zeros, and whether rounding up carried into a new digit in
+// front of them (there being `keep + 1` then).
+fn round_digits(digits: &[u8], keep: i64) -> (Vec<u8>, bool) {
+    if keep < 0 {
+        return (Vec::new(), false);
+    }
+    let keep = keep as usize;
+    let mut kept: Vec<u8> = digits.iter().take(keep).cloned().collect();
+    kept.resize(keep, b'0');
+    if keep >= digits.len() {
+        return (kept, false);
+    }
+    let odd = kept.last().map_or(false, |&c| (c - b'0') % 2 == 1);
+    let up = digits[keep] > b'5' || digits[keep] == b'5' && (digits.len() > keep + 1 || odd);
+    if !up {
+        return (kept, false);
+    }
+    for c in kept.iter_mut().rev() {
+        if *c == b'9' {
+            *c = b'0';
+        } else {
+            *c += 1;
+            return (kept, false);
+        }
+    }
+    kept.insert(0, b'1');
+    (kept, true)
+}
+
+// As "%.*f" of 0.DIGITS * 10^point.
+fn fixed(digits: &[u8], point: i64, precision: usize) -> String {
+    // the value times 10^precision, rounded to an integer
+    let (mut scaled, _) = round_digits(digits, point + precision as i64);
+    if scaled.len() < precision + 1 {
+        let zeros = precision + 1 - scaled.len();
+        scaled.splice(0..0, (0..zeros).map(|_| b'0'));
+    }
+    let mut s = String::from_utf8(scaled).unwrap();
+    if precision > 0 {
+        let point = s.len() - precision;
+        s.insert(point, '.');
+    }
+    s
+}
+
+// As "%.*e" of 0.DIGITS * 10^point.
+fn scientific(digits: &[u8], point: i64, precision: usize, upper: bool) -> String {
+    let (mut kept, exp10) = if digits.is_empty() {
+        (vec![b'0'; precision + 1], 0)
+    } else {
+        let (mut kept, carried) = round_digits(digits, precision as i64 + 1);
+        if carried {
+            kept.pop();
+        }
+        (kept, point - 1 + carried as i64)
+    };
+    if precision > 0 {
+        kept.insert(1, b'.');
+    }
+    let mut s = String::from_utf8(kept).unwrap();
+    s.push(if upper { 'E' } else { 'e' });
+    s.push(if exp10 < 0 { '-' } else { '+' });
+    s.push_str(&format!("{:02}", exp10.abs()));
+    s
+}
+
+// Take the zeros off the end of the fraction, and the point if that was all
+// there was to it, as "%g" does.
+fn strip_fraction_zeros(s: String) -> String {
+    let point = match s.find('.') {
+        Some(point) => point,
+        None => return s,
+    };
+    let end = s.find(|c| c == 'e' || c == 'E').unwrap_or(s.len());
+    let mut keep = end;
+    while keep > point + 1 && s.as_bytes()[keep - 1] == b'0' {
+        keep -= 1;
+    }
+    if keep == point + 1 {
+        keep = point;
+    }
+    format!("{}{}", &s[..keep], &s[end..])
+}
+
+/// An unsigned integer of any size, as 32 bit limbs from the least
+/// significant, with no zero limbs at the top.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Big(Vec<u32>);
+
+impl Big {
+    fn from_u64(n: u64) -> Big {
+        let mut big = Big(vec![n as u32, (n >> 32) as u32]);
+        big.trim();
+        big
+    }
+
+    fn trim(&mut self) {
+        while self.0.last() == Some(&0) {
+            self.0.pop();
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn bit_len(&self) -> usize {
+        match self.0.last() {
+            Some(&top) => self.0.len() * 32 - top.leading_zeros() as usize,
+            None => 0,
+        }
+    }
+
+    fn bit(&self, i: usize) -> bool {
+        self.0.get(i / 32).map_or(false, |&limb| limb >> (i % 32) & 1 == 1)
+    }
+
+    // The 64 bits from bit `i` up.
+    fn bits(&self, i: usize) -> u64 {
+        (0..64).filter(|&j| self.bit(i + j)).fold(0, |bits, j| bits | 1 << j)
+    }
+
+    fn any_below(&self, i: usize) -> bool {
+        let limbs = cmp::min(i / 32, self.0.len());
+        self.0[..limbs].iter().any(|&limb| limb != 0) ||
+        (i % 32 != 0 && self.0.get(i / 32).map_or(false, |&limb| limb << (32 - i % 32) != 0))
+    }
+
+    fn mul_small(&mut self, m: u32) {
+        let mut carry = 0;
+        for limb in &mut self.0 {
+            let product = *limb as u64 * m as u64 + carry;
+            *limb = product as u32;
+            carry = product >> 32;
+        }
+        if carry != 0 {
+            self.0.push(carry as u32);
+        }
+        self.trim();
+    }
+
+    fn add_small(&mut self, a: u32) {
+        let mut carry = a as u64;
+        for limb in &mut self.0 {
+            if carry == 0 {
+                return;
+            }
+            let sum = *limb as u64 + carry;
+            *limb = sum as u32;
+            carry = sum >> 32;
+        }
+        if carry != 0 {
+            self.0.push(carry as u32);
+        }
+    }
+
+    // Multiply by `base^exp`, a few factors of `base` at a time.
+    fn mul_pow(&mut self, base: u32, mut exp: u32) {
+        let mut chunk = base;
+        let mut chunk_exp = 1;
+        while let Some(next) = chunk.checked_mul(base) {
+            chunk = next;
+            chunk_exp += 1;
+        }
+        while exp >= chunk_exp {
+            self.mul_small(chunk);
+            exp -= chunk_exp;
+        }
+        self.mul_small(base.pow(exp));
+    }
+
+    fn shl(&self, bits: usize) -> Big {
+        if self.is_zero() {
+            return self.clone();
+        }
+        let mut limbs = vec![0; bits / 32];
+        let shift = bits % 32;
+        if shift == 0 {
+            limbs.extend_from_slice(&self.0);
+        } else {
+            let mut carry = 0;
+            for &limb in &self.0 {
+                limbs.push(limb << shift | carry);
+                carry = limb >> (32 - shift);
+            }
+            limbs.push(carry);
+        }
+        let mut big = Big(limbs);
+        big.trim();
+        big
+    }
+
+    fn add(&self, other: &Big) -> Big {
+        let mut limbs = Vec::with_capacity(cmp::max(self.0.len(), other.0.len()) + 1);
+        let mut carry = 0;
+        for i in 0..cmp::max(self.0.len(), other.0.len()) {
+            let sum = *self.0.get(i).unwrap_or(&0) as u64 + *other.0.get(i).unwrap_or(&0) as u64 +
+                      carry;
+            limbs.push(sum as u32);
+            carry = sum >> 32;
+        }
+        limbs.push(carry as u32);
+        let mut big = Big(limbs);
+        big.trim();
+        big
+    }
+
+    // `self - other`, `other` being no larger.
+    fn sub(&self, other: &Big) -> Big {
+        let mut big = self.clone();
+        big.sub_assign(other);
+        big
+    }
+
+    fn sub_assign(&mut self, other: &Big) {
+        let mut borrow = 0;
+        for (i, limb) in self.0.iter_mut().enumerate() {
+            let diff = *limb as i64 - *other.0.get(i).unwrap_or(&0) as i64 - borrow;
+            *limb = diff as u32;
+            borrow = if diff < 0 { 1 } else { 0 };
+        }
+        self.trim();
+    }
+
+    fn mul(&self, other: &Big) -> Big {
+        let mut limbs = vec![0u32; self.0.len() + other.0.len()];
+        for (i, &a) in self.0.iter().enumerate() {
+            let mut carry = 0;
+            for (j, &b) in other.0.iter().enumerate() {
+                let product = a as u64 * b as u64 + limbs[i + j] as u64 + carry;
+                limbs[i + j] = product as u32;
+                carry = product >> 32;
+            }
+            limbs[i + other.0.len()] = carry as u32;
+        }
+        let mut big = Big(limbs);
+        big.trim();
+        big
+    }
+
+    // Long division a bit at a time: quotient and remainder.
+    fn div_rem(&self, divisor: &Big) -> (Big, Big) {
+        let mut quotient = vec![0u32; self.0.len()];
+        let mut remainder = Big(Vec::new());
+        for i in (0..self.bit_len()).rev() {
+            remainder.mul_small(2);
+            if self.bit(i) {
+                remainder.add_small(1);
+            }
+            if remainder >= *divisor {
+                remainder.sub_assign(divisor);
+                quotient[i / 32] |= 1 << (i % 32);
+            }
+        }
+        let mut quotient = Big(quotient);
+        quotient.trim();
+        (quotient, remainder)
+    }
+
+    // The remainder after dividing in place.
+    fn div_rem_small(&mut self, d: u32) -> u32 {
+        let mut remainder = 0u64;
+        for limb in self.0.iter_mut().rev() {
+            let n = remainder << 32 | *limb as u64;
+            *limb = (n / d as u64) as u32;
+            remainder = n % d as u64;
+        }
+        self.trim();
+        remainder as u32
+    }
+
+    fn to_decimal(&self) -> Vec<u8> {
+        let mut n = self.clone();
+        let mut chunks = Vec::new();
+        while !n.is_zero() {
+            chunks.push(n.div_rem_small(1_000_000_000));
+        }
+        let mut s = format!("{}", chunks.pop().unwrap_or(0));
+        for chunk in chunks.iter().rev() {
+            s.push_str(&format!("{:09}", chunk));
+        }
+        s.into_bytes()
+    }
+}
+
+impl Ord for Big {
+    fn cmp(&self, other: &Big) -> Ordering {
+        self.0.len().cmp(&other.0.len()).then_with(|| self.0.iter().rev().cmp(other.0.iter().rev()))
+    }
+}
+
+impl PartialOrd for Big {
+    fn partial_cmp(&self, other: &Big) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> LongDouble {
+        let parsed = LongDouble::parse_prefix(s);
+        assert_eq!(parsed.len, s.len(), "{}", s);
+        parsed.value
+    }
+
+    fn fmt(s: &str, conversion: char, precision: usize) -> String {
+        parse(s).format(conversion, precision)
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(fmt("0.1", 'e', 6), "1.000000e-01");
+        assert_eq!(fmt("0.1", 'g', 6), "0.1");
+        assert_eq!(fmt("0.1", 'g', 25), "0.1000000000000000000013553");
+        assert_eq!(fmt("0.1", 'f', 20), "0.10000000000000000000");
+        assert_eq!(fmt("123456789012345678901234567890", 'e', 6), "1.234568e+29");
+        assert_eq!(fmt("3", 'g', 25), "3");
+        assert_eq!(fmt("-2.5", 'f', 0), "-2");
+        assert_eq!(fmt("3.5", 'f', 0), "4");
+        assert_eq!(fmt("0.5", 'f', 0), "0");
+        assert_eq!(fmt("9.9999", 'f', 2), "10.00");
+        assert_eq!(fmt("0.0001", 'g', 6), "0.0001");
+        assert_eq!(fmt("0.00001", 'G', 6), "1E-05");
+        assert_eq!(fmt("1e100", 'g', 6), "1e+100");
+        assert_eq!(fmt("100000", 'g', 6), "100000");
+        assert_eq!(fmt("999999.5", 'g', 6), "1e+06");
+        assert_eq!(fmt("0", 'e', 2), "0.00e+00");
+        assert_eq!(fmt("-0", 'g', 6), "-0");
+        assert_eq!(fmt("0x1p-2", 'g', 6), "0.25");
+        assert_eq!(fmt("0x.8", 'f', 1), "0.5");
+        assert_eq!(fmt("-inf", 'f', 6), "-inf");
+        assert_eq!(fmt("Infinity", 'E', 6), "INF");
+        assert_eq!(fmt("nan(123)", 'g', 6), "nan");
+        assert_eq!(fmt("-nan", 'g', 6), "-nan");
+    }
+
+    #[test]
+    fn test_limits() {
+        assert_eq!(fmt("1.18973149535723176502e+4932", 'g', 21), "1.18973149535723176502e+4932");
+        assert_eq!(fmt("3.64519953188247460253e-4951", 'g', 21), "3.64519953188247460253e-4951");
+        let parsed = LongDouble::parse_prefix("1e5000");
+        assert!(parsed.value.is_infinite() && parsed.out_of_range);
+        let parsed = LongDouble::parse_prefix("1e-5000");
+        assert!(parsed.value.is_zero() && parsed.out_of_range);
+        let parsed = LongDouble::parse_prefix("1e-4940");
+        assert_eq!(parsed.value.format('g', 6), "1e-4940");
+        assert!(parsed.out_of_range);
+        assert!(!LongDouble::parse_prefix("1e-4930").out_of_range);
+    }
+
+    #[test]
+    fn test_parse_prefix() {
+        let parsed = LongDouble::parse_prefix(" \t+1.5e3x");
+        assert_eq!(parsed.len, 8);
+        assert_eq!(parsed.value.format('g', 6), "1500");
+        assert_eq!(LongDouble::parse_prefix("1e").len, 1);
+        assert_eq!(LongDouble::parse_prefix("1e+").len, 1);
+        assert_eq!(LongDouble::parse_prefix("0x").len, 1);
+        assert_eq!(LongDouble::parse_prefix("0x1p").len, 3);
+        assert_eq!(LongDouble::parse_prefix("infinite").len, 3);
+        assert_eq!(LongDouble::parse_prefix("nan(").len, 3);
+        assert_eq!(LongDouble::parse_prefix(".").len, 0);
+        assert_eq!(LongDouble::parse_prefix("-x").len, 0);
+        assert_eq!(LongDouble::parse_prefix("").len, 0);
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!((parse("0.1") + parse("0.2")).format('g', 21), "0.300000000000000000011");
+        assert_eq!((parse("0.000001") * LongDouble::from(3)).format('g', 21),
+                   "3.00000000000000000011e-06");
+        assert_eq!((parse("1") + parse("-1")).format('g', 6), "0");
+        assert_eq!((parse("1e4000") * parse("1e4000")).format('g', 6), "inf");
+        assert_eq!((parse("inf") + parse("-inf")).format('g', 6), "-nan");
+        assert_eq!(LongDouble::from(u64::max_value()).format('f', 0), "18446744073709551615");
+        assert_eq!((-LongDouble::from(5) + parse("2.5")).format('f', 1), "-2.5");
+    }
+
+    #[test]
+    fn test_compare() {
+        assert!(parse("1e3") > parse("999.9"));
+        assert!(parse("-1e3") < parse("-999.9"));
+        assert!(parse("-inf") < parse("-1e4000"));
+        assert!(parse("0") == parse("-0"));
+        assert!(parse("1e-4950") > parse("0"));
+        assert!(parse("nan").partial_cmp(&parse("1")).is_none());
+        assert_eq!(parse("nan").nan_mantissa(), Some(0xc000_0000_0000_0000));
+        assert_eq!(parse("-nan(123)").nan_mantissa(), Some(0xc000_0000_0000_007b));
+        assert_eq!(parse("nan(0x10)").nan_mantissa(), Some(0xc000_0000_0000_0010));
+        assert_eq!(parse("nan(010)").nan_mantissa(), Some(0xc000_0000_0000_0008));
+        assert_eq!(parse("nan(08)").nan_mantissa(), Some(0xc000_0000_0000_0000));
+        assert_eq!(parse("nan(0x4000000000000001)").nan_mantissa(), Some(0xc000_0000_0000_0001));
+        assert_eq!(parse("nan(99999999999999999999)").nan_mantissa(), Some(u64::max_value()));
+        assert_eq!(parse("1").nan_mantissa(), None);
+        assert!(parse("0.1") != parse("0.10000000000000000001"));
+        assert!(parse("0.1") == parse("0.100000000000000000001"));
+    }
+}
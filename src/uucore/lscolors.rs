@@ -0,0 +1,264 @@
+// This file is part of the uutils coreutils package.
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+//
+
+//! Parsing of the `LS_COLORS` environment variable, as produced by dircolors
+//! and consumed by ls.
+//!
+//! The variable is a colon-separated list of `KEY=VALUE` entries.  A key is
+//! either a two-letter file type code such as `di`, or a `*SUFFIX` pattern
+//! matched against the end of file names.  Values are SGR sequences, which may
+//! contain the escapes understood by GNU ls.
+
+use std::collections::HashMap;
+use std::env;
+
+/// Names that dircolors databases use for each file type code.
+pub static FILE_TYPES: &'static [(&'static str, &'static str)] = &[
+    ("normal", "no"),
+    ("norm", "no"),
+    ("file", "fi"),
+    ("reset", "rs"),
+    ("dir", "di"),
+    ("lnk", "ln"),
+    ("link", "ln"),
+    ("symlink", "ln"),
+    ("orphan", "or"),
+    ("missing", "mi"),
+    ("fifo", "pi"),
+    ("pipe", "pi"),
+    ("sock", "so"),
+    ("blk", "bd"),
+    ("block", "bd"),
+    ("chr", "cd"),
+    ("char", "cd"),
+    ("door", "do"),
+    ("exec", "ex"),
+    ("left", "lc"),
+    ("leftcode", "lc"),
+    ("right", "rc"),
+    ("rightcode", "rc"),
+    ("end", "ec"),
+    ("endcode", "ec"),
+    ("suid", "su"),
+    ("setuid", "su"),
+    ("sgid", "sg"),
+    ("setgid", "sg"),
+    ("sticky", "st"),
+    ("other_writable", "ow"),
+    ("owr", "ow"),
+    ("sticky_other_writable", "tw"),
+    ("owt", "tw"),
+    ("capability", "ca"),
+    ("multihardlink", "mh"),
+    ("clrtoeol", "cl"),
+];
+
+/// Used when `LS_COLORS` is not set.
+pub static DEFAULT_COLORS: &'static str = "rs=0:di=01;34:ln=01;36:mh=00:pi=40;33:so=01;35:do=01;35:bd=40;33;01:cd=40;33;01:or=40;31;01:mi=00:su=37;41:sg=30;43:ca=30;41:tw=30;42:ow=34;42:st=37;44:ex=01;32:*.tar=01;31:*.tgz=01;31:*.arc=01;31:*.arj=01;31:*.taz=01;31:*.lha=01;31:*.lz4=01;31:*.lzh=01;31:*.lzma=01;31:*.tlz=01;31:*.txz=01;31:*.tzo=01;31:*.t7z=01;31:*.zip=01;31:*.z=01;31:*.Z=01;31:*.dz=01;31:*.gz=01;31:*.lrz=01;31:*.lz=01;31:*.lzo=01;31:*.xz=01;31:*.bz2=01;31:*.bz=01;31:*.tbz=01;31:*.tbz2=01;31:*.tz=01;31:*.deb=01;31:*.rpm=01;31:*.jar=01;31:*.war=01;31:*.ear=01;31:*.sar=01;31:*.rar=01;31:*.alz=01;31:*.ace=01;31:*.zoo=01;31:*.cpio=01;31:*.7z=01;31:*.rz=01;31:*.cab=01;31:*.jpg=01;35:*.jpeg=01;35:*.gif=01;35:*.bmp=01;35:*.pbm=01;35:*.pgm=01;35:*.ppm=01;35:*.tga=01;35:*.xbm=01;35:*.xpm=01;35:*.tif=01;35:*.tiff=01;35:*.png=01;35:*.svg=01;35:*.svgz=01;35:*.mng=01;35:*.pcx=01;35:*.mov=01;35:*.mpg=01;35:*.mpeg=01;35:*.m2v=01;35:*.mkv=01;35:*.webm=01;35:*.ogm=01;35:*.mp4=01;35:*.m4v=01;35:*.mp4v=01;35:*.vob=01;35:*.qt=01;35:*.nuv=01;35:*.wmv=01;35:*.asf=01;35:*.rm=01;35:*.rmvb=01;35:*.flc=01;35:*.avi=01;35:*.fli=01;35:*.flv=01;35:*.gl=01;35:*.dl=01;35:*.xcf=01;35:*.xwd=01;35:*.yuv=01;35:*.cgm=01;35:*.emf=01;35:*.ogv=01;35:*.ogx=01;35:*.aac=00;36:*.au=00;36:*.flac=00;36:*.m4a=00;36:*.mid=00;36:*.midi=00;36:*.mka=00;36:*.mp3=00;36:*.mpc=00;36:*.ogg=00;36:*.ra=00;36:*.wav=00;36:*.oga=00;36:*.opus=00;36:*.spx=00;36:*.xspf=00;36:";
+
+/// Look up the file type code for a dircolors keyword, ignoring case.
+pub fn file_type_code(keyword: &str) -> Option<&'static str> {
+    let keyword = keyword.to_lowercase();
+    FILE_TYPES.iter().find(|&&(name, _)| name == keyword).map(|&(_, code)| code)
+}
+
+/// The sequences are bytes, as an escape in `LS_COLORS` can stand for any
+/// byte, such as the one-byte CSI of `\233`, which is not UTF-8.
+pub struct LsColors {
+    codes: HashMap<String, Vec<u8>>,
+    // in the order given, as later entries take precedence
+    suffixes: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl LsColors {
+    /// Parse a value in the format of `LS_COLORS`.
+    pub fn parse(spec: &str) -> Result<LsColors, String> {
+        let mut colors = LsColors {
+            codes: HashMap::new(),
+            suffixes: Vec::new(),
+        };
+        for entry in spec.split(':').filter(|e| !e.is_empty()) {
+            let (key, value) = match entry.find('=') {
+                Some(pos) => (&entry[..pos], &entry[pos + 1..]),
+                None => return Err(format!("missing '=' in '{}'", entry)),
+            };
+            let value = try!(unescape(value));
+            if key.starts_with('*') {
+                colors.suffixes.push((try!(unescape(&key[1..])), value));
+            } else if key.len() == 2 {
+                colors.codes.insert(key.to_owned(), value);
+            } else {
+                return Err(format!("unrecognized key '{}'", key));
+            }
+        }
+        Ok(colors)
+    }
+
+    /// Colors from the environment, or the defaults if `LS_COLORS` is unset.
+    pub fn from_env() -> Result<LsColors, String> {
+        match env::var("LS_COLORS") {
+            Ok(spec) => LsColors::parse(&spec),
+            Err(_) => LsColors::parse(DEFAULT_COLORS),
+        }
+    }
+
+    /// The sequence for a two-letter file type code.
+    pub fn code(&self, code: &str) -> Option<&[u8]> {
+        self.codes.get(code).map(|c| &c[..])
+    }
+
+    /// The sequence of the last suffix pattern that `name` ends with.
+    pub fn suffix(&self, name: &[u8]) -> Option<&[u8]> {
+        self.suffixes.iter()
+            .rev()
+            .find(|&&(ref suffix, _)| name.ends_with(suffix))
+            .map(|&(_, ref c)| &c[..])
+    }
+
+    /// Surround `name` with the escape sequences selecting `color`.
+    pub fn paint(&self, name: &[u8], color: &[u8]) -> Vec<u8> {
+        let mut painted = self.start(color);
+        painted.extend_from_slice(name);
+        painted.extend(self.end());
+        painted
+    }
+
+    /// The escape sequence that selects `color`.
+    pub fn start(&self, color: &[u8]) -> Vec<u8> {
+        let mut start = self.code("lc").unwrap_or(b"\x1b[").to_vec();
+        start.extend_from_slice(color);
+        start.extend_from_slice(self.code("rc").unwrap_or(b"m"));
+        start
+    }
+
+    /// The escape sequence that goes back to no color.
+    pub fn end(&self) -> Vec<u8> {
+        match self.code("ec") {
+            Some(end) => end.to_vec(),
+            None => self.start(self.code("rs").unwrap_or(b"0")),
+        }
+    }
+}
+
+/// Decode the backslash and caret escapes allowed in `LS_COLORS` values.
+fn unescape(s: &str) -> Result<Vec<u8>, String> {
+    let mut result = Vec::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                let escaped = match chars.next() {
+                    Some('a') => b'\x07',
+                    Some('b') => b'\x08',
+                    Some('e') => b'\x1b',
+                    Some('f') => b'\x0c',
+                    Some('n') => b'\n',
+                    Some('r') => b'\r',
+                    Some('t') => b'\t',
+                    Some('v') => b'\x0b',
+                    Some('?') => b'\x7f',
+                    Some('_') => b' ',
+                    Some(d @ '0'...'7') => {
+                        let mut value = d.to_digit(8).unwrap();
+                        for _ in 0..2 {
+                            match chars.peek().and_then(|c| c.to_digit(8)) {
+                                Some(digit) => {
+                                    value = value * 8 + digit;
+                                    chars.next();
+                                }
+                                None => break,
+                            }
+                        }
+                        value as u8
+                    }
+                    Some('x') | Some('X') => {
+                        let mut value = 0;
+                        for _ in 0..2 {
+                            match chars.peek().and_then(|c| c.to_digit(16)) {
+                                Some(digit) => {
+                                    value = value * 16 + digit;
+                                    chars.next();
+                                }
+                                None => break,
+                            }
+                        }
+                        value as u8
+                    }
+                    Some(other) => {
+                        push_char(&mut result, other);
+                        continue;
+                    }
+                    None => return Err("trailing backslash".to_owned()),
+                };
+                result.push(escaped);
+            }
+            '^' => {
+                match chars.next() {
+                    Some('?') => result.push(b'\x7f'),
+                    Some(c @ '@'...'~') => result.push((c as u8) & 0x1f),
+                    _ => return Err("invalid control character after '^'".to_owned()),
+                }
+            }
+            c => push_char(&mut result, c),
+        }
+    }
+    Ok(result)
+}
+
+fn push_char(bytes: &mut Vec<u8>, c: char) {
+    let mut buf = [0; 4];
+    bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_codes_and_suffixes() {
+        let colors = LsColors::parse("di=01;34:*.tar=01;31:*.gz=31:*.tar.gz=32").unwrap();
+        assert_eq!(colors.code("di"), Some(&b"01;34"[..]));
+        assert_eq!(colors.code("ln"), None);
+        assert_eq!(colors.suffix(b"a.tar"), Some(&b"01;31"[..]));
+        assert_eq!(colors.suffix(b"a.tar.gz"), Some(&b"32"[..]));
+        assert_eq!(colors.suffix(b"a.txt"), None);
+    }
+
+    #[test]
+    fn test_escapes() {
+        let colors = LsColors::parse(r"lc=\e[:rc=^[m:ec=\x1b[0m:no=\101\_").unwrap();
+        assert_eq!(colors.code("lc"), Some(&b"\x1b["[..]));
+        assert_eq!(colors.code("rc"), Some(&b"\x1bm"[..]));
+        assert_eq!(colors.code("no"), Some(&b"A "[..]));
+
+        // bytes past ASCII are themselves, as the one-byte CSI, and not UTF-8
+        let colors = LsColors::parse(r"lc=\233:rc=\x9bm:fi=é").unwrap();
+        assert_eq!(colors.code("lc"), Some(&b"\x9b"[..]));
+        assert_eq!(colors.code("rc"), Some(&b"\x9bm"[..]));
+        assert_eq!(colors.code("fi"), Some("é".as_bytes()));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(LsColors::parse("di").is_err());
+        assert!(LsColors::parse("dir=01").is_err());
+        assert!(LsColors::parse(r"di=01\").is_err());
+    }
+
+    #[test]
+    fn test_paint() {
+        let colors = LsColors::parse("di=01;34").unwrap();
+        assert_eq!(colors.paint(b"dir", b"01;34"), b"\x1b[01;34mdir\x1b[0m");
+        let colors = LsColors::parse("ec=END").unwrap();
+        assert_eq!(colors.paint(b"f", b"1"), b"\x1b[1mfEND");
+    }
+
+    #[test]
+    fn test_file_type_code() {
+        assert_eq!(file_type_code("DIR"), Some("di"));
+        assert_eq!(file_type_code("nonsense"), None);
+    }
+}
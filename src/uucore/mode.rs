@@ -8,6 +8,47 @@
 
 use std::error::Error;
 
+#[cfg(unix)]
+use libc::umask;
+
+#[cfg(target_os = "redox")]
+unsafe fn umask(_mask: u32) -> u32 {
+    // XXX Redox does not currently have umask
+    0
+}
+
+/// The file mode creation mask of the process.
+pub fn get_umask() -> u32 {
+    // the mask can only be read by setting it
+    let mask = unsafe { umask(0) };
+    unsafe { umask(mask) };
+    mask as u32
+}
+
+/// Calls `f` with the file mode creation mask set to `mask`, and then puts
+/// back the one there was, so that files `f` creates get exactly the mode
+/// they are created with.
+pub fn with_umask<T, F: FnOnce() -> T>(mask: u32, f: F) -> T {
+    let last_umask = unsafe { umask(mask as _) };
+    let result = f();
+    unsafe { umask(last_umask) };
+    result
+}
+
+/// The mode `mode` gives a file that would otherwise have `fperm`, as
+/// `mkfifo -m` and `mknod -m` take it: octal or symbolic changes, separated
+/// by commas and made in turn.
+pub fn parse_mode(mut fperm: u32, mode: &str, considering_dir: bool) -> Result<u32, String> {
+    for change in mode.split(',') {
+        fperm = if change.contains(|c: char| c.is_digit(10)) {
+            try!(parse_numeric(fperm, change))
+        } else {
+            try!(parse_symbolic(fperm, change, considering_dir))
+        };
+    }
+    Ok(fperm)
+}
+
 pub fn parse_numeric(fperm: u32, mut mode: &str) -> Result<u32, String> {
     let (op, pos) = parse_op(mode, Some('='))?;
     mode = mode[pos..].trim_left_matches('0');
@@ -29,23 +70,12 @@ pub fn parse_numeric(fperm: u32, mut mode: &str) -> Result<u32, String> {
 }
 
 pub fn parse_symbolic(mut fperm: u32, mut mode: &str, considering_dir: bool) -> Result<u32, String> {
-    #[cfg(unix)]
-    use libc::umask;
-
-    #[cfg(target_os = "redox")]
-    unsafe fn umask(_mask: u32) -> u32 {
-        // XXX Redox does not currently have umask
-        0
-    }
-
     let (mask, pos) = parse_levels(mode);
     if pos == mode.len() {
         return Err(format!("invalid mode ({})", mode));
     }
     let respect_umask = pos == 0;
-    let last_umask = unsafe {
-        umask(0)
-    };
+    let last_umask = get_umask();
     mode = &mode[pos..];
     while mode.len() > 0 {
         let (op, pos) = parse_op(mode, None)?;
@@ -62,9 +92,6 @@ pub fn parse_symbolic(mut fperm: u32, mut mode: &str, considering_dir: bool) ->
             _ => unreachable!()
         }
     }
-    unsafe {
-        umask(last_umask);
-    }
     Ok(fperm)
 }
 
@@ -126,4 +153,32 @@ fn parse_change(mode: &str, fperm: u32, considering_dir: bool) -> (u32, usize) {
         srwx = 0;
     }
     (srwx, pos)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symbolic_modes() {
+        with_umask(0o022, || {
+            assert_eq!(parse_mode(0o666, "u+x", false), Ok(0o766));
+            assert_eq!(parse_mode(0o666, "a-w", false), Ok(0o444));
+            assert_eq!(parse_mode(0o666, "g-r", false), Ok(0o626));
+            assert_eq!(parse_mode(0o666, "+x", false), Ok(0o777));
+            // without a who, the umask masks what is set
+            assert_eq!(parse_mode(0o000, "=rw", false), Ok(0o644));
+            assert_eq!(parse_mode(0o666, "a=r,u+w", false), Ok(0o644));
+            assert_eq!(parse_mode(0o666, "600,g+r", false), Ok(0o640));
+            assert_eq!(get_umask(), 0o022);
+        });
+    }
+
+    #[test]
+    fn test_numeric_modes() {
+        assert_eq!(parse_mode(0o666, "644", false), Ok(0o644));
+        assert_eq!(parse_mode(0o666, "+100", false), Ok(0o766));
+        assert_eq!(parse_mode(0o666, "-4", false), Ok(0o662));
+        assert!(parse_mode(0o666, "99", false).is_err());
+    }
+}
@@ -0,0 +1,439 @@
+// This file is part of the uutils coreutils package.
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+//
+
+//! Mount points, and the edges of file systems they make in a tree.
+//!
+//! `rm --one-file-system`, `cp -x` and `du -x` stay on the file system the
+//! tree they walk starts on, by asking a [`Boundary`](struct.Boundary.html)
+//! about each directory they come to.  A directory on another device is
+//! beyond it, and so is one something is mounted on, as a bind mount of a
+//! directory from the same file system does not change the device.
+//!
+//! The mount table itself is listed by [`mounts`](fn.mounts.html), and a
+//! [`MountFilter`](struct.MountFilter.html) picks the file systems worth
+//! reporting from it the way GNU df does by default, and with `-a`, `-l`,
+//! `-t` and `-x`.
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, BufRead, BufReader};
+use std::os::unix::ffi::OsStringExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::str;
+
+/// The edge of the file system a tree starts on.
+pub struct Boundary {
+    dev: u64,
+    // the device and inode numbers of the mount points on `dev`, which are
+    // what a directory something is mounted on has when it is looked at
+    mount_points: HashSet<(u64, u64)>,
+}
+
+impl Boundary {
+    /// The edge of the file system `root` is on, following it if it is a
+    /// symbolic link and `follow` is set.
+    pub fn of<P: AsRef<Path>>(root: P, follow: bool) -> io::Result<Boundary> {
+        let metadata = if follow { fs::metadata(root) } else { fs::symlink_metadata(root) };
+        Ok(Boundary::new(try!(metadata).dev()))
+    }
+
+    /// The edge of the file system on the device `dev`.
+    pub fn new(dev: u64) -> Boundary {
+        let mount_points = mount_points().unwrap_or_default().into_iter()
+            .filter_map(|path| fs::metadata(path).ok())
+            .filter(|metadata| metadata.dev() == dev)
+            .map(|metadata| (metadata.dev(), metadata.ino()))
+            .collect();
+        Boundary {
+            dev: dev,
+            mount_points: mount_points,
+        }
+    }
+
+    /// Whether the directory with the device and inode numbers `dev` and
+    /// `ino` is on another file system than the tree's root.
+    pub fn crosses(&self, dev: u64, ino: u64) -> bool {
+        dev != self.dev || self.mount_points.contains(&(dev, ino))
+    }
+}
+
+/// Where file systems are mounted, as the mount table lists them.
+pub fn mount_points() -> io::Result<HashSet<PathBuf>> {
+    Ok(try!(mounts()).into_iter().map(|mount| mount.mount_point).collect())
+}
+
+/// A file system mounted somewhere, as the mount table lists it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mount {
+    /// What is mounted, such as `/dev/sda1`, `server:/export` or `proc`.
+    pub device: String,
+    pub mount_point: PathBuf,
+    pub fs_type: String,
+    /// The directory of the file system that is mounted, `/` unless it is a
+    /// bind mount of part of it.  Only known from `/proc/self/mountinfo`.
+    pub root: Option<PathBuf>,
+    /// The major and minor numbers of the device, known from the same.
+    pub dev: Option<(u64, u64)>,
+}
+
+impl Mount {
+    /// Whether this is a pseudo file system with nothing stored on it, such
+    /// as `proc`, which df only lists with `-a`.  The types are GNU's.
+    pub fn is_dummy(&self) -> bool {
+        match &*self.fs_type {
+            "autofs" | "proc" | "subfs" | "debugfs" | "devpts" | "fusectl" | "fuse.portal" | "mqueue"
+            | "rpc_pipefs" | "sysfs" | "devfs" | "kernfs" | "ignore" => true,
+            "none" => !self.is_bind(),
+            _ => false,
+        }
+    }
+
+    /// Whether the file system is on another machine, which df leaves out
+    /// with `-l`.  The types are GNU's.
+    pub fn is_remote(&self) -> bool {
+        if self.device.contains(':') || self.device == "-hosts" {
+            return true;
+        }
+        match &*self.fs_type {
+            "smbfs" | "smb3" | "cifs" => self.device.starts_with("//"),
+            "acfs" | "afs" | "coda" | "auristorfs" | "fhgfs" | "gpfs" | "ibrix" | "ocfs2" | "vxfs" => true,
+            _ => false,
+        }
+    }
+
+    /// Whether this is a squashfs image mounted through a loop device, as
+    /// every snap package is, which is not worth listing by default.
+    pub fn is_loop_squashfs(&self) -> bool {
+        self.fs_type == "squashfs" && self.device.starts_with("/dev/loop")
+    }
+
+    fn is_bind(&self) -> bool {
+        self.root.as_ref().map_or(false, |root| root != Path::new("/"))
+    }
+
+    // Whether this should be listed rather than `other`, a mount of the
+    // same device: a real device wins over a name such as `tmpfs`, then
+    // the mount of the file system's top over a bind mount of part of it.
+    fn preferred_to(&self, other: &Mount) -> bool {
+        let real = |mount: &Mount| mount.device.contains('/');
+        let depth = |mount: &Mount| mount.root.as_ref().map_or(0, |root| root.components().count());
+        (real(self), depth(other)) > (real(other), depth(self))
+    }
+}
+
+/// All the mounted file systems, in the order they were mounted.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn mounts() -> io::Result<Vec<Mount>> {
+    match fs::File::open("/proc/self/mountinfo") {
+        Ok(file) => read_mounts(BufReader::new(file), true),
+        Err(_) => read_mounts(BufReader::new(try!(fs::File::open("/proc/mounts"))), false),
+    }
+}
+
+/// All the mounted file systems, as far as `/etc/mtab` tells, in the order
+/// they were mounted.  Without it, none are known.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn mounts() -> io::Result<Vec<Mount>> {
+    match fs::File::open("/etc/mtab") {
+        Ok(file) => read_mounts(BufReader::new(file), false),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+// The mounts in `table`, which is mountinfo if `mountinfo` is set, or else
+// in the format of mtab and /proc/mounts.
+fn read_mounts<R: BufRead>(table: R, mountinfo: bool) -> io::Result<Vec<Mount>> {
+    let mut mounts = Vec::new();
+    for line in table.split(b'\n') {
+        let line = try!(line);
+        let fields: Vec<&[u8]> = line.split(|&b| b == b' ').collect();
+        let mount = if mountinfo {
+            // the type and device follow the optional fields, ended by `-`
+            let rest = match fields.iter().skip(6).position(|&field| field == b"-") {
+                Some(end) => &fields[7 + end..],
+                None => continue,
+            };
+            if rest.len() < 2 {
+                continue;
+            }
+            Mount {
+                device: lossy(rest[1]),
+                mount_point: path(fields[4]),
+                fs_type: lossy(rest[0]),
+                root: Some(path(fields[3])),
+                dev: parse_dev(fields[2]),
+            }
+        } else {
+            if fields.len() < 3 {
+                continue;
+            }
+            Mount {
+                device: lossy(fields[0]),
+                mount_point: path(fields[1]),
+                fs_type: lossy(fields[2]),
+                root: None,
+                dev: None,
+            }
+        };
+        mounts.push(mount);
+    }
+    Ok(mounts)
+}
+
+// `MAJOR:MINOR`
+fn parse_dev(field: &[u8]) -> Option<(u64, u64)> {
+    let field = match str::from_utf8(field) {
+        Ok(field) => field,
+        Err(_) => return None,
+    };
+    let mut numbers = field.splitn(2, ':').map(|n| n.parse().ok());
+    match (numbers.next(), numbers.next()) {
+        (Some(Some(major)), Some(Some(minor))) => Some((major, minor)),
+        _ => None,
+    }
+}
+
+fn lossy(field: &[u8]) -> String {
+    String::from_utf8_lossy(&unescape(field)).into_owned()
+}
+
+fn path(field: &[u8]) -> PathBuf {
+    PathBuf::from(OsString::from_vec(unescape(field)))
+}
+
+/// Which mounted file systems to list.  By default these are the ones GNU
+/// df lists: each device once, leaving out pseudo file systems, mounts
+/// hidden by a later one on the same mount point, and squashfs images on
+/// loop devices.  File systems with no blocks at all are left out by df as
+/// well, which needs their sizes rather than the mount table.
+#[derive(Clone, Debug, Default)]
+pub struct MountFilter {
+    /// Keep every mount, hidden or not (`df -a`).  `local` and the types
+    /// still apply.
+    pub all: bool,
+    /// Only keep file systems on this machine (`df -l`).
+    pub local: bool,
+    /// Only keep file systems of these types, if there are any (`df -t`).
+    pub types: Vec<String>,
+    /// Leave out file systems of these types (`df -x`).
+    pub exclude_types: Vec<String>,
+}
+
+impl MountFilter {
+    pub fn new() -> MountFilter {
+        MountFilter::default()
+    }
+
+    /// The mounts among `mounts`, in the order of the mount table, that are
+    /// to be listed.
+    pub fn filter(&self, mounts: Vec<Mount>) -> Vec<Mount> {
+        let mut kept: Vec<Option<Mount>> = Vec::with_capacity(mounts.len());
+        let mut by_mount_point: HashMap<PathBuf, usize> = HashMap::new();
+        let mut by_dev: HashMap<(u64, u64), usize> = HashMap::new();
+        for mount in mounts {
+            if !self.selects(&mount) || (!self.all && (mount.is_dummy() || mount.is_loop_squashfs())) {
+                continue;
+            }
+            if self.all {
+                kept.push(Some(mount));
+                continue;
+            }
+            // a mount that wins over or hides an earlier one takes its place
+            let mut slot = None;
+            if let Some(dev) = mount.dev {
+                if let Some(&seen) = by_dev.get(&dev) {
+                    let keep_new = match kept[seen] {
+                        Some(ref seen) if seen.dev == Some(dev) => mount.preferred_to(seen),
+                        _ => true,
+                    };
+                    if !keep_new {
+                        continue;
+                    }
+                    slot = Some(seen);
+                }
+            }
+            // a later mount on the same point hides the earlier one
+            if let Some(&hidden) = by_mount_point.get(&mount.mount_point) {
+                match slot {
+                    Some(seen) if seen != hidden => kept[hidden] = None,
+                    _ => slot = Some(hidden),
+                }
+            }
+            let index = match slot {
+                Some(index) => index,
+                None => {
+                    kept.push(None);
+                    kept.len() - 1
+                }
+            };
+            if let Some(dev) = mount.dev {
+                by_dev.insert(dev, index);
+            }
+            by_mount_point.insert(mount.mount_point.clone(), index);
+            kept[index] = Some(mount);
+        }
+        kept.into_iter().flatten().collect()
+    }
+
+    /// Whether `mount` is of a type that is asked for, and local if only
+    /// those are, as a file system named on df's command line must be.
+    pub fn selects(&self, mount: &Mount) -> bool {
+        if self.local && mount.is_remote() {
+            return false;
+        }
+        if !self.types.is_empty() && !self.types.contains(&mount.fs_type) {
+            return false;
+        }
+        !self.exclude_types.contains(&mount.fs_type)
+    }
+}
+
+// The mount table writes spaces, tabs, newlines and backslashes in names as
+// three octal digits after a backslash.
+fn unescape(name: &[u8]) -> Vec<u8> {
+    let mut unescaped = Vec::with_capacity(name.len());
+    let mut i = 0;
+    while i < name.len() {
+        match name.get(i + 1..i + 4) {
+            Some(digits) if name[i] == b'\\' && digits.iter().all(|&d| b'0' <= d && d <= b'7') => {
+                unescaped.push(digits.iter().fold(0u8, |byte, &d| byte.wrapping_mul(8) + (d - b'0')));
+                i += 4;
+            }
+            _ => {
+                unescaped.push(name[i]);
+                i += 1;
+            }
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_mounts() {
+        let mountinfo = "22 1 8:1 / / rw,relatime shared:1 - ext4 /dev/sda1 rw\n\
+                         40 22 8:1 /home/a /mnt/with\\040space rw shared:1 - ext4 /dev/sda1 rw\n\
+                         41 22 0:5 / /back\\134slash rw - tmpfs tmpfs rw\n";
+        let mounts = read_mounts(mountinfo.as_bytes(), true).unwrap();
+        let mount_points: Vec<&Path> = mounts.iter().map(|mount| &*mount.mount_point).collect();
+        assert_eq!(mount_points, [Path::new("/"), Path::new("/mnt/with space"), Path::new("/back\\slash")]);
+        assert_eq!(mounts[1], Mount {
+            device: "/dev/sda1".to_owned(),
+            mount_point: PathBuf::from("/mnt/with space"),
+            fs_type: "ext4".to_owned(),
+            root: Some(PathBuf::from("/home/a")),
+            dev: Some((8, 1)),
+        });
+
+        let table = "/dev/sda1 / ext4 rw 0 0\nproc /proc proc rw 0 0\n";
+        let mounts = read_mounts(table.as_bytes(), false).unwrap();
+        assert_eq!(mounts[1].mount_point, Path::new("/proc"));
+        assert_eq!((&*mounts[1].fs_type, mounts[1].dev), ("proc", None));
+    }
+
+    fn mount(device: &str, mount_point: &str, fs_type: &str, root: &str, dev: (u64, u64)) -> Mount {
+        Mount {
+            device: device.to_owned(),
+            mount_point: PathBuf::from(mount_point),
+            fs_type: fs_type.to_owned(),
+            root: Some(PathBuf::from(root)),
+            dev: Some(dev),
+        }
+    }
+
+    #[test]
+    fn test_filter() {
+        let mounts = vec![mount("/dev/sda1", "/", "ext4", "/", (8, 1)),
+                          mount("proc", "/proc", "proc", "/", (0, 20)),
+                          mount("/dev/sda1", "/srv", "ext4", "/home/srv", (8, 1)),
+                          mount("tmpfs", "/tmp", "tmpfs", "/", (0, 30)),
+                          mount("tmpfs", "/tmp", "tmpfs", "/", (0, 31)),
+                          mount("/dev/loop0", "/snap/core/1", "squashfs", "/", (7, 0)),
+                          mount("server:/export", "/net", "nfs", "/", (0, 40))];
+        let mount_points = |filter: &MountFilter| -> Vec<PathBuf> {
+            filter.filter(mounts.clone()).into_iter().map(|mount| mount.mount_point).collect()
+        };
+        let paths = |paths: &[&str]| -> Vec<PathBuf> { paths.iter().map(PathBuf::from).collect() };
+
+        // one of each device, and only the last mount on /tmp
+        let mut filter = MountFilter::new();
+        assert_eq!(mount_points(&filter), paths(&["/", "/tmp", "/net"]));
+        assert_eq!(filter.filter(mounts.clone())[1].dev, Some((0, 31)));
+
+        filter.local = true;
+        assert_eq!(mount_points(&filter), paths(&["/", "/tmp"]));
+
+        let filter = MountFilter { all: true, ..MountFilter::new() };
+        assert_eq!(filter.filter(mounts.clone()), mounts);
+
+        let filter = MountFilter { all: true, types: vec!["ext4".to_owned()], ..MountFilter::new() };
+        assert_eq!(mount_points(&filter), paths(&["/", "/srv"]));
+
+        let filter = MountFilter { exclude_types: vec!["tmpfs".to_owned()], ..MountFilter::new() };
+        assert_eq!(mount_points(&filter), paths(&["/", "/net"]));
+
+        // a mount that is left out as a second one of its device does not
+        // hide what it is mounted on
+        let mounts = vec![mount("/dev/sda1", "/", "ext4", "/", (8, 1)),
+                          mount("tmpfs", "/x", "tmpfs", "/", (0, 30)),
+                          mount("/dev/sda1", "/x", "ext4", "/home", (8, 1))];
+        let kept = MountFilter::new().filter(mounts.clone());
+        assert_eq!(kept, &mounts[..2]);
+
+        // and one that hides another is listed where that one was
+        let mounts = vec![mount("tmpfs", "/x", "tmpfs", "/", (0, 30)),
+                          mount("/dev/sda1", "/", "ext4", "/", (8, 1)),
+                          mount("tmpfs", "/x", "tmpfs", "/", (0, 31))];
+        let kept = MountFilter::new().filter(mounts.clone());
+        assert_eq!(kept, [mounts[2].clone(), mounts[1].clone()]);
+    }
+
+    #[test]
+    fn test_mount_kinds() {
+        assert!(mount("proc", "/proc", "proc", "/", (0, 1)).is_dummy());
+        assert!(mount("none", "/x", "none", "/", (0, 1)).is_dummy());
+        assert!(!mount("none", "/x", "none", "/y", (0, 1)).is_dummy());
+        assert!(!mount("tmpfs", "/tmp", "tmpfs", "/", (0, 1)).is_dummy());
+
+        assert!(mount("server:/export", "/net", "nfs", "/", (0, 1)).is_remote());
+        assert!(mount("//server/share", "/net", "cifs", "/", (0, 1)).is_remote());
+        assert!(mount("fs", "/afs", "afs", "/", (0, 1)).is_remote());
+        assert!(!mount("/dev/sda1", "/", "ext4", "/", (0, 1)).is_remote());
+    }
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(unescape(b"a\\011b\\012"), b"a\tb\n");
+        assert_eq!(unescape(b"\\"), b"\\");
+        assert_eq!(unescape(b"\\09x"), b"\\09x");
+    }
+
+    #[test]
+    fn test_boundary() {
+        let here = fs::metadata(".").unwrap();
+        let boundary = Boundary::of(".", false).unwrap();
+        assert!(!boundary.crosses(here.dev(), here.ino()));
+        assert!(boundary.crosses(here.dev().wrapping_add(1), here.ino()));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_mount_points() {
+        let mount_points = mount_points().unwrap();
+        assert!(mount_points.contains(Path::new("/")));
+
+        // a mount point is beyond the edge, whatever device it is on
+        let root = fs::metadata("/").unwrap();
+        let boundary = Boundary::of("/", false).unwrap();
+        assert!(boundary.crosses(root.dev(), root.ino()));
+    }
+}
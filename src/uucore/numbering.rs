@@ -0,0 +1,201 @@
+// This file is part of the uutils coreutils package.
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+//
+
+//! Line numbers as `nl`, `cat -n` and `pr -n` put them before lines.
+//!
+//! A number fills a field at least `width` columns wide, justified as its
+//! [`NumberFormat`](enum.NumberFormat.html) says, and is followed by a
+//! separator.  By default this is `nl`'s `rn` format: six columns, numbers
+//! to the right, and a tab.
+
+use std::io::{self, Write};
+use std::str::FromStr;
+
+/// How a number is placed in its field, as `nl -n` names them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NumberFormat {
+    /// `ln`: to the left, with spaces after it.
+    Left,
+    /// `rn`: to the right, with spaces before it.
+    Right,
+    /// `rz`: to the right, with zeroes before it.
+    RightZero,
+}
+
+impl FromStr for NumberFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<NumberFormat, String> {
+        match s {
+            "ln" => Ok(NumberFormat::Left),
+            "rn" => Ok(NumberFormat::Right),
+            "rz" => Ok(NumberFormat::RightZero),
+            _ => Err(format!("invalid line numbering format: '{}'", s)),
+        }
+    }
+}
+
+/// How line numbers are written.
+#[derive(Clone, Debug)]
+pub struct LineNumbers {
+    format: NumberFormat,
+    width: usize,
+    separator: String,
+    radix: u32,
+}
+
+impl LineNumbers {
+    pub fn new() -> LineNumbers {
+        LineNumbers {
+            format: NumberFormat::Right,
+            width: 6,
+            separator: "\t".to_owned(),
+            radix: 10,
+        }
+    }
+
+    pub fn format(mut self, format: NumberFormat) -> LineNumbers {
+        self.format = format;
+        self
+    }
+
+    /// The least number of columns a number takes up.  Wider numbers are
+    /// written whole.
+    pub fn width(mut self, width: usize) -> LineNumbers {
+        self.width = width;
+        self
+    }
+
+    /// What is written after each number.
+    pub fn separator(mut self, separator: &str) -> LineNumbers {
+        self.separator = separator.to_owned();
+        self
+    }
+
+    /// The base numbers are written in, from 2 to 36, with lowercase
+    /// letters for digits beyond 9.
+    ///
+    /// # Panics
+    ///
+    /// If `radix` is outside that range.
+    pub fn radix(mut self, radix: u32) -> LineNumbers {
+        assert!(radix >= 2 && radix <= 36, "radix out of range: {}", radix);
+        self.radix = radix;
+        self
+    }
+
+    /// Write `number` in its field, and the separator.
+    pub fn write<W: Write>(&self, out: &mut W, number: u64) -> io::Result<()> {
+        let mut buf = [0; 64];
+        let digits = self.digits(number, &mut buf);
+        let fill = self.width.saturating_sub(digits.len());
+        match self.format {
+            NumberFormat::Left => {
+                try!(out.write_all(digits));
+                try!(write_fill(out, b' ', fill));
+            }
+            NumberFormat::Right => {
+                try!(write_fill(out, b' ', fill));
+                try!(out.write_all(digits));
+            }
+            NumberFormat::RightZero => {
+                try!(write_fill(out, b'0', fill));
+                try!(out.write_all(digits));
+            }
+        }
+        out.write_all(self.separator.as_bytes())
+    }
+
+    /// `number` in its field, and the separator, as `write` would write them.
+    pub fn render(&self, number: u64) -> String {
+        let mut out = Vec::new();
+        self.write(&mut out, number).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    // The digits of `number`, written to the end of `buf`.
+    fn digits<'a>(&self, mut number: u64, buf: &'a mut [u8; 64]) -> &'a [u8] {
+        let mut start = buf.len();
+        loop {
+            let digit = (number % self.radix as u64) as u8;
+            start -= 1;
+            buf[start] = if digit < 10 { b'0' + digit } else { b'a' + digit - 10 };
+            number /= self.radix as u64;
+            if number == 0 {
+                return &buf[start..];
+            }
+        }
+    }
+}
+
+impl Default for LineNumbers {
+    fn default() -> LineNumbers {
+        LineNumbers::new()
+    }
+}
+
+fn write_fill<W: Write>(out: &mut W, byte: u8, count: usize) -> io::Result<()> {
+    let fill = [byte; 32];
+    let mut left = count;
+    while left > 0 {
+        let n = if left < fill.len() { left } else { fill.len() };
+        try!(out.write_all(&fill[..n]));
+        left -= n;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default() {
+        assert_eq!(LineNumbers::new().render(1), "     1\t");
+        assert_eq!(LineNumbers::new().render(0), "     0\t");
+        assert_eq!(LineNumbers::new().render(1234567), "1234567\t");
+    }
+
+    #[test]
+    fn test_formats() {
+        let numbers = LineNumbers::new().width(4).separator("|");
+        assert_eq!(numbers.clone().format(NumberFormat::Left).render(42), "42  |");
+        assert_eq!(numbers.clone().format(NumberFormat::Right).render(42), "  42|");
+        assert_eq!(numbers.clone().format(NumberFormat::RightZero).render(42), "0042|");
+        // too wide for the field
+        assert_eq!(numbers.clone().format(NumberFormat::Left).render(12345), "12345|");
+        assert_eq!(numbers.clone().format(NumberFormat::RightZero).render(12345), "12345|");
+
+        assert_eq!("ln".parse(), Ok(NumberFormat::Left));
+        assert_eq!("rn".parse(), Ok(NumberFormat::Right));
+        assert_eq!("rz".parse(), Ok(NumberFormat::RightZero));
+        assert!("lz".parse::<NumberFormat>().is_err());
+    }
+
+    #[test]
+    fn test_width_and_separator() {
+        assert_eq!(LineNumbers::new().width(1).separator("").render(7), "7");
+        assert_eq!(LineNumbers::new().width(0).separator(": ").render(7), "7: ");
+        assert_eq!(LineNumbers::new().width(40).separator("").render(7), format!("{:>40}", 7));
+    }
+
+    #[test]
+    fn test_radix() {
+        let numbers = LineNumbers::new().width(0).separator("");
+        assert_eq!(numbers.clone().radix(16).render(255), "ff");
+        assert_eq!(numbers.clone().radix(8).render(8), "10");
+        assert_eq!(numbers.clone().radix(2).render(5), "101");
+        assert_eq!(numbers.clone().radix(36).render(35), "z");
+        assert_eq!(numbers.clone().radix(2).render(u64::max_value()), "1".repeat(64));
+        assert_eq!(LineNumbers::new().radix(16).format(NumberFormat::RightZero).render(10), "00000a\t");
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_radix_out_of_range() {
+        LineNumbers::new().radix(37);
+    }
+}
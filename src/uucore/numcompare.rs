@@ -0,0 +1,199 @@
+// This file is part of the uutils coreutils package.
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+//
+
+//! Comparing the numbers text starts with, as `sort -n`, `-g` and `-h` do.
+//!
+//! Each comparison skips the blanks before the number and ignores whatever
+//! follows it.  Text that does not start with a number is taken as zero by
+//! [`numeric`](fn.numeric.html) and [`human_numeric`](fn.human_numeric.html),
+//! and as less than any number by
+//! [`general_numeric`](fn.general_numeric.html).
+
+use long_double::LongDouble;
+use std::cmp::Ordering;
+
+/// Compare decimal numbers, with a sign and a fraction but no exponent, as
+/// they are written, so that no number is too long or too precise.
+pub fn numeric(a: &str, b: &str) -> Ordering {
+    Decimal::parse(skip_blanks(a)).0.cmp(&Decimal::parse(skip_blanks(b)).0)
+}
+
+/// Compare floating point numbers, as read by `strtold`: with exponents,
+/// in hexadecimal, infinities and NaNs.  Text that is not a number comes
+/// first, then NaNs, then the numbers from -inf to inf, -0 being equal to
+/// 0.  NaNs are ordered as GNU `sort` orders them, by their bytes in an
+/// x86's memory: `nan`, `-nan`, then those with payloads such as
+/// `nan(0x10)`.
+pub fn general_numeric(a: &str, b: &str) -> Ordering {
+    let pa = LongDouble::parse_prefix(a);
+    let pb = LongDouble::parse_prefix(b);
+    match (pa.len, pb.len) {
+        (0, 0) => return Ordering::Equal,
+        (0, _) => return Ordering::Less,
+        (_, 0) => return Ordering::Greater,
+        _ => {}
+    }
+    match pa.value.partial_cmp(&pb.value) {
+        Some(ordering) => ordering,
+        None => match (pa.value.is_nan(), pb.value.is_nan()) {
+            (true, true) => nan_bytes_order(&pa.value).cmp(&nan_bytes_order(&pb.value)),
+            (true, false) => Ordering::Less,
+            _ => Ordering::Greater,
+        },
+    }
+}
+
+// A NaN's place among the others as `memcmp` finds it: the mantissa comes
+// first in memory, from its lowest byte, and the sign bit last.
+fn nan_bytes_order(nan: &LongDouble) -> (u64, bool) {
+    (nan.nan_mantissa().unwrap_or(0).swap_bytes(), nan.is_sign_negative())
+}
+
+/// Compare sizes with SI suffixes, as `du -h` prints them, so that 2K comes
+/// before 1G.  Sizes go by their suffix first, from none through K (or k),
+/// M, G, T, P, E, Z to Y, and only then by the number.  A size of zero has
+/// no suffix, whatever follows it.
+pub fn human_numeric(a: &str, b: &str) -> Ordering {
+    let (a, b) = (skip_blanks(a), skip_blanks(b));
+    let (number_a, rest_a) = Decimal::parse(a);
+    let (number_b, rest_b) = Decimal::parse(b);
+    number_a.unit_order(rest_a).cmp(&number_b.unit_order(rest_b)).then(number_a.cmp(&number_b))
+}
+
+fn skip_blanks(s: &str) -> &str {
+    s.trim_left_matches(|c| c == ' ' || c == '\t' || c == '\n')
+}
+
+// A decimal number as written, without the zeros that change nothing.
+#[derive(Debug, PartialEq, Eq)]
+struct Decimal<'a> {
+    negative: bool,
+    integer: &'a str,
+    fraction: &'a str,
+}
+
+impl<'a> Decimal<'a> {
+    // The number `s` starts with, zero if none, and what follows it.
+    fn parse(s: &'a str) -> (Decimal<'a>, &'a str) {
+        let negative = s.starts_with('-');
+        let unsigned = if negative { &s[1..] } else { s };
+        let integer_len = unsigned.find(|c: char| !c.is_digit(10)).unwrap_or(unsigned.len());
+        let integer = &unsigned[..integer_len];
+        let mut rest = &unsigned[integer_len..];
+        let mut fraction = "";
+        if rest.starts_with('.') {
+            let fraction_len = rest[1..].find(|c: char| !c.is_digit(10)).unwrap_or(rest.len() - 1);
+            fraction = &rest[1..1 + fraction_len];
+            rest = &rest[1 + fraction_len..];
+        }
+        if integer.is_empty() && fraction.is_empty() {
+            return (Decimal::zero(), s);
+        }
+        let number = Decimal {
+            negative: negative,
+            integer: integer.trim_left_matches('0'),
+            fraction: fraction.trim_right_matches('0'),
+        };
+        if number.is_zero() {
+            (Decimal::zero(), rest)
+        } else {
+            (number, rest)
+        }
+    }
+
+    fn zero() -> Decimal<'static> {
+        Decimal {
+            negative: false,
+            integer: "",
+            fraction: "",
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.integer.is_empty() && self.fraction.is_empty()
+    }
+
+    // Where the suffix the number is followed by puts it among the others.
+    fn unit_order(&self, suffix: &str) -> i32 {
+        if self.is_zero() {
+            return 0;
+        }
+        let order = match suffix.chars().next() {
+            Some('K') | Some('k') => 1,
+            Some('M') => 2,
+            Some('G') => 3,
+            Some('T') => 4,
+            Some('P') => 5,
+            Some('E') => 6,
+            Some('Z') => 7,
+            Some('Y') => 8,
+            _ => 0,
+        };
+        if self.negative { -order } else { order }
+    }
+
+    fn cmp_magnitude(&self, other: &Decimal) -> Ordering {
+        self.integer.len().cmp(&other.integer.len())
+            .then(self.integer.cmp(other.integer))
+            .then(self.fraction.cmp(other.fraction))
+    }
+}
+
+impl<'a> Ord for Decimal<'a> {
+    fn cmp(&self, other: &Decimal) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, false) => self.cmp_magnitude(other),
+            (true, true) => other.cmp_magnitude(self),
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+        }
+    }
+}
+
+impl<'a> PartialOrd for Decimal<'a> {
+    fn partial_cmp(&self, other: &Decimal) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(compare: fn(&str, &str) -> Ordering, items: &[&'static str]) -> Vec<&'static str> {
+        let mut items = items.to_vec();
+        items.sort_by(|a, b| compare(a, b));
+        items
+    }
+
+    #[test]
+    fn test_numeric() {
+        assert_eq!(sorted(numeric, &["10", "9.5", "-3", " 2x", "-10.25", "abc", "-0.0", "000123456789012345678901234567890",
+                                     "123456789012345678901234567891", ".5", "0.50001"]),
+                   vec!["-10.25", "-3", "abc", "-0.0", ".5", "0.50001", " 2x", "9.5", "10",
+                        "000123456789012345678901234567890", "123456789012345678901234567891"]);
+        assert_eq!(numeric("1e5", "2"), Ordering::Less);
+        assert_eq!(numeric("-", "0"), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_general_numeric() {
+        assert_eq!(sorted(general_numeric, &["1e3", "abc", "-inf", "0x10", "nan", "999"]),
+                   vec!["abc", "nan", "-inf", "0x10", "999", "1e3"]);
+        assert_eq!(sorted(general_numeric, &["nan(0x10)", "-nan", "nan(123)", "nan", "-nan(5)", "nan(x)"]),
+                   vec!["nan", "nan(x)", "-nan", "-nan(5)", "nan(0x10)", "nan(123)"]);
+        assert_eq!(sorted(general_numeric, &["inf", "0x1p-1", "-0", "", "-1e400", "0.25", "4e-5000"]),
+                   vec!["", "-1e400", "-0", "4e-5000", "0.25", "0x1p-1", "inf"]);
+        assert_eq!(general_numeric("-0", "0"), Ordering::Equal);
+        assert_eq!(general_numeric(" 0x.8", "0.5"), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_human_numeric() {
+        assert_eq!(sorted(human_numeric, &["1G", "2K", "999", "1.5k", "-1M", "-5", "0M", "10T", "2M"]),
+                   vec!["-1M", "-5", "0M", "999", "1.5k", "2K", "2M", "1G", "10T"]);
+    }
+}
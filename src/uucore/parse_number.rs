@@ -0,0 +1,222 @@
+// This file is part of the uutils coreutils package.
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+//
+
+//! Parsing of numeric option arguments, with diagnostics worded as in GNU:
+//!
+//! ```text
+//! invalid number of lines: '10x'
+//! invalid number of bytes: '9999999999999999999999': Value too large for defined data type
+//! invalid number of columns: '0': Numerical result out of range
+//! ```
+//!
+//! A number may have leading blanks and a `+` sign.  When size suffixes are
+//! allowed, it may be followed by `b` (512), or by `K`, `M`, `G`, `T`, `P`,
+//! `E`, `Z` or `Y` (`k` and `m` too) for powers of 1024, which become powers
+//! of 1000 when followed by `B` and stay powers of 1024 when followed by
+//! `iB`.  A suffix on its own counts as one of it.  Where C's radix
+//! prefixes are allowed, as for device numbers, it may be in hexadecimal
+//! after `0x` or `0X`, or in octal after `0`.
+
+use std::usize;
+
+static TOO_LARGE: &'static str = "Value too large for defined data type";
+static OUT_OF_RANGE: &'static str = "Numerical result out of range";
+
+/// How to parse one kind of numeric argument.
+pub struct NumberArg {
+    what: &'static str,
+    suffixes: bool,
+    min: u64,
+    saturate: bool,
+    c_radix: bool,
+}
+
+impl NumberArg {
+    /// `what` the number is, as in "invalid `what`: '10x'".
+    pub fn new(what: &'static str) -> NumberArg {
+        NumberArg {
+            what: what,
+            suffixes: false,
+            min: 0,
+            saturate: false,
+            c_radix: false,
+        }
+    }
+
+    /// Allow size suffixes such as `K` and `MiB`.
+    pub fn suffixes(mut self, suffixes: bool) -> NumberArg {
+        self.suffixes = suffixes;
+        self
+    }
+
+    /// Reject numbers below `min`.
+    pub fn min(mut self, min: u64) -> NumberArg {
+        self.min = min;
+        self
+    }
+
+    /// Take numbers that are too large as the largest there is instead of
+    /// rejecting them.
+    pub fn saturate(mut self, saturate: bool) -> NumberArg {
+        self.saturate = saturate;
+        self
+    }
+
+    /// Read numbers in hexadecimal after `0x` or `0X` and in octal after
+    /// `0`, as C does.
+    pub fn c_radix(mut self, c_radix: bool) -> NumberArg {
+        self.c_radix = c_radix;
+        self
+    }
+
+    pub fn parse(&self, arg: &str) -> Result<u64, String> {
+        self.parse_max(arg, u64::max_value())
+    }
+
+    pub fn parse_usize(&self, arg: &str) -> Result<usize, String> {
+        self.parse_max(arg, usize::MAX as u64).map(|n| n as usize)
+    }
+
+    /// The diagnostic for `arg` not being a number at all.
+    pub fn invalid(&self, arg: &str) -> String {
+        format!("invalid {}: '{}'", self.what, arg)
+    }
+
+    fn parse_max(&self, arg: &str, max: u64) -> Result<u64, String> {
+        let number = arg.trim_left();
+        let number = if number.starts_with('+') { &number[1..] } else { number };
+        let (number, radix) = if !self.c_radix {
+            (number, 10)
+        } else if number.starts_with("0x") || number.starts_with("0X") {
+            (&number[2..], 16)
+        } else if number.starts_with('0') {
+            (number, 8)
+        } else {
+            (number, 10)
+        };
+        let digits = number.chars().take_while(|c| c.is_digit(radix)).count();
+        let (number, suffix) = number.split_at(digits);
+
+        let multiplier = if suffix.is_empty() {
+            Some(1)
+        } else if self.suffixes {
+            match multiplier(suffix) {
+                Some(Some(multiplier)) => Some(multiplier),
+                Some(None) => None,
+                None => return Err(self.invalid(arg)),
+            }
+        } else {
+            return Err(self.invalid(arg));
+        };
+        if number.is_empty() && suffix.is_empty() {
+            return Err(self.invalid(arg));
+        }
+
+        // only digits are left, so failing to parse them means overflow
+        let value = if number.is_empty() { Some(1) } else { u64::from_str_radix(number, radix).ok() };
+        match value.and_then(|value| multiplier.and_then(|m| value.checked_mul(m))) {
+            Some(value) if value > max => self.too_large(arg, max),
+            None => self.too_large(arg, max),
+            Some(value) if value < self.min => Err(format!("{}: {}", self.invalid(arg), OUT_OF_RANGE)),
+            Some(value) => Ok(value),
+        }
+    }
+
+    fn too_large(&self, arg: &str, max: u64) -> Result<u64, String> {
+        if self.saturate {
+            Ok(max)
+        } else {
+            Err(format!("{}: {}", self.invalid(arg), TOO_LARGE))
+        }
+    }
+}
+
+// What a size suffix multiplies by: `None` if it isn't one and `Some(None)`
+// if the multiplier doesn't fit in 64 bits.
+fn multiplier(suffix: &str) -> Option<Option<u64>> {
+    let letter = suffix.chars().next().unwrap();
+    let base = match &suffix[letter.len_utf8()..] {
+        "" | "iB" => 1024u64,
+        "B" => 1000,
+        _ => return None,
+    };
+    let power = match letter {
+        'b' => return Some(Some(512)),
+        'K' | 'k' => 1,
+        'M' | 'm' => 2,
+        'G' => 3,
+        'T' => 4,
+        'P' => 5,
+        'E' => 6,
+        'Z' => 7,
+        'Y' => 8,
+        _ => return None,
+    };
+    Some((0..power).fold(Some(1u64), |m, _| m.and_then(|m| m.checked_mul(base))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain() {
+        let lines = NumberArg::new("number of lines");
+        assert_eq!(lines.parse("0"), Ok(0));
+        assert_eq!(lines.parse("  +12"), Ok(12));
+        assert_eq!(lines.parse("18446744073709551615"), Ok(u64::max_value()));
+        assert_eq!(lines.parse("10x"), Err("invalid number of lines: '10x'".to_owned()));
+        assert_eq!(lines.parse("1K"), Err("invalid number of lines: '1K'".to_owned()));
+        assert_eq!(lines.parse("-1"), Err("invalid number of lines: '-1'".to_owned()));
+        assert_eq!(lines.parse(""), Err("invalid number of lines: ''".to_owned()));
+        assert_eq!(lines.parse("++1"), Err("invalid number of lines: '++1'".to_owned()));
+    }
+
+    #[test]
+    fn test_suffixes() {
+        let bytes = NumberArg::new("number of bytes").suffixes(true);
+        assert_eq!(bytes.parse("2b"), Ok(1024));
+        assert_eq!(bytes.parse("2k"), Ok(2048));
+        assert_eq!(bytes.parse("2KB"), Ok(2000));
+        assert_eq!(bytes.parse("2KiB"), Ok(2048));
+        assert_eq!(bytes.parse("3m"), Ok(3 * 1024 * 1024));
+        assert_eq!(bytes.parse("1EB"), Ok(1000000000000000000));
+        assert_eq!(bytes.parse("M"), Ok(1024 * 1024));
+        assert_eq!(bytes.parse("1g"), Err("invalid number of bytes: '1g'".to_owned()));
+        assert_eq!(bytes.parse("1KiBx"), Err("invalid number of bytes: '1KiBx'".to_owned()));
+        assert_eq!(bytes.parse("1Z"),
+                   Err("invalid number of bytes: '1Z': Value too large for defined data type".to_owned()));
+    }
+
+    #[test]
+    fn test_range() {
+        let columns = NumberArg::new("number of columns").min(1);
+        assert_eq!(columns.parse("1"), Ok(1));
+        assert_eq!(columns.parse("0"),
+                   Err("invalid number of columns: '0': Numerical result out of range".to_owned()));
+        assert_eq!(columns.parse("99999999999999999999"),
+                   Err("invalid number of columns: '99999999999999999999': \
+                        Value too large for defined data type".to_owned()));
+        let fields = NumberArg::new("field number").saturate(true);
+        assert_eq!(fields.parse_usize("99999999999999999999"), Ok(usize::MAX));
+    }
+
+    #[test]
+    fn test_c_radix() {
+        let major = NumberArg::new("major device number").c_radix(true);
+        assert_eq!(major.parse("10"), Ok(10));
+        assert_eq!(major.parse("010"), Ok(8));
+        assert_eq!(major.parse("0x1f"), Ok(31));
+        assert_eq!(major.parse("0XFF"), Ok(255));
+        assert_eq!(major.parse("0"), Ok(0));
+        assert_eq!(major.parse("08"), Err("invalid major device number: '08'".to_owned()));
+        assert_eq!(major.parse("0x"), Err("invalid major device number: '0x'".to_owned()));
+        assert_eq!(major.parse("0x1g"), Err("invalid major device number: '0x1g'".to_owned()));
+        assert!(major.parse("0x10000000000000000").is_err());
+        // without them, a leading zero is only that
+        assert_eq!(NumberArg::new("number").parse("010"), Ok(10));
+    }
+}
@@ -0,0 +1,146 @@
+// This file is part of the uutils coreutils package.
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+//
+
+//! Sizing copy buffers to the pipes they are copied through.
+//!
+//! A buffer smaller than the pipe it fills takes more system calls than it
+//! needs to, so where the system can tell how much a pipe holds
+//! (`F_GETPIPE_SZ`), a buffer for copying through pipes is made that big.
+//! Two variables in the environment tune this:
+//!
+//! - `UUTILS_PIPE_SIZE`: grow each pipe to hold this much (`F_SETPIPE_SZ`)
+//!   first, as far as the system allows.  Pipes are left as they are
+//!   without it, as they are shared with the processes at their other ends.
+//! - `UUTILS_BUFFER_SIZE`: use buffers of this size, whatever the pipes hold,
+//!   up to `MAX_BUFFER_SIZE`.
+//!
+//! Both take a number of bytes with an optional suffix such as `K` or `M`.
+//! A value that is not a size is ignored, as is anything the system refuses.
+
+#[cfg(target_os = "linux")]
+use super::libc;
+use parse_number::NumberArg;
+use std::cmp;
+use std::env;
+
+/// A buffer size copying through pipes never goes beyond.
+pub const MAX_BUFFER_SIZE: usize = 8 << 20;
+
+/// The size of buffer to copy through the file descriptors `fds` with:
+/// the most any pipe among them holds, but no less than `default`.  Pipes
+/// are grown first if `UUTILS_PIPE_SIZE` asks for it.
+pub fn buffer_size(default: usize, fds: &[i32]) -> usize {
+    let wanted = size_from_env("UUTILS_PIPE_SIZE");
+    let mut size = default;
+    for &fd in fds {
+        if let Some(wanted) = wanted {
+            grow_pipe(fd, wanted);
+        }
+        if let Some(capacity) = pipe_capacity(fd) {
+            size = cmp::max(size, cmp::min(capacity, MAX_BUFFER_SIZE));
+        }
+    }
+    requested_size(size_from_env("UUTILS_BUFFER_SIZE"), size)
+}
+
+// The buffer size `requested` asks for, within bounds, or else `size`.
+fn requested_size(requested: Option<usize>, size: usize) -> usize {
+    match requested {
+        None | Some(0) => size,
+        Some(requested) => cmp::min(requested, MAX_BUFFER_SIZE),
+    }
+}
+
+/// How much the pipe `fd` holds, or `None` if it is not a pipe or the
+/// system cannot tell.
+#[cfg(target_os = "linux")]
+pub fn pipe_capacity(fd: i32) -> Option<usize> {
+    match unsafe { libc::fcntl(fd, libc::F_GETPIPE_SZ) } {
+        -1 => None,
+        capacity => Some(capacity as usize),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn pipe_capacity(_: i32) -> Option<usize> {
+    None
+}
+
+/// Grow the pipe `fd` to hold at least `size`, or as much as the system
+/// lets it, and return how much it holds.  A pipe is never shrunk.
+#[cfg(target_os = "linux")]
+pub fn grow_pipe(fd: i32, size: usize) -> Option<usize> {
+    match pipe_capacity(fd) {
+        Some(capacity) if capacity < size => {}
+        capacity => return capacity,
+    }
+    let size = cmp::min(size, libc::c_int::max_value() as usize) as libc::c_int;
+    if unsafe { libc::fcntl(fd, libc::F_SETPIPE_SZ, size) } == -1 {
+        // beyond /proc/sys/fs/pipe-max-size, which only root may go
+        if let Some(max) = max_pipe_size() {
+            unsafe { libc::fcntl(fd, libc::F_SETPIPE_SZ, cmp::min(size, max)) };
+        }
+    }
+    pipe_capacity(fd)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn grow_pipe(_: i32, _: usize) -> Option<usize> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn max_pipe_size() -> Option<libc::c_int> {
+    use std::fs::File;
+    use std::io::Read;
+    let mut max = String::new();
+    match File::open("/proc/sys/fs/pipe-max-size").and_then(|mut file| file.read_to_string(&mut max)) {
+        Ok(_) => max.trim().parse().ok(),
+        Err(_) => None,
+    }
+}
+
+// The size the variable `name` gives, if any.
+fn size_from_env(name: &str) -> Option<usize> {
+    env::var(name).ok().and_then(|value| NumberArg::new("size").suffixes(true).min(1).parse_usize(&value).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_pipe_capacity() {
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        let capacity = pipe_capacity(fds[0]).unwrap();
+        assert!(capacity >= 4096);
+        assert_eq!(buffer_size(1, &fds), capacity);
+        assert_eq!(buffer_size(capacity * 2, &fds), capacity * 2);
+
+        // growing to more than it holds, but never less
+        let grown = grow_pipe(fds[1], capacity * 2).unwrap();
+        assert!(grown >= capacity);
+        assert_eq!(grow_pipe(fds[1], 1), Some(grown));
+
+        // anything else is not a pipe
+        assert_eq!(pipe_capacity(-1), None);
+        unsafe {
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+    }
+
+    #[test]
+    fn test_requested_size() {
+        assert_eq!(requested_size(None, 4096), 4096);
+        assert_eq!(requested_size(Some(0), 4096), 4096);
+        assert_eq!(requested_size(Some(1), 4096), 1);
+        assert_eq!(requested_size(Some(MAX_BUFFER_SIZE + 1), 4096), MAX_BUFFER_SIZE);
+        assert_eq!(requested_size(Some(usize::max_value()), 4096), MAX_BUFFER_SIZE);
+    }
+}
@@ -0,0 +1,292 @@
+// This file is part of the uutils coreutils package.
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+//
+
+//! Copying the attributes of one file to another, as selected by
+//! `--preserve=ATTR_LIST` and `--no-preserve=ATTR_LIST`.
+//!
+//! Ownership is changed first, since that clears the set-user-ID and
+//! set-group-ID bits, and timestamps last, since changing anything else
+//! may touch them.  Links are only recorded here; keeping hard links
+//! together is up to whoever walks the files.
+
+#[cfg(unix)]
+use super::libc;
+#[cfg(unix)]
+extern crate xattr;
+
+use std::fs::{self, Metadata};
+use std::io::{self, Error};
+use std::path::Path;
+#[cfg(unix)]
+use std::ffi::CString;
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+
+/// The attribute names, in the order GNU lists them.
+pub static NAMES: &'static [&'static str] = &["mode", "timestamps", "ownership", "links", "context", "xattr", "all"];
+
+#[cfg(unix)]
+static CONTEXT_XATTR: &'static str = "security.selinux";
+
+/// Which attributes to preserve.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Attributes {
+    pub mode: bool,
+    pub ownership: bool,
+    pub timestamps: bool,
+    pub links: bool,
+    pub context: bool,
+    pub xattr: bool,
+    /// Whether failing to preserve the context or extended attributes goes
+    /// unreported, as it does when they were only asked for with `all`.
+    /// Asking for either by name makes failing to preserve it an error
+    /// again; leaving one out does not.
+    pub best_effort: bool,
+}
+
+impl Attributes {
+    /// Nothing at all.
+    pub fn none() -> Attributes {
+        Attributes::default()
+    }
+
+    /// Mode, ownership and timestamps, as preserved by `cp -p` and a bare
+    /// `--preserve`.
+    pub fn basic() -> Attributes {
+        Attributes {
+            mode: true,
+            ownership: true,
+            timestamps: true,
+            ..Attributes::default()
+        }
+    }
+
+    /// Everything, as preserved by `cp -a`.
+    pub fn all() -> Attributes {
+        Attributes {
+            mode: true,
+            ownership: true,
+            timestamps: true,
+            links: true,
+            context: true,
+            xattr: true,
+            best_effort: true,
+        }
+    }
+
+    /// Add the attributes in the comma separated `list` (the argument of
+    /// `--preserve`) if `keep`, or remove them (as `--no-preserve` does)
+    /// otherwise.  The error is the whole diagnostic for a name that isn't
+    /// one of [`NAMES`](static.NAMES.html).
+    pub fn update(&mut self, list: &str, keep: bool) -> Result<(), String> {
+        for name in list.split(',') {
+            match name {
+                "mode" => self.mode = keep,
+                "timestamps" => self.timestamps = keep,
+                "ownership" => self.ownership = keep,
+                "links" => self.links = keep,
+                "context" => {
+                    self.context = keep;
+                    self.best_effort = self.best_effort && !keep;
+                }
+                "xattr" => {
+                    self.xattr = keep;
+                    self.best_effort = self.best_effort && !keep;
+                }
+                "all" if keep => *self = Attributes::all(),
+                "all" => *self = Attributes::none(),
+                _ => {
+                    let valid: Vec<String> = NAMES.iter().map(|name| format!("\n  - '{}'", name)).collect();
+                    return Err(format!("invalid argument '{}' for '--{}preserve'\nValid arguments are:{}",
+                                       name,
+                                       if keep { "" } else { "no-" },
+                                       valid.concat()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Give `dest` the selected attributes of `source`.  Unless `follow`,
+    /// a symbolic link is taken as itself rather than the file it points
+    /// to, and only its ownership and timestamps are copied.
+    pub fn preserve(&self, source: &Path, dest: &Path, follow: bool) -> io::Result<()> {
+        let metadata = try!(if follow { fs::metadata(source) } else { fs::symlink_metadata(source) });
+        let is_link = metadata.file_type().is_symlink();
+
+        if self.ownership {
+            try!(chown(dest, &metadata, follow).map_err(|e| context(e, "failed to preserve ownership for", dest)));
+        }
+        if (self.xattr || self.context) && !is_link {
+            let result = if follow {
+                fs::canonicalize(source).and_then(|source| self.copy_xattrs(&source, dest))
+            } else {
+                self.copy_xattrs(source, dest)
+            };
+            if !self.best_effort {
+                try!(result);
+            }
+        }
+        if self.mode && !is_link {
+            try!(fs::set_permissions(dest, metadata.permissions())
+                .map_err(|e| context(e, "preserving permissions for", dest)));
+        }
+        if self.timestamps {
+            try!(set_times(dest, &metadata, follow).map_err(|e| context(e, "preserving times for", dest)));
+        }
+        Ok(())
+    }
+
+    // The security context is kept in an extended attribute of its own, so
+    // that is the one copied for `context` and the one left out otherwise.
+    #[cfg(unix)]
+    fn copy_xattrs(&self, source: &Path, dest: &Path) -> io::Result<()> {
+        let names = try!(self::xattr::list(source).map_err(|e| context(e, "getting attributes of", source)));
+        for name in names {
+            let wanted = if name.as_bytes() == CONTEXT_XATTR.as_bytes() { self.context } else { self.xattr };
+            if !wanted {
+                continue;
+            }
+            if let Some(value) = try!(self::xattr::get(source, &name).map_err(|e| context(e, "getting attributes of", source))) {
+                try!(self::xattr::set(dest, &name, &value).map_err(|e| context(e, "setting attributes for", dest)));
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn copy_xattrs(&self, _: &Path, _: &Path) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn context(e: Error, what: &str, path: &Path) -> Error {
+    Error::new(e.kind(), format!("{} '{}': {}", what, path.display(), e))
+}
+
+#[cfg(unix)]
+fn cstring(path: &Path) -> io::Result<CString> {
+    CString::new(path.as_os_str().as_bytes()).map_err(|e| Error::new(io::ErrorKind::InvalidInput, e))
+}
+
+// Only the superuser may give a file away, so anyone else failing to is
+// not an error, though the group may still be one they belong to.
+#[cfg(unix)]
+fn chown(path: &Path, metadata: &Metadata, follow: bool) -> io::Result<()> {
+    let path = try!(cstring(path));
+    let chown = |uid, gid| unsafe {
+        if follow {
+            libc::chown(path.as_ptr(), uid, gid)
+        } else {
+            libc::lchown(path.as_ptr(), uid, gid)
+        }
+    };
+    if chown(metadata.uid(), metadata.gid()) == 0 {
+        return Ok(());
+    }
+    let e = Error::last_os_error();
+    let privileged = unsafe { libc::geteuid() } == 0;
+    match e.raw_os_error() {
+        Some(libc::EPERM) | Some(libc::EINVAL) if !privileged => {
+            chown(!0, metadata.gid());
+            Ok(())
+        }
+        _ => Err(e),
+    }
+}
+
+#[cfg(not(unix))]
+fn chown(_: &Path, _: &Metadata, _: bool) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn set_times(path: &Path, metadata: &Metadata, follow: bool) -> io::Result<()> {
+    let path = try!(cstring(path));
+    let times = [libc::timespec {
+                     tv_sec: metadata.atime() as libc::time_t,
+                     tv_nsec: metadata.atime_nsec() as libc::c_long,
+                 },
+                 libc::timespec {
+                     tv_sec: metadata.mtime() as libc::time_t,
+                     tv_nsec: metadata.mtime_nsec() as libc::c_long,
+                 }];
+    let flags = if follow { 0 } else { libc::AT_SYMLINK_NOFOLLOW };
+    if unsafe { libc::utimensat(libc::AT_FDCWD, path.as_ptr(), times.as_ptr(), flags) } == 0 {
+        Ok(())
+    } else {
+        Err(Error::last_os_error())
+    }
+}
+
+#[cfg(not(unix))]
+fn set_times(_: &Path, _: &Metadata, _: bool) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs::File;
+    #[cfg(unix)]
+    use std::os::unix::fs::PermissionsExt;
+    use std::process;
+
+    #[test]
+    fn test_update() {
+        let mut attributes = Attributes::none();
+        attributes.update("mode,timestamps", true).unwrap();
+        assert_eq!(attributes, Attributes { mode: true, timestamps: true, ..Attributes::none() });
+        attributes.update("mode", false).unwrap();
+        assert_eq!(attributes, Attributes { timestamps: true, ..Attributes::none() });
+
+        let mut attributes = Attributes::none();
+        attributes.update("all", true).unwrap();
+        attributes.update("ownership,links", false).unwrap();
+        assert!(attributes.best_effort && attributes.xattr && !attributes.ownership && !attributes.links);
+        attributes.update("context", false).unwrap();
+        assert!(attributes.best_effort && attributes.xattr && !attributes.context);
+        attributes.update("xattr", true).unwrap();
+        assert!(!attributes.best_effort);
+        attributes.update("all", false).unwrap();
+        assert_eq!(attributes, Attributes::none());
+    }
+
+    #[test]
+    fn test_update_invalid() {
+        let err = Attributes::none().update("mode,bogus", true).unwrap_err();
+        assert!(err.starts_with("invalid argument 'bogus' for '--preserve'\nValid arguments are:\n  - 'mode'\n"));
+        assert!(err.ends_with("\n  - 'all'"));
+        let err = Attributes::none().update("", false).unwrap_err();
+        assert!(err.starts_with("invalid argument '' for '--no-preserve'"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_preserve() {
+        let dir = env::temp_dir();
+        let source = dir.join(format!("uucore-preserve-src-{}", process::id()));
+        let dest = dir.join(format!("uucore-preserve-dest-{}", process::id()));
+        File::create(&source).unwrap();
+        File::create(&dest).unwrap();
+        fs::set_permissions(&source, fs::Permissions::from_mode(0o640)).unwrap();
+        let times = [libc::timespec { tv_sec: 1000000000, tv_nsec: 5 }, libc::timespec { tv_sec: 1234567890, tv_nsec: 0 }];
+        let path = cstring(&source).unwrap();
+        assert_eq!(unsafe { libc::utimensat(libc::AT_FDCWD, path.as_ptr(), times.as_ptr(), 0) }, 0);
+
+        Attributes::basic().preserve(&source, &dest, true).unwrap();
+        let metadata = fs::metadata(&dest).unwrap();
+        assert_eq!(metadata.mode() & 0o7777, 0o640);
+        assert_eq!((metadata.atime(), metadata.atime_nsec()), (1000000000, 5));
+        assert_eq!(metadata.mtime(), 1234567890);
+
+        fs::remove_file(&source).unwrap();
+        fs::remove_file(&dest).unwrap();
+    }
+}
@@ -9,12 +9,15 @@
 
 use super::libc;
 use libc::{c_int, pid_t, uid_t, gid_t};
+use std::cmp;
 use std::fmt;
+use std::fs;
 use std::io;
 use std::process::Child;
 use std::sync::{Arc, Condvar, Mutex};
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::Duration;
+use timer::{Clock, Deadline};
 
 pub fn geteuid() -> uid_t {
     unsafe {
@@ -134,17 +137,143 @@ impl ChildExt for Child {
         // Main thread waits
         let &(ref lock, ref cvar) = &*state;
         let mut exitstatus = lock.lock().unwrap();
-        // Condvar::wait_timeout_ms() can wake too soon, in this case wait again
-        let start = Instant::now();
+        // Condvar::wait_timeout_ms() can wake too soon, in this case wait again.
+        // It does not count time spent suspended either, so it is only
+        // trusted for a second at a time.
+        let deadline = Deadline::after(Clock::Boottime, timeout);
         loop {
             if let Some(exitstatus) = exitstatus.take() {
                 return exitstatus.map(Some);
             }
-            if start.elapsed() >= timeout {
+            if deadline.has_passed() {
                 return Ok(None);
             }
-            let cvar_timeout = timeout - start.elapsed();
+            let cvar_timeout = cmp::min(deadline.remaining(), Duration::from_secs(1));
             exitstatus = cvar.wait_timeout(exitstatus, cvar_timeout).unwrap().0;
         }
     }
 }
+
+/// Which descriptors above standard error a command is started with.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FdPolicy {
+    /// Every one that is open and not set to close on exec, as exec leaves
+    /// them.
+    All,
+    /// Only these; any other is set to close on exec.
+    Only(Vec<c_int>),
+}
+
+impl FdPolicy {
+    /// The descriptors above standard error that are open now.  Taken
+    /// before the utility opens anything, these are the ones it was started
+    /// with, and a command it runs is given just those, as it would be if
+    /// the utility were not in between.
+    pub fn inherited() -> FdPolicy {
+        FdPolicy::Only(open_fds().into_iter().filter(|&fd| fd > 2).collect())
+    }
+}
+
+/// Get the descriptors ready for a command to be started on them.
+///
+/// Standard input, output and error are made blocking.  A program that
+/// made one nonblocking and exited without setting it back leaves it so
+/// for every process sharing it, and most commands fail on the `EAGAIN`
+/// they then get.  Descriptors above standard error that `policy` does not
+/// pass on are set to close on exec.
+pub fn prepare_fds(policy: &FdPolicy) -> io::Result<()> {
+    for fd in 0..3 {
+        try!(make_blocking(fd));
+    }
+    if let FdPolicy::Only(ref keep) = *policy {
+        for fd in open_fds() {
+            if fd > 2 && !keep.contains(&fd) {
+                try!(set_cloexec(fd));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The descriptors open in this process, lowest first.
+pub fn open_fds() -> Vec<c_int> {
+    let listed = fs::read_dir("/proc/self/fd").or_else(|_| fs::read_dir("/dev/fd")).map(|dir| {
+        dir.filter_map(|entry| entry.ok().and_then(|entry| entry.file_name().to_str().and_then(|name| name.parse().ok())))
+            .collect::<Vec<c_int>>()
+    });
+    let mut fds = match listed {
+        Ok(fds) => fds,
+        Err(_) => {
+            let max = unsafe { libc::sysconf(libc::_SC_OPEN_MAX) };
+            (0..if max > 0 && max < 65536 { max as c_int } else { 65536 }).collect()
+        }
+    };
+    // the one the directory was read through is closed again by now
+    fds.retain(|&fd| unsafe { libc::fcntl(fd, libc::F_GETFD) } != -1);
+    fds.sort();
+    fds
+}
+
+// Clears O_NONBLOCK on fd, if it is open.
+fn make_blocking(fd: c_int) -> io::Result<()> {
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+    if flags == -1 || flags & libc::O_NONBLOCK == 0 {
+        return Ok(());
+    }
+    if unsafe { libc::fcntl(fd, libc::F_SETFL, flags & !libc::O_NONBLOCK) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn set_cloexec(fd: c_int) -> io::Result<()> {
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFD) };
+    if flags == -1 || unsafe { libc::fcntl(fd, libc::F_SETFD, flags | libc::FD_CLOEXEC) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::os::unix::io::AsRawFd;
+
+    #[test]
+    fn test_make_blocking() {
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        let flags = unsafe { libc::fcntl(fds[0], libc::F_GETFL) };
+        unsafe { libc::fcntl(fds[0], libc::F_SETFL, flags | libc::O_NONBLOCK) };
+        make_blocking(fds[0]).unwrap();
+        assert_eq!(unsafe { libc::fcntl(fds[0], libc::F_GETFL) } & libc::O_NONBLOCK, 0);
+        unsafe {
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+        // a closed descriptor is left alone
+        make_blocking(fds[0]).unwrap();
+    }
+
+    #[test]
+    fn test_prepare_fds() {
+        let file = File::open("/dev/null").unwrap();
+        let fd = file.as_raw_fd();
+        assert!(open_fds().contains(&fd));
+        assert!(!open_fds().windows(2).any(|pair| pair[0] >= pair[1]));
+        match FdPolicy::inherited() {
+            FdPolicy::Only(fds) => assert!(fds.contains(&fd) && fds.iter().all(|&fd| fd > 2)),
+            FdPolicy::All => unreachable!(),
+        }
+
+        // as left open on exec by something careless
+        unsafe { libc::fcntl(fd, libc::F_SETFD, 0) };
+        prepare_fds(&FdPolicy::All).unwrap();
+        assert_eq!(unsafe { libc::fcntl(fd, libc::F_GETFD) } & libc::FD_CLOEXEC, 0);
+        prepare_fds(&FdPolicy::Only(vec![fd])).unwrap();
+        assert_eq!(unsafe { libc::fcntl(fd, libc::F_GETFD) } & libc::FD_CLOEXEC, 0);
+        prepare_fds(&FdPolicy::Only(vec![])).unwrap();
+        assert_eq!(unsafe { libc::fcntl(fd, libc::F_GETFD) } & libc::FD_CLOEXEC, libc::FD_CLOEXEC);
+    }
+}
@@ -0,0 +1,320 @@
+// This file is part of the uutils coreutils package.
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+//
+
+//! Quoting of names in diagnostics and listings, following the styles of
+//! GNU's quotearg.
+//!
+//! Diagnostics use two of them: `quote()` for arbitrary operands, which uses
+//! typographic quotes in UTF-8 locales, and `quote_path()` for file names,
+//! which always produces something that can be pasted back into a shell.
+
+use std::env;
+use std::ffi::OsStr;
+use std::str;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum QuotingStyle {
+    /// the name as it is
+    Literal,
+    /// quoted for the shell when needed
+    Shell,
+    /// always quoted for the shell
+    ShellAlways,
+    /// like Shell, with non-printable characters in `$'...'`
+    ShellEscape,
+    /// like ShellAlways, with non-printable characters in `$'...'`
+    ShellEscapeAlways,
+    /// double quoted, with C escapes
+    C,
+    /// C escapes without the quotes
+    Escape,
+    /// quoted with the locale's quotation marks
+    Locale,
+    /// like Locale, but with double quotes outside UTF-8 locales
+    Clocale,
+}
+
+impl QuotingStyle {
+    /// The style called `name` by `--quoting-style` and `QUOTING_STYLE`.
+    pub fn from_name(name: &str) -> Option<QuotingStyle> {
+        Some(match name {
+            "literal" => QuotingStyle::Literal,
+            "shell" => QuotingStyle::Shell,
+            "shell-always" => QuotingStyle::ShellAlways,
+            "shell-escape" => QuotingStyle::ShellEscape,
+            "shell-escape-always" => QuotingStyle::ShellEscapeAlways,
+            "c" => QuotingStyle::C,
+            "escape" => QuotingStyle::Escape,
+            "locale" => QuotingStyle::Locale,
+            "clocale" => QuotingStyle::Clocale,
+            _ => return None,
+        })
+    }
+
+    /// The style selected by the `QUOTING_STYLE` environment variable.
+    pub fn from_env() -> Option<QuotingStyle> {
+        env::var("QUOTING_STYLE").ok().and_then(|name| QuotingStyle::from_name(&name))
+    }
+}
+
+/// Quote an operand for a diagnostic.
+pub fn quote<S: AsRef<OsStr>>(name: S) -> String {
+    quote_as(name, QuotingStyle::Locale)
+}
+
+/// Quote a file name for a diagnostic.
+pub fn quote_path<S: AsRef<OsStr>>(name: S) -> String {
+    quote_as(name, QuotingStyle::ShellEscapeAlways)
+}
+
+pub fn quote_as<S: AsRef<OsStr>>(name: S, style: QuotingStyle) -> String {
+    quote_with(&units(name.as_ref()), style, locale_is_utf8())
+}
+
+/// Whether the character set of the current locale is UTF-8, according to
+/// the usual precedence of the locale variables.
+pub fn locale_is_utf8() -> bool {
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default()
+        .to_lowercase();
+    locale.contains("utf-8") || locale.contains("utf8")
+}
+
+/// A piece of a name: a character, or a byte that is not valid UTF-8.
+#[derive(Clone, Copy, PartialEq)]
+enum Unit {
+    Char(char),
+    Byte(u8),
+}
+
+impl Unit {
+    fn is_printable(&self) -> bool {
+        match *self {
+            Unit::Char(c) => !c.is_control(),
+            Unit::Byte(_) => false,
+        }
+    }
+}
+
+#[cfg(unix)]
+fn units(name: &OsStr) -> Vec<Unit> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut result = Vec::new();
+    let mut bytes = name.as_bytes();
+    while !bytes.is_empty() {
+        let (valid, rest) = match str::from_utf8(bytes) {
+            Ok(s) => (s, &[][..]),
+            Err(e) => {
+                let valid = unsafe { str::from_utf8_unchecked(&bytes[..e.valid_up_to()]) };
+                (valid, &bytes[e.valid_up_to()..])
+            }
+        };
+        result.extend(valid.chars().map(Unit::Char));
+        if let Some((&byte, rest)) = rest.split_first() {
+            result.push(Unit::Byte(byte));
+            bytes = rest;
+        } else {
+            bytes = rest;
+        }
+    }
+    result
+}
+
+#[cfg(not(unix))]
+fn units(name: &OsStr) -> Vec<Unit> {
+    name.to_string_lossy().chars().map(Unit::Char).collect()
+}
+
+fn quote_with(name: &[Unit], style: QuotingStyle, utf8: bool) -> String {
+    match style {
+        QuotingStyle::Literal => literal(name),
+        QuotingStyle::Shell => shell(name, false, false),
+        QuotingStyle::ShellAlways => shell(name, true, false),
+        QuotingStyle::ShellEscape => shell(name, false, true),
+        QuotingStyle::ShellEscapeAlways => shell(name, true, true),
+        QuotingStyle::C => format!("\"{}\"", escaped(name, "\"", false)),
+        QuotingStyle::Escape => escaped(name, "", true),
+        QuotingStyle::Locale | QuotingStyle::Clocale => {
+            let (left, right) = if utf8 {
+                ("\u{2018}", "\u{2019}")
+            } else if style == QuotingStyle::Locale {
+                ("'", "'")
+            } else {
+                ("\"", "\"")
+            };
+            format!("{}{}{}", left, escaped(name, right, false), right)
+        }
+    }
+}
+
+fn literal(name: &[Unit]) -> String {
+    name.iter()
+        .map(|unit| match *unit {
+            Unit::Char(c) => c,
+            Unit::Byte(_) => '\u{fffd}',
+        })
+        .collect()
+}
+
+/// C-style backslash escapes for anything non-printable, for backslashes and
+/// for any occurrence of `closing`.
+fn escaped(name: &[Unit], closing: &str, escape_space: bool) -> String {
+    let mut result = String::with_capacity(name.len());
+    for unit in name {
+        match *unit {
+            Unit::Char(c) if c.is_control() => push_c_escape(&mut result, c as u32),
+            Unit::Char('\\') => result.push_str("\\\\"),
+            Unit::Char(' ') if escape_space => result.push_str("\\ "),
+            Unit::Char(c) if closing.starts_with(c) => {
+                result.push('\\');
+                result.push(c);
+            }
+            Unit::Char(c) => result.push(c),
+            Unit::Byte(b) => push_c_escape(&mut result, b as u32),
+        }
+    }
+    result
+}
+
+fn push_c_escape(result: &mut String, value: u32) {
+    match value {
+        0x07 => result.push_str("\\a"),
+        0x08 => result.push_str("\\b"),
+        0x0c => result.push_str("\\f"),
+        0x0a => result.push_str("\\n"),
+        0x0d => result.push_str("\\r"),
+        0x09 => result.push_str("\\t"),
+        0x0b => result.push_str("\\v"),
+        v if v <= 0xff => result.push_str(&format!("\\{:03o}", v)),
+        v => result.push_str(&format!("\\u{:04x}", v)),
+    }
+}
+
+// characters the shell would interpret
+const SHELL_SPECIAL: &'static str = " \t\n!\"$&'()*;<=>?[\\]^`|";
+// only special at the start of a word
+const SHELL_SPECIAL_FIRST: &'static str = "#~";
+
+fn shell(name: &[Unit], always: bool, escape: bool) -> String {
+    let needs_quoting = name.is_empty() ||
+                        name.iter().enumerate().any(|(i, unit)| match *unit {
+        Unit::Char(c) => SHELL_SPECIAL.contains(c) || (i == 0 && SHELL_SPECIAL_FIRST.contains(c)) ||
+                         (escape && c.is_control()),
+        Unit::Byte(_) => escape,
+    });
+    if !always && !needs_quoting {
+        return literal(name);
+    }
+
+    let printable = !escape || name.iter().all(|unit| unit.is_printable());
+    let has_single = name.contains(&Unit::Char('\''));
+    if printable && has_single &&
+       !name.iter().any(|unit| match *unit {
+        Unit::Char(c) => "\"$`\\!".contains(c),
+        Unit::Byte(_) => false,
+    }) {
+        // "it's" reads better than 'it'\''s'
+        return format!("\"{}\"", literal(name));
+    }
+
+    let mut result = String::with_capacity(name.len() + 2);
+    let mut rest = name;
+    while !rest.is_empty() || result.is_empty() {
+        let run = if escape {
+            rest.iter().take_while(|unit| unit.is_printable()).count()
+        } else {
+            rest.len()
+        };
+        if run > 0 || rest.is_empty() {
+            result.push('\'');
+            for unit in &rest[..run] {
+                match *unit {
+                    Unit::Char('\'') => result.push_str("'\\''"),
+                    Unit::Char(c) => result.push(c),
+                    Unit::Byte(_) => result.push('\u{fffd}'),
+                }
+            }
+            result.push('\'');
+        }
+        rest = &rest[run..];
+
+        let run = rest.iter().take_while(|unit| !unit.is_printable()).count();
+        if run > 0 {
+            result.push_str("$'");
+            result.push_str(&escaped(&rest[..run], "'", false));
+            result.push('\'');
+        }
+        rest = &rest[run..];
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+
+    fn q(name: &str, style: QuotingStyle, utf8: bool) -> String {
+        quote_with(&units(OsStr::new(name)), style, utf8)
+    }
+
+    #[test]
+    fn test_locale() {
+        assert_eq!(q("foo", QuotingStyle::Locale, false), "'foo'");
+        assert_eq!(q("foo", QuotingStyle::Locale, true), "\u{2018}foo\u{2019}");
+        assert_eq!(q("it's", QuotingStyle::Locale, false), "'it\\'s'");
+        assert_eq!(q("it's", QuotingStyle::Locale, true), "\u{2018}it's\u{2019}");
+        assert_eq!(q("a\nb", QuotingStyle::Locale, false), "'a\\nb'");
+        assert_eq!(q("foo", QuotingStyle::Clocale, false), "\"foo\"");
+    }
+
+    #[test]
+    fn test_shell() {
+        assert_eq!(q("foo", QuotingStyle::Shell, false), "foo");
+        assert_eq!(q("foo", QuotingStyle::ShellAlways, false), "'foo'");
+        assert_eq!(q("a b", QuotingStyle::Shell, false), "'a b'");
+        assert_eq!(q("~a", QuotingStyle::Shell, false), "'~a'");
+        assert_eq!(q("a~", QuotingStyle::Shell, false), "a~");
+        assert_eq!(q("", QuotingStyle::Shell, false), "''");
+        assert_eq!(q("it's", QuotingStyle::Shell, false), "\"it's\"");
+        assert_eq!(q("it's $x", QuotingStyle::Shell, false), "'it'\\''s $x'");
+    }
+
+    #[test]
+    fn test_shell_escape() {
+        assert_eq!(q("foo", QuotingStyle::ShellEscape, false), "foo");
+        assert_eq!(q("foo", QuotingStyle::ShellEscapeAlways, true), "'foo'");
+        assert_eq!(q("a\nb", QuotingStyle::ShellEscape, false), "'a'$'\\n''b'");
+        assert_eq!(q("\tb", QuotingStyle::ShellEscape, false), "$'\\t''b'");
+        assert_eq!(q("a\n", QuotingStyle::ShellEscapeAlways, false), "'a'$'\\n'");
+    }
+
+    #[test]
+    fn test_c_and_escape() {
+        assert_eq!(q("a\"b\\", QuotingStyle::C, false), "\"a\\\"b\\\\\"");
+        assert_eq!(q("a b\x01", QuotingStyle::Escape, false), "a\\ b\\001");
+        assert_eq!(q("a b", QuotingStyle::Literal, false), "a b");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_invalid_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+        let name = OsStr::from_bytes(b"a\xffb");
+        assert_eq!(quote_with(&units(name), QuotingStyle::C, false), "\"a\\377b\"");
+        assert_eq!(quote_with(&units(name), QuotingStyle::ShellEscape, false), "'a'$'\\377''b'");
+    }
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(QuotingStyle::from_name("shell-escape"), Some(QuotingStyle::ShellEscape));
+        assert_eq!(QuotingStyle::from_name("bogus"), None);
+    }
+}
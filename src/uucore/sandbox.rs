@@ -0,0 +1,249 @@
+// This file is part of the uutils coreutils package.
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+//
+
+//! Opt-in filesystem sandboxing for utilities that only read their operands.
+//!
+//! The policy is enforced by the kernel through Landlock: once applied, the
+//! process (and anything it spawns) can no longer open any path except the
+//! ones explicitly allowed.  Descriptors that are already open, such as the
+//! standard streams, keep working.  `no_new_privs` is set as well, so the
+//! restriction cannot be shed by executing a setuid binary.
+
+use super::libc;
+use libc::{c_int, c_long, c_void};
+use std::ffi::{CString, OsStr};
+use std::io::{Error, ErrorKind, Result};
+use std::mem;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+// Landlock is recent enough that the libc crate does not know about it yet.
+// These syscall numbers are shared by every architecture.
+const SYS_LANDLOCK_CREATE_RULESET: c_long = 444;
+const SYS_LANDLOCK_ADD_RULE: c_long = 445;
+const SYS_LANDLOCK_RESTRICT_SELF: c_long = 446;
+
+const LANDLOCK_CREATE_RULESET_VERSION: u32 = 1 << 0;
+const LANDLOCK_RULE_PATH_BENEATH: c_int = 1;
+
+const PR_SET_NO_NEW_PRIVS: c_int = 38;
+
+const ACCESS_FS_EXECUTE: u64 = 1 << 0;
+const ACCESS_FS_WRITE_FILE: u64 = 1 << 1;
+const ACCESS_FS_READ_FILE: u64 = 1 << 2;
+const ACCESS_FS_REMOVE_FILE: u64 = 1 << 5;
+const ACCESS_FS_MAKE_REG: u64 = 1 << 8;
+const ACCESS_FS_REFER: u64 = 1 << 13;
+const ACCESS_FS_TRUNCATE: u64 = 1 << 14;
+
+// every access right known to the first version of the ABI
+const ACCESS_FS_ABI_V1: u64 = (1 << 13) - 1;
+
+#[repr(C)]
+struct RulesetAttr {
+    handled_access_fs: u64,
+}
+
+#[repr(C, packed)]
+struct PathBeneathAttr {
+    allowed_access: u64,
+    parent_fd: i32,
+}
+
+/// Set of paths a sandboxed utility is still allowed to touch.
+pub struct Sandbox {
+    read: Vec<CString>,
+    write: Vec<CString>,
+    temp_dirs: Vec<CString>,
+}
+
+impl Sandbox {
+    pub fn new() -> Sandbox {
+        Sandbox {
+            read: Vec::new(),
+            write: Vec::new(),
+            temp_dirs: Vec::new(),
+        }
+    }
+
+    /// The sandbox for a utility that only reads the files `inputs` and
+    /// writes `output`, if there is one.  `-` stands for a standard stream,
+    /// which is already open, so it needs nothing.
+    pub fn for_operands<S: AsRef<Path>>(inputs: &[S], output: Option<&Path>) -> Sandbox {
+        let mut sandbox = Sandbox::new();
+        for input in inputs {
+            sandbox.allow_read(input);
+        }
+        if let Some(output) = output {
+            sandbox.allow_write(output);
+        }
+        sandbox
+    }
+
+    /// Allow reading the file at `path`.  Operands that do not exist are
+    /// ignored, so that opening them later still reports the usual error.
+    pub fn allow_read<P: AsRef<Path>>(&mut self, path: P) -> &mut Sandbox {
+        if let Some(s) = to_cstring(path.as_ref()) {
+            self.read.push(s);
+        }
+        self
+    }
+
+    /// Allow creating, truncating and writing the file at `path`.
+    pub fn allow_write<P: AsRef<Path>>(&mut self, path: P) -> &mut Sandbox {
+        if let Some(s) = to_cstring(path.as_ref()) {
+            self.write.push(s);
+        }
+        self
+    }
+
+    /// Allow creating files in the directory `path`, and writing, reading
+    /// back and removing them, as is done with temporary files.  Other
+    /// files there can be read and written as well, as Landlock cannot
+    /// tell them apart.
+    pub fn allow_temp_dir<P: AsRef<Path>>(&mut self, path: P) -> &mut Sandbox {
+        if let Some(s) = to_cstring(path.as_ref()) {
+            self.temp_dirs.push(s);
+        }
+        self
+    }
+
+    /// Confine the current process.  Fails if the kernel does not support
+    /// Landlock, as silently running unconfined would defeat the purpose.
+    pub fn apply(&self) -> Result<()> {
+        let abi = unsafe {
+            libc::syscall(SYS_LANDLOCK_CREATE_RULESET,
+                          0 as *const c_void,
+                          0 as libc::size_t,
+                          LANDLOCK_CREATE_RULESET_VERSION)
+        };
+        if abi < 0 {
+            let err = Error::last_os_error();
+            return match err.raw_os_error() {
+                Some(libc::ENOSYS) | Some(libc::EOPNOTSUPP) => {
+                    Err(Error::new(ErrorKind::Other, "sandboxing is not supported by this kernel"))
+                }
+                _ => Err(err),
+            };
+        }
+
+        let mut handled = ACCESS_FS_ABI_V1;
+        if abi >= 2 {
+            handled |= ACCESS_FS_REFER;
+        }
+        if abi >= 3 {
+            handled |= ACCESS_FS_TRUNCATE;
+        }
+
+        let attr = RulesetAttr { handled_access_fs: handled };
+        let ruleset = unsafe {
+            libc::syscall(SYS_LANDLOCK_CREATE_RULESET,
+                          &attr as *const RulesetAttr,
+                          mem::size_of::<RulesetAttr>(),
+                          0u32)
+        };
+        if ruleset < 0 {
+            return Err(Error::last_os_error());
+        }
+        let ruleset = ruleset as c_int;
+
+        let result = self.add_rules(ruleset, handled).and_then(|_| restrict_self(ruleset));
+        unsafe { libc::close(ruleset) };
+        result
+    }
+
+    /// Confine the current process as `apply` does, or exit with `status`
+    /// saying why that failed.
+    pub fn enter(&self, status: i32) {
+        if let Err(e) = self.apply() {
+            crash!(status, "cannot enter sandbox: {}", e);
+        }
+    }
+
+    fn add_rules(&self, ruleset: c_int, handled: u64) -> Result<()> {
+        for path in &self.read {
+            match add_rule(ruleset, path, ACCESS_FS_READ_FILE) {
+                // nonexistent operands are simply left out of the policy
+                Err(ref e) if e.kind() == ErrorKind::NotFound => {}
+                other => try!(other),
+            }
+        }
+        let write_access = (ACCESS_FS_READ_FILE | ACCESS_FS_WRITE_FILE | ACCESS_FS_TRUNCATE) & handled;
+        for path in &self.write {
+            match add_rule(ruleset, path, write_access) {
+                Err(ref e) if e.kind() == ErrorKind::NotFound => {
+                    // the file is yet to be created, so grant that on its directory
+                    let parent = parent_of(path);
+                    try!(add_rule(ruleset, &parent, write_access | ACCESS_FS_MAKE_REG));
+                }
+                other => try!(other),
+            }
+        }
+        let temp_access = write_access | ACCESS_FS_MAKE_REG | ACCESS_FS_REMOVE_FILE;
+        for path in &self.temp_dirs {
+            match add_rule(ruleset, path, temp_access) {
+                // creating the first temporary file reports it instead
+                Err(ref e) if e.kind() == ErrorKind::NotFound => {}
+                other => try!(other),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn to_cstring(path: &Path) -> Option<CString> {
+    if path.as_os_str().as_bytes() == b"-" {
+        return None;
+    }
+    CString::new(path.as_os_str().as_bytes()).ok()
+}
+
+fn parent_of(path: &CString) -> CString {
+    let parent = match Path::new(OsStr::from_bytes(path.as_bytes())).parent() {
+        Some(p) if !p.as_os_str().is_empty() => p.as_os_str().as_bytes().to_vec(),
+        _ => b".".to_vec(),
+    };
+    CString::new(parent).unwrap()
+}
+
+fn add_rule(ruleset: c_int, path: &CString, access: u64) -> Result<()> {
+    let fd = unsafe { libc::open(path.as_ptr(), libc::O_PATH | libc::O_CLOEXEC) };
+    if fd < 0 {
+        return Err(Error::last_os_error());
+    }
+
+    let mut stat: libc::stat = unsafe { mem::zeroed() };
+    let mut allowed = access;
+    if unsafe { libc::fstat(fd, &mut stat) } == 0 && stat.st_mode & libc::S_IFMT != libc::S_IFDIR {
+        // directory-only rights are rejected on anything but a directory
+        allowed &= ACCESS_FS_EXECUTE | ACCESS_FS_WRITE_FILE | ACCESS_FS_READ_FILE | ACCESS_FS_TRUNCATE;
+    }
+
+    let attr = PathBeneathAttr {
+        allowed_access: allowed,
+        parent_fd: fd,
+    };
+    let res = unsafe {
+        libc::syscall(SYS_LANDLOCK_ADD_RULE,
+                      ruleset,
+                      LANDLOCK_RULE_PATH_BENEATH,
+                      &attr as *const PathBeneathAttr,
+                      0u32)
+    };
+    let err = Error::last_os_error();
+    unsafe { libc::close(fd) };
+    if res < 0 { Err(err) } else { Ok(()) }
+}
+
+fn restrict_self(ruleset: c_int) -> Result<()> {
+    if unsafe { libc::prctl(PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) } != 0 {
+        return Err(Error::last_os_error());
+    }
+    if unsafe { libc::syscall(SYS_LANDLOCK_RESTRICT_SELF, ruleset, 0u32) } < 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(())
+}
@@ -7,6 +7,9 @@
  * that was distributed with this source code.
  */
 
+#[cfg(target_os = "linux")]
+use super::libc;
+
 pub static DEFAULT_SIGNAL:usize= 15;
 
 
@@ -147,9 +150,93 @@ pub fn signal_by_name_or_value(signal_name_or_value: &str) -> Option<usize> {
             return Some(signal.value);
         }
     }
+    if signal_name_or_value.bytes().all(|b| b.is_ascii_digit()) {
+        return match signal_name_or_value.parse() {
+            Ok(value) if signal_name_by_value(value).is_some() => Some(value),
+            _ => None,
+        };
+    }
+    if signal_name_or_value.starts_with("SIG") {
+        realtime_signal_by_name(&signal_name_or_value[3..])
+    } else {
+        realtime_signal_by_name(signal_name_or_value)
+    }
+}
+
+/// The name of a signal, without "SIG", real-time signals being named from
+/// the nearer of RTMIN and RTMAX as in "RTMIN+3" and "RTMAX-1".
+pub fn signal_name_by_value(signal_value: usize) -> Option<String> {
+    for signal in &ALL_SIGNALS {
+        if signal.value == signal_value {
+            return Some(signal.name.to_owned());
+        }
+    }
+    realtime_signal_name(signal_value)
+}
+
+/// The highest signal number, real-time signals included.
+pub fn signal_max() -> usize {
+    match realtime_signals() {
+        Some((_, max)) => max,
+        None => ALL_SIGNALS.len(),
+    }
+}
+
+// The lowest and highest real-time signals, which the C library may have
+// taken some of for itself.
+#[cfg(target_os = "linux")]
+fn realtime_signals() -> Option<(usize, usize)> {
+    Some((libc::SIGRTMIN() as usize, libc::SIGRTMAX() as usize))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn realtime_signals() -> Option<(usize, usize)> {
     None
 }
 
+fn realtime_signal_by_name(name: &str) -> Option<usize> {
+    let (min, max) = match realtime_signals() {
+        Some(range) => range,
+        None => return None,
+    };
+    let offset = |digits: &str| {
+        if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) {
+            digits.parse::<usize>().ok()
+        } else {
+            None
+        }
+    };
+    let value = if name == "RTMIN" {
+        Some(min)
+    } else if name == "RTMAX" {
+        Some(max)
+    } else if name.starts_with("RTMIN+") {
+        offset(&name[6..]).and_then(|n| min.checked_add(n))
+    } else if name.starts_with("RTMAX-") {
+        offset(&name[6..]).and_then(|n| max.checked_sub(n))
+    } else {
+        None
+    };
+    value.and_then(|value| if value >= min && value <= max { Some(value) } else { None })
+}
+
+fn realtime_signal_name(signal_value: usize) -> Option<String> {
+    match realtime_signals() {
+        Some((min, max)) if signal_value >= min && signal_value <= max => {
+            Some(if signal_value == min {
+                "RTMIN".to_owned()
+            } else if signal_value == max {
+                "RTMAX".to_owned()
+            } else if signal_value <= min + (max - min) / 2 {
+                format!("RTMIN+{}", signal_value - min)
+            } else {
+                format!("RTMAX-{}", max - signal_value)
+            })
+        }
+        _ => None,
+    }
+}
+
 #[inline(always)]
 pub fn is_signal(num: usize) -> bool {
     num < ALL_SIGNALS.len()
@@ -0,0 +1,198 @@
+// This file is part of the uutils coreutils package.
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+//
+
+//! Scaling of sizes for output, as selected by `--block-size`, `-h` and
+//! `--si` and the `BLOCK_SIZE` family of environment variables.
+//!
+//! A block size is given as an optional number followed by an optional unit:
+//! `K`, `M`, `G`, ... for powers of 1024, the same followed by `B` for powers
+//! of 1000, or followed by `iB` for powers of 1024 again.  When the number is
+//! left out, sizes are printed with the unit, so `--block-size=M` gives `12M`.
+
+use std::env;
+
+static UNITS: &'static str = "KMGTPEZY";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BlockSize {
+    /// in units of this many bytes, rounded up, each followed by the suffix
+    Units(u64, String),
+    /// with the largest unit that keeps the number below the base, which is
+    /// 1024 for `-h` and 1000 for `--si`
+    Human(u64),
+}
+
+impl BlockSize {
+    /// Parse a `--block-size` argument.
+    pub fn parse(spec: &str) -> Option<BlockSize> {
+        // a request for thousands separators, which the C locale doesn't have
+        let spec = spec.trim_left_matches('\'');
+        match spec {
+            "human-readable" => return Some(BlockSize::Human(1024)),
+            "si" => return Some(BlockSize::Human(1000)),
+            _ => {}
+        }
+
+        let digits = spec.chars().take_while(|c| c.is_digit(10)).count();
+        let (number, unit) = spec.split_at(digits);
+        let mut multiplier = 1u64;
+        if let Some(letter) = unit.chars().next() {
+            let base = match &unit[letter.len_utf8()..] {
+                "" | "iB" => 1024,
+                "B" => 1000,
+                _ => return None,
+            };
+            // GNU doesn't take lowercase p, z or y
+            let power = match letter {
+                'K' | 'k' => 1,
+                'M' | 'm' => 2,
+                'G' | 'g' => 3,
+                'T' | 't' => 4,
+                'P' => 5,
+                'E' | 'e' => 6,
+                'Z' => 7,
+                'Y' => 8,
+                _ => return None,
+            };
+            for _ in 0..power {
+                multiplier = match multiplier.checked_mul(base) {
+                    Some(m) => m,
+                    None => return None,
+                };
+            }
+        }
+
+        if number.is_empty() {
+            if unit.is_empty() {
+                return None;
+            }
+            let suffix = if unit.starts_with('K') && unit.ends_with("B") && !unit.ends_with("iB") {
+                format!("k{}", &unit[1..])
+            } else {
+                unit.to_owned()
+            };
+            return Some(BlockSize::Units(multiplier, suffix));
+        }
+        match number.parse::<u64>().ok().and_then(|n| n.checked_mul(multiplier)) {
+            Some(0) | None => None,
+            Some(size) => Some(BlockSize::Units(size, String::new())),
+        }
+    }
+
+    /// The block size in the environment variable `var` of the utility, or
+    /// failing that in `BLOCK_SIZE` or `BLOCKSIZE`.  A value that doesn't
+    /// parse is ignored.
+    pub fn from_env(var: &str) -> Option<BlockSize> {
+        [var, "BLOCK_SIZE", "BLOCKSIZE"]
+            .iter()
+            .filter_map(|var| env::var(var).ok())
+            .next()
+            .and_then(|spec| BlockSize::parse(&spec))
+    }
+
+    /// The block size for disk usage when nothing else is asked for.
+    pub fn default_for_blocks() -> BlockSize {
+        if env::var_os("POSIXLY_CORRECT").is_some() {
+            BlockSize::Units(512, String::new())
+        } else {
+            BlockSize::Units(1024, String::new())
+        }
+    }
+
+    pub fn format(&self, bytes: u64) -> String {
+        match *self {
+            BlockSize::Units(size, ref suffix) => {
+                format!("{}{}", bytes / size + if bytes % size == 0 { 0 } else { 1 }, suffix)
+            }
+            BlockSize::Human(base) => human(bytes, base),
+        }
+    }
+}
+
+/// `bytes` in the largest unit of `base` that it amounts to at least one of,
+/// rounded up to one decimal below ten and to a whole number otherwise.
+pub fn human(bytes: u64, base: u64) -> String {
+    if bytes < base {
+        return bytes.to_string();
+    }
+    let mut power = 0;
+    let mut amount = bytes as f64 / base as f64;
+    while amount >= base as f64 && power + 1 < UNITS.len() {
+        amount /= base as f64;
+        power += 1;
+    }
+
+    let mut rounded = (amount * 10.0).ceil() / 10.0;
+    if rounded >= 10.0 {
+        rounded = amount.ceil();
+        if rounded >= base as f64 && power + 1 < UNITS.len() {
+            rounded = 1.0;
+            power += 1;
+        }
+    }
+    let unit = match &UNITS[power..power + 1] {
+        "K" if base == 1000 => "k",
+        unit => unit,
+    };
+    if rounded < 10.0 {
+        format!("{:.1}{}", rounded, unit)
+    } else {
+        format!("{}{}", rounded, unit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn units(size: u64, suffix: &str) -> Option<BlockSize> {
+        Some(BlockSize::Units(size, suffix.to_owned()))
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(BlockSize::parse("512"), units(512, ""));
+        assert_eq!(BlockSize::parse("2K"), units(2048, ""));
+        assert_eq!(BlockSize::parse("2k"), units(2048, ""));
+        assert_eq!(BlockSize::parse("1MB"), units(1000000, ""));
+        assert_eq!(BlockSize::parse("1MiB"), units(1048576, ""));
+        assert_eq!(BlockSize::parse("M"), units(1048576, "M"));
+        assert_eq!(BlockSize::parse("KB"), units(1000, "kB"));
+        assert_eq!(BlockSize::parse("'1K"), units(1024, ""));
+        assert_eq!(BlockSize::parse("si"), Some(BlockSize::Human(1000)));
+        assert_eq!(BlockSize::parse("human-readable"), Some(BlockSize::Human(1024)));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert_eq!(BlockSize::parse(""), None);
+        assert_eq!(BlockSize::parse("0"), None);
+        assert_eq!(BlockSize::parse("1X"), None);
+        assert_eq!(BlockSize::parse("1p"), None);
+        assert_eq!(BlockSize::parse("1KiBs"), None);
+        assert_eq!(BlockSize::parse("16E"), None);
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(BlockSize::Units(1024, String::new()).format(4096), "4");
+        assert_eq!(BlockSize::Units(1024, String::new()).format(4097), "5");
+        assert_eq!(BlockSize::Units(1024, "K".to_owned()).format(0), "0K");
+    }
+
+    #[test]
+    fn test_human() {
+        assert_eq!(human(0, 1024), "0");
+        assert_eq!(human(1023, 1024), "1023");
+        assert_eq!(human(1024, 1024), "1.0K");
+        assert_eq!(human(1025, 1024), "1.1K");
+        assert_eq!(human(4096, 1000), "4.1k");
+        assert_eq!(human(10 * 1024 - 1, 1024), "10K");
+        assert_eq!(human(123456, 1024), "121K");
+        assert_eq!(human(1024 * 1024 - 1, 1024), "1.0M");
+        assert_eq!(human(5 * 1024 * 1024 * 1024, 1024), "5.0G");
+    }
+}
@@ -0,0 +1,275 @@
+// This file is part of the uutils coreutils package.
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+//
+
+//! Skipping the start of the input and limiting how much of the rest is
+//! read, the same way whatever the input turns out to be.
+//!
+//! Regular files, including one redirected to standard input, are skipped
+//! with `lseek` without reading what is skipped; anything else is read and
+//! discarded, however little each read happens to return.
+
+#[cfg(unix)]
+use super::libc;
+use std::cmp;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Chain, Cursor, Error, ErrorKind, Read, Seek, SeekFrom, Stdin};
+
+const DISCARD_BUF_SIZE: usize = 64 * 1024;
+
+/// Input whose start can be skipped.
+pub trait Skip: Read {
+    /// Skip `n` bytes, or whatever is left if the input ends first, and
+    /// return how many were skipped.  Fewer than `n` means the end of the
+    /// input was reached.
+    fn skip(&mut self, n: u64) -> io::Result<u64> {
+        discard(self, n)
+    }
+}
+
+/// Skip by reading and throwing away what is read.
+pub fn discard<R: Read + ?Sized>(input: &mut R, n: u64) -> io::Result<u64> {
+    let mut buf = vec![0; cmp::min(n, DISCARD_BUF_SIZE as u64) as usize];
+    let mut skipped = 0;
+    while skipped < n {
+        let want = cmp::min(n - skipped, buf.len() as u64) as usize;
+        match input.read(&mut buf[..want]) {
+            Ok(0) => break,
+            Ok(k) => skipped += k as u64,
+            Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(skipped)
+}
+
+impl Skip for File {
+    fn skip(&mut self, n: u64) -> io::Result<u64> {
+        let metadata = try!(self.metadata());
+        if !metadata.is_file() {
+            return discard(self, n);
+        }
+        // never seek past the end, so that running out is noticed
+        let pos = try!(self.seek(SeekFrom::Current(0)));
+        let skipped = cmp::min(n, metadata.len().saturating_sub(pos));
+        try!(self.seek(SeekFrom::Start(pos + skipped)));
+        Ok(skipped)
+    }
+}
+
+impl<R: Skip> Skip for BufReader<R> {
+    fn skip(&mut self, n: u64) -> io::Result<u64> {
+        let buffered = try!(skip_buffered(self, n));
+        if buffered == n || buffered == 0 {
+            return Ok(buffered);
+        }
+        // the buffer is empty now, so the inner reader is right behind it
+        Ok(buffered + try!(self.get_mut().skip(n - buffered)))
+    }
+}
+
+impl Skip for Stdin {
+    fn skip(&mut self, n: u64) -> io::Result<u64> {
+        let buffered = try!(skip_buffered(&mut self.lock(), n));
+        if buffered == n || buffered == 0 {
+            return Ok(buffered);
+        }
+        Ok(buffered + try!(skip_stdin(self, n - buffered)))
+    }
+}
+
+#[cfg(unix)]
+fn skip_stdin(stdin: &mut Stdin, n: u64) -> io::Result<u64> {
+    let mut stat: libc::stat = unsafe { ::std::mem::zeroed() };
+    let regular = unsafe { libc::fstat(libc::STDIN_FILENO, &mut stat) } == 0 &&
+                  stat.st_mode & libc::S_IFMT == libc::S_IFREG;
+    let pos = unsafe { libc::lseek(libc::STDIN_FILENO, 0, libc::SEEK_CUR) };
+    if !regular || pos < 0 {
+        return discard(stdin, n);
+    }
+    let skipped = cmp::min(n, (stat.st_size as u64).saturating_sub(pos as u64));
+    if unsafe { libc::lseek(libc::STDIN_FILENO, (pos as u64 + skipped) as libc::off_t, libc::SEEK_SET) } < 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(skipped)
+}
+
+#[cfg(not(unix))]
+fn skip_stdin(stdin: &mut Stdin, n: u64) -> io::Result<u64> {
+    discard(stdin, n)
+}
+
+// Consume up to `n` bytes of what `input` has buffered, filling the buffer
+// first if it is empty.  Returns 0 only at the end of the input.
+fn skip_buffered<B: BufRead>(input: &mut B, n: u64) -> io::Result<u64> {
+    loop {
+        match input.fill_buf() {
+            Ok(buf) => {
+                let buffered = cmp::min(buf.len() as u64, n);
+                input.consume(buffered as usize);
+                return Ok(buffered);
+            }
+            Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+impl<'a> Skip for &'a [u8] {}
+impl<T: AsRef<[u8]>> Skip for Cursor<T> {}
+impl<T: Read, U: Read> Skip for Chain<T, U> {}
+
+impl<S: Skip + ?Sized> Skip for Box<S> {
+    fn skip(&mut self, n: u64) -> io::Result<u64> {
+        (**self).skip(n)
+    }
+}
+
+/// Wrapper which skips `skip` bytes at the start of `inner` and then
+/// passes on at most `limit` bytes of the rest, or all of it if `limit` is
+/// `None`.
+pub struct SkipTake<R> {
+    inner: R,
+    skip: u64,
+    limit: Option<u64>,
+}
+
+impl<R> SkipTake<R> {
+    pub fn new(inner: R, skip: u64, limit: Option<u64>) -> SkipTake<R> {
+        SkipTake {
+            inner: inner,
+            skip: skip,
+            limit: limit,
+        }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+}
+
+impl<R: Skip> Read for SkipTake<R> {
+    /// The first read skips; if the input ends before all of it has been
+    /// skipped, that read fails with `ErrorKind::UnexpectedEof`.
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        while self.skip > 0 {
+            match self.inner.skip(self.skip) {
+                Ok(0) => {
+                    return Err(Error::new(ErrorKind::UnexpectedEof, "cannot skip past end of combined input"));
+                }
+                Ok(n) => self.skip -= n,
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        match self.limit {
+            None => self.inner.read(out),
+            Some(ref mut limit) => {
+                let len = cmp::min(*limit, out.len() as u64) as usize;
+                let n = try!(self.inner.read(&mut out[..len]));
+                *limit -= n as u64;
+                Ok(n)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use faults::Faulty;
+    use std::env;
+    use std::fs;
+    use std::io::{Cursor, ErrorKind, Read, Write};
+    use std::process;
+
+    impl<T: Read> Skip for Faulty<T> {}
+
+    struct Failing;
+
+    impl Read for Failing {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(Error::new(ErrorKind::PermissionDenied, "No access"))
+        }
+    }
+
+    impl Skip for Failing {}
+
+    fn read_all<R: Read>(mut reader: R) -> io::Result<Vec<u8>> {
+        let mut result = vec![];
+        try!(reader.read_to_end(&mut result));
+        Ok(result)
+    }
+
+    #[test]
+    fn test_without_limits() {
+        assert_eq!(read_all(SkipTake::new(&b"abcdefgh"[..], 0, None)).unwrap(), b"abcdefgh");
+    }
+
+    #[test]
+    fn test_skipping() {
+        assert_eq!(read_all(SkipTake::new(&b"abcdefgh"[..], 2, None)).unwrap(), b"cdefgh");
+        assert_eq!(read_all(SkipTake::new(&b"abcdefgh"[..], 8, None)).unwrap(), b"");
+    }
+
+    #[test]
+    fn test_skipping_short_reads() {
+        let input = Faulty::new(&b"abcdefgh"[..]).short(3).interrupt(2);
+        assert_eq!(read_all(SkipTake::new(input, 5, None)).unwrap(), b"fgh");
+        let input = Cursor::new(&b"a"[..]).chain(Cursor::new(&b"bcdefgh"[..]));
+        assert_eq!(read_all(SkipTake::new(input, 2, None)).unwrap(), b"cdefgh");
+    }
+
+    #[test]
+    fn test_skipping_past_end() {
+        let err = read_all(SkipTake::new(&b"abcdefgh"[..], 9, None)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+        // without eating all memory for the discarded bytes
+        let err = read_all(SkipTake::new(&b"abcdefgh"[..], u64::max_value(), None)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = read_all(SkipTake::new(Failing, 2, None)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+        let err = read_all(SkipTake::new(Failing, 0, Some(6))).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    fn test_limiting() {
+        assert_eq!(read_all(SkipTake::new(&b"abcdefgh"[..], 0, Some(0))).unwrap(), b"");
+        assert_eq!(read_all(SkipTake::new(&b"abcdefgh"[..], 0, Some(6))).unwrap(), b"abcdef");
+        assert_eq!(read_all(SkipTake::new(&b"abcdefgh"[..], 0, Some(20))).unwrap(), b"abcdefgh");
+        assert_eq!(read_all(SkipTake::new(&b"abcdefgh"[..], 2, Some(4))).unwrap(), b"cdef");
+
+        let mut v = [0; 3];
+        let mut sut = SkipTake::new(&b"abcdefgh"[..], 0, Some(6));
+        assert_eq!(sut.read(&mut v).unwrap(), 3);
+        assert_eq!(&v, b"abc");
+        assert_eq!(sut.read(&mut v).unwrap(), 3);
+        assert_eq!(&v, b"def");
+        assert_eq!(sut.read(&mut v).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_file() {
+        let path = env::temp_dir().join(format!("uucore-skiptake-{}", process::id()));
+        File::create(&path).unwrap().write_all(b"abcdefgh").unwrap();
+
+        let mut file = File::open(&path).unwrap();
+        assert_eq!(file.skip(3).unwrap(), 3);
+        assert_eq!(file.skip(10).unwrap(), 5);
+        assert_eq!(file.skip(10).unwrap(), 0);
+
+        // whatever was buffered is skipped before the file itself
+        let mut file = BufReader::with_capacity(2, File::open(&path).unwrap());
+        assert_eq!(file.skip(5).unwrap(), 5);
+        assert_eq!(read_all(file).unwrap(), b"fgh");
+
+        fs::remove_file(&path).unwrap();
+    }
+}
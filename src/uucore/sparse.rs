@@ -0,0 +1,183 @@
+// This file is part of the uutils coreutils package.
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+//
+
+//! Reading files that may be sparse without reading their holes.
+//!
+//! Where the system can tell where the data in a file is (`SEEK_DATA` and
+//! `SEEK_HOLE`), the zeros of a hole are made up rather than read, so that
+//! going through a huge, mostly empty file costs little more than going
+//! through the data in it.  Elsewhere, and for anything but regular files,
+//! everything is read as usual.
+
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd", target_os = "macos",
+          target_os = "solaris"))]
+use super::libc;
+use std::cmp;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd", target_os = "macos",
+          target_os = "solaris"))]
+use std::os::unix::io::AsRawFd;
+
+/// A file read from its current position, with the holes in it skipped.
+///
+/// Finding the data moves the file's own position about, so it is only
+/// where the reader says it is after a read of data or a seek.
+pub struct SparseReader {
+    file: File,
+    sparse: bool,
+    pos: u64,
+    /// where the next data starts, anything from `pos` up to it being a hole
+    data: u64,
+    /// where the data from `data` on ends
+    hole: u64,
+    /// whether the file's position is `pos`
+    synced: bool,
+}
+
+impl SparseReader {
+    pub fn new(mut file: File) -> SparseReader {
+        let sparse = file.metadata().map(|metadata| metadata.is_file()).unwrap_or(false);
+        let pos = file.seek(SeekFrom::Current(0)).unwrap_or(0);
+        SparseReader {
+            file: file,
+            sparse: sparse,
+            pos: pos,
+            data: pos,
+            hole: pos,
+            synced: true,
+        }
+    }
+
+    pub fn get_ref(&self) -> &File {
+        &self.file
+    }
+}
+
+impl Read for SparseReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.sparse || buf.is_empty() {
+            return self.file.read(buf);
+        }
+        if self.pos >= self.hole {
+            let (data, hole) = try!(regions(&self.file, self.pos));
+            self.data = data;
+            self.hole = hole;
+            self.synced = false;
+        }
+        if self.pos < self.data {
+            let n = cmp::min(buf.len() as u64, self.data - self.pos) as usize;
+            for byte in &mut buf[..n] {
+                *byte = 0;
+            }
+            self.pos += n as u64;
+            return Ok(n);
+        }
+        if !self.synced {
+            try!(self.file.seek(SeekFrom::Start(self.pos)));
+            self.synced = true;
+        }
+        // stop at the hole, so that it is noticed
+        let len = cmp::min(buf.len() as u64, self.hole - self.pos) as usize;
+        let n = try!(self.file.read(&mut buf[..len]));
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for SparseReader {
+    fn seek(&mut self, from: SeekFrom) -> io::Result<u64> {
+        if !self.sparse {
+            return self.file.seek(from);
+        }
+        let from = match from {
+            SeekFrom::Current(offset) if offset < 0 => {
+                match self.pos.checked_sub(offset.wrapping_neg() as u64) {
+                    Some(pos) => SeekFrom::Start(pos),
+                    None => return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid seek to a negative position")),
+                }
+            }
+            SeekFrom::Current(offset) => SeekFrom::Start(self.pos + offset as u64),
+            from => from,
+        };
+        self.pos = try!(self.file.seek(from));
+        self.data = self.pos;
+        self.hole = self.pos;
+        self.synced = true;
+        Ok(self.pos)
+    }
+}
+
+// Where the next data at or after `pos` starts and where it ends.  Past the
+// last of the data, both are the end of the file.
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd", target_os = "macos",
+          target_os = "solaris"))]
+fn regions(file: &File, pos: u64) -> io::Result<(u64, u64)> {
+    let fd = file.as_raw_fd();
+    let data = unsafe { libc::lseek(fd, pos as libc::off_t, libc::SEEK_DATA) };
+    if data < 0 {
+        let e = io::Error::last_os_error();
+        return match e.raw_os_error() {
+            Some(libc::ENXIO) => {
+                let end = cmp::max(try!(file.metadata()).len(), pos);
+                Ok((end, end))
+            }
+            // not something the file system can tell
+            _ => Ok((pos, u64::max_value())),
+        };
+    }
+    let hole = unsafe { libc::lseek(fd, data, libc::SEEK_HOLE) };
+    if hole < 0 {
+        return Ok((data as u64, u64::max_value()));
+    }
+    Ok((data as u64, hole as u64))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd", target_os = "macos",
+              target_os = "solaris")))]
+fn regions(_: &File, pos: u64) -> io::Result<(u64, u64)> {
+    Ok((pos, u64::max_value()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::io::Write;
+    use std::process;
+
+    #[test]
+    fn test_sparse() {
+        let path = env::temp_dir().join(format!("uucore-sparse-{}", process::id()));
+        let mut file = File::create(&path).unwrap();
+        file.write_all(b"abc").unwrap();
+        file.seek(SeekFrom::Start(1 << 20)).unwrap();
+        file.write_all(b"def").unwrap();
+        file.set_len(2 << 20).unwrap();
+
+        let mut expected = vec![0; 2 << 20];
+        expected[..3].copy_from_slice(b"abc");
+        expected[1 << 20..(1 << 20) + 3].copy_from_slice(b"def");
+
+        let mut contents = vec![];
+        SparseReader::new(File::open(&path).unwrap()).read_to_end(&mut contents).unwrap();
+        assert!(contents == expected);
+
+        // from anywhere, and the file is left where the reader is
+        let mut reader = SparseReader::new(File::open(&path).unwrap());
+        assert_eq!(reader.seek(SeekFrom::End(-(1 << 20) - 2)).unwrap(), (1 << 20) - 2);
+        let mut buf = [1; 4];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"\0\0de");
+        assert_eq!(reader.seek(SeekFrom::Current(-3)).unwrap(), (1 << 20) - 1);
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"\0def");
+        assert_eq!(reader.get_ref().seek(SeekFrom::Current(0)).unwrap(), (1 << 20) + 3);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
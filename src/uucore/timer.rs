@@ -0,0 +1,189 @@
+// This file is part of the uutils coreutils package.
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+//
+
+//! Measuring and waiting out spans of time.
+//!
+//! Spans are measured by clocks that only ever count forwards, so that
+//! setting the system's time neither cuts a wait short nor draws it out.
+//! [`Clock::Monotonic`](enum.Clock.html) stands still while the system is
+//! suspended, and [`Clock::Boottime`](enum.Clock.html) goes on counting, so
+//! that `sleep 1h` ends an hour after it began even if the system slept
+//! through part of it.  Where the system has only one such clock, both
+//! are it.
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use super::libc;
+use std::thread;
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Clock {
+    /// Counts only while the system is running.
+    Monotonic,
+    /// Also counts while the system is suspended.
+    Boottime,
+}
+
+impl Clock {
+    /// The time on the clock, since some point in the past that stays the
+    /// same while the process runs.
+    pub fn now(self) -> Duration {
+        now(self)
+    }
+}
+
+/// A time on a clock to wait until.
+#[derive(Clone, Copy, Debug)]
+pub struct Deadline {
+    clock: Clock,
+    // `None` when it is further off than the clock can count, as after
+    // `sleep inf`, and so never comes
+    at: Option<Duration>,
+}
+
+impl Deadline {
+    /// The time `duration` from now on `clock`.
+    pub fn after(clock: Clock, duration: Duration) -> Deadline {
+        Deadline { clock: clock, at: clock.now().checked_add(duration) }
+    }
+
+    /// How long is left until the deadline, or nothing once it has passed.
+    /// One that never comes has the longest span there is left.
+    pub fn remaining(&self) -> Duration {
+        let at = match self.at {
+            Some(at) => at,
+            None => return Duration::new(u64::MAX, 999_999_999),
+        };
+        let now = self.clock.now();
+        if now < at { at - now } else { Duration::new(0, 0) }
+    }
+
+    pub fn has_passed(&self) -> bool {
+        self.at.is_some() && self.remaining() == Duration::new(0, 0)
+    }
+
+    /// Sleep until the deadline has passed, whatever interrupts the sleep.
+    pub fn sleep(&self) {
+        sleep_until(self);
+    }
+}
+
+/// Sleep for `duration` as `clock` measures it.
+pub fn sleep(clock: Clock, duration: Duration) {
+    Deadline::after(clock, duration).sleep()
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn clock_id(clock: Clock) -> libc::clockid_t {
+    match clock {
+        Clock::Monotonic => libc::CLOCK_MONOTONIC,
+        Clock::Boottime => libc::CLOCK_BOOTTIME,
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn now(clock: Clock) -> Duration {
+    let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    // CLOCK_BOOTTIME is only as old as Linux 2.6.39
+    if unsafe { libc::clock_gettime(clock_id(clock), &mut ts) } != 0 {
+        unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts) };
+    }
+    Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn sleep_until(deadline: &Deadline) {
+    let at = match deadline.at {
+        Some(at) => at,
+        None => sleep_forever(),
+    };
+    let ts = libc::timespec {
+        tv_sec: at.as_secs() as libc::time_t,
+        tv_nsec: at.subsec_nanos() as libc::c_long,
+    };
+    loop {
+        match unsafe { libc::clock_nanosleep(clock_id(deadline.clock), libc::TIMER_ABSTIME, &ts, 0 as *mut _) } {
+            0 => return,
+            libc::EINTR => {}
+            // a clock that cannot be slept on is watched instead
+            _ => break,
+        }
+    }
+    while !deadline.has_passed() {
+        thread::sleep(deadline.remaining());
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn now(_: Clock) -> Duration {
+    use std::sync::{Once, ONCE_INIT};
+    use std::time::Instant;
+
+    static START: Once = ONCE_INIT;
+    static mut ORIGIN: Option<Instant> = None;
+    unsafe {
+        START.call_once(|| ORIGIN = Some(Instant::now()));
+        ORIGIN.unwrap().elapsed()
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn sleep_until(deadline: &Deadline) {
+    if deadline.at.is_none() {
+        sleep_forever();
+    }
+    while !deadline.has_passed() {
+        thread::sleep(deadline.remaining());
+    }
+}
+
+fn sleep_forever() -> ! {
+    loop {
+        thread::sleep(Duration::from_secs(u32::MAX as u64));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clocks_count_forwards() {
+        for &clock in &[Clock::Monotonic, Clock::Boottime] {
+            let before = clock.now();
+            thread::sleep(Duration::from_millis(10));
+            assert!(clock.now() >= before + Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_deadline() {
+        for &clock in &[Clock::Monotonic, Clock::Boottime] {
+            let start = clock.now();
+            let deadline = Deadline::after(clock, Duration::from_millis(50));
+            assert!(!deadline.has_passed());
+            assert!(deadline.remaining() <= Duration::from_millis(50));
+
+            deadline.sleep();
+            assert!(deadline.has_passed());
+            assert_eq!(deadline.remaining(), Duration::new(0, 0));
+            assert!(clock.now() - start >= Duration::from_millis(50));
+
+            // one that has passed is not waited for
+            let start = clock.now();
+            deadline.sleep();
+            assert!(clock.now() - start < Duration::from_millis(50));
+        }
+    }
+
+    #[test]
+    fn test_deadline_past_the_clock() {
+        // as `sleep inf` asks for
+        let deadline = Deadline::after(Clock::Boottime, Duration::new(u64::MAX, 0));
+        assert!(!deadline.has_passed());
+        assert!(deadline.remaining() > Duration::from_secs(u32::MAX as u64));
+    }
+}
@@ -0,0 +1,577 @@
+// This file is part of the uutils coreutils package.
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+//
+
+//! Time zones, as the `TZ` environment variable names them.
+//!
+//! `TZ` may name a file of the zoneinfo database, such as
+//! `America/New_York`, found under `$TZDIR` or `/usr/share/zoneinfo` unless
+//! the name is an absolute path, or it may be a POSIX rule such as
+//! `EST5EDT,M3.2.0,M11.1.0`.  Without `TZ` the zone is that of
+//! `/etc/localtime`, and failing everything else it is UTC.
+
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+const SECS_PER_DAY: i64 = 86400;
+
+// `try!` for options
+macro_rules! try_opt(
+    ($e:expr) => (match $e { Some(x) => x, None => return None })
+);
+
+/// What local time is at some moment: its offset east of UTC in seconds,
+/// whether it is daylight saving time, and the abbreviation it goes by.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LocalTimeType {
+    pub offset: i32,
+    pub is_dst: bool,
+    pub abbreviation: String,
+}
+
+/// The local time types of a zone, and when each one applies.
+#[derive(Clone, Debug)]
+pub struct TimeZone {
+    // in seconds since the epoch, ascending, each with the index in `types`
+    // of the type it starts
+    transitions: Vec<(i64, usize)>,
+    types: Vec<LocalTimeType>,
+    // how local time goes on after the last transition
+    rule: Option<Rule>,
+}
+
+impl TimeZone {
+    /// Coordinated Universal Time.
+    pub fn utc() -> TimeZone {
+        TimeZone::fixed(LocalTimeType {
+            offset: 0,
+            is_dst: false,
+            abbreviation: "UTC".to_owned(),
+        })
+    }
+
+    /// The zone `TZ` names, or without `TZ` the system's.
+    pub fn local() -> TimeZone {
+        match env::var("TZ") {
+            Ok(tz) => TimeZone::named(&tz),
+            Err(_) => TimeZone::read_file(Path::new("/etc/localtime")).unwrap_or_else(TimeZone::utc),
+        }
+    }
+
+    /// The zone `tz` names, as the value of `TZ`.  An empty or unknown name
+    /// is UTC, which goes by the name itself if there is one, as with glibc.
+    pub fn named(tz: &str) -> TimeZone {
+        let name = if tz.starts_with(':') { &tz[1..] } else { tz };
+        if name.is_empty() {
+            return TimeZone::utc();
+        }
+        let path = if name.starts_with('/') {
+            PathBuf::from(name)
+        } else {
+            env::var_os("TZDIR").map_or_else(|| PathBuf::from("/usr/share/zoneinfo"), PathBuf::from).join(name)
+        };
+        // a name with ".." could be anywhere, so only take absolute paths as
+        // they are
+        let from_file = if name.starts_with('/') || !name.split('/').any(|part| part == "..") {
+            TimeZone::read_file(&path)
+        } else {
+            None
+        };
+        from_file.or_else(|| TimeZone::parse_posix(name)).unwrap_or_else(|| {
+            let mut utc = TimeZone::utc();
+            utc.types[0].abbreviation = name.chars().take_while(|c| c.is_ascii_alphabetic()).collect();
+            if utc.types[0].abbreviation.is_empty() {
+                utc.types[0].abbreviation = "UTC".to_owned();
+            }
+            utc
+        })
+    }
+
+    fn read_file(path: &Path) -> Option<TimeZone> {
+        let mut data = Vec::new();
+        match File::open(path).and_then(|mut file| file.read_to_end(&mut data)) {
+            Ok(_) => TimeZone::parse_tzif(&data),
+            Err(_) => None,
+        }
+    }
+
+    /// A zone from the contents of a zoneinfo file, in the TZif format of
+    /// RFC 8536.
+    pub fn parse_tzif(data: &[u8]) -> Option<TimeZone> {
+        let (header, rest) = try_opt!(TzifHeader::parse(data));
+        if header.version >= b'2' {
+            // skip the data with 32-bit times for what follows it
+            let (_, after) = try_opt!(split(rest, header.len(4)));
+            let (header, after) = try_opt!(TzifHeader::parse(after));
+            let (block, footer) = try_opt!(split(after, header.len(8)));
+            let mut zone = try_opt!(header.zone(block, 8));
+            let footer = try_opt!(footer.split(|&b| b == b'\n').nth(1));
+            if !footer.is_empty() {
+                let footer = try_opt!(::std::str::from_utf8(footer).ok());
+                zone.rule = TimeZone::parse_posix(footer).map(|posix| match posix.rule {
+                    Some(rule) => rule,
+                    None => Rule::Fixed(posix.types[0].clone()),
+                });
+            }
+            return Some(zone);
+        }
+        let (block, _) = try_opt!(split(rest, header.len(4)));
+        header.zone(block, 4)
+    }
+
+    /// A zone from a POSIX rule, `STD OFFSET [DST [OFFSET] [,START[/TIME],END[/TIME]]]`,
+    /// where offsets are west of UTC, and the start and end of daylight
+    /// saving time are each `Mm.w.d` for day `d` (0 being Sunday) of week
+    /// `w` (5 being the last) of month `m`, `Jn` for day `n` of a year
+    /// without February 29th, or `n` for day `n` counted from 0.
+    pub fn parse_posix(tz: &str) -> Option<TimeZone> {
+        let mut parser = PosixParser { rest: tz };
+        let std_name = try_opt!(parser.name());
+        let std_offset = -try_opt!(parser.offset());
+        let std = LocalTimeType {
+            offset: std_offset,
+            is_dst: false,
+            abbreviation: std_name,
+        };
+        if parser.rest.is_empty() {
+            return Some(TimeZone::fixed(std));
+        }
+
+        let dst_name = try_opt!(parser.name());
+        let dst_offset = if parser.rest.is_empty() || parser.rest.starts_with(',') {
+            std_offset + 3600
+        } else {
+            -try_opt!(parser.offset())
+        };
+        let dst = LocalTimeType {
+            offset: dst_offset,
+            is_dst: true,
+            abbreviation: dst_name,
+        };
+        // the rule of the United States when none is given
+        let (start, end) = if parser.rest.is_empty() {
+            ((Day::Month(3, 2, 0), 7200), (Day::Month(11, 1, 0), 7200))
+        } else {
+            try_opt!(parser.eat(','));
+            let start = try_opt!(parser.transition());
+            try_opt!(parser.eat(','));
+            let end = try_opt!(parser.transition());
+            (start, end)
+        };
+        if !parser.rest.is_empty() {
+            return None;
+        }
+        let rule = Rule::Dst {
+            std: std.clone(),
+            dst: dst,
+            start: start,
+            end: end,
+        };
+        Some(TimeZone {
+            transitions: Vec::new(),
+            types: vec![std],
+            rule: Some(rule),
+        })
+    }
+
+    fn fixed(local: LocalTimeType) -> TimeZone {
+        TimeZone {
+            transitions: Vec::new(),
+            types: vec![local],
+            rule: None,
+        }
+    }
+
+    /// Local time at `time`, in seconds since the epoch.
+    pub fn find(&self, time: i64) -> LocalTimeType {
+        if self.transitions.is_empty() {
+            return match self.rule {
+                Some(ref rule) => rule.find(time),
+                None => self.types[0].clone(),
+            };
+        }
+        match self.transitions.binary_search_by(|&(at, _)| at.cmp(&time)) {
+            Ok(i) => self.types[self.transitions[i].1].clone(),
+            // before the first transition, the first type the file lists
+            Err(0) => self.types[0].clone(),
+            Err(i) if i == self.transitions.len() => match self.rule {
+                Some(ref rule) => rule.find(time),
+                None => self.types[self.transitions[i - 1].1].clone(),
+            },
+            Err(i) => self.types[self.transitions[i - 1].1].clone(),
+        }
+    }
+
+    /// The time, in seconds since the epoch, at which local time reads
+    /// `local`, counted in seconds since the epoch as though local time
+    /// were UTC.  Of a local time that happens twice, as clocks go back, it
+    /// is the earlier; one that clocks skip over never happens, so it is
+    /// `None`.
+    pub fn to_utc(&self, local: i64) -> Option<i64> {
+        // any offset local time has is in force within a day of it
+        let mut offsets = vec![
+            self.find(local - SECS_PER_DAY).offset,
+            self.find(local).offset,
+            self.find(local + SECS_PER_DAY).offset,
+        ];
+        offsets.dedup();
+        offsets.iter()
+            .map(|&offset| local - offset as i64)
+            .filter(|&time| self.find(time).offset as i64 == local - time)
+            .min()
+    }
+}
+
+fn split(data: &[u8], at: usize) -> Option<(&[u8], &[u8])> {
+    if data.len() < at {
+        None
+    } else {
+        Some(data.split_at(at))
+    }
+}
+
+fn be(bytes: &[u8]) -> i64 {
+    let unsigned = bytes.iter().fold(0u64, |n, &b| n << 8 | b as u64);
+    // sign extended from however many bytes there are
+    let shift = 64 - 8 * bytes.len();
+    ((unsigned << shift) as i64) >> shift
+}
+
+struct TzifHeader {
+    version: u8,
+    isutcnt: usize,
+    isstdcnt: usize,
+    leapcnt: usize,
+    timecnt: usize,
+    typecnt: usize,
+    charcnt: usize,
+}
+
+impl TzifHeader {
+    fn parse(data: &[u8]) -> Option<(TzifHeader, &[u8])> {
+        let (header, rest) = try_opt!(split(data, 44));
+        if &header[..4] != b"TZif" {
+            return None;
+        }
+        let count = |i: usize| be(&header[20 + 4 * i..24 + 4 * i]) as usize;
+        let header = TzifHeader {
+            version: header[4],
+            isutcnt: count(0),
+            isstdcnt: count(1),
+            leapcnt: count(2),
+            timecnt: count(3),
+            typecnt: count(4),
+            charcnt: count(5),
+        };
+        if header.typecnt == 0 {
+            return None;
+        }
+        Some((header, rest))
+    }
+
+    // The length of the data after the header, with times `time_len` bytes
+    // long.
+    fn len(&self, time_len: usize) -> usize {
+        self.timecnt * (time_len + 1) + self.typecnt * 6 + self.charcnt + self.leapcnt * (time_len + 4)
+            + self.isstdcnt + self.isutcnt
+    }
+
+    fn zone(&self, data: &[u8], time_len: usize) -> Option<TimeZone> {
+        let (times, data) = try_opt!(split(data, self.timecnt * time_len));
+        let (indices, data) = try_opt!(split(data, self.timecnt));
+        let (types, data) = try_opt!(split(data, self.typecnt * 6));
+        let (chars, _) = try_opt!(split(data, self.charcnt));
+
+        let mut transitions = Vec::with_capacity(self.timecnt);
+        for (time, &index) in times.chunks(time_len).zip(indices) {
+            if index as usize >= self.typecnt {
+                return None;
+            }
+            transitions.push((be(time), index as usize));
+        }
+        let mut local_types = Vec::with_capacity(self.typecnt);
+        for ttinfo in types.chunks(6) {
+            let start = ttinfo[5] as usize;
+            if start > chars.len() {
+                return None;
+            }
+            let end = chars[start..].iter().position(|&b| b == 0).map_or(chars.len(), |len| start + len);
+            local_types.push(LocalTimeType {
+                offset: be(&ttinfo[..4]) as i32,
+                is_dst: ttinfo[4] != 0,
+                abbreviation: String::from_utf8_lossy(&chars[start..end]).into_owned(),
+            });
+        }
+        Some(TimeZone {
+            transitions: transitions,
+            types: local_types,
+            rule: None,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Rule {
+    Fixed(LocalTimeType),
+    Dst {
+        std: LocalTimeType,
+        dst: LocalTimeType,
+        // days, with the local time of day at which each starts
+        start: (Day, i32),
+        end: (Day, i32),
+    },
+}
+
+impl Rule {
+    fn find(&self, time: i64) -> LocalTimeType {
+        let (std, dst, start, end) = match *self {
+            Rule::Fixed(ref local) => return local.clone(),
+            Rule::Dst { ref std, ref dst, start, end } => (std, dst, start, end),
+        };
+        let (year, _, _) = civil_from_days((time + std.offset as i64).div_euclid(SECS_PER_DAY));
+        // as with glibc, the rule only keeps time since 1970
+        if year < 1970 {
+            return std.clone();
+        }
+        // daylight saving time starts in standard time and ends in itself
+        let starts = start.0.days(year) * SECS_PER_DAY + start.1 as i64 - std.offset as i64;
+        let ends = end.0.days(year) * SECS_PER_DAY + end.1 as i64 - dst.offset as i64;
+        let in_dst = if starts < ends {
+            starts <= time && time < ends
+        } else {
+            // in the southern hemisphere, across the new year
+            time < ends || starts <= time
+        };
+        if in_dst { dst.clone() } else { std.clone() }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Day {
+    // day of the year from 1, never counting February 29th
+    Julian(i64),
+    // day of the year from 0
+    Zero(i64),
+    // month, week from 1 with 5 the last, and day of the week from Sunday
+    Month(i64, i64, i64),
+}
+
+impl Day {
+    // The day it is in `year`, in days since the epoch.
+    fn days(&self, year: i64) -> i64 {
+        let jan1 = days_from_civil(year, 1, 1);
+        match *self {
+            Day::Julian(n) => jan1 + n - 1 + if is_leap(year) && n >= 60 { 1 } else { 0 },
+            Day::Zero(n) => jan1 + n,
+            Day::Month(month, week, weekday) => {
+                let first = days_from_civil(year, month, 1);
+                let first_weekday = (first + 4).rem_euclid(7);
+                let mut day = first + (weekday - first_weekday).rem_euclid(7) + (week - 1) * 7;
+                let next_month = if month == 12 {
+                    days_from_civil(year + 1, 1, 1)
+                } else {
+                    days_from_civil(year, month + 1, 1)
+                };
+                while day >= next_month {
+                    day -= 7;
+                }
+                day
+            }
+        }
+    }
+}
+
+fn is_leap(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+/// Days since the epoch of a date in the proleptic Gregorian calendar.
+pub fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// The year, month and day `days` since the epoch.
+pub fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+struct PosixParser<'a> {
+    rest: &'a str,
+}
+
+impl<'a> PosixParser<'a> {
+    fn eat(&mut self, c: char) -> Option<()> {
+        if self.rest.starts_with(c) {
+            self.rest = &self.rest[c.len_utf8()..];
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    // Three or more letters, or anything but '>' between '<' and '>'.
+    fn name(&mut self) -> Option<String> {
+        let (name, rest) = if self.eat('<').is_some() {
+            let end = try_opt!(self.rest.find('>'));
+            (&self.rest[..end], &self.rest[end + 1..])
+        } else {
+            let end = self.rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(self.rest.len());
+            (&self.rest[..end], &self.rest[end..])
+        };
+        if name.len() < 3 {
+            return None;
+        }
+        self.rest = rest;
+        Some(name.to_owned())
+    }
+
+    fn number(&mut self) -> Option<i32> {
+        let end = self.rest.find(|c: char| !c.is_digit(10)).unwrap_or(self.rest.len());
+        let number = try_opt!(self.rest[..end].parse().ok());
+        self.rest = &self.rest[end..];
+        Some(number)
+    }
+
+    // [+-]hh[:mm[:ss]], in seconds
+    fn offset(&mut self) -> Option<i32> {
+        let sign = if self.eat('-').is_some() {
+            -1
+        } else {
+            let _ = self.eat('+');
+            1
+        };
+        let hours = try_opt!(self.number());
+        let mut seconds = hours * 3600;
+        if self.eat(':').is_some() {
+            seconds += try_opt!(self.number()) * 60;
+            if self.eat(':').is_some() {
+                seconds += try_opt!(self.number());
+            }
+        }
+        if hours > 167 {
+            return None;
+        }
+        Some(sign * seconds)
+    }
+
+    // A day and its time of day, 02:00 unless given.
+    fn transition(&mut self) -> Option<(Day, i32)> {
+        let day = if self.eat('J').is_some() {
+            match try_opt!(self.number()) {
+                n if n >= 1 && n <= 365 => Day::Julian(n as i64),
+                _ => return None,
+            }
+        } else if self.eat('M').is_some() {
+            let month = try_opt!(self.number());
+            try_opt!(self.eat('.'));
+            let week = try_opt!(self.number());
+            try_opt!(self.eat('.'));
+            let weekday = try_opt!(self.number());
+            if month < 1 || month > 12 || week < 1 || week > 5 || weekday > 6 {
+                return None;
+            }
+            Day::Month(month as i64, week as i64, weekday as i64)
+        } else {
+            match try_opt!(self.number()) {
+                n if n <= 365 => Day::Zero(n as i64),
+                _ => return None,
+            }
+        };
+        let time = if self.eat('/').is_some() { try_opt!(self.offset()) } else { 7200 };
+        Some((day, time))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2021-03-14 and 2021-11-07 in New York, at 02:00 local time
+    const SPRING_FORWARD: i64 = 1615705200;
+    const FALL_BACK: i64 = 1636264800;
+
+    #[test]
+    fn test_civil() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11017);
+        assert_eq!(civil_from_days(11017), (2000, 3, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+    }
+
+    #[test]
+    fn test_posix() {
+        let zone = TimeZone::parse_posix("EST5EDT,M3.2.0,M11.1.0").unwrap();
+        assert_eq!(zone.find(SPRING_FORWARD - 1).abbreviation, "EST");
+        assert_eq!(zone.find(SPRING_FORWARD).abbreviation, "EDT");
+        assert_eq!(zone.find(SPRING_FORWARD).offset, -4 * 3600);
+        assert_eq!(zone.find(FALL_BACK - 1).abbreviation, "EDT");
+        assert_eq!(zone.find(FALL_BACK).abbreviation, "EST");
+        assert_eq!(zone.find(days_from_civil(1969, 7, 1) * SECS_PER_DAY).abbreviation, "EST");
+
+        let south = TimeZone::parse_posix("<+1030>-10:30<+11>-11,M10.1.0,M4.1.0").unwrap();
+        assert_eq!(south.find(days_from_civil(2021, 1, 1) * SECS_PER_DAY).offset, 11 * 3600);
+        assert_eq!(south.find(days_from_civil(2021, 7, 1) * SECS_PER_DAY).abbreviation, "+1030");
+
+        assert_eq!(TimeZone::parse_posix("JST-9").unwrap().find(0).offset, 9 * 3600);
+        assert!(TimeZone::parse_posix("EST5EDT,M13.1.0,M11.1.0").is_none());
+        assert!(TimeZone::parse_posix("X5").is_none());
+        assert_eq!(TimeZone::named("Nowhere/Nothing").find(0).abbreviation, "Nowhere");
+    }
+
+    #[test]
+    fn test_to_utc() {
+        let zone = TimeZone::parse_posix("EST5EDT,M3.2.0,M11.1.0").unwrap();
+        // 01:30 local, before the clocks go forward
+        assert_eq!(zone.to_utc(SPRING_FORWARD - 5 * 3600 - 1800), Some(SPRING_FORWARD - 1800));
+        // 02:30 never happens
+        assert_eq!(zone.to_utc(SPRING_FORWARD - 5 * 3600 + 1800), None);
+        // 01:30 happens twice
+        assert_eq!(zone.to_utc(FALL_BACK - 4 * 3600 - 1800), Some(FALL_BACK - 1800));
+    }
+
+    #[test]
+    fn test_tzif() {
+        let mut data = b"TZif2".to_vec();
+        data.extend_from_slice(&[0; 15]);
+        // no isut, isstd or leap seconds; one transition, two types, 8
+        // bytes of abbreviations
+        let counts = |data: &mut Vec<u8>| for &n in &[0u32, 0, 0, 1, 2, 8] {
+            data.extend_from_slice(&[(n >> 24) as u8, (n >> 16) as u8, (n >> 8) as u8, n as u8]);
+        };
+        counts(&mut data);
+        data.extend_from_slice(&[0, 0, 0, 100, 1]);
+        data.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0, 0x0e, 0x10, 1, 4]);
+        data.extend_from_slice(b"GMT\0BST\0");
+        let v1 = data[44..].to_vec();
+        data.extend_from_slice(&data[..44].to_vec());
+        data.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 100, 1]);
+        data.extend_from_slice(&v1[5..]);
+        data.extend_from_slice(b"\nGMT0BST,M3.5.0/1,M10.5.0\n");
+
+        let zone = TimeZone::parse_tzif(&data).unwrap();
+        assert_eq!(zone.find(99).abbreviation, "GMT");
+        assert_eq!(zone.find(100).abbreviation, "BST");
+        assert_eq!(zone.find(100).offset, 3600);
+        assert_eq!(zone.find(days_from_civil(2021, 1, 1) * SECS_PER_DAY).abbreviation, "GMT");
+        assert_eq!(zone.find(days_from_civil(2021, 7, 1) * SECS_PER_DAY).abbreviation, "BST");
+        assert!(TimeZone::parse_tzif(b"TZif").is_none());
+    }
+}
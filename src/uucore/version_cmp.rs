@@ -0,0 +1,189 @@
+// This file is part of the uutils coreutils package.
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+//
+
+//! Comparing version numbers within file names, as GNU's `filevercmp` does
+//! for `sort -V` and `ls -v`.
+//!
+//! Names are compared a run of non-digits, then a run of digits, at a time.
+//! Non-digits compare letters first, then other characters, except that `~`
+//! comes before anything, even the end of the name.  Digits compare as the
+//! numbers they spell, so that leading zeros make no difference.  File
+//! suffixes such as `.tar.gz` only break ties between the rest of the names.
+
+use std::cmp::Ordering;
+
+/// Compare `a` and `b` as version numbers, or names holding them.  The empty
+/// name comes first, then `.`, `..` and other names starting with a dot, then
+/// the rest.  Names that differ only in leading zeros are equal.
+pub fn version_cmp(a: &str, b: &str) -> Ordering {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    match (a.is_empty(), b.is_empty()) {
+        (true, true) => return Ordering::Equal,
+        (true, false) => return Ordering::Less,
+        (false, true) => return Ordering::Greater,
+        (false, false) => {}
+    }
+    match (a[0] == b'.', b[0] == b'.') {
+        (true, false) => return Ordering::Less,
+        (false, true) => return Ordering::Greater,
+        (true, true) => {
+            for special in &[&b"."[..], &b".."[..]] {
+                match (a == *special, b == *special) {
+                    (true, true) => return Ordering::Equal,
+                    (true, false) => return Ordering::Less,
+                    (false, true) => return Ordering::Greater,
+                    (false, false) => {}
+                }
+            }
+        }
+        (false, false) => {}
+    }
+
+    let (a_prefix, b_prefix) = (prefix_len(a), prefix_len(b));
+    let result = verrevcmp(&a[..a_prefix], &b[..b_prefix]);
+    if result == Ordering::Equal && (a_prefix < a.len() || b_prefix < b.len()) {
+        verrevcmp(a, b)
+    } else {
+        result
+    }
+}
+
+// The length of `name` without its file suffix, the longest match of
+// `(\.[A-Za-z~][A-Za-z0-9~]*)*$`.  That can be all of a hidden name.
+fn prefix_len(name: &[u8]) -> usize {
+    let mut prefix = 0;
+    let mut i = 0;
+    loop {
+        while i + 1 < name.len() && name[i] == b'.' && (name[i + 1].is_ascii_alphabetic() || name[i + 1] == b'~') {
+            i += 2;
+            while i < name.len() && (name[i].is_ascii_alphanumeric() || name[i] == b'~') {
+                i += 1;
+            }
+        }
+        if i == name.len() {
+            return prefix;
+        }
+        i += 1;
+        prefix = i;
+    }
+}
+
+// Where the character at `i` of `s` sorts among non-digits.
+fn order(s: &[u8], i: usize) -> i32 {
+    match s.get(i) {
+        None => -1,
+        Some(&c) if c.is_ascii_digit() => 0,
+        Some(&c) if c.is_ascii_alphabetic() => c as i32,
+        Some(&b'~') => -2,
+        Some(&c) => c as i32 + 256,
+    }
+}
+
+fn verrevcmp(a: &[u8], b: &[u8]) -> Ordering {
+    let is_digit = |s: &[u8], i: usize| s.get(i).map_or(false, |c| c.is_ascii_digit());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        while (i < a.len() && !is_digit(a, i)) || (j < b.len() && !is_digit(b, j)) {
+            let (x, y) = (order(a, i), order(b, j));
+            if x != y {
+                return x.cmp(&y);
+            }
+            i += 1;
+            j += 1;
+        }
+        while a.get(i) == Some(&b'0') {
+            i += 1;
+        }
+        while b.get(j) == Some(&b'0') {
+            j += 1;
+        }
+        // the first differing digit decides, unless one number is longer
+        let mut first_diff = Ordering::Equal;
+        while is_digit(a, i) && is_digit(b, j) {
+            if first_diff == Ordering::Equal {
+                first_diff = a[i].cmp(&b[j]);
+            }
+            i += 1;
+            j += 1;
+        }
+        if is_digit(a, i) {
+            return Ordering::Greater;
+        }
+        if is_digit(b, j) {
+            return Ordering::Less;
+        }
+        if first_diff != Ordering::Equal {
+            return first_diff;
+        }
+    }
+    Ordering::Equal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gnu_order() {
+        // as GNU sort -s -V puts them
+        let expected = vec!["", ".", "..", ".a", ".a1", ".a10", ".bashrc", "1", "1.0~rc1", "1.0", "1.0.a",
+                            "1.0a", "1.0-rc1", "1.0.1", "2.6.4", "2.6.39", "10", "a~", "a", "a.b", "a.txt", "a1~",
+                            "a1", "a1.b2", "a1.b10", "a1.c", "a1.tar", "a1.tar.gz", "a1b", "a1x.c", "a2", "a10",
+                            "a 1", "a%", "a-1", "a.1", "b.tar.gz~", "b.tar.gz", "b.tar.gz.1", "x.~", "x.1a", "#1",
+                            "\u{e9}1", "\u{e9}2"];
+        let mut names = expected.clone();
+        names.reverse();
+        names.sort_by(|a, b| version_cmp(a, b));
+        assert_eq!(names, expected);
+        assert_eq!(version_cmp("a01", "a1"), Ordering::Equal);
+        assert_eq!(version_cmp("~", ""), Ordering::Greater);
+        // all of ".c" is a suffix, leaving less than ".b_" has
+        assert_eq!(version_cmp(".c", ".b_"), Ordering::Less);
+    }
+
+    // Names from a few characters that matter, generated from a fixed seed.
+    fn names(count: usize) -> Vec<String> {
+        let alphabet = ['a', 'b', 'Z', '0', '1', '9', '.', '~', '-', '_'];
+        let mut state: u32 = 0x2545_f491;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as usize
+        };
+        (0..count).map(|_| {
+            let len = next() % 7;
+            (0..len).map(|_| alphabet[next() % alphabet.len()]).collect()
+        }).collect()
+    }
+
+    #[test]
+    fn test_total_order() {
+        let names = names(60);
+        for a in &names {
+            assert_eq!(version_cmp(a, a), Ordering::Equal);
+            for b in &names {
+                let ab = version_cmp(a, b);
+                assert_eq!(ab, version_cmp(b, a).reverse(), "{:?} {:?}", a, b);
+                for c in &names {
+                    if ab != Ordering::Greater && version_cmp(b, c) != Ordering::Greater {
+                        assert_ne!(version_cmp(a, c), Ordering::Greater, "{:?} {:?} {:?}", a, b, c);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_numbers() {
+        // names that are only numbers compare as the numbers do
+        for a in 0..120u32 {
+            for b in 0..120u32 {
+                assert_eq!(version_cmp(&a.to_string(), &format!("{:03}", b)), a.cmp(&b));
+            }
+        }
+    }
+}
@@ -12,9 +12,10 @@
 include!(concat!(env!("OUT_DIR"), "/uutils_crates.rs"));
 
 use std::collections::hash_map::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::env;
-use std::io::Write;
+use std::fs;
+use std::io::{ErrorKind, Write};
 
 extern crate uucore;
 
@@ -23,19 +24,130 @@ static VERSION: &'static str = env!("CARGO_PKG_VERSION");
 
 include!(concat!(env!("OUT_DIR"), "/uutils_map.rs"));
 
+// Other names utilities go by: the name, the utility, and the options it
+// stands for, which come before any given.  The utility is told the name
+// it was called by, as hashsum needs to be.  A utility of the same name
+// comes first, so arch is only uname -m where there is no arch.
+static ALIASES: &'static [(&'static str, &'static str, &'static [&'static str])] = &[
+    ("arch", "uname", &["-m"]),
+    ("dir", "ls", &["-C"]),
+    ("vdir", "ls", &["-l"]),
+    ("md5sum", "hashsum", &[]),
+    ("sha1sum", "hashsum", &[]),
+    ("sha224sum", "hashsum", &[]),
+    ("sha256sum", "hashsum", &[]),
+    ("sha384sum", "hashsum", &[]),
+    ("sha512sum", "hashsum", &[]),
+    ("sha3sum", "hashsum", &[]),
+    ("sha3-224sum", "hashsum", &[]),
+    ("sha3-256sum", "hashsum", &[]),
+    ("sha3-384sum", "hashsum", &[]),
+    ("sha3-512sum", "hashsum", &[]),
+    ("shake128sum", "hashsum", &[]),
+    ("shake256sum", "hashsum", &[]),
+];
+
+type UtilityMain = fn(Vec<String>) -> i32;
+
+// The utility `name` calls for and the options to put before the arguments.
+fn find_util(cmap: &UtilityMap, name: &str) -> Option<(UtilityMain, &'static [&'static str])> {
+    if let Some(&uumain) = cmap.get(name) {
+        return Some((uumain, &[]));
+    }
+    ALIASES.iter()
+        .find(|&&(alias, _, _)| alias == name)
+        .and_then(|&(_, util, options)| cmap.get(util).map(|&uumain| (uumain, options)))
+}
+
+fn run(uumain: UtilityMain, options: &[&str], mut args: Vec<String>) -> i32 {
+    for (i, option) in options.iter().enumerate() {
+        args.insert(i + 1, (*option).to_owned());
+    }
+    uumain(args)
+}
+
+// Every name there is a utility for, aliases included.
+fn names(cmap: &UtilityMap) -> Vec<&'static str> {
+    let mut names: Vec<&str> = cmap.keys().map(|&s| s).collect();
+    for &(alias, _, _) in ALIASES {
+        if !cmap.contains_key(alias) && find_util(cmap, alias).is_some() {
+            names.push(alias);
+        }
+    }
+    names.sort();
+    names
+}
+
 fn usage(cmap: &UtilityMap) {
     println!("{} {}", NAME, VERSION);
     println!("");
     println!("Usage:");
-    println!("  {} [util [arguments...]]\n", NAME);
+    println!("  {} [util [arguments...]]", NAME);
+    println!("  {} --install [--symlinks|--hardlinks] DIR\n", NAME);
     println!("Currently defined functions:");
-    let mut utils: Vec<&str> = cmap.keys().map(|&s| s).collect();
-    utils.sort();
-    for util in utils {
+    for util in names(cmap) {
         println!("\t{}", util);
     }
 }
 
+// Links every name there is a utility for in `dir` to this binary, with
+// symbolic links if `symlinks` and hard links otherwise, as busybox
+// --install does.  Names already in `dir` are left alone.
+fn install(cmap: &UtilityMap, args: &[String]) -> i32 {
+    let mut symlinks = false;
+    let mut dir = None;
+    for arg in args {
+        match &arg[..] {
+            "-s" | "--symlinks" => symlinks = true,
+            "--hardlinks" => symlinks = false,
+            _ if dir.is_none() && !arg.starts_with('-') => dir = Some(PathBuf::from(arg)),
+            _ => {
+                println!("{}: --install: unexpected argument '{}'", NAME, arg);
+                return 1;
+            }
+        }
+    }
+    let dir = match dir {
+        Some(dir) => dir,
+        None => {
+            println!("{}: --install: missing directory operand", NAME);
+            return 1;
+        }
+    };
+    let binary = match env::current_exe() {
+        Ok(binary) => binary,
+        Err(e) => {
+            println!("{}: cannot find this binary: {}", NAME, e);
+            return 1;
+        }
+    };
+
+    let mut status = 0;
+    for name in names(cmap) {
+        let link = dir.join(name);
+        let result = if symlinks { symlink(&binary, &link) } else { fs::hard_link(&binary, &link) };
+        match result {
+            Err(ref e) if e.kind() == ErrorKind::AlreadyExists => {}
+            Err(e) => {
+                println!("{}: cannot link '{}': {}", NAME, link.display(), e);
+                status = 1;
+            }
+            Ok(_) => {}
+        }
+    }
+    status
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_file(target, link)
+}
+
 fn main() {
     uucore::panic::install_sigpipe_hook();
 
@@ -47,18 +159,19 @@ fn main() {
     let binary = Path::new(&args0[..]);
     let binary_as_util = binary.file_name().unwrap().to_str().unwrap();
 
-    if let Some(&uumain) = umap.get(binary_as_util) {
-        std::process::exit(uumain(args));
+    if let Some((uumain, options)) = find_util(&umap, binary_as_util) {
+        std::process::exit(run(uumain, options, args));
     }
 
     if binary_as_util.ends_with("uutils") || binary_as_util.starts_with("uutils") ||
+     binary_as_util.ends_with("coreutils") || binary_as_util.starts_with("coreutils") ||
      binary_as_util.ends_with("busybox") || binary_as_util.starts_with("busybox") {
         args.remove(0);
     } else {
         let mut found = false;
-        for util in umap.keys() {
+        for util in names(&umap) {
             if binary_as_util.ends_with(util) {
-                args[0] = (*util).to_owned();
+                args[0] = util.to_owned();
                 found = true;
                 break;
             }
@@ -72,19 +185,21 @@ fn main() {
     // try first arg as util name.
     if args.len() >= 1 {
 
-        let util = &args[0][..];
+        let util = &args[0].clone()[..];
 
-        match umap.get(util) {
-            Some(&uumain) => {
-                std::process::exit(uumain(args.clone()));
+        match find_util(&umap, util) {
+            Some((uumain, options)) => {
+                std::process::exit(run(uumain, options, args));
             }
             None => {
-                if &args[0][..] == "--help" {
+                if util == "--install" {
+                    std::process::exit(install(&umap, &args[1..]));
+                } else if util == "--help" {
                     // see if they want help on a specific util
                     if args.len() >= 2 {
                         let util = &args[1][..];
-                        match umap.get(util) {
-                            Some(&uumain) => {
+                        match find_util(&umap, util) {
+                            Some((uumain, _)) => {
                                 let code = uumain(vec![util.to_owned(), "--help".to_owned()]);
                                 std::io::stdout().flush().expect("could not flush stdout");
                                 std::process::exit(code);
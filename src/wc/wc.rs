@@ -9,18 +9,23 @@
  * file that was distributed with this source code.
  */
 
+extern crate bytecount;
 extern crate getopts;
+extern crate unicode_width;
 
 #[macro_use]
 extern crate uucore;
 
 use getopts::{Matches, Options};
-use std::ascii::AsciiExt;
-use std::fs::File;
-use std::io::{stdin, BufRead, BufReader, Read};
+use std::cmp;
+use std::fs::{self, File};
+use std::io::{self, stdin, BufRead, BufReader, Read};
 use std::path::Path;
-use std::result::Result as StdResult;
 use std::str::from_utf8;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use unicode_width::UnicodeWidthChar;
+use uucore::quoting::quote;
 
 struct Settings {
     show_bytes: bool,
@@ -56,6 +61,12 @@ impl Settings {
             show_max_line_length: false,
         }
     }
+
+    /// Whether only newlines and bytes are to be counted, which can be done
+    /// without looking at lines or characters.
+    fn lines_and_bytes_only(&self) -> bool {
+        !self.show_words && !self.show_chars && !self.show_max_line_length
+    }
 }
 
 struct Result {
@@ -76,8 +87,12 @@ pub fn uumain(args: Vec<String>) -> i32 {
     opts.optflag("c", "bytes", "print the byte counts");
     opts.optflag("m", "chars", "print the character counts");
     opts.optflag("l", "lines", "print the newline counts");
-    opts.optflag("L", "max-line-length", "print the length of the longest line");
+    opts.optflag("L", "max-line-length", "print the maximum display width");
     opts.optflag("w", "words", "print the word counts");
+    opts.optopt("", "files0-from", "read input from the files specified by NUL-terminated names \
+                                    in file F; if F is - then read names from standard input", "F");
+    #[cfg(all(target_os = "linux", feature = "sandbox"))]
+    opts.optflag("", "sandbox", "only allow reading the FILEs given on the command line");
     opts.optflag("h", "help", "display this help and exit");
     opts.optflag("V", "version", "output version information and exit");
 
@@ -102,20 +117,111 @@ pub fn uumain(args: Vec<String>) -> i32 {
         return 0;
     }
 
-    if matches.free.is_empty() {
-        matches.free.push("-".to_owned());
+    let settings = Settings::new(&matches);
+
+    let list = match matches.opt_str("files0-from") {
+        Some(list) => list,
+        None => {
+            if matches.free.is_empty() {
+                matches.free.push("-".to_owned());
+            }
+            return count(matches.free.iter().cloned(), &settings, &matches, true);
+        }
+    };
+    if !matches.free.is_empty() {
+        show_error!("extra operand {}", quote(&matches.free[0]));
+        show_error!("file operands cannot be combined with --files0-from");
+        return 1;
     }
+    let mut names = match Files0::open(&list) {
+        Ok(names) => names,
+        Err(e) => {
+            show_error!("cannot open {} for reading: {}", quote(&list), e);
+            return 1;
+        }
+    };
+    // how many names come down a pipe is not known until the end of it, so
+    // each file's counts are printed as soon as they are known, rather than
+    // lined up with the others
+    let aligned = list != "-" && fs::metadata(&list).map(|m| m.is_file()).unwrap_or(false);
+    let status = count(&mut names, &settings, &matches, aligned);
+    if names.failed { 1 } else { status }
+}
 
-    let settings = Settings::new(&matches);
+#[cfg(all(target_os = "linux", feature = "sandbox"))]
+fn count<I: Iterator<Item = String>>(files: I, settings: &Settings, matches: &Matches, aligned: bool) -> i32 {
+    if matches.opt_present("sandbox") {
+        // the sandbox is told every name before anything is read
+        let files: Vec<String> = files.collect();
+        uucore::sandbox::Sandbox::for_operands(&files, None).enter(1);
+        return wc(files.into_iter(), settings, aligned);
+    }
+    wc(files, settings, aligned)
+}
+
+#[cfg(not(all(target_os = "linux", feature = "sandbox")))]
+fn count<I: Iterator<Item = String>>(files: I, settings: &Settings, _: &Matches, aligned: bool) -> i32 {
+    wc(files, settings, aligned)
+}
+
+/// The file names in a list separated by NUL bytes, read as they are
+/// needed, so that the list can be as long as it likes.  A name that is not
+/// allowed is reported and skipped.
+struct Files0 {
+    list: String,
+    names: io::Split<BufReader<Box<Read>>>,
+    number: usize,
+    failed: bool,
+}
 
-    match wc(matches.free, &settings) {
-        Ok(()) => ( /* pass */ ),
-        Err(e) => return e
+impl Files0 {
+    fn open(list: &str) -> io::Result<Files0> {
+        let reader = if list == "-" {
+            Box::new(stdin()) as Box<Read>
+        } else {
+            Box::new(try!(File::open(list))) as Box<Read>
+        };
+        Ok(Files0 {
+            list: list.to_owned(),
+            names: BufReader::new(reader).split(0),
+            number: 0,
+            failed: false,
+        })
     }
+}
 
-    0
+impl Iterator for Files0 {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        while let Some(name) = self.names.next() {
+            let name = match name {
+                Ok(name) => name,
+                Err(e) => {
+                    show_error!("{}: read error: {}", quote(&self.list), e);
+                    self.failed = true;
+                    return None;
+                }
+            };
+            self.number += 1;
+            if name.is_empty() {
+                show_error!("{}:{}: invalid zero-length file name", quote(&self.list), self.number);
+                self.failed = true;
+            } else if name == b"-" && self.list == "-" {
+                show_error!("when reading file names from standard input, no file name of {} allowed",
+                            quote("-"));
+                self.failed = true;
+            } else {
+                return Some(String::from_utf8_lossy(&name).into_owned());
+            }
+        }
+        None
+    }
 }
 
+// big enough that a file of any size is read in few calls
+const NEWLINES_BUF_SIZE: usize = 256 * 1024;
+
 const CR: u8 = '\r' as u8;
 const LF: u8 = '\n' as u8;
 const SPACE: u8 = ' ' as u8;
@@ -128,7 +234,33 @@ fn is_word_seperator(byte: u8) -> bool {
     byte == SPACE || byte == TAB || byte == CR || byte == SYN || byte == FF
 }
 
-fn wc(files: Vec<String>, settings: &Settings) -> StdResult<(), i32> {
+/// Count the files and print their counts, lined up in columns as wide as
+/// the total number of bytes if `aligned`, which means waiting for the last
+/// file before printing any.
+fn wc<I: Iterator<Item = String>>(files: I, settings: &Settings, aligned: bool) -> i32 {
+    if !settings.lines_and_bytes_only() {
+        return print_counts(files.map(|path| count_by_line(path, settings)), settings, aligned);
+    }
+    if !aligned {
+        let counted = files.map(|path| {
+            let counted = count_newlines(&path);
+            report(path, counted)
+        });
+        return print_counts(counted, settings, aligned);
+    }
+    // nothing is printed until the last file is counted, so they may as
+    // well be counted at the same time
+    let files: Vec<String> = files.collect();
+    let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let counted = count_newlines_all(&files, threads);
+    print_counts(files.into_iter().zip(counted).map(|(path, counted)| report(path, counted)),
+                 settings,
+                 aligned)
+}
+
+// Print the counts of each file, unless it could not be opened, and the
+// total.  Each comes with whether anything went wrong counting it.
+fn print_counts<I: Iterator<Item = (Option<Result>, bool)>>(counted: I, settings: &Settings, aligned: bool) -> i32 {
     let mut total_line_count: usize = 0;
     let mut total_word_count: usize = 0;
     let mut total_char_count: usize = 0;
@@ -137,84 +269,44 @@ fn wc(files: Vec<String>, settings: &Settings) -> StdResult<(), i32> {
 
     let mut results = vec!();
     let mut max_width: usize = 0;
+    let mut count = 0;
+    let mut status = 0;
 
-    for path in &files {
-        let mut reader = try!(open(&path[..]));
-
-        let mut line_count: usize = 0;
-        let mut word_count: usize = 0;
-        let mut byte_count: usize = 0;
-        let mut char_count: usize = 0;
-        let mut longest_line_length: usize = 0;
-        let mut raw_line = Vec::new();
-
-        // reading from a TTY seems to raise a condition on, rather than return Some(0) like a file.
-        // hence the option wrapped in a result here
-        while match reader.read_until(LF, &mut raw_line) {
-            Ok(n) if n > 0 => true,
-            Err(ref e) if !raw_line.is_empty() => {
-                show_warning!("Error while reading {}: {}", path, e);
-                !raw_line.is_empty()
-            },
-            _ => false,
-        } {
-            // GNU 'wc' only counts lines that end in LF as lines
-            if *raw_line.last().unwrap() == LF {
-                line_count += 1;
-            }
-
-            byte_count += raw_line.len();
-
-            // try and convert the bytes to UTF-8 first
-            let current_char_count;
-            match from_utf8(&raw_line[..]) {
-                Ok(line) => {
-                    word_count += line.split_whitespace().count();
-                    current_char_count = line.chars().count();
-                },
-                Err(..) => {
-                    word_count += raw_line.split(|&x| is_word_seperator(x)).count();
-                    current_char_count = raw_line.iter().filter(|c|c.is_ascii()).count()
-                }
-            }
-            char_count += current_char_count;
-
-            if current_char_count > longest_line_length {
-                // we subtract one here because `line.len()` includes the LF
-                // matches GNU 'wc' behaviour
-                longest_line_length = current_char_count - 1;
-            }
-
-            raw_line.truncate(0);
+    for (result, failed) in counted {
+        count += 1;
+        if failed {
+            status = 1;
         }
+        // one file that can't be read doesn't keep the others from being counted
+        let result = match result {
+            Some(result) => result,
+            None => continue,
+        };
 
-        results.push(Result {
-            title: path.clone(),
-            bytes: byte_count,
-            chars: char_count,
-            lines: line_count,
-            words: word_count,
-            max_line_length: longest_line_length,
-        });
-
-        total_line_count += line_count;
-        total_word_count += word_count;
-        total_char_count += char_count;
-        total_byte_count += byte_count;
+        total_line_count += result.lines;
+        total_word_count += result.words;
+        total_char_count += result.chars;
+        total_byte_count += result.bytes;
 
-        if longest_line_length > total_longest_line_length {
-            total_longest_line_length = longest_line_length;
+        if result.max_line_length > total_longest_line_length {
+            total_longest_line_length = result.max_line_length;
         }
 
         // used for formatting
         max_width = total_byte_count.to_string().len() + 1;
+
+        if aligned {
+            results.push(result);
+        } else {
+            print_stats(settings, &result, result.bytes.to_string().len() + 1);
+        }
     }
 
     for result in &results {
         print_stats(settings, &result, max_width);
     }
 
-    if files.len() > 1 {
+    if count > 1 {
         let result = Result {
             title: "total".to_owned(),
             bytes: total_byte_count,
@@ -226,7 +318,188 @@ fn wc(files: Vec<String>, settings: &Settings) -> StdResult<(), i32> {
         print_stats(settings, &result, max_width);
     }
 
-    Ok(())
+    status
+}
+
+// Count everything in `path` a line at a time, saying what went wrong as it
+// happens.
+fn count_by_line(path: String, settings: &Settings) -> (Option<Result>, bool) {
+    let mut reader = match open(&path) {
+        Ok(reader) => reader,
+        Err(e) => {
+            show_error!("{}: {}", path, e);
+            return (None, true);
+        }
+    };
+
+    let mut line_count: usize = 0;
+    let mut word_count: usize = 0;
+    let mut byte_count: usize = 0;
+    let mut char_count: usize = 0;
+    let mut longest_line_length: usize = 0;
+    let mut raw_line = Vec::new();
+    let mut failed = false;
+
+    // reading from a TTY seems to raise a condition on, rather than return Some(0) like a file.
+    // hence the option wrapped in a result here
+    while match reader.read_until(LF, &mut raw_line) {
+        Ok(n) if n > 0 => true,
+        Err(ref e) if !raw_line.is_empty() => {
+            show_warning!("Error while reading {}: {}", path, e);
+            !raw_line.is_empty()
+        },
+        Err(e) => {
+            show_error!("{}: {}", path, e);
+            failed = true;
+            false
+        }
+        _ => false,
+    } {
+        // GNU 'wc' only counts lines that end in LF as lines
+        if *raw_line.last().unwrap() == LF {
+            line_count += 1;
+        }
+
+        byte_count += raw_line.len();
+
+        // try and convert the bytes to UTF-8 first
+        match from_utf8(&raw_line[..]) {
+            Ok(line) => word_count += line.split_whitespace().count(),
+            Err(..) => word_count += raw_line.split(|&x| is_word_seperator(x)).count(),
+        }
+
+        if settings.show_chars || settings.show_max_line_length {
+            let (chars, widest) = measure(&raw_line);
+            char_count += chars;
+            if widest > longest_line_length {
+                longest_line_length = widest;
+            }
+        }
+
+        raw_line.truncate(0);
+    }
+
+    (Some(Result {
+        title: path,
+        bytes: byte_count,
+        chars: char_count,
+        lines: line_count,
+        words: word_count,
+        max_line_length: longest_line_length,
+    }), failed)
+}
+
+// The newlines and bytes in a file, and the error that cut the count short
+// if one did, or the error opening it.
+type Newlines = io::Result<(usize, usize, Option<io::Error>)>;
+
+/// Count the newlines and bytes in `path`, which is all `-l` and `-c` need,
+/// a buffer at a time rather than a line at a time.
+fn count_newlines(path: &str) -> Newlines {
+    let mut reader = try!(open(path));
+    let mut buf = vec![0; NEWLINES_BUF_SIZE];
+    let mut lines = 0;
+    let mut bytes = 0;
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) => return Ok((lines, bytes, None)),
+            Ok(n) => {
+                lines += bytecount::count(&buf[..n], LF);
+                bytes += n;
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Ok((lines, bytes, Some(e))),
+        }
+    }
+}
+
+/// Count the newlines in each of `files` on up to `threads` threads, each
+/// taking the next file not yet counted, and give the counts in the order
+/// the files were named.
+fn count_newlines_all(files: &[String], threads: usize) -> Vec<Newlines> {
+    let threads = cmp::min(threads, files.len());
+    if threads <= 1 {
+        return files.iter().map(|path| count_newlines(path)).collect();
+    }
+    let next = AtomicUsize::new(0);
+    let mut counted: Vec<Option<Newlines>> = files.iter().map(|_| None).collect();
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads).map(|_| {
+            scope.spawn(|| {
+                let mut done = vec![];
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= files.len() {
+                        return done;
+                    }
+                    done.push((i, count_newlines(&files[i])));
+                }
+            })
+        }).collect();
+        for worker in workers {
+            for (i, newlines) in worker.join().unwrap() {
+                counted[i] = Some(newlines);
+            }
+        }
+    });
+    counted.into_iter().map(|newlines| newlines.unwrap()).collect()
+}
+
+// Say what went wrong counting the newlines in `path`, and give its counts.
+fn report(path: String, newlines: Newlines) -> (Option<Result>, bool) {
+    let (lines, bytes, error) = match newlines {
+        Ok(newlines) => newlines,
+        Err(e) => {
+            show_error!("{}: {}", path, e);
+            return (None, true);
+        }
+    };
+    if let Some(ref e) = error {
+        show_error!("{}: {}", path, e);
+    }
+    (Some(Result {
+        title: path,
+        bytes: bytes,
+        chars: 0,
+        lines: lines,
+        words: 0,
+        max_line_length: 0,
+    }), error.is_some())
+}
+
+/// The characters in `line`, and how many columns the widest part of it
+/// takes up, as (characters, columns).  Line breaks, carriage returns and
+/// form feeds start a new part, tabs stop at every eighth column, and
+/// characters take up as many columns as a terminal gives them.  A byte that
+/// is not part of a valid UTF-8 character counts as a character one column
+/// wide.
+fn measure(line: &[u8]) -> (usize, usize) {
+    let mut chars = 0;
+    let mut column = 0;
+    let mut widest = 0;
+    let mut i = 0;
+    while i < line.len() {
+        let len = uucore::utf8::utf8_char_width(line[i]);
+        let c = line.get(i..i + len)
+            .and_then(|bytes| from_utf8(bytes).ok())
+            .and_then(|s| s.chars().next());
+        chars += 1;
+        match c {
+            Some('\n') | Some('\r') | Some('\x0c') => {
+                widest = cmp::max(widest, column);
+                column = 0;
+            }
+            Some('\t') => column += 8 - column % 8,
+            Some(c) => column += c.width().unwrap_or(0),
+            None => {
+                column += 1;
+                i += 1;
+                continue;
+            }
+        }
+        i += len;
+    }
+    (chars, cmp::max(widest, column))
 }
 
 fn print_stats(settings: &Settings, result: &Result, max_width: usize) {
@@ -236,12 +509,12 @@ fn print_stats(settings: &Settings, result: &Result, max_width: usize) {
     if settings.show_words {
         print!("{:1$}", result.words, max_width);
     }
-    if settings.show_bytes {
-        print!("{:1$}", result.bytes, max_width);
-    }
     if settings.show_chars {
         print!("{:1$}", result.chars, max_width);
     }
+    if settings.show_bytes {
+        print!("{:1$}", result.bytes, max_width);
+    }
     if settings.show_max_line_length {
         print!("{:1$}", result.max_line_length, max_width);
     }
@@ -254,24 +527,36 @@ fn print_stats(settings: &Settings, result: &Result, max_width: usize) {
     }
 }
 
-fn open(path: &str) -> StdResult<BufReader<Box<Read+'static>>, i32> {
+fn open(path: &str) -> io::Result<BufReader<Box<Read+'static>>> {
     if "-" == path {
         let reader = Box::new(stdin()) as Box<Read>;
         return Ok(BufReader::new(reader));
     }
 
-    let fpath = Path::new(path);
-    if fpath.is_dir() {
-        show_info!("{}: is a directory", path);
-    }
-    match File::open(&fpath) {
-        Ok(fd) => {
-            let reader = Box::new(fd) as Box<Read>;
-            Ok(BufReader::new(reader))
-        }
-        Err(e) => {
-            show_error!("wc: {}: {}", path, e);
-            Err(1)
+    let fd = try!(File::open(Path::new(path)));
+    Ok(BufReader::new(Box::new(fd) as Box<Read>))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_newlines_all() {
+        let fixture = |name| format!("{}/../../tests/fixtures/wc/{}", env!("CARGO_MANIFEST_DIR"), name);
+        let files: Vec<String> = (0..25)
+            .map(|i| fixture(if i % 2 == 0 { "lorem_ipsum.txt" } else { "moby_dick.txt" }))
+            .chain(Some(fixture("missing.txt")))
+            .collect();
+        for &threads in &[1, 4, 100] {
+            let counted = count_newlines_all(&files, threads);
+            assert_eq!(counted.len(), files.len());
+            for (i, newlines) in counted[..25].iter().enumerate() {
+                let (lines, bytes, ref error) = *newlines.as_ref().unwrap();
+                assert!(error.is_none());
+                assert_eq!((lines, bytes), if i % 2 == 0 { (13, 772) } else { (18, 1115) });
+            }
+            assert!(counted[25].is_err());
         }
     }
 }
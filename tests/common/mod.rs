@@ -1,3 +1,4 @@
 #[macro_use]
 pub mod macros;
 pub mod util;
+pub mod random;
@@ -0,0 +1,78 @@
+//! Small random inputs and options, for checking utilities against simple
+//! models of what they should do, and against GNU's where it is installed.
+
+#![allow(dead_code)]
+
+extern crate rand;
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+use self::rand::SeedableRng;
+
+pub use self::rand::{Rng, XorShiftRng};
+
+/// How many random cases a test tries.
+pub const CASES: usize = 300;
+
+/// A generator that gives the same cases on every run, so that a failure can
+/// be reproduced.
+pub fn rng() -> XorShiftRng {
+    XorShiftRng::from_seed([0x193a_6754, 0xa8a7_d469, 0x9783_0e05, 0x113b_a7bb])
+}
+
+/// A string of up to `max_len` characters from `alphabet`, but at least
+/// `min_len`.
+pub fn word<R: Rng>(rng: &mut R, alphabet: &[char], min_len: usize, max_len: usize) -> String {
+    let len = rng.gen_range(min_len, max_len + 1);
+    (0..len).map(|_| *rng.choose(alphabet).unwrap()).collect()
+}
+
+/// Up to `max` lines from `line`, each ended by a newline.
+pub fn lines<R: Rng, F: FnMut(&mut R) -> String>(rng: &mut R, max: usize, mut line: F) -> Vec<String> {
+    let count = rng.gen_range(0, max + 1);
+    (0..count).map(|_| line(rng)).collect()
+}
+
+/// Whether to give the option `name`, which is added to `args` if so, one
+/// time in four.
+pub fn flag<R: Rng>(rng: &mut R, args: &mut Vec<String>, name: &str) -> bool {
+    let given = rng.gen_weighted_bool(4);
+    if given {
+        args.push(name.to_owned());
+    }
+    given
+}
+
+/// The lines as a file holds them.
+pub fn text(lines: &[String]) -> String {
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+/// GNU's `util`, if that is what `util` is on this system.
+pub fn gnu(util: &str) -> Option<Gnu> {
+    match Command::new(util).arg("--version").output() {
+        Ok(ref output) if String::from_utf8_lossy(&output.stdout).contains("GNU coreutils") => {
+            Some(Gnu { util: util.to_owned() })
+        }
+        _ => None,
+    }
+}
+
+pub struct Gnu {
+    util: String,
+}
+
+impl Gnu {
+    /// What the utility prints given `args` and `input`, in the C locale.
+    pub fn run(&self, args: &[String], input: &str) -> String {
+        let mut child = Command::new(&self.util)
+            .args(args)
+            .env("LC_ALL", "C")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+        String::from_utf8(child.wait_with_output().unwrap().stdout).unwrap()
+    }
+}
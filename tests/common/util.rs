@@ -49,6 +49,8 @@ pub struct CmdResult {
     //tmpd is used for convenience functions for asserts against fixtures
     tmpd: Option<Rc<TempDir>>,
     pub success: bool,
+    /// the status code, unless a signal ended the command
+    pub code: Option<i32>,
     pub stdout: String,
     pub stderr: String,
 }
@@ -66,6 +68,12 @@ impl CmdResult {
         Box::new(self)
     }
 
+    /// asserts that the command exited with the status code `code`
+    pub fn code_is(&self, code: i32) -> Box<&CmdResult> {
+        assert_eq!(Some(code), self.code);
+        Box::new(self)
+    }
+
     /// asserts that the command resulted in empty (zero-length) stderr stream output
     /// generally, it's better to use stdout_only() instead,
     /// but you might find yourself using this function if
@@ -399,6 +407,19 @@ impl TestScenario {
         cmd
     }
 
+    /// The utility, started by `sh` once it has run the commands in `setup`,
+    /// so that it gets the limits `ulimit` sets there, or the signals
+    /// `trap` ignores.
+    #[cfg(unix)]
+    pub fn ucmd_after(&self, setup: &str) -> UCommand {
+        let mut cmd = self.cmd("sh");
+        cmd.arg("-c");
+        cmd.arg(format!("{}; exec \"$0\" \"$@\"", setup));
+        cmd.arg(&self.bin_path);
+        cmd.arg(&self.util_name);
+        cmd
+    }
+
     pub fn cmd<S: AsRef<OsStr>>(&self, bin: S) -> UCommand {
         UCommand::new_from_tmp(bin, self.tmpd.clone(), true)
     }
@@ -556,6 +577,7 @@ impl UCommand {
         CmdResult {
             tmpd: self.tmpd.clone(),
             success: prog.status.success(),
+            code: prog.status.code(),
             stdout: from_utf8(&prog.stdout).unwrap().to_string(),
             stderr: from_utf8(&prog.stderr).unwrap().to_string(),
         }
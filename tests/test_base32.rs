@@ -83,6 +83,6 @@ fn test_wrap_bad_arg() {
         new_ucmd!()
             .arg(wrap_param).arg("b")
             .fails()
-            .stderr_only("base32: error: invalid wrap size: ‘b’: invalid digit found in string\n");
+            .stderr_only("base32: error: invalid wrap size: 'b': invalid digit found in string\n");
     }
 }
@@ -76,6 +76,16 @@ fn test_wrap_bad_arg() {
             .arg(wrap_param)
             .arg("b")
             .fails()
-            .stderr_only("base64: error: invalid wrap size: ‘b’: invalid digit found in string\n");
+            .stderr_only("base64: error: invalid wrap size: 'b': invalid digit found in string\n");
     }
 }
+
+#[test]
+fn test_wrap_bad_arg_utf8_locale() {
+    new_ucmd!()
+        .arg("-w")
+        .arg("b")
+        .env("LC_ALL", "en_US.UTF-8")
+        .fails()
+        .stderr_only("base64: error: invalid wrap size: \u{2018}b\u{2019}: invalid digit found in string\n");
+}
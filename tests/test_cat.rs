@@ -94,6 +94,33 @@ fn test_stdin_nonprinting_and_tabs() {
         .stdout_only("^I^@\n");
 }
 
+#[test]
+fn test_show_tabs_across_lines() {
+    new_ucmd!()
+        .arg("-T")
+        .pipe_in("a\tb\tc\n\td\n")
+        .succeeds()
+        .stdout_only("a^Ib^Ic\n^Id\n");
+}
+
+#[test]
+fn test_show_nonprinting_high_bytes() {
+    new_ucmd!()
+        .arg("-v")
+        .pipe_in(&b"\x80\x89\xa0\xc1\xff\x7f\t\n"[..])
+        .succeeds()
+        .stdout_only("M-^@M-^IM- M-AM-^?^?\t\n");
+}
+
+#[test]
+fn test_show_ends_carriage_return() {
+    new_ucmd!()
+        .arg("-E")
+        .pipe_in("a\r\nb\rc\r")
+        .succeeds()
+        .stdout_only("a^M$\nb\rc\r");
+}
+
 #[test]
 fn test_stdin_squeeze_blank() {
     for same_param in vec!["-s", "--squeeze-blank"] {
@@ -105,6 +132,30 @@ fn test_stdin_squeeze_blank() {
     }
 }
 
+#[test]
+fn test_squeeze_blank_across_files() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("a");
+    at.append("a", "a\n\n");
+    at.touch("b");
+    at.append("b", "\n\nb\n");
+    ucmd.args(&["-s", "-n", "a", "b"])
+        .succeeds()
+        .stdout_only("     1\ta\n     2\t\n     3\tb\n");
+}
+
+#[test]
+fn test_squeeze_blank_line_across_reads() {
+    // the second newline ends the line started by the first read, and is
+    // not a blank line of its own
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("a");
+    at.append("a", &format!("{}\n\nb\n", "x".repeat(1024 * 31)));
+    ucmd.args(&["-s", "a"])
+        .succeeds()
+        .stdout_only(format!("{}\n\nb\n", "x".repeat(1024 * 31)));
+}
+
 #[test]
 fn test_stdin_number_non_blank() {
     for same_param in vec!["-b", "--number-nonblank"] {
@@ -139,6 +190,25 @@ fn test_squeeze_blank_before_numbering() {
     }
 }
 
+#[test]
+fn test_buffer_size_from_env() {
+    // buffers smaller than the input, and pipes asked to grow, change nothing
+    let input: String = (0..1000).map(|n| format!("{}\n", n)).collect();
+    for &(var, size) in &[("UUTILS_BUFFER_SIZE", "7"), ("UUTILS_PIPE_SIZE", "1M"), ("UUTILS_BUFFER_SIZE", "x")] {
+        new_ucmd!()
+            .env(var, size)
+            .pipe_in(input.clone())
+            .succeeds()
+            .stdout_only(input.clone());
+        new_ucmd!()
+            .env(var, size)
+            .arg("-n")
+            .pipe_in("a\nb\n")
+            .succeeds()
+            .stdout_only("     1\ta\n     2\tb\n");
+    }
+}
+
 
 
 #[test]
@@ -165,3 +235,12 @@ fn test_domain_socket() {
 
     thread.join().unwrap();
 }
+
+#[test]
+#[cfg(all(target_os = "linux", feature = "sandbox"))]
+fn test_sandbox_reads_operands() {
+    new_ucmd!()
+        .args(&["--sandbox", "alpha.txt"])
+        .succeeds()
+        .stdout_only("abcde\nfghij\nklmno\npqrst\nuvwxyz\n");
+}
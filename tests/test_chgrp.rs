@@ -136,3 +136,19 @@ fn test_big_h() {
         .lines()
         .fold(0, |acc, _| acc + 1) > 1);
 }
+
+#[test]
+#[cfg(unix)]
+fn test_no_dereference() {
+    use std::os::unix::fs::symlink;
+
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("file");
+    // only the link itself can be changed, having nothing to point to
+    symlink("missing", at.plus("dangling")).unwrap();
+
+    ucmd.arg("-h")
+        .arg("--reference=file")
+        .arg("dangling")
+        .succeeds();
+}
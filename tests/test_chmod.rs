@@ -1,6 +1,6 @@
 use common::util::*;
 use std::fs::{metadata, OpenOptions, set_permissions};
-use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::os::unix::fs::{symlink, OpenOptionsExt, PermissionsExt};
 use std::sync::Mutex;
 
 extern crate libc;
@@ -147,3 +147,25 @@ fn test_chmod_reference_file() {
     mkfile(&at.plus_as_string(REFERENCE_FILE), REFERENCE_PERMS);
     run_single_test(&tests[0], at, ucmd);
 }
+
+#[test]
+fn test_chmod_no_dereference() {
+    let tests = vec!{
+        TestCase{args: vec!{"-h", "0640", TEST_FILE}, before: 0o100600, after: 0o100640},
+    };
+    run_tests(tests);
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_chmod_no_dereference_symlink() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    mkfile(&at.plus_as_string(TEST_FILE), 0o100600);
+    symlink(TEST_FILE, at.plus("link")).unwrap();
+
+    // links have no mode of their own on Linux
+    ucmd.arg("-h").arg("0640").arg("link")
+        .fails()
+        .stderr_is("chmod: error: changing permissions of 'link': Operation not supported");
+    assert_eq!(at.metadata(TEST_FILE).permissions().mode(), 0o100600);
+}
@@ -0,0 +1,17 @@
+use common::util::*;
+
+
+#[test]
+fn test_missing_operand() {
+    new_ucmd!().fails()
+        .stderr_is("chroot: missing operand\nTry 'chroot --help' for more information.\n");
+}
+
+#[test]
+fn test_no_such_directory() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("a");
+
+    ucmd.arg("a").fails()
+        .stderr_is("chroot: error: cannot change root directory to 'a': no such directory\n");
+}
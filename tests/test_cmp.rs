@@ -0,0 +1,62 @@
+use common::util::*;
+
+
+#[test]
+fn test_cmp_same() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("a");
+    at.append("a", "abc\n");
+    ucmd.arg("a").arg("-").pipe_in("abc\n").succeeds().no_stdout();
+}
+
+#[test]
+fn test_cmp_differ() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("a");
+    at.append("a", "a\nbX\nc");
+    at.touch("b");
+    at.append("b", "a\nbY\nd");
+    ucmd.arg("a").arg("b").fails().stdout_only("a b differ: char 4, line 2\n");
+}
+
+#[test]
+fn test_cmp_verbose() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("a");
+    at.append("a", "a\nbX\nc");
+    at.touch("b");
+    at.append("b", "a\nbY\ndef");
+    let result = ucmd.arg("-l").arg("a").arg("b").run();
+    assert!(!result.success);
+    assert_eq!(result.stdout, "4 130 131\n6 143 144\n");
+    assert_eq!(result.stderr, "cmp: EOF on a after byte 6\n");
+}
+
+#[test]
+fn test_cmp_eof() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("a");
+    at.append("a", "abc");
+    ucmd.arg("a").arg("-").pipe_in("abcd").fails().stderr_is("cmp: EOF on a after byte 3, in line 1");
+}
+
+#[test]
+fn test_cmp_empty() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("empty");
+    ucmd.arg("empty").arg("-").pipe_in("a").fails().stderr_is("cmp: EOF on empty which is empty");
+}
+
+#[test]
+fn test_cmp_silent() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("a");
+    at.append("a", "abc");
+    ucmd.arg("-s").arg("a").arg("-").pipe_in("abd").fails().no_stdout();
+}
+
+#[test]
+fn test_cmp_missing_file() {
+    new_ucmd!().arg("nonexistent").arg("-").fails()
+        .stderr_is("cmp: error: nonexistent: No such file or directory (os error 2)");
+}
@@ -1,4 +1,5 @@
 use common::util::*;
+use std::io::Read;
 
 
 #[test]
@@ -134,3 +135,23 @@ fn no_arguments() {
 fn one_argument() {
     new_ucmd!().arg("a").fails().no_stdout().no_stderr();
 }
+
+#[test]
+#[cfg(feature = "decompress")]
+fn ab_decompress() {
+    new_ucmd!().args(&["--decompress", "a.gz", "b.zst"]).succeeds().stdout_only_fixture("ab.expected");
+
+    // the fixture is binary, so it cannot go through pipe_in_fixture()
+    let (at, mut ucmd) = at_and_ucmd!();
+    let mut compressed = Vec::new();
+    at.open("b.zst").read_to_end(&mut compressed).unwrap();
+    ucmd.args(&["--decompress", "a", "-"]).pipe_in(compressed).succeeds().stdout_only_fixture("ab.expected");
+}
+
+#[test]
+fn crlf() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("crlf");
+    at.append("crlf", "a\r\nb\r\n");
+    ucmd.args(&["--crlf", "crlf", "-"]).pipe_in("b\nc\n").succeeds().stdout_only("a\r\n\t\tb\r\n\tc\r\n");
+}
@@ -1,5 +1,12 @@
+extern crate filetime;
+#[cfg(unix)]
+extern crate libc;
+
 use common::util::*;
-use std::fs::set_permissions;
+use self::filetime::FileTime;
+use std::fs::{self, set_permissions};
+#[cfg(unix)]
+use std::os::unix::fs::{symlink, MetadataExt, PermissionsExt};
 
 static TEST_EXISTING_FILE:           &str = "existing_file.txt";
 static TEST_HELLO_WORLD_SOURCE:      &str = "hello_world.txt";
@@ -96,6 +103,24 @@ fn test_cp_recurse() {
     assert_eq!(at.read(TEST_COPY_TO_FOLDER_NEW_FILE), "Hello, World!\n");
 }
 
+#[test]
+#[cfg(unix)]
+fn test_cp_recurse_one_file_system() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.mkdir("hello_dir_with_file/sub");
+    at.touch("hello_dir_with_file/sub/file");
+
+    // all on one file system, so nothing is left out
+    ucmd.arg("-r")
+        .arg("-x")
+        .arg(TEST_COPY_FROM_FOLDER)
+        .arg(TEST_COPY_TO_FOLDER_NEW)
+        .succeeds();
+
+    assert_eq!(at.read(TEST_COPY_TO_FOLDER_NEW_FILE), "Hello, World!\n");
+    assert!(at.file_exists("hello_dir_new/sub/file"));
+}
+
 #[test]
 fn test_cp_with_dirs_t() {
     let (at, mut ucmd) = at_and_ucmd!();
@@ -196,6 +221,16 @@ fn test_cp_arg_symlink() {
     assert!(at.is_symlink(TEST_HELLO_WORLD_DEST));
 }
 
+#[test]
+fn test_cp_arg_symlink_relative_into_dir() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    ucmd.args(&["-s", TEST_HELLO_WORLD_SOURCE, TEST_COPY_TO_FOLDER_FILE])
+        .fails()
+        .stderr_is(format!("cp: error: {}: can make relative symbolic links only in current directory",
+                           TEST_COPY_TO_FOLDER_FILE));
+    assert!(!at.file_exists(TEST_COPY_TO_FOLDER_FILE));
+}
+
 
 #[test]
 fn test_cp_arg_no_clobber() {
@@ -255,6 +290,103 @@ fn test_cp_arg_remove_destination() {
     assert_eq!(at.read(TEST_HELLO_WORLD_DEST), "Hello, World!\n");
 }
 
+#[test]
+#[cfg(unix)]
+fn test_cp_arg_remove_destination_links() {
+    let ts = TestScenario::new(util_name!());
+    let at = &ts.fixtures;
+    fs::hard_link(at.plus(TEST_HELLO_WORLD_SOURCE), at.plus("hard")).unwrap();
+    at.symlink(TEST_HELLO_WORLD_SOURCE, "soft");
+    at.symlink("nowhere", "dangling");
+
+    // each is replaced by a copy, leaving the source alone
+    ts.ucmd().arg("--remove-destination")
+        .arg(TEST_HELLO_WORLD_SOURCE)
+        .arg("hard")
+        .succeeds();
+    for dest in &["soft", "dangling"] {
+        ts.ucmd().arg("--remove-destination")
+            .arg(TEST_HELLO_WORLD_SOURCE)
+            .arg(dest)
+            .succeeds();
+        assert!(!at.is_symlink(dest));
+    }
+    for dest in &["hard", "soft", "dangling"] {
+        assert_eq!(at.read(dest), "Hello, World!\n");
+        assert_ne!(at.metadata(dest).ino(), at.metadata(TEST_HELLO_WORLD_SOURCE).ino());
+    }
+    assert_eq!(at.read(TEST_HELLO_WORLD_SOURCE), "Hello, World!\n");
+    assert!(!at.file_exists("nowhere"));
+}
+
+#[test]
+fn test_cp_arg_remove_destination_same_file() {
+    new_ucmd!()
+        .arg("--remove-destination")
+        .arg(TEST_HELLO_WORLD_SOURCE)
+        .arg(format!("./{}", TEST_HELLO_WORLD_SOURCE))
+        .fails();
+}
+
+#[test]
+#[cfg(unix)]
+fn test_cp_arg_attributes_only() {
+    let ts = TestScenario::new(util_name!());
+    let at = &ts.fixtures;
+    set_permissions(at.plus(TEST_HELLO_WORLD_SOURCE), PermissionsExt::from_mode(0o600)).unwrap();
+    fs::hard_link(at.plus(TEST_EXISTING_FILE), at.plus("other_link")).unwrap();
+    let existing = at.read(TEST_EXISTING_FILE);
+
+    // a new file is empty, an existing one and its other links untouched
+    ts.ucmd().arg("--attributes-only")
+        .arg(TEST_HELLO_WORLD_SOURCE)
+        .arg(TEST_HELLO_WORLD_DEST)
+        .succeeds();
+    assert_eq!(at.read(TEST_HELLO_WORLD_DEST), "");
+    assert_eq!(at.metadata(TEST_HELLO_WORLD_DEST).permissions().mode() & 0o777, 0o600);
+
+    ts.ucmd().arg("--attributes-only")
+        .arg("--preserve=mode")
+        .arg(TEST_HELLO_WORLD_SOURCE)
+        .arg(TEST_EXISTING_FILE)
+        .succeeds();
+    assert_eq!(at.read(TEST_EXISTING_FILE), existing);
+    assert_eq!(at.metadata("other_link").permissions().mode() & 0o777, 0o600);
+
+    // unless it is removed first, which leaves the other links be
+    ts.ucmd().arg("--attributes-only")
+        .arg("--remove-destination")
+        .arg(TEST_HELLO_WORLD_SOURCE)
+        .arg("other_link")
+        .succeeds();
+    assert_eq!(at.read("other_link"), "");
+    assert_eq!(at.read(TEST_EXISTING_FILE), existing);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_cp_arg_attributes_only_symlink() {
+    let ts = TestScenario::new(util_name!());
+    let at = &ts.fixtures;
+    at.symlink(TEST_HELLO_WORLD_SOURCE, "link");
+
+    // a link copied as a link is made as usual, one followed gives an
+    // empty file
+    ts.ucmd().arg("--attributes-only")
+        .arg("-P")
+        .arg("link")
+        .arg("link_copy")
+        .succeeds();
+    assert!(at.is_symlink("link_copy"));
+    ts.ucmd().arg("--attributes-only")
+        .arg("link")
+        .arg(TEST_HELLO_WORLD_DEST)
+        .succeeds();
+    assert!(!at.is_symlink(TEST_HELLO_WORLD_DEST));
+    assert_eq!(at.read(TEST_HELLO_WORLD_DEST), "");
+    assert_eq!(at.read(TEST_HELLO_WORLD_SOURCE), "Hello, World!\n");
+}
+
 #[test]
 fn test_cp_arg_backup() {
     let (at, mut ucmd) = at_and_ucmd!();
@@ -283,3 +415,488 @@ fn test_cp_arg_suffix() {
     assert_eq!(at.read(TEST_HOW_ARE_YOU_SOURCE), "Hello, World!\n");
     assert_eq!(at.read(&*format!("{}.bak", TEST_HOW_ARE_YOU_SOURCE)), "How are you?\n");
 }
+
+#[test]
+fn test_cp_arg_backup_numbered() {
+    let scene = TestScenario::new(util_name!());
+    let at = &scene.fixtures;
+
+    scene.ucmd().arg("--backup=numbered").arg(TEST_HELLO_WORLD_SOURCE).arg(TEST_HOW_ARE_YOU_SOURCE)
+        .succeeds().no_stdout();
+    scene.ucmd().arg("-v").arg("--backup=nu").arg(TEST_EXISTING_FILE).arg(TEST_HOW_ARE_YOU_SOURCE)
+        .succeeds()
+        .stdout_only(format!("'{0}' -> '{1}' (backup: '{1}.~2~')\n", TEST_EXISTING_FILE, TEST_HOW_ARE_YOU_SOURCE));
+
+    assert_eq!(at.read(&*format!("{}.~1~", TEST_HOW_ARE_YOU_SOURCE)), "How are you?\n");
+    assert_eq!(at.read(&*format!("{}.~2~", TEST_HOW_ARE_YOU_SOURCE)), "Hello, World!\n");
+}
+
+#[test]
+fn test_cp_arg_backup_version_control() {
+    let (at, mut ucmd) = at_and_ucmd!();
+
+    let result = ucmd.arg("-b")
+        .env("VERSION_CONTROL", "t")
+        .arg(TEST_HELLO_WORLD_SOURCE)
+        .arg(TEST_HOW_ARE_YOU_SOURCE)
+        .run();
+
+    assert!(result.success);
+    assert_eq!(at.read(TEST_HOW_ARE_YOU_SOURCE), "Hello, World!\n");
+    assert_eq!(at.read(&*format!("{}.~1~", TEST_HOW_ARE_YOU_SOURCE)), "How are you?\n");
+}
+
+#[test]
+fn test_cp_arg_backup_invalid() {
+    new_ucmd!()
+        .arg("--backup=n")
+        .arg(TEST_HELLO_WORLD_SOURCE)
+        .arg(TEST_HOW_ARE_YOU_SOURCE)
+        .fails()
+        .stderr_is("cp: error: ambiguous argument 'n' for 'backup type'\n\
+                    Valid arguments are:\n  - 'none', 'off'\n  - 'simple', 'never'\n  \
+                    - 'existing', 'nil'\n  - 'numbered', 't'");
+}
+
+#[test]
+fn test_cp_arg_backup_no_clobber() {
+    new_ucmd!()
+        .arg("-b")
+        .arg("-n")
+        .arg(TEST_HELLO_WORLD_SOURCE)
+        .arg(TEST_HOW_ARE_YOU_SOURCE)
+        .fails()
+        .stderr_is("cp: error: options --backup and --no-clobber are mutually exclusive");
+}
+
+#[test]
+fn test_cp_arg_reflink_auto_truncates_dest() {
+    let (at, mut ucmd) = at_and_ucmd!();
+
+    // the destination is longer than the source
+    let result = ucmd.arg(TEST_HOW_ARE_YOU_SOURCE)
+        .arg("--reflink=auto")
+        .arg(TEST_EXISTING_FILE)
+        .run();
+
+    assert!(result.success);
+    assert_eq!(at.read(TEST_EXISTING_FILE), "How are you?\n");
+}
+
+#[test]
+fn test_cp_arg_reflink_never() {
+    let (at, mut ucmd) = at_and_ucmd!();
+
+    let result = ucmd.arg(TEST_HELLO_WORLD_SOURCE)
+        .arg("--reflink=never")
+        .arg(TEST_HELLO_WORLD_DEST)
+        .run();
+
+    assert!(result.success);
+    assert_eq!(at.read(TEST_HELLO_WORLD_DEST), "Hello, World!\n");
+}
+
+#[test]
+fn test_cp_arg_reflink_always() {
+    let (at, mut ucmd) = at_and_ucmd!();
+
+    // whether this works depends on the filesystem the tests run on
+    let result = ucmd.arg(TEST_HELLO_WORLD_SOURCE)
+        .arg("--reflink")
+        .arg(TEST_HELLO_WORLD_DEST)
+        .run();
+
+    if result.success {
+        assert_eq!(at.read(TEST_HELLO_WORLD_DEST), "Hello, World!\n");
+    } else {
+        assert_eq!(result.stderr,
+                   format!("cp: error: failed to clone '{}' from '{}': Operation not supported\n",
+                           TEST_HELLO_WORLD_DEST, TEST_HELLO_WORLD_SOURCE));
+    }
+}
+
+#[test]
+fn test_cp_arg_reflink_invalid() {
+    new_ucmd!()
+        .arg("--reflink=sometimes")
+        .arg(TEST_HELLO_WORLD_SOURCE)
+        .arg(TEST_HELLO_WORLD_DEST)
+        .fails()
+        .stderr_is("cp: error: invalid argument 'sometimes' for '--reflink'\n\
+                    Valid arguments are:\n  - 'auto'\n  - 'always'\n  - 'never'");
+}
+
+#[test]
+fn test_cp_arg_sparse_always() {
+    let (at, mut ucmd) = at_and_ucmd!();
+
+    let result = ucmd.arg(TEST_HELLO_WORLD_SOURCE)
+        .arg("--sparse=always")
+        .arg(TEST_HELLO_WORLD_DEST)
+        .run();
+
+    assert!(result.success);
+    assert_eq!(at.read(TEST_HELLO_WORLD_DEST), "Hello, World!\n");
+}
+
+#[test]
+fn test_cp_arg_sparse_invalid() {
+    new_ucmd!()
+        .arg("--sparse=sometimes")
+        .arg(TEST_HELLO_WORLD_SOURCE)
+        .arg(TEST_HELLO_WORLD_DEST)
+        .fails()
+        .stderr_is("cp: error: invalid argument 'sometimes' for '--sparse'\n\
+                    Valid arguments are:\n  - 'never'\n  - 'auto'\n  - 'always'");
+}
+
+#[test]
+fn test_cp_arg_sparse_with_reflink() {
+    new_ucmd!()
+        .arg("--sparse=never")
+        .arg("--reflink=always")
+        .arg(TEST_HELLO_WORLD_SOURCE)
+        .arg(TEST_HELLO_WORLD_DEST)
+        .fails()
+        .stderr_is("cp: error: --reflink can be used only with --sparse=auto");
+}
+
+#[test]
+#[cfg(unix)]
+fn test_cp_archive() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    let mtime = FileTime::from_seconds_since_1970(1234567890, 0);
+    set_permissions(at.plus(TEST_COPY_FROM_FOLDER_FILE), fs::Permissions::from_mode(0o640)).unwrap();
+    filetime::set_file_times(at.plus(TEST_COPY_FROM_FOLDER_FILE), mtime, mtime).unwrap();
+    symlink("hello_world.txt", at.plus("hello_dir_with_file/link")).unwrap();
+    filetime::set_file_times(at.plus(TEST_COPY_FROM_FOLDER), mtime, mtime).unwrap();
+
+    let result = ucmd.arg("-a")
+        .arg(TEST_COPY_FROM_FOLDER)
+        .arg(TEST_COPY_TO_FOLDER_NEW)
+        .run();
+
+    assert!(result.success);
+    assert_eq!(at.read(TEST_COPY_TO_FOLDER_NEW_FILE), "Hello, World!\n");
+    let metadata = at.metadata(TEST_COPY_TO_FOLDER_NEW_FILE);
+    assert_eq!(metadata.permissions().mode() & 0o7777, 0o640);
+    assert_eq!(FileTime::from_last_modification_time(&metadata), mtime);
+    // the link is copied as it is, not what it points to
+    assert!(at.is_symlink("hello_dir_new/link"));
+    assert_eq!(fs::read_link(at.plus("hello_dir_new/link")).unwrap().to_str(), Some("hello_world.txt"));
+    // and the directory keeps its time despite being filled
+    assert_eq!(FileTime::from_last_modification_time(&at.metadata(TEST_COPY_TO_FOLDER_NEW)), mtime);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_cp_arg_no_dereference() {
+    let scene = TestScenario::new(util_name!());
+    let at = &scene.fixtures;
+    symlink(TEST_HELLO_WORLD_SOURCE, at.plus("link")).unwrap();
+
+    scene.ucmd().arg("-P").arg("link").arg(TEST_HELLO_WORLD_DEST).succeeds().no_stdout();
+    assert!(at.is_symlink(TEST_HELLO_WORLD_DEST));
+
+    // without it, only recursive copies leave links alone
+    scene.ucmd().arg("link").arg("copy").succeeds().no_stdout();
+    assert!(!at.is_symlink("copy"));
+    assert_eq!(at.read("copy"), "Hello, World!\n");
+}
+
+#[test]
+#[cfg(unix)]
+fn test_cp_arg_no_preserve() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    let mtime = FileTime::from_seconds_since_1970(1234567890, 0);
+    filetime::set_file_times(at.plus(TEST_HELLO_WORLD_SOURCE), mtime, mtime).unwrap();
+
+    let result = ucmd.arg("-a")
+        .arg("--no-preserve=timestamps")
+        .arg(TEST_HELLO_WORLD_SOURCE)
+        .arg(TEST_HELLO_WORLD_DEST)
+        .run();
+
+    assert!(result.success);
+    assert!(FileTime::from_last_modification_time(&at.metadata(TEST_HELLO_WORLD_DEST)) != mtime);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_cp_preserve_links() {
+    use std::os::unix::fs::MetadataExt;
+
+    let (at, mut ucmd) = at_and_ucmd!();
+    fs::hard_link(at.plus(TEST_COPY_FROM_FOLDER_FILE), at.plus("hello_dir_with_file/link")).unwrap();
+
+    ucmd.arg("-R")
+        .arg("--preserve=links")
+        .arg(TEST_COPY_FROM_FOLDER)
+        .arg(TEST_COPY_TO_FOLDER_NEW)
+        .succeeds()
+        .no_stderr();
+
+    let copy = at.metadata(TEST_COPY_TO_FOLDER_NEW_FILE);
+    assert_eq!(copy.ino(), at.metadata("hello_dir_new/link").ino());
+    assert_eq!(copy.nlink(), 2);
+    assert!(copy.ino() != at.metadata(TEST_COPY_FROM_FOLDER_FILE).ino());
+}
+
+#[test]
+#[cfg(unix)]
+fn test_cp_preserve_links_across_operands() {
+    use std::os::unix::fs::MetadataExt;
+
+    let scene = TestScenario::new(util_name!());
+    let at = &scene.fixtures;
+    fs::hard_link(at.plus(TEST_HELLO_WORLD_SOURCE), at.plus("link")).unwrap();
+
+    scene.ucmd().arg("-a").arg(TEST_HELLO_WORLD_SOURCE).arg("link").arg(TEST_COPY_TO_FOLDER).succeeds().no_stderr();
+    assert_eq!(at.metadata(TEST_COPY_TO_FOLDER_FILE).ino(), at.metadata("hello_dir/link").ino());
+
+    // without it, each is a copy of its own
+    at.mkdir(TEST_COPY_TO_FOLDER_NEW);
+    scene.ucmd().arg(TEST_HELLO_WORLD_SOURCE).arg("link").arg(TEST_COPY_TO_FOLDER_NEW).succeeds().no_stderr();
+    assert!(at.metadata(TEST_COPY_TO_FOLDER_NEW_FILE).ino() != at.metadata("hello_dir_new/link").ino());
+}
+
+#[test]
+fn test_cp_arg_preserve_invalid() {
+    new_ucmd!()
+        .arg("--preserve=mode,colour")
+        .arg(TEST_HELLO_WORLD_SOURCE)
+        .arg(TEST_HELLO_WORLD_DEST)
+        .fails()
+        .stderr_is("cp: error: invalid argument 'colour' for '--preserve'\n\
+                    Valid arguments are:\n  - 'mode'\n  - 'timestamps'\n  - 'ownership'\n  \
+                    - 'links'\n  - 'context'\n  - 'xattr'\n  - 'all'");
+}
+
+#[test]
+fn test_cp_parents() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.mkdir("dest");
+
+    ucmd.arg("--parents")
+        .arg("-v")
+        .arg(TEST_COPY_FROM_FOLDER_FILE)
+        .arg("dest")
+        .succeeds()
+        .stdout_only("hello_dir_with_file -> dest/hello_dir_with_file\n\
+                      'hello_dir_with_file/hello_world.txt' -> 'dest/hello_dir_with_file/hello_world.txt'\n");
+
+    assert_eq!(at.read("dest/hello_dir_with_file/hello_world.txt"), "Hello, World!\n");
+}
+
+#[test]
+#[cfg(unix)]
+fn test_cp_parents_preserve() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    let mtime = FileTime::from_seconds_since_1970(1234567890, 0);
+    at.mkdir_all("src/dir");
+    at.touch("src/dir/file");
+    set_permissions(at.plus("src/dir"), fs::Permissions::from_mode(0o750)).unwrap();
+    filetime::set_file_times(at.plus("src/dir"), mtime, mtime).unwrap();
+    at.mkdir("dest");
+
+    ucmd.arg("--parents")
+        .arg("-rp")
+        .arg("src/dir")
+        .arg("dest")
+        .succeeds();
+
+    assert!(at.file_exists("dest/src/dir/file"));
+    let metadata = at.metadata("dest/src/dir");
+    assert_eq!(metadata.permissions().mode() & 0o7777, 0o750);
+    assert_eq!(FileTime::from_last_modification_time(&metadata), mtime);
+}
+
+#[test]
+fn test_cp_parents_dest_not_directory() {
+    new_ucmd!()
+        .arg("--parents")
+        .arg(TEST_HELLO_WORLD_SOURCE)
+        .arg(TEST_HELLO_WORLD_DEST)
+        .fails()
+        .stderr_is("cp: error: with --parents, the destination must be a directory");
+}
+
+#[test]
+fn test_cp_arg_update() {
+    let scene = TestScenario::new(util_name!());
+    let at = &scene.fixtures;
+    let old = FileTime::from_seconds_since_1970(1000000000, 0);
+    filetime::set_file_times(at.plus(TEST_HELLO_WORLD_SOURCE), old, old).unwrap();
+
+    // the destination is newer, so it is kept
+    scene.ucmd()
+        .arg("-u")
+        .arg(TEST_HELLO_WORLD_SOURCE)
+        .arg(TEST_HOW_ARE_YOU_SOURCE)
+        .succeeds()
+        .no_stderr();
+    assert_eq!(at.read(TEST_HOW_ARE_YOU_SOURCE), "How are you?\n");
+
+    // and a missing one is copied
+    scene.ucmd()
+        .arg("-u")
+        .arg(TEST_HELLO_WORLD_SOURCE)
+        .arg(TEST_HELLO_WORLD_DEST)
+        .succeeds();
+    assert_eq!(at.read(TEST_HELLO_WORLD_DEST), "Hello, World!\n");
+}
+
+#[test]
+fn test_cp_arg_interactive_then_no_clobber() {
+    let (at, mut ucmd) = at_and_ucmd!();
+
+    // the last of -i and -n wins, so nothing is asked
+    let result = ucmd.arg("-i")
+        .arg("-n")
+        .arg(TEST_HELLO_WORLD_SOURCE)
+        .arg(TEST_HOW_ARE_YOU_SOURCE)
+        .run();
+
+    assert!(result.success);
+    assert!(!result.stdout.contains("overwrite"));
+    assert_eq!(at.read(TEST_HOW_ARE_YOU_SOURCE), "How are you?\n");
+}
+
+#[test]
+fn test_cp_arg_no_clobber_then_interactive() {
+    let (at, mut ucmd) = at_and_ucmd!();
+
+    ucmd.arg("-n")
+        .arg("-i")
+        .arg(TEST_HELLO_WORLD_SOURCE)
+        .arg(TEST_HOW_ARE_YOU_SOURCE)
+        .pipe_in("y\n")
+        .succeeds();
+
+    assert_eq!(at.read(TEST_HOW_ARE_YOU_SOURCE), "Hello, World!\n");
+}
+
+#[test]
+#[cfg(unix)]
+fn test_cp_arg_dereference_last_wins() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    symlink("hello_world.txt", at.plus("hello_dir_with_file/link")).unwrap();
+
+    ucmd.arg("-r")
+        .arg("-P")
+        .arg("-L")
+        .arg(TEST_COPY_FROM_FOLDER)
+        .arg(TEST_COPY_TO_FOLDER_NEW)
+        .succeeds();
+
+    assert!(!at.is_symlink("hello_dir_new/link"));
+    assert_eq!(at.read("hello_dir_new/link"), "Hello, World!\n");
+}
+
+#[test]
+#[cfg(unix)]
+fn test_cp_recursive_special_files() {
+    use std::ffi::CString;
+    use std::os::unix::fs::FileTypeExt;
+
+    let (at, mut ucmd) = at_and_ucmd!();
+    let fifo = CString::new(at.plus_as_string("hello_dir_with_file/fifo")).unwrap();
+    assert_eq!(unsafe { self::libc::mkfifo(fifo.as_ptr(), 0o644) }, 0);
+
+    // copying the FIFO makes a new one instead of waiting to read it
+    ucmd.arg("-r")
+        .arg(TEST_COPY_FROM_FOLDER)
+        .arg(TEST_COPY_TO_FOLDER_NEW)
+        .succeeds();
+
+    assert!(at.metadata("hello_dir_new/fifo").file_type().is_fifo());
+}
+
+#[test]
+#[cfg(unix)]
+fn test_cp_arg_copy_contents() {
+    let (at, mut ucmd) = at_and_ucmd!();
+
+    ucmd.arg("-R")
+        .arg("--copy-contents")
+        .arg("/dev/null")
+        .arg(TEST_HELLO_WORLD_DEST)
+        .succeeds();
+
+    assert!(at.metadata(TEST_HELLO_WORLD_DEST).is_file());
+    assert_eq!(at.read(TEST_HELLO_WORLD_DEST), "");
+}
+
+#[test]
+#[cfg(unix)]
+fn test_cp_copy_contents_fifo() {
+    use std::ffi::CString;
+    use std::thread;
+
+    let (at, mut ucmd) = at_and_ucmd!();
+    let path = at.plus_as_string("fifo");
+    let fifo = CString::new(path.clone()).unwrap();
+    assert_eq!(unsafe { self::libc::mkfifo(fifo.as_ptr(), 0o644) }, 0);
+
+    // opening the FIFO to write waits for cp to open it to read
+    let writer = thread::spawn(move || fs::write(path, "Hello, World!\n").unwrap());
+    ucmd.arg("-R")
+        .arg("--copy-contents")
+        .arg("fifo")
+        .arg(TEST_HELLO_WORLD_DEST)
+        .succeeds();
+    writer.join().unwrap();
+
+    assert!(at.metadata(TEST_HELLO_WORLD_DEST).is_file());
+    assert_eq!(at.read(TEST_HELLO_WORLD_DEST), "Hello, World!\n");
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_cp_to_pipe() {
+    // the test harness reads standard output from a pipe
+    new_ucmd!()
+        .arg(TEST_HELLO_WORLD_SOURCE)
+        .arg("/dev/stdout")
+        .succeeds()
+        .stdout_only("Hello, World!\n");
+}
+
+#[test]
+#[cfg(unix)]
+fn test_cp_write_failure_removes_new_dest() {
+    let scene = TestScenario::new(util_name!());
+    let at = &scene.fixtures;
+    let data: String = (0..20000).map(|i| (b'a' + (i % 26) as u8) as char).collect();
+    at.touch("big");
+    at.append("big", &data);
+    at.touch("existing");
+
+    // a file size limit of 4096 bytes, which writes past fail with EFBIG
+    // rather than ending the process
+    let limit = "trap '' XFSZ; ulimit -f 8";
+    scene.ucmd_after(limit)
+        .args(&["--sparse=always", "big", "new"])
+        .fails()
+        .code_is(3)
+        .stderr_is("cp: error: cannot write 'new' at byte 4096: File too large (os error 27)");
+    assert!(!at.file_exists("new"));
+
+    // one that was there before is not removed
+    scene.ucmd_after(limit)
+        .args(&["--sparse=always", "big", "existing"])
+        .fails()
+        .code_is(3);
+    assert_eq!(at.read("existing"), &data[..4096]);
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_cp_no_space() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    ucmd.args(&[TEST_HELLO_WORLD_SOURCE, "/dev/full"])
+        .fails()
+        .code_is(3)
+        .stderr_is("cp: error: cannot write '/dev/full' at byte 0: No space left on device (os error 28)");
+    assert!(at.file_exists(TEST_HELLO_WORLD_SOURCE));
+}
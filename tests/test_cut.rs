@@ -33,13 +33,43 @@ fn test_byte_sequence() {
 fn test_char_sequence() {
     for param in vec!["-c", "--characters"] {
         for example_seq in EXAMPLE_SEQUENCES {
-            //as of coreutils 8.25 a char range is effectively the same as a byte range; there is no distinct treatment of utf8 chars.
+            // the input is ASCII, so its characters are its bytes
             new_ucmd!().args(&[param, example_seq.sequence, INPUT])
                 .succeeds().stdout_only_fixture(format!("sequences/byte_{}.expected", example_seq.name));
         }
     }
 }
 
+#[test]
+fn test_characters_multibyte() {
+    new_ucmd!().args(&["-c", "2,4-"])
+        .pipe_in("aébc€d\n日本語\n")
+        .succeeds().stdout_only("éc€d\n本\n");
+    new_ucmd!().args(&["-c", "-2,4", "--output-delimiter=:"])
+        .pipe_in("日本語です\n")
+        .succeeds().stdout_only("日本:で\n");
+    // bytes that are not UTF-8 are characters on their own
+    new_ucmd!().args(&["-c", "2-3"])
+        .pipe_in(&b"\xffa\xe2\x82\xac\xe2\x82\n"[..])
+        .succeeds().stdout_only("a€\n");
+}
+
+#[test]
+fn test_bytes_whole_characters() {
+    // "é" is bytes 2-3 and "€" bytes 5-7
+    for &(list, expected) in &[("1-2", "a\n"), ("2", "\n"), ("3-5", "éb\n"), ("2-7", "éb€\n"), ("6-", "€d\n")] {
+        new_ucmd!().args(&["-n", "-b", list])
+            .pipe_in("aéb€d\n")
+            .succeeds().stdout_only(expected);
+    }
+    new_ucmd!().args(&["-n", "--complement", "-b", "3"])
+        .pipe_in("aéb€d\n")
+        .succeeds().stdout_only("ab€d\n");
+    new_ucmd!().args(&["-n", "-b", "1-2,3-5,6", "--output-delimiter=:"])
+        .pipe_in("aéb€\n")
+        .succeeds().stdout_only("a:éb\n");
+}
+
 #[test]
 fn test_field_sequence() {
     for param in vec!["-f", "--fields"] {
@@ -66,6 +96,34 @@ fn test_output_delimiter() {
         .succeeds().stdout_only_fixture("output_delimiter.expected");
 }
 
+#[test]
+fn test_output_delimiter_fields() {
+    new_ucmd!().args(&["-d,", "-f", "1,3-", "--output-delimiter=\t"])
+        .pipe_in("a,b,c,d\n,,\n")
+        .succeeds().stdout_only("a\tc\td\n\t\n");
+    new_ucmd!().args(&["-d\t", "-f", "1-2,3-5", "--output-delimiter= | "])
+        .pipe_in("a\tb\n\t\n")
+        .succeeds().stdout_only("a | b\n | \n");
+    // an empty output delimiter is a NUL
+    new_ucmd!().args(&["-d,", "-f", "1,2", "--output-delimiter="])
+        .pipe_in("a,b\n")
+        .succeeds().stdout_only("a\0b\n");
+}
+
+#[test]
+fn test_output_delimiter_bytes() {
+    // overlapping ranges are merged, but touching ones are not
+    new_ucmd!().args(&["-b", "1-2,3-4,4-6", "--output-delimiter=\u{e9}"])
+        .pipe_in("abcdefgh\nab\n")
+        .succeeds().stdout_only("ab\u{e9}cdef\nab\n");
+    new_ucmd!().args(&["-c", "1,3", "--output-delimiter="])
+        .pipe_in("abc")
+        .succeeds().stdout_only("a\0c\n");
+    new_ucmd!().args(&["-b", "3,5-"])
+        .pipe_in("ab\nabc")
+        .succeeds().stdout_only("\nc\n");
+}
+
 #[test]
 fn test_complement() {
     new_ucmd!().args(&["-d_","--complement", "-f", "2"])
@@ -73,6 +131,17 @@ fn test_complement() {
         .succeeds().stdout_only("9\n8\n7\n");
 }
 
+#[test]
+fn test_complement_bytes() {
+    new_ucmd!().args(&["--complement", "-b", "5-,2-3"])
+        .pipe_in("abcdefg\nxy\n")
+        .succeeds().stdout_only("ad\nx\n");
+    // nothing is left of any line
+    new_ucmd!().args(&["--complement", "-b", "1-"])
+        .pipe_in("abc\n\nxy")
+        .succeeds().stdout_only("\n\n\n");
+}
+
 #[test]
 fn test_zero_terminated() {
     new_ucmd!().args(&["-d_","-z", "-f", "1"])
@@ -95,3 +164,55 @@ fn test_zero_terminated_only_delimited() {
         .pipe_in("91\n\082\n7_3")
         .succeeds().stdout_only("82\n7\0");
 }
+
+#[test]
+fn test_zero_terminated_names() {
+    // as from find -print0, with a newline in a name
+    new_ucmd!().args(&["-z", "-d/", "-f", "2-"])
+        .pipe_in("./a\nb/c\0./d\0")
+        .succeeds().stdout_only("a\nb/c\0d\0");
+    new_ucmd!().args(&["-z", "-b", "3-"])
+        .pipe_in("./a\nb\0./d")
+        .succeeds().stdout_only("a\nb\0d\0");
+    new_ucmd!().args(&["-z", "--complement", "-c", "2", "--output-delimiter=:"])
+        .pipe_in("a\nb\0\0")
+        .succeeds().stdout_only("a:b\0\0");
+    new_ucmd!().args(&["-z", "--graphemes", "-c", "2"])
+        .pipe_in("\ne\u{301}\0x")
+        .succeeds().stdout_only("e\u{301}\0\0");
+}
+
+#[test]
+fn test_output_terminator() {
+    new_ucmd!().args(&["-d_", "-z", "-f", "2", "--output-terminator=newline"])
+        .pipe_in("9_1\n8_2\0no delimiter\07_3")
+        .succeeds().stdout_only("1\n8\nno delimiter\n3\n");
+    new_ucmd!().args(&["-z", "-c", "1", "--output-terminator", "crlf"])
+        .pipe_in("ab\0cd")
+        .succeeds().stdout_only("a\r\nc\r\n");
+    new_ucmd!().args(&["-c", "1", "--output-terminator", "tab"])
+        .fails().stderr_is("cut: error: invalid output terminator: 'tab'");
+}
+
+#[test]
+fn test_graphemes() {
+    // "e" and a combining acute accent, and a family joined by zero width joiners
+    let accented = "e\u{301}";
+    let family = "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}";
+    new_ucmd!()
+        .args(&["--graphemes", "-c", "2,4-"])
+        .pipe_in(format!("a{0}b{1}c\nx{1}\n", accented, family))
+        .succeeds()
+        .stdout_only(format!("{}{}c\n{}\n", accented, family, family));
+    new_ucmd!()
+        .args(&["--graphemes", "-c", "1-2,4", "--output-delimiter=:"])
+        .pipe_in(format!("{0}{0}b{1}\n", accented, family))
+        .succeeds()
+        .stdout_only(format!("{0}{0}:{1}\n", accented, family));
+    // bytes that are not UTF-8 are characters on their own
+    new_ucmd!()
+        .args(&["--graphemes", "-c", "3"])
+        .pipe_in(&b"\xff\xfee\xcc\x81\n"[..])
+        .succeeds()
+        .stdout_only(format!("{}\n", accented));
+}
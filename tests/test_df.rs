@@ -0,0 +1,78 @@
+use common::util::*;
+
+
+fn words(line: &str) -> Vec<&str> {
+    line.split_whitespace().collect()
+}
+
+#[test]
+fn test_df_lists_file_systems() {
+    let result = new_ucmd!().succeeds();
+    let mut lines = result.stdout.lines();
+    assert_eq!(words(lines.next().unwrap()),
+               ["Filesystem", "1K-blocks", "Used", "Available", "Use%", "Mounted", "on"]);
+    assert!(lines.next().is_some());
+    // no pseudo file systems by default, and no mount point twice
+    assert!(!result.stdout.lines().any(|line| line.starts_with("proc ")));
+    let mut mount_points: Vec<&str> = result.stdout.lines().skip(1).map(|line| words(line)[5]).collect();
+    let count = mount_points.len();
+    mount_points.sort();
+    mount_points.dedup();
+    assert_eq!(mount_points.len(), count);
+}
+
+#[test]
+fn test_df_file() {
+    let result = new_ucmd!().arg(".").succeeds();
+    let lines: Vec<&str> = result.stdout.lines().collect();
+    assert_eq!(lines.len(), 2);
+    let mount_point = words(lines[1])[5];
+    assert!(::std::env::current_dir().unwrap().canonicalize().unwrap().starts_with(mount_point));
+}
+
+#[test]
+fn test_df_headers() {
+    let header = |args: &[&str]| {
+        let result = new_ucmd!().args(args).arg(".").succeeds();
+        words(result.stdout.lines().next().unwrap()).join(" ")
+    };
+    assert_eq!(header(&["-T", "-h"]), "Filesystem Type Size Used Avail Use% Mounted on");
+    assert_eq!(header(&["-i"]), "Filesystem Inodes IUsed IFree IUse% Mounted on");
+    assert_eq!(header(&["-P"]), "Filesystem 1024-blocks Used Available Capacity Mounted on");
+    assert_eq!(header(&["-B", "512"]), "Filesystem 512B-blocks Used Available Use% Mounted on");
+    assert_eq!(header(&["-B", "2K"]), "Filesystem 2K-blocks Used Available Use% Mounted on");
+    assert_eq!(header(&["-B", "1MB"]), "Filesystem 1MB-blocks Used Available Use% Mounted on");
+    assert_eq!(header(&["-BM"]), "Filesystem 1M-blocks Used Available Use% Mounted on");
+}
+
+#[test]
+fn test_df_total() {
+    let result = new_ucmd!().args(&["--total", ".", "."]).succeeds();
+    let lines: Vec<&str> = result.stdout.lines().collect();
+    assert_eq!(lines.len(), 4);
+    let (row, total) = (words(lines[1]), words(lines[3]));
+    assert_eq!(total[0], "total");
+    assert_eq!(total[5], "-");
+    let size: u64 = row[1].parse().unwrap();
+    assert_eq!(total[1].parse::<u64>().unwrap(), 2 * size);
+}
+
+#[test]
+fn test_df_type_filters() {
+    new_ucmd!().args(&["-t", "no-such-type"]).fails()
+        .stderr_is("df: error: no file systems processed");
+    new_ucmd!().args(&["-t", "tmpfs", "-x", "tmpfs"]).fails()
+        .stderr_is("df: error: file system type 'tmpfs' both selected and excluded");
+
+    // a named file is still left out when its type is
+    let result = new_ucmd!().args(&["-T", "."]).succeeds();
+    let fs_type = words(result.stdout.lines().nth(1).unwrap())[1].to_owned();
+    new_ucmd!().args(&["-x", &fs_type, "."]).fails()
+        .stderr_is("df: error: no file systems processed");
+    new_ucmd!().args(&["-t", &fs_type, "."]).succeeds();
+}
+
+#[test]
+fn test_df_nonexistent() {
+    new_ucmd!().arg("no-such-file").fails();
+}
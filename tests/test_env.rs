@@ -1,4 +1,6 @@
 use common::util::*;
+#[cfg(unix)]
+extern crate libc;
 
 
 #[test]
@@ -81,3 +83,69 @@ fn test_unset_variable() {
 
     assert_eq!(out.lines().any(|line| line.starts_with("HOME=")), false);
 }
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_signal_handling() {
+    new_ucmd!()
+        .args(&["--ignore-signal=INT,term", "--block-signal=USR1", "--list-signal-handling", "true"])
+        .succeeds()
+        .stderr_is("INT        ( 2): IGNORE\nUSR1       (10): BLOCK\nTERM       (15): IGNORE");
+    new_ucmd!()
+        .args(&["--ignore-signal=INT", "--default-signal=SIGINT", "--ignore-signal=", "--list-signal-handling", "true"])
+        .succeeds()
+        .no_stderr();
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_signal_handling_inherited() {
+    let result = new_ucmd!()
+        .args(&["--ignore-signal=HUP", "--block-signal=RTMIN+1,63", "grep", "^Sig\\(Blk\\|Ign\\)", "/proc/self/status"])
+        .succeeds();
+    assert!(result.stdout.contains("SigBlk:\t4000000400000000\n"));
+    assert!(result.stdout.lines().any(|line| line.starts_with("SigIgn:") && line.ends_with("1")));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_file_descriptors() {
+    use std::os::unix::process::CommandExt;
+
+    let mut ucmd = new_ucmd!();
+    unsafe {
+        ucmd.raw.pre_exec(|| {
+            // env is started with standard input nonblocking, and one more
+            // descriptor to pass on
+            let flags = libc::fcntl(0, libc::F_GETFL);
+            libc::fcntl(0, libc::F_SETFL, flags | libc::O_NONBLOCK);
+            libc::dup2(2, 9);
+            Ok(())
+        });
+    }
+    let result = ucmd.args(&["sh", "-c", "grep ^flags: /proc/self/fdinfo/0; ls /proc/self/fd"]).succeeds();
+    let mut lines = result.stdout.lines();
+    let flags = u32::from_str_radix(lines.next().unwrap().split('\t').nth(1).unwrap(), 8).unwrap();
+    assert_eq!(flags & libc::O_NONBLOCK as u32, 0);
+    assert!(lines.any(|fd| fd == "9"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_invalid_signal() {
+    new_ucmd!()
+        .args(&["--ignore-signal=INT,foo", "true"])
+        .fails()
+        .stderr_is("env: error: 'foo': invalid signal\nTry 'env --help' for more information.");
+    new_ucmd!()
+        .args(&["--block-signal=0", "true"])
+        .fails()
+        .stderr_is("env: error: '0': invalid signal\nTry 'env --help' for more information.");
+    new_ucmd!()
+        .args(&["--ignore-signal=KILL", "true"])
+        .fails();
+    new_ucmd!()
+        .args(&["--ignore-signal", "true"])
+        .succeeds()
+        .no_stderr();
+}
@@ -0,0 +1,19 @@
+use common::util::*;
+
+
+#[test]
+fn test_graphemes() {
+    // a family joined by zero width joiners, two columns wide on a terminal
+    let family = "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}";
+    let words = |n: usize| vec![family; n].join(" ");
+    new_ucmd!()
+        .args(&["-w", "20"])
+        .pipe_in(format!("{}\n", words(8)))
+        .succeeds()
+        .stdout_only(format!("{0}\n{0}\n{1}\n", words(3), words(2)));
+    new_ucmd!()
+        .args(&["-w", "20", "--graphemes"])
+        .pipe_in(format!("{}\n", words(8)))
+        .succeeds()
+        .stdout_only(format!("{}\n{}\n", words(6), words(2)));
+}
@@ -24,3 +24,47 @@ fn test_40_column_word_boundary() {
         .run()
         .stdout_is_fixture("lorem_ipsum_40_column_word.expected");
 }
+
+#[test]
+fn test_zero_width() {
+    new_ucmd!()
+        .args(&["-w", "0", "lorem_ipsum.txt"])
+        .fails()
+        .stderr_is("fold: error: invalid number of columns: '0': Numerical result out of range");
+}
+
+#[test]
+fn test_lines_are_folded_apart() {
+    new_ucmd!()
+        .args(&["-w", "5"])
+        .pipe_in("ab\ncd\nh\u{e9}llo w\u{f6}rld\n")
+        .succeeds()
+        .stdout_is("ab\ncd\nh\u{e9}llo\n w\u{f6}rl\nd\n");
+}
+
+#[test]
+fn test_graphemes() {
+    // "e" and a combining acute accent
+    let accented = "e\u{301}";
+    let input = format!("{}\n", accented.repeat(5));
+    new_ucmd!()
+        .args(&["-w", "2"])
+        .pipe_in(input.clone())
+        .succeeds()
+        .stdout_is(format!("{}\n", accented).repeat(5));
+    new_ucmd!()
+        .args(&["-w", "2", "--graphemes"])
+        .pipe_in(input)
+        .succeeds()
+        .stdout_is(format!("{0}\n{0}\n{1}\n", accented.repeat(2), accented));
+}
+
+#[test]
+fn test_graphemes_word_boundary() {
+    let family = "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}";
+    new_ucmd!()
+        .args(&["-s", "-w", "4", "--graphemes"])
+        .pipe_in(format!("{0}{0} {0}{0}{0}\n", family))
+        .succeeds()
+        .stdout_is(format!("{0}{0} \n{0}{0}{0}\n", family));
+}
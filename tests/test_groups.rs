@@ -0,0 +1,10 @@
+use common::util::*;
+
+
+#[test]
+fn test_groups_of_users() {
+    let result = new_ucmd!().args(&["root", "no_such_user_here"]).run();
+    assert!(!result.success);
+    assert!(result.stdout.starts_with("root : "));
+    assert_eq!(result.stderr.trim(), "groups: error: 'no_such_user_here': no such user");
+}
@@ -68,3 +68,89 @@ fn test_verbose() {
         .args(&["-v", INPUT])
         .run().stdout_is_fixture("lorem_ipsum_verbose.expected");
 }
+
+#[test]
+fn test_size_suffix() {
+    new_ucmd!()
+        .args(&["-c", "1KB"])
+        .pipe_in("a".repeat(1500))
+        .run().stdout_is("a".repeat(1000));
+}
+
+#[test]
+fn test_invalid_count() {
+    new_ucmd!()
+        .args(&["-n", "10x", INPUT])
+        .fails().stderr_is("head: error: invalid number of lines: '10x'");
+}
+
+#[test]
+fn test_sparse_file() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    let file = at.make_file("sparse");
+    file.set_len(1 << 20).unwrap();
+    at.append("sparse", "end\n");
+
+    ucmd.args(&["-c", "3", "sparse"]).run().stdout_is("\0\0\0");
+}
+
+#[test]
+fn test_all_but_last_lines() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("lines");
+    at.append("lines", "one\ntwo\nthree\nfour");
+
+    ucmd.args(&["-n", "-2", "lines"]).run().stdout_is("one\ntwo\n");
+    new_ucmd!().args(&["-n", "-2"]).pipe_in("one\ntwo\nthree\nfour").run().stdout_is("one\ntwo\n");
+    new_ucmd!().args(&["-n", "-5"]).pipe_in("one\ntwo\n").run().stdout_is("");
+    new_ucmd!().args(&["-n", "-0"]).pipe_in("one\ntwo").run().stdout_is("one\ntwo");
+}
+
+#[test]
+fn test_all_but_last_bytes() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("bytes");
+    at.append("bytes", "0123456789");
+
+    ucmd.args(&["-c", "-3", "bytes"]).run().stdout_is("0123456");
+    new_ucmd!().args(&["-c-3"]).pipe_in("0123456789").run().stdout_is("0123456");
+    new_ucmd!().args(&["-c", "-1K"]).pipe_in("a".repeat(1500)).run().stdout_is("a".repeat(476));
+}
+
+#[test]
+fn test_multiple_files_headers() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("a");
+    at.append("a", "one\n");
+    at.touch("b");
+    at.append("b", "two\n");
+
+    ucmd.args(&["a", "-", "b"]).pipe_in("in\n").run()
+        .stdout_is("==> a <==\none\n\n==> standard input <==\nin\n\n==> b <==\ntwo\n");
+}
+
+#[test]
+fn test_last_of_quiet_and_verbose_wins() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("a");
+    at.append("a", "one\n");
+    at.touch("b");
+    at.append("b", "two\n");
+
+    ucmd.args(&["-v", "-q", "a", "b"]).run().stdout_is("one\ntwo\n");
+    let scene = TestScenario::new(util_name!());
+    scene.ucmd().args(&["-q", "-v", "-"]).pipe_in("in\n").run().stdout_is("==> standard input <==\nin\n");
+    scene.ucmd().args(&["--silent", "-v", "--quiet", "-"]).pipe_in("in\n").run().stdout_is("in\n");
+}
+
+#[test]
+fn test_missing_file() {
+    new_ucmd!().args(&["missing", INPUT]).fails()
+        .stdout_is_fixture("lorem_ipsum_verbose.expected");
+}
+
+#[test]
+fn test_zero_terminated() {
+    new_ucmd!().args(&["-z", "-n", "2"]).pipe_in("a\0b\0c\0").run().stdout_is("a\0b\0");
+    new_ucmd!().args(&["-z", "-n", "-1"]).pipe_in("a\0b\nc\0").run().stdout_is("a\0");
+}
@@ -0,0 +1,38 @@
+extern crate libc;
+
+use common::util::*;
+
+
+#[test]
+fn test_id_user_and_group() {
+    let uid = unsafe { libc::geteuid() };
+    new_ucmd!().arg("-u").succeeds().stdout_only(format!("{}\n", uid));
+    let rgid = unsafe { libc::getgid() };
+    new_ucmd!().args(&["-g", "-r"]).succeeds().stdout_only(format!("{}\n", rgid));
+}
+
+#[test]
+fn test_id_default_format() {
+    let uid = unsafe { libc::getuid() };
+    let result = new_ucmd!().run();
+    assert!(result.success);
+    assert!(result.stdout.starts_with(&format!("uid={}", uid)));
+    assert!(result.stdout.contains(" groups="));
+}
+
+#[test]
+fn test_id_conflicting_options() {
+    new_ucmd!().args(&["-u", "-g"])
+               .fails()
+               .stderr_is("id: error: cannot print \"only\" of more than one choice");
+    new_ucmd!().arg("-n")
+               .fails()
+               .stderr_is("id: error: cannot print only names or real IDs in default format");
+}
+
+#[test]
+fn test_id_unknown_user() {
+    new_ucmd!().arg("no_such_user_here")
+               .fails()
+               .stderr_is("id: error: 'no_such_user_here': no such user");
+}
@@ -1,4 +1,7 @@
+extern crate filetime;
+
 use common::util::*;
+use self::filetime::FileTime;
 use std::os::unix::fs::PermissionsExt;
 
 
@@ -194,3 +197,48 @@ fn test_install_target_new_file_failing_nonexistent_parent() {
     assert!(err.contains("not a directory"))
 }
 
+
+#[test]
+fn test_install_compare() {
+    let scene = TestScenario::new(util_name!());
+    let at = &scene.fixtures;
+    let source = "test_install_compare_source";
+    let target = "test_install_compare_dir/target";
+    at.mkdir("test_install_compare_dir");
+    at.touch(source);
+    at.append(source, "abc\n");
+    scene.ucmd().arg("-C").arg(source).arg(target).succeeds().no_stderr();
+    assert_eq!(at.read(target), "abc\n");
+
+    // an identical destination is left alone
+    let time = FileTime::from_seconds_since_1970(1000000000, 0);
+    filetime::set_file_times(at.plus_as_string(target), time, time).unwrap();
+    scene.ucmd().arg("-C").arg(source).arg(target).succeeds().no_stderr();
+    assert_eq!(FileTime::from_last_modification_time(&at.metadata(target)), time);
+
+    // but not once the contents or the mode are different
+    scene.ucmd().arg("-C").arg("-m").arg("644").arg(source).arg(target).succeeds().no_stderr();
+    assert_eq!(at.metadata(target).permissions().mode() & 0o777, 0o644);
+    assert!(FileTime::from_last_modification_time(&at.metadata(target)) != time);
+
+    at.append(source, "def\n");
+    scene.ucmd().arg("-C").arg("-m").arg("644").arg(source).arg(target).succeeds().no_stderr();
+    assert_eq!(at.read(target), "abc\ndef\n");
+}
+
+#[test]
+fn test_install_write_failure() {
+    let scene = TestScenario::new(util_name!());
+    let at = &scene.fixtures;
+    at.touch("big");
+    at.append("big", &"x".repeat(20000));
+    at.mkdir("dir");
+
+    // writing more than 4096 bytes fails with EFBIG
+    scene.ucmd_after("trap '' XFSZ; ulimit -f 8")
+        .args(&["big", "dir/big"])
+        .fails()
+        .code_is(3)
+        .stderr_is("install: error: cannot write 'dir/big' at byte 0: File too large (os error 27)");
+    assert!(!at.file_exists("dir/big"));
+}
@@ -1,4 +1,6 @@
 use common::util::*;
+use common::random::{self, Rng};
+use std::io::Write;
 
 
 #[test]
@@ -124,3 +126,172 @@ fn multitab_character() {
         .arg("э")
         .fails().stderr_is("join: error: multi-character tab э");
 }
+
+#[test]
+#[cfg(feature = "decompress")]
+fn decompress() {
+    new_ucmd!()
+        .arg("--decompress")
+        .arg("fields_1.txt.gz")
+        .arg("fields_2.txt.zst")
+        .succeeds().stdout_only_fixture("default.expected");
+}
+
+#[test]
+fn invalid_field_number() {
+    new_ucmd!()
+        .arg("fields_1.txt")
+        .arg("fields_2.txt")
+        .arg("-1")
+        .arg("0")
+        .fails().stderr_is("join: error: invalid field number: '0'");
+}
+
+#[test]
+fn crlf() {
+    let scene = TestScenario::new(util_name!());
+    let at = &scene.fixtures;
+    at.touch("crlf_1.txt");
+    at.append("crlf_1.txt", "a 1\r\nb 2\r\n");
+    at.touch("crlf_2.txt");
+    at.append("crlf_2.txt", "a x\r\nb y\n");
+
+    scene.ucmd()
+        .args(&["--crlf", "crlf_1.txt", "crlf_2.txt"])
+        .succeeds().stdout_only("a 1 x\r\nb 2 y\r\n");
+    // otherwise the carriage return is part of the last field
+    #[cfg(unix)]
+    scene.ucmd()
+        .args(&["-t", " ", "crlf_1.txt", "crlf_2.txt"])
+        .succeeds().stdout_only("a 1\r x\r\nb 2\r y\n");
+}
+
+#[test]
+fn output_terminator() {
+    let scene = TestScenario::new(util_name!());
+    let at = &scene.fixtures;
+    at.touch("nul_1.txt");
+    at.append("nul_1.txt", "1 a\nb\x002 c\x00");
+    at.touch("nul_2.txt");
+    at.append("nul_2.txt", "1 x\x002 y\x00");
+
+    scene.ucmd()
+        .args(&["-z", "--output-terminator=newline", "-t", " ", "nul_1.txt", "nul_2.txt"])
+        .succeeds().stdout_only("1 a\nb x\n2 c y\n");
+    scene.ucmd()
+        .args(&["--output-terminator=nul", "fields_1.txt", "fields_2.txt"])
+        .succeeds().stdout_only("1 a\x002 b\x003 c\x005 e\x008 h\x00");
+}
+
+#[test]
+fn stats() {
+    let scene = TestScenario::new(util_name!());
+    let at = &scene.fixtures;
+    at.touch("stats_1.txt");
+    at.append("stats_1.txt", "a 1\nb 2\nb 3\nc 4\nd 5\n");
+    at.touch("stats_2.txt");
+    at.append("stats_2.txt", "b x\nb y\nc z\ne w\nf v\n");
+
+    scene.ucmd()
+        .args(&["--stats", "stats_1.txt", "stats_2.txt"])
+        .succeeds()
+        .stdout_is("b 2 x\nb 2 y\nb 3 x\nb 3 y\nc 4 z\n")
+        .stderr_is("join: paired lines: 5\n\
+                    join: unpaired lines in stats_1.txt: 2\n\
+                    join: unpaired lines in stats_2.txt: 2\n\
+                    join: duplicate-key groups: 1");
+    // unpaired lines are counted whether they are printed or not
+    scene.ucmd()
+        .args(&["--stats", "-a", "2", "stats_1.txt", "-"])
+        .pipe_in("a y\n")
+        .succeeds()
+        .stdout_is("a 1 y\n")
+        .stderr_is("join: paired lines: 1\n\
+                    join: unpaired lines in stats_1.txt: 4\n\
+                    join: unpaired lines in -: 0\n\
+                    join: duplicate-key groups: 0");
+}
+
+// The join field of `line`, as compared.
+fn model_key(line: &str, field: usize, ignore_case: bool) -> String {
+    let key = line.split(',').nth(field - 1).unwrap_or("");
+    if ignore_case { key.to_uppercase() } else { key.to_owned() }
+}
+
+// `line` as join prints it, the join field first.
+fn model_fields(line: &str, field: usize) -> Vec<&str> {
+    let fields: Vec<&str> = line.split(',').collect();
+    let mut printed = vec![fields.get(field - 1).map_or("", |key| *key)];
+    printed.extend(fields.iter().enumerate().filter(|&(i, _)| i != field - 1).map(|(_, field)| *field));
+    printed
+}
+
+#[test]
+fn test_random_against_model() {
+    let scene = TestScenario::new(util_name!());
+    let mut rng = random::rng();
+    let gnu = random::gnu("join");
+    let alphabet = ['a', 'A', 'b', '_'];
+    for _ in 0..random::CASES {
+        let (field1, field2) = (rng.gen_range(1, 3), rng.gen_range(1, 3));
+        let mut args = vec!["-t".to_owned(), ",".to_owned(), "-1".to_owned(), field1.to_string(), "-2".to_owned(),
+                            field2.to_string()];
+        let ignore_case = random::flag(&mut rng, &mut args, "-i");
+        let unpaired = if random::flag(&mut rng, &mut args, "-a") {
+            let file = rng.gen_range(1, 3);
+            args.push(file.to_string());
+            file
+        } else {
+            0
+        };
+
+        // inputs sorted on their join fields, as join needs them
+        let mut file = |rng: &mut random::XorShiftRng, field: usize| {
+            let mut lines = random::lines(rng, 8, |rng| {
+                let fields = rng.gen_range(1, 4);
+                (0..fields).map(|_| random::word(rng, &alphabet, 1, 2)).collect::<Vec<_>>().join(",")
+            });
+            lines.sort_by_key(|line| model_key(line, field, ignore_case));
+            lines
+        };
+        let lines1 = file(&mut rng, field1);
+        let lines2 = file(&mut rng, field2);
+
+        // each key in turn, with the lines of either file that have it
+        let mut keys: Vec<String> = lines1.iter().map(|line| model_key(line, field1, ignore_case))
+            .chain(lines2.iter().map(|line| model_key(line, field2, ignore_case))).collect();
+        keys.sort();
+        keys.dedup();
+        let mut expected = Vec::new();
+        for key in &keys {
+            let with_key = |lines: &[String], field| -> Vec<String> {
+                lines.iter().filter(|line| model_key(line, field, ignore_case) == *key).cloned().collect()
+            };
+            let (paired1, paired2) = (with_key(&lines1, field1), with_key(&lines2, field2));
+            for line1 in &paired1 {
+                for line2 in &paired2 {
+                    let mut fields = model_fields(line1, field1);
+                    fields.extend(model_fields(line2, field2).into_iter().skip(1));
+                    expected.push(fields.join(","));
+                }
+            }
+            if paired2.is_empty() && unpaired == 1 {
+                expected.extend(paired1.iter().map(|line| model_fields(line, field1).join(",")));
+            }
+            if paired1.is_empty() && unpaired == 2 {
+                expected.extend(paired2.iter().map(|line| model_fields(line, field2).join(",")));
+            }
+        }
+
+        scene.fixtures.make_file("file1").write_all(random::text(&lines1).as_bytes()).unwrap();
+        args.push(scene.fixtures.plus_as_string("file1"));
+        args.push("-".to_owned());
+        let input = random::text(&lines2);
+        let expected = random::text(&expected);
+        if let Some(ref gnu) = gnu {
+            assert_eq!(gnu.run(&args, &input), expected, "GNU join {:?} of {:?} and {:?}", args, lines1, input);
+        }
+        let result = scene.ucmd().args(&args).pipe_in(input.clone()).run();
+        assert_eq!(result.stdout, expected, "join {:?} of {:?} and {:?}", args, lines1, input);
+    }
+}
@@ -0,0 +1,53 @@
+use common::util::*;
+use std::io::{BufRead, BufReader};
+use std::os::unix::process::ExitStatusExt;
+use std::process::{Command, Stdio};
+
+// a pid no process can have, being above the kernel's limit
+static NO_SUCH_PID: &'static str = "2147483647";
+
+#[test]
+fn test_kill_zero() {
+    let mut child = Command::new("sleep").arg("10").spawn().unwrap();
+
+    new_ucmd!().arg("-0").arg(child.id().to_string()).succeeds().no_stdout();
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+#[test]
+fn test_kill_zero_no_such_process() {
+    new_ucmd!()
+        .arg("-s")
+        .arg("0")
+        .arg(NO_SUCH_PID)
+        .fails()
+        .stderr_is(format!("kill: error: {}: No such process (os error 3)", NO_SUCH_PID));
+}
+
+#[test]
+fn test_kill_default_signal() {
+    let mut child = Command::new("sleep").arg("10").spawn().unwrap();
+
+    new_ucmd!().arg(child.id().to_string()).succeeds();
+
+    assert_eq!(child.wait().unwrap().signal(), Some(15));
+}
+
+#[test]
+fn test_kill_timeout() {
+    // TERM is ignored, so only the KILL sent after the timeout ends it
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg("trap '' TERM; echo ready; sleep 10")
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut line = String::new();
+    BufReader::new(child.stdout.take().unwrap()).read_line(&mut line).unwrap();
+
+    new_ucmd!().arg("--timeout").arg("0.2").arg(child.id().to_string()).succeeds();
+
+    assert_eq!(child.wait().unwrap().signal(), Some(9));
+}
@@ -226,6 +226,21 @@ fn test_symlink_existing_backup() {
     assert_eq!(at.resolve_link(resulting_backup), file);
 }
 
+#[test]
+fn test_symlink_force_backup() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    let file = "test_symlink_force_backup";
+    let link = "test_symlink_force_backup_link";
+
+    at.touch(file);
+    at.touch(link);
+    ucmd.args(&["-sf", "--backup=simple", "--suffix=.old", file, link]).succeeds().no_stderr();
+
+    assert!(at.is_symlink(link));
+    assert_eq!(at.resolve_link(link), file);
+    assert!(at.file_exists(&format!("{}.old", link)));
+}
+
 #[test]
 fn test_symlink_target_dir() {
     let (at, mut ucmd) = at_and_ucmd!();
@@ -380,3 +395,27 @@ fn test_symlink_missing_destination() {
     ucmd.args(&["-s", "-T", file]).fails()
         .stderr_is(format!("ln: error: missing destination file operand after '{}'", file));
 }
+
+#[test]
+#[cfg(unix)]
+fn test_hard_link_logical_physical() {
+    use std::os::unix::fs::MetadataExt;
+    let ts = TestScenario::new(util_name!());
+    let at = &ts.fixtures;
+    let file = "test_hard_link_logical_physical";
+    let link = "test_hard_link_logical_physical_link";
+
+    at.touch(file);
+    at.symlink(file, link);
+
+    ts.ucmd().args(&["-L", link, "logical"]).succeeds().no_stderr();
+    assert!(!at.is_symlink("logical"));
+    assert_eq!(at.metadata("logical").ino(), at.metadata(file).ino());
+
+    ts.ucmd().args(&["-L", "-P", link, "physical"]).succeeds().no_stderr();
+    assert!(at.is_symlink("physical"));
+    assert_eq!(at.resolve_link("physical"), file);
+
+    ts.ucmd().args(&[link, "default"]).succeeds().no_stderr();
+    assert!(at.is_symlink("default"));
+}
@@ -1,3 +1,7 @@
+extern crate filetime;
+extern crate libc;
+
+use self::filetime::FileTime;
 use common::util::*;
 
 
@@ -11,3 +15,382 @@ fn test_ls_ls_i() {
     new_ucmd!().arg("-i").succeeds();
     new_ucmd!().arg("-il").succeeds();
 }
+
+#[test]
+fn test_ls_long() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("test-long");
+    let result = ucmd.arg("-l").arg("test-long").succeeds();
+    assert!(result.stdout.starts_with("-rw"));
+    assert!(result.stdout.trim_right().ends_with(" test-long"));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_ls_long_acl_indicator() {
+    use std::ffi::CString;
+
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.mkdir("dir");
+    at.touch("dir/acl");
+    at.touch("dir/plain");
+    // an ACL giving user 65534 read access as well
+    let mut acl = vec![2, 0, 0, 0];
+    for &(tag, perm, id) in &[(1u16, 6u16, !0u32), (2, 4, 65534), (4, 4, !0), (0x10, 4, !0), (0x20, 4, !0)] {
+        acl.extend_from_slice(&[tag as u8, (tag >> 8) as u8, perm as u8, (perm >> 8) as u8]);
+        acl.extend_from_slice(&[id as u8, (id >> 8) as u8, (id >> 16) as u8, (id >> 24) as u8]);
+    }
+    let path = CString::new(at.plus_as_string("dir/acl")).unwrap();
+    let name = CString::new("system.posix_acl_access").unwrap();
+    if unsafe { self::libc::setxattr(path.as_ptr(), name.as_ptr(), acl.as_ptr() as *const _, acl.len(), 0) } != 0 {
+        // no ACLs on this file system
+        return;
+    }
+    let result = ucmd.args(&["-l", "dir"]).succeeds();
+    let indicators: Vec<_> = result.stdout.lines().skip(1).map(|line| &line[10..12]).collect();
+    assert_eq!(indicators, vec!["+ ", "  "]);
+}
+
+#[test]
+fn test_ls_long_total() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.mkdir("test-long-dir");
+    at.touch("test-long-dir/test-long-file");
+    let result = ucmd.arg("-l").arg("test-long-dir").succeeds();
+    assert!(result.stdout.starts_with("total "));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_ls_inode_and_size() {
+    use std::os::unix::fs::MetadataExt;
+
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.mkdir("dir");
+    at.touch("dir/big");
+    at.append("dir/big", &"x".repeat(100000));
+    at.touch("dir/empty");
+    let big = at.metadata("dir/big");
+    let empty = at.metadata("dir/empty");
+    let blocks = |md: &::std::fs::Metadata| ((md.blocks() + 1) / 2).to_string();
+    let width = blocks(&big).len();
+    ucmd.args(&["-s", "-i", "dir"]).succeeds().stdout_only(format!(
+        "total {}\n{} {:>w$} big\n{} {:>w$} empty\n",
+        (big.blocks() + empty.blocks() + 1) / 2,
+        big.ino(),
+        blocks(&big),
+        empty.ino(),
+        blocks(&empty),
+        w = width));
+}
+
+#[test]
+fn test_ls_block_size() {
+    let scene = TestScenario::new(util_name!());
+    scene.fixtures.touch("file");
+    scene.fixtures.append("file", &"x".repeat(5000));
+
+    for &(arg, size) in &[("--block-size=1", "5000"),
+                          ("--block-size=K", "5K"),
+                          ("--block-size=1KB", "5"),
+                          ("-h", "4.9K"),
+                          ("--si", "5.0k")] {
+        let result = scene.ucmd().args(&["-l", arg, "file"]).succeeds();
+        let fields: Vec<_> = result.stdout.split_whitespace().collect();
+        assert_eq!(fields[4], size);
+    }
+
+    scene.ucmd()
+        .arg("--block-size=1X")
+        .fails()
+        .stderr_is("ls: error: invalid --block-size argument '1X'");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_ls_hyperlink() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("a b");
+    let dir = ::std::fs::canonicalize(at.as_string()).unwrap();
+    let result = ucmd.args(&["--hyperlink=always", "a b"]).succeeds();
+    assert!(result.stdout.starts_with("\x1b]8;;file://"));
+    assert!(result.stdout.ends_with(&format!("{}/a%20b\x1b\\a b\x1b]8;;\x1b\\\n", dir.display())));
+
+    new_ucmd!().arg("--hyperlink=sometimes").fails();
+}
+
+#[test]
+fn test_ls_dired() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.mkdir("dir");
+    at.mkdir("dir/sub");
+    at.touch("dir/a b");
+    at.touch("dir/sub/c");
+    let result = ucmd.args(&["-lR", "--dired", "dir"]).succeeds();
+    let out = &result.stdout;
+
+    let spans = |label: &str| -> Vec<String> {
+        let line = out.lines().find(|l| l.starts_with(label)).unwrap();
+        let offsets: Vec<usize> = line[label.len()..].split_whitespace().map(|n| n.parse().unwrap()).collect();
+        offsets.chunks(2).map(|span| out[span[0]..span[1]].to_owned()).collect()
+    };
+    assert_eq!(spans("//DIRED//"), vec!["a b", "sub", "c"]);
+    assert_eq!(spans("//SUBDIRED//"), vec!["dir", "dir/sub"]);
+    assert!(out.starts_with("  dir:\n  total "));
+    assert!(out.ends_with("//DIRED-OPTIONS// --quoting-style=literal\n"));
+}
+
+#[test]
+fn test_ls_dired_color() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    // names that are also in the escape sequence coloring them
+    at.mkdir("dir");
+    at.mkdir("dir/01");
+    at.mkdir("dir/m");
+    let result = ucmd.args(&["-l", "--dired", "--color=always", "dir"])
+        .env("LS_COLORS", "di=01;34")
+        .succeeds();
+    let out = &result.stdout;
+
+    let line = out.lines().find(|l| l.starts_with("//DIRED//")).unwrap();
+    let offsets: Vec<usize> = line["//DIRED//".len()..].split_whitespace().map(|n| n.parse().unwrap()).collect();
+    // the names found inside the escape sequences would be followed by more of them
+    let names: Vec<_> = offsets.chunks(2).map(|span| &out[span[0]..span[1] + 5]).collect();
+    assert_eq!(names, ["01\x1b[0m\n", "m\x1b[0m\n"]);
+}
+
+#[test]
+fn test_ls_layouts() {
+    let scene = TestScenario::new(util_name!());
+    for name in &["alpha", "beta", "gamma", "delta", "epsilon", "zeta", "eta", "theta"] {
+        scene.fixtures.touch(name);
+    }
+
+    scene.ucmd().args(&["-C", "-w", "30"]).succeeds().stdout_only(
+        "alpha  delta    eta    theta\nbeta   epsilon  gamma  zeta\n");
+    scene.ucmd().args(&["-x", "-w", "30"]).succeeds().stdout_only(
+        "alpha  beta   delta  epsilon\neta    gamma  theta  zeta\n");
+    scene.ucmd().args(&["-m", "-w", "30"]).succeeds().stdout_only(
+        "alpha, beta, delta, epsilon,\neta, gamma, theta, zeta\n");
+    scene.ucmd().args(&["-C", "-w", "0"]).succeeds().stdout_only(
+        "alpha  beta  delta  epsilon  eta  gamma  theta  zeta\n");
+    scene.ucmd().args(&["-C1"]).succeeds().stdout_only(
+        "alpha\nbeta\ndelta\nepsilon\neta\ngamma\ntheta\nzeta\n");
+    // a name too wide for the line gets one of its own
+    scene.ucmd().args(&["-C", "-w", "3", "alpha", "beta"]).succeeds().stdout_only("alpha\nbeta\n");
+    scene.ucmd().arg("-C").env("COLUMNS", "30").succeeds().stdout_only(
+        "alpha  delta    eta    theta\nbeta   epsilon  gamma  zeta\n");
+
+    scene.ucmd().args(&["-w", "x"]).fails().stderr_is("ls: error: invalid line width: 'x'");
+}
+
+#[test]
+fn test_ls_long_numeric() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("test-numeric");
+    let result = ucmd.arg("-n").arg("test-numeric").succeeds();
+    let fields: Vec<_> = result.stdout.split_whitespace().collect();
+    assert!(fields[2].parse::<u32>().is_ok());
+    assert!(fields[3].parse::<u32>().is_ok());
+}
+
+#[test]
+fn test_ls_long_omit_owner_or_group() {
+    let scene = TestScenario::new(util_name!());
+    scene.fixtures.touch("test-omit");
+
+    // mode, links, owner, group, size, month, day, time and name
+    for &(arg, count) in &[("-l", 9), ("-g", 8), ("-o", 8), ("-lG", 8), ("-go", 7)] {
+        let result = scene.ucmd().arg(arg).arg("test-omit").succeeds();
+        assert_eq!(result.stdout.split_whitespace().count(), count);
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_ls_color() {
+    let scene = TestScenario::new(util_name!());
+    scene.fixtures.mkdir("a");
+    scene.fixtures.touch("b.tar");
+    scene.fixtures.touch("c");
+
+    scene.ucmd()
+        .arg("--color=always")
+        .env("LS_COLORS", "di=01;34:*.tar=31")
+        .succeeds()
+        .stdout_only("\x1b[01;34ma\x1b[0m\n\x1b[31mb.tar\x1b[0m\nc\n");
+
+    // a pipe is not a terminal
+    scene.ucmd()
+        .arg("--color=auto")
+        .succeeds()
+        .stdout_only("a\nb.tar\nc\n");
+
+    scene.ucmd()
+        .arg("--color=never")
+        .succeeds()
+        .stdout_only("a\nb.tar\nc\n");
+
+    scene.ucmd()
+        .arg("--color=sometimes")
+        .fails();
+}
+
+#[test]
+fn test_ls_recursive() {
+    let scene = TestScenario::new(util_name!());
+    scene.fixtures.mkdir_all("a/b/c");
+    scene.fixtures.touch("a/f");
+    scene.fixtures.touch("a/b/g");
+
+    scene.ucmd()
+        .arg("-R")
+        .arg("a")
+        .succeeds()
+        .stdout_only("a:\nb\nf\n\na/b:\nc\ng\n\na/b/c:\n");
+
+    scene.ucmd()
+        .arg("-R")
+        .arg("a/f")
+        .arg("a/b")
+        .succeeds()
+        .stdout_only("a/f\n\na/b:\nc\ng\n\na/b/c:\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_ls_recursive_symlink_loop() {
+    let scene = TestScenario::new(util_name!());
+    scene.fixtures.mkdir("a");
+    // the link points to the directory containing a
+    scene.fixtures.symlink(".", "a/loop");
+
+    // the link is not followed without -L
+    scene.ucmd()
+        .arg("-R")
+        .arg("a")
+        .succeeds()
+        .stdout_only("a:\nloop\n");
+
+    let result = scene.ucmd().arg("-RL").arg("a").run();
+    assert!(!result.success);
+    assert!(result.stderr.contains("a/loop/a: not listing already-listed directory"));
+}
+
+#[test]
+fn test_ls_sort() {
+    let scene = TestScenario::new(util_name!());
+    let at = &scene.fixtures;
+    for &(file, contents) in &[("b.txt", "12345"), ("a.rs", "1"), ("c", "123")] {
+        at.touch(file);
+        at.append(file, contents);
+    }
+    for &(file, secs) in &[("b.txt", 3000), ("a.rs", 1000), ("c", 2000)] {
+        let time = FileTime::from_seconds_since_1970(secs, 0);
+        filetime::set_file_times(at.plus_as_string(file), time, time).unwrap();
+    }
+
+    for &(args, expected) in &[(&["-S"][..], "b.txt\nc\na.rs\n"),
+                               (&["--sort=size"][..], "b.txt\nc\na.rs\n"),
+                               (&["-t"][..], "b.txt\nc\na.rs\n"),
+                               (&["-tr"][..], "a.rs\nc\nb.txt\n"),
+                               (&["-X"][..], "c\na.rs\nb.txt\n"),
+                               (&["--sort=extension", "-r"][..], "b.txt\na.rs\nc\n"),
+                               (&["-r"][..], "c\nb.txt\na.rs\n")] {
+        scene.ucmd().args(args).succeeds().stdout_only(expected);
+    }
+
+    scene.ucmd().arg("--sort=bogus").fails();
+}
+
+#[test]
+fn test_ls_unsorted() {
+    let scene = TestScenario::new(util_name!());
+    scene.fixtures.mkdir_all("d/sub");
+    for name in &["d/x", "d/.hidden", "d/sub/y"] {
+        scene.fixtures.touch(name);
+    }
+
+    // the entries come in the order the directory holds them
+    let sorted_lines = |args: &[&str]| {
+        let result = scene.ucmd().args(args).succeeds();
+        let mut lines: Vec<_> = result.stdout.lines().map(String::from).collect();
+        lines.sort();
+        lines
+    };
+    assert_eq!(sorted_lines(&["-U1", "d"]), ["sub", "x"]);
+    assert_eq!(sorted_lines(&["--sort=none", "-1", "d"]), ["sub", "x"]);
+    // -f lists everything, not in the long format
+    assert_eq!(sorted_lines(&["-f", "d"]), [".", "..", ".hidden", "sub", "x"]);
+    assert_eq!(sorted_lines(&["-fl", "d"]), [".", "..", ".hidden", "sub", "x"]);
+    assert!(scene.ucmd().args(&["-f", "d"]).succeeds().stdout.starts_with(".\n..\n"));
+
+    let result = scene.ucmd().args(&["-UR1", "d"]).succeeds();
+    assert!(result.stdout.starts_with("d:\n"));
+    assert!(result.stdout.ends_with("\n\nd/sub:\ny\n"));
+    // -i lines up the inode numbers, so the listing is not streamed
+    assert!(scene.ucmd().args(&["-Ui1", "d/sub"]).succeeds().stdout.ends_with(" y\n"));
+}
+
+#[test]
+fn test_ls_ignore_hide() {
+    let scene = TestScenario::new(util_name!());
+    for name in &["a.txt", "b.rs", ".hidden"] {
+        scene.fixtures.touch(name);
+    }
+
+    for &(args, expected) in &[(&["-I", "*.txt"][..], "b.rs\n"),
+                               (&["--ignore=[[:alpha:]].rs", "--ignore=a?txt"][..], ""),
+                               (&["-A", "-I", "*"][..], ".hidden\n"),
+                               (&["-a", "-I", ".*"][..], "a.txt\nb.rs\n"),
+                               (&["--hide=*.txt"][..], "b.rs\n"),
+                               (&["-A", "--hide=*.txt"][..], ".hidden\na.txt\nb.rs\n"),
+                               (&["-I", "a.txt", "a.txt"][..], "a.txt\n")] {
+        scene.ucmd().args(args).succeeds().stdout_only(expected);
+    }
+}
+
+#[test]
+fn test_ls_sort_version() {
+    let scene = TestScenario::new(util_name!());
+    for name in &["a10", "a9", "a1.tar.gz", "a1.2", "a1~", ".hidden"] {
+        scene.fixtures.touch(name);
+    }
+
+    scene.ucmd()
+        .args(&["-A", "-v"])
+        .succeeds()
+        .stdout_only(".hidden\na1~\na1.tar.gz\na1.2\na9\na10\n");
+}
+
+#[test]
+fn test_ls_time_style() {
+    let scene = TestScenario::new(util_name!());
+    scene.fixtures.touch("old");
+    let time = FileTime::from_seconds_since_1970(981173106, 500000000);
+    filetime::set_file_times(scene.fixtures.plus_as_string("old"), time, time).unwrap();
+
+    for &(args, date) in &[(&["-l"][..], "Feb  3  2001"),
+                           (&["-l", "--time-style=long-iso"][..], "2001-02-03 04:05"),
+                           (&["-l", "--time-style=iso"][..], "2001-02-03 "),
+                           (&["--full-time"][..], "2001-02-03 04:05:06.500000000 +0000"),
+                           (&["-l", "--time-style=+%Y/%m/%d %N"][..], "2001/02/03 500000000"),
+                           (&["-l", "--time-style=+old %Y\nrecent %H"][..], "old 2001")] {
+        let result = scene.ucmd().args(args).arg("old").env("TZ", "UTC").succeeds();
+        assert!(result.stdout.ends_with(&format!(" {} old\n", date)), "{:?}", result.stdout);
+    }
+
+    let result = scene.ucmd().arg("-l").arg("old").env("TIME_STYLE", "long-iso").env("TZ", "UTC").succeeds();
+    assert!(result.stdout.ends_with(" 2001-02-03 04:05 old\n"));
+
+    scene.ucmd().arg("-l").arg("--time-style=bogus").arg("old").fails();
+}
+
+#[test]
+fn test_ls_time_style_recent() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("recent");
+    let result = ucmd.args(&["-l", "--time-style=+old\nrecent", "recent"]).succeeds();
+    assert!(result.stdout.ends_with(" recent recent\n"));
+}
+
@@ -0,0 +1,42 @@
+use common::util::*;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+
+#[test]
+fn test_create_fifos() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    ucmd.args(&["a", "b"]).succeeds().no_stderr();
+    assert!(at.metadata("a").file_type().is_fifo());
+    assert!(at.metadata("b").file_type().is_fifo());
+}
+
+#[test]
+fn test_mode_ignores_umask() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    ucmd.args(&["-m", "666", "fifo"]).succeeds().no_stderr();
+    assert_eq!(at.metadata("fifo").permissions().mode() & 0o777, 0o666);
+}
+
+#[test]
+fn test_symbolic_mode() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    ucmd.args(&["-m", "a=r,u+w", "fifo"]).succeeds().no_stderr();
+    assert_eq!(at.metadata("fifo").permissions().mode() & 0o777, 0o644);
+}
+
+#[test]
+fn test_mode_only_permission_bits() {
+    new_ucmd!().args(&["-m", "4755", "fifo"])
+               .fails()
+               .stderr_is("mkfifo: mode must specify only file permission bits");
+}
+
+#[test]
+fn test_existing_file() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("file");
+    ucmd.args(&["file", "fifo"])
+        .fails()
+        .stderr_is("mkfifo: cannot create fifo 'file': File exists (os error 17)");
+    assert!(at.metadata("fifo").file_type().is_fifo());
+}
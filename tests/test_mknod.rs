@@ -0,0 +1,48 @@
+extern crate libc;
+
+use common::util::*;
+use std::os::unix::fs::{FileTypeExt, MetadataExt, PermissionsExt};
+
+
+#[test]
+fn test_fifo() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    ucmd.args(&["-m", "600", "fifo", "p"]).succeeds().no_stderr();
+    let metadata = at.metadata("fifo");
+    assert!(metadata.file_type().is_fifo());
+    assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+}
+
+#[test]
+fn test_fifo_extra_operands() {
+    new_ucmd!().args(&["fifo", "p", "1", "2"])
+               .fails()
+               .stderr_is("mknod: extra operand '1'\n\
+                           Fifos do not have major and minor device numbers.\n\
+                           Try 'mknod --help' for more information.");
+}
+
+#[test]
+fn test_invalid_device_numbers() {
+    new_ucmd!().args(&["dev", "c", "08", "0"])
+               .fails()
+               .stderr_is("mknod: invalid major device number '08'");
+    new_ucmd!().args(&["dev", "b", "1", "0xg"])
+               .fails()
+               .stderr_is("mknod: invalid minor device number '0xg'");
+}
+
+#[test]
+fn test_character_device() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    let result = ucmd.args(&["dev", "c", "0x1", "010"]).run();
+    if unsafe { libc::geteuid() } == 0 && result.success {
+        let metadata = at.metadata("dev");
+        assert!(metadata.file_type().is_char_device());
+        // 1 and 8, as <sys/sysmacros.h> puts them together
+        assert_eq!(metadata.rdev(), 0x108);
+    } else {
+        assert!(result.stderr.contains("Operation not permitted"));
+        assert!(result.stderr.contains("only a privileged user can create block and character special files"));
+    }
+}
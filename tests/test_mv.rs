@@ -110,18 +110,68 @@ fn test_mv_interactive() {
     at.touch(file_b);
 
 
-    scene.ucmd().arg("-i").arg(file_a).arg(file_b).pipe_in("n").succeeds().no_stderr();
+    scene.ucmd().arg("-i").arg(file_a).arg(file_b).pipe_in("n").succeeds()
+        .stderr_is(format!("mv: overwrite '{}'?", file_b));
 
     assert!(at.file_exists(file_a));
     assert!(at.file_exists(file_b));
 
 
-    scene.ucmd().arg("-i").arg(file_a).arg(file_b).pipe_in("Yesh").succeeds().no_stderr();
+    scene.ucmd().arg("-i").arg(file_a).arg(file_b).pipe_in("Yesh").succeeds()
+        .stderr_is(format!("mv: overwrite '{}'?", file_b));
 
     assert!(!at.file_exists(file_a));
     assert!(at.file_exists(file_b));
 }
 
+#[test]
+fn test_mv_interactive_each_file() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    let dir = "test_mv_interactive_each_file_dir";
+
+    at.mkdir(dir);
+    for name in &["a", "b", "c"] {
+        at.touch(name);
+        at.touch(&format!("{}/{}", dir, name));
+    }
+
+    ucmd.arg("-i").arg("a").arg("b").arg("c").arg(dir).pipe_in("y\nn\ny\n").succeeds();
+
+    assert!(!at.file_exists("a"));
+    assert!(at.file_exists("b"));
+    assert!(!at.file_exists("c"));
+}
+
+#[test]
+fn test_mv_overwrite_last_option_wins() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    let file_a = "test_mv_overwrite_last_option_wins_a";
+    let file_b = "test_mv_overwrite_last_option_wins_b";
+
+    at.touch(file_a);
+    at.touch(file_b);
+
+    ucmd.arg("-n").arg("-i").arg("-f").arg(file_a).arg(file_b).succeeds().no_stderr();
+
+    assert!(!at.file_exists(file_a));
+    assert!(at.file_exists(file_b));
+}
+
+#[test]
+fn test_mv_force_then_no_clobber() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    let file_a = "test_mv_force_then_no_clobber_a";
+    let file_b = "test_mv_force_then_no_clobber_b";
+
+    at.touch(file_a);
+    at.touch(file_b);
+
+    ucmd.arg("-f").arg("-n").arg(file_a).arg(file_b).succeeds().no_stderr();
+
+    assert!(at.file_exists(file_a));
+    assert!(at.file_exists(file_b));
+}
+
 #[test]
 fn test_mv_no_clobber() {
     let (at, mut ucmd) = at_and_ucmd!();
@@ -236,6 +286,43 @@ fn test_mv_backup_numbering() {
     assert!(at.file_exists(&format!("{}.~1~", file_b)));
 }
 
+#[test]
+fn test_mv_backup_via_env() {
+    let scene = TestScenario::new(util_name!());
+    let at = &scene.fixtures;
+    let file_a = "test_mv_backup_via_env_a.txt";
+    let file_b = "test_mv_backup_via_env_b.txt";
+
+    at.touch(file_a);
+    at.touch(file_b);
+    scene.ucmd().arg("-b").env("VERSION_CONTROL", "numbered").arg(file_a).arg(file_b).succeeds().no_stderr();
+    assert!(at.file_exists(&format!("{}.~1~", file_b)));
+
+    // and a suffix alone asks for a backup
+    at.touch(file_a);
+    scene.ucmd().arg("-S").arg(".bak").arg(file_a).arg(file_b).succeeds().no_stderr();
+    assert!(at.file_exists(&format!("{}.~2~", file_b)));
+    at.touch(file_a);
+    scene.ucmd().args(&["-S", ".bak", "--backup=simple", file_a, file_b]).succeeds().no_stderr();
+    assert!(at.file_exists(&format!("{}.bak", file_b)));
+}
+
+#[test]
+fn test_mv_backup_invalid() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("a");
+    ucmd.args(&["--backup=nope", "a", "b"])
+        .fails()
+        .stderr_is("mv: error: invalid argument 'nope' for 'backup type'\n\
+                    Valid arguments are:\n  \
+                    - 'none', 'off'\n  \
+                    - 'simple', 'never'\n  \
+                    - 'existing', 'nil'\n  \
+                    - 'numbered', 't'\n\
+                    Try 'mv --help' for more information.");
+    assert!(at.file_exists("a"));
+}
+
 #[test]
 fn test_mv_existing_backup() {
     let (at, mut ucmd) = at_and_ucmd!();
@@ -281,6 +368,28 @@ fn test_mv_update_option() {
     assert!(!at.file_exists(file_b));
 }
 
+#[test]
+fn test_mv_update_interactive_backup() {
+    let scene = TestScenario::new(util_name!());
+    let at = &scene.fixtures;
+    let file_a = "test_mv_update_interactive_backup_file_a";
+    let file_b = "test_mv_update_interactive_backup_file_b";
+
+    at.touch(file_a);
+    at.touch(file_b);
+    let ts = time::now().to_timespec();
+    let now = FileTime::from_seconds_since_1970(ts.sec as u64, ts.nsec as u32);
+    let later = FileTime::from_seconds_since_1970(ts.sec as u64 + 3600, ts.nsec as u32);
+    filetime::set_file_times(at.plus_as_string(file_a), now, now).unwrap();
+    filetime::set_file_times(at.plus_as_string(file_b), now, later).unwrap();
+
+    // the newer destination is kept without a question or a backup
+    scene.ucmd().arg("-u").arg("-i").arg("-b").arg(file_a).arg(file_b).succeeds().no_stderr();
+
+    assert!(at.file_exists(file_a));
+    assert!(!at.file_exists(&format!("{}~", file_b)));
+}
+
 #[test]
 fn test_mv_target_dir() {
     let (at, mut ucmd) = at_and_ucmd!();
@@ -344,7 +453,7 @@ fn test_mv_backup_dir() {
     at.mkdir(dir_a);
     at.mkdir(dir_b);
     ucmd.arg("-vbT").arg(dir_a).arg(dir_b).succeeds()
-        .stdout_only(format!("‘{}’ -> ‘{}’ (backup: ‘{}~’)\n",
+        .stdout_only(format!("'{}' -> '{}' (backup: '{}~')\n",
                        dir_a,
                        dir_b,
                        dir_b));
@@ -375,7 +484,7 @@ fn test_mv_errors() {
     // $ mv -T file dir
     // err == mv: cannot overwrite directory ‘dir’ with non-directory
     scene.ucmd().arg("-T").arg(file_a).arg(dir).fails()
-    .stderr_is(format!("mv: error: cannot overwrite directory ‘{}’ with non-directory\n",
+    .stderr_is(format!("mv: error: cannot overwrite directory '{}' with non-directory\n",
                        dir));
 
     // $ at.mkdir dir && at.touch file
@@ -396,16 +505,61 @@ fn test_mv_verbose() {
     at.touch(file_b);
 
     scene.ucmd().arg("-v").arg(file_a).arg(file_b).succeeds()
-       .stdout_only(format!("‘{}’ -> ‘{}’\n", file_a, file_b));
+       .stdout_only(format!("'{}' -> '{}'\n", file_a, file_b));
 
     at.touch(file_a);
     scene.ucmd().arg("-vb").arg(file_a).arg(file_b).succeeds()
-        .stdout_only(format!("‘{}’ -> ‘{}’ (backup: ‘{}~’)\n",
+        .stdout_only(format!("'{}' -> '{}' (backup: '{}~')\n",
                              file_a,
                              file_b,
                              file_b));
 }
 
+#[test]
+fn test_mv_verbose_quoting() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    let file_a = "test_mv_verbose_quoting_a b";
+    let file_b = "test_mv_verbose_quoting_it's";
+    at.touch(file_a);
+
+    ucmd.arg("-v").arg(file_a).arg(file_b).env("LC_ALL", "en_US.UTF-8").succeeds()
+        .stdout_only(format!("'{}' -> \"{}\"\n", file_a, file_b));
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_mv_across_file_systems() {
+    use std::fs;
+    use std::io::Read;
+    use std::os::unix::fs::{symlink, MetadataExt};
+    use std::path::Path;
+    use std::process;
+
+    let (at, mut ucmd) = at_and_ucmd!();
+    // usually a file system of its own
+    let other = Path::new("/dev/shm");
+    if !other.is_dir() || fs::metadata(other).unwrap().dev() == at.metadata(".").dev() {
+        return;
+    }
+    let dest = other.join(format!("uutils-test-mv-{}", process::id()));
+    at.mkdir_all("dir/sub");
+    at.touch("dir/sub/file");
+    at.append("dir/sub/file", "contents");
+    symlink("sub/file", at.plus("dir/link")).unwrap();
+    let mtime = FileTime::from_seconds_since_1970(1234567890, 0);
+    set_file_times(at.plus("dir/sub/file"), mtime, mtime).unwrap();
+
+    ucmd.arg("dir").arg(&dest).succeeds().no_stderr();
+
+    assert!(!at.dir_exists("dir"));
+    let mut contents = String::new();
+    fs::File::open(dest.join("sub/file")).unwrap().read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "contents");
+    assert_eq!(fs::read_link(dest.join("link")).unwrap(), Path::new("sub/file"));
+    assert_eq!(FileTime::from_last_modification_time(&fs::metadata(dest.join("sub/file")).unwrap()), mtime);
+    fs::remove_dir_all(&dest).unwrap();
+}
+
 // Todo:
 
 // $ at.touch a b
@@ -51,3 +51,11 @@ fn test_sections_and_styles() {
             .stdout_is(output);
     }
 }
+
+#[test]
+fn test_invalid_number_format() {
+    new_ucmd!()
+        .args(&["-n", "lz"])
+        .fails()
+        .stdout_is("invalid line numbering format: 'lz'\n");
+}
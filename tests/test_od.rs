@@ -660,3 +660,30 @@ fn test_traditional_only_label(){
             (0000042)
             "));
 }
+
+#[test]
+fn test_skip_bytes_across_files() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("a");
+    at.append("a", "abcdefgh");
+
+    // the whole of the first file is skipped, then part of the second
+    ucmd.arg("-c").arg("-j").arg("10").arg("a").arg("a").arg("-")
+        .pipe_in("xyz")
+        .succeeds()
+        .stdout_only(unindent("
+            0000012   c   d   e   f   g   h   x   y   z
+            0000023
+            "));
+}
+
+#[test]
+fn test_skip_bytes_past_end() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("a");
+    at.append("a", "abcdefgh");
+
+    ucmd.arg("-j").arg("9").arg("a")
+        .fails()
+        .stderr_is("od: error: cannot skip past end of combined input");
+}
@@ -8,3 +8,14 @@ fn test_combine_pairs_of_lines() {
         .run()
         .stdout_is_fixture("html_colors.expected");
 }
+
+#[test]
+fn test_output_terminator() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("nul.txt");
+    at.append("nul.txt", "a\nb\x00c\x00");
+
+    ucmd.args(&["-z", "-d", ",", "--output-terminator=newline", "nul.txt", "-"])
+        .pipe_in("d\x00")
+        .succeeds().stdout_only("a\nb,d\nc,\n");
+}
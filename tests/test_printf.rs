@@ -173,7 +173,40 @@ fn sub_num_sci_trunc() {
 
 #[test]
 fn sub_num_dec_trunc() {
-    new_ucmd!().args(&["pi is ~ %g", "3.1415926535"]).succeeds().stdout_only("pi is ~ 3.141593");
+    new_ucmd!().args(&["pi is ~ %g", "3.1415926535"]).succeeds().stdout_only("pi is ~ 3.14159");
+}
+
+#[test]
+fn sub_num_sci_in() {
+    new_ucmd!().args(&["%f %g", "1.5e3", "-2.5E-7"]).succeeds().stdout_only("1500.000000 -2.5e-07");
+}
+
+#[test]
+fn sub_num_sci_long_double() {
+    new_ucmd!().args(&["%Le %Lg", "0.1", "123456789012345678901234567890"])
+        .succeeds().stdout_only("1.000000e-01 1.23457e+29");
+}
+
+#[test]
+fn sub_num_dec_extended_precision() {
+    new_ucmd!().args(&["%.25Lg %.25g", "0.1", "3"]).succeeds().stdout_only("0.1000000000000000000013553 3");
+}
+
+#[test]
+fn sub_num_float_hex_exponent_in() {
+    new_ucmd!().args(&["%g", "0x1.8p-2"]).succeeds().stdout_only("0.375");
+}
+
+#[test]
+fn sub_num_float_inf_nan() {
+    new_ucmd!().args(&["%f %E %g", "-inf", "Infinity", "nan"]).succeeds().stdout_only("-inf INF nan");
+}
+
+#[test]
+fn sub_num_float_out_of_range() {
+    let result = new_ucmd!().args(&["%g %g", "1e5000", "1e-5000"]).run();
+    assert_eq!(result.stdout, "inf 0");
+    assert!(result.stderr.contains("1e5000: Numerical result out of range"));
 }
 
 #[cfg_attr(not(feature="test_unimplemented"),ignore)]
@@ -60,6 +60,70 @@ fn test_rm_interactive() {
     assert!(at.file_exists(file_b));
 }
 
+#[test]
+fn test_rm_interactive_once() {
+    let scene = TestScenario::new(util_name!());
+    let at = &scene.fixtures;
+
+    for name in &["a", "b", "c", "d"] {
+        at.touch(name);
+    }
+
+    // three files or fewer are removed without asking
+    scene.ucmd().args(&["-I", "a", "b", "c"]).succeeds().no_stderr();
+    assert!(!at.file_exists("a"));
+
+    for name in &["a", "b", "c"] {
+        at.touch(name);
+    }
+    scene.ucmd().args(&["-I", "a", "b", "c", "d"]).pipe_in("n").succeeds()
+        .stderr_is("rm: remove 4 arguments?");
+    assert!(at.file_exists("a"));
+    assert!(at.file_exists("d"));
+
+    scene.ucmd().args(&["--interactive=once", "a", "b", "c", "d"]).pipe_in("y").succeeds()
+        .stderr_is("rm: remove 4 arguments?");
+    assert!(!at.file_exists("a"));
+    assert!(!at.file_exists("d"));
+}
+
+#[test]
+fn test_rm_interactive_once_recursive() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    let dir = "test_rm_interactive_once_recursive_dir";
+
+    at.mkdir(dir);
+    at.touch(&format!("{}/file", dir));
+
+    ucmd.args(&["-I", "-r", dir]).pipe_in("n").succeeds()
+        .stderr_is("rm: remove 1 argument recursively?");
+    assert!(at.dir_exists(dir));
+}
+
+#[test]
+fn test_rm_interactive_last_option_wins() {
+    let scene = TestScenario::new(util_name!());
+    let at = &scene.fixtures;
+
+    for name in &["a", "b", "c", "d"] {
+        at.touch(name);
+    }
+
+    scene.ucmd().args(&["-I", "-f", "a", "b", "c", "d"]).succeeds().no_stderr();
+    assert!(!at.file_exists("a"));
+
+    // -I takes -f back, so that a missing file is an error again
+    scene.ucmd().args(&["-f", "-I", "a"]).fails();
+
+    scene.ucmd().args(&["--interactive=foo", "b"]).fails()
+        .stderr_is("rm: error: invalid argument 'foo' for '--interactive'\n\
+                    Valid arguments are:\n  \
+                    - 'never', 'no', 'none'\n  \
+                    - 'once'\n  \
+                    - 'always', 'yes'\n\
+                    Try 'rm --help' for more information.");
+}
+
 #[test]
 fn test_rm_force() {
     let (at, mut ucmd) = at_and_ucmd!();
@@ -106,6 +170,74 @@ fn test_rm_recursive() {
     assert!(!at.file_exists(file_b));
 }
 
+#[test]
+fn test_rm_recursive_one_file_system() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    let dir = "test_rm_recursive_one_file_system";
+
+    at.mkdir(dir);
+    at.mkdir(&format!("{}/sub", dir));
+    at.touch(&format!("{}/sub/file", dir));
+
+    // everything is on the one file system here
+    ucmd.arg("-r").arg("--one-file-system").arg(dir).succeeds().no_stderr();
+
+    assert!(!at.dir_exists(dir));
+}
+
+#[test]
+#[cfg(unix)]
+fn test_rm_recursive_deeper_than_path_max() {
+    use std::fs;
+
+    let (at, mut ucmd) = at_and_ucmd!();
+    let dir = "test_rm_recursive_deeper_than_path_max";
+
+    // built from the bottom up, so that no path is ever too long to use
+    at.mkdir(dir);
+    for _ in 0..3000 {
+        at.mkdir("wrap");
+        fs::rename(at.plus(dir), at.plus("wrap/d")).unwrap();
+        fs::rename(at.plus("wrap"), at.plus(dir)).unwrap();
+    }
+
+    ucmd.arg("-r").arg(dir).succeeds().no_stderr();
+
+    assert!(!at.dir_exists(dir));
+}
+
+#[test]
+fn test_rm_preserve_root() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    let dir = "test_rm_preserve_root";
+
+    at.mkdir(dir);
+
+    // -i, with nothing to answer, so that nothing is lost should it fail
+    ucmd.arg("-r").arg("-i").arg("/").arg(dir).pipe_in("").fails()
+        .stderr_is("rm: error: it is dangerous to operate recursively on '/'\n\
+                rm: error: use --no-preserve-root to override this failsafe\n\
+                rm: remove directory 'test_rm_preserve_root'? ");
+
+    assert!(at.dir_exists(dir));
+}
+
+#[test]
+#[cfg(unix)]
+fn test_rm_preserve_root_same_as_root() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    let dir = "test_rm_preserve_root_same_as_root";
+
+    at.mkdir(dir);
+    at.symlink("/", &format!("{}/root", dir));
+
+    ucmd.arg("-r").arg("-i").arg("--no-preserve-root").arg("--preserve-root")
+        .arg(&format!("{}/root/", dir)).pipe_in("").fails()
+        .stderr_is("rm: error: it is dangerous to operate recursively on \
+                'test_rm_preserve_root_same_as_root/root/' (same as '/')\n\
+                rm: error: use --no-preserve-root to override this failsafe\n");
+}
+
 #[test]
 fn test_rm_errors() {
     let (at, mut ucmd) = at_and_ucmd!();
@@ -173,3 +305,20 @@ fn test_rm_no_operand() {
     ucmd.fails()
         .stderr_is("rm: error: missing an argument\nrm: error: for help, try 'rm --help'\n");
 }
+
+#[test]
+fn test_rm_sync() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    let dir = "test_rm_sync_dir";
+    let file_a = "test_rm_sync_dir/a";
+    let file_b = "test_rm_sync_dir/b";
+
+    at.mkdir(dir);
+    at.touch(file_a);
+    at.touch(file_b);
+
+    ucmd.arg("--sync").arg(file_a).arg(file_b).succeeds().no_stderr();
+
+    assert!(!at.file_exists(file_a));
+    assert!(!at.file_exists(file_b));
+}
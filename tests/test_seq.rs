@@ -24,3 +24,50 @@ fn test_equalize_widths() {
     new_ucmd!()
         .args(&["-w", "5", "10"]).run().stdout_is("05\n06\n07\n08\n09\n10\n");
 }
+
+#[test]
+fn test_equalize_widths_negative() {
+    new_ucmd!()
+        .args(&["-w", "-5", "3", "5"]).run().stdout_is("-5\n-2\n01\n04\n");
+}
+
+#[test]
+fn test_decimals() {
+    new_ucmd!()
+        .args(&["-w", "0.9", "0.1", "1.2"]).run().stdout_is("0.9\n1.0\n1.1\n1.2\n");
+}
+
+#[test]
+fn test_scientific_notation() {
+    new_ucmd!()
+        .args(&["1.5e-3", "1e-3", "5e-3"]).run().stdout_is("0.0015\n0.0025\n0.0035\n0.0045\n");
+    new_ucmd!()
+        .args(&["1e2", "0.5e2", "2e2"]).run().stdout_is("100\n150\n200\n");
+}
+
+#[test]
+fn test_hex() {
+    new_ucmd!()
+        .args(&["0x10", "0.5", "17"]).run().stdout_is("16.0\n16.5\n17.0\n");
+    new_ucmd!()
+        .args(&["0x1p4", "0.5", "17"]).run().stdout_is("16\n16.5\n17\n");
+}
+
+#[test]
+fn test_last_after_rounding() {
+    new_ucmd!()
+        .args(&["0", "0.000001", "0.000003"]).run().stdout_is("0.000000\n0.000001\n0.000002\n0.000003\n");
+}
+
+#[test]
+fn test_invalid_arguments() {
+    new_ucmd!()
+        .args(&["1", "0", "3"]).fails()
+        .stderr_is("seq: error: invalid Zero increment value: '0'\nTry 'seq --help' for more information.");
+    new_ucmd!()
+        .args(&["nan"]).fails()
+        .stderr_is("seq: error: invalid 'not-a-number' argument: 'nan'\nTry 'seq --help' for more information.");
+    new_ucmd!()
+        .args(&["1e5000"]).fails()
+        .stderr_is("seq: error: invalid floating point argument: '1e5000'\nTry 'seq --help' for more information.");
+}
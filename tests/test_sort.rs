@@ -1,4 +1,7 @@
 use common::util::*;
+use common::random::{self, Rng};
+use std::cmp::Ordering;
+use std::io::Write;
 
 
 
@@ -27,11 +30,37 @@ fn test_numeric_unsorted_ints() {
     test_helper("numeric_unsorted_ints", "-n");
 }
 
+#[test]
+fn test_general_numeric() {
+    test_helper("general_numeric", "-g");
+}
+
+#[test]
+fn test_general_numeric_special_values() {
+    // text, NaNs in GNU's order, infinities, signed zeros and hex floats
+    test_helper("general_numeric_special", "-g");
+}
+
 #[test]
 fn test_human_block_sizes() {
     test_helper("human_block_sizes", "-h");
 }
 
+#[test]
+fn test_human_suffixes() {
+    new_ucmd!().arg("-h").pipe_in("1G\n2K\n-1M\n500\n3k\n")
+        .succeeds().stdout_only("-1M\n500\n2K\n3k\n1G\n");
+    new_ucmd!().args(&["-k", "2h"]).pipe_in("b 10K\na 2M\nc 900\n")
+        .succeeds().stdout_only("c 900\nb 10K\na 2M\n");
+}
+
+#[test]
+fn test_numeric_prefix() {
+    // only the digits count, anything else being 0
+    new_ucmd!().arg("-n").pipe_in("1e3\n-2\nx\n100\n")
+        .succeeds().stdout_only("-2\nx\n1e3\n100\n");
+}
+
 #[test]
 fn test_month_default() {
     test_helper("month_default", "-M");
@@ -57,6 +86,16 @@ fn test_version() {
     test_helper("version", "-V");
 }
 
+#[test]
+fn test_version_file_names() {
+    // as GNU's filevercmp: ~ first, suffixes break ties, and leading zeros
+    // are left to the last-resort comparison
+    new_ucmd!().arg("-V").pipe_in("a10.txt\na9.txt\na1.tar.gz\na1~\nfoo-1.0rc1\nfoo-1.0\na01\n")
+        .succeeds().stdout_only("a1~\na01\na1.tar.gz\na9.txt\na10.txt\nfoo-1.0\nfoo-1.0rc1\n");
+    new_ucmd!().arg("-uV").pipe_in("a01\na1\n").succeeds().stdout_only("a01\n");
+    new_ucmd!().arg("-k2V").pipe_in("x a10\ny a9\n").succeeds().stdout_only("y a9\nx a10\n");
+}
+
 #[test]
 fn test_ignore_case() {
     test_helper("ignore_case", "-f");
@@ -106,12 +145,72 @@ fn test_merge_reversed() {
         .succeeds().stdout_only_fixture("merge_ints_reversed.expected");
 }
 
+#[test]
+fn test_merge_into_input() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("a");
+    at.append("a", "1\n3\n5\n");
+    at.touch("b");
+    at.append("b", "2\n4\n");
+    ucmd.args(&["-m", "-o", "a", "a", "b"]).succeeds().no_stdout();
+    assert_eq!(at.read("a"), "1\n2\n3\n4\n5\n");
+}
+
+#[test]
+fn test_output_no_clobber() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("out");
+    at.append("out", "old\n");
+    ucmd.args(&["--no-clobber", "-o", "out"]).pipe_in("b\na\n").fails();
+    assert_eq!(at.read("out"), "old\n");
+}
+
+#[test]
+fn test_random() {
+    let ts = TestScenario::new(util_name!());
+    ts.fixtures.touch("seed");
+    ts.fixtures.append("seed", "0123456789abcdefghij");
+    let input = "a\nb\na\nc\nb\na\nd\n";
+    let first = ts.ucmd().args(&["-R", "--random-source=seed"]).pipe_in(input).succeeds().stdout;
+    let second = ts.ucmd().args(&["-k", "1R", "--random-source=seed"]).pipe_in(input).succeeds().stdout;
+    assert_eq!(first, second);
+
+    // identical lines are kept together
+    let mut lines: Vec<&str> = first.lines().collect();
+    let mut runs = lines.clone();
+    runs.dedup();
+    assert_eq!(runs.len(), 4);
+    lines.sort();
+    assert_eq!(lines, vec!["a", "a", "a", "b", "b", "c", "d"]);
+
+    ts.fixtures.touch("short");
+    ts.ucmd().args(&["-R", "--random-source=short"]).fails()
+        .stderr_is("sort: error: short: end of file");
+}
+
+#[test]
+fn test_parallel_stable() {
+    let lines: Vec<String> = (0..50000).map(|i| format!("{} {}", (i * 7919) % 97, i)).collect();
+    let mut expected = lines.clone();
+    expected.sort_by_key(|line| line.split(' ').next().unwrap().parse::<u32>().unwrap());
+    new_ucmd!().args(&["--parallel=4", "-s", "-k", "1,1n"]).pipe_in(lines.join("\n") + "\n")
+        .succeeds().stdout_only(expected.join("\n") + "\n");
+}
+
+#[test]
+fn test_invalid_parallel() {
+    new_ucmd!().arg("--parallel=0").fails()
+        .stderr_is("sort: error: number in parallel must be nonzero");
+    new_ucmd!().arg("--parallel=x").fails()
+        .stderr_is("sort: error: invalid --parallel argument 'x'");
+}
+
 #[test]
 fn test_check() {
     new_ucmd!()
         .arg("-c")
         .arg("check_fail.txt")
-        .fails().stdout_is("sort: disorder in line 4\n");
+        .fails().stderr_is("sort: error: check_fail.txt:6: disorder: 5");
 
     new_ucmd!()
         .arg("-c")
@@ -119,7 +218,347 @@ fn test_check() {
         .succeeds().stdout_is("");
 }
 
+#[test]
+fn test_check_silent() {
+    for arg in &["-C", "--check=quiet", "--check=silent"] {
+        new_ucmd!()
+            .args(&[arg, "check_fail.txt"])
+            .fails().no_stdout().no_stderr();
+    }
+    new_ucmd!()
+        .args(&["--check=sometimes", "check_fail.txt"])
+        .fails().stderr_is("sort: error: invalid argument 'sometimes' for '--check'");
+    new_ucmd!()
+        .args(&["-C", "check_fail.txt", "multiple_files.expected"])
+        .fails().stderr_is("sort: error: extra operand 'multiple_files.expected' not allowed with -C");
+}
+
+#[test]
+fn test_check_unique() {
+    new_ucmd!()
+        .arg("-c")
+        .pipe_in("a\na\n")
+        .succeeds().no_stderr();
+    new_ucmd!()
+        .args(&["-c", "-u"])
+        .pipe_in("a\na\n")
+        .fails().stderr_is("sort: error: -:2: disorder: a");
+}
+
+#[test]
+fn test_merge_batches() {
+    new_ucmd!()
+        .args(&["-m", "--batch-size=2"])
+        .arg("merge_ints_interleaved_1.txt")
+        .arg("merge_ints_interleaved_2.txt")
+        .arg("merge_ints_interleaved_3.txt")
+        .succeeds().stdout_only_fixture("merge_ints_interleaved.expected");
+}
+
+#[test]
+#[cfg(feature = "decompress")]
+fn test_decompress() {
+    new_ucmd!()
+        .args(&["--decompress", "default_unsorted_ints.txt.gz"])
+        .succeeds().stdout_is_fixture("default_unsorted_ints.expected");
+    new_ucmd!()
+        .args(&["--decompress", "default_unsorted_ints.txt.zst"])
+        .succeeds().stdout_is_fixture("default_unsorted_ints.expected");
+    // plain input passes through
+    new_ucmd!()
+        .args(&["--decompress", "default_unsorted_ints.txt"])
+        .succeeds().stdout_is_fixture("default_unsorted_ints.expected");
+}
+
+#[test]
+fn test_buffer_size_spills() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.mkdir("tmp");
+    ucmd.args(&["-S", "100b", "-T", "tmp", "default_unsorted_ints.txt"])
+        .succeeds().stdout_is_fixture("default_unsorted_ints.expected");
+    // the temporary files are gone afterwards
+    assert!(at.plus("tmp").read_dir().unwrap().next().is_none());
+}
+
+#[test]
+fn test_batch_size() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.mkdir("tmp");
+    // runs merged a pair at a time keep equal lines in the input's order
+    let lines: Vec<String> = (0..300).map(|i| format!("{} {}", i % 3, i)).collect();
+    let mut expected = lines.clone();
+    expected.sort_by_key(|line| line.chars().next());
+    ucmd.args(&["-S", "100b", "--batch-size=2", "-T", "tmp", "-s", "-k", "1,1"])
+        .pipe_in(lines.join("\n") + "\n")
+        .succeeds().stdout_only(expected.join("\n") + "\n");
+    assert!(at.plus("tmp").read_dir().unwrap().next().is_none());
+
+    new_ucmd!().arg("--batch-size=1").fails()
+        .stderr_is("sort: error: invalid --batch-size argument '1'\nsort: error: minimum --batch-size argument is '2'");
+}
+
+#[test]
+fn test_compress_program() {
+    new_ucmd!()
+        .args(&["-S", "100b", "--compress-program=gzip", "-nu", "numeric_unsorted_ints_unique.txt"])
+        .succeeds().stdout_is_fixture("numeric_unsorted_ints_unique.expected");
+
+    new_ucmd!()
+        .args(&["-S", "100b", "--compress-program=nonexistent-compressor", "default_unsorted_ints.txt"])
+        .fails();
+}
+
+#[test]
+#[cfg(all(target_os = "linux", feature = "sandbox"))]
+fn test_sandbox_spills() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.mkdir("tmp");
+    ucmd.args(&["--sandbox", "-S", "100b", "-T", "tmp", "default_unsorted_ints.txt"])
+        .succeeds().stdout_is_fixture("default_unsorted_ints.expected");
+    assert!(at.plus("tmp").read_dir().unwrap().next().is_none());
+
+    // into $TMPDIR, along with a copy of the input the output overwrites
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.mkdir("tmp");
+    at.touch("a");
+    at.append("a", "1\n3\n5\n");
+    at.touch("b");
+    at.append("b", "2\n4\n");
+    ucmd.env("TMPDIR", at.plus_as_string("tmp"))
+        .args(&["--sandbox", "-S", "100b", "-m", "-o", "a", "a", "b"])
+        .succeeds().no_stdout();
+    assert_eq!(at.read("a"), "1\n2\n3\n4\n5\n");
+    assert!(at.plus("tmp").read_dir().unwrap().next().is_none());
+
+    new_ucmd!()
+        .args(&["--sandbox", "--compress-program=gzip", "default_unsorted_ints.txt"])
+        .fails().stderr_is("sort: error: options '--compress-program' and '--sandbox' are incompatible");
+}
+
+#[test]
+fn test_invalid_buffer_size() {
+    new_ucmd!().args(&["-S", "12x"]).fails();
+    new_ucmd!().args(&["-S", "101%"]).fails();
+}
+
+#[test]
+fn test_crlf() {
+    new_ucmd!().arg("--crlf").pipe_in("b\r\na\n").succeeds().stdout_only("a\r\nb\r\n");
+}
+
+#[test]
+fn test_zero_terminated() {
+    // newlines are part of the records, and blanks between their fields
+    new_ucmd!().arg("-z").pipe_in("c\0b\na\0a\nb").succeeds().stdout_only("a\nb\0b\na\0c\0");
+    new_ucmd!().args(&["-z", "-k", "2"]).pipe_in("x\nb\0y\na\0").succeeds().stdout_only("y\na\0x\nb\0");
+    new_ucmd!().args(&["-z", "-c"]).pipe_in("a\nb\0a\nc\0").succeeds().no_stdout();
+
+    // and still are once spilled to temporary files and merged back
+    let records: Vec<String> = (0..100).map(|i| format!("{}\n{}", 99 - i, i)).collect();
+    let mut expected = records.clone();
+    expected.sort();
+    new_ucmd!().args(&["-z", "-S", "100b", "--batch-size=2"])
+        .pipe_in(records.join("\0") + "\0")
+        .succeeds().stdout_only(expected.join("\0") + "\0");
+}
+
+#[test]
+#[cfg(unix)]
+fn test_carriage_return_kept() {
+    new_ucmd!().pipe_in("b\r\na\n").succeeds().stdout_only("a\nb\r\n");
+}
+
 fn test_helper(file_name: &str, args: &str) {
     new_ucmd!().arg(args).arg(format!("{}{}", file_name, ".txt"))
         .succeeds().stdout_is_fixture(format!("{}{}", file_name, ".expected"));
 }
+
+#[test]
+fn test_keys() {
+    let input = "b 2 x\na 10 y\nc 1 x\na 2 z\n";
+    new_ucmd!().args(&["-k", "2n"]).pipe_in(input)
+        .succeeds().stdout_only("c 1 x\na 2 z\nb 2 x\na 10 y\n");
+    new_ucmd!().args(&["-k", "3,3", "-k", "2,2nr"]).pipe_in(input)
+        .succeeds().stdout_only("b 2 x\nc 1 x\na 10 y\na 2 z\n");
+    // keys with no options of their own take the global ones
+    new_ucmd!().args(&["-k", "1,1", "-n", "-k", "2"]).pipe_in(input)
+        .succeeds().stdout_only("c 1 x\na 2 z\nb 2 x\na 10 y\n");
+}
+
+#[test]
+fn test_key_character_offsets() {
+    // without -b the blanks before a field are part of it
+    new_ucmd!().args(&["-k", "2.2,2.2"]).pipe_in("x  ab\nx cb\nx  ba\n")
+        .succeeds().stdout_only("x  ab\nx  ba\nx cb\n");
+    new_ucmd!().args(&["-k", "2.2b,2.2b"]).pipe_in("x  ab\nx cb\nx  ba\n")
+        .succeeds().stdout_only("x  ba\nx  ab\nx cb\n");
+}
+
+#[test]
+fn test_key_field_separator() {
+    new_ucmd!().args(&["-t", ":", "-k", "3n,3", "-k", "1,1r"]).pipe_in("a:x:3\nb:y:1\nc::3\n")
+        .succeeds().stdout_only("b:y:1\nc::3\na:x:3\n");
+    new_ucmd!().args(&["-t", "ab", "-k", "1"]).fails()
+        .stderr_is("sort: error: multi-character tab 'ab'");
+}
+
+#[test]
+fn test_key_unique() {
+    new_ucmd!().args(&["-u", "-k", "2,2"]).pipe_in("a 1\nb 2\nc 1\n")
+        .succeeds().stdout_only("a 1\nb 2\n");
+}
+
+#[test]
+fn test_unique_first_of_run() {
+    new_ucmd!().args(&["-f", "-u"]).pipe_in("b\nA\na\nB\n")
+        .succeeds().stdout_only("A\nb\n");
+}
+
+#[test]
+fn test_stable_key() {
+    new_ucmd!().args(&["-s", "-k", "1,1"]).pipe_in("b 2\na 2\nb 1\na 1\n")
+        .succeeds().stdout_only("a 2\na 1\nb 2\nb 1\n");
+    new_ucmd!().args(&["-k", "1,1"]).pipe_in("b 2\na 2\nb 1\na 1\n")
+        .succeeds().stdout_only("a 1\na 2\nb 1\nb 2\n");
+}
+
+#[test]
+fn test_invalid_key() {
+    new_ucmd!().args(&["-k", "0"]).fails()
+        .stderr_is("sort: error: field number is zero: invalid field specification '0'");
+}
+
+// A model of sort with -t, -k and the options n, f and r, slow and simple
+// enough to be plainly right.
+struct ModelKey {
+    start: usize,
+    end: Option<usize>,
+    numeric: bool,
+    fold: bool,
+    reverse: bool,
+}
+
+impl ModelKey {
+    fn extract<'a>(&self, line: &'a str) -> String {
+        let fields: Vec<&str> = line.split(',').collect();
+        let end = self.end.map_or(fields.len(), |end| end.min(fields.len()));
+        if self.start > end {
+            return String::new();
+        }
+        fields[self.start - 1..end].join(",")
+    }
+
+    fn compare(&self, a: &str, b: &str) -> Ordering {
+        let (a, b) = (self.extract(a), self.extract(b));
+        let cmp = if self.numeric {
+            model_number(&a).partial_cmp(&model_number(&b)).unwrap()
+        } else if self.fold {
+            a.to_uppercase().cmp(&b.to_uppercase())
+        } else {
+            a.cmp(&b)
+        };
+        if self.reverse { cmp.reverse() } else { cmp }
+    }
+}
+
+// The number a key starts with after blanks, or 0.
+fn model_number(key: &str) -> f64 {
+    let key = key.trim_left_matches(' ');
+    let digits = |from: usize| from + key[from..].bytes().take_while(|c| c.is_ascii_digit()).count();
+    let mut end = digits(if key.starts_with('-') { 1 } else { 0 });
+    if key[end..].starts_with('.') {
+        end = digits(end + 1);
+    }
+    key[..end].parse().unwrap_or(0.0)
+}
+
+fn model_sort(lines: &[String], keys: &[ModelKey], whole: &ModelKey, stable: bool, unique: bool) -> Vec<String> {
+    let compare_keys = |a: &String, b: &String| {
+        keys.iter().fold(Ordering::Equal, |cmp, key| cmp.then_with(|| key.compare(a, b)))
+    };
+    let mut sorted = lines.to_vec();
+    sorted.sort_by(|a, b| {
+        let cmp = compare_keys(a, b);
+        if stable || unique {
+            cmp
+        } else {
+            cmp.then_with(|| if whole.reverse { b.cmp(a) } else { a.cmp(b) })
+        }
+    });
+    if unique {
+        sorted.dedup_by(|b, a| compare_keys(a, b) == Ordering::Equal);
+    }
+    sorted
+}
+
+#[test]
+fn test_random_against_model() {
+    let scene = TestScenario::new(util_name!());
+    let mut rng = random::rng();
+    let gnu = random::gnu("sort");
+    let alphabet = ['a', 'b', 'B', '_', '0', '1', '9', '-', '.', ' '];
+    for _ in 0..random::CASES {
+        let mut lines = random::lines(&mut rng, 12, |rng| {
+            let fields = rng.gen_range(1, 4);
+            (0..fields).map(|_| random::word(rng, &alphabet, 0, 3)).collect::<Vec<_>>().join(",")
+        });
+        let mut args = vec!["-t".to_owned(), ",".to_owned()];
+        let whole = ModelKey {
+            start: 1,
+            end: None,
+            numeric: random::flag(&mut rng, &mut args, "-n"),
+            fold: random::flag(&mut rng, &mut args, "-f"),
+            reverse: random::flag(&mut rng, &mut args, "-r"),
+        };
+        let stable = random::flag(&mut rng, &mut args, "-s");
+        let unique = random::flag(&mut rng, &mut args, "-u");
+
+        // keys without options of their own take the global ones
+        let mut keys = Vec::new();
+        for _ in 0..rng.gen_range(0, 3) {
+            let start = rng.gen_range(1, 4);
+            let end = if rng.gen() { Some(rng.gen_range(start, 4)) } else { None };
+            let (numeric, fold, reverse) = (rng.gen_weighted_bool(4), rng.gen_weighted_bool(4), rng.gen_weighted_bool(4));
+            let mut spec = start.to_string();
+            if let Some(end) = end {
+                spec.push_str(&format!(",{}", end));
+            }
+            for &(flag, set) in &[('n', numeric), ('f', fold), ('r', reverse)] {
+                if set {
+                    spec.push(flag);
+                }
+            }
+            args.push(format!("-k{}", spec));
+            keys.push(if numeric || fold || reverse {
+                ModelKey { start: start, end: end, numeric: numeric, fold: fold, reverse: reverse }
+            } else {
+                ModelKey { start: start, end: end, ..whole }
+            });
+        }
+        if keys.is_empty() {
+            keys.push(ModelKey { ..whole });
+        }
+
+        // or merged from two files each already sorted, where lines that
+        // compare equal come from the first file first
+        let mut input = random::text(&lines);
+        if random::flag(&mut rng, &mut args, "-m") {
+            let split = rng.gen_range(0, lines.len() + 1);
+            let mut merged = Vec::new();
+            for &(name, part) in &[("first", &lines[..split]), ("second", &lines[split..])] {
+                let part = model_sort(part, &keys, &whole, stable, false);
+                scene.fixtures.make_file(name).write_all(random::text(&part).as_bytes()).unwrap();
+                args.push(scene.fixtures.plus_as_string(name));
+                merged.extend(part);
+            }
+            lines = merged;
+            input.clear();
+        }
+        let expected = random::text(&model_sort(&lines, &keys, &whole, stable, unique));
+        if let Some(ref gnu) = gnu {
+            assert_eq!(gnu.run(&args, &input), expected, "GNU sort {:?} of {:?}", args, lines);
+        }
+        let result = scene.ucmd().args(&args).pipe_in(input.clone()).run();
+        assert_eq!(result.stdout, expected, "sort {:?} of {:?}", args, lines);
+    }
+}
@@ -143,3 +143,23 @@ fn test_split_str_prefixed_chunks_by_lines() {
     assert_eq!(glob.count(), 10);
     assert_eq!(glob.collate(), at.read(name).into_bytes());
 }
+
+#[test]
+fn test_split_would_overwrite_input() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("xab");
+    at.append("xab", "1\n2\n3\n");
+    ucmd.args(&["-l", "1", "xab"]).fails()
+        .stderr_is("split: error: 'xab' would overwrite input; aborting");
+    assert_eq!(at.read("xaa"), "1\n");
+    assert_eq!(at.read("xab"), "1\n2\n3\n");
+}
+
+#[test]
+fn test_split_no_clobber() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("xaa");
+    at.append("xaa", "old\n");
+    ucmd.arg("--no-clobber").pipe_in("new\n").fails();
+    assert_eq!(at.read("xaa"), "old\n");
+}
@@ -222,6 +222,25 @@ fn test_multi_files() {
         .stdout_is(expected_result(&args));
 }
 
+#[test]
+#[cfg(target_os = "linux")]
+fn test_cached() {
+    for mode in &["--cached=default", "--cached=never", "--cached=always"] {
+        let args = [mode, "-c", NORMAL_FMTSTR, "/dev", "/etc/fstab"];
+        new_ucmd!().args(&args)
+            .run()
+            .stdout_is(expected_result(&args));
+    }
+}
+
+#[test]
+fn test_cached_invalid() {
+    new_ucmd!()
+        .args(&["--cached=sometimes", "/"])
+        .fails()
+        .no_stdout();
+}
+
 #[test]
 #[cfg(target_os = "linux")]
 fn test_printf() {
@@ -3,7 +3,8 @@ extern crate uu_tail;
 use common::util::*;
 use std::char::from_digit;
 use self::uu_tail::parse_size;
-use std::io::Write;
+use std::fs;
+use std::io::{Read, Write};
 use std::process::{Command, Stdio};
 use std::thread::sleep;
 use std::time::Duration;
@@ -72,6 +73,75 @@ fn test_follow_multiple() {
     child.kill().unwrap();
 }
 
+#[test]
+fn test_follow_multiple_partial_lines() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("a");
+    at.append("a", "one\n");
+    at.touch("b");
+    at.append("b", "two\n");
+    let mut child = ucmd.args(&["-f", "-s", ".1", "a", "b"]).run_no_wait();
+    let expected = "==> a <==\none\n\n==> b <==\ntwo\n";
+    assert_eq!(read_size(&mut child, expected.len()), expected);
+
+    // a line written to one file a piece at a time is not broken up by
+    // one written to another in between
+    at.append("a", "thr");
+    sleep(Duration::from_millis(300));
+    at.append("b", "four\n");
+    sleep(Duration::from_millis(300));
+    at.append("a", "ee\n");
+    let expected = "four\n\n==> a <==\nthree\n";
+    assert_eq!(read_size(&mut child, expected.len()), expected);
+
+    child.kill().unwrap();
+}
+
+#[test]
+fn test_follow_truncated() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("log");
+    at.append("log", "one\ntwo\n");
+    let mut child = ucmd.arg("-f").arg("log").run_no_wait();
+    assert_eq!(read_size(&mut child, 8), "one\ntwo\n");
+
+    // the file is read again from its start
+    at.make_file("log").write_all(b"new\n").unwrap();
+    assert_eq!(read_size(&mut child, 4), "new\n");
+    at.append("log", "more\n");
+    assert_eq!(read_size(&mut child, 5), "more\n");
+
+    child.kill().unwrap();
+}
+
+#[test]
+fn test_follow_name_retry() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("log");
+    at.append("log", "one\n");
+    let mut child = ucmd.args(&["-F", "-s", ".1", "log"]).run_no_wait();
+    assert_eq!(read_size(&mut child, 4), "one\n");
+
+    // rotated, with what was written last still read, then recreated
+    at.append("log", "two\n");
+    fs::rename(at.plus("log"), at.plus("log.1")).unwrap();
+    sleep(Duration::from_millis(500));
+    at.make_file("log").write_all(b"three\n").unwrap();
+    assert_eq!(read_size(&mut child, 10), "two\nthree\n");
+
+    // replaced outright
+    at.make_file("log.new").write_all(b"four\n").unwrap();
+    fs::rename(at.plus("log.new"), at.plus("log")).unwrap();
+    assert_eq!(read_size(&mut child, 5), "four\n");
+
+    child.kill().unwrap();
+    let mut stderr = String::new();
+    child.stderr.take().unwrap().read_to_string(&mut stderr).unwrap();
+    assert!(stderr.contains("'log' has become inaccessible: "));
+    assert!(stderr.contains("'log' has appeared;  following new file"));
+    assert!(stderr.contains("'log' has been replaced;  following new file"));
+}
+
 #[test]
 fn test_follow_stdin() {
     new_ucmd!().arg("-f").pipe_in_fixture(FOOBAR_TXT).run().stdout_is_fixture("follow_stdin.expected");
@@ -89,7 +159,10 @@ fn test_follow_with_pid() {
     let mut dummy = Command::new(dummy_cmd).stdout(Stdio::null()).spawn().unwrap();
     let pid = dummy.id();
 
-    let mut child = ucmd.arg("-f").arg(format!("--pid={}", pid)).arg(FOOBAR_TXT).arg(FOOBAR_2_TXT).run_no_wait();
+    // checking on the process every tenth of a second, well within the
+    // second that it is given to notice
+    let mut child = ucmd.args(&["-f", "-s", ".1"]).arg(format!("--pid={}", pid)).arg(FOOBAR_TXT).arg(FOOBAR_2_TXT)
+        .run_no_wait();
 
     let expected = at.read("foobar_follow_multiple.expected");
     assert_eq!(read_size(&mut child, expected.len()), expected);
@@ -119,6 +192,33 @@ fn test_follow_with_pid() {
     assert_eq!(child.kill().is_err(), true);
 }
 
+#[test]
+#[cfg(unix)]
+fn test_follow_pid_exits_after_job() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.touch("log");
+
+    // the job writes the last of its log as it finishes
+    let mut job = Command::new("sh").args(&["-c", "sleep 1; echo done >> log"]).current_dir(at.as_string())
+        .spawn().unwrap();
+    let mut child = ucmd.args(&["-f", "-s", ".1", "log"]).arg(format!("--pid={}", job.id())).run_no_wait();
+    job.wait().unwrap();
+    sleep(Duration::from_millis(500));
+
+    assert!(child.try_wait().unwrap().is_some(), "tail is still running");
+    let mut stdout = String::new();
+    child.stdout.take().unwrap().read_to_string(&mut stdout).unwrap();
+    assert_eq!(stdout, "done\n");
+}
+
+#[test]
+fn test_invalid_pid() {
+    for pid in &["x", "-1", "99999999999"] {
+        new_ucmd!().args(&["-f", "/dev/null"]).arg(format!("--pid={}", pid)).fails()
+            .stderr_is(format!("tail: error: invalid PID: '{}'", pid));
+    }
+}
+
 #[test]
 fn test_single_big_args() {
     const FILE: &'static str = "single_big_args.txt";
@@ -187,6 +287,40 @@ fn test_bytes_big() {
     }
 }
 
+#[test]
+fn test_from_beginning() {
+    new_ucmd!().args(&["-n", "+10", FOOBAR_TXT]).run().stdout_is("diez\nonce\n");
+    new_ucmd!().args(&["-c", "+56", FOOBAR_TXT]).run().stdout_is("once\n");
+    new_ucmd!().args(&["-n", "+0"]).pipe_in("one\ntwo\n").run().stdout_is("one\ntwo\n");
+    new_ucmd!().args(&["-c", "+5"]).pipe_in("one\ntwo\n").run().stdout_is("two\n");
+}
+
+#[test]
+fn test_counts_beyond_file() {
+    new_ucmd!().args(&["-c", "100", FOOBAR_TXT]).run().stdout_is_fixture(FOOBAR_TXT);
+    for &count in &["-n0", "-c0"] {
+        new_ucmd!().args(&[count, FOOBAR_TXT]).run().stdout_is("");
+        new_ucmd!().arg(count).pipe_in_fixture(FOOBAR_TXT).run().stdout_is("");
+    }
+}
+
+#[test]
+fn test_lines_file_of_whole_blocks() {
+    // as long as the blocks `tail` reads backwards in
+    let (at, mut ucmd) = at_and_ucmd!();
+    let mut file = at.make_file("blocks");
+    for _ in 0..(1 << 16) / 8 {
+        file.write_all(b"1234567\n").unwrap();
+    }
+
+    ucmd.args(&["-n", "2", "blocks"]).run().stdout_is("1234567\n1234567\n");
+}
+
+#[test]
+fn test_zero_terminated_stdin() {
+    new_ucmd!().args(&["-z", "-n", "2"]).pipe_in("a\0b\0c\0").run().stdout_is("b\0c\0");
+}
+
 #[test]
 fn test_parse_size() {
     // No suffix.
@@ -257,7 +391,59 @@ fn test_multiple_input_files_with_suppressed_headers() {
 }
 
 #[test]
-fn test_multiple_input_quiet_flag_overrides_verbose_flag_for_suppressing_headers() {
-    // TODO: actually the later one should win, i.e. -qv should lead to headers being printed, -vq to them being suppressed
-    new_ucmd!().arg(FOOBAR_TXT).arg(FOOBAR_2_TXT).arg("-q").arg("-v").run().stdout_is_fixture("foobar_multiple_quiet.expected");
+fn test_multiple_input_last_of_quiet_and_verbose_wins() {
+    new_ucmd!().arg(FOOBAR_TXT).arg(FOOBAR_2_TXT).arg("-v").arg("-q").run().stdout_is_fixture("foobar_multiple_quiet.expected");
+    new_ucmd!().arg(FOOBAR_TXT).arg(FOOBAR_2_TXT).arg("-q").arg("-v").run().stdout_is_fixture("foobar_follow_multiple.expected");
+    new_ucmd!().arg(FOOBAR_TXT).arg(FOOBAR_2_TXT).arg("-vq").run().stdout_is_fixture("foobar_multiple_quiet.expected");
+    new_ucmd!().arg(FOOBAR_TXT).arg(FOOBAR_2_TXT).arg("--silent").run().stdout_is_fixture("foobar_multiple_quiet.expected");
+}
+
+#[test]
+fn test_standard_input_header() {
+    new_ucmd!().args(&["-v"]).pipe_in("one\n").run().stdout_is("==> standard input <==\none\n");
+    new_ucmd!().args(&["-n", "1", "-", FOOBAR_2_TXT]).pipe_in("one\n").run()
+        .stdout_is("==> standard input <==\none\n\n==> foobar2.txt <==\ndeux\n");
+}
+
+#[test]
+fn test_directory() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    at.mkdir("dir");
+    ucmd.args(&["-n", "1", "dir", FOOBAR_2_TXT]).fails()
+        .stdout_is("==> dir <==\n\n==> foobar2.txt <==\ndeux\n")
+        .stderr_is("tail: error: error reading 'dir': Is a directory");
+}
+
+#[test]
+fn test_follow_multiple_quiet() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    let mut child = ucmd.args(&["-f", "-q", "-n", "1", FOOBAR_TXT, FOOBAR_2_TXT]).run_no_wait();
+    assert_eq!(read_size(&mut child, 10), "once\ndeux\n");
+
+    // no header on switching from one file to the other either
+    at.append(FOOBAR_TXT, "doce\n");
+    assert_eq!(read_size(&mut child, 5), "doce\n");
+
+    child.kill().unwrap();
+}
+
+#[test]
+fn test_sparse_file() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    let mut file = at.make_file("sparse");
+    file.set_len(1 << 30).unwrap();
+    file.write_all(b"start\n").unwrap();
+    at.append("sparse", "end\n");
+
+    ucmd.args(&["-c", "10", "sparse"]).run().stdout_is("\0\0\0\0\0\0end\n");
+}
+
+#[test]
+fn test_sparse_file_lines() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    let file = at.make_file("sparse");
+    file.set_len(1 << 20).unwrap();
+    at.append("sparse", "\nlast\n");
+
+    ucmd.args(&["-n", "1", "sparse"]).run().stdout_is("last\n");
 }
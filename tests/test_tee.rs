@@ -0,0 +1,77 @@
+extern crate libc;
+
+use common::util::*;
+
+
+#[test]
+fn test_tee_file() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    ucmd.arg("out").pipe_in("abc\n").succeeds().stdout_only("abc\n");
+    assert_eq!(at.read("out"), "abc\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_tee_pipe_to() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    ucmd.args(&["--pipe-to", "tr a-z A-Z > upper", "--pipe-to", "wc -l > count"])
+        .pipe_in("abc\ndef\n")
+        .succeeds()
+        .stdout_only("abc\ndef\n");
+    assert_eq!(at.read("upper"), "ABC\nDEF\n");
+    assert_eq!(at.read("count").trim(), "2");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_tee_pipe_to_failure() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    // one command failing does not keep the input from the others
+    let result = ucmd.args(&["--pipe-to", "exit 3", "--pipe-to", "cat > copy"])
+        .pipe_in("abc\n")
+        .run();
+    assert!(!result.success);
+    assert_eq!(result.stdout, "abc\n");
+    assert!(result.stderr.contains("exit 3"));
+    assert_eq!(at.read("copy"), "abc\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_tee_fifos_opened_out_of_order() {
+    use std::ffi::CString;
+    use std::fs::File;
+    use std::io::Read;
+    use std::thread;
+
+    let (at, mut ucmd) = at_and_ucmd!();
+    for name in &["a", "b"] {
+        let fifo = CString::new(at.plus_as_string(name)).unwrap();
+        assert_eq!(unsafe { self::libc::mkfifo(fifo.as_ptr(), 0o644) }, 0);
+    }
+
+    // like `paste b a`, which waits for b to be opened before opening a
+    let (a, b) = (at.plus("a"), at.plus("b"));
+    let reader = thread::spawn(move || {
+        let mut b = File::open(b).unwrap();
+        let mut a = File::open(a).unwrap();
+        let (mut from_a, mut from_b) = (String::new(), String::new());
+        b.read_to_string(&mut from_b).unwrap();
+        a.read_to_string(&mut from_a).unwrap();
+        (from_a, from_b)
+    });
+
+    ucmd.args(&["a", "b"]).pipe_in("abc\n").succeeds().stdout_only("abc\n");
+    assert_eq!(reader.join().unwrap(), ("abc\n".to_owned(), "abc\n".to_owned()));
+}
+
+#[test]
+fn test_tee_unopenable_file() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    // the other files are still written
+    let result = ucmd.args(&["missing/out", "out"]).pipe_in("abc\n").run();
+    assert!(!result.success);
+    assert_eq!(result.stdout, "abc\n");
+    assert!(result.stderr.starts_with("tee: missing/out: "));
+    assert_eq!(at.read("out"), "abc\n");
+}
@@ -284,3 +284,20 @@ fn test_touch_set_date() {
     assert_eq!(atime, start_of_year);
     assert_eq!(mtime, start_of_year);
 }
+
+#[test]
+fn test_touch_set_timestamp_across_dst() {
+    let scene = TestScenario::new(util_name!());
+    let at = &scene.fixtures;
+
+    // noon before and after the clocks went forward in New York in 2021
+    scene.ucmd().env("TZ", "America/New_York").args(&["-t", "202103131200", "winter"]).succeeds().no_stderr();
+    scene.ucmd().env("TZ", "America/New_York").args(&["-t", "202103151200", "summer"]).succeeds().no_stderr();
+    assert_eq!(get_file_times(&at, "winter").1, FileTime::from_seconds_since_1970(1615654800, 0));
+    assert_eq!(get_file_times(&at, "summer").1, FileTime::from_seconds_since_1970(1615824000, 0));
+
+    // 02:30 that night never happened
+    scene.ucmd().env("TZ", "America/New_York").args(&["-t", "202103140230", "gap"])
+        .fails().stderr_is("touch: error: invalid date format '202103140230'");
+    assert!(!at.file_exists("gap"));
+}
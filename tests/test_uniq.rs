@@ -1,4 +1,5 @@
 use common::util::*;
+use common::random::{self, Rng};
 
 
 static INPUT: &'static str = "sorted.txt";
@@ -103,3 +104,78 @@ fn test_stdin_zero_terminated() {
         .args(&["-z"]).pipe_in_fixture(SORTED_ZERO_TERMINATED)
         .run().stdout_is_fixture("sorted-zero-terminated.expected");
 }
+
+#[test]
+fn test_stdin_crlf() {
+    new_ucmd!()
+        .args(&["--crlf", "-c"]).pipe_in("a\r\na\nb\r\n")
+        .run().stdout_is("      2 a\r\n      1 b\r\n");
+}
+
+// What uniq compares of `line`, after skipping `fields` fields and then
+// `chars` characters.
+fn model_key(line: &str, fields: usize, chars: usize, width: Option<usize>, fold: bool) -> String {
+    let is_blank = |c| c == ' ' || c == '\t';
+    let mut rest = line;
+    for _ in 0..fields {
+        rest = rest.trim_left_matches(&is_blank).trim_left_matches(|c| !is_blank(c));
+    }
+    let key: String = rest.chars().skip(chars).take(width.unwrap_or(line.len())).collect();
+    if fold { key.to_uppercase() } else { key }
+}
+
+#[test]
+fn test_random_against_model() {
+    let mut rng = random::rng();
+    let gnu = random::gnu("uniq");
+    let alphabet = ['a', 'A', 'b', ' ', '\t'];
+    for _ in 0..random::CASES {
+        // lines from a few, so that many are repeated
+        let pool: Vec<String> = (0..4).map(|_| random::word(&mut rng, &alphabet, 0, 4)).collect();
+        let lines = random::lines(&mut rng, 12, |rng| rng.choose(&pool).unwrap().clone());
+
+        let mut args = Vec::new();
+        let all_repeated = random::flag(&mut rng, &mut args, "-D");
+        let count = !all_repeated && random::flag(&mut rng, &mut args, "-c");
+        let repeated = random::flag(&mut rng, &mut args, "-d");
+        let unique = !all_repeated && random::flag(&mut rng, &mut args, "-u");
+        let fold = random::flag(&mut rng, &mut args, "-i");
+        let mut number = |rng: &mut random::XorShiftRng, args: &mut Vec<String>, name: &str| {
+            if random::flag(rng, args, name) {
+                let n = rng.gen_range(0, 3);
+                args.push(n.to_string());
+                Some(n)
+            } else {
+                None
+            }
+        };
+        let fields = number(&mut rng, &mut args, "-f").unwrap_or(0);
+        let chars = number(&mut rng, &mut args, "-s").unwrap_or(0);
+        let width = number(&mut rng, &mut args, "-w");
+
+        let mut expected = Vec::new();
+        let mut start = 0;
+        while start < lines.len() {
+            let key = model_key(&lines[start], fields, chars, width, fold);
+            let end = start + lines[start..].iter()
+                .take_while(|line| model_key(line, fields, chars, width, fold) == key).count();
+            let n = end - start;
+            if all_repeated {
+                if n > 1 {
+                    expected.extend(lines[start..end].iter().cloned());
+                }
+            } else if (n == 1 || !unique) && (n > 1 || !repeated) {
+                expected.push(if count { format!("{:7} {}", n, lines[start]) } else { lines[start].clone() });
+            }
+            start = end;
+        }
+
+        let input = random::text(&lines);
+        let expected = random::text(&expected);
+        if let Some(ref gnu) = gnu {
+            assert_eq!(gnu.run(&args, &input), expected, "GNU uniq {:?} of {:?}", args, input);
+        }
+        let result = new_ucmd!().args(&args).pipe_in(input.clone()).run();
+        assert_eq!(result.stdout, expected, "uniq {:?} of {:?}", args, input);
+    }
+}
@@ -0,0 +1,39 @@
+use common::util::*;
+
+#[test]
+#[cfg(feature = "hashsum")]
+fn test_alias() {
+    TestScenario::new("md5sum").ucmd().pipe_in("hello\n").succeeds()
+        .stdout_only("b1946ac92492d2347c6235b4d2611184  -\n");
+}
+
+#[test]
+#[cfg(feature = "uname")]
+fn test_alias_with_options() {
+    let ts = TestScenario::new("arch");
+    let machine = ts.cmd("uname").arg("-m").run().stdout;
+    ts.ucmd().succeeds().stdout_only(machine);
+}
+
+#[test]
+#[cfg(feature = "uname")]
+fn test_install() {
+    let ts = TestScenario::new("--install");
+    let at = &ts.fixtures;
+    at.mkdir("bin");
+    ts.ucmd().arg("--symlinks").arg("bin").succeeds().no_stderr();
+    assert!(at.is_symlink("bin/uname"));
+    assert!(at.is_symlink("bin/arch"));
+
+    // names already there are left alone
+    ts.ucmd().arg("bin").succeeds().no_stderr();
+    assert!(at.is_symlink("bin/arch"));
+
+    let machine = ts.cmd("uname").arg("-m").run().stdout;
+    ts.cmd(at.plus("bin/arch")).succeeds().stdout_only(machine);
+}
+
+#[test]
+fn test_install_missing_directory() {
+    TestScenario::new("--install").ucmd().fails().stdout_is("uutils: --install: missing directory operand");
+}
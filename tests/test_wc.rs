@@ -48,3 +48,107 @@ fn test_multiple_default() {
                "   13  109  772 lorem_ipsum.txt\n   18  204 1115 moby_dick.txt\n    5   57  302 \
                 alice_in_wonderland.txt\n   36  370 2189 total\n");
 }
+
+#[test]
+fn test_lines_and_bytes_of_many_files() {
+    // counted on several threads at once, but given in order
+    let files: Vec<&str> = (0..40)
+        .map(|i| if i % 3 == 0 { "moby_dick.txt" } else { "lorem_ipsum.txt" })
+        .collect();
+    let result = new_ucmd!().arg("-lc").args(&files).arg("missing.txt").run();
+    assert!(!result.success);
+    assert!(result.stderr.contains("missing.txt"));
+    let mut expected = String::new();
+    let (mut lines, mut bytes) = (0, 0);
+    for file in &files {
+        let counts = if *file == "moby_dick.txt" { (18, 1115) } else { (13, 772) };
+        expected.push_str(&format!("{:6}{:6} {}\n", counts.0, counts.1, file));
+        lines += counts.0;
+        bytes += counts.1;
+    }
+    expected.push_str(&format!("{:6}{:6} total\n", lines, bytes));
+    assert_eq!(result.stdout, expected);
+}
+
+#[test]
+fn test_files0_from() {
+    new_ucmd!()
+        .arg("--files0-from=-")
+        .pipe_in("lorem_ipsum.txt\0moby_dick.txt\0")
+        .run()
+        .stdout_is("  13 109 772 lorem_ipsum.txt\n   18  204 1115 moby_dick.txt\n   31  313 1887 total\n");
+}
+
+#[test]
+fn test_files0_from_file() {
+    let (at, mut ucmd) = at_and_ucmd!();
+    // a list in a file is read in full first, so the counts line up
+    let mut list = String::new();
+    for _ in 0..1000 {
+        list.push_str("lorem_ipsum.txt\0");
+    }
+    at.touch("list");
+    at.append("list", &list);
+    let result = ucmd.args(&["-l", "--files0-from=list"]).run();
+    assert!(result.success);
+    let lines: Vec<&str> = result.stdout.lines().collect();
+    assert_eq!(lines.len(), 1001);
+    assert_eq!(lines[0], "     13 lorem_ipsum.txt");
+    assert_eq!(lines[1000], "  13000 total");
+}
+
+#[test]
+fn test_files0_from_with_operand() {
+    new_ucmd!()
+        .args(&["--files0-from=-", "moby_dick.txt"])
+        .pipe_in("lorem_ipsum.txt\0")
+        .fails()
+        .no_stdout();
+}
+
+#[test]
+fn test_unreadable_file_does_not_abort() {
+    let result = new_ucmd!()
+        .args(&["lorem_ipsum.txt", "missing.txt", "moby_dick.txt"])
+        .run();
+    assert!(!result.success);
+    assert!(result.stderr.contains("missing.txt"));
+    assert_eq!(result.stdout,
+               "   13  109  772 lorem_ipsum.txt\n   18  204 1115 moby_dick.txt\n   31  313 1887 total\n");
+}
+
+#[test]
+fn test_files0_from_zero_length_name() {
+    let result = new_ucmd!()
+        .arg("--files0-from=-")
+        .pipe_in("\0moby_dick.txt")
+        .run();
+    assert!(!result.success);
+    assert_eq!(result.stderr, "wc: error: '-':1: invalid zero-length file name\n");
+    assert_eq!(result.stdout, "   18  204 1115 moby_dick.txt\n");
+}
+
+#[test]
+fn test_multibyte_chars() {
+    new_ucmd!().args(&["-c", "-m", "-L"])
+        .pipe_in("h\u{e9}llo\n\u{65e5}\u{672c}\u{8a9e}\n")
+        .run()
+        .stdout_is(" 10 17  6\n");
+    // a byte that is not UTF-8 is a character of its own
+    new_ucmd!().args(&["-c", "-m", "-L"])
+        .pipe_in(&b"a\xffb\xe6\x97\n"[..])
+        .run()
+        .stdout_is(" 6 6 5\n");
+}
+
+#[test]
+fn test_max_line_length() {
+    new_ucmd!().arg("--max-line-length")
+        .pipe_in("a\tb\nab\rc\ne\u{301}\nlast line")
+        .run()
+        .stdout_is("  9\n");
+    new_ucmd!().arg("-L")
+        .pipe_in("\t\u{ff21}\n")
+        .run()
+        .stdout_is("10\n");
+}
@@ -16,9 +16,16 @@ macro_rules! unix_only {
 }
 unix_only! {
     "chmod", test_chmod;
+    "chroot", test_chroot;
     "chown", test_chown;
     "chgrp", test_chgrp;
+    "df", test_df;
+    "groups", test_groups;
+    "id", test_id;
     "install", test_install;
+    "kill", test_kill;
+    "mkfifo", test_mkfifo;
+    "mknod", test_mknod;
     "mv", test_mv;
     "pathchk", test_pathchk;
     "pinky", test_pinky;
@@ -45,6 +52,7 @@ generic! {
     "basename", test_basename;
     "cat", test_cat;
     "cksum", test_cksum;
+    "cmp", test_cmp;
     "comm", test_comm;
     "cp", test_cp;
     "cut", test_cut;
@@ -55,6 +63,7 @@ generic! {
     "expr", test_expr;
     "factor", test_factor;
     "false", test_false;
+    "fmt", test_fmt;
     "fold", test_fold;
     "hashsum", test_hashsum;
     "head", test_head;
@@ -81,6 +90,7 @@ generic! {
     "sum", test_sum;
     "tac", test_tac;
     "tail", test_tail;
+    "tee", test_tee;
     "test", test_test;
     "tr", test_tr;
     "true", test_true;
@@ -91,3 +101,7 @@ generic! {
     // Be aware of the trailing semicolon after the last item
     "wc", test_wc
 }
+
+// The multicall binary itself
+#[cfg(unix)]
+mod test_uutils;